@@ -22,6 +22,7 @@ fn main() {
     let src_codec_dir = src_dir.join("codec");
     let src_codec_audio_dir = src_codec_dir.join("audio");
     let src_codec_video_dir = src_codec_dir.join("video");
+    let src_metrics_dir = src_dir.join("metrics");
 
     println!("cargo:rerun-if-changed={}", src_dir.display());
 
@@ -42,16 +43,36 @@ fn main() {
         .file(src_dir.join("logger.c"))
         .file(src_dir.join("packet.c"))
         .file(src_dir.join("time.c"))
+        .file(src_format_dir.join("chapter.c"))
         .file(src_format_dir.join("demuxer.c"))
+        .file(src_format_dir.join("encryption.c"))
         .file(src_format_dir.join("io.c"))
         .file(src_format_dir.join("muxer.c"))
         .file(src_format_dir.join("stream.c"))
         .file(src_codec_dir.join("bsf.c"))
         .file(src_codec_dir.join("mod.c"))
         .file(src_codec_dir.join("frame.c"))
+        .file(src_codec_dir.join("parser.c"))
         .file(src_codec_audio_dir.join("resampler.c"))
+        .file(src_codec_video_dir.join("crop.c"))
+        .file(src_codec_video_dir.join("fit.c"))
+        .file(src_codec_video_dir.join("overlay.c"))
+        .file(src_codec_video_dir.join("pixel.c"))
+        .file(src_codec_video_dir.join("pool.c"))
         .file(src_codec_video_dir.join("scaler.c"))
-        .compile("ffwrapper");
+        .file(src_codec_video_dir.join("sprite.c"))
+        .file(src_codec_video_dir.join("transform.c"))
+        .file(src_metrics_dir.join("loudness.c"));
+
+    let avfilter = std::env::var_os("CARGO_FEATURE_AVFILTER").is_some();
+
+    if avfilter {
+        build
+            .file(src_codec_audio_dir.join("playback_rate.c"))
+            .file(src_codec_video_dir.join("filter.c"));
+    }
+
+    build.compile("ffwrapper");
 
     for dir in ac_ffmpeg_build::ffmpeg_lib_dirs(true) {
         println!("cargo:rustc-link-search=native={}", dir.display());
@@ -60,6 +81,11 @@ fn main() {
     let ffmpeg_link_mode = link_mode();
 
     link("avcodec", ffmpeg_link_mode);
+
+    if avfilter {
+        link("avfilter", ffmpeg_link_mode);
+    }
+
     link("avformat", ffmpeg_link_mode);
     link("avutil", ffmpeg_link_mode);
     link("swresample", ffmpeg_link_mode);
@@ -1,17 +1,19 @@
 use std::{
     borrow::Borrow,
-    ffi::CString,
+    ffi::{CStr, CString},
+    fmt::{self, Debug, Display, Formatter},
     ops::Deref,
     os::raw::{c_char, c_int, c_void},
     str::FromStr,
 };
 
-use super::UnknownChannelLayout;
+use super::{ChannelOrder, StandardChannelLayout, UnknownChannelLayout};
 
 extern "C" {
     fn ffw_get_channel_layout_by_name(name: *const c_char) -> u64;
     fn ffw_get_channel_layout_channels(layout: u64) -> c_int;
     fn ffw_get_default_channel_layout(channels: c_int) -> u64;
+    fn ffw_get_channel_layout_string(buf: *mut c_char, size: c_int, layout: u64);
 }
 
 /// Channel layout reference.
@@ -33,6 +35,27 @@ impl ChannelLayoutRef {
         unsafe { ffw_get_channel_layout_channels(self.to_raw()) as _ }
     }
 
+    /// Get the channel mask. The method returns None if the channel layout
+    /// is unspecified.
+    pub fn mask(&self) -> Option<u64> {
+        let mask = self.to_raw();
+
+        if mask == 0 {
+            None
+        } else {
+            Some(mask)
+        }
+    }
+
+    /// Get the channel order.
+    pub fn order(&self) -> ChannelOrder {
+        if self.to_raw() == 0 {
+            ChannelOrder::Unspecified
+        } else {
+            ChannelOrder::Native
+        }
+    }
+
     /// Get the raw representation.
     fn to_raw(&self) -> u64 {
         unsafe { *(self.as_ptr() as *const u64) }
@@ -45,6 +68,26 @@ impl PartialEq for ChannelLayoutRef {
     }
 }
 
+impl Display for ChannelLayoutRef {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        let mut buf = [0u8; 256];
+
+        let description = unsafe {
+            ffw_get_channel_layout_string(buf.as_mut_ptr() as _, buf.len() as _, self.to_raw());
+
+            CStr::from_ptr(buf.as_ptr() as _)
+        };
+
+        f.write_str(&description.to_string_lossy())
+    }
+}
+
+impl Debug for ChannelLayoutRef {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(f, "ChannelLayout({})", self)
+    }
+}
+
 impl ToOwned for ChannelLayoutRef {
     type Owned = ChannelLayout;
 
@@ -68,6 +111,38 @@ impl ChannelLayout {
             Some(Self(layout))
         }
     }
+
+    /// Create a channel layout from a given channel mask.
+    pub fn from_mask(mask: u64) -> Option<Self> {
+        if mask == 0 {
+            None
+        } else {
+            Some(Self(mask))
+        }
+    }
+
+    /// Get a given standard channel layout.
+    pub fn standard(layout: StandardChannelLayout) -> Self {
+        Self(layout.mask())
+    }
+}
+
+impl From<StandardChannelLayout> for ChannelLayout {
+    fn from(layout: StandardChannelLayout) -> Self {
+        Self::standard(layout)
+    }
+}
+
+impl Display for ChannelLayout {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        Display::fmt(self.as_ref(), f)
+    }
+}
+
+impl Debug for ChannelLayout {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        Debug::fmt(self.as_ref(), f)
+    }
 }
 
 impl AsRef<ChannelLayoutRef> for ChannelLayout {
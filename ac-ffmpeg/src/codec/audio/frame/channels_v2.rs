@@ -1,6 +1,7 @@
 use std::{
     borrow::Borrow,
-    ffi::CString,
+    ffi::{CStr, CString},
+    fmt::{self, Debug, Display, Formatter},
     io,
     ops::Deref,
     os::raw::{c_char, c_int, c_void},
@@ -8,7 +9,7 @@ use std::{
     str::FromStr,
 };
 
-use super::UnknownChannelLayout;
+use super::{ChannelOrder, StandardChannelLayout, UnknownChannelLayout};
 
 use crate::Error;
 
@@ -19,6 +20,16 @@ extern "C" {
     fn ffw_channel_layout_is_valid(layout: *const c_void) -> c_int;
     fn ffw_channel_layout_get_channels(layout: *const c_void) -> u32;
     fn ffw_channel_layout_compare(a: *const c_void, b: *const c_void) -> c_int;
+    fn ffw_channel_layout_from_mask(layout: *mut *mut c_void, mask: u64) -> c_int;
+    fn ffw_channel_layout_get_mask(layout: *const c_void, mask: *mut u64) -> c_int;
+    fn ffw_channel_layout_get_order(layout: *const c_void) -> c_int;
+    fn ffw_channel_layout_get_ambisonic_order(layout: *const c_void) -> c_int;
+    fn ffw_channel_layout_ambisonic(
+        layout: *mut *mut c_void,
+        order: u32,
+        non_diegetic: u64,
+    ) -> c_int;
+    fn ffw_channel_layout_describe(layout: *const c_void, buf: *mut c_char, size: usize) -> c_int;
     fn ffw_channel_layout_free(layout: *mut c_void);
 }
 
@@ -41,12 +52,78 @@ impl ChannelLayoutRef {
         unsafe { ffw_channel_layout_get_channels(self.as_ptr()) }
     }
 
+    /// Get the channel mask. The method returns None if the channel layout
+    /// cannot be represented as a mask (e.g. for layouts with custom channel
+    /// order).
+    pub fn mask(&self) -> Option<u64> {
+        let mut mask = 0;
+
+        let ret = unsafe { ffw_channel_layout_get_mask(self.as_ptr(), &mut mask) };
+
+        if ret == 0 {
+            None
+        } else {
+            Some(mask)
+        }
+    }
+
+    /// Get the channel order.
+    pub fn order(&self) -> ChannelOrder {
+        match unsafe { ffw_channel_layout_get_order(self.as_ptr()) } {
+            1 => ChannelOrder::Native,
+            2 => ChannelOrder::Custom,
+            3 => ChannelOrder::Ambisonic,
+            _ => ChannelOrder::Unspecified,
+        }
+    }
+
+    /// Check if this is an ambisonic (B-format) channel layout.
+    pub fn is_ambisonic(&self) -> bool {
+        self.order() == ChannelOrder::Ambisonic
+    }
+
+    /// Get the ambisonic order. The method returns None if this is not an
+    /// ambisonic channel layout or if the ambisonic channels are not
+    /// complete.
+    pub fn ambisonic_order(&self) -> Option<u32> {
+        let order = unsafe { ffw_channel_layout_get_ambisonic_order(self.as_ptr()) };
+
+        if order < 0 {
+            None
+        } else {
+            Some(order as u32)
+        }
+    }
+
     /// Check if the channel layout is valid.
     fn is_valid(&self) -> bool {
         unsafe { ffw_channel_layout_is_valid(self.as_ptr()) != 0 }
     }
 }
 
+impl Display for ChannelLayoutRef {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        let mut buf = [0u8; 256];
+
+        let ret =
+            unsafe { ffw_channel_layout_describe(self.as_ptr(), buf.as_mut_ptr() as _, buf.len()) };
+
+        if ret < 0 {
+            return Err(fmt::Error);
+        }
+
+        let description = unsafe { CStr::from_ptr(buf.as_ptr() as _) };
+
+        f.write_str(&description.to_string_lossy())
+    }
+}
+
+impl Debug for ChannelLayoutRef {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(f, "ChannelLayout({})", self)
+    }
+}
+
 impl PartialEq for ChannelLayoutRef {
     fn eq(&self, other: &Self) -> bool {
         unsafe { ffw_channel_layout_compare(self.as_ptr(), other.as_ptr()) == 0 }
@@ -102,6 +179,75 @@ impl ChannelLayout {
             }
         }
     }
+
+    /// Create a native channel layout from a given channel mask.
+    pub fn from_mask(mask: u64) -> Option<Self> {
+        if mask == 0 {
+            return None;
+        }
+
+        unsafe {
+            let mut ptr = ptr::null_mut();
+
+            let ret = ffw_channel_layout_from_mask(&mut ptr, mask);
+
+            if ret != 0 {
+                return None;
+            }
+
+            let res = Self::from_raw_ptr(ptr);
+
+            if res.is_valid() {
+                Some(res)
+            } else {
+                None
+            }
+        }
+    }
+
+    /// Create an ambisonic (B-format) channel layout of a given order. The
+    /// ambisonic channels are in the ACN order and they are followed by
+    /// non-diegetic channels (e.g. head-locked stereo) given by a channel
+    /// mask.
+    ///
+    /// Ambisonic channel layouts are available only with FFmpeg 5.1 or
+    /// newer.
+    pub fn ambisonic(order: u32, non_diegetic_mask: u64) -> Option<Self> {
+        unsafe {
+            let mut ptr = ptr::null_mut();
+
+            let ret = ffw_channel_layout_ambisonic(&mut ptr, order, non_diegetic_mask);
+
+            if ret != 0 {
+                return None;
+            }
+
+            Some(Self::from_raw_ptr(ptr))
+        }
+    }
+
+    /// Get a given standard channel layout.
+    pub fn standard(layout: StandardChannelLayout) -> Self {
+        Self::from_mask(layout.mask()).expect("unable to allocate channel layout")
+    }
+}
+
+impl From<StandardChannelLayout> for ChannelLayout {
+    fn from(layout: StandardChannelLayout) -> Self {
+        Self::standard(layout)
+    }
+}
+
+impl Display for ChannelLayout {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        Display::fmt(self.as_ref(), f)
+    }
+}
+
+impl Debug for ChannelLayout {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        Debug::fmt(self.as_ref(), f)
+    }
 }
 
 impl Drop for ChannelLayout {
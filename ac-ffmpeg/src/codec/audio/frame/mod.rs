@@ -8,7 +8,7 @@ mod channels_v2;
 
 use std::{
     ffi::{CStr, CString},
-    fmt::{self, Display, Formatter},
+    fmt::{self, Debug, Display, Formatter},
     marker::PhantomData,
     ops::{Deref, DerefMut},
     os::raw::{c_char, c_int, c_void},
@@ -16,7 +16,10 @@ use std::{
     str::FromStr,
 };
 
-use crate::time::{TimeBase, Timestamp};
+use crate::{
+    time::{TimeBase, Timestamp},
+    Error,
+};
 
 #[cfg(not(channel_layout_v2))]
 pub use channels_v1::{ChannelLayout, ChannelLayoutRef};
@@ -29,6 +32,9 @@ extern "C" {
     fn ffw_get_sample_format_name(format: c_int) -> *const c_char;
     fn ffw_sample_format_is_planar(format: c_int) -> c_int;
     fn ffw_sample_format_is_none(format: c_int) -> c_int;
+    fn ffw_sample_format_get_bytes_per_sample(format: c_int) -> c_int;
+    fn ffw_sample_format_get_packed(format: c_int) -> c_int;
+    fn ffw_sample_format_get_planar(format: c_int) -> c_int;
 
     fn ffw_frame_new_silence(
         channel_layout: *const c_void,
@@ -47,6 +53,7 @@ extern "C" {
     fn ffw_frame_clone(frame: *const c_void) -> *mut c_void;
     fn ffw_frame_free(frame: *mut c_void);
     fn ffw_frame_is_writable(frame: *const c_void) -> c_int;
+    fn ffw_frame_is_corrupt(frame: *const c_void) -> c_int;
     fn ffw_frame_make_writable(frame: *mut c_void) -> c_int;
 }
 
@@ -62,6 +69,69 @@ impl Display for UnknownChannelLayout {
 
 impl std::error::Error for UnknownChannelLayout {}
 
+/// Channel order of a channel layout.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ChannelOrder {
+    /// Only the number of channels is known.
+    Unspecified,
+    /// Channels are ordered as given by the channel mask.
+    Native,
+    /// Channels have a custom order.
+    Custom,
+    /// Ambisonic (B-format) channels in the ACN order optionally followed
+    /// by non-diegetic channels.
+    Ambisonic,
+}
+
+/// Standard channel layouts.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum StandardChannelLayout {
+    Mono,
+    Stereo,
+    Surround2_1,
+    Surround3_0,
+    Quad,
+    Surround5_0,
+    Surround5_0Back,
+    Surround5_1,
+    Surround5_1Back,
+    Surround6_1,
+    Surround7_1,
+    Surround7_1Wide,
+}
+
+impl StandardChannelLayout {
+    /// Get the channel mask.
+    pub fn mask(self) -> u64 {
+        const FL: u64 = 0x1;
+        const FR: u64 = 0x2;
+        const FC: u64 = 0x4;
+        const LFE: u64 = 0x8;
+        const BL: u64 = 0x10;
+        const BR: u64 = 0x20;
+        const FLC: u64 = 0x40;
+        const FRC: u64 = 0x80;
+        const BC: u64 = 0x100;
+        const SL: u64 = 0x200;
+        const SR: u64 = 0x400;
+
+        match self {
+            Self::Mono => FC,
+            Self::Stereo => FL | FR,
+            Self::Surround2_1 => FL | FR | LFE,
+            Self::Surround3_0 => FL | FR | FC,
+            Self::Quad => FL | FR | BL | BR,
+            Self::Surround5_0 => FL | FR | FC | SL | SR,
+            Self::Surround5_0Back => FL | FR | FC | BL | BR,
+            Self::Surround5_1 => FL | FR | FC | LFE | SL | SR,
+            Self::Surround5_1Back => FL | FR | FC | LFE | BL | BR,
+            Self::Surround6_1 => FL | FR | FC | LFE | BC | SL | SR,
+            Self::Surround7_1 => FL | FR | FC | LFE | BL | BR | SL | SR,
+            Self::Surround7_1Wide => FL | FR | FC | LFE | FLC | FRC | SL | SR,
+        }
+    }
+}
+
 /// Get channel layout with a given name.
 pub fn get_channel_layout(name: &str) -> ChannelLayout {
     ChannelLayout::from_str(name).unwrap()
@@ -108,6 +178,48 @@ impl SampleFormat {
     pub fn is_planar(self) -> bool {
         unsafe { ffw_sample_format_is_planar(self.into_raw()) != 0 }
     }
+
+    /// Check if the sample format is packed (i.e. samples of all channels
+    /// are interleaved in a single plane).
+    pub fn is_packed(self) -> bool {
+        !self.is_planar()
+    }
+
+    /// Get size of a single sample in bytes.
+    pub fn bytes_per_sample(self) -> usize {
+        unsafe { ffw_sample_format_get_bytes_per_sample(self.into_raw()) as usize }
+    }
+
+    /// Get the packed variant of the sample format (e.g. `s16` for `s16p`).
+    pub fn to_packed(self) -> Self {
+        unsafe { Self(ffw_sample_format_get_packed(self.into_raw())) }
+    }
+
+    /// Get the planar variant of the sample format (e.g. `s16p` for `s16`).
+    pub fn to_planar(self) -> Self {
+        unsafe { Self(ffw_sample_format_get_planar(self.into_raw())) }
+    }
+
+    /// Get all sample formats known to FFmpeg.
+    pub fn all() -> impl Iterator<Item = Self> {
+        (0..)
+            .map(Self)
+            .take_while(|format| unsafe { !ffw_get_sample_format_name(format.0).is_null() })
+    }
+}
+
+impl Debug for SampleFormat {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        let ptr = unsafe { ffw_get_sample_format_name(self.into_raw()) };
+
+        if ptr.is_null() {
+            write!(f, "SampleFormat({})", self.0)
+        } else {
+            let name = unsafe { CStr::from_ptr(ptr as _) };
+
+            write!(f, "SampleFormat({})", name.to_string_lossy())
+        }
+    }
 }
 
 impl FromStr for SampleFormat {
@@ -133,6 +245,238 @@ pub fn get_sample_format(name: &str) -> SampleFormat {
     SampleFormat::from_str(name).unwrap()
 }
 
+/// Convert given audio samples from one sample format into another. There
+/// must be a single plane for packed source formats and one plane per
+/// channel for planar source formats. The output has the same structure
+/// according to the target format. Floating point samples are clipped
+/// when converted into integer formats.
+///
+/// This is meant for one-off conversions, use the audio resampler for
+/// continuous streams or for changing the sample rate or the channel
+/// layout.
+pub fn convert_samples(
+    planes: &[&[u8]],
+    source: SampleFormat,
+    target: SampleFormat,
+    channels: usize,
+) -> Result<Vec<Vec<u8>>, Error> {
+    if channels == 0 {
+        return Err(Error::new("invalid number of channels"));
+    }
+
+    let source_type = SampleType::try_from_format(source)?;
+    let target_type = SampleType::try_from_format(target)?;
+
+    let source_size = source_type.size();
+    let target_size = target_type.size();
+
+    let (source_planes, source_stride) = if source.is_planar() {
+        (channels, 1)
+    } else {
+        (1, channels)
+    };
+
+    if planes.len() != source_planes {
+        return Err(Error::new("invalid number of planes"));
+    }
+
+    let plane_len = planes[0].len();
+
+    let frame_size = source_size * source_stride;
+
+    let samples = plane_len / frame_size;
+
+    if samples * frame_size != plane_len || planes.iter().any(|p| p.len() != plane_len) {
+        return Err(Error::new("invalid plane size"));
+    }
+
+    let (target_planes, target_stride) = if target.is_planar() {
+        (channels, 1)
+    } else {
+        (1, channels)
+    };
+
+    let mut res = vec![vec![0; samples * target_stride * target_size]; target_planes];
+
+    for i in 0..samples {
+        for c in 0..channels {
+            let (src_plane, src_index) = if source.is_planar() {
+                (c, i)
+            } else {
+                (0, i * channels + c)
+            };
+
+            let (dst_plane, dst_index) = if target.is_planar() {
+                (c, i)
+            } else {
+                (0, i * channels + c)
+            };
+
+            let offset = src_index * source_size;
+
+            let sample = source_type.to_f64(&planes[src_plane][offset..offset + source_size]);
+
+            let offset = dst_index * target_size;
+
+            target_type.write_f64(sample, &mut res[dst_plane][offset..offset + target_size]);
+        }
+    }
+
+    Ok(res)
+}
+
+/// Numeric type of audio samples.
+#[derive(Copy, Clone)]
+enum SampleType {
+    U8,
+    S16,
+    S32,
+    S64,
+    F32,
+    F64,
+}
+
+impl SampleType {
+    /// Get sample type of a given sample format.
+    fn from_format(format: SampleFormat) -> Self {
+        Self::try_from_format(format).expect("unsupported sample format")
+    }
+
+    /// Get sample type of a given sample format (if known).
+    fn try_from_format(format: SampleFormat) -> Result<Self, Error> {
+        let name = unsafe { ffw_get_sample_format_name(format.into_raw()) };
+
+        if name.is_null() {
+            return Err(Error::new("invalid sample format"));
+        }
+
+        let name = unsafe { CStr::from_ptr(name as _) };
+
+        let res = match name.to_str().unwrap_or("").trim_end_matches('p') {
+            "u8" => Self::U8,
+            "s16" => Self::S16,
+            "s32" => Self::S32,
+            "s64" => Self::S64,
+            "flt" => Self::F32,
+            "dbl" => Self::F64,
+            _ => return Err(Error::new("unsupported sample format")),
+        };
+
+        Ok(res)
+    }
+
+    /// Get size of a single sample in bytes.
+    fn size(self) -> usize {
+        match self {
+            Self::U8 => 1,
+            Self::S16 => 2,
+            Self::S32 | Self::F32 => 4,
+            Self::S64 | Self::F64 => 8,
+        }
+    }
+
+    /// Convert a given sample into f32 in the range from -1.0 to 1.0.
+    fn to_f32(self, data: &[u8]) -> f32 {
+        self.to_f64(data) as f32
+    }
+
+    /// Convert a given sample into f64 in the range from -1.0 to 1.0.
+    fn to_f64(self, data: &[u8]) -> f64 {
+        match self {
+            Self::U8 => (data[0] as f64 - 128.0) / 128.0,
+            Self::S16 => i16::from_ne_bytes([data[0], data[1]]) as f64 / 32_768.0,
+            Self::S32 => {
+                let mut bytes = [0; 4];
+
+                bytes.copy_from_slice(&data[..4]);
+
+                i32::from_ne_bytes(bytes) as f64 / 2_147_483_648.0
+            }
+            Self::S64 => {
+                let mut bytes = [0; 8];
+
+                bytes.copy_from_slice(&data[..8]);
+
+                i64::from_ne_bytes(bytes) as f64 / 9_223_372_036_854_775_808.0
+            }
+            Self::F32 => {
+                let mut bytes = [0; 4];
+
+                bytes.copy_from_slice(&data[..4]);
+
+                f32::from_ne_bytes(bytes) as f64
+            }
+            Self::F64 => {
+                let mut bytes = [0; 8];
+
+                bytes.copy_from_slice(&data[..8]);
+
+                f64::from_ne_bytes(bytes)
+            }
+        }
+    }
+
+    /// Write a given f64 sample in the range from -1.0 to 1.0 into a given
+    /// buffer. Values outside of the range are clipped for integer types.
+    fn write_f64(self, sample: f64, data: &mut [u8]) {
+        match self {
+            Self::U8 => data[0] = (sample * 128.0 + 128.0).round().clamp(0.0, 255.0) as u8,
+            Self::S16 => {
+                let v = (sample * 32_768.0).round().clamp(-32_768.0, 32_767.0) as i16;
+
+                data.copy_from_slice(&v.to_ne_bytes());
+            }
+            Self::S32 => {
+                let v = (sample * 2_147_483_648.0)
+                    .round()
+                    .clamp(-2_147_483_648.0, 2_147_483_647.0) as i32;
+
+                data.copy_from_slice(&v.to_ne_bytes());
+            }
+            Self::S64 => {
+                // the cast saturates
+                let v = (sample * 9_223_372_036_854_775_808.0).round() as i64;
+
+                data.copy_from_slice(&v.to_ne_bytes());
+            }
+            Self::F32 => data.copy_from_slice(&(sample as f32).to_ne_bytes()),
+            Self::F64 => data.copy_from_slice(&sample.to_ne_bytes()),
+        }
+    }
+}
+
+/// Convert samples of a given audio frame into interleaved f32 samples.
+fn get_interleaved_f32(
+    planes: &[Plane],
+    sample_format: SampleFormat,
+    channels: usize,
+    samples: usize,
+) -> Vec<f32> {
+    let sample_type = SampleType::from_format(sample_format);
+
+    let size = sample_type.size();
+
+    let mut res = Vec::with_capacity(channels * samples);
+
+    if sample_format.is_planar() {
+        let planes = planes.iter().map(|plane| plane.data()).collect::<Vec<_>>();
+
+        for i in 0..samples {
+            for plane in &planes {
+                res.push(sample_type.to_f32(&plane[i * size..]));
+            }
+        }
+    } else if let Some(plane) = planes.first() {
+        let data = plane.data();
+
+        for i in 0..channels * samples {
+            res.push(sample_type.to_f32(&data[i * size..]));
+        }
+    }
+
+    res
+}
+
 /// Audio plane. This is an array of audio sample data. Depending on the
 /// sample format, this can either be samples for a single channel, or
 /// for all channels multiplexed together.
@@ -294,6 +638,40 @@ impl AudioFrameMut {
         }
     }
 
+    /// Create an audio frame from given interleaved f32 samples. The
+    /// sample format of the frame will be `flt` and the time base will be in
+    /// microseconds. Incomplete samples at the end (i.e. samples for only
+    /// some of the channels) are ignored.
+    ///
+    /// This is useful e.g. for passing audio from DSP libraries or
+    /// synthesizers into encoders.
+    pub fn from_interleaved_f32(
+        channel_layout: &ChannelLayoutRef,
+        sample_rate: u32,
+        data: &[f32],
+    ) -> Self {
+        let channels = (channel_layout.channels() as usize).max(1);
+
+        let samples = data.len() / channels;
+
+        let mut frame = Self::silence(
+            channel_layout,
+            get_sample_format("flt"),
+            sample_rate,
+            samples,
+        );
+
+        let mut planes = frame.planes_mut();
+
+        let plane = planes[0].data_mut();
+
+        for (dst, src) in plane.chunks_exact_mut(4).zip(&data[..samples * channels]) {
+            dst.copy_from_slice(&src.to_ne_bytes());
+        }
+
+        frame
+    }
+
     /// Get frame sample format.
     pub fn sample_format(&self) -> SampleFormat {
         unsafe { SampleFormat::from_raw(ffw_frame_get_format(self.ptr)) }
@@ -453,6 +831,39 @@ impl AudioFrame {
         self
     }
 
+    /// Get the samples converted into interleaved f32 samples in the range
+    /// from -1.0 to 1.0. All sample formats are supported.
+    ///
+    /// This is useful e.g. for passing decoded audio into DSP libraries or
+    /// speech recognition models.
+    pub fn to_interleaved_f32(&self) -> Vec<f32> {
+        get_interleaved_f32(
+            &self.planes(),
+            self.sample_format(),
+            self.channel_layout().channels() as _,
+            self.samples(),
+        )
+    }
+
+    /// Get the samples as an f32 array of the shape `(channels, samples)`
+    /// in the range from -1.0 to 1.0. All sample formats are supported.
+    #[cfg(feature = "ndarray")]
+    pub fn to_ndarray(&self) -> ndarray::Array2<f32> {
+        let channels = self.channel_layout().channels() as usize;
+        let samples = self.samples();
+
+        let interleaved = self.to_interleaved_f32();
+
+        ndarray::Array2::from_shape_fn((channels, samples), |(channel, sample)| {
+            interleaved[sample * channels + channel]
+        })
+    }
+
+    /// Check if the frame has been flagged as corrupt by the decoder.
+    pub fn is_corrupt(&self) -> bool {
+        unsafe { ffw_frame_is_corrupt(self.ptr) != 0 }
+    }
+
     /// Get raw pointer.
     pub(crate) fn as_ptr(&self) -> *const c_void {
         self.ptr
@@ -512,3 +923,118 @@ impl Drop for AudioFrame {
 
 unsafe impl Send for AudioFrame {}
 unsafe impl Sync for AudioFrame {}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        convert_samples, get_channel_layout, get_sample_format, AudioFrameMut, ChannelLayout,
+    };
+
+    use crate::codec::AudioCodecParameters;
+
+    #[test]
+    fn test_channel_mask() {
+        assert_eq!(get_channel_layout("mono").mask(), Some(0x4));
+        assert_eq!(get_channel_layout("stereo").mask(), Some(0x3));
+        assert_eq!(get_channel_layout("5.1").mask(), Some(0x60f));
+
+        let layout = ChannelLayout::from_mask(0x3).unwrap();
+
+        assert_eq!(layout.mask(), Some(0x3));
+        assert_eq!(layout.channels(), 2);
+
+        // parameters without any channel layout
+        let params = AudioCodecParameters::builder("pcm_s16le").unwrap().build();
+
+        assert_eq!(params.channel_layout().mask(), None);
+    }
+
+    #[test]
+    fn test_interleaved_f32() {
+        let layout = ChannelLayout::from_channels(2).unwrap();
+
+        let data = [0.0, 0.5, -0.5, 1.0, 0.25, -0.25, 0.125];
+
+        let frame = AudioFrameMut::from_interleaved_f32(&layout, 48_000, &data).freeze();
+
+        assert_eq!(frame.samples(), 3);
+        assert_eq!(frame.sample_format(), get_sample_format("flt"));
+        assert_eq!(frame.to_interleaved_f32(), &data[..6]);
+    }
+
+    #[test]
+    fn test_convert_samples() {
+        let s16 = get_sample_format("s16");
+        let fltp = get_sample_format("fltp");
+
+        assert_eq!(s16.bytes_per_sample(), 2);
+        assert_eq!(s16.to_planar(), get_sample_format("s16p"));
+        assert_eq!(fltp.to_packed(), get_sample_format("flt"));
+
+        let data = [0i16, -32_768, 16_384, 32_767]
+            .iter()
+            .flat_map(|v| v.to_ne_bytes())
+            .collect::<Vec<_>>();
+
+        let planes = convert_samples(&[&data], s16, fltp, 2).unwrap();
+
+        let to_f32 = |plane: &[u8]| {
+            plane
+                .chunks_exact(4)
+                .map(|c| f32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(planes.len(), 2);
+        assert_eq!(to_f32(&planes[0]), [0.0, 0.5]);
+        assert_eq!(to_f32(&planes[1]), [-1.0, 32_767.0 / 32_768.0]);
+
+        let back = convert_samples(&[&planes[0], &planes[1]], fltp, s16, 2).unwrap();
+
+        assert_eq!(back, [data]);
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn test_ndarray() {
+        let layout = ChannelLayout::from_channels(2).unwrap();
+
+        let data = [0.0, 0.5, -0.5, 1.0, 0.25, -0.25];
+
+        let frame = AudioFrameMut::from_interleaved_f32(&layout, 48_000, &data).freeze();
+
+        let array = frame.to_ndarray();
+
+        assert_eq!(array.dim(), (2, 3));
+
+        // one row per channel
+        let samples = array.iter().copied().collect::<Vec<_>>();
+
+        assert_eq!(samples, [0.0, -0.5, 0.25, 0.5, 1.0, -0.25]);
+    }
+
+    #[cfg(channel_layout_v2)]
+    #[test]
+    fn test_ambisonic() {
+        use super::{ChannelOrder, StandardChannelLayout};
+
+        let layout = ChannelLayout::ambisonic(1, 0).unwrap();
+
+        assert_eq!(layout.channels(), 4);
+        assert_eq!(layout.order(), ChannelOrder::Ambisonic);
+        assert!(layout.is_ambisonic());
+        assert_eq!(layout.ambisonic_order(), Some(1));
+
+        let stereo = StandardChannelLayout::Stereo.mask();
+
+        let layout = ChannelLayout::ambisonic(2, stereo).unwrap();
+
+        assert_eq!(layout.channels(), 11);
+        assert_eq!(layout.ambisonic_order(), Some(2));
+
+        let layout = ChannelLayout::from(StandardChannelLayout::Stereo);
+
+        assert!(!layout.is_ambisonic());
+        assert_eq!(layout.ambisonic_order(), None);
+    }
+}
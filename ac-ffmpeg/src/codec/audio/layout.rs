@@ -0,0 +1,183 @@
+//! Channel layout conversion planning.
+
+use crate::codec::audio::{
+    resampler::AudioResamplerBuilder, AudioResampler, ChannelLayout, ChannelLayoutRef,
+};
+
+/// Kind of a channel layout conversion.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ChannelConversion {
+    /// The layouts are identical, no conversion is needed.
+    None,
+    /// The channels only need to be reordered, selected or duplicated
+    /// without any mixing (e.g. between layouts with an unspecified channel
+    /// order but the same number of channels).
+    Remap,
+    /// The conversion can be done using the standard downmix/upmix of the
+    /// resampler (e.g. 5.1 to stereo).
+    Standard,
+    /// The conversion requires a custom mixing matrix because the standard
+    /// mixing cannot be derived from the layouts (e.g. layouts with an
+    /// unspecified channel order and a different number of channels). The
+    /// planner provides a best-effort default matrix.
+    Custom,
+}
+
+/// Plan of a channel layout conversion. It reports what kind of conversion
+/// is needed for given source and target channel layouts and it constructs
+/// the corresponding resampler configuration.
+///
+/// # Example
+/// ```text
+/// let plan = ChannelLayoutPlan::new(&source_layout, &target_layout);
+///
+/// if plan.conversion() == ChannelConversion::Custom {
+///     log::warn!("no standard conversion, using a custom mixing matrix");
+/// }
+///
+/// let resampler = plan
+///     .resampler_builder()
+///     .source_sample_format(sample_format)
+///     .source_sample_rate(sample_rate)
+///     .target_sample_format(sample_format)
+///     .target_sample_rate(sample_rate)
+///     .build()?;
+/// ```
+pub struct ChannelLayoutPlan {
+    source: ChannelLayout,
+    target: ChannelLayout,
+    conversion: ChannelConversion,
+    matrix: Option<Vec<f64>>,
+}
+
+impl ChannelLayoutPlan {
+    /// Plan conversion from a given source channel layout to a given target
+    /// channel layout.
+    pub fn new(source: &ChannelLayoutRef, target: &ChannelLayoutRef) -> Self {
+        let (conversion, matrix) = if source == target {
+            (ChannelConversion::None, None)
+        } else {
+            plan(
+                source.mask(),
+                source.channels() as usize,
+                target.mask(),
+                target.channels() as usize,
+            )
+        };
+
+        Self {
+            source: source.to_owned(),
+            target: target.to_owned(),
+            conversion,
+            matrix,
+        }
+    }
+
+    /// Get the kind of the conversion.
+    pub fn conversion(&self) -> ChannelConversion {
+        self.conversion
+    }
+
+    /// Check if the conversion reduces the number of channels.
+    pub fn is_downmix(&self) -> bool {
+        self.target.channels() < self.source.channels()
+    }
+
+    /// Get the mixing matrix used for the conversion. The matrix is
+    /// available only for the `Remap` and `Custom` conversions, the
+    /// resampler derives the matrix itself otherwise. See
+    /// `AudioResamplerBuilder::matrix()` for the matrix layout.
+    pub fn matrix(&self) -> Option<&[f64]> {
+        self.matrix.as_deref()
+    }
+
+    /// Get a resampler builder with the source channel layout, the target
+    /// channel layout and the mixing matrix set. The sample formats and
+    /// sample rates still need to be set.
+    pub fn resampler_builder(&self) -> AudioResamplerBuilder {
+        let mut builder = AudioResampler::builder()
+            .source_channel_layout(self.source.clone())
+            .target_channel_layout(self.target.clone());
+
+        if let Some(matrix) = self.matrix.clone() {
+            builder = builder.matrix(matrix);
+        }
+
+        builder
+    }
+}
+
+/// Plan a conversion between two different channel layouts given by their
+/// channel masks (if known) and numbers of channels.
+fn plan(
+    source_mask: Option<u64>,
+    sources: usize,
+    target_mask: Option<u64>,
+    targets: usize,
+) -> (ChannelConversion, Option<Vec<f64>>) {
+    if source_mask.is_some() && target_mask.is_some() {
+        if source_mask == target_mask {
+            return (ChannelConversion::None, None);
+        }
+
+        return (ChannelConversion::Standard, None);
+    }
+
+    let mut matrix = vec![0.0; sources * targets];
+
+    if sources == targets {
+        for channel in 0..sources {
+            matrix[channel * sources + channel] = 1.0;
+        }
+
+        return (ChannelConversion::Remap, Some(matrix));
+    }
+
+    if targets == 1 {
+        // average all source channels
+        matrix.fill(1.0 / sources as f64);
+    } else if sources == 1 {
+        // duplicate the source channel
+        matrix.fill(1.0);
+    } else {
+        // keep the channels present in both, drop the remaining source
+        // channels and keep the remaining target channels silent
+        for channel in 0..sources.min(targets) {
+            matrix[channel * sources + channel] = 1.0;
+        }
+    }
+
+    (ChannelConversion::Custom, Some(matrix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{plan, ChannelConversion};
+
+    #[test]
+    fn test_plan() {
+        // 5.1 to stereo
+        let (conversion, matrix) = plan(Some(0x60f), 6, Some(0x3), 2);
+
+        assert_eq!(conversion, ChannelConversion::Standard);
+        assert!(matrix.is_none());
+
+        // unspecified stereo to stereo
+        let (conversion, matrix) = plan(None, 2, Some(0x3), 2);
+
+        assert_eq!(conversion, ChannelConversion::Remap);
+        assert_eq!(matrix.unwrap(), vec![1.0, 0.0, 0.0, 1.0]);
+
+        // unspecified quad to mono
+        let (conversion, matrix) = plan(None, 4, Some(0x4), 1);
+
+        assert_eq!(conversion, ChannelConversion::Custom);
+        assert_eq!(matrix.unwrap(), vec![0.25; 4]);
+
+        // unspecified 3 channels to unspecified 2 channels
+        let (conversion, matrix) = plan(None, 3, None, 2);
+
+        assert_eq!(conversion, ChannelConversion::Custom);
+        assert_eq!(matrix.unwrap(), vec![1.0, 0.0, 0.0, 0.0, 1.0, 0.0]);
+    }
+}
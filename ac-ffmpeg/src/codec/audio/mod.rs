@@ -1,25 +1,38 @@
 //! Audio decoder/encoder.
 
 pub mod frame;
+pub mod layout;
+#[cfg(feature = "avfilter")]
+pub mod playback_rate;
 pub mod resampler;
 pub mod transcoder;
 
-use std::{ffi::CString, os::raw::c_void, ptr};
+use std::{collections::VecDeque, ffi::CString, os::raw::c_void, ptr, time::Instant};
 
 use crate::{
-    codec::{AudioCodecParameters, CodecError, CodecParameters, CodecTag, Decoder, Encoder},
+    codec::{
+        AudioCodecParameters, CodecError, CodecParameters, CodecTag, Compliance, Decoder,
+        DecoderStatistics, Encoder,
+    },
     format::stream::Stream,
     packet::Packet,
     time::TimeBase,
-    Error,
+    Error, ErrorKind,
 };
 
 pub use self::{
-    frame::{AudioFrame, AudioFrameMut, ChannelLayout, ChannelLayoutRef, SampleFormat},
+    frame::{
+        AudioFrame, AudioFrameMut, ChannelLayout, ChannelLayoutRef, ChannelOrder, SampleFormat,
+        StandardChannelLayout,
+    },
+    layout::ChannelLayoutPlan,
     resampler::AudioResampler,
     transcoder::AudioTranscoder,
 };
 
+#[cfg(feature = "avfilter")]
+pub use self::playback_rate::PlaybackRate;
+
 /// Builder for the audio decoder.
 pub struct AudioDecoderBuilder {
     ptr: *mut c_void,
@@ -43,7 +56,10 @@ impl AudioDecoderBuilder {
         let ptr = unsafe { super::ffw_decoder_new(codec.as_ptr() as _) };
 
         if ptr.is_null() {
-            return Err(Error::new("unknown codec"));
+            return Err(Error::with_kind(
+                ErrorKind::DecoderNotFound,
+                "unknown codec",
+            ));
         }
 
         unsafe { Ok(Self::from_raw_ptr(ptr)) }
@@ -137,6 +153,7 @@ impl AudioDecoderBuilder {
         let res = AudioDecoder {
             ptr,
             time_base: self.time_base,
+            statistics: DecoderStatistics::new(),
         };
 
         Ok(res)
@@ -156,6 +173,7 @@ unsafe impl Sync for AudioDecoderBuilder {}
 pub struct AudioDecoder {
     ptr: *mut c_void,
     time_base: TimeBase,
+    statistics: DecoderStatistics,
 }
 
 impl AudioDecoder {
@@ -191,6 +209,11 @@ impl AudioDecoder {
     pub fn builder(codec: &str) -> Result<AudioDecoderBuilder, Error> {
         AudioDecoderBuilder::new(codec)
     }
+
+    /// Get decoder statistics.
+    pub fn statistics(&self) -> DecoderStatistics {
+        self.statistics
+    }
 }
 
 impl Decoder for AudioDecoder {
@@ -212,7 +235,15 @@ impl Decoder for AudioDecoder {
     fn try_push(&mut self, packet: Packet) -> Result<(), CodecError> {
         let packet = packet.with_time_base(self.time_base);
 
-        unsafe {
+        let _span = trace_span!(
+            "audio_decoder_push",
+            stream_index = packet.stream_index(),
+            pts = packet.pts().as_micros(),
+        );
+
+        let start = Instant::now();
+
+        let res = unsafe {
             match super::ffw_decoder_push_packet(self.ptr, packet.as_ptr()) {
                 1 => Ok(()),
                 0 => Err(CodecError::again(
@@ -220,7 +251,12 @@ impl Decoder for AudioDecoder {
                 )),
                 e => Err(CodecError::from_raw_error_code(e)),
             }
-        }
+        };
+
+        self.statistics
+            .record_push(packet.pts(), start.elapsed(), &res);
+
+        res
     }
 
     fn try_flush(&mut self) -> Result<(), CodecError> {
@@ -238,7 +274,9 @@ impl Decoder for AudioDecoder {
     fn take(&mut self) -> Result<Option<AudioFrame>, Error> {
         let mut fptr = ptr::null_mut();
 
-        unsafe {
+        let start = Instant::now();
+
+        let res = unsafe {
             match super::ffw_decoder_take_frame(self.ptr, &mut fptr) {
                 1 => {
                     if fptr.is_null() {
@@ -250,7 +288,20 @@ impl Decoder for AudioDecoder {
                 0 => Ok(None),
                 e => Err(Error::from_raw_error_code(e)),
             }
+        };
+
+        let frame_info = match &res {
+            Ok(Some(frame)) => Some((frame.pts(), frame.is_corrupt())),
+            _ => None,
+        };
+
+        if let Some((pts, _)) = frame_info {
+            trace_event!(pts = pts.as_micros(), "audio frame decoded");
         }
+
+        self.statistics.record_take(start.elapsed(), frame_info);
+
+        res
     }
 }
 
@@ -293,6 +344,9 @@ pub struct AudioEncoderBuilder {
     sample_format: Option<SampleFormat>,
     sample_rate: Option<u32>,
     channel_layout: Option<ChannelLayout>,
+
+    reproducible: bool,
+    auto_convert: bool,
 }
 
 impl AudioEncoderBuilder {
@@ -303,7 +357,10 @@ impl AudioEncoderBuilder {
         let ptr = unsafe { super::ffw_encoder_new(codec.as_ptr() as _) };
 
         if ptr.is_null() {
-            return Err(Error::new("unknown codec"));
+            return Err(Error::with_kind(
+                ErrorKind::EncoderNotFound,
+                "unknown codec",
+            ));
         }
 
         unsafe {
@@ -318,6 +375,9 @@ impl AudioEncoderBuilder {
             sample_format: None,
             sample_rate: None,
             channel_layout: None,
+
+            reproducible: false,
+            auto_convert: false,
         };
 
         Ok(res)
@@ -352,6 +412,9 @@ impl AudioEncoderBuilder {
             sample_format: Some(sample_format),
             sample_rate: Some(sample_rate),
             channel_layout: Some(channel_layout),
+
+            reproducible: false,
+            auto_convert: false,
         };
 
         Ok(res)
@@ -389,6 +452,13 @@ impl AudioEncoderBuilder {
         self
     }
 
+    /// Set the preferred number of samples per audio channel in a frame.
+    /// Only some encoders with a configurable frame size (e.g. FLAC) use
+    /// it. Check `AudioEncoder::frame_size()` for the final value.
+    pub fn frame_size(self, samples: usize) -> Self {
+        self.set_option("frame_size", samples)
+    }
+
     /// Set encoder time base. The default time base is in microseconds.
     pub fn time_base(mut self, time_base: TimeBase) -> Self {
         self.time_base = time_base;
@@ -422,6 +492,92 @@ impl AudioEncoderBuilder {
         self
     }
 
+    /// Convert pushed frames that do not match the sample format, the
+    /// sample rate, the channel layout or the frame size of the encoder
+    /// automatically using a resampler instead of failing. The resampler is
+    /// created for the first frame that does not match, so the parameters
+    /// of the pushed frames must not change after that. Automatic
+    /// conversion is disabled by default.
+    pub fn auto_convert(mut self, enabled: bool) -> Self {
+        self.auto_convert = enabled;
+        self
+    }
+
+    /// Get sample formats supported by the encoder (in the order of
+    /// preference). The list is empty if the encoder does not restrict the
+    /// sample formats.
+    pub fn supported_sample_formats(&self) -> Vec<SampleFormat> {
+        (0..)
+            .map(|index| unsafe {
+                super::ffw_encoder_get_supported_sample_format(self.raw.ptr, index)
+            })
+            .take_while(|&format| format >= 0)
+            .map(SampleFormat::from_raw)
+            .collect()
+    }
+
+    /// Get sample rates supported by the encoder. The list is empty if the
+    /// encoder does not restrict the sample rates.
+    pub fn supported_sample_rates(&self) -> Vec<u32> {
+        (0..)
+            .map(|index| unsafe {
+                super::ffw_encoder_get_supported_sample_rate(self.raw.ptr, index)
+            })
+            .take_while(|&rate| rate > 0)
+            .map(|rate| rate as u32)
+            .collect()
+    }
+
+    /// Get the preferred sample format of the encoder (if the encoder
+    /// restricts the supported sample formats).
+    pub(crate) fn default_sample_format(&self) -> Option<SampleFormat> {
+        let format = unsafe { super::ffw_encoder_get_default_sample_format(self.raw.ptr) };
+
+        if format < 0 {
+            None
+        } else {
+            Some(SampleFormat::from_raw(format))
+        }
+    }
+
+    /// Get a sample rate supported by the encoder that is closest to a given
+    /// sample rate.
+    pub(crate) fn closest_sample_rate(&self, sample_rate: u32) -> u32 {
+        unsafe { super::ffw_encoder_get_closest_sample_rate(self.raw.ptr, sample_rate as _) as _ }
+    }
+
+    /// Get a channel layout supported by the encoder that is closest to a
+    /// given channel layout.
+    pub(crate) fn closest_channel_layout(&self, layout: &ChannelLayoutRef) -> ChannelLayout {
+        unsafe {
+            let ptr = super::ffw_encoder_get_closest_channel_layout(self.raw.ptr, layout.as_ptr());
+
+            ChannelLayoutRef::from_raw_ptr(ptr).to_owned()
+        }
+    }
+
+    /// Check if the encoder supports a given sample format.
+    pub(crate) fn supports_sample_format(&self, format: SampleFormat) -> bool {
+        unsafe { super::ffw_encoder_supports_sample_format(self.raw.ptr, format.into_raw()) != 0 }
+    }
+
+    /// Make the encoder output reproducible, i.e. identical across runs and
+    /// machines (e.g. for golden file tests). The encoder will use
+    /// bit-exact algorithms only and it will run in a single thread. The
+    /// thread count cannot be overridden using encoder options in this
+    /// mode.
+    pub fn reproducible(mut self, reproducible: bool) -> Self {
+        self.reproducible = reproducible;
+        self
+    }
+
+    /// Set the level of compliance with the standards. Use
+    /// `Compliance::Experimental` in order to use experimental encoders.
+    /// The default is `Compliance::Normal`.
+    pub fn compliance(self, compliance: Compliance) -> Self {
+        self.set_option("strict", compliance.into_raw())
+    }
+
     /// Build the encoder.
     pub fn build(self) -> Result<AudioEncoder, Error> {
         let sample_format = self
@@ -438,6 +594,10 @@ impl AudioEncoderBuilder {
 
         let tb = self.time_base;
 
+        if self.reproducible && unsafe { super::ffw_encoder_set_reproducible(self.raw.ptr) } < 0 {
+            panic!("unable to allocate an option");
+        }
+
         unsafe {
             super::ffw_encoder_set_time_base(self.raw.ptr, tb.num() as _, tb.den() as _);
             super::ffw_encoder_set_sample_format(self.raw.ptr, sample_format.into_raw());
@@ -452,9 +612,21 @@ impl AudioEncoderBuilder {
             }
         }
 
+        // some encoders may change the time base
+        let tb = unsafe { super::get_encoder_time_base(self.raw.ptr) };
+
         let res = AudioEncoder {
             raw: self.raw,
             time_base: tb,
+
+            sample_format,
+            sample_rate,
+            channel_layout,
+
+            auto_convert: self.auto_convert,
+            resampler: None,
+            pending: None,
+            ready: VecDeque::new(),
         };
 
         Ok(res)
@@ -465,6 +637,15 @@ impl AudioEncoderBuilder {
 pub struct AudioEncoder {
     raw: RawAudioEncoder,
     time_base: TimeBase,
+
+    sample_format: SampleFormat,
+    sample_rate: u32,
+    channel_layout: ChannelLayout,
+
+    auto_convert: bool,
+    resampler: Option<AudioResampler>,
+    pending: Option<AudioFrame>,
+    ready: VecDeque<Packet>,
 }
 
 impl AudioEncoder {
@@ -480,40 +661,141 @@ impl AudioEncoder {
         AudioEncoderBuilder::new(codec)
     }
 
+    /// Get the encoder time base. It is the time base requested in the
+    /// builder unless the encoder adjusted it. Encoded packets carry this
+    /// time base, so they can be pushed to a muxer as they are, the muxer
+    /// will rescale them into the stream time base.
+    pub fn time_base(&self) -> TimeBase {
+        self.time_base
+    }
+
     /// Number of samples per audio channel in an audio frame. Each encoded
     /// frame except the last one must contain exactly this number of samples.
     /// The method returns None if the number of samples per frame is not
-    /// restricted.
+    /// restricted. This is the same as `frame_size()`.
     pub fn samples_per_frame(&self) -> Option<usize> {
-        let res = unsafe { super::ffw_encoder_get_frame_size(self.raw.ptr) as _ };
+        self.frame_size()
+    }
 
-        if res == 0 {
+    /// Get the number of samples per audio channel in every frame except
+    /// the last one (e.g. 1024 for AAC or 960 for Opus). Use it to size the
+    /// audio FIFO (see `AudioResamplerBuilder::target_frame_samples()`).
+    /// The method returns None if the number of samples per frame is not
+    /// restricted. Encoders with a variable frame size (see
+    /// `has_variable_frame_size()`) may report a frame size which is only a
+    /// hint.
+    pub fn frame_size(&self) -> Option<usize> {
+        let res = unsafe { super::ffw_encoder_get_frame_size(self.raw.ptr) };
+
+        if res > 0 {
+            Some(res as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Check if the encoder accepts frames with any number of samples
+    /// (`AV_CODEC_CAP_VARIABLE_FRAME_SIZE`). The frame size reported by
+    /// such encoders is only a hint.
+    pub fn has_variable_frame_size(&self) -> bool {
+        unsafe { super::ffw_encoder_has_variable_frame_size(self.raw.ptr) != 0 }
+    }
+
+    /// Get the frame size every frame except the last one must have (if
+    /// any).
+    fn required_frame_size(&self) -> Option<usize> {
+        if self.has_variable_frame_size() {
             None
         } else {
-            Some(res)
+            self.frame_size()
         }
     }
-}
 
-impl Encoder for AudioEncoder {
-    type CodecParameters = AudioCodecParameters;
-    type Frame = AudioFrame;
+    /// Check if a given frame needs to be converted before pushing it to
+    /// the encoder.
+    fn needs_conversion(&self, frame: &AudioFrame) -> bool {
+        if frame.sample_format() != self.sample_format
+            || frame.sample_rate() != self.sample_rate
+            || frame.channel_layout() != &self.channel_layout
+        {
+            return true;
+        }
 
-    fn codec_parameters(&self) -> Self::CodecParameters {
-        let ptr = unsafe { super::ffw_encoder_get_codec_parameters(self.raw.ptr) };
+        self.required_frame_size()
+            .map(|samples| frame.samples() != samples)
+            .unwrap_or(false)
+    }
+
+    /// Convert a given frame using the internal resampler, push the
+    /// converted frames to the encoder and put all encoded packets into the
+    /// internal ready queue. The resampler is flushed if there is no
+    /// frame.
+    fn push_converted(&mut self, frame: Option<AudioFrame>) -> Result<(), CodecError> {
+        if self.resampler.is_none() {
+            let frame = match frame.as_ref() {
+                Some(frame) => frame,
+                None => return Ok(()),
+            };
+
+            let resampler = AudioResampler::builder()
+                .source_channel_layout(frame.channel_layout().to_owned())
+                .source_sample_format(frame.sample_format())
+                .source_sample_rate(frame.sample_rate())
+                .target_channel_layout(self.channel_layout.clone())
+                .target_sample_format(self.sample_format)
+                .target_sample_rate(self.sample_rate)
+                .target_frame_samples(self.required_frame_size())
+                .build()?;
+
+            self.resampler = Some(resampler);
+        }
 
-        if ptr.is_null() {
-            panic!("unable to allocate codec parameters");
+        // finish the conversion interrupted by a previous error (if any)
+        self.encode_converted()?;
+
+        let resampler = self.resampler.as_mut().unwrap();
+
+        if let Some(frame) = frame {
+            resampler.try_push(frame)?;
+        } else {
+            resampler.try_flush()?;
         }
 
-        let params = unsafe { CodecParameters::from_raw_ptr(ptr) };
+        self.encode_converted()
+    }
 
-        params.into_audio_codec_parameters().unwrap()
+    /// Push all frames available in the internal resampler to the encoder
+    /// and put all encoded packets into the internal ready queue. A frame
+    /// rejected by the encoder is kept and it will be pushed again by the
+    /// next call.
+    fn encode_converted(&mut self) -> Result<(), CodecError> {
+        loop {
+            let frame = if let Some(frame) = self.pending.take() {
+                frame
+            } else if let Some(frame) = self.resampler.as_mut().unwrap().take()? {
+                frame
+            } else {
+                return Ok(());
+            };
+
+            if let Err(err) = self.push_to_encoder(frame.clone()) {
+                self.pending = Some(frame);
+
+                return Err(err);
+            }
+
+            while let Some(packet) = self.take_from_encoder()? {
+                self.ready.push_back(packet);
+            }
+        }
     }
 
-    fn try_push(&mut self, frame: AudioFrame) -> Result<(), CodecError> {
+    /// Push a given frame directly to the encoder.
+    fn push_to_encoder(&mut self, frame: AudioFrame) -> Result<(), CodecError> {
         let frame = frame.with_time_base(self.time_base);
 
+        let _span = trace_span!("audio_encoder_push", pts = frame.pts().as_micros());
+
         unsafe {
             match super::ffw_encoder_push_frame(self.raw.ptr, frame.as_ptr()) {
                 1 => Ok(()),
@@ -525,7 +807,8 @@ impl Encoder for AudioEncoder {
         }
     }
 
-    fn try_flush(&mut self) -> Result<(), CodecError> {
+    /// Flush the encoder directly.
+    fn flush_encoder(&mut self) -> Result<(), CodecError> {
         unsafe {
             match super::ffw_encoder_push_frame(self.raw.ptr, ptr::null()) {
                 1 => Ok(()),
@@ -537,10 +820,11 @@ impl Encoder for AudioEncoder {
         }
     }
 
-    fn take(&mut self) -> Result<Option<Packet>, Error> {
+    /// Take the next packet directly from the encoder.
+    fn take_from_encoder(&mut self) -> Result<Option<Packet>, Error> {
         let mut pptr = ptr::null_mut();
 
-        unsafe {
+        let res = unsafe {
             match super::ffw_encoder_take_packet(self.raw.ptr, &mut pptr) {
                 1 => {
                     if pptr.is_null() {
@@ -552,6 +836,184 @@ impl Encoder for AudioEncoder {
                 0 => Ok(None),
                 e => Err(Error::from_raw_error_code(e)),
             }
+        };
+
+        if let Ok(Some(packet)) = &res {
+            trace_event!(
+                pts = packet.pts().as_micros(),
+                dts = packet.dts().as_micros(),
+                "audio packet encoded"
+            );
+        }
+
+        res
+    }
+}
+
+impl Encoder for AudioEncoder {
+    type CodecParameters = AudioCodecParameters;
+    type Frame = AudioFrame;
+
+    fn codec_parameters(&self) -> Self::CodecParameters {
+        let ptr = unsafe { super::ffw_encoder_get_codec_parameters(self.raw.ptr) };
+
+        if ptr.is_null() {
+            panic!("unable to allocate codec parameters");
+        }
+
+        let params = unsafe { CodecParameters::from_raw_ptr(ptr) };
+
+        params.into_audio_codec_parameters().unwrap()
+    }
+
+    fn try_push(&mut self, frame: AudioFrame) -> Result<(), CodecError> {
+        if !self.auto_convert {
+            return self.push_to_encoder(frame);
+        }
+
+        if !self.ready.is_empty() {
+            return Err(CodecError::again(
+                "all packets must be consumed before pushing a new frame",
+            ));
+        }
+
+        if self.resampler.is_none() && !self.needs_conversion(&frame) {
+            self.push_to_encoder(frame)
+        } else {
+            self.push_converted(Some(frame))
+        }
+    }
+
+    fn try_flush(&mut self) -> Result<(), CodecError> {
+        if !self.ready.is_empty() {
+            return Err(CodecError::again(
+                "all packets must be consumed before flushing",
+            ));
+        }
+
+        self.push_converted(None)?;
+
+        self.flush_encoder()
+    }
+
+    fn take(&mut self) -> Result<Option<Packet>, Error> {
+        if let Some(packet) = self.ready.pop_front() {
+            return Ok(Some(packet));
+        }
+
+        self.take_from_encoder()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{frame::get_sample_format, AudioEncoder, AudioFrameMut, ChannelLayout};
+
+    use crate::{
+        codec::Encoder,
+        time::{TimeBase, Timestamp},
+    };
+
+    #[test]
+    fn test_supported_formats() {
+        let builder = AudioEncoder::builder("pcm_s16le").unwrap();
+
+        assert_eq!(
+            builder.supported_sample_formats(),
+            [get_sample_format("s16")]
+        );
+        assert!(builder.supported_sample_rates().is_empty());
+
+        let builder = AudioEncoder::builder("mp2").unwrap();
+
+        let rates = builder.supported_sample_rates();
+
+        assert!(rates.contains(&48_000));
+        assert!(!rates.contains(&8_000));
+    }
+
+    #[test]
+    fn test_auto_convert() {
+        let layout = ChannelLayout::from_channels(1).unwrap();
+
+        let mut encoder = AudioEncoder::builder("pcm_s16le")
+            .unwrap()
+            .sample_format(get_sample_format("s16"))
+            .sample_rate(8_000)
+            .channel_layout(layout)
+            .time_base(TimeBase::new(1, 8_000))
+            .auto_convert(true)
+            .build()
+            .unwrap();
+
+        let stereo = ChannelLayout::from_channels(2).unwrap();
+
+        let mut bytes = 0;
+
+        for i in 0..10 {
+            let frame = AudioFrameMut::silence(&stereo, get_sample_format("flt"), 16_000, 1_600)
+                .with_time_base(TimeBase::new(1, 16_000))
+                .with_pts(Timestamp::new(i * 1_600, TimeBase::new(1, 16_000)))
+                .freeze();
+
+            encoder.push(frame).unwrap();
+
+            while let Some(packet) = encoder.take().unwrap() {
+                bytes += packet.data().len();
+            }
+        }
+
+        encoder.flush().unwrap();
+
+        while let Some(packet) = encoder.take().unwrap() {
+            bytes += packet.data().len();
         }
+
+        // one second of mono 16-bit samples at 8 kHz
+        assert!((15_800..=16_200).contains(&bytes));
+    }
+
+    #[test]
+    fn test_ready_queue() {
+        let layout = ChannelLayout::from_channels(1).unwrap();
+        let format = get_sample_format("s16");
+
+        let mut encoder = AudioEncoder::builder("mp2")
+            .unwrap()
+            .sample_format(format)
+            .sample_rate(48_000)
+            .channel_layout(layout.clone())
+            .time_base(TimeBase::new(1, 48_000))
+            .auto_convert(true)
+            .build()
+            .unwrap();
+
+        let frame = AudioFrameMut::silence(&layout, format, 48_000, 4 * 1_152)
+            .with_time_base(TimeBase::new(1, 48_000))
+            .freeze();
+
+        // the frame needs to be split, so the packets go to the ready queue
+        encoder.try_push(frame.clone()).unwrap();
+
+        let err = encoder.try_push(frame.clone()).unwrap_err();
+
+        assert!(err.is_again());
+        assert!(encoder.try_flush().unwrap_err().is_again());
+
+        let mut packets = 0;
+
+        while encoder.take().unwrap().is_some() {
+            packets += 1;
+        }
+
+        assert!(packets > 0);
+
+        encoder
+            .try_push(frame.with_pts(Timestamp::new(4 * 1_152, TimeBase::new(1, 48_000))))
+            .unwrap();
+
+        while encoder.take().unwrap().is_some() {}
+
+        encoder.try_flush().unwrap();
     }
 }
@@ -0,0 +1,202 @@
+#include <math.h>
+#include <stdio.h>
+
+#include <libavfilter/avfilter.h>
+#include <libavfilter/buffersink.h>
+#include <libavfilter/buffersrc.h>
+#include <libavutil/channel_layout.h>
+#include <libavutil/frame.h>
+#include <libavutil/opt.h>
+
+typedef struct PlaybackRate {
+    AVFilterGraph* graph;
+    AVFilterContext* source;
+    AVFilterContext* sink;
+    AVFrame* frame;
+} PlaybackRate;
+
+void ffw_playback_rate_free(PlaybackRate* pr);
+
+static int create_filter(
+    AVFilterContext** ctx,
+    AVFilterGraph* graph,
+    const char* name,
+    const char* instance_name,
+    const char* args) {
+    const AVFilter* filter;
+
+    if (!(filter = avfilter_get_by_name(name))) {
+        return AVERROR_FILTER_NOT_FOUND;
+    }
+
+    return avfilter_graph_create_filter(ctx, filter, instance_name, args, NULL, graph);
+}
+
+static int create_atempo_filter(AVFilterContext** ctx, AVFilterGraph* graph, const char* instance_name, double tempo) {
+    const AVFilter* filter;
+    int ret;
+
+    if (!(filter = avfilter_get_by_name("atempo"))) {
+        return AVERROR_FILTER_NOT_FOUND;
+    }
+
+    if (!(*ctx = avfilter_graph_alloc_filter(graph, filter, instance_name))) {
+        return AVERROR(ENOMEM);
+    }
+
+    if ((ret = av_opt_set_double(*ctx, "tempo", tempo, AV_OPT_SEARCH_CHILDREN)) < 0) {
+        return ret;
+    }
+
+    return avfilter_init_str(*ctx, NULL);
+}
+
+#ifdef FFW_FEATURE_CHANNEL_LAYOUT_V2
+PlaybackRate* ffw_playback_rate_new(
+    const AVChannelLayout* channel_layout,
+    int sample_format,
+    int sample_rate,
+    double tempo) {
+#else
+PlaybackRate* ffw_playback_rate_new(
+    const uint64_t* channel_layout,
+    int sample_format,
+    int sample_rate,
+    double tempo) {
+#endif
+    PlaybackRate* res;
+    AVFilterContext* atempo1;
+    AVFilterContext* atempo2;
+    const char* sample_format_name;
+
+    char layout[256];
+    char args[512];
+
+    if (!(sample_format_name = av_get_sample_fmt_name(sample_format))) {
+        return NULL;
+    }
+
+#ifdef FFW_FEATURE_CHANNEL_LAYOUT_V2
+    if (av_channel_layout_describe(channel_layout, layout, sizeof(layout)) < 0) {
+        return NULL;
+    }
+#else
+    av_get_channel_layout_string(
+        layout,
+        sizeof(layout),
+        av_get_channel_layout_nb_channels(*channel_layout),
+        *channel_layout);
+#endif
+
+    snprintf(
+        args,
+        sizeof(args),
+        "time_base=1/%d:sample_rate=%d:sample_fmt=%s:channel_layout=%s",
+        sample_rate,
+        sample_rate,
+        sample_format_name,
+        layout);
+
+    if (!(res = calloc(1, sizeof(PlaybackRate)))) {
+        return NULL;
+    }
+
+    if (!(res->graph = avfilter_graph_alloc())) {
+        goto err;
+    }
+
+    if (!(res->frame = av_frame_alloc())) {
+        goto err;
+    }
+
+    if (create_filter(&res->source, res->graph, "abuffer", "in", args) < 0) {
+        goto err;
+    }
+
+    // a single atempo filter instance may not support the whole range of
+    // playback rates, so we use two of them
+    if (create_atempo_filter(&atempo1, res->graph, "atempo1", tempo) < 0) {
+        goto err;
+    }
+
+    if (create_atempo_filter(&atempo2, res->graph, "atempo2", tempo) < 0) {
+        goto err;
+    }
+
+    if (create_filter(&res->sink, res->graph, "abuffersink", "out", NULL) < 0) {
+        goto err;
+    }
+
+    if (avfilter_link(res->source, 0, atempo1, 0) < 0) {
+        goto err;
+    } else if (avfilter_link(atempo1, 0, atempo2, 0) < 0) {
+        goto err;
+    } else if (avfilter_link(atempo2, 0, res->sink, 0) < 0) {
+        goto err;
+    }
+
+    if (avfilter_graph_config(res->graph, NULL) < 0) {
+        goto err;
+    }
+
+    return res;
+
+err:
+    ffw_playback_rate_free(res);
+
+    return NULL;
+}
+
+int ffw_playback_rate_set_tempo(PlaybackRate* pr, double tempo) {
+    char value[64];
+    long micros;
+
+    // commands take string arguments only; FFmpeg expects the decimal
+    // point, so the value is formatted without using the current locale
+    micros = lround(tempo * 1000000);
+
+    snprintf(value, sizeof(value), "%ld.%06ld", micros / 1000000, micros % 1000000);
+
+    return avfilter_graph_send_command(pr->graph, "atempo", "tempo", value, NULL, 0, 0);
+}
+
+int ffw_playback_rate_push_frame(PlaybackRate* pr, const AVFrame* frame) {
+    int ret;
+
+    if (frame) {
+        ret = av_buffersrc_add_frame_flags(pr->source, (AVFrame*)frame, AV_BUFFERSRC_FLAG_KEEP_REF);
+    } else {
+        ret = av_buffersrc_add_frame(pr->source, NULL);
+    }
+
+    return ret;
+}
+
+int ffw_playback_rate_take_frame(PlaybackRate* pr, AVFrame** frame) {
+    int ret;
+
+    ret = av_buffersink_get_frame(pr->sink, pr->frame);
+
+    if (ret == AVERROR(EAGAIN) || ret == AVERROR_EOF) {
+        return 0;
+    } else if (ret < 0) {
+        return ret;
+    }
+
+    *frame = av_frame_clone(pr->frame);
+
+    av_frame_unref(pr->frame);
+
+    return 1;
+}
+
+void ffw_playback_rate_free(PlaybackRate* pr) {
+    if (!pr) {
+        return;
+    }
+
+    avfilter_graph_free(&pr->graph);
+    av_frame_free(&pr->frame);
+
+    free(pr);
+}
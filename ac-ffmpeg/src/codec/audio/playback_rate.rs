@@ -0,0 +1,346 @@
+//! Audio playback rate control.
+
+use std::{
+    os::raw::{c_int, c_void},
+    ptr,
+};
+
+use crate::{
+    codec::{
+        audio::{AudioFrame, ChannelLayout, SampleFormat},
+        CodecError,
+    },
+    time::TimeBase,
+    Error,
+};
+
+extern "C" {
+    fn ffw_playback_rate_new(
+        channel_layout: *const c_void,
+        sample_format: c_int,
+        sample_rate: c_int,
+        tempo: f64,
+    ) -> *mut c_void;
+    fn ffw_playback_rate_set_tempo(pr: *mut c_void, tempo: f64) -> c_int;
+    fn ffw_playback_rate_push_frame(pr: *mut c_void, frame: *const c_void) -> c_int;
+    fn ffw_playback_rate_take_frame(pr: *mut c_void, frame: *mut *mut c_void) -> c_int;
+    fn ffw_playback_rate_free(pr: *mut c_void);
+}
+
+/// Minimum supported playback rate.
+pub const MIN_PLAYBACK_RATE: f64 = 0.25;
+
+/// Maximum supported playback rate.
+pub const MAX_PLAYBACK_RATE: f64 = 4.0;
+
+/// Check if a given playback rate is supported.
+fn check_rate(rate: f64) -> Result<(), Error> {
+    if (MIN_PLAYBACK_RATE..=MAX_PLAYBACK_RATE).contains(&rate) {
+        Ok(())
+    } else {
+        Err(Error::new(format!(
+            "playback rate must be between {} and {}",
+            MIN_PLAYBACK_RATE, MAX_PLAYBACK_RATE
+        )))
+    }
+}
+
+/// Get tempo of a single atempo filter for a given playback rate. The
+/// playback rate is split between two filter instances because older FFmpeg
+/// versions support only tempo between 0.5 and 2.0 for a single instance.
+fn filter_tempo(rate: f64) -> f64 {
+    rate.sqrt()
+}
+
+/// Builder for the playback rate control.
+pub struct PlaybackRateBuilder {
+    channel_layout: Option<ChannelLayout>,
+    sample_format: Option<SampleFormat>,
+    sample_rate: Option<u32>,
+    rate: f64,
+}
+
+impl PlaybackRateBuilder {
+    /// Create a new builder.
+    fn new() -> Self {
+        Self {
+            channel_layout: None,
+            sample_format: None,
+            sample_rate: None,
+            rate: 1.0,
+        }
+    }
+
+    /// Set channel layout of the input frames.
+    pub fn channel_layout(mut self, channel_layout: ChannelLayout) -> Self {
+        self.channel_layout = Some(channel_layout);
+        self
+    }
+
+    /// Set sample format of the input frames.
+    pub fn sample_format(mut self, sample_format: SampleFormat) -> Self {
+        self.sample_format = Some(sample_format);
+        self
+    }
+
+    /// Set sample rate of the input frames.
+    pub fn sample_rate(mut self, sample_rate: u32) -> Self {
+        self.sample_rate = Some(sample_rate);
+        self
+    }
+
+    /// Set the initial playback rate. The default is 1.0.
+    pub fn rate(mut self, rate: f64) -> Self {
+        self.rate = rate;
+        self
+    }
+
+    /// Build the playback rate control.
+    pub fn build(self) -> Result<PlaybackRate, Error> {
+        let channel_layout = self
+            .channel_layout
+            .ok_or_else(|| Error::new("channel layout was not set"))?;
+        let sample_format = self
+            .sample_format
+            .ok_or_else(|| Error::new("sample format was not set"))?;
+        let sample_rate = self
+            .sample_rate
+            .ok_or_else(|| Error::new("sample rate was not set"))?;
+
+        check_rate(self.rate)?;
+
+        let ptr = unsafe {
+            ffw_playback_rate_new(
+                channel_layout.as_ptr(),
+                sample_format.into_raw(),
+                sample_rate as _,
+                filter_tempo(self.rate),
+            )
+        };
+
+        if ptr.is_null() {
+            return Err(Error::new(
+                "unable to create a playback rate control for a given configuration",
+            ));
+        }
+
+        let res = PlaybackRate {
+            ptr,
+
+            channel_layout,
+            sample_format,
+            sample_rate,
+            rate: self.rate,
+        };
+
+        Ok(res)
+    }
+}
+
+/// Pitch-preserving audio playback rate control. It supports playback rates
+/// from 0.25 to 4.0.
+///
+/// # Operation
+/// 1. Push an audio frame.
+/// 2. Take all frames until you get None.
+/// 3. If there are more frames to be processed, continue with 1.
+/// 4. Flush the playback rate control.
+/// 5. Take all frames until you get None.
+///
+/// Output frames use the same channel layout, sample format and sample rate
+/// as the input frames. Timestamps of the output frames will be in
+/// 1 / sample_rate time base and they will be continuous, starting at the
+/// timestamp of the first input frame, i.e. they reflect the playback time
+/// rather than the media time. The playback rate can be changed at any time
+/// without introducing gaps in the output timestamps.
+pub struct PlaybackRate {
+    ptr: *mut c_void,
+
+    channel_layout: ChannelLayout,
+    sample_format: SampleFormat,
+    sample_rate: u32,
+    rate: f64,
+}
+
+impl PlaybackRate {
+    /// Get a builder for the playback rate control.
+    pub fn builder() -> PlaybackRateBuilder {
+        PlaybackRateBuilder::new()
+    }
+
+    /// Get the current playback rate.
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// Change the playback rate. The change will be applied to all frames
+    /// that have not been processed yet.
+    pub fn set_rate(&mut self, rate: f64) -> Result<(), Error> {
+        check_rate(rate)?;
+
+        let ret = unsafe { ffw_playback_rate_set_tempo(self.ptr, filter_tempo(rate)) };
+
+        if ret < 0 {
+            return Err(Error::from_raw_error_code(ret));
+        }
+
+        self.rate = rate;
+
+        Ok(())
+    }
+
+    /// Push a given frame. Frames are buffered internally, so any number
+    /// of frames can be pushed without taking the output frames.
+    pub fn push(&mut self, frame: AudioFrame) -> Result<(), Error> {
+        self.try_push(frame).map_err(|err| err.unwrap_inner())
+    }
+
+    /// Push a given frame. The method never returns the "again" error.
+    pub fn try_push(&mut self, frame: AudioFrame) -> Result<(), CodecError> {
+        if frame.channel_layout() != &self.channel_layout {
+            return Err(CodecError::error(
+                "invalid frame, channel layout does not match",
+            ));
+        }
+
+        if frame.sample_format() != self.sample_format {
+            return Err(CodecError::error(
+                "invalid frame, sample format does not match",
+            ));
+        }
+
+        if frame.sample_rate() != self.sample_rate {
+            return Err(CodecError::error(
+                "invalid frame, sample rate does not match",
+            ));
+        }
+
+        let frame = frame.with_time_base(TimeBase::new(1, self.sample_rate));
+
+        let ret = unsafe { ffw_playback_rate_push_frame(self.ptr, frame.as_ptr()) };
+
+        if ret < 0 {
+            return Err(CodecError::from_raw_error_code(ret));
+        }
+
+        Ok(())
+    }
+
+    /// Flush the playback rate control.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.try_flush().map_err(|err| err.unwrap_inner())
+    }
+
+    /// Flush the playback rate control. The method never returns the
+    /// "again" error.
+    pub fn try_flush(&mut self) -> Result<(), CodecError> {
+        let ret = unsafe { ffw_playback_rate_push_frame(self.ptr, ptr::null()) };
+
+        if ret < 0 {
+            return Err(CodecError::from_raw_error_code(ret));
+        }
+
+        Ok(())
+    }
+
+    /// Take a frame (if available).
+    pub fn take(&mut self) -> Result<Option<AudioFrame>, Error> {
+        let mut fptr = ptr::null_mut();
+
+        let tb = TimeBase::new(1, self.sample_rate);
+
+        unsafe {
+            match ffw_playback_rate_take_frame(self.ptr, &mut fptr) {
+                1 => {
+                    if fptr.is_null() {
+                        panic!("unable to allocate an audio frame")
+                    } else {
+                        Ok(Some(AudioFrame::from_raw_ptr(fptr, tb)))
+                    }
+                }
+                0 => Ok(None),
+                e => Err(Error::from_raw_error_code(e)),
+            }
+        }
+    }
+}
+
+impl Drop for PlaybackRate {
+    fn drop(&mut self) {
+        unsafe { ffw_playback_rate_free(self.ptr) }
+    }
+}
+
+unsafe impl Send for PlaybackRate {}
+unsafe impl Sync for PlaybackRate {}
+
+#[cfg(test)]
+mod tests {
+    use super::PlaybackRate;
+
+    use crate::codec::audio::{frame::get_sample_format, AudioFrameMut, ChannelLayout};
+
+    /// Push one second of silence in 10 chunks, change the rate halfway
+    /// through and return the number of output samples.
+    fn process(initial_rate: f64, rate: f64) -> usize {
+        let layout = ChannelLayout::from_channels(2).unwrap();
+        let format = get_sample_format("flt");
+
+        let mut pr = PlaybackRate::builder()
+            .channel_layout(layout.clone())
+            .sample_format(format)
+            .sample_rate(48_000)
+            .rate(initial_rate)
+            .build()
+            .unwrap();
+
+        let mut samples = 0;
+
+        for i in 0..10 {
+            if i == 5 {
+                pr.set_rate(rate).unwrap();
+            }
+
+            let frame = AudioFrameMut::silence(&layout, format, 48_000, 4_800).freeze();
+
+            pr.push(frame).unwrap();
+
+            while let Some(frame) = pr.take().unwrap() {
+                samples += frame.samples();
+            }
+        }
+
+        pr.flush().unwrap();
+
+        while let Some(frame) = pr.take().unwrap() {
+            samples += frame.samples();
+        }
+
+        samples
+    }
+
+    #[test]
+    fn test_playback_rate() {
+        let expected = [(1.0, 1.0, 48_000), (2.0, 2.0, 24_000), (0.5, 2.0, 60_000)];
+
+        for &(initial_rate, rate, samples) in &expected {
+            let res = process(initial_rate, rate) as i64;
+
+            // allow for the atempo fragment granularity and the samples
+            // buffered when the rate changes
+            assert!((res - samples).abs() < 9_600, "{} != {}", res, samples);
+        }
+    }
+
+    #[test]
+    fn test_invalid_rate() {
+        let layout = ChannelLayout::from_channels(2).unwrap();
+
+        let builder = PlaybackRate::builder()
+            .channel_layout(layout)
+            .sample_format(get_sample_format("flt"))
+            .sample_rate(48_000);
+
+        assert!(builder.rate(8.0).build().is_err());
+    }
+}
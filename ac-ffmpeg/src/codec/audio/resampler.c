@@ -1,3 +1,6 @@
+#include <math.h>
+
+#include <libavutil/opt.h>
 #include <libswresample/swresample.h>
 
 typedef struct AudioResampler {
@@ -71,6 +74,30 @@ err:
 
 void ffw_audio_resampler_free(AudioResampler* resampler);
 
+static int set_downmix_options(
+    struct SwrContext* context,
+    double center_mix_level,
+    double surround_mix_level,
+    double lfe_mix_level,
+    int matrix_encoding) {
+    int ret;
+
+    // NaN means the default value
+    if (!isnan(center_mix_level) && (ret = av_opt_set_double(context, "center_mix_level", center_mix_level, 0)) < 0) {
+        return ret;
+    }
+
+    if (!isnan(surround_mix_level) && (ret = av_opt_set_double(context, "surround_mix_level", surround_mix_level, 0)) < 0) {
+        return ret;
+    }
+
+    if (!isnan(lfe_mix_level) && (ret = av_opt_set_double(context, "lfe_mix_level", lfe_mix_level, 0)) < 0) {
+        return ret;
+    }
+
+    return av_opt_set_int(context, "matrix_encoding", matrix_encoding, 0);
+}
+
 #ifdef FFW_FEATURE_CHANNEL_LAYOUT_V2
 AudioResampler* ffw_audio_resampler_new(
     const AVChannelLayout* target_channel_layout,
@@ -79,7 +106,12 @@ AudioResampler* ffw_audio_resampler_new(
     int target_frame_samples,
     const AVChannelLayout* source_channel_layout,
     int source_sample_format,
-    int source_sample_rate) {
+    int source_sample_rate,
+    const double* matrix,
+    double center_mix_level,
+    double surround_mix_level,
+    double lfe_mix_level,
+    int matrix_encoding) {
     AudioResampler* res;
     int ret;
 
@@ -112,6 +144,14 @@ AudioResampler* ffw_audio_resampler_new(
         goto err;
     }
 
+    if (matrix && swr_set_matrix(res->resample_context, matrix, source_channel_layout->nb_channels) < 0) {
+        goto err;
+    }
+
+    if (set_downmix_options(res->resample_context, center_mix_level, surround_mix_level, lfe_mix_level, matrix_encoding) < 0) {
+        goto err;
+    }
+
     if (swr_init(res->resample_context) < 0) {
         goto err;
     }
@@ -131,7 +171,12 @@ AudioResampler* ffw_audio_resampler_new(
     int target_frame_samples,
     const uint64_t* source_channel_layout,
     int source_sample_format,
-    int source_sample_rate) {
+    int source_sample_rate,
+    const double* matrix,
+    double center_mix_level,
+    double surround_mix_level,
+    double lfe_mix_level,
+    int matrix_encoding) {
     AudioResampler* res = calloc(1, sizeof(AudioResampler));
 
     if (!res) {
@@ -160,6 +205,14 @@ AudioResampler* ffw_audio_resampler_new(
         goto err;
     }
 
+    if (matrix && swr_set_matrix(res->resample_context, matrix, av_get_channel_layout_nb_channels(*source_channel_layout)) < 0) {
+        goto err;
+    }
+
+    if (set_downmix_options(res->resample_context, center_mix_level, surround_mix_level, lfe_mix_level, matrix_encoding) < 0) {
+        goto err;
+    }
+
     if (swr_init(res->resample_context) < 0) {
         goto err;
     }
@@ -23,12 +23,45 @@ extern "C" {
         source_channel_layout: *const c_void,
         source_sample_format: c_int,
         source_sample_rate: c_int,
+        matrix: *const f64,
+        center_mix_level: f64,
+        surround_mix_level: f64,
+        lfe_mix_level: f64,
+        matrix_encoding: c_int,
     ) -> *mut c_void;
     fn ffw_audio_resampler_free(resampler: *mut c_void);
     fn ffw_audio_resampler_push_frame(resampler: *mut c_void, frame: *const c_void) -> c_int;
     fn ffw_audio_resampler_take_frame(resampler: *mut c_void, frame: *mut *mut c_void) -> c_int;
 }
 
+/// Matrix encoding of a stereo downmix.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MatrixEncoding {
+    /// Plain stereo downmix. This is the default.
+    None,
+    /// Dolby Surround compatible downmix.
+    Dolby,
+    /// Dolby Pro Logic II compatible downmix.
+    DolbyProLogicII,
+}
+
+impl MatrixEncoding {
+    /// Get the raw value of the matrix encoding.
+    fn into_raw(self) -> c_int {
+        match self {
+            Self::None => 0,
+            Self::Dolby => 1,
+            Self::DolbyProLogicII => 2,
+        }
+    }
+}
+
+/// Get a linear mix level corresponding to a given gain in decibels (e.g.
+/// -3 dB corresponds to roughly 0.708).
+pub fn mix_level_from_db(db: f64) -> f64 {
+    10f64.powf(db / 20.0)
+}
+
 /// Builder for the audio resampler.
 pub struct AudioResamplerBuilder {
     source_channel_layout: Option<ChannelLayout>,
@@ -40,6 +73,13 @@ pub struct AudioResamplerBuilder {
     target_sample_rate: Option<u32>,
 
     target_frame_samples: Option<usize>,
+
+    matrix: Option<Vec<f64>>,
+
+    center_mix_level: Option<f64>,
+    surround_mix_level: Option<f64>,
+    lfe_mix_level: Option<f64>,
+    matrix_encoding: MatrixEncoding,
 }
 
 impl AudioResamplerBuilder {
@@ -55,6 +95,13 @@ impl AudioResamplerBuilder {
             target_sample_rate: None,
 
             target_frame_samples: None,
+
+            matrix: None,
+
+            center_mix_level: None,
+            surround_mix_level: None,
+            lfe_mix_level: None,
+            matrix_encoding: MatrixEncoding::None,
         }
     }
 
@@ -101,6 +148,46 @@ impl AudioResamplerBuilder {
         self
     }
 
+    /// Set a custom mixing matrix. The matrix contains one row of
+    /// coefficients for each target channel and each row contains one
+    /// coefficient for each source channel (i.e. `matrix[t * sources + s]`
+    /// is the contribution of the source channel `s` into the target channel
+    /// `t`). The matrix is derived from the source and target channel
+    /// layouts by default.
+    pub fn matrix(mut self, matrix: Vec<f64>) -> Self {
+        self.matrix = Some(matrix);
+        self
+    }
+
+    /// Set a linear mix level of the center channel used when the matrix is
+    /// derived from the channel layouts (e.g. for a 5.1 to stereo downmix).
+    /// The default is -3 dB. See also `mix_level_from_db()`.
+    pub fn center_mix_level(mut self, level: f64) -> Self {
+        self.center_mix_level = Some(level);
+        self
+    }
+
+    /// Set a linear mix level of the surround channels used when the matrix
+    /// is derived from the channel layouts. The default is -3 dB.
+    pub fn surround_mix_level(mut self, level: f64) -> Self {
+        self.surround_mix_level = Some(level);
+        self
+    }
+
+    /// Set a linear mix level of the LFE channel used when the matrix is
+    /// derived from the channel layouts. The default is zero (i.e. the LFE
+    /// channel is dropped when downmixing).
+    pub fn lfe_mix_level(mut self, level: f64) -> Self {
+        self.lfe_mix_level = Some(level);
+        self
+    }
+
+    /// Set the matrix encoding of a stereo downmix.
+    pub fn matrix_encoding(mut self, encoding: MatrixEncoding) -> Self {
+        self.matrix_encoding = encoding;
+        self
+    }
+
     /// Build the resampler.
     pub fn build(self) -> Result<AudioResampler, Error> {
         let source_channel_layout = self
@@ -125,6 +212,33 @@ impl AudioResamplerBuilder {
 
         let target_frame_samples = self.target_frame_samples.unwrap_or(0);
 
+        if let Some(matrix) = self.matrix.as_ref() {
+            let size = source_channel_layout.channels() as usize
+                * target_channel_layout.channels() as usize;
+
+            if matrix.len() != size {
+                return Err(Error::new("invalid mixing matrix size"));
+            }
+        }
+
+        let mix_levels = [
+            self.center_mix_level,
+            self.surround_mix_level,
+            self.lfe_mix_level,
+        ];
+
+        for level in mix_levels.iter().flatten() {
+            if !level.is_finite() || level.abs() > 32.0 {
+                return Err(Error::new("invalid mix level"));
+            }
+        }
+
+        let matrix = self
+            .matrix
+            .as_ref()
+            .map(|matrix| matrix.as_ptr())
+            .unwrap_or(ptr::null());
+
         let ptr = unsafe {
             ffw_audio_resampler_new(
                 target_channel_layout.as_ptr(),
@@ -134,6 +248,11 @@ impl AudioResamplerBuilder {
                 source_channel_layout.as_ptr(),
                 source_sample_format.into_raw(),
                 source_sample_rate as _,
+                matrix,
+                self.center_mix_level.unwrap_or(f64::NAN),
+                self.surround_mix_level.unwrap_or(f64::NAN),
+                self.lfe_mix_level.unwrap_or(f64::NAN),
+                self.matrix_encoding.into_raw(),
             )
         };
 
@@ -182,6 +301,14 @@ impl AudioResampler {
         AudioResamplerBuilder::new()
     }
 
+    /// Check if a given frame has the source channel layout, sample format
+    /// and sample rate of the resampler.
+    pub(crate) fn accepts(&self, frame: &AudioFrame) -> bool {
+        frame.channel_layout() == &self.source_channel_layout
+            && frame.sample_format() == self.source_sample_format
+            && frame.sample_rate() == self.source_sample_rate
+    }
+
     /// Push a given frame to the resampler.
     ///
     /// # Panics
@@ -213,6 +340,8 @@ impl AudioResampler {
 
         let frame = frame.with_time_base(TimeBase::new(1, self.source_sample_rate));
 
+        let _span = trace_span!("audio_resampler_push", pts = frame.pts().as_micros());
+
         unsafe {
             match ffw_audio_resampler_push_frame(self.ptr, frame.as_ptr()) {
                 1 => Ok(()),
@@ -276,3 +405,122 @@ impl Drop for AudioResampler {
 
 unsafe impl Send for AudioResampler {}
 unsafe impl Sync for AudioResampler {}
+
+#[cfg(test)]
+mod tests {
+    use super::{mix_level_from_db, AudioResampler, AudioResamplerBuilder, MatrixEncoding};
+
+    use crate::codec::audio::{
+        frame::{get_channel_layout, get_sample_format},
+        AudioFrameMut,
+    };
+
+    /// Get a resampler builder for a 5.1 to stereo downmix.
+    fn downmix() -> AudioResamplerBuilder {
+        AudioResampler::builder()
+            .source_channel_layout(get_channel_layout("5.1"))
+            .source_sample_format(get_sample_format("flt"))
+            .source_sample_rate(48_000)
+            .target_channel_layout(get_channel_layout("stereo"))
+            .target_sample_format(get_sample_format("flt"))
+            .target_sample_rate(48_000)
+    }
+
+    /// Downmix a 5.1 signal having only a given channel set to 1.0 and
+    /// return the resulting left and right sample.
+    fn downmix_channel(builder: AudioResamplerBuilder, channel: usize) -> (f32, f32) {
+        let mut resampler = builder.build().unwrap();
+
+        let mut data = vec![0.0; 6 * 16];
+
+        for sample in data.chunks_exact_mut(6) {
+            sample[channel] = 1.0;
+        }
+
+        let frame =
+            AudioFrameMut::from_interleaved_f32(&get_channel_layout("5.1"), 48_000, &data).freeze();
+
+        let mut output = Vec::new();
+
+        resampler.push(frame).unwrap();
+
+        while let Some(frame) = resampler.take().unwrap() {
+            output.extend(frame.to_interleaved_f32());
+        }
+
+        resampler.flush().unwrap();
+
+        while let Some(frame) = resampler.take().unwrap() {
+            output.extend(frame.to_interleaved_f32());
+        }
+
+        (output[0], output[1])
+    }
+
+    /// Check that two given levels are approximately equal.
+    fn assert_level(actual: f32, expected: f64) {
+        assert!((actual as f64 - expected).abs() < 0.001, "{}", actual);
+    }
+
+    #[test]
+    fn test_mix_levels() {
+        let sqrt1_2 = std::f64::consts::FRAC_1_SQRT_2;
+
+        assert!((mix_level_from_db(-3.0) - 0.708).abs() < 0.001);
+        assert!((mix_level_from_db(0.0) - 1.0).abs() < 1e-9);
+
+        // center channel (-3 dB by default)
+        let (left, right) = downmix_channel(downmix(), 2);
+
+        assert_level(left, sqrt1_2);
+        assert_level(right, sqrt1_2);
+
+        let (left, right) = downmix_channel(downmix().center_mix_level(0.5), 2);
+
+        assert_level(left, 0.5);
+        assert_level(right, 0.5);
+
+        // LFE channel (dropped by default)
+        let (left, right) = downmix_channel(downmix(), 3);
+
+        assert_level(left, 0.0);
+        assert_level(right, 0.0);
+
+        let (left, right) = downmix_channel(downmix().lfe_mix_level(1.0), 3);
+
+        assert_level(left, sqrt1_2);
+        assert_level(right, sqrt1_2);
+
+        // left surround channel
+        let (left, right) = downmix_channel(downmix().surround_mix_level(0.5), 4);
+
+        assert_level(left, 0.5);
+        assert_level(right, 0.0);
+
+        // invalid mix levels
+        assert!(downmix().center_mix_level(f64::NAN).build().is_err());
+        assert!(downmix().surround_mix_level(100.0).build().is_err());
+    }
+
+    #[test]
+    fn test_matrix_encoding() {
+        // the left surround channel is mixed into both output channels
+        let builder = downmix().matrix_encoding(MatrixEncoding::Dolby);
+
+        let (_, right) = downmix_channel(builder, 4);
+
+        assert!(right.abs() > 0.1);
+
+        let builder = downmix().matrix_encoding(MatrixEncoding::DolbyProLogicII);
+
+        let (_, right) = downmix_channel(builder, 4);
+
+        assert!(right.abs() > 0.1);
+
+        let builder = downmix().matrix_encoding(MatrixEncoding::None);
+
+        let (_, right) = downmix_channel(builder, 4);
+
+        assert_level(right, 0.0);
+    }
+}
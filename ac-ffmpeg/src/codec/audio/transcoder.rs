@@ -11,14 +11,33 @@ use crate::{
     codec::{
         audio::{
             AudioDecoder, AudioDecoderBuilder, AudioEncoder, AudioEncoderBuilder, AudioFrame,
-            AudioResampler,
+            AudioResampler, ChannelLayout, SampleFormat,
         },
+        hook::FrameHooks,
         AudioCodecParameters, CodecError, Decoder, Encoder,
     },
     packet::Packet,
     time::TimeBase,
 };
 
+/// Output parameter changed by the transcoder because the requested value
+/// is not supported by the encoder.
+#[derive(Debug, Clone)]
+pub enum NegotiatedParameter {
+    /// The sample format was changed.
+    SampleFormat {
+        requested: SampleFormat,
+        chosen: SampleFormat,
+    },
+    /// The sample rate was changed.
+    SampleRate { requested: u32, chosen: u32 },
+    /// The channel layout was changed.
+    ChannelLayout {
+        requested: ChannelLayout,
+        chosen: ChannelLayout,
+    },
+}
+
 /// Builder for the AudioTranscoder.
 pub struct AudioTranscoderBuilder {
     input: AudioCodecParameters,
@@ -26,6 +45,10 @@ pub struct AudioTranscoderBuilder {
 
     decoder_builder: AudioDecoderBuilder,
     encoder_builder: AudioEncoderBuilder,
+
+    hooks: FrameHooks,
+
+    negotiate: bool,
 }
 
 impl AudioTranscoderBuilder {
@@ -40,6 +63,10 @@ impl AudioTranscoderBuilder {
 
             decoder_builder,
             encoder_builder,
+
+            hooks: FrameHooks::new(),
+
+            negotiate: true,
         };
 
         Ok(res)
@@ -63,6 +90,29 @@ impl AudioTranscoderBuilder {
         self
     }
 
+    /// Set a hook that will be called for every decoded audio frame before
+    /// it gets resampled and encoded. See the `codec::hook` module for the
+    /// ordering and threading guarantees.
+    pub fn frame_hook<F>(mut self, hook: F) -> Self
+    where
+        F: FnMut(AudioFrame) -> Result<AudioFrame, Error> + Send + 'static,
+    {
+        self.hooks = self.hooks.with_audio_hook(hook);
+        self
+    }
+
+    /// Enable or disable negotiation of the output parameters. If enabled,
+    /// the output sample format, sample rate and channel layout are replaced
+    /// with the closest values supported by the encoder (e.g. 48 kHz for
+    /// Opus) instead of failing when the encoder is opened. The resampler
+    /// converts the audio accordingly. Changed parameters are reported by
+    /// the `negotiated_parameters` method of the transcoder. Negotiation is
+    /// enabled by default.
+    pub fn negotiate(mut self, negotiate: bool) -> Self {
+        self.negotiate = negotiate;
+        self
+    }
+
     /// Build the transcoder.
     pub fn build(self) -> Result<AudioTranscoder, Error> {
         let decoder = self
@@ -70,21 +120,67 @@ impl AudioTranscoderBuilder {
             .time_base(TimeBase::new(1, self.input.sample_rate()))
             .build()?;
 
-        let encoder = self
-            .encoder_builder
-            .time_base(TimeBase::new(1, self.output.sample_rate()))
+        let mut encoder_builder = self.encoder_builder;
+
+        let mut sample_format = self.output.sample_format();
+        let mut sample_rate = self.output.sample_rate();
+        let mut channel_layout = self.output.channel_layout().to_owned();
+
+        let mut negotiated = Vec::new();
+
+        if self.negotiate {
+            if !encoder_builder.supports_sample_format(sample_format) {
+                if let Some(chosen) = encoder_builder.default_sample_format() {
+                    negotiated.push(NegotiatedParameter::SampleFormat {
+                        requested: sample_format,
+                        chosen,
+                    });
+
+                    sample_format = chosen;
+                }
+            }
+
+            let chosen = encoder_builder.closest_sample_rate(sample_rate);
+
+            if chosen != sample_rate {
+                negotiated.push(NegotiatedParameter::SampleRate {
+                    requested: sample_rate,
+                    chosen,
+                });
+
+                sample_rate = chosen;
+            }
+
+            let chosen = encoder_builder.closest_channel_layout(&channel_layout);
+
+            if chosen != channel_layout {
+                negotiated.push(NegotiatedParameter::ChannelLayout {
+                    requested: channel_layout,
+                    chosen: chosen.clone(),
+                });
+
+                channel_layout = chosen;
+            }
+
+            encoder_builder = encoder_builder
+                .sample_format(sample_format)
+                .sample_rate(sample_rate)
+                .channel_layout(channel_layout.clone());
+        }
+
+        let encoder = encoder_builder
+            .time_base(TimeBase::new(1, sample_rate))
             .build()?;
 
         let source_channel_layout = self.input.channel_layout();
-        let target_channel_layout = self.output.channel_layout();
 
         let resampler = AudioResampler::builder()
             .source_channel_layout(source_channel_layout.to_owned())
             .source_sample_format(self.input.sample_format())
             .source_sample_rate(self.input.sample_rate())
-            .target_channel_layout(target_channel_layout.to_owned())
-            .target_sample_format(self.output.sample_format())
-            .target_sample_rate(self.output.sample_rate())
+            .target_channel_layout(channel_layout)
+            .target_sample_format(sample_format)
+            .target_sample_rate(sample_rate)
             .target_frame_samples(encoder.samples_per_frame())
             .build()?;
 
@@ -93,6 +189,10 @@ impl AudioTranscoderBuilder {
             audio_encoder: encoder,
             audio_resampler: resampler,
 
+            hooks: self.hooks,
+
+            negotiated,
+
             ready: VecDeque::new(),
         };
 
@@ -113,6 +213,10 @@ pub struct AudioTranscoder {
     audio_encoder: AudioEncoder,
     audio_resampler: AudioResampler,
 
+    hooks: FrameHooks,
+
+    negotiated: Vec<NegotiatedParameter>,
+
     ready: VecDeque<Packet>,
 }
 
@@ -138,6 +242,12 @@ impl AudioTranscoder {
         self.audio_encoder.codec_parameters()
     }
 
+    /// Get output parameters that were changed during negotiation with the
+    /// encoder.
+    pub fn negotiated_parameters(&self) -> &[NegotiatedParameter] {
+        &self.negotiated
+    }
+
     /// Push a given packet to the transcoder.
     ///
     /// # Panics
@@ -205,9 +315,12 @@ impl AudioTranscoder {
         Ok(())
     }
 
-    /// Push a given frame to the internal resampler, take all resampled frames
-    /// and pass them to the push_to_encoder method.
+    /// Pass a given frame through the frame hooks and push it to the internal
+    /// resampler, take all resampled frames and pass them to the
+    /// push_to_encoder method.
     fn push_to_resampler(&mut self, frame: AudioFrame) -> Result<(), CodecError> {
+        let frame = self.hooks.audio_frame(frame)?;
+
         self.audio_resampler.try_push(frame)?;
 
         while let Some(frame) = self.audio_resampler.take()? {
@@ -270,3 +383,99 @@ impl AudioTranscoder {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::{AudioTranscoder, NegotiatedParameter};
+
+    use crate::{
+        codec::{
+            audio::{frame::get_sample_format, ChannelLayout},
+            AudioCodecParameters,
+        },
+        format::{demuxer::Demuxer, io::IO},
+        test_util::encode_audio,
+    };
+
+    /// Get mp2 codec parameters with a given sample format and sample rate.
+    fn mp2(sample_format: &str, sample_rate: u32) -> AudioCodecParameters {
+        AudioCodecParameters::builder("mp2")
+            .unwrap()
+            .sample_format(get_sample_format(sample_format))
+            .sample_rate(sample_rate)
+            .channel_layout(&ChannelLayout::from_channels(1).unwrap())
+            .build()
+    }
+
+    #[test]
+    fn test_negotiation() {
+        let data = encode_audio(10);
+
+        let mut demuxer = Demuxer::builder()
+            .build(IO::from_read_stream(Cursor::new(data)))
+            .unwrap()
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)
+            .unwrap();
+
+        let input = demuxer.streams()[0]
+            .codec_parameters()
+            .into_audio_codec_parameters()
+            .unwrap();
+
+        // the encoder supports neither fltp nor 8 kHz
+        let mut transcoder = AudioTranscoder::new(input.clone(), mp2("fltp", 8000)).unwrap();
+
+        let negotiated = transcoder.negotiated_parameters();
+
+        assert_eq!(negotiated.len(), 2);
+        assert!(matches!(
+            negotiated[0],
+            NegotiatedParameter::SampleFormat { .. }
+        ));
+        assert!(matches!(
+            negotiated[1],
+            NegotiatedParameter::SampleRate {
+                requested: 8000,
+                ..
+            }
+        ));
+
+        let output = transcoder.codec_parameters();
+
+        assert_eq!(output.sample_format(), get_sample_format("s16"));
+        assert_ne!(output.sample_rate(), 8000);
+
+        let mut packets = 0;
+
+        while let Some(packet) = demuxer.take().unwrap() {
+            transcoder.push(packet).unwrap();
+
+            while transcoder.take().unwrap().is_some() {
+                packets += 1;
+            }
+        }
+
+        transcoder.flush().unwrap();
+
+        while transcoder.take().unwrap().is_some() {
+            packets += 1;
+        }
+
+        assert!(packets > 0);
+
+        // supported parameters are kept
+        let transcoder = AudioTranscoder::new(input.clone(), mp2("s16", 48_000)).unwrap();
+
+        assert!(transcoder.negotiated_parameters().is_empty());
+
+        // the encoder cannot be opened without negotiation
+        assert!(AudioTranscoder::builder(input, mp2("fltp", 8000))
+            .unwrap()
+            .negotiate(false)
+            .build()
+            .is_err());
+    }
+}
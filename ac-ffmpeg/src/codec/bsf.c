@@ -38,6 +38,19 @@ int ffw_bsf_set_output_codec_parameters(AVBSFContext* context, const AVCodecPara
     return avcodec_parameters_copy(context->par_out, params);
 }
 
+AVCodecParameters* ffw_bsf_get_output_codec_parameters(const AVBSFContext* context) {
+    AVCodecParameters* res = avcodec_parameters_alloc();
+    if (!res) {
+        return NULL;
+    }
+
+    if (avcodec_parameters_copy(res, context->par_out) < 0) {
+        avcodec_parameters_free(&res);
+    }
+
+    return res;
+}
+
 int ffw_bsf_init(AVBSFContext* context, uint32_t itb_num, uint32_t itb_den, uint32_t otb_num, uint32_t otb_den) {
     context->time_base_in.num = itb_num;
     context->time_base_in.den = itb_den;
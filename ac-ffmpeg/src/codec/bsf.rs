@@ -12,6 +12,7 @@ extern "C" {
     fn ffw_bsf_new(name: *const c_char, context: *mut *mut c_void) -> c_int;
     fn ffw_bsf_set_input_codec_parameters(context: *mut c_void, params: *const c_void) -> c_int;
     fn ffw_bsf_set_output_codec_parameters(context: *mut c_void, params: *const c_void) -> c_int;
+    fn ffw_bsf_get_output_codec_parameters(context: *const c_void) -> *mut c_void;
     fn ffw_bsf_init(
         context: *mut c_void,
         itb_num: u32,
@@ -162,6 +163,21 @@ impl BitstreamFilter {
         BitstreamFilterBuilder::new(name)
     }
 
+    /// Get codec parameters of the filter output. They may differ from the
+    /// input codec parameters (e.g. the `h264_mp4toannexb` filter converts
+    /// the extradata into the Annex B format).
+    pub fn output_codec_parameters(&self) -> CodecParameters {
+        unsafe {
+            let ptr = ffw_bsf_get_output_codec_parameters(self.ptr);
+
+            if ptr.is_null() {
+                panic!("unable to allocate codec parameters");
+            }
+
+            CodecParameters::from_raw_ptr(ptr)
+        }
+    }
+
     /// Push a given packet to the filter.
     pub fn push(&mut self, mut packet: Packet) -> Result<(), Error> {
         let ret = unsafe { ffw_bsf_push(self.ptr, packet.as_mut_ptr()) };
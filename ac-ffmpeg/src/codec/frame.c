@@ -1,3 +1,6 @@
+#include <limits.h>
+#include <string.h>
+
 #include <libavutil/avutil.h>
 #include <libavutil/channel_layout.h>
 #include <libavutil/frame.h>
@@ -78,6 +81,100 @@ int ffw_channel_layout_compare(const AVChannelLayout* a, const AVChannelLayout*
     return av_channel_layout_compare(a, b);
 }
 
+int ffw_channel_layout_from_mask(AVChannelLayout** layout, uint64_t mask) {
+    AVChannelLayout* res;
+    int ret;
+
+    if (!(res = calloc(1, sizeof(AVChannelLayout)))) {
+        return AVERROR(ENOMEM);
+    }
+
+    if ((ret = av_channel_layout_from_mask(res, mask)) != 0) {
+        goto err;
+    }
+
+    *layout = res;
+
+    return 0;
+
+err:
+    free(res);
+
+    return ret;
+}
+
+int ffw_channel_layout_get_mask(const AVChannelLayout* layout, uint64_t* mask) {
+    if (layout->order != AV_CHANNEL_ORDER_NATIVE) {
+        return 0;
+    }
+
+    *mask = layout->u.mask;
+
+    return 1;
+}
+
+int ffw_channel_layout_get_order(const AVChannelLayout* layout) {
+    switch (layout->order) {
+        case AV_CHANNEL_ORDER_NATIVE: return 1;
+        case AV_CHANNEL_ORDER_CUSTOM: return 2;
+        case AV_CHANNEL_ORDER_AMBISONIC: return 3;
+        default: return 0;
+    }
+}
+
+int ffw_channel_layout_get_ambisonic_order(const AVChannelLayout* layout) {
+    int channels;
+    int order;
+
+    if (layout->order != AV_CHANNEL_ORDER_AMBISONIC) {
+        return -1;
+    }
+
+    // the mask contains non-diegetic channels
+    channels = layout->nb_channels - av_popcount64(layout->u.mask);
+
+    for (order = 0; (order + 1) * (order + 1) < channels; order++) {
+    }
+
+    if ((order + 1) * (order + 1) != channels) {
+        return -1;
+    }
+
+    return order;
+}
+
+int ffw_channel_layout_ambisonic(AVChannelLayout** layout, uint32_t order, uint64_t non_diegetic) {
+    AVChannelLayout* res;
+    int64_t channels;
+
+    if (order > 15) {
+        return AVERROR(EINVAL);
+    }
+
+    channels = (int64_t)(order + 1) * (order + 1) + av_popcount64(non_diegetic);
+
+    if (!(res = calloc(1, sizeof(AVChannelLayout)))) {
+        return AVERROR(ENOMEM);
+    }
+
+    res->order = AV_CHANNEL_ORDER_AMBISONIC;
+    res->nb_channels = (int)channels;
+    res->u.mask = non_diegetic;
+
+    if (!av_channel_layout_check(res)) {
+        free(res);
+        return AVERROR(EINVAL);
+    }
+
+    *layout = res;
+
+    return 0;
+}
+
+int ffw_channel_layout_describe(const AVChannelLayout* layout, char* buf, size_t size) {
+    return av_channel_layout_describe(layout, buf, size);
+}
+
 void ffw_channel_layout_free(AVChannelLayout* layout) {
     if (!layout) {
         return;
@@ -102,6 +199,10 @@ int ffw_get_channel_layout_channels(uint64_t layout) {
     return av_get_channel_layout_nb_channels(layout);
 }
 
+void ffw_get_channel_layout_string(char* buf, int size, uint64_t layout) {
+    av_get_channel_layout_string(buf, size, 0, layout);
+}
+
 #endif // FFW_FEATURE_CHANNEL_LAYOUT_V2
 
 int ffw_get_sample_format_by_name(const char* name) {
@@ -120,6 +221,18 @@ int ffw_sample_format_is_none(int format) {
     return format == AV_SAMPLE_FMT_NONE;
 }
 
+int ffw_sample_format_get_bytes_per_sample(int format) {
+    return av_get_bytes_per_sample(format);
+}
+
+int ffw_sample_format_get_packed(int format) {
+    return av_get_packed_sample_fmt(format);
+}
+
+int ffw_sample_format_get_planar(int format) {
+    return av_get_planar_sample_fmt(format);
+}
+
 int ffw_get_pixel_format_by_name(const char* name) {
     return av_get_pix_fmt(name);
 }
@@ -132,7 +245,103 @@ int ffw_pixel_format_is_none(int format) {
     return format == AV_PIX_FMT_NONE;
 }
 
+int ffw_pixel_format_get_planes(int format) {
+    int res = av_pix_fmt_count_planes(format);
+
+    return res < 0 ? 0 : res;
+}
+
+int ffw_pixel_format_get_components(int format) {
+    const AVPixFmtDescriptor* desc = av_pix_fmt_desc_get(format);
+
+    return desc ? desc->nb_components : 0;
+}
+
+int ffw_pixel_format_get_log2_chroma_w(int format) {
+    const AVPixFmtDescriptor* desc = av_pix_fmt_desc_get(format);
+
+    return desc ? desc->log2_chroma_w : 0;
+}
+
+int ffw_pixel_format_get_log2_chroma_h(int format) {
+    const AVPixFmtDescriptor* desc = av_pix_fmt_desc_get(format);
+
+    return desc ? desc->log2_chroma_h : 0;
+}
+
+int ffw_pixel_format_get_bit_depth(int format) {
+    const AVPixFmtDescriptor* desc = av_pix_fmt_desc_get(format);
+    int res = 0;
+    int i;
+
+    if (!desc) {
+        return 0;
+    }
+
+    for (i = 0; i < desc->nb_components; i++) {
+        if (desc->comp[i].depth > res) {
+            res = desc->comp[i].depth;
+        }
+    }
+
+    return res;
+}
+
+int ffw_pixel_format_get_bits_per_pixel(int format) {
+    const AVPixFmtDescriptor* desc = av_pix_fmt_desc_get(format);
+
+    return desc ? av_get_bits_per_pixel(desc) : 0;
+}
+
+int ffw_pixel_format_has_flag(int format, int flag) {
+    const AVPixFmtDescriptor* desc = av_pix_fmt_desc_get(format);
+    uint64_t mask;
+
+    if (!desc) {
+        return 0;
+    }
+
+    switch (flag) {
+        case 0: mask = AV_PIX_FMT_FLAG_HWACCEL; break;
+        case 1: mask = AV_PIX_FMT_FLAG_RGB; break;
+        case 2: mask = AV_PIX_FMT_FLAG_PLANAR; break;
+        case 3: mask = AV_PIX_FMT_FLAG_ALPHA; break;
+        case 4: mask = AV_PIX_FMT_FLAG_FLOAT; break;
+        case 5: mask = AV_PIX_FMT_FLAG_PAL; break;
+        case 6: mask = AV_PIX_FMT_FLAG_BE; break;
+        case 7: mask = AV_PIX_FMT_FLAG_BITSTREAM; break;
+        default: return 0;
+    }
+
+    return (desc->flags & mask) != 0;
+}
+
+int ffw_pixel_format_get_loss(int dst, int src, int has_alpha) {
+    return av_get_pix_fmt_loss(dst, src, has_alpha);
+}
+
+int ffw_pixel_format_find_best(const int* formats, size_t count, int src, int has_alpha, int* loss) {
+    int res = AV_PIX_FMT_NONE;
+    int tmp;
+    size_t i;
+
+    *loss = 0;
+
+    // this is what avcodec_find_best_pix_fmt_of_list() does
+    for (i = 0; i < count; i++) {
+        tmp = 0;
+        res = av_find_best_pix_fmt_of_2(res, formats[i], src, has_alpha, &tmp);
+    }
+
+    if (res != AV_PIX_FMT_NONE) {
+        *loss = av_get_pix_fmt_loss(res, src, has_alpha);
+    }
+
+    return res;
+}
+
 AVFrame* ffw_frame_new_black(int, int, int);
+int ffw_frame_new_external(int, int, int, uint8_t* const*, const size_t*, size_t, void (*)(void*, uint8_t*), void*, AVFrame**);
 void ffw_frame_free(AVFrame*);
 
 #ifdef FFW_FEATURE_CHANNEL_LAYOUT_V2
@@ -237,6 +446,116 @@ err:
     return NULL;
 }
 
+static void ffw_frame_release_external_plane(void* opaque, uint8_t* data) {
+    AVBufferRef* owner = opaque;
+
+    av_buffer_unref(&owner);
+}
+
+int ffw_frame_new_external(
+    int pixel_format,
+    int width,
+    int height,
+    uint8_t* const* data,
+    const size_t* linesize,
+    size_t planes,
+    void (*free_callback)(void*, uint8_t*),
+    void* opaque,
+    AVFrame** frame) {
+    const AVPixFmtDescriptor* desc;
+    AVBufferRef* owner;
+    AVFrame* res;
+    size_t sizes[AV_NUM_DATA_POINTERS];
+    size_t lines;
+    size_t i;
+    int ret;
+
+    if (width <= 0 || height <= 0 || planes > AV_NUM_DATA_POINTERS) {
+        ret = AVERROR(EINVAL);
+        goto err;
+    }
+
+    if (!(desc = av_pix_fmt_desc_get(pixel_format))) {
+        ret = AVERROR(EINVAL);
+        goto err;
+    } else if ((int)planes != av_pix_fmt_count_planes(pixel_format)) {
+        ret = AVERROR(EINVAL);
+        goto err;
+    }
+
+    for (i = 0; i < planes; i++) {
+        // chroma planes may be subsampled vertically
+        lines = (i == 1 || i == 2) ? AV_CEIL_RSHIFT(height, desc->log2_chroma_h) : height;
+
+        if (!data[i] || linesize[i] > INT_MAX / lines) {
+            ret = AVERROR(EINVAL);
+            goto err;
+        }
+
+        sizes[i] = linesize[i] * lines;
+    }
+
+    if (!(res = av_frame_alloc())) {
+        ret = AVERROR(ENOMEM);
+        goto err;
+    }
+
+    res->format = pixel_format;
+    res->width = width;
+    res->height = height;
+
+    for (i = 0; i < planes; i++) {
+        res->data[i] = data[i];
+        res->linesize[i] = linesize[i];
+    }
+
+    // the first plane owns the free callback
+    res->buf[0] = av_buffer_create(
+        data[0],
+        sizes[0],
+        free_callback,
+        opaque,
+        AV_BUFFER_FLAG_READONLY);
+
+    if (!res->buf[0]) {
+        av_frame_free(&res);
+        ret = AVERROR(ENOMEM);
+        goto err;
+    }
+
+    // every other plane holds a reference to the first one, so the free
+    // callback is called exactly once when the last plane is released;
+    // from now on, the callback is called by av_frame_free() on error
+    for (i = 1; i < planes; i++) {
+        if (!(owner = av_buffer_ref(res->buf[0]))) {
+            av_frame_free(&res);
+            return AVERROR(ENOMEM);
+        }
+
+        res->buf[i] = av_buffer_create(
+            data[i],
+            sizes[i],
+            ffw_frame_release_external_plane,
+            owner,
+            AV_BUFFER_FLAG_READONLY);
+
+        if (!res->buf[i]) {
+            av_buffer_unref(&owner);
+            av_frame_free(&res);
+            return AVERROR(ENOMEM);
+        }
+    }
+
+    *frame = res;
+
+    return 0;
+
+err:
+    free_callback(opaque, NULL);
+
+    return ret;
+}
+
 int ffw_frame_get_format(const AVFrame* frame) {
     return frame->format;
 }
@@ -249,6 +568,34 @@ int ffw_frame_get_height(const AVFrame* frame) {
     return frame->height;
 }
 
+void ffw_frame_get_sample_aspect_ratio(const AVFrame* frame, int* num, int* den) {
+    *num = frame->sample_aspect_ratio.num;
+    *den = frame->sample_aspect_ratio.den;
+}
+
+void ffw_frame_set_sample_aspect_ratio(AVFrame* frame, int num, int den) {
+    frame->sample_aspect_ratio.num = num;
+    frame->sample_aspect_ratio.den = den;
+}
+
+void ffw_frame_get_crop(const AVFrame* frame, size_t* top, size_t* bottom, size_t* left, size_t* right) {
+    *top = frame->crop_top;
+    *bottom = frame->crop_bottom;
+    *left = frame->crop_left;
+    *right = frame->crop_right;
+}
+
+void ffw_frame_set_crop(AVFrame* frame, size_t top, size_t bottom, size_t left, size_t right) {
+    frame->crop_top = top;
+    frame->crop_bottom = bottom;
+    frame->crop_left = left;
+    frame->crop_right = right;
+}
+
+int ffw_frame_apply_cropping(AVFrame* frame) {
+    return av_frame_apply_cropping(frame, AV_FRAME_CROP_UNALIGNED);
+}
+
 int ffw_frame_get_sample_rate(const AVFrame* frame) {
     return frame->sample_rate;
 }
@@ -324,6 +671,73 @@ int ffw_frame_make_writable(AVFrame* frame) {
     return av_frame_make_writable(frame);
 }
 
+int ffw_frame_clone_deep(const AVFrame* frame, AVFrame** res) {
+    AVFrame* tmp;
+    int ret;
+
+    if (!(tmp = av_frame_alloc())) {
+        return AVERROR(ENOMEM);
+    }
+
+    tmp->format = frame->format;
+    tmp->width = frame->width;
+    tmp->height = frame->height;
+
+    if ((ret = av_frame_get_buffer(tmp, 0)) < 0) {
+        goto err;
+    } else if ((ret = av_frame_copy(tmp, frame)) < 0) {
+        goto err;
+    } else if ((ret = av_frame_copy_props(tmp, frame)) < 0) {
+        goto err;
+    }
+
+    *res = tmp;
+
+    return 0;
+
+err:
+    av_frame_free(&tmp);
+
+    return ret;
+}
+
+int ffw_frame_is_corrupt(const AVFrame* frame) {
+    return (frame->flags & AV_FRAME_FLAG_CORRUPT) || frame->decode_error_flags;
+}
+
+int ffw_frame_is_interlaced(const AVFrame* frame) {
+#ifdef AV_FRAME_FLAG_INTERLACED
+    return (frame->flags & AV_FRAME_FLAG_INTERLACED) != 0;
+#else
+    return frame->interlaced_frame != 0;
+#endif
+}
+
+int ffw_frame_is_top_field_first(const AVFrame* frame) {
+#ifdef AV_FRAME_FLAG_TOP_FIELD_FIRST
+    return (frame->flags & AV_FRAME_FLAG_TOP_FIELD_FIRST) != 0;
+#else
+    return frame->top_field_first != 0;
+#endif
+}
+
+void ffw_frame_set_interlaced(AVFrame* frame, int interlaced, int top_field_first) {
+#ifdef AV_FRAME_FLAG_INTERLACED
+    frame->flags &= ~(AV_FRAME_FLAG_INTERLACED | AV_FRAME_FLAG_TOP_FIELD_FIRST);
+
+    if (interlaced) {
+        frame->flags |= AV_FRAME_FLAG_INTERLACED;
+    }
+
+    if (top_field_first) {
+        frame->flags |= AV_FRAME_FLAG_TOP_FIELD_FIRST;
+    }
+#else
+    frame->interlaced_frame = interlaced != 0;
+    frame->top_field_first = top_field_first != 0;
+#endif
+}
+
 int ffw_frame_get_picture_type(const AVFrame* frame) {
     switch (frame->pict_type) {
         case AV_PICTURE_TYPE_I: return 1;
@@ -353,3 +767,111 @@ void ffw_frame_set_picture_type(AVFrame* frame, int picture_type) {
 
     frame->pict_type = type;
 }
+
+int ffw_frame_side_data_type_to_raw(int type) {
+    switch (type) {
+        case 0: return AV_FRAME_DATA_A53_CC;
+        case 1: return AV_FRAME_DATA_DISPLAYMATRIX;
+        case 2: return AV_FRAME_DATA_MASTERING_DISPLAY_METADATA;
+        case 3: return AV_FRAME_DATA_CONTENT_LIGHT_LEVEL;
+        case 4: return AV_FRAME_DATA_ICC_PROFILE;
+#if LIBAVUTIL_VERSION_INT >= AV_VERSION_INT(56, 31, 100)
+        case 5: return AV_FRAME_DATA_DYNAMIC_HDR_PLUS;
+        case 6: return AV_FRAME_DATA_S12M_TIMECODE;
+#endif
+#if LIBAVUTIL_VERSION_INT >= AV_VERSION_INT(56, 70, 100)
+        case 7: return AV_FRAME_DATA_SEI_UNREGISTERED;
+        case 8: return AV_FRAME_DATA_FILM_GRAIN_PARAMS;
+#endif
+        default: return -1;
+    }
+}
+
+int ffw_frame_side_data_type_from_raw(int raw) {
+    int type;
+
+    for (type = 0; type < 9; type++) {
+        if (ffw_frame_side_data_type_to_raw(type) == raw) {
+            return type;
+        }
+    }
+
+    return -1;
+}
+
+const char* ffw_frame_side_data_type_name(int raw) {
+    return av_frame_side_data_name(raw);
+}
+
+size_t ffw_frame_get_nb_side_data(const AVFrame* frame) {
+    return frame->nb_side_data;
+}
+
+const AVFrameSideData* ffw_frame_get_side_data(const AVFrame* frame, size_t index) {
+    return frame->side_data[index];
+}
+
+int ffw_frame_side_data_get_type(const AVFrameSideData* side_data) {
+    return side_data->type;
+}
+
+const uint8_t* ffw_frame_side_data_get_data(const AVFrameSideData* side_data) {
+    return side_data->data;
+}
+
+size_t ffw_frame_side_data_get_size(const AVFrameSideData* side_data) {
+    return side_data->size;
+}
+
+int ffw_frame_add_side_data(AVFrame* frame, int raw, const uint8_t* data, size_t size) {
+    AVFrameSideData* side_data;
+
+    side_data = av_frame_new_side_data(frame, raw, size);
+
+    if (!side_data) {
+        return AVERROR(ENOMEM);
+    }
+
+    memcpy(side_data->data, data, size);
+
+    return 0;
+}
+
+void ffw_frame_remove_side_data(AVFrame* frame, int raw) {
+    av_frame_remove_side_data(frame, raw);
+}
+
+int ffw_frame_copy_side_data(AVFrame* dst, const AVFrame* src, int raw) {
+    AVFrameSideData* side_data;
+    AVBufferRef* buffer;
+    int i;
+
+    for (i = 0; i < src->nb_side_data; i++) {
+        side_data = src->side_data[i];
+
+        if (raw >= 0 && side_data->type != raw) {
+            continue;
+        }
+
+        av_frame_remove_side_data(dst, side_data->type);
+    }
+
+    for (i = 0; i < src->nb_side_data; i++) {
+        side_data = src->side_data[i];
+
+        if (raw >= 0 && side_data->type != raw) {
+            continue;
+        }
+
+        if (!(buffer = av_buffer_ref(side_data->buf))) {
+            return AVERROR(ENOMEM);
+        }
+
+        if (!av_frame_new_side_data_from_buf(dst, side_data->type, buffer)) {
+            av_buffer_unref(&buffer);
+            return AVERROR(ENOMEM);
+        }
+    }
+
+    return 0;
+}
@@ -0,0 +1,105 @@
+//! Frame hooks.
+//!
+//! Frame hooks allow inspecting or transforming decoded frames inside the
+//! high-level transcode pipelines (e.g. to embed or detect a watermark)
+//! without reimplementing the pipelines.
+//!
+//! # Guarantees
+//! * Every decoded frame reaching the encoding stage is passed to the
+//!   corresponding hook exactly once.
+//! * Frames are passed in the order they were produced by the decoder
+//!   (i.e. in presentation order) and before they are converted into the
+//!   encoder format.
+//! * Hooks are called synchronously from the thread driving the pipeline and
+//!   never concurrently. A hook must be `Send` only because the pipeline
+//!   itself may be moved to another thread.
+//! * If a hook returns an error, the pipeline stops and returns the error.
+//!
+//! A hook should not change the frame format (i.e. pixel format,
+//! resolution, sample format, sample rate or channel layout). The encoder
+//! is configured using the first frame returned by the hook and subsequent
+//! frames are expected to match it. A detection hook can simply inspect the
+//! frame and return it unchanged.
+
+use crate::{
+    codec::{audio::AudioFrame, video::VideoFrame},
+    Error,
+};
+
+/// Video frame hook.
+type VideoFrameHook = Box<dyn FnMut(VideoFrame) -> Result<VideoFrame, Error> + Send>;
+
+/// Audio frame hook.
+type AudioFrameHook = Box<dyn FnMut(AudioFrame) -> Result<AudioFrame, Error> + Send>;
+
+/// Set of frame hooks applied to decoded frames.
+///
+/// # Example
+/// ```text
+/// let mut hooks = FrameHooks::new().with_video_hook(|frame| {
+///     let mut frame = frame.into_mut();
+///
+///     embed_watermark(&mut frame);
+///
+///     Ok(frame.freeze())
+/// });
+/// ```
+#[derive(Default)]
+pub struct FrameHooks {
+    video: Option<VideoFrameHook>,
+    audio: Option<AudioFrameHook>,
+}
+
+impl FrameHooks {
+    /// Create an empty set of hooks. Frames will be passed through as they
+    /// are.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set a hook that will be called for every decoded video frame.
+    pub fn with_video_hook<F>(mut self, hook: F) -> Self
+    where
+        F: FnMut(VideoFrame) -> Result<VideoFrame, Error> + Send + 'static,
+    {
+        self.video = Some(Box::new(hook));
+        self
+    }
+
+    /// Set a hook that will be called for every decoded audio frame.
+    pub fn with_audio_hook<F>(mut self, hook: F) -> Self
+    where
+        F: FnMut(AudioFrame) -> Result<AudioFrame, Error> + Send + 'static,
+    {
+        self.audio = Some(Box::new(hook));
+        self
+    }
+
+    /// Check if there is a video frame hook.
+    pub fn has_video_hook(&self) -> bool {
+        self.video.is_some()
+    }
+
+    /// Check if there is an audio frame hook.
+    pub fn has_audio_hook(&self) -> bool {
+        self.audio.is_some()
+    }
+
+    /// Pass a given video frame to the video frame hook (if any).
+    pub fn video_frame(&mut self, frame: VideoFrame) -> Result<VideoFrame, Error> {
+        if let Some(hook) = self.video.as_mut() {
+            hook(frame)
+        } else {
+            Ok(frame)
+        }
+    }
+
+    /// Pass a given audio frame to the audio frame hook (if any).
+    pub fn audio_frame(&mut self, frame: AudioFrame) -> Result<AudioFrame, Error> {
+        if let Some(hook) = self.audio.as_mut() {
+            hook(frame)
+        } else {
+            Ok(frame)
+        }
+    }
+}
@@ -0,0 +1,419 @@
+//! Still image encoding and decoding.
+//!
+//! The helpers encode a single video frame into PNG, JPEG or WebP bytes and
+//! decode an image buffer into a video frame. The one-shot encoder/decoder
+//! lifecycle (including the pixel format conversion and flushing) is
+//! handled internally.
+//!
+//! # Example
+//! ```text
+//! let jpeg = ImageEncoder::new(ImageFormat::Jpeg)
+//!     .quality(85)
+//!     .encode(&frame)?;
+//!
+//! let png = image::encode(&frame, ImageFormat::Png)?;
+//!
+//! let frame = image::decode(&png)?;
+//! ```
+//!
+//! If the `image` feature is enabled, video frames can be also converted
+//! from/into `RgbImage`, `RgbaImage` and `DynamicImage` of the `image`
+//! crate using the `From`/`TryFrom` traits. Frames of any pixel format can
+//! be converted, the conversion into RGB(A) is done automatically.
+
+#[cfg(feature = "image")]
+use std::convert::TryFrom;
+
+#[cfg(any(feature = "image", feature = "ndarray"))]
+use crate::codec::video::VideoFrameMut;
+
+use crate::{
+    codec::{
+        video::{
+            frame::get_pixel_format, PixelFormat, VideoDecoder, VideoEncoder, VideoFrame,
+            VideoFrameScaler,
+        },
+        Decoder, Encoder,
+    },
+    packet::PacketMut,
+    time::{TimeBase, Timestamp},
+    Error,
+};
+
+/// Value of the `FF_QP2LAMBDA` constant.
+const QP2LAMBDA: u32 = 118;
+
+/// Image format.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ImageFormat {
+    /// PNG (lossless).
+    Png,
+    /// JPEG (lossy).
+    Jpeg,
+    /// WebP (lossy). Encoding requires FFmpeg built with libwebp.
+    WebP,
+}
+
+impl ImageFormat {
+    /// Detect the image format from the beginning of given image data.
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(Self::Png)
+        } else if data.starts_with(b"\xff\xd8\xff") {
+            Some(Self::Jpeg)
+        } else if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+            Some(Self::WebP)
+        } else {
+            None
+        }
+    }
+
+    /// Get name of the encoder used for the format.
+    fn encoder_name(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "mjpeg",
+            Self::WebP => "libwebp",
+        }
+    }
+
+    /// Get name of the decoder used for the format.
+    fn decoder_name(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "mjpeg",
+            Self::WebP => "webp",
+        }
+    }
+
+    /// Get the pixel format used for encoding an image of a given pixel
+    /// format.
+    fn pixel_format(self, source: PixelFormat) -> PixelFormat {
+        let alpha = source.has_alpha();
+
+        let name = match self {
+            Self::Png if alpha => "rgba",
+            Self::Png => "rgb24",
+            Self::Jpeg => "yuvj420p",
+            Self::WebP if alpha => "yuva420p",
+            Self::WebP => "yuv420p",
+        };
+
+        get_pixel_format(name)
+    }
+}
+
+/// Image encoder. It encodes single video frames into images of a given
+/// format. Frames of any pixel format are accepted, they are converted into
+/// a pixel format supported by the image format (keeping the alpha channel
+/// if the image format supports it).
+#[derive(Debug, Clone)]
+pub struct ImageEncoder {
+    format: ImageFormat,
+    quality: u32,
+}
+
+impl ImageEncoder {
+    /// Create a new encoder for a given image format.
+    pub fn new(format: ImageFormat) -> Self {
+        Self {
+            format,
+            quality: 90,
+        }
+    }
+
+    /// Set quality of lossy image formats (0 - 100). The option is ignored
+    /// for PNG. The default is 90.
+    pub fn quality(mut self, quality: u32) -> Self {
+        self.quality = quality.min(100);
+        self
+    }
+
+    /// Encode a given frame.
+    pub fn encode(&self, frame: &VideoFrame) -> Result<Vec<u8>, Error> {
+        let pixel_format = self.format.pixel_format(frame.pixel_format());
+
+        let frame = convert(frame, pixel_format)?;
+
+        let time_base = TimeBase::new(1, 1);
+
+        let frame = frame
+            .with_time_base(time_base)
+            .with_pts(Timestamp::new(0, time_base));
+
+        let mut builder = VideoEncoder::builder(self.format.encoder_name())?
+            .pixel_format(pixel_format)
+            .width(frame.width())
+            .height(frame.height())
+            .time_base(time_base);
+
+        if let Some(sar) = frame.sample_aspect_ratio() {
+            builder = builder.sample_aspect_ratio(sar);
+        }
+
+        match self.format {
+            ImageFormat::Png => (),
+            ImageFormat::Jpeg => {
+                // map the quality to the JPEG quantizer scale (2 - 31)
+                let q = 2 + (100 - self.quality) * 29 / 100;
+
+                builder = builder
+                    .set_option("flags", "+qscale")
+                    .set_option("global_quality", q * QP2LAMBDA);
+            }
+            ImageFormat::WebP => {
+                builder = builder.set_option("quality", self.quality);
+            }
+        }
+
+        let mut encoder = builder.build()?;
+
+        encoder.push(frame)?;
+        encoder.flush()?;
+
+        let mut res = Vec::new();
+
+        while let Some(packet) = encoder.take()? {
+            res.extend_from_slice(packet.data());
+        }
+
+        if res.is_empty() {
+            return Err(Error::new("no image produced by the encoder"));
+        }
+
+        Ok(res)
+    }
+}
+
+/// Encode a given frame into an image of a given format using the default
+/// settings.
+pub fn encode(frame: &VideoFrame, format: ImageFormat) -> Result<Vec<u8>, Error> {
+    ImageEncoder::new(format).encode(frame)
+}
+
+/// Decode a given image. The image format is detected from the data. The
+/// frame is returned in the pixel format produced by the decoder.
+pub fn decode(data: &[u8]) -> Result<VideoFrame, Error> {
+    let format = ImageFormat::detect(data).ok_or_else(|| Error::new("unknown image format"))?;
+
+    decode_as(data, format)
+}
+
+/// Decode a given image of a given format.
+pub fn decode_as(data: &[u8], format: ImageFormat) -> Result<VideoFrame, Error> {
+    let mut decoder = VideoDecoder::new(format.decoder_name())?;
+
+    let packet = PacketMut::from(data).with_key_flag(true).freeze();
+
+    decoder.push(packet)?;
+    decoder.flush()?;
+
+    decoder
+        .take()?
+        .ok_or_else(|| Error::new("no frame produced by the decoder"))
+}
+
+/// Convert a given frame into a given pixel format (if needed).
+pub(crate) fn convert(frame: &VideoFrame, pixel_format: PixelFormat) -> Result<VideoFrame, Error> {
+    if frame.pixel_format() == pixel_format {
+        return Ok(frame.clone());
+    }
+
+    VideoFrameScaler::builder()
+        .source_pixel_format(frame.pixel_format())
+        .source_width(frame.width())
+        .source_height(frame.height())
+        .target_pixel_format(pixel_format)
+        .target_width(frame.width())
+        .target_height(frame.height())
+        .build()?
+        .scale(frame)
+}
+
+/// Get packed pixels (without any padding) of a given frame with a packed
+/// pixel format of a given pixel size.
+#[cfg(any(feature = "image", feature = "ndarray"))]
+pub(crate) fn packed_pixels(frame: &VideoFrame, pixel_size: usize) -> Vec<u8> {
+    let width = pixel_size * frame.width();
+
+    let planes = frame.planes();
+
+    let mut res = Vec::with_capacity(width * frame.height());
+
+    for line in planes[0].lines().take(frame.height()) {
+        res.extend_from_slice(&line[..width]);
+    }
+
+    res
+}
+
+/// Create a frame of a given packed pixel format with a given pixel size
+/// from given packed pixels (without any padding).
+#[cfg(any(feature = "image", feature = "ndarray"))]
+pub(crate) fn from_packed_pixels(
+    pixel_format: &str,
+    pixel_size: usize,
+    width: usize,
+    height: usize,
+    pixels: &[u8],
+) -> VideoFrame {
+    let mut frame = VideoFrameMut::black(get_pixel_format(pixel_format), width, height);
+
+    let line_size = pixel_size * width;
+
+    if line_size > 0 {
+        let mut planes = frame.planes_mut();
+
+        for (dst, src) in planes[0].lines_mut().zip(pixels.chunks_exact(line_size)) {
+            dst[..line_size].copy_from_slice(src);
+        }
+    }
+
+    frame.freeze()
+}
+
+#[cfg(feature = "image")]
+impl From<&::image::RgbImage> for VideoFrame {
+    fn from(image: &::image::RgbImage) -> Self {
+        from_packed_pixels(
+            "rgb24",
+            3,
+            image.width() as usize,
+            image.height() as usize,
+            image.as_raw(),
+        )
+    }
+}
+
+#[cfg(feature = "image")]
+impl From<&::image::RgbaImage> for VideoFrame {
+    fn from(image: &::image::RgbaImage) -> Self {
+        from_packed_pixels(
+            "rgba",
+            4,
+            image.width() as usize,
+            image.height() as usize,
+            image.as_raw(),
+        )
+    }
+}
+
+#[cfg(feature = "image")]
+impl From<&::image::DynamicImage> for VideoFrame {
+    fn from(image: &::image::DynamicImage) -> Self {
+        match image {
+            ::image::DynamicImage::ImageRgb8(image) => image.into(),
+            ::image::DynamicImage::ImageRgba8(image) => image.into(),
+            image if image.color().has_alpha() => (&image.to_rgba8()).into(),
+            image => (&image.to_rgb8()).into(),
+        }
+    }
+}
+
+#[cfg(feature = "image")]
+impl TryFrom<&VideoFrame> for ::image::RgbImage {
+    type Error = Error;
+
+    fn try_from(frame: &VideoFrame) -> Result<Self, Self::Error> {
+        let frame = convert(frame, get_pixel_format("rgb24"))?;
+
+        let pixels = packed_pixels(&frame, 3);
+
+        let res = Self::from_raw(frame.width() as u32, frame.height() as u32, pixels).unwrap();
+
+        Ok(res)
+    }
+}
+
+#[cfg(feature = "image")]
+impl TryFrom<&VideoFrame> for ::image::RgbaImage {
+    type Error = Error;
+
+    fn try_from(frame: &VideoFrame) -> Result<Self, Self::Error> {
+        let frame = convert(frame, get_pixel_format("rgba"))?;
+
+        let pixels = packed_pixels(&frame, 4);
+
+        let res = Self::from_raw(frame.width() as u32, frame.height() as u32, pixels).unwrap();
+
+        Ok(res)
+    }
+}
+
+#[cfg(feature = "image")]
+impl TryFrom<&VideoFrame> for ::image::DynamicImage {
+    type Error = Error;
+
+    /// The image will be RGBA if the frame has an alpha channel and RGB
+    /// otherwise.
+    fn try_from(frame: &VideoFrame) -> Result<Self, Self::Error> {
+        if frame.pixel_format().has_alpha() {
+            ::image::RgbaImage::try_from(frame).map(Self::from)
+        } else {
+            ::image::RgbImage::try_from(frame).map(Self::from)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ImageFormat;
+
+    #[test]
+    fn test_format_detection() {
+        assert_eq!(
+            ImageFormat::detect(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+            Some(ImageFormat::Png)
+        );
+        assert_eq!(
+            ImageFormat::detect(b"\xff\xd8\xff\xe0\0\x10JFIF"),
+            Some(ImageFormat::Jpeg)
+        );
+        assert_eq!(
+            ImageFormat::detect(b"RIFF\x24\0\0\0WEBPVP8 "),
+            Some(ImageFormat::WebP)
+        );
+        assert_eq!(ImageFormat::detect(b"GIF89a"), None);
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn test_image_conversion() {
+        use std::convert::TryFrom;
+
+        use ::image::{DynamicImage, RgbImage, RgbaImage};
+
+        use crate::codec::video::{frame::get_pixel_format, VideoFrame, VideoFrameMut};
+
+        let pixels = (0..18).map(|v| v * 10).collect::<Vec<u8>>();
+
+        let image = RgbImage::from_raw(3, 2, pixels.clone()).unwrap();
+
+        let frame = VideoFrame::from(&image);
+
+        assert!(frame.pixel_format() == get_pixel_format("rgb24"));
+        assert_eq!((frame.width(), frame.height()), (3, 2));
+
+        let image = RgbImage::try_from(&frame).unwrap();
+
+        assert_eq!(image.as_raw(), &pixels);
+
+        let pixels = (0..24).map(|v| v * 10).collect::<Vec<u8>>();
+
+        let image = RgbaImage::from_raw(3, 2, pixels.clone()).unwrap();
+
+        let frame = VideoFrame::from(&image);
+
+        match DynamicImage::try_from(&frame).unwrap() {
+            DynamicImage::ImageRgba8(image) => assert_eq!(image.as_raw(), &pixels),
+            _ => panic!("unexpected image type"),
+        }
+
+        // frames without alpha are converted into RGB images
+        let frame = VideoFrameMut::black(get_pixel_format("yuv420p"), 4, 2).freeze();
+
+        let image = DynamicImage::try_from(&frame).unwrap();
+
+        assert!(matches!(image, DynamicImage::ImageRgb8(_)));
+    }
+}
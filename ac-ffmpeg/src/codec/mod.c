@@ -1,5 +1,8 @@
+#include <stdlib.h>
+
 #include <libavcodec/avcodec.h>
 #include <libavutil/channel_layout.h>
+#include <libavutil/opt.h>
 
 static const AVCodec* ffw_find_codec(const char* name, int type) {
     const AVCodec* codec;
@@ -20,6 +23,17 @@ static const AVCodec* ffw_find_codec(const char* name, int type) {
     return NULL;
 }
 
+static enum AVFieldOrder ffw_field_order_from_raw(int field_order) {
+    switch (field_order) {
+        case 1: return AV_FIELD_PROGRESSIVE;
+        case 2: return AV_FIELD_TT;
+        case 3: return AV_FIELD_BB;
+        case 4: return AV_FIELD_TB;
+        case 5: return AV_FIELD_BT;
+        default: return AV_FIELD_UNKNOWN;
+    }
+}
+
 AVCodecParameters* ffw_codec_parameters_new(const char* codec_name, int codec_type) {
     AVCodecParameters* res;
     const AVCodec* codec;
@@ -52,6 +66,28 @@ AVCodecParameters* ffw_subtitle_codec_parameters_new(const char* codec_name) {
     return ffw_codec_parameters_new(codec_name, AVMEDIA_TYPE_SUBTITLE);
 }
 
+AVCodecParameters* ffw_data_codec_parameters_new(const char* codec_name) {
+    AVCodecParameters* res;
+    const AVCodecDescriptor* descriptor;
+
+    // there are usually no encoders/decoders for data codecs, so we need to
+    // use codec descriptors instead
+    descriptor = avcodec_descriptor_get_by_name(codec_name);
+    if (descriptor == NULL || descriptor->type != AVMEDIA_TYPE_DATA) {
+        return NULL;
+    }
+
+    res = avcodec_parameters_alloc();
+    if (res == NULL) {
+        return NULL;
+    }
+
+    res->codec_type = AVMEDIA_TYPE_DATA;
+    res->codec_id = descriptor->id;
+
+    return res;
+}
+
 AVCodecParameters* ffw_codec_parameters_clone(const AVCodecParameters* src) {
     AVCodecParameters* res = avcodec_parameters_alloc();
     if (res == NULL) {
@@ -86,6 +122,14 @@ int ffw_codec_parameters_is_subtitle_codec(const AVCodecParameters* params) {
     return params->codec_type == AVMEDIA_TYPE_SUBTITLE;
 }
 
+int ffw_codec_parameters_is_data_codec(const AVCodecParameters* params) {
+    return params->codec_type == AVMEDIA_TYPE_DATA;
+}
+
+const char* ffw_codec_parameters_get_codec_name(const AVCodecParameters* params) {
+    return avcodec_get_name(params->codec_id);
+}
+
 const char* ffw_codec_parameters_get_decoder_name(const AVCodecParameters* params) {
     const AVCodec* codec = avcodec_find_decoder(params->codec_id);
     if (!codec) {
@@ -120,6 +164,22 @@ int ffw_codec_parameters_get_height(const AVCodecParameters* params) {
     return params->height;
 }
 
+void ffw_codec_parameters_get_sample_aspect_ratio(const AVCodecParameters* params, int* num, int* den) {
+    *num = params->sample_aspect_ratio.num;
+    *den = params->sample_aspect_ratio.den;
+}
+
+int ffw_codec_parameters_get_field_order(const AVCodecParameters* params) {
+    switch (params->field_order) {
+        case AV_FIELD_PROGRESSIVE: return 1;
+        case AV_FIELD_TT: return 2;
+        case AV_FIELD_BB: return 3;
+        case AV_FIELD_TB: return 4;
+        case AV_FIELD_BT: return 5;
+        default: return 0;
+    }
+}
+
 int ffw_codec_parameters_get_sample_rate(const AVCodecParameters* params) {
     return params->sample_rate;
 }
@@ -134,6 +194,32 @@ const uint64_t * ffw_codec_parameters_get_channel_layout(const AVCodecParameters
 }
 #endif
 
+int ffw_unknown_profile() {
+#if defined(AV_PROFILE_UNKNOWN)
+    return AV_PROFILE_UNKNOWN;
+#else
+    return FF_PROFILE_UNKNOWN;
+#endif
+}
+
+int ffw_codec_parameters_get_profile(const AVCodecParameters* params) {
+    return params->profile;
+}
+
+int ffw_codec_parameters_is_eac3_joc(const AVCodecParameters* params) {
+    if (params->codec_id != AV_CODEC_ID_EAC3) {
+        return 0;
+    }
+
+#if defined(AV_PROFILE_EAC3_DDP_ATMOS)
+    return params->profile == AV_PROFILE_EAC3_DDP_ATMOS;
+#elif defined(FF_PROFILE_EAC3_DDP_ATMOS)
+    return params->profile == FF_PROFILE_EAC3_DDP_ATMOS;
+#else
+    return 0;
+#endif
+}
+
 uint32_t ffw_codec_parameters_get_codec_tag(const AVCodecParameters* params) {
     return params->codec_tag;
 }
@@ -158,10 +244,19 @@ void ffw_codec_parameters_set_width(AVCodecParameters* params, int width) {
     params->width = width;
 }
 
+void ffw_codec_parameters_set_sample_aspect_ratio(AVCodecParameters* params, int num, int den) {
+    params->sample_aspect_ratio.num = num;
+    params->sample_aspect_ratio.den = den;
+}
+
 void ffw_codec_parameters_set_height(AVCodecParameters* params, int height) {
     params->height = height;
 }
 
+void ffw_codec_parameters_set_field_order(AVCodecParameters* params, int field_order) {
+    params->field_order = ffw_field_order_from_raw(field_order);
+}
+
 void ffw_codec_parameters_set_sample_rate(AVCodecParameters* params, int sample_rate) {
     params->sample_rate = sample_rate;
 }
@@ -181,6 +276,10 @@ int ffw_codec_parameters_set_channel_layout(AVCodecParameters* params, const uin
 }
 #endif
 
+void ffw_codec_parameters_set_profile(AVCodecParameters* params, int profile) {
+    params->profile = profile;
+}
+
 void ffw_codec_parameters_set_codec_tag(AVCodecParameters* params, uint32_t codec_tag) {
     params->codec_tag = codec_tag;
 }
@@ -223,6 +322,7 @@ Decoder* ffw_decoder_new(const char* codec);
 Decoder* ffw_decoder_from_codec_parameters(const AVCodecParameters* params);
 int ffw_decoder_set_extradata(Decoder* decoder, const uint8_t* extradata, int size);
 int ffw_decoder_set_initial_option(Decoder* decoder, const char* key, const char* value);
+void ffw_decoder_set_frame_pool(Decoder* decoder, void* pool);
 int ffw_decoder_open(Decoder* decoder);
 int ffw_decoder_push_packet(Decoder* decoder, const AVPacket* packet);
 int ffw_decoder_take_frame(Decoder* decoder, AVFrame** frame);
@@ -328,6 +428,18 @@ int ffw_decoder_set_initial_option(Decoder* decoder, const char* key, const char
     return av_dict_set(&decoder->options, key, value, 0);
 }
 
+int ffw_frame_pool_get_buffer(AVCodecContext* avctx, AVFrame* frame, int flags);
+
+void ffw_decoder_set_frame_pool(Decoder* decoder, void* pool) {
+    decoder->cc->opaque = pool;
+    decoder->cc->get_buffer2 = ffw_frame_pool_get_buffer;
+
+#if LIBAVCODEC_VERSION_MAJOR < 59
+    // allow frame threads to call the callback directly
+    decoder->cc->thread_safe_callbacks = 1;
+#endif
+}
+
 void ffw_decoder_set_pkt_timebase(Decoder* decoder, int num, int den) {
     AVRational r;
 
@@ -422,15 +534,30 @@ int ffw_encoder_get_width(const Encoder* encoder);
 int ffw_encoder_get_height(const Encoder* encoder);
 int ffw_encoder_get_sample_format(const Encoder* encoder);
 int ffw_encoder_get_sample_rate(const Encoder* encoder);
+int ffw_encoder_get_default_pixel_format(const Encoder* encoder);
+int ffw_encoder_get_default_sample_format(const Encoder* encoder);
+int ffw_encoder_get_closest_sample_rate(const Encoder* encoder, int sample_rate);
+int ffw_encoder_supports_sample_format(const Encoder* encoder, int format);
+int ffw_encoder_get_supported_pixel_format(const Encoder* encoder, size_t index);
+int ffw_encoder_get_supported_sample_format(const Encoder* encoder, size_t index);
+int ffw_encoder_get_supported_sample_rate(const Encoder* encoder, size_t index);
+int ffw_encoder_has_variable_frame_size(const Encoder* encoder);
+void ffw_encoder_get_time_base(const Encoder* encoder, int* num, int* den);
 void ffw_encoder_set_time_base(Encoder* encoder, int num, int den);
 void ffw_encoder_set_bit_rate(Encoder* encoder, int64_t bit_rate);
 void ffw_encoder_set_pixel_format(Encoder* encoder, int format);
 void ffw_encoder_set_width(Encoder* encoder, int width);
 void ffw_encoder_set_height(Encoder* encoder, int height);
+void ffw_encoder_set_sample_aspect_ratio(Encoder* encoder, int num, int den);
+void ffw_encoder_set_frame_rate(Encoder* encoder, int num, int den);
+void ffw_encoder_set_field_order(Encoder* encoder, int field_order);
 void ffw_encoder_set_sample_format(Encoder* encoder, int format);
 void ffw_encoder_set_sample_rate(Encoder* encoder, int sample_rate);
 void ffw_encoder_set_codec_tag(Encoder* encoder, uint32_t codec_tag);
+int ffw_encoder_set_reproducible(Encoder* encoder);
 int ffw_encoder_set_initial_option(Encoder* encoder, const char* key, const char* value);
+const char* ffw_encoder_get_codec_name(const Encoder* encoder);
+int ffw_encoder_has_private_option(const Encoder* encoder, const char* name);
 int ffw_encoder_open(Encoder* encoder);
 int ffw_encoder_push_frame(Encoder* encoder, const AVFrame* frame);
 int ffw_encoder_take_packet(Encoder* encoder, AVPacket** packet);
@@ -438,9 +565,11 @@ void ffw_encoder_free(Encoder* encoder);
 
 #ifdef FFW_FEATURE_CHANNEL_LAYOUT_V2
 const AVChannelLayout * ffw_encoder_get_channel_layout(const Encoder* encoder);
+const AVChannelLayout * ffw_encoder_get_closest_channel_layout(const Encoder* encoder, const AVChannelLayout* layout);
 int ffw_encoder_set_channel_layout(Encoder* encoder, const AVChannelLayout* layout);
 #else
 const uint64_t * ffw_encoder_get_channel_layout(const Encoder* encoder);
+const uint64_t * ffw_encoder_get_closest_channel_layout(const Encoder* encoder, const uint64_t* layout);
 int ffw_encoder_set_channel_layout(Encoder* encoder, const uint64_t* layout);
 #endif
 
@@ -559,20 +688,166 @@ int ffw_encoder_get_sample_rate(const Encoder* encoder) {
     return encoder->cc->sample_rate;
 }
 
+int ffw_encoder_get_default_pixel_format(const Encoder* encoder) {
+    if (!encoder->codec->pix_fmts) {
+        return AV_PIX_FMT_NONE;
+    }
+
+    return encoder->codec->pix_fmts[0];
+}
+
+int ffw_encoder_get_default_sample_format(const Encoder* encoder) {
+    if (!encoder->codec->sample_fmts) {
+        return AV_SAMPLE_FMT_NONE;
+    }
+
+    return encoder->codec->sample_fmts[0];
+}
+
+int ffw_encoder_get_closest_sample_rate(const Encoder* encoder, int sample_rate) {
+    const int* rate;
+    int res;
+
+    if (!(rate = encoder->codec->supported_samplerates)) {
+        return sample_rate;
+    }
+
+    res = *rate;
+
+    for (; *rate; rate++) {
+        if (abs(*rate - sample_rate) < abs(res - sample_rate)) {
+            res = *rate;
+        }
+    }
+
+    return res;
+}
+
+int ffw_encoder_supports_sample_format(const Encoder* encoder, int format) {
+    const enum AVSampleFormat* current;
+
+    if (!(current = encoder->codec->sample_fmts)) {
+        return 1;
+    }
+
+    for (; *current != AV_SAMPLE_FMT_NONE; current++) {
+        if (*current == format) {
+            return 1;
+        }
+    }
+
+    return 0;
+}
+
+int ffw_encoder_get_supported_pixel_format(const Encoder* encoder, size_t index) {
+    const enum AVPixelFormat* current;
+
+    if (!(current = encoder->codec->pix_fmts)) {
+        return AV_PIX_FMT_NONE;
+    }
+
+    for (; *current != AV_PIX_FMT_NONE && index > 0; current++, index--) {
+    }
+
+    return *current;
+}
+
+int ffw_encoder_get_supported_sample_format(const Encoder* encoder, size_t index) {
+    const enum AVSampleFormat* current;
+
+    if (!(current = encoder->codec->sample_fmts)) {
+        return AV_SAMPLE_FMT_NONE;
+    }
+
+    for (; *current != AV_SAMPLE_FMT_NONE && index > 0; current++, index--) {
+    }
+
+    return *current;
+}
+
+int ffw_encoder_get_supported_sample_rate(const Encoder* encoder, size_t index) {
+    const int* current;
+
+    if (!(current = encoder->codec->supported_samplerates)) {
+        return 0;
+    }
+
+    for (; *current && index > 0; current++, index--) {
+    }
+
+    return *current;
+}
+
 #ifdef FFW_FEATURE_CHANNEL_LAYOUT_V2
 const AVChannelLayout * ffw_encoder_get_channel_layout(const Encoder* encoder) {
     return &encoder->cc->ch_layout;
 }
+
+const AVChannelLayout * ffw_encoder_get_closest_channel_layout(const Encoder* encoder, const AVChannelLayout* layout) {
+    const AVChannelLayout* current;
+    const AVChannelLayout* res;
+
+    if (!(current = encoder->codec->ch_layouts)) {
+        return layout;
+    }
+
+    res = current;
+
+    // prefer an exact match, then the closest number of channels
+    for (; current->nb_channels; current++) {
+        if (av_channel_layout_compare(current, layout) == 0) {
+            return current;
+        } else if (abs(current->nb_channels - layout->nb_channels) < abs(res->nb_channels - layout->nb_channels)) {
+            res = current;
+        }
+    }
+
+    return res;
+}
 #else
 const uint64_t * ffw_encoder_get_channel_layout(const Encoder* encoder) {
     return &encoder->cc->channel_layout;
 }
+
+const uint64_t * ffw_encoder_get_closest_channel_layout(const Encoder* encoder, const uint64_t* layout) {
+    const uint64_t* current;
+    const uint64_t* res;
+    int channels;
+
+    if (!(current = encoder->codec->channel_layouts)) {
+        return layout;
+    }
+
+    res = current;
+
+    channels = av_get_channel_layout_nb_channels(*layout);
+
+    // prefer an exact match, then the closest number of channels
+    for (; *current; current++) {
+        if (*current == *layout) {
+            return current;
+        } else if (abs(av_get_channel_layout_nb_channels(*current) - channels) < abs(av_get_channel_layout_nb_channels(*res) - channels)) {
+            res = current;
+        }
+    }
+
+    return res;
+}
 #endif
 
 int ffw_encoder_get_frame_size(const Encoder* encoder) {
     return encoder->cc->frame_size;
 }
 
+int ffw_encoder_has_variable_frame_size(const Encoder* encoder) {
+    return (encoder->codec->capabilities & AV_CODEC_CAP_VARIABLE_FRAME_SIZE) != 0;
+}
+
+void ffw_encoder_get_time_base(const Encoder* encoder, int* num, int* den) {
+    *num = encoder->cc->time_base.num;
+    *den = encoder->cc->time_base.den;
+}
+
 void ffw_encoder_set_time_base(Encoder* encoder, int num, int den) {
     encoder->cc->time_base.num = num;
     encoder->cc->time_base.den = den;
@@ -590,6 +865,20 @@ void ffw_encoder_set_width(Encoder* encoder, int width) {
     encoder->cc->width = width;
 }
 
+void ffw_encoder_set_sample_aspect_ratio(Encoder* encoder, int num, int den) {
+    encoder->cc->sample_aspect_ratio.num = num;
+    encoder->cc->sample_aspect_ratio.den = den;
+}
+
+void ffw_encoder_set_frame_rate(Encoder* encoder, int num, int den) {
+    encoder->cc->framerate.num = num;
+    encoder->cc->framerate.den = den;
+}
+
+void ffw_encoder_set_field_order(Encoder* encoder, int field_order) {
+    encoder->cc->field_order = ffw_field_order_from_raw(field_order);
+}
+
 void ffw_encoder_set_height(Encoder* encoder, int height) {
     encoder->cc->height = height;
 }
@@ -625,6 +914,25 @@ int ffw_encoder_set_initial_option(Encoder* encoder, const char* key, const char
     return av_dict_set(&encoder->options, key, value, 0);
 }
 
+const char* ffw_encoder_get_codec_name(const Encoder* encoder) {
+    return avcodec_get_name(encoder->codec->id);
+}
+
+int ffw_encoder_has_private_option(const Encoder* encoder, const char* name) {
+    if (!encoder->codec->priv_class || !encoder->cc->priv_data) {
+        return 0;
+    }
+
+    return av_opt_find(encoder->cc->priv_data, name, NULL, 0, 0) != NULL;
+}
+
+int ffw_encoder_set_reproducible(Encoder* encoder) {
+    encoder->cc->flags |= AV_CODEC_FLAG_BITEXACT;
+
+    // the output of multi-threaded encoders may depend on thread timing
+    return av_dict_set(&encoder->options, "threads", "1", 0);
+}
+
 int ffw_encoder_open(Encoder* encoder) {
     return avcodec_open2(encoder->cc, encoder->codec, &encoder->options);
 }
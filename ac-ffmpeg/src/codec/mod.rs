@@ -2,6 +2,9 @@
 
 pub mod audio;
 pub mod bsf;
+pub mod hook;
+pub mod image;
+pub mod parser;
 pub mod video;
 
 use std::{
@@ -9,34 +12,48 @@ use std::{
     fmt::{self, Display, Formatter},
     os::raw::{c_char, c_int, c_void},
     ptr, slice,
+    time::Duration,
 };
 
 use crate::{
     codec::{
         audio::{ChannelLayoutRef, SampleFormat},
-        video::PixelFormat,
+        video::{FieldOrder, PixelFormat},
     },
     packet::Packet,
-    Error,
+    time::{self, Rational, TimeBase, Timestamp},
+    Error, ErrorKind,
 };
 
 extern "C" {
     fn ffw_audio_codec_parameters_new(codec: *const c_char) -> *mut c_void;
     fn ffw_video_codec_parameters_new(codec: *const c_char) -> *mut c_void;
     fn ffw_subtitle_codec_parameters_new(codec: *const c_char) -> *mut c_void;
+    fn ffw_data_codec_parameters_new(codec: *const c_char) -> *mut c_void;
     fn ffw_codec_parameters_clone(params: *const c_void) -> *mut c_void;
     fn ffw_codec_parameters_get_media_type(params: *const c_void) -> c_int;
     fn ffw_codec_parameters_is_audio_codec(params: *const c_void) -> c_int;
     fn ffw_codec_parameters_is_video_codec(params: *const c_void) -> c_int;
     fn ffw_codec_parameters_is_subtitle_codec(params: *const c_void) -> c_int;
+    fn ffw_codec_parameters_is_data_codec(params: *const c_void) -> c_int;
+    fn ffw_codec_parameters_get_codec_name(params: *const c_void) -> *const c_char;
     fn ffw_codec_parameters_get_decoder_name(params: *const c_void) -> *const c_char;
     fn ffw_codec_parameters_get_encoder_name(params: *const c_void) -> *const c_char;
     fn ffw_codec_parameters_get_bit_rate(params: *const c_void) -> i64;
     fn ffw_codec_parameters_get_format(params: *const c_void) -> c_int;
     fn ffw_codec_parameters_get_width(params: *const c_void) -> c_int;
     fn ffw_codec_parameters_get_height(params: *const c_void) -> c_int;
+    fn ffw_codec_parameters_get_sample_aspect_ratio(
+        params: *const c_void,
+        num: *mut c_int,
+        den: *mut c_int,
+    );
+    fn ffw_codec_parameters_get_field_order(params: *const c_void) -> c_int;
     fn ffw_codec_parameters_get_sample_rate(params: *const c_void) -> c_int;
     fn ffw_codec_parameters_get_channel_layout(params: *const c_void) -> *const c_void;
+    fn ffw_unknown_profile() -> c_int;
+    fn ffw_codec_parameters_get_profile(params: *const c_void) -> c_int;
+    fn ffw_codec_parameters_is_eac3_joc(params: *const c_void) -> c_int;
     fn ffw_codec_parameters_get_codec_tag(params: *const c_void) -> u32;
     fn ffw_codec_parameters_get_extradata(params: *mut c_void) -> *mut c_void;
     fn ffw_codec_parameters_get_extradata_size(params: *const c_void) -> c_int;
@@ -44,9 +61,12 @@ extern "C" {
     fn ffw_codec_parameters_set_format(params: *mut c_void, format: c_int);
     fn ffw_codec_parameters_set_width(params: *mut c_void, width: c_int);
     fn ffw_codec_parameters_set_height(params: *mut c_void, height: c_int);
+    fn ffw_codec_parameters_set_sample_aspect_ratio(params: *mut c_void, num: c_int, den: c_int);
+    fn ffw_codec_parameters_set_field_order(params: *mut c_void, field_order: c_int);
     fn ffw_codec_parameters_set_sample_rate(params: *mut c_void, rate: c_int);
     fn ffw_codec_parameters_set_channel_layout(params: *mut c_void, layout: *const c_void)
         -> c_int;
+    fn ffw_codec_parameters_set_profile(params: *mut c_void, profile: c_int);
     fn ffw_codec_parameters_set_codec_tag(params: *mut c_void, codec_tag: u32);
     fn ffw_codec_parameters_set_extradata(
         params: *mut c_void,
@@ -64,6 +84,7 @@ extern "C" {
         value: *const c_char,
     ) -> c_int;
     fn ffw_decoder_set_pkt_timebase(decoder: *mut c_void, num: c_int, den: c_int);
+    fn ffw_decoder_set_frame_pool(decoder: *mut c_void, pool: *mut c_void);
     fn ffw_decoder_open(decoder: *mut c_void) -> c_int;
     fn ffw_decoder_push_packet(decoder: *mut c_void, packet: *const c_void) -> c_int;
     fn ffw_decoder_take_frame(decoder: *mut c_void, frame: *mut *mut c_void) -> c_int;
@@ -80,26 +101,55 @@ extern "C" {
     fn ffw_encoder_get_sample_rate(encoder: *const c_void) -> c_int;
     fn ffw_encoder_get_channel_layout(encoder: *const c_void) -> *const c_void;
     fn ffw_encoder_get_frame_size(encoder: *const c_void) -> c_int;
+    fn ffw_encoder_has_variable_frame_size(encoder: *const c_void) -> c_int;
+    fn ffw_encoder_get_default_pixel_format(encoder: *const c_void) -> c_int;
+    fn ffw_encoder_get_default_sample_format(encoder: *const c_void) -> c_int;
+    fn ffw_encoder_get_closest_sample_rate(encoder: *const c_void, sample_rate: c_int) -> c_int;
+    fn ffw_encoder_get_closest_channel_layout(
+        encoder: *const c_void,
+        layout: *const c_void,
+    ) -> *const c_void;
+    fn ffw_encoder_supports_sample_format(encoder: *const c_void, format: c_int) -> c_int;
+    fn ffw_encoder_get_supported_pixel_format(encoder: *const c_void, index: usize) -> c_int;
+    fn ffw_encoder_get_supported_sample_format(encoder: *const c_void, index: usize) -> c_int;
+    fn ffw_encoder_get_supported_sample_rate(encoder: *const c_void, index: usize) -> c_int;
+    fn ffw_encoder_get_time_base(encoder: *const c_void, num: *mut c_int, den: *mut c_int);
     fn ffw_encoder_set_time_base(encoder: *mut c_void, num: c_int, den: c_int);
     fn ffw_encoder_set_bit_rate(encoder: *mut c_void, bit_rate: i64);
     fn ffw_encoder_set_pixel_format(encoder: *mut c_void, format: c_int);
     fn ffw_encoder_set_width(encoder: *mut c_void, width: c_int);
     fn ffw_encoder_set_height(encoder: *mut c_void, height: c_int);
+    fn ffw_encoder_set_sample_aspect_ratio(encoder: *mut c_void, num: c_int, den: c_int);
+    fn ffw_encoder_set_frame_rate(encoder: *mut c_void, num: c_int, den: c_int);
+    fn ffw_encoder_set_field_order(encoder: *mut c_void, field_order: c_int);
     fn ffw_encoder_set_sample_format(encoder: *mut c_void, format: c_int);
     fn ffw_encoder_set_sample_rate(encoder: *mut c_void, sample_rate: c_int);
     fn ffw_encoder_set_channel_layout(encoder: *mut c_void, layout: *const c_void) -> c_int;
     fn ffw_encoder_set_codec_tag(encoder: *mut c_void, codec_tag: u32);
+    fn ffw_encoder_set_reproducible(encoder: *mut c_void) -> c_int;
     fn ffw_encoder_set_initial_option(
         encoder: *mut c_void,
         key: *const c_char,
         value: *const c_char,
     ) -> c_int;
+    fn ffw_encoder_get_codec_name(encoder: *const c_void) -> *const c_char;
+    fn ffw_encoder_has_private_option(encoder: *const c_void, name: *const c_char) -> c_int;
     fn ffw_encoder_open(encoder: *mut c_void) -> c_int;
     fn ffw_encoder_push_frame(encoder: *mut c_void, frame: *const c_void) -> c_int;
     fn ffw_encoder_take_packet(encoder: *mut c_void, packet: *mut *mut c_void) -> c_int;
     fn ffw_encoder_free(encoder: *mut c_void);
 }
 
+/// Get the time base of a given encoder.
+unsafe fn get_encoder_time_base(encoder: *const c_void) -> TimeBase {
+    let mut num = 0;
+    let mut den = 0;
+
+    ffw_encoder_get_time_base(encoder, &mut num, &mut den);
+
+    TimeBase::new(num as _, den as _)
+}
+
 /// Error variants.
 #[derive(Debug, Clone)]
 enum CodecErrorVariant {
@@ -144,6 +194,14 @@ impl CodecError {
         matches!(&self.variant, CodecErrorVariant::Again(_))
     }
 
+    /// Get the error kind.
+    pub fn kind(&self) -> ErrorKind {
+        match &self.variant {
+            CodecErrorVariant::Again(_) => ErrorKind::Again,
+            CodecErrorVariant::Error(err) => err.kind(),
+        }
+    }
+
     /// Get the inner error (if any).
     pub fn into_inner(self) -> Option<Error> {
         if let CodecErrorVariant::Error(err) = self.variant {
@@ -216,6 +274,22 @@ impl InnerCodecParameters {
         unsafe { ffw_codec_parameters_is_subtitle_codec(self.ptr) != 0 }
     }
 
+    /// Check if these codec parameters are for a data codec.
+    fn is_data_codec(&self) -> bool {
+        unsafe { ffw_codec_parameters_is_data_codec(self.ptr) != 0 }
+    }
+
+    /// Get name of the codec.
+    fn codec_name(&self) -> &'static str {
+        unsafe {
+            let ptr = ffw_codec_parameters_get_codec_name(self.ptr);
+
+            let name = CStr::from_ptr(ptr as _);
+
+            name.to_str().unwrap()
+        }
+    }
+
     /// Get name of the decoder that is able to decode this codec or None
     /// if the decoder is not available.
     fn decoder_name(&self) -> Option<&'static str> {
@@ -253,6 +327,16 @@ impl InnerCodecParameters {
         let codec_tag = unsafe { ffw_codec_parameters_get_codec_tag(self.ptr) };
         codec_tag.into()
     }
+
+    /// Replace extradata.
+    fn set_extradata(&mut self, data: &[u8]) {
+        let res =
+            unsafe { ffw_codec_parameters_set_extradata(self.ptr, data.as_ptr(), data.len() as _) };
+
+        if res < 0 {
+            panic!("unable to allocate extradata");
+        }
+    }
 }
 
 impl Drop for InnerCodecParameters {
@@ -276,6 +360,41 @@ impl Clone for InnerCodecParameters {
 unsafe impl Send for InnerCodecParameters {}
 unsafe impl Sync for InnerCodecParameters {}
 
+/// Level of compliance with the standards (a typed equivalent of the
+/// `strict` option). It controls e.g. whether experimental encoders can be
+/// used or whether codecs can be muxed into containers that do not
+/// officially support them.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Compliance {
+    /// Strictly conform to an older, more strict version of the
+    /// specifications.
+    VeryStrict,
+    /// Strictly conform to all the things in the specifications.
+    Strict,
+    /// The default.
+    Normal,
+    /// Allow unofficial extensions.
+    Unofficial,
+    /// Allow non-standardized experimental things (e.g. experimental
+    /// encoders).
+    Experimental,
+}
+
+impl Compliance {
+    /// Get the raw value of the `strict` option.
+    pub(crate) fn into_raw(self) -> c_int {
+        match self {
+            Self::VeryStrict => 2,
+            Self::Strict => 1,
+            Self::Normal => 0,
+            Self::Unofficial => -1,
+            Self::Experimental => -2,
+        }
+    }
+}
+
+/// Media type of a codec.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum MediaType {
     Unknown = -1,
     Video,
@@ -323,6 +442,17 @@ impl AsRef<InnerCodecParameters> for CodecParametersVariant {
     }
 }
 
+impl AsMut<InnerCodecParameters> for CodecParametersVariant {
+    fn as_mut(&mut self) -> &mut InnerCodecParameters {
+        match self {
+            Self::Audio(audio) => &mut audio.inner,
+            Self::Video(video) => &mut video.inner,
+            Self::Subtitle(subtitle) => &mut subtitle.inner,
+            Self::Other(other) => &mut other.inner,
+        }
+    }
+}
+
 /// Codec parameters.
 #[derive(Clone)]
 pub struct CodecParameters {
@@ -357,6 +487,18 @@ impl CodecParameters {
         self.inner.as_ref().is_subtitle_codec()
     }
 
+    /// Check if these codec parameters are for a data codec (e.g. KLV
+    /// metadata or SCTE-35 messages).
+    pub fn is_data_codec(&self) -> bool {
+        self.inner.as_ref().is_data_codec()
+    }
+
+    /// Get name of the codec (e.g. "h264", "aac" or "klv"). The name is
+    /// known even if there is no decoder or encoder for the codec.
+    pub fn codec_name(&self) -> &'static str {
+        self.inner.as_ref().codec_name()
+    }
+
     /// Get name of the decoder that is able to decode this codec or None
     /// if the decoder is not available.
     pub fn decoder_name(&self) -> Option<&'static str> {
@@ -464,6 +606,12 @@ impl CodecParameters {
             }
         }
     }
+
+    /// Replace extradata (e.g. SPS/PPS or AudioSpecificConfig received out
+    /// of band). An empty slice removes the extradata.
+    pub fn set_extradata(&mut self, data: &[u8]) {
+        self.inner.as_mut().set_extradata(data)
+    }
 }
 
 impl From<AudioCodecParameters> for CodecParameters {
@@ -489,6 +637,14 @@ impl From<SubtitleCodecParameters> for CodecParameters {
     }
 }
 
+impl From<OtherCodecParameters> for CodecParameters {
+    fn from(params: OtherCodecParameters) -> Self {
+        Self {
+            inner: CodecParametersVariant::Other(params),
+        }
+    }
+}
+
 /// Builder for audio codec parameters.
 pub struct AudioCodecParametersBuilder {
     inner: InnerCodecParameters,
@@ -553,6 +709,17 @@ impl AudioCodecParametersBuilder {
         self
     }
 
+    /// Set codec profile. Use None for an unknown profile.
+    pub fn profile(self, profile: Option<i32>) -> Self {
+        unsafe {
+            let profile = profile.unwrap_or_else(|| ffw_unknown_profile());
+
+            ffw_codec_parameters_set_profile(self.inner.ptr, profile);
+        }
+
+        self
+    }
+
     /// Set codec tag.
     pub fn codec_tag(self, codec_tag: impl Into<CodecTag>) -> Self {
         unsafe {
@@ -654,6 +821,26 @@ impl AudioCodecParameters {
         }
     }
 
+    /// Get codec profile (if known).
+    pub fn profile(&self) -> Option<i32> {
+        let profile = unsafe { ffw_codec_parameters_get_profile(self.inner.ptr) };
+
+        if profile == unsafe { ffw_unknown_profile() } {
+            None
+        } else {
+            Some(profile)
+        }
+    }
+
+    /// Check if this is an E-AC-3 stream carrying Joint Object Coding
+    /// (i.e. Dolby Atmos) data. The JOC data is kept as a part of the
+    /// bitstream, so it survives stream copy. Note that the profile is
+    /// detected only by FFmpeg 6.1 or newer, the method always returns false
+    /// with older versions.
+    pub fn is_eac3_joc(&self) -> bool {
+        unsafe { ffw_codec_parameters_is_eac3_joc(self.inner.ptr) != 0 }
+    }
+
     /// Get codec tag.
     pub fn codec_tag(&self) -> CodecTag {
         self.inner.codec_tag()
@@ -672,6 +859,11 @@ impl AudioCodecParameters {
             }
         }
     }
+
+    /// Replace extradata. An empty slice removes the extradata.
+    pub fn set_extradata(&mut self, data: &[u8]) {
+        self.inner.set_extradata(data)
+    }
 }
 
 impl AsRef<InnerCodecParameters> for AudioCodecParameters {
@@ -745,6 +937,24 @@ impl VideoCodecParametersBuilder {
         self
     }
 
+    /// Set the sample (pixel) aspect ratio.
+    pub fn sample_aspect_ratio(self, sar: Rational) -> Self {
+        unsafe {
+            ffw_codec_parameters_set_sample_aspect_ratio(self.inner.ptr, sar.num(), sar.den());
+        }
+
+        self
+    }
+
+    /// Set the field order.
+    pub fn field_order(self, field_order: FieldOrder) -> Self {
+        unsafe {
+            ffw_codec_parameters_set_field_order(self.inner.ptr, field_order as _);
+        }
+
+        self
+    }
+
     /// Set codec tag.
     pub fn codec_tag(self, codec_tag: impl Into<CodecTag>) -> Self {
         unsafe {
@@ -844,6 +1054,31 @@ impl VideoCodecParameters {
         unsafe { ffw_codec_parameters_get_height(self.inner.ptr) as _ }
     }
 
+    /// Get the sample (pixel) aspect ratio (if known).
+    pub fn sample_aspect_ratio(&self) -> Option<Rational> {
+        let mut num = 0;
+        let mut den = 0;
+
+        unsafe {
+            ffw_codec_parameters_get_sample_aspect_ratio(self.inner.ptr, &mut num, &mut den);
+        }
+
+        time::positive_rational(num, den)
+    }
+
+    /// Get the display aspect ratio. Square pixels are assumed if the
+    /// sample aspect ratio is not known.
+    pub fn display_aspect_ratio(&self) -> Option<Rational> {
+        let sar = self.sample_aspect_ratio().unwrap_or(Rational::new(1, 1));
+
+        time::display_aspect_ratio(self.width(), self.height(), sar)
+    }
+
+    /// Get the field order.
+    pub fn field_order(&self) -> FieldOrder {
+        unsafe { FieldOrder::from_raw(ffw_codec_parameters_get_field_order(self.inner.ptr)) }
+    }
+
     /// Get codec tag.
     pub fn codec_tag(&self) -> CodecTag {
         self.inner.codec_tag()
@@ -862,6 +1097,11 @@ impl VideoCodecParameters {
             }
         }
     }
+
+    /// Replace extradata. An empty slice removes the extradata.
+    pub fn set_extradata(&mut self, data: &[u8]) {
+        self.inner.set_extradata(data)
+    }
 }
 
 impl AsRef<InnerCodecParameters> for VideoCodecParameters {
@@ -922,6 +1162,11 @@ impl SubtitleCodecParameters {
             }
         }
     }
+
+    /// Replace extradata. An empty slice removes the extradata.
+    pub fn set_extradata(&mut self, data: &[u8]) {
+        self.inner.set_extradata(data)
+    }
 }
 
 impl AsRef<InnerCodecParameters> for SubtitleCodecParameters {
@@ -943,6 +1188,36 @@ pub struct OtherCodecParameters {
 }
 
 impl OtherCodecParameters {
+    /// Create new codec parameters for a given data codec (e.g. "klv",
+    /// "scte_35", "timed_id3" or "bin_data"). Packets of data streams are
+    /// passed through as they are, there are no encoders or decoders
+    /// needed.
+    pub fn data(codec: &str) -> Result<Self, Error> {
+        let codec = CString::new(codec).expect("invalid codec name");
+
+        let ptr = unsafe { ffw_data_codec_parameters_new(codec.as_ptr() as *const _) };
+
+        if ptr.is_null() {
+            return Err(Error::new("unknown data codec"));
+        }
+
+        let params = unsafe { InnerCodecParameters::from_raw_ptr(ptr) };
+
+        let res = OtherCodecParameters { inner: params };
+
+        Ok(res)
+    }
+
+    /// Check if these codec parameters are for a data codec.
+    pub fn is_data_codec(&self) -> bool {
+        self.inner.is_data_codec()
+    }
+
+    /// Get name of the codec.
+    pub fn codec_name(&self) -> &'static str {
+        self.inner.codec_name()
+    }
+
     pub fn extradata(&self) -> Option<&[u8]> {
         unsafe {
             let data = ffw_codec_parameters_get_extradata(self.inner.ptr) as *const u8;
@@ -955,6 +1230,11 @@ impl OtherCodecParameters {
             }
         }
     }
+
+    /// Replace extradata. An empty slice removes the extradata.
+    pub fn set_extradata(&mut self, data: &[u8]) {
+        self.inner.set_extradata(data)
+    }
 }
 
 impl AsRef<InnerCodecParameters> for OtherCodecParameters {
@@ -991,6 +1271,123 @@ impl From<&[u8; 4]> for CodecTag {
     }
 }
 
+/// Decoder statistics.
+#[derive(Debug, Copy, Clone)]
+pub struct DecoderStatistics {
+    packets: u64,
+    dropped_packets: u64,
+    frames: u64,
+    corrupt_frames: u64,
+    decode_time: Duration,
+    last_packet_pts: Timestamp,
+    last_frame_pts: Timestamp,
+}
+
+impl DecoderStatistics {
+    /// Create new empty statistics.
+    fn new() -> Self {
+        Self {
+            packets: 0,
+            dropped_packets: 0,
+            frames: 0,
+            corrupt_frames: 0,
+            decode_time: Duration::from_secs(0),
+            last_packet_pts: Timestamp::null(),
+            last_frame_pts: Timestamp::null(),
+        }
+    }
+
+    /// Update the statistics using the result of a push operation.
+    fn record_push(&mut self, pts: Timestamp, elapsed: Duration, res: &Result<(), CodecError>) {
+        self.decode_time += elapsed;
+
+        match res {
+            Ok(()) => {
+                self.packets += 1;
+
+                if !pts.is_null() {
+                    self.last_packet_pts = pts;
+                }
+            }
+            Err(err) if !err.is_again() => {
+                self.packets += 1;
+                self.dropped_packets += 1;
+            }
+            _ => (),
+        }
+    }
+
+    /// Update the statistics using the result of a take operation.
+    fn record_take(&mut self, elapsed: Duration, frame: Option<(Timestamp, bool)>) {
+        self.decode_time += elapsed;
+
+        if let Some((pts, corrupt)) = frame {
+            self.frames += 1;
+
+            if corrupt {
+                self.corrupt_frames += 1;
+            }
+
+            if !pts.is_null() {
+                self.last_frame_pts = pts;
+            }
+        }
+    }
+
+    /// Get the number of packets pushed to the decoder (including the
+    /// dropped ones).
+    pub fn packets(&self) -> u64 {
+        self.packets
+    }
+
+    /// Get the number of packets that were rejected by the decoder (e.g.
+    /// because they contained invalid data).
+    pub fn dropped_packets(&self) -> u64 {
+        self.dropped_packets
+    }
+
+    /// Get the number of decoded frames.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Get the number of decoded frames flagged as corrupt by the decoder.
+    pub fn corrupt_frames(&self) -> u64 {
+        self.corrupt_frames
+    }
+
+    /// Get the total time spent in the decoder.
+    pub fn decode_time(&self) -> Duration {
+        self.decode_time
+    }
+
+    /// Get the average time needed to decode a single frame or None if no
+    /// frames have been decoded yet.
+    pub fn average_decode_time(&self) -> Option<Duration> {
+        if self.frames == 0 {
+            None
+        } else {
+            let nanos = self.decode_time.as_nanos() / self.frames as u128;
+
+            Some(Duration::from_nanos(nanos as u64))
+        }
+    }
+
+    /// Get the current decoder delay (i.e. the difference between the
+    /// presentation timestamp of the last pushed packet and the presentation
+    /// timestamp of the last decoded frame). The method returns None if the
+    /// delay cannot be determined.
+    pub fn delay(&self) -> Option<Duration> {
+        if self.last_packet_pts.is_null() || self.last_frame_pts.is_null() {
+            None
+        } else if self.last_packet_pts > self.last_frame_pts {
+            Some(self.last_packet_pts - self.last_frame_pts)
+        } else {
+            Some(Duration::from_secs(0))
+        }
+    }
+}
+
 /// A media decoder.
 ///
 /// # Common decoder operation
@@ -1076,3 +1473,25 @@ pub trait Encoder {
     /// Take the next packet from the encoder.
     fn take(&mut self) -> Result<Option<Packet>, Error>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::AudioCodecParameters;
+
+    #[test]
+    fn test_profile() {
+        let params = AudioCodecParameters::builder("aac")
+            .unwrap()
+            .profile(Some(1))
+            .build();
+
+        assert_eq!(params.profile(), Some(1));
+
+        let params = AudioCodecParameters::builder("aac")
+            .unwrap()
+            .profile(None)
+            .build();
+
+        assert_eq!(params.profile(), None);
+    }
+}
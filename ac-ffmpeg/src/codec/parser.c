@@ -0,0 +1,183 @@
+#include <libavcodec/avcodec.h>
+
+typedef struct Parser {
+    AVCodecParserContext* pc;
+    AVCodecContext* cc;
+
+    uint8_t* buffer;
+    unsigned int buffer_capacity;
+    int buffer_size;
+    int offset;
+
+    int64_t pts;
+    int64_t dts;
+
+    int flush;
+} Parser;
+
+Parser* ffw_parser_new(const char* codec);
+int ffw_parser_push(Parser* parser, const uint8_t* data, int size, int64_t pts, int64_t dts);
+int ffw_parser_flush(Parser* parser);
+int ffw_parser_take(Parser* parser, AVPacket** packet);
+void ffw_parser_free(Parser* parser);
+
+Parser* ffw_parser_new(const char* codec) {
+    const AVCodec* decoder;
+    Parser* res;
+
+    decoder = avcodec_find_decoder_by_name(codec);
+    if (!decoder) {
+        return NULL;
+    }
+
+    res = calloc(1, sizeof(Parser));
+    if (!res) {
+        return NULL;
+    }
+
+    res->pts = AV_NOPTS_VALUE;
+    res->dts = AV_NOPTS_VALUE;
+
+    res->pc = av_parser_init(decoder->id);
+    if (!res->pc) {
+        goto err;
+    }
+
+    res->cc = avcodec_alloc_context3(decoder);
+    if (!res->cc) {
+        goto err;
+    }
+
+    return res;
+
+err:
+    ffw_parser_free(res);
+
+    return NULL;
+}
+
+int ffw_parser_push(Parser* parser, const uint8_t* data, int size, int64_t pts, int64_t dts) {
+    // check if the previous data has been consumed
+    if (parser->offset < parser->buffer_size || parser->flush) {
+        return 0;
+    }
+
+    av_fast_padded_malloc(&parser->buffer, &parser->buffer_capacity, size);
+    if (!parser->buffer) {
+        return AVERROR(ENOMEM);
+    }
+
+    memcpy(parser->buffer, data, size);
+
+    parser->buffer_size = size;
+    parser->offset = 0;
+    parser->pts = pts;
+    parser->dts = dts;
+
+    return 1;
+}
+
+int ffw_parser_flush(Parser* parser) {
+    // check if the previous data has been consumed
+    if (parser->offset < parser->buffer_size) {
+        return 0;
+    }
+
+    parser->flush = 1;
+
+    return 1;
+}
+
+int ffw_parser_take(Parser* parser, AVPacket** packet) {
+    AVPacket* pkt;
+    uint8_t* data;
+    int size;
+    int len;
+    int ret;
+
+    data = NULL;
+    size = 0;
+
+    while (parser->offset < parser->buffer_size && size == 0) {
+        len = av_parser_parse2(
+            parser->pc,
+            parser->cc,
+            &data,
+            &size,
+            parser->buffer + parser->offset,
+            parser->buffer_size - parser->offset,
+            parser->pts,
+            parser->dts,
+            -1);
+
+        if (len < 0) {
+            return len;
+        }
+
+        parser->offset += len;
+
+        // the timestamps belong only to the first frame starting in the
+        // pushed data
+        parser->pts = AV_NOPTS_VALUE;
+        parser->dts = AV_NOPTS_VALUE;
+    }
+
+    if (size == 0 && parser->flush) {
+        av_parser_parse2(
+            parser->pc,
+            parser->cc,
+            &data,
+            &size,
+            NULL,
+            0,
+            AV_NOPTS_VALUE,
+            AV_NOPTS_VALUE,
+            -1);
+
+        parser->flush = 0;
+    }
+
+    if (size == 0) {
+        return 0;
+    }
+
+    pkt = av_packet_alloc();
+    if (!pkt) {
+        return AVERROR(ENOMEM);
+    }
+
+    ret = av_new_packet(pkt, size);
+    if (ret < 0) {
+        goto err;
+    }
+
+    memcpy(pkt->data, data, size);
+
+    pkt->pts = parser->pc->pts;
+    pkt->dts = parser->pc->dts;
+
+    if (parser->pc->key_frame == 1) {
+        pkt->flags |= AV_PKT_FLAG_KEY;
+    }
+
+    *packet = pkt;
+
+    return 1;
+
+err:
+    av_packet_free(&pkt);
+
+    return ret;
+}
+
+void ffw_parser_free(Parser* parser) {
+    if (!parser) {
+        return;
+    }
+
+    av_parser_close(parser->pc);
+    avcodec_free_context(&parser->cc);
+    av_free(parser->buffer);
+
+    free(parser);
+}
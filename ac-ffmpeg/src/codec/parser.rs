@@ -0,0 +1,421 @@
+//! Elementary stream parser.
+
+use std::{
+    ffi::CString,
+    os::raw::{c_char, c_int, c_void},
+    ptr,
+};
+
+use crate::{
+    codec::CodecError,
+    packet::Packet,
+    time::{TimeBase, Timestamp},
+    Error,
+};
+
+extern "C" {
+    fn ffw_parser_new(codec: *const c_char) -> *mut c_void;
+    fn ffw_parser_push(
+        parser: *mut c_void,
+        data: *const u8,
+        size: c_int,
+        pts: i64,
+        dts: i64,
+    ) -> c_int;
+    fn ffw_parser_flush(parser: *mut c_void) -> c_int;
+    fn ffw_parser_take(parser: *mut c_void, packet: *mut *mut c_void) -> c_int;
+    fn ffw_parser_free(parser: *mut c_void);
+}
+
+/// A builder for elementary stream parsers.
+pub struct ParserBuilder {
+    ptr: *mut c_void,
+    time_base: TimeBase,
+}
+
+impl ParserBuilder {
+    /// Create a new parser builder for a given codec.
+    fn new(codec: &str) -> Result<Self, Error> {
+        let codec = CString::new(codec).expect("invalid codec name");
+
+        let ptr = unsafe { ffw_parser_new(codec.as_ptr() as _) };
+
+        if ptr.is_null() {
+            return Err(Error::new("unknown codec or no parser available"));
+        }
+
+        let res = Self {
+            ptr,
+            time_base: TimeBase::MICROSECONDS,
+        };
+
+        Ok(res)
+    }
+
+    /// Set parser time base. By default it's in microseconds. All timestamps
+    /// passed to the parser will be rescaled to this time base and all output
+    /// packets will use this time base.
+    pub fn time_base(mut self, time_base: TimeBase) -> Self {
+        self.time_base = time_base;
+        self
+    }
+
+    /// Build the parser.
+    pub fn build(mut self) -> Parser {
+        let ptr = self.ptr;
+
+        self.ptr = ptr::null_mut();
+
+        Parser {
+            ptr,
+            time_base: self.time_base,
+        }
+    }
+}
+
+impl Drop for ParserBuilder {
+    fn drop(&mut self) {
+        unsafe { ffw_parser_free(self.ptr) }
+    }
+}
+
+unsafe impl Send for ParserBuilder {}
+unsafe impl Sync for ParserBuilder {}
+
+/// Elementary stream parser. It can be used for splitting raw elementary
+/// streams (e.g. H.264 Annex B byte streams, AAC ADTS or MP3) into packets.
+///
+/// # Parser operation
+/// 1. Push a chunk of data to the parser.
+/// 2. Take all packets from the parser until you get None.
+/// 3. If there is more data to be parsed, continue with 1.
+/// 4. Flush the parser.
+/// 5. Take all packets from the parser until you get None.
+///
+/// The chunks of data can be of any size. They do not need to be aligned
+/// with frame boundaries.
+pub struct Parser {
+    ptr: *mut c_void,
+    time_base: TimeBase,
+}
+
+impl Parser {
+    /// Create a new parser for a given codec.
+    pub fn new(codec: &str) -> Result<Self, Error> {
+        ParserBuilder::new(codec).map(|builder| builder.build())
+    }
+
+    /// Get a parser builder for a given codec.
+    ///
+    /// # Example
+    /// ```text
+    /// ...
+    ///
+    /// let parser = Parser::builder("h264")?
+    ///     .time_base(TimeBase::new(1, 90_000))
+    ///     .build();
+    ///
+    /// ...
+    /// ```
+    pub fn builder(codec: &str) -> Result<ParserBuilder, Error> {
+        ParserBuilder::new(codec)
+    }
+
+    /// Get parser time base.
+    pub fn time_base(&self) -> TimeBase {
+        self.time_base
+    }
+
+    /// Push a given chunk of data to the parser. The timestamps (if not null)
+    /// will be assigned to the first packet starting within the chunk.
+    ///
+    /// # Panics
+    /// The method panics if the operation is not expected (i.e. another
+    /// operation needs to be done).
+    pub fn push(&mut self, data: &[u8], pts: Timestamp, dts: Timestamp) -> Result<(), Error> {
+        self.try_push(data, pts, dts)
+            .map_err(|err| err.unwrap_inner())
+    }
+
+    /// Push a given chunk of data to the parser. The timestamps (if not null)
+    /// will be assigned to the first packet starting within the chunk.
+    pub fn try_push(
+        &mut self,
+        data: &[u8],
+        pts: Timestamp,
+        dts: Timestamp,
+    ) -> Result<(), CodecError> {
+        let pts = pts.with_time_base(self.time_base);
+        let dts = dts.with_time_base(self.time_base);
+
+        unsafe {
+            match ffw_parser_push(
+                self.ptr,
+                data.as_ptr(),
+                data.len() as _,
+                pts.timestamp(),
+                dts.timestamp(),
+            ) {
+                1 => Ok(()),
+                0 => Err(CodecError::again(
+                    "all packets must be consumed before pushing new data",
+                )),
+                e => Err(CodecError::from_raw_error_code(e)),
+            }
+        }
+    }
+
+    /// Flush the parser.
+    ///
+    /// # Panics
+    /// The method panics if the operation is not expected (i.e. another
+    /// operation needs to be done).
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.try_flush().map_err(|err| err.unwrap_inner())
+    }
+
+    /// Flush the parser.
+    pub fn try_flush(&mut self) -> Result<(), CodecError> {
+        unsafe {
+            match ffw_parser_flush(self.ptr) {
+                1 => Ok(()),
+                0 => Err(CodecError::again(
+                    "all packets must be consumed before flushing",
+                )),
+                e => Err(CodecError::from_raw_error_code(e)),
+            }
+        }
+    }
+
+    /// Take the next packet from the parser (if available).
+    pub fn take(&mut self) -> Result<Option<Packet>, Error> {
+        let mut pptr = ptr::null_mut();
+
+        unsafe {
+            match ffw_parser_take(self.ptr, &mut pptr) {
+                1 => {
+                    if pptr.is_null() {
+                        panic!("unable to allocate a packet")
+                    } else {
+                        Ok(Some(Packet::from_raw_ptr(pptr, self.time_base)))
+                    }
+                }
+                0 => Ok(None),
+                e => Err(Error::from_raw_error_code(e)),
+            }
+        }
+    }
+}
+
+impl Drop for Parser {
+    fn drop(&mut self) {
+        unsafe { ffw_parser_free(self.ptr) }
+    }
+}
+
+unsafe impl Send for Parser {}
+unsafe impl Sync for Parser {}
+
+#[cfg(test)]
+mod tests {
+    use super::Parser;
+
+    use crate::{
+        packet::Packet,
+        test_util::encode_h264,
+        time::{TimeBase, Timestamp},
+    };
+
+    /// Create an AAC LC ADTS frame (mono, 44.1 kHz) with a given payload
+    /// size.
+    fn adts_frame(payload_size: usize) -> Vec<u8> {
+        let len = payload_size + 7;
+
+        let mut res = vec![
+            0xff,
+            0xf1,
+            0x50,
+            0x40 | (len >> 11) as u8,
+            (len >> 3) as u8,
+            ((len & 7) << 5) as u8 | 0x1f,
+            0xfc,
+        ];
+
+        res.resize(len, 0);
+        res
+    }
+
+    /// Take all packets that are currently available.
+    fn take_all(parser: &mut Parser) -> Vec<Packet> {
+        let mut res = Vec::new();
+
+        while let Some(packet) = parser.take().unwrap() {
+            res.push(packet);
+        }
+
+        res
+    }
+
+    /// Push given data in chunks of a given size, flush the parser and
+    /// return the number of packets taken before flushing and all packets.
+    fn parse(parser: &mut Parser, data: &[u8], chunk_size: usize) -> (usize, Vec<Packet>) {
+        let mut res = Vec::new();
+
+        for chunk in data.chunks(chunk_size) {
+            parser
+                .push(chunk, Timestamp::null(), Timestamp::null())
+                .unwrap();
+
+            res.extend(take_all(parser));
+        }
+
+        let before_flush = res.len();
+
+        parser.flush().unwrap();
+
+        res.extend(take_all(parser));
+
+        (before_flush, res)
+    }
+
+    #[test]
+    fn test_adts() {
+        let time_base = TimeBase::new(1, 44_100);
+
+        let frames = [20, 33, 47, 61, 75]
+            .iter()
+            .map(|&size| adts_frame(size))
+            .collect::<Vec<_>>();
+
+        let mut parser = Parser::builder("aac").unwrap().time_base(time_base).build();
+
+        // frame-aligned chunks
+        for (index, frame) in frames.iter().enumerate() {
+            let ts = Timestamp::new(index as i64 * 1024, time_base);
+
+            parser.push(frame, ts, ts).unwrap();
+
+            let packets = take_all(&mut parser);
+
+            assert_eq!(packets.len(), 1);
+
+            let packet = &packets[0];
+
+            assert_eq!(packet.data(), frame.as_slice());
+            assert_eq!(packet.pts().timestamp(), index as i64 * 1024);
+            assert_eq!(packet.dts().timestamp(), index as i64 * 1024);
+        }
+
+        // there is nothing left
+        parser.flush().unwrap();
+
+        assert!(parser.take().unwrap().is_none());
+
+        // chunks split in the middle of frames (including the headers)
+        let data = frames.concat();
+
+        for chunk_size in [1, 5, 50, data.len()] {
+            let mut parser = Parser::new("aac").unwrap();
+
+            let (_, packets) = parse(&mut parser, &data, chunk_size);
+
+            let packets = packets
+                .iter()
+                .map(|packet| packet.data().to_vec())
+                .collect::<Vec<_>>();
+
+            // frames without timestamps
+            assert_eq!(packets, frames);
+        }
+    }
+
+    #[test]
+    fn test_h264() {
+        let encoded = match encode_h264(25) {
+            Some(packets) => packets,
+            None => return,
+        };
+
+        let data = encoded
+            .iter()
+            .flat_map(|packet| packet.data().iter().copied())
+            .collect::<Vec<_>>();
+
+        for chunk_size in [7, 100, 4096] {
+            let mut parser = Parser::new("h264").unwrap();
+
+            let (before_flush, packets) = parse(&mut parser, &data, chunk_size);
+
+            // the end of the last frame is known only after flushing
+            assert!(before_flush < encoded.len());
+            assert_eq!(packets.len(), encoded.len());
+
+            for (packet, expected) in packets.iter().zip(&encoded) {
+                assert_eq!(packet.is_key(), expected.is_key());
+            }
+
+            // nothing is lost (zero bytes of start codes may end up in the
+            // preceding packet)
+            let parsed = packets
+                .iter()
+                .flat_map(|packet| packet.data().iter().copied())
+                .collect::<Vec<_>>();
+
+            assert_eq!(parsed, data);
+        }
+    }
+
+    #[test]
+    fn test_again() {
+        let frame = adts_frame(20);
+
+        let mut parser = Parser::new("aac").unwrap();
+
+        // empty input is accepted and it does not produce anything
+        parser
+            .push(&[], Timestamp::null(), Timestamp::null())
+            .unwrap();
+
+        assert!(parser.take().unwrap().is_none());
+
+        parser
+            .push(&frame, Timestamp::null(), Timestamp::null())
+            .unwrap();
+
+        // the data must be consumed first
+        let err = parser
+            .try_push(&frame, Timestamp::null(), Timestamp::null())
+            .unwrap_err();
+
+        assert!(err.is_again());
+        assert!(parser.try_flush().unwrap_err().is_again());
+
+        assert!(parser.take().unwrap().is_some());
+        assert!(parser.take().unwrap().is_none());
+
+        // the flushed packets must be taken first
+        parser.flush().unwrap();
+
+        let err = parser
+            .try_push(&frame, Timestamp::null(), Timestamp::null())
+            .unwrap_err();
+
+        assert!(err.is_again());
+
+        assert!(parser.take().unwrap().is_none());
+
+        parser
+            .push(&frame, Timestamp::null(), Timestamp::null())
+            .unwrap();
+
+        assert!(parser.take().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_unknown_codec() {
+        assert!(Parser::new("foo").is_err());
+
+        // a decoder without a parser
+        assert!(Parser::new("pcm_s16le").is_err());
+    }
+}
@@ -0,0 +1,126 @@
+//! Closed captions (ATSC A53 Part 4, CEA-608/708).
+//!
+//! Decoders (e.g. `h264`, `hevc` or `mpeg2video`) attach closed captions
+//! found in the bitstream to decoded frames as
+//! `FrameSideDataType::A53ClosedCaptions` side data. The side data contains
+//! raw `cc_data` triplets (`cc_valid`/`cc_type` byte followed by two bytes
+//! of caption data). Captions are preserved through a transcode as long as
+//! the side data stays attached to the frames passed to an encoder that can
+//! embed them (see `VideoEncoderBuilder::closed_captions()`). Captions can
+//! be attached to any frame using `VideoFrame::set_closed_captions()`.
+
+use crate::{
+    codec::{video::VideoFrame, CodecParameters, SubtitleCodecParameters},
+    packet::{Packet, PacketMut},
+    Error,
+};
+
+/// Closed caption extractor. It converts closed captions attached to video
+/// frames into packets of a separate EIA-608 stream (e.g. to be stored as a
+/// `c608` track in MP4/MOV or to be decoded using the `cc_dec` decoder).
+///
+/// # Example
+/// ```text
+/// let extractor = ClosedCaptionExtractor::new()?;
+///
+/// let index = muxer_builder.add_stream(&extractor.codec_parameters())?;
+///
+/// let mut extractor = extractor.with_stream_index(index);
+///
+/// ...
+///
+/// if let Some(packet) = extractor.push(&frame) {
+///     muxer.push(packet)?;
+/// }
+/// ```
+pub struct ClosedCaptionExtractor {
+    codec_parameters: SubtitleCodecParameters,
+    stream_index: usize,
+}
+
+impl ClosedCaptionExtractor {
+    /// Create a new closed caption extractor.
+    pub fn new() -> Result<Self, Error> {
+        let res = Self {
+            codec_parameters: SubtitleCodecParameters::new("cc_dec")?,
+            stream_index: 0,
+        };
+
+        Ok(res)
+    }
+
+    /// Set stream index of the produced packets. The default is 0.
+    pub fn with_stream_index(mut self, index: usize) -> Self {
+        self.stream_index = index;
+        self
+    }
+
+    /// Get codec parameters of the closed caption stream.
+    pub fn codec_parameters(&self) -> CodecParameters {
+        self.codec_parameters.clone().into()
+    }
+
+    /// Extract closed captions from a given frame. The method returns None
+    /// if there are no captions attached to the frame. The packet has the
+    /// same timestamp, duration and time base as the frame.
+    pub fn push(&mut self, frame: &VideoFrame) -> Option<Packet> {
+        let data = frame.closed_captions()?;
+
+        if data.is_empty() {
+            return None;
+        }
+
+        let packet = PacketMut::from(data)
+            .with_stream_index(self.stream_index)
+            .with_time_base(frame.time_base())
+            .with_pts(frame.pts())
+            .with_dts(frame.pts())
+            .with_raw_duration(frame.raw_duration())
+            .with_key_flag(true)
+            .freeze();
+
+        Some(packet)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ClosedCaptionExtractor;
+
+    use crate::{
+        codec::video::{frame::get_pixel_format, VideoFrameMut},
+        time::{TimeBase, Timestamp},
+    };
+
+    #[test]
+    fn test_extraction() {
+        let time_base = TimeBase::new(1, 25);
+
+        let mut frame = VideoFrameMut::black(get_pixel_format("yuv420p"), 16, 8)
+            .with_time_base(time_base)
+            .freeze()
+            .with_pts(Timestamp::new(5, time_base));
+
+        let extractor = ClosedCaptionExtractor::new().unwrap();
+
+        assert!(extractor.codec_parameters().is_subtitle_codec());
+
+        let mut extractor = extractor.with_stream_index(2);
+
+        assert!(extractor.push(&frame).is_none());
+
+        frame.set_closed_captions(&[0xfc, 0x94, 0x20]).unwrap();
+
+        // the captions are replaced
+        frame.set_closed_captions(&[0xfc, 0x94, 0x2c]).unwrap();
+
+        assert_eq!(frame.closed_captions(), Some(&[0xfc, 0x94, 0x2c][..]));
+
+        let packet = extractor.push(&frame).unwrap();
+
+        assert_eq!(packet.data(), [0xfc, 0x94, 0x2c]);
+        assert_eq!(packet.stream_index(), 2);
+        assert_eq!(packet.pts(), Timestamp::new(5, time_base));
+        assert!(packet.is_key());
+    }
+}
@@ -0,0 +1,145 @@
+//! Frame conversion helpers shared by the frame analyzers and the
+//! transcoding pipeline.
+
+use crate::{
+    codec::video::{
+        scaler::{Algorithm, VideoFrameScaler},
+        PixelFormat, VideoFrame,
+    },
+    Error,
+};
+
+/// Frame converter. The underlying scaler is re-created only when the
+/// source or the target format/size changes.
+pub(crate) struct FrameConverter {
+    pixel_format: PixelFormat,
+    algorithm: Algorithm,
+    scaler: Option<(ScalerKey, VideoFrameScaler)>,
+}
+
+/// Source pixel format, source width, source height, target width and
+/// target height of a scaler.
+type ScalerKey = (PixelFormat, usize, usize, usize, usize);
+
+impl FrameConverter {
+    /// Create a new converter into a given pixel format.
+    pub fn new(pixel_format: PixelFormat, algorithm: Algorithm) -> Self {
+        Self {
+            pixel_format,
+            algorithm,
+            scaler: None,
+        }
+    }
+
+    /// Convert a given frame into the target pixel format and a given size.
+    pub fn convert(
+        &mut self,
+        frame: &VideoFrame,
+        width: usize,
+        height: usize,
+    ) -> Result<VideoFrame, Error> {
+        let key = (
+            frame.pixel_format(),
+            frame.width(),
+            frame.height(),
+            width,
+            height,
+        );
+
+        let reuse = matches!(&self.scaler, Some((k, _)) if *k == key);
+
+        if !reuse {
+            let scaler = VideoFrameScaler::builder()
+                .source_pixel_format(key.0)
+                .source_width(key.1)
+                .source_height(key.2)
+                .target_pixel_format(self.pixel_format)
+                .target_width(width)
+                .target_height(height)
+                .algorithm(self.algorithm)
+                .build()?;
+
+            self.scaler = Some((key, scaler));
+        }
+
+        let (_, scaler) = self.scaler.as_mut().unwrap();
+
+        scaler.scale(frame)
+    }
+
+    /// Convert a given frame into the target pixel format and a given size
+    /// and return the first plane packed without any padding. The pixel
+    /// format must be a packed format with a given number of bytes per
+    /// pixel.
+    pub fn convert_packed(
+        &mut self,
+        frame: &VideoFrame,
+        width: usize,
+        height: usize,
+        bytes_per_pixel: usize,
+    ) -> Result<Vec<u8>, Error> {
+        let converted = self.convert(frame, width, height)?;
+
+        let planes = converted.planes();
+        let plane = &planes[0];
+
+        let data = plane.data();
+        let stride = plane.line_size();
+
+        let width = bytes_per_pixel * converted.width();
+        let height = converted.height();
+
+        let mut res = Vec::with_capacity(width * height);
+
+        for y in 0..height {
+            res.extend_from_slice(&data[y * stride..y * stride + width]);
+        }
+
+        Ok(res)
+    }
+}
+
+/// Calculate the mean absolute difference of two images of the same size
+/// (0.0 - 255.0).
+pub(crate) fn mean_absolute_difference(a: &[u8], b: &[u8]) -> f64 {
+    if a.is_empty() {
+        return 0.0;
+    }
+
+    let sum = a
+        .iter()
+        .zip(b)
+        .map(|(&a, &b)| (a as i32 - b as i32).unsigned_abs() as u64)
+        .sum::<u64>();
+
+    sum as f64 / a.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mean_absolute_difference, FrameConverter};
+
+    use crate::codec::video::{frame::get_pixel_format, scaler::Algorithm, VideoFrameMut};
+
+    #[test]
+    fn test_mean_absolute_difference() {
+        assert_eq!(mean_absolute_difference(&[], &[]), 0.0);
+        assert_eq!(mean_absolute_difference(&[10, 20], &[10, 20]), 0.0);
+        assert_eq!(mean_absolute_difference(&[0, 255], &[255, 0]), 255.0);
+        assert_eq!(mean_absolute_difference(&[10, 20], &[20, 10]), 10.0);
+    }
+
+    #[test]
+    fn test_convert_packed() {
+        let mut converter = FrameConverter::new(get_pixel_format("rgb24"), Algorithm::Area);
+
+        for (width, height) in [(34, 18), (17, 9), (34, 18)] {
+            let frame = VideoFrameMut::black(get_pixel_format("yuv420p"), width, height).freeze();
+
+            let data = converter.convert_packed(&frame, 10, 4, 3).unwrap();
+
+            assert_eq!(data.len(), 10 * 4 * 3);
+            assert!(data.iter().all(|&b| b < 8));
+        }
+    }
+}
@@ -0,0 +1,132 @@
+#include <libavutil/frame.h>
+#include <libavutil/mem.h>
+#include <libavutil/pixdesc.h>
+
+int ffw_frame_detect_content(const AVFrame* frame, double limit, int* x1, int* y1, int* x2, int* y2);
+AVFrame* ffw_frame_crop(const AVFrame* src, int x, int y, int width, int height);
+
+// Detect bounding box of the frame content (i.e. the area that is not
+// covered by black bars). A row/column is considered to be a part of the
+// content if its average brightness is above a given limit (relative to the
+// maximum component value). The function returns 1 if some content has been
+// found, 0 if the whole frame is black and a negative error code otherwise.
+int ffw_frame_detect_content(const AVFrame* frame, double limit, int* x1, int* y1, int* x2, int* y2) {
+    const AVPixFmtDescriptor* desc;
+    uint16_t* line;
+    int64_t* columns;
+    int64_t row;
+    int64_t threshold;
+    int components;
+    int max_value;
+    int ret;
+    int c, x, y;
+
+    if (!(desc = av_pix_fmt_desc_get(frame->format))) {
+        return AVERROR(EINVAL);
+    }
+
+    if (desc->flags & (AV_PIX_FMT_FLAG_BITSTREAM | AV_PIX_FMT_FLAG_HWACCEL | AV_PIX_FMT_FLAG_PAL)) {
+        return AVERROR(ENOSYS);
+    }
+
+    if (frame->width < 1 || frame->height < 1) {
+        return 0;
+    }
+
+    // use luma for YUV/gray formats and all color components for RGB
+    if ((desc->flags & AV_PIX_FMT_FLAG_RGB) && desc->nb_components >= 3) {
+        components = 3;
+    } else {
+        components = 1;
+    }
+
+    max_value = (1 << desc->comp[0].depth) - 1;
+
+    line = av_malloc_array(frame->width, sizeof(uint16_t));
+    columns = av_calloc(frame->width, sizeof(int64_t));
+
+    if (!line || !columns) {
+        ret = AVERROR(ENOMEM);
+        goto end;
+    }
+
+    *x1 = frame->width;
+    *y1 = frame->height;
+    *x2 = -1;
+    *y2 = -1;
+
+    threshold = (int64_t)(limit * max_value * components * frame->width);
+
+    for (y = 0; y < frame->height; y++) {
+        row = 0;
+
+        for (c = 0; c < components; c++) {
+            av_read_image_line(
+                line,
+                (const uint8_t**)frame->data,
+                frame->linesize,
+                desc,
+                0, y, c,
+                frame->width,
+                0);
+
+            for (x = 0; x < frame->width; x++) {
+                row += line[x];
+                columns[x] += line[x];
+            }
+        }
+
+        if (row > threshold) {
+            if (y < *y1) {
+                *y1 = y;
+            }
+
+            *y2 = y;
+        }
+    }
+
+    threshold = (int64_t)(limit * max_value * components * frame->height);
+
+    for (x = 0; x < frame->width; x++) {
+        if (columns[x] > threshold) {
+            if (x < *x1) {
+                *x1 = x;
+            }
+
+            *x2 = x;
+        }
+    }
+
+    ret = (*x2 >= 0 && *y2 >= 0) ? 1 : 0;
+
+end:
+    av_free(line);
+    av_free(columns);
+
+    return ret;
+}
+
+AVFrame* ffw_frame_crop(const AVFrame* src, int x, int y, int width, int height) {
+    AVFrame* dst;
+
+    if (x < 0 || y < 0 || width < 1 || height < 1) {
+        return NULL;
+    } else if ((x + width) > src->width || (y + height) > src->height) {
+        return NULL;
+    }
+
+    if (!(dst = av_frame_clone(src))) {
+        return NULL;
+    }
+
+    dst->crop_left = x;
+    dst->crop_top = y;
+    dst->crop_right = src->width - x - width;
+    dst->crop_bottom = src->height - y - height;
+
+    if (av_frame_apply_cropping(dst, AV_FRAME_CROP_UNALIGNED) < 0) {
+        av_frame_free(&dst);
+    }
+
+    return dst;
+}
@@ -0,0 +1,442 @@
+//! Black bar detection and cropping.
+
+use std::os::raw::{c_int, c_void};
+
+use crate::{codec::video::VideoFrame, Error};
+
+extern "C" {
+    fn ffw_frame_detect_content(
+        frame: *const c_void,
+        limit: f64,
+        x1: *mut c_int,
+        y1: *mut c_int,
+        x2: *mut c_int,
+        y2: *mut c_int,
+    ) -> c_int;
+    fn ffw_frame_crop(
+        frame: *const c_void,
+        x: c_int,
+        y: c_int,
+        width: c_int,
+        height: c_int,
+    ) -> *mut c_void;
+}
+
+/// Crop rectangle.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct CropRect {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+impl CropRect {
+    /// Create a new crop rectangle.
+    pub const fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Get the horizontal offset.
+    pub fn x(&self) -> usize {
+        self.x
+    }
+
+    /// Get the vertical offset.
+    pub fn y(&self) -> usize {
+        self.y
+    }
+
+    /// Get the width.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Get the height.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Get the smallest rectangle containing both rectangles.
+    fn union(&self, other: &Self) -> Self {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+
+        Self::new(x, y, right - x, bottom - y)
+    }
+
+    /// Shrink the rectangle so that its dimensions are multiples of a given
+    /// number. The rectangle will stay centered and its offsets will be
+    /// even (to keep it aligned with chroma subsampling).
+    fn round(&self, round: usize) -> Self {
+        if round < 2 {
+            return *self;
+        }
+
+        let (x, width) = round_span(self.x, self.width, round);
+        let (y, height) = round_span(self.y, self.height, round);
+
+        Self::new(x, y, width, height)
+    }
+}
+
+/// Round a given span (offset + length) down to a multiple of a given
+/// number.
+fn round_span(offset: usize, len: usize, round: usize) -> (usize, usize) {
+    let rounded = len - len % round;
+
+    if rounded == 0 {
+        return (offset, len);
+    }
+
+    let shrink = len - rounded;
+
+    (offset + ((shrink / 2 + 1) & !1).min(shrink), rounded)
+}
+
+/// Builder for the crop detector.
+pub struct CropDetectorBuilder {
+    limit: f64,
+    round: usize,
+    skip: usize,
+    window: usize,
+}
+
+impl CropDetectorBuilder {
+    /// Create a new builder.
+    fn new() -> Self {
+        Self {
+            limit: 24.0 / 255.0,
+            round: 16,
+            skip: 2,
+            window: 100,
+        }
+    }
+
+    /// Set the black threshold relative to the maximum component value
+    /// (0.0 - 1.0). Rows and columns with average brightness equal to or
+    /// below the threshold are considered to be black. The default is
+    /// 24/255.
+    pub fn limit(mut self, limit: f64) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Round the detected dimensions down to a multiple of a given number.
+    /// The default is 16. Use 0 or 1 to disable rounding.
+    pub fn round(mut self, round: usize) -> Self {
+        self.round = round;
+        self
+    }
+
+    /// Ignore a given number of initial frames. The default is 2.
+    pub fn skip(mut self, frames: usize) -> Self {
+        self.skip = frames;
+        self
+    }
+
+    /// Set the maximum number of frames to analyze. Frames pushed beyond
+    /// this limit will be ignored. The default is 100. Use 0 for no limit.
+    pub fn window(mut self, frames: usize) -> Self {
+        self.window = frames;
+        self
+    }
+
+    /// Build the crop detector.
+    pub fn build(self) -> CropDetector {
+        CropDetector {
+            limit: self.limit,
+            round: self.round,
+            skip: self.skip,
+            window: self.window,
+            frames: 0,
+            width: 0,
+            height: 0,
+            content: None,
+        }
+    }
+}
+
+/// Black bar (letterbox/pillarbox) detector.
+///
+/// The detector analyzes a window of frames and finds the smallest
+/// rectangle containing the non-black area of all analyzed frames. Taking
+/// all frames into account makes the result stable even if the window
+/// contains dark scenes.
+///
+/// See [supported pixel formats](crate::codec::video#software-frame-processing).
+pub struct CropDetector {
+    limit: f64,
+    round: usize,
+    skip: usize,
+    window: usize,
+    frames: usize,
+    width: usize,
+    height: usize,
+    content: Option<CropRect>,
+}
+
+impl CropDetector {
+    /// Get a builder for the crop detector.
+    pub fn builder() -> CropDetectorBuilder {
+        CropDetectorBuilder::new()
+    }
+
+    /// Create a new crop detector with the default settings.
+    pub fn new() -> Self {
+        Self::builder().build()
+    }
+
+    /// Analyze a given frame. All frames are expected to have the same
+    /// dimensions.
+    pub fn push(&mut self, frame: &VideoFrame) -> Result<(), Error> {
+        if self.is_complete() {
+            return Ok(());
+        }
+
+        let frame_index = self.frames;
+
+        self.frames += 1;
+
+        if frame_index < self.skip {
+            return Ok(());
+        }
+
+        let width = frame.width();
+        let height = frame.height();
+
+        if self.content.is_some() && (width != self.width || height != self.height) {
+            return Err(Error::new("frame dimensions changed"));
+        }
+
+        self.width = width;
+        self.height = height;
+
+        let mut x1 = 0;
+        let mut y1 = 0;
+        let mut x2 = 0;
+        let mut y2 = 0;
+
+        let ret = unsafe {
+            ffw_frame_detect_content(
+                frame.as_ptr(),
+                self.limit,
+                &mut x1,
+                &mut y1,
+                &mut x2,
+                &mut y2,
+            )
+        };
+
+        if ret < 0 {
+            return Err(Error::from_raw_error_code(ret));
+        } else if ret == 0 {
+            return Ok(());
+        }
+
+        let rect = CropRect::new(
+            x1 as usize,
+            y1 as usize,
+            (x2 - x1 + 1) as usize,
+            (y2 - y1 + 1) as usize,
+        );
+
+        if let Some(content) = self.content.as_mut() {
+            *content = content.union(&rect);
+        } else {
+            self.content = Some(rect);
+        }
+
+        Ok(())
+    }
+
+    /// Check if the sampling window is full.
+    pub fn is_complete(&self) -> bool {
+        self.window > 0 && self.frames >= self.skip.saturating_add(self.window)
+    }
+
+    /// Get the detected crop rectangle. The method returns None if no
+    /// content has been detected yet (e.g. all analyzed frames were black).
+    pub fn crop(&self) -> Option<CropRect> {
+        self.content.map(|rect| rect.round(self.round))
+    }
+
+    /// Check if the detected crop rectangle removes anything from the frames.
+    pub fn is_cropping(&self) -> bool {
+        self.crop()
+            .map(|rect| rect != CropRect::new(0, 0, self.width, self.height))
+            .unwrap_or(false)
+    }
+}
+
+impl Default for CropDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VideoFrame {
+    /// Crop the frame. The method does not copy the frame data, the returned
+    /// frame references the same buffers. All frame properties (e.g.
+    /// timestamps) are preserved.
+    pub fn crop(&self, rect: CropRect) -> Result<VideoFrame, Error> {
+        if rect.width < 1 || rect.height < 1 {
+            return Err(Error::new("invalid crop rectangle"));
+        } else if (rect.x + rect.width) > self.width() || (rect.y + rect.height) > self.height() {
+            return Err(Error::new("crop rectangle out of frame bounds"));
+        }
+
+        let ptr = unsafe {
+            ffw_frame_crop(
+                self.as_ptr(),
+                rect.x as _,
+                rect.y as _,
+                rect.width as _,
+                rect.height as _,
+            )
+        };
+
+        if ptr.is_null() {
+            return Err(Error::new(
+                "unable to crop the frame, unsupported pixel format",
+            ));
+        }
+
+        let frame = unsafe { VideoFrame::from_raw_ptr(ptr, self.time_base()) };
+
+        Ok(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CropDetector, CropRect};
+
+    use crate::codec::video::{frame::get_pixel_format, VideoFrame, VideoFrameMut};
+
+    /// Create a 64x48 yuv420p frame with black bars around a given content
+    /// rectangle.
+    fn letterboxed_frame(content: CropRect) -> VideoFrame {
+        let mut frame = VideoFrameMut::black(get_pixel_format("yuv420p"), 64, 48);
+
+        {
+            let mut planes = frame.planes_mut();
+            let plane = &mut planes[0];
+            let stride = plane.line_size();
+            let data = plane.data_mut();
+
+            for y in content.y..content.y + content.height {
+                let offset = y * stride + content.x;
+
+                data[offset..offset + content.width].fill(200);
+            }
+        }
+
+        frame.freeze()
+    }
+
+    #[test]
+    fn test_union() {
+        let a = CropRect::new(0, 140, 1920, 800);
+        let b = CropRect::new(10, 130, 1900, 700);
+
+        assert_eq!(a.union(&b), CropRect::new(0, 130, 1920, 810));
+    }
+
+    #[test]
+    fn test_round() {
+        let rect = CropRect::new(0, 138, 1920, 804);
+
+        assert_eq!(rect.round(16), CropRect::new(0, 140, 1920, 800));
+        assert_eq!(rect.round(0), rect);
+
+        // too small to be rounded
+        let rect = CropRect::new(3, 3, 10, 10);
+
+        assert_eq!(rect.round(16), rect);
+    }
+
+    #[test]
+    fn test_detection() {
+        let content = CropRect::new(4, 8, 56, 32);
+
+        let full = letterboxed_frame(CropRect::new(0, 0, 64, 48));
+        let letterboxed = letterboxed_frame(content);
+
+        let mut detector = CropDetector::builder().round(0).skip(2).window(3).build();
+
+        // the skipped frames are not analyzed
+        detector.push(&full).unwrap();
+        detector.push(&full).unwrap();
+
+        assert_eq!(detector.crop(), None);
+
+        for _ in 0..3 {
+            detector.push(&letterboxed).unwrap();
+        }
+
+        assert!(detector.is_complete());
+        assert_eq!(detector.crop(), Some(content));
+        assert!(detector.is_cropping());
+
+        // frames beyond the window are ignored
+        detector.push(&full).unwrap();
+
+        assert_eq!(detector.crop(), Some(content));
+
+        let cropped = letterboxed.crop(content).unwrap();
+
+        assert_eq!(cropped.width(), 56);
+        assert_eq!(cropped.height(), 32);
+    }
+
+    #[test]
+    fn test_detection_rounding() {
+        let mut detector = CropDetector::builder().skip(0).build();
+
+        detector
+            .push(&letterboxed_frame(CropRect::new(4, 8, 56, 32)))
+            .unwrap();
+
+        assert_eq!(detector.crop(), Some(CropRect::new(8, 8, 48, 32)));
+    }
+
+    #[test]
+    fn test_black_frames() {
+        let mut detector = CropDetector::builder().skip(0).build();
+
+        let black = VideoFrameMut::black(get_pixel_format("yuv420p"), 64, 48).freeze();
+
+        detector.push(&black).unwrap();
+
+        assert_eq!(detector.crop(), None);
+        assert!(!detector.is_cropping());
+
+        // the content is accumulated over all frames
+        detector
+            .push(&letterboxed_frame(CropRect::new(0, 8, 64, 32)))
+            .unwrap();
+        detector
+            .push(&letterboxed_frame(CropRect::new(0, 4, 64, 32)))
+            .unwrap();
+
+        assert_eq!(detector.crop(), Some(CropRect::new(0, 4, 64, 36)));
+
+        // the default rounding keeps it centered
+        let mut detector = CropDetector::builder().skip(0).round(16).build();
+
+        detector
+            .push(&letterboxed_frame(CropRect::new(0, 4, 64, 36)))
+            .unwrap();
+
+        assert_eq!(detector.crop(), Some(CropRect::new(0, 6, 64, 32)));
+    }
+}
@@ -0,0 +1,314 @@
+//! Video deinterlacing.
+
+use crate::{
+    codec::{
+        video::{filter::LazyVideoFilterGraph, FieldOrder, VideoFrame},
+        CodecError,
+    },
+    Error,
+};
+
+/// Deinterlacing filter.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DeinterlacerKind {
+    /// Yet Another DeInterlacing Filter (`yadif`).
+    Yadif,
+    /// Bob Weaver deinterlacing filter (`bwdif`). It is slower than yadif
+    /// but it usually gives better results.
+    Bwdif,
+}
+
+/// Deinterlacing mode.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DeinterlaceMode {
+    /// Output one frame for each input frame.
+    Frame,
+    /// Output one frame for each field, i.e. double the frame rate.
+    Field,
+}
+
+/// Builder for the deinterlacer.
+pub struct DeinterlacerBuilder {
+    kind: DeinterlacerKind,
+    mode: DeinterlaceMode,
+    field_order: Option<FieldOrder>,
+    interlaced_only: bool,
+}
+
+impl DeinterlacerBuilder {
+    /// Create a new builder.
+    fn new() -> Self {
+        Self {
+            kind: DeinterlacerKind::Yadif,
+            mode: DeinterlaceMode::Frame,
+            field_order: None,
+            interlaced_only: true,
+        }
+    }
+
+    /// Set the deinterlacing filter. The default is
+    /// `DeinterlacerKind::Yadif`.
+    pub fn kind(mut self, kind: DeinterlacerKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    /// Set the deinterlacing mode. The default is `DeinterlaceMode::Frame`.
+    pub fn mode(mut self, mode: DeinterlaceMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Override the field order of input frames (e.g. if the source does not
+    /// signal it correctly). By default, the field order is taken from the
+    /// frame interlacing flags.
+    pub fn field_order(mut self, field_order: Option<FieldOrder>) -> Self {
+        self.field_order = field_order;
+        self
+    }
+
+    /// Deinterlace only frames flagged as interlaced. Progressive frames will
+    /// be passed through unchanged. The default is true. Set it to false for
+    /// sources that do not flag interlaced frames.
+    pub fn interlaced_only(mut self, interlaced_only: bool) -> Self {
+        self.interlaced_only = interlaced_only;
+        self
+    }
+
+    /// Build the deinterlacer.
+    pub fn build(self) -> Deinterlacer {
+        Deinterlacer {
+            filters: self.filters(),
+            graph: LazyVideoFilterGraph::new(),
+        }
+    }
+
+    /// Get the filter graph description.
+    fn filters(&self) -> String {
+        let filter = match self.kind {
+            DeinterlacerKind::Yadif => "yadif",
+            DeinterlacerKind::Bwdif => "bwdif",
+        };
+
+        let mode = match self.mode {
+            DeinterlaceMode::Frame => "send_frame",
+            DeinterlaceMode::Field => "send_field",
+        };
+
+        let parity = match self
+            .field_order
+            .and_then(|order| order.is_top_field_first())
+        {
+            Some(true) => "tff",
+            Some(false) => "bff",
+            None => "auto",
+        };
+
+        let deint = if self.interlaced_only {
+            "interlaced"
+        } else {
+            "all"
+        };
+
+        format!("{}=mode={}:parity={}:deint={}", filter, mode, parity, deint)
+    }
+}
+
+/// Video deinterlacer using the `yadif` or `bwdif` filter. The filter graph
+/// is created when the first frame is pushed, so the deinterlacer accepts
+/// frames of any pixel format supported by the filter. Note that the time
+/// base of the output frames may differ from the time base of the input
+/// frames (e.g. in the `DeinterlaceMode::Field` mode).
+///
+/// # Deinterlacer operation
+/// 1. Push a video frame to the deinterlacer.
+/// 2. Take all frames from the deinterlacer until you get None.
+/// 3. If there are more frames to be processed, continue with 1.
+/// 4. Flush the deinterlacer.
+/// 5. Take all frames from the deinterlacer until you get None.
+pub struct Deinterlacer {
+    filters: String,
+    graph: LazyVideoFilterGraph,
+}
+
+impl Deinterlacer {
+    /// Get a builder for the deinterlacer.
+    pub fn builder() -> DeinterlacerBuilder {
+        DeinterlacerBuilder::new()
+    }
+
+    /// Create a new deinterlacer with the default settings.
+    pub fn new() -> Self {
+        Self::builder().build()
+    }
+
+    /// Push a given frame to the deinterlacer.
+    ///
+    /// # Panics
+    /// The method panics if the operation is not expected (i.e. another
+    /// operation needs to be done).
+    pub fn push(&mut self, frame: VideoFrame) -> Result<(), Error> {
+        self.try_push(frame).map_err(|err| err.unwrap_inner())
+    }
+
+    /// Push a given frame to the deinterlacer.
+    pub fn try_push(&mut self, frame: VideoFrame) -> Result<(), CodecError> {
+        let filters = &self.filters;
+
+        self.graph.push(frame, |_| Ok(filters.clone()))?;
+
+        Ok(())
+    }
+
+    /// Flush the deinterlacer.
+    ///
+    /// # Panics
+    /// The method panics if the operation is not expected (i.e. another
+    /// operation needs to be done).
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.try_flush().map_err(|err| err.unwrap_inner())
+    }
+
+    /// Flush the deinterlacer.
+    pub fn try_flush(&mut self) -> Result<(), CodecError> {
+        self.graph.flush()?;
+
+        Ok(())
+    }
+
+    /// Take the next frame from the deinterlacer (if available).
+    pub fn take(&mut self) -> Result<Option<VideoFrame>, Error> {
+        self.graph.take()
+    }
+}
+
+impl Default for Deinterlacer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DeinterlaceMode, Deinterlacer, DeinterlacerKind};
+
+    use crate::{
+        codec::video::{frame::get_pixel_format, FieldOrder, VideoFrame, VideoFrameMut},
+        time::{TimeBase, Timestamp},
+    };
+
+    /// Push a given number of black 64x48 frames (25 fps) with a given
+    /// field order into a given deinterlacer and collect all output frames.
+    fn deinterlace(
+        mut deinterlacer: Deinterlacer,
+        frames: i64,
+        field_order: FieldOrder,
+    ) -> Vec<VideoFrame> {
+        let time_base = TimeBase::new(1, 25);
+
+        let frame = VideoFrameMut::black(get_pixel_format("yuv420p"), 64, 48)
+            .with_time_base(time_base)
+            .with_field_order(field_order)
+            .freeze();
+
+        let mut res = Vec::new();
+
+        for index in 0..frames {
+            let frame = frame.clone().with_pts(Timestamp::new(index, time_base));
+
+            deinterlacer.push(frame).unwrap();
+
+            while let Some(frame) = deinterlacer.take().unwrap() {
+                res.push(frame);
+            }
+        }
+
+        deinterlacer.flush().unwrap();
+
+        while let Some(frame) = deinterlacer.take().unwrap() {
+            res.push(frame);
+        }
+
+        res
+    }
+
+    /// Get presentation timestamps of given frames in milliseconds.
+    fn timestamps(frames: &[VideoFrame]) -> Vec<i64> {
+        frames
+            .iter()
+            .map(|frame| frame.pts().as_millis().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_filters() {
+        let builder = Deinterlacer::builder();
+
+        assert_eq!(
+            builder.filters(),
+            "yadif=mode=send_frame:parity=auto:deint=interlaced"
+        );
+
+        let builder = Deinterlacer::builder()
+            .kind(DeinterlacerKind::Bwdif)
+            .mode(DeinterlaceMode::Field)
+            .field_order(Some(FieldOrder::BottomFirst))
+            .interlaced_only(false);
+
+        assert_eq!(
+            builder.filters(),
+            "bwdif=mode=send_field:parity=bff:deint=all"
+        );
+    }
+
+    #[test]
+    fn test_frame_mode() {
+        for kind in [DeinterlacerKind::Yadif, DeinterlacerKind::Bwdif] {
+            let deinterlacer = Deinterlacer::builder().kind(kind).build();
+
+            let frames = deinterlace(deinterlacer, 10, FieldOrder::TopFirst);
+
+            let expected = (0..10).map(|index| index * 40).collect::<Vec<_>>();
+
+            assert_eq!(timestamps(&frames), expected);
+
+            for frame in frames {
+                assert_eq!(frame.width(), 64);
+                assert_eq!(frame.height(), 48);
+            }
+        }
+    }
+
+    #[test]
+    fn test_field_mode() {
+        let deinterlacer = Deinterlacer::builder().mode(DeinterlaceMode::Field).build();
+
+        let frames = deinterlace(deinterlacer, 10, FieldOrder::TopFirst);
+
+        // one frame per field
+        let expected = (0..20).map(|index| index * 20).collect::<Vec<_>>();
+
+        assert_eq!(timestamps(&frames), expected);
+    }
+
+    #[test]
+    fn test_progressive_frames() {
+        let deinterlacer = Deinterlacer::builder().mode(DeinterlaceMode::Field).build();
+
+        // progressive frames are passed through
+        let frames = deinterlace(deinterlacer, 10, FieldOrder::Progressive);
+
+        let expected = (0..10).map(|index| index * 40).collect::<Vec<_>>();
+
+        assert_eq!(timestamps(&frames), expected);
+
+        let deinterlacer = Deinterlacer::builder()
+            .mode(DeinterlaceMode::Field)
+            .interlaced_only(false)
+            .build();
+
+        let frames = deinterlace(deinterlacer, 10, FieldOrder::Progressive);
+
+        assert_eq!(frames.len(), 20);
+    }
+}
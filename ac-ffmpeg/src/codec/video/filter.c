@@ -0,0 +1,181 @@
+#include <stdio.h>
+
+#include <libavfilter/avfilter.h>
+#include <libavfilter/buffersink.h>
+#include <libavfilter/buffersrc.h>
+#include <libavutil/frame.h>
+#include <libavutil/pixdesc.h>
+
+typedef struct VideoFilterGraph {
+    AVFilterGraph* graph;
+    AVFilterContext* source;
+    AVFilterContext* sink;
+    AVFrame* frame;
+} VideoFilterGraph;
+
+VideoFilterGraph* ffw_video_filter_graph_new(
+    int format,
+    int width,
+    int height,
+    int sar_num,
+    int sar_den,
+    int tb_num,
+    int tb_den,
+    const char* filters);
+void ffw_video_filter_graph_get_time_base(const VideoFilterGraph* graph, int* num, int* den);
+int ffw_video_filter_graph_push_frame(VideoFilterGraph* graph, const AVFrame* frame);
+int ffw_video_filter_graph_take_frame(VideoFilterGraph* graph, AVFrame** frame);
+void ffw_video_filter_graph_free(VideoFilterGraph* graph);
+
+VideoFilterGraph* ffw_video_filter_graph_new(
+    int format,
+    int width,
+    int height,
+    int sar_num,
+    int sar_den,
+    int tb_num,
+    int tb_den,
+    const char* filters) {
+    VideoFilterGraph* res;
+    AVFilterInOut* inputs;
+    AVFilterInOut* outputs;
+    const AVFilter* buffer;
+    const AVFilter* buffersink;
+
+    char args[512];
+
+    inputs = NULL;
+    outputs = NULL;
+
+    buffer = avfilter_get_by_name("buffer");
+    buffersink = avfilter_get_by_name("buffersink");
+
+    if (!buffer || !buffersink) {
+        return NULL;
+    }
+
+    if (!(res = calloc(1, sizeof(VideoFilterGraph)))) {
+        return NULL;
+    }
+
+    if (!(res->graph = avfilter_graph_alloc())) {
+        goto err;
+    }
+
+    if (!(res->frame = av_frame_alloc())) {
+        goto err;
+    }
+
+    snprintf(
+        args,
+        sizeof(args),
+        "video_size=%dx%d:pix_fmt=%d:time_base=%d/%d:pixel_aspect=%d/%d",
+        width,
+        height,
+        format,
+        tb_num,
+        tb_den,
+        sar_num,
+        sar_den);
+
+    if (avfilter_graph_create_filter(&res->source, buffer, "in", args, NULL, res->graph) < 0) {
+        goto err;
+    }
+
+    if (avfilter_graph_create_filter(&res->sink, buffersink, "out", NULL, NULL, res->graph) < 0) {
+        goto err;
+    }
+
+    if (!(outputs = avfilter_inout_alloc())) {
+        goto err;
+    } else if (!(inputs = avfilter_inout_alloc())) {
+        goto err;
+    }
+
+    outputs->name = av_strdup("in");
+    outputs->filter_ctx = res->source;
+    outputs->pad_idx = 0;
+    outputs->next = NULL;
+
+    inputs->name = av_strdup("out");
+    inputs->filter_ctx = res->sink;
+    inputs->pad_idx = 0;
+    inputs->next = NULL;
+
+    if (!outputs->name || !inputs->name) {
+        goto err;
+    }
+
+    if (avfilter_graph_parse_ptr(res->graph, filters, &inputs, &outputs, NULL) < 0) {
+        goto err;
+    }
+
+    if (avfilter_graph_config(res->graph, NULL) < 0) {
+        goto err;
+    }
+
+    avfilter_inout_free(&inputs);
+    avfilter_inout_free(&outputs);
+
+    return res;
+
+err:
+    avfilter_inout_free(&inputs);
+    avfilter_inout_free(&outputs);
+
+    ffw_video_filter_graph_free(res);
+
+    return NULL;
+}
+
+void ffw_video_filter_graph_get_time_base(const VideoFilterGraph* graph, int* num, int* den) {
+    AVRational tb = av_buffersink_get_time_base(graph->sink);
+
+    *num = tb.num;
+    *den = tb.den;
+}
+
+int ffw_video_filter_graph_push_frame(VideoFilterGraph* graph, const AVFrame* frame) {
+    int ret;
+
+    if (frame) {
+        ret = av_buffersrc_add_frame_flags(graph->source, (AVFrame*)frame, AV_BUFFERSRC_FLAG_KEEP_REF);
+    } else {
+        ret = av_buffersrc_add_frame(graph->source, NULL);
+    }
+
+    if (ret < 0) {
+        return ret;
+    }
+
+    return 1;
+}
+
+int ffw_video_filter_graph_take_frame(VideoFilterGraph* graph, AVFrame** frame) {
+    int ret;
+
+    ret = av_buffersink_get_frame(graph->sink, graph->frame);
+
+    if (ret == AVERROR(EAGAIN) || ret == AVERROR_EOF) {
+        return 0;
+    } else if (ret < 0) {
+        return ret;
+    }
+
+    *frame = av_frame_clone(graph->frame);
+
+    av_frame_unref(graph->frame);
+
+    return 1;
+}
+
+void ffw_video_filter_graph_free(VideoFilterGraph* graph) {
+    if (!graph) {
+        return;
+    }
+
+    avfilter_graph_free(&graph->graph);
+    av_frame_free(&graph->frame);
+
+    free(graph);
+}
@@ -0,0 +1,202 @@
+//! Internal video filter graph.
+
+use std::{
+    ffi::CString,
+    os::raw::{c_char, c_int, c_void},
+    ptr,
+};
+
+use crate::{
+    codec::video::{PixelFormat, VideoFrame},
+    time::{Rational, TimeBase},
+    Error,
+};
+
+extern "C" {
+    fn ffw_video_filter_graph_new(
+        format: c_int,
+        width: c_int,
+        height: c_int,
+        sar_num: c_int,
+        sar_den: c_int,
+        tb_num: c_int,
+        tb_den: c_int,
+        filters: *const c_char,
+    ) -> *mut c_void;
+    fn ffw_video_filter_graph_get_time_base(graph: *const c_void, num: *mut c_int, den: *mut c_int);
+    fn ffw_video_filter_graph_push_frame(graph: *mut c_void, frame: *const c_void) -> c_int;
+    fn ffw_video_filter_graph_take_frame(graph: *mut c_void, frame: *mut *mut c_void) -> c_int;
+    fn ffw_video_filter_graph_free(graph: *mut c_void);
+}
+
+/// A simple video filter graph with a single input and a single output
+/// described using the FFmpeg filter graph syntax.
+pub(crate) struct VideoFilterGraph {
+    ptr: *mut c_void,
+    input_time_base: TimeBase,
+    output_time_base: TimeBase,
+}
+
+impl VideoFilterGraph {
+    /// Create a new filter graph for input frames with given parameters.
+    pub fn new(
+        pixel_format: PixelFormat,
+        width: usize,
+        height: usize,
+        sample_aspect_ratio: Option<Rational>,
+        time_base: TimeBase,
+        filters: &str,
+    ) -> Result<Self, Error> {
+        let filters = CString::new(filters).expect("invalid filter description");
+
+        // zero means unknown
+        let sar = sample_aspect_ratio.unwrap_or_else(|| Rational::new(0, 1));
+
+        let ptr = unsafe {
+            ffw_video_filter_graph_new(
+                pixel_format.into_raw(),
+                width as _,
+                height as _,
+                sar.num() as _,
+                sar.den() as _,
+                time_base.num() as _,
+                time_base.den() as _,
+                filters.as_ptr() as _,
+            )
+        };
+
+        if ptr.is_null() {
+            return Err(Error::new("unable to create a video filter graph"));
+        }
+
+        let mut num = 0;
+        let mut den = 0;
+
+        unsafe {
+            ffw_video_filter_graph_get_time_base(ptr, &mut num, &mut den);
+        }
+
+        let res = Self {
+            ptr,
+            input_time_base: time_base,
+            output_time_base: TimeBase::new(num as _, den as _),
+        };
+
+        Ok(res)
+    }
+
+    /// Push a given frame to the filter graph.
+    pub fn push(&mut self, frame: VideoFrame) -> Result<(), Error> {
+        let frame = frame.with_time_base(self.input_time_base);
+
+        let ret = unsafe { ffw_video_filter_graph_push_frame(self.ptr, frame.as_ptr()) };
+
+        if ret < 0 {
+            return Err(Error::from_raw_error_code(ret));
+        }
+
+        Ok(())
+    }
+
+    /// Flush the filter graph.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        let ret = unsafe { ffw_video_filter_graph_push_frame(self.ptr, ptr::null()) };
+
+        if ret < 0 {
+            return Err(Error::from_raw_error_code(ret));
+        }
+
+        Ok(())
+    }
+
+    /// Take the next frame from the filter graph (if available).
+    pub fn take(&mut self) -> Result<Option<VideoFrame>, Error> {
+        let mut fptr = ptr::null_mut();
+
+        unsafe {
+            match ffw_video_filter_graph_take_frame(self.ptr, &mut fptr) {
+                1 => {
+                    if fptr.is_null() {
+                        panic!("unable to allocate a video frame")
+                    } else {
+                        Ok(Some(VideoFrame::from_raw_ptr(fptr, self.output_time_base)))
+                    }
+                }
+                0 => Ok(None),
+                e => Err(Error::from_raw_error_code(e)),
+            }
+        }
+    }
+}
+
+impl Drop for VideoFilterGraph {
+    fn drop(&mut self) {
+        unsafe { ffw_video_filter_graph_free(self.ptr) }
+    }
+}
+
+unsafe impl Send for VideoFilterGraph {}
+unsafe impl Sync for VideoFilterGraph {}
+
+/// A video filter graph created when the first frame is pushed, so that it
+/// accepts frames of any format supported by the filters.
+pub(crate) struct LazyVideoFilterGraph {
+    graph: Option<VideoFilterGraph>,
+}
+
+impl LazyVideoFilterGraph {
+    /// Create a new lazy filter graph.
+    pub fn new() -> Self {
+        Self { graph: None }
+    }
+
+    /// Check if the filter graph has been created already.
+    pub fn is_initialized(&self) -> bool {
+        self.graph.is_some()
+    }
+
+    /// Push a given frame to the filter graph. If the graph does not exist
+    /// yet, it is created from a description returned by a given closure
+    /// for the frame.
+    pub fn push<F>(&mut self, frame: VideoFrame, filters: F) -> Result<(), Error>
+    where
+        F: FnOnce(&VideoFrame) -> Result<String, Error>,
+    {
+        if self.graph.is_none() {
+            let graph = VideoFilterGraph::new(
+                frame.pixel_format(),
+                frame.width(),
+                frame.height(),
+                frame.sample_aspect_ratio(),
+                frame.time_base(),
+                &filters(&frame)?,
+            )?;
+
+            self.graph = Some(graph);
+        }
+
+        if let Some(graph) = self.graph.as_mut() {
+            graph.push(frame)?;
+        }
+
+        Ok(())
+    }
+
+    /// Flush the filter graph. Nothing happens if the graph does not exist.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        if let Some(graph) = self.graph.as_mut() {
+            graph.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Take the next frame from the filter graph (if available).
+    pub fn take(&mut self) -> Result<Option<VideoFrame>, Error> {
+        if let Some(graph) = self.graph.as_mut() {
+            graph.take()
+        } else {
+            Ok(None)
+        }
+    }
+}
@@ -0,0 +1,198 @@
+#include <string.h>
+
+#include <libavutil/frame.h>
+#include <libavutil/pixdesc.h>
+#include <libswscale/swscale.h>
+
+AVFrame* ffw_frame_fit(
+    const AVFrame* src,
+    int twidth, int theight,
+    int sx, int sy, int swidth, int sheight,
+    int dx, int dy, int dwidth, int dheight,
+    int fill, uint8_t r, uint8_t g, uint8_t b,
+    int flags);
+
+// defined in pixel.c
+AVFrame* ffw_pixel_alloc_frame(int format, int width, int height);
+int ffw_pixel_get_size(const AVPixFmtDescriptor* desc, int plane);
+
+// Fill a given frame with a given RGB color.
+static int fill_frame(AVFrame* frame, const AVPixFmtDescriptor* desc, int planes, uint8_t r, uint8_t g, uint8_t b) {
+    struct SwsContext* sws;
+    AVFrame* rgb;
+    AVFrame* tmp;
+    uint8_t* line;
+    int pixel_size;
+    int width, height;
+    int log2_w, log2_h;
+    int i, x, y;
+    int ret;
+
+    sws = NULL;
+    tmp = NULL;
+    ret = -1;
+
+    if (!(rgb = ffw_pixel_alloc_frame(AV_PIX_FMT_RGB24, 2, 2))) {
+        goto end;
+    } else if (!(tmp = ffw_pixel_alloc_frame(frame->format, 2, 2))) {
+        goto end;
+    }
+
+    for (y = 0; y < 2; y++) {
+        for (x = 0; x < 2; x++) {
+            line = rgb->data[0] + y * rgb->linesize[0];
+
+            line[3 * x] = r;
+            line[3 * x + 1] = g;
+            line[3 * x + 2] = b;
+        }
+    }
+
+    sws = sws_getContext(
+        2, 2, AV_PIX_FMT_RGB24,
+        2, 2, frame->format,
+        SWS_POINT, NULL, NULL, NULL);
+
+    if (!sws) {
+        goto end;
+    }
+
+    sws_scale(sws,
+        (const uint8_t* const*)rgb->data, rgb->linesize, 0, 2,
+        tmp->data, tmp->linesize);
+
+    // replicate the converted pixel
+    for (i = 0; i < planes; i++) {
+        pixel_size = ffw_pixel_get_size(desc, i);
+
+        if (i == 1 || i == 2) {
+            log2_w = desc->log2_chroma_w;
+            log2_h = desc->log2_chroma_h;
+        } else {
+            log2_w = 0;
+            log2_h = 0;
+        }
+
+        width = AV_CEIL_RSHIFT(frame->width, log2_w);
+        height = AV_CEIL_RSHIFT(frame->height, log2_h);
+
+        for (y = 0; y < height; y++) {
+            line = frame->data[i] + y * frame->linesize[i];
+
+            for (x = 0; x < width; x++) {
+                memcpy(line + x * pixel_size, tmp->data[i], pixel_size);
+            }
+        }
+    }
+
+    ret = 0;
+
+end:
+    sws_freeContext(sws);
+    av_frame_free(&rgb);
+    av_frame_free(&tmp);
+
+    return ret;
+}
+
+AVFrame* ffw_frame_fit(
+    const AVFrame* src,
+    int twidth, int theight,
+    int sx, int sy, int swidth, int sheight,
+    int dx, int dy, int dwidth, int dheight,
+    int fill, uint8_t r, uint8_t g, uint8_t b,
+    int flags) {
+    const AVPixFmtDescriptor* desc;
+    struct SwsContext* sws;
+    AVFrame* dst;
+    const uint8_t* src_data[4];
+    uint8_t* dst_data[4];
+    int pixel_size;
+    int planes;
+    int log2_w, log2_h;
+    int i;
+
+    if (!(desc = av_pix_fmt_desc_get(src->format))) {
+        return NULL;
+    }
+
+    if (desc->flags & (AV_PIX_FMT_FLAG_BITSTREAM | AV_PIX_FMT_FLAG_HWACCEL | AV_PIX_FMT_FLAG_PAL)) {
+        return NULL;
+    }
+
+    if ((planes = av_pix_fmt_count_planes(src->format)) < 0) {
+        return NULL;
+    }
+
+    for (i = 0; i < planes; i++) {
+        if (!ffw_pixel_get_size(desc, i)) {
+            return NULL;
+        }
+    }
+
+    // align the offsets with the chroma subsampling
+    sx &= ~((1 << desc->log2_chroma_w) - 1);
+    sy &= ~((1 << desc->log2_chroma_h) - 1);
+    dx &= ~((1 << desc->log2_chroma_w) - 1);
+    dy &= ~((1 << desc->log2_chroma_h) - 1);
+
+    if (!(dst = ffw_pixel_alloc_frame(src->format, twidth, theight))) {
+        return NULL;
+    }
+
+    if (av_frame_copy_props(dst, src) != 0) {
+        goto err;
+    }
+
+    dst->sample_aspect_ratio.num = 1;
+    dst->sample_aspect_ratio.den = 1;
+
+    if (fill && fill_frame(dst, desc, planes, r, g, b) != 0) {
+        goto err;
+    }
+
+    memset(src_data, 0, sizeof(src_data));
+    memset(dst_data, 0, sizeof(dst_data));
+
+    for (i = 0; i < planes; i++) {
+        pixel_size = ffw_pixel_get_size(desc, i);
+
+        if (i == 1 || i == 2) {
+            log2_w = desc->log2_chroma_w;
+            log2_h = desc->log2_chroma_h;
+        } else {
+            log2_w = 0;
+            log2_h = 0;
+        }
+
+        src_data[i] = src->data[i]
+            + (sy >> log2_h) * src->linesize[i]
+            + (sx >> log2_w) * pixel_size;
+
+        dst_data[i] = dst->data[i]
+            + (dy >> log2_h) * dst->linesize[i]
+            + (dx >> log2_w) * pixel_size;
+    }
+
+    sws = sws_getContext(
+        swidth, sheight, src->format,
+        dwidth, dheight, src->format,
+        flags, NULL, NULL, NULL);
+
+    if (!sws) {
+        goto err;
+    }
+
+    sws_scale(sws,
+        src_data, src->linesize, 0, sheight,
+        dst_data, dst->linesize);
+
+    sws_freeContext(sws);
+
+    return dst;
+
+err:
+    av_frame_free(&dst);
+
+    return NULL;
+}
@@ -0,0 +1,303 @@
+//! Fitting video frames into a given target size.
+
+use std::os::raw::{c_int, c_void};
+
+use crate::{
+    codec::video::{scaler::Algorithm, VideoFrame},
+    Error,
+};
+
+extern "C" {
+    fn ffw_frame_get_sample_aspect_ratio(frame: *const c_void, num: *mut c_int, den: *mut c_int);
+    fn ffw_frame_fit(
+        frame: *const c_void,
+        twidth: c_int,
+        theight: c_int,
+        sx: c_int,
+        sy: c_int,
+        swidth: c_int,
+        sheight: c_int,
+        dx: c_int,
+        dy: c_int,
+        dwidth: c_int,
+        dheight: c_int,
+        fill: c_int,
+        r: u8,
+        g: u8,
+        b: u8,
+        flags: c_int,
+    ) -> *mut c_void;
+}
+
+/// Method of fitting a frame into a target size.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum FitMode {
+    /// Scale the frame to fit entirely within the target size while
+    /// preserving its aspect ratio. The remaining area will be padded
+    /// (letterbox/pillarbox).
+    Contain,
+    /// Scale the frame to cover the whole target size while preserving its
+    /// aspect ratio. Parts of the frame outside of the target area will be
+    /// cropped.
+    Cover,
+    /// Scale the frame to exactly the target size ignoring its aspect ratio.
+    Stretch,
+}
+
+/// RGB color.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Color {
+    r: u8,
+    g: u8,
+    b: u8,
+}
+
+impl Color {
+    /// Black color.
+    pub const BLACK: Self = Self::rgb(0, 0, 0);
+
+    /// White color.
+    pub const WHITE: Self = Self::rgb(255, 255, 255);
+
+    /// Create a new color from its RGB components.
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Get the red component.
+    pub fn red(self) -> u8 {
+        self.r
+    }
+
+    /// Get the green component.
+    pub fn green(self) -> u8 {
+        self.g
+    }
+
+    /// Get the blue component.
+    pub fn blue(self) -> u8 {
+        self.b
+    }
+}
+
+/// Rectangle.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct Rect {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+impl Rect {
+    /// Create a new rectangle.
+    fn new(x: usize, y: usize, width: usize, height: usize) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+/// Source and target area of a fit operation.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct FitGeometry {
+    source: Rect,
+    target: Rect,
+}
+
+impl FitGeometry {
+    /// Calculate the geometry for a given source size, sample aspect ratio,
+    /// target size and fit mode. The target is expected to have square
+    /// pixels.
+    fn new(
+        swidth: usize,
+        sheight: usize,
+        sar: (u32, u32),
+        twidth: usize,
+        theight: usize,
+        mode: FitMode,
+    ) -> Self {
+        let sar = if sar.0 == 0 || sar.1 == 0 {
+            1.0
+        } else {
+            sar.0 as f64 / sar.1 as f64
+        };
+
+        // source display width
+        let sdwidth = swidth as f64 * sar;
+
+        let hscale = twidth as f64 / sdwidth;
+        let vscale = theight as f64 / sheight as f64;
+
+        let full_source = Rect::new(0, 0, swidth, sheight);
+        let full_target = Rect::new(0, 0, twidth, theight);
+
+        match mode {
+            FitMode::Stretch => Self {
+                source: full_source,
+                target: full_target,
+            },
+            FitMode::Contain => {
+                let scale = hscale.min(vscale);
+
+                let width = scaled_size(sdwidth * scale, twidth);
+                let height = scaled_size(sheight as f64 * scale, theight);
+
+                Self {
+                    source: full_source,
+                    target: Rect::new((twidth - width) / 2, (theight - height) / 2, width, height),
+                }
+            }
+            FitMode::Cover => {
+                let scale = hscale.max(vscale);
+
+                let width = scaled_size(twidth as f64 / scale / sar, swidth);
+                let height = scaled_size(theight as f64 / scale, sheight);
+
+                Self {
+                    source: Rect::new((swidth - width) / 2, (sheight - height) / 2, width, height),
+                    target: full_target,
+                }
+            }
+        }
+    }
+}
+
+/// Round a given scaled size and limit it by a given maximum. Sizes lower
+/// than the maximum are rounded down to an even number in order to keep the
+/// result symmetric with chroma subsampling.
+fn scaled_size(size: f64, max: usize) -> usize {
+    let size = (size.round() as usize).max(1);
+
+    if size >= max {
+        max
+    } else if size > 1 {
+        size & !1
+    } else {
+        size
+    }
+}
+
+/// Fit a given frame into a given target size using a given fit mode. Padded
+/// areas (if any) will be filled with a given color. The resulting frame will
+/// have the same pixel format and properties (e.g. timestamps) as the source
+/// frame and it will have square pixels.
+///
+/// See [supported pixel formats](crate::codec::video#software-frame-processing).
+pub fn fit(
+    frame: &VideoFrame,
+    width: usize,
+    height: usize,
+    mode: FitMode,
+    pad_color: Color,
+) -> Result<VideoFrame, Error> {
+    fit_with_algorithm(frame, width, height, mode, pad_color, Algorithm::Bicubic)
+}
+
+/// Fit a given frame into a given target size using a given fit mode and
+/// scaling algorithm. See `fit()` for more info.
+pub fn fit_with_algorithm(
+    frame: &VideoFrame,
+    width: usize,
+    height: usize,
+    mode: FitMode,
+    pad_color: Color,
+    algorithm: Algorithm,
+) -> Result<VideoFrame, Error> {
+    if width < 1 {
+        return Err(Error::new("invalid target width"));
+    } else if height < 1 {
+        return Err(Error::new("invalid target height"));
+    }
+
+    let mut sar_num = 0;
+    let mut sar_den = 0;
+
+    unsafe {
+        ffw_frame_get_sample_aspect_ratio(frame.as_ptr(), &mut sar_num, &mut sar_den);
+    }
+
+    let sar = (sar_num.max(0) as u32, sar_den.max(0) as u32);
+
+    let geometry = FitGeometry::new(frame.width(), frame.height(), sar, width, height, mode);
+
+    let source = geometry.source;
+    let target = geometry.target;
+
+    let fill = target.width < width || target.height < height;
+
+    let ptr = unsafe {
+        ffw_frame_fit(
+            frame.as_ptr(),
+            width as _,
+            height as _,
+            source.x as _,
+            source.y as _,
+            source.width as _,
+            source.height as _,
+            target.x as _,
+            target.y as _,
+            target.width as _,
+            target.height as _,
+            fill as _,
+            pad_color.r,
+            pad_color.g,
+            pad_color.b,
+            algorithm as _,
+        )
+    };
+
+    if ptr.is_null() {
+        return Err(Error::new(
+            "unable to fit the frame, unsupported pixel format",
+        ));
+    }
+
+    let res = unsafe { VideoFrame::from_raw_ptr(ptr, frame.time_base()) };
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FitGeometry, FitMode, Rect};
+
+    #[test]
+    fn test_contain() {
+        let g = FitGeometry::new(1920, 1080, (1, 1), 640, 640, FitMode::Contain);
+
+        assert_eq!(g.source, Rect::new(0, 0, 1920, 1080));
+        assert_eq!(g.target, Rect::new(0, 140, 640, 360));
+
+        let g = FitGeometry::new(1080, 1920, (1, 1), 1280, 720, FitMode::Contain);
+
+        assert_eq!(g.target, Rect::new(438, 0, 404, 720));
+    }
+
+    #[test]
+    fn test_contain_anamorphic() {
+        // 720x576 with 16:15 SAR is 768x576 (4:3) when displayed
+        let g = FitGeometry::new(720, 576, (16, 15), 1024, 768, FitMode::Contain);
+
+        assert_eq!(g.target, Rect::new(0, 0, 1024, 768));
+    }
+
+    #[test]
+    fn test_cover() {
+        let g = FitGeometry::new(1920, 1080, (1, 1), 640, 640, FitMode::Cover);
+
+        assert_eq!(g.source, Rect::new(420, 0, 1080, 1080));
+        assert_eq!(g.target, Rect::new(0, 0, 640, 640));
+    }
+
+    #[test]
+    fn test_stretch() {
+        let g = FitGeometry::new(1920, 1080, (1, 1), 640, 640, FitMode::Stretch);
+
+        assert_eq!(g.source, Rect::new(0, 0, 1920, 1080));
+        assert_eq!(g.target, Rect::new(0, 0, 640, 640));
+    }
+}
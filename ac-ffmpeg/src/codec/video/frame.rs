@@ -12,17 +12,62 @@ use std::{
     time::Duration,
 };
 
-use crate::time::{TimeBase, Timestamp};
+#[cfg(feature = "ndarray")]
+use crate::codec::image::{convert, from_packed_pixels, packed_pixels};
+
+use crate::{
+    codec::video::sei::{parse_s12m_timecodes, UnregisteredSei},
+    time::{self, Rational, TimeBase, Timestamp},
+    timecode::Timecode,
+    Error,
+};
 
 extern "C" {
     fn ffw_get_pixel_format_by_name(name: *const c_char) -> c_int;
     fn ffw_pixel_format_is_none(format: c_int) -> c_int;
     fn ffw_get_pixel_format_name(format: c_int) -> *const c_char;
+    fn ffw_pixel_format_get_planes(format: c_int) -> c_int;
+    fn ffw_pixel_format_get_components(format: c_int) -> c_int;
+    fn ffw_pixel_format_get_log2_chroma_w(format: c_int) -> c_int;
+    fn ffw_pixel_format_get_log2_chroma_h(format: c_int) -> c_int;
+    fn ffw_pixel_format_get_bit_depth(format: c_int) -> c_int;
+    fn ffw_pixel_format_get_bits_per_pixel(format: c_int) -> c_int;
+    fn ffw_pixel_format_has_flag(format: c_int, flag: c_int) -> c_int;
+    fn ffw_pixel_format_get_loss(dst: c_int, src: c_int, has_alpha: c_int) -> c_int;
+    fn ffw_pixel_format_find_best(
+        formats: *const c_int,
+        count: usize,
+        src: c_int,
+        has_alpha: c_int,
+        loss: *mut c_int,
+    ) -> c_int;
 
     fn ffw_frame_new_black(pixel_format: c_int, width: c_int, height: c_int) -> *mut c_void;
+    fn ffw_frame_new_external(
+        pixel_format: c_int,
+        width: c_int,
+        height: c_int,
+        data: *const *const u8,
+        line_sizes: *const usize,
+        planes: usize,
+        free_callback: extern "C" fn(opaque: *mut c_void, data: *mut u8),
+        opaque: *mut c_void,
+        frame: *mut *mut c_void,
+    ) -> c_int;
     fn ffw_frame_get_format(frame: *const c_void) -> c_int;
     fn ffw_frame_get_width(frame: *const c_void) -> c_int;
     fn ffw_frame_get_height(frame: *const c_void) -> c_int;
+    fn ffw_frame_get_sample_aspect_ratio(frame: *const c_void, num: *mut c_int, den: *mut c_int);
+    fn ffw_frame_get_crop(
+        frame: *const c_void,
+        top: *mut usize,
+        bottom: *mut usize,
+        left: *mut usize,
+        right: *mut usize,
+    );
+    fn ffw_frame_set_crop(frame: *mut c_void, top: usize, bottom: usize, left: usize, right: usize);
+    fn ffw_frame_apply_cropping(frame: *mut c_void) -> c_int;
+    fn ffw_frame_set_sample_aspect_ratio(frame: *mut c_void, num: c_int, den: c_int);
     fn ffw_frame_get_best_effort_timestamp(frame: *const c_void) -> i64;
     fn ffw_frame_get_pts(frame: *const c_void) -> i64;
     fn ffw_frame_set_pts(frame: *mut c_void, pts: i64);
@@ -34,9 +79,31 @@ extern "C" {
     fn ffw_frame_clone(frame: *const c_void) -> *mut c_void;
     fn ffw_frame_free(frame: *mut c_void);
     fn ffw_frame_is_writable(frame: *const c_void) -> c_int;
+    fn ffw_frame_is_corrupt(frame: *const c_void) -> c_int;
     fn ffw_frame_make_writable(frame: *mut c_void) -> c_int;
+    fn ffw_frame_clone_deep(frame: *const c_void, res: *mut *mut c_void) -> c_int;
     fn ffw_frame_get_picture_type(frame: *const c_void) -> c_int;
     fn ffw_frame_set_picture_type(frame: *mut c_void, picture_type: c_int);
+    fn ffw_frame_is_interlaced(frame: *const c_void) -> c_int;
+    fn ffw_frame_is_top_field_first(frame: *const c_void) -> c_int;
+    fn ffw_frame_set_interlaced(frame: *mut c_void, interlaced: c_int, top_field_first: c_int);
+
+    fn ffw_frame_side_data_type_to_raw(data_type: c_int) -> c_int;
+    fn ffw_frame_side_data_type_from_raw(raw: c_int) -> c_int;
+    fn ffw_frame_side_data_type_name(raw: c_int) -> *const c_char;
+    fn ffw_frame_get_nb_side_data(frame: *const c_void) -> usize;
+    fn ffw_frame_get_side_data(frame: *const c_void, index: usize) -> *const c_void;
+    fn ffw_frame_side_data_get_type(side_data: *const c_void) -> c_int;
+    fn ffw_frame_side_data_get_data(side_data: *const c_void) -> *const u8;
+    fn ffw_frame_side_data_get_size(side_data: *const c_void) -> usize;
+    fn ffw_frame_add_side_data(
+        frame: *mut c_void,
+        raw: c_int,
+        data: *const u8,
+        size: usize,
+    ) -> c_int;
+    fn ffw_frame_remove_side_data(frame: *mut c_void, raw: c_int);
+    fn ffw_frame_copy_side_data(dst: *mut c_void, src: *const c_void, raw: c_int) -> c_int;
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -73,6 +140,61 @@ impl PictureType {
     }
 }
 
+/// Callback releasing an external frame buffer.
+type FreeCallback = Box<dyn FnOnce() + Send>;
+
+/// Free an external frame buffer.
+extern "C" fn free_external_buffer(opaque: *mut c_void, _: *mut u8) {
+    let callback = unsafe { Box::from_raw(opaque as *mut FreeCallback) };
+
+    callback();
+}
+
+/// Field order of interlaced video.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FieldOrder {
+    /// Unknown field order.
+    Unknown = 0,
+    /// Progressive video.
+    Progressive = 1,
+    /// Top field coded first, top field displayed first.
+    TopFirst = 2,
+    /// Bottom field coded first, bottom field displayed first.
+    BottomFirst = 3,
+    /// Top field coded first, bottom field displayed first.
+    TopCodedBottomFirst = 4,
+    /// Bottom field coded first, top field displayed first.
+    BottomCodedTopFirst = 5,
+}
+
+impl FieldOrder {
+    pub(crate) fn from_raw(value: c_int) -> Self {
+        match value {
+            1 => FieldOrder::Progressive,
+            2 => FieldOrder::TopFirst,
+            3 => FieldOrder::BottomFirst,
+            4 => FieldOrder::TopCodedBottomFirst,
+            5 => FieldOrder::BottomCodedTopFirst,
+            _ => FieldOrder::Unknown,
+        }
+    }
+
+    /// Check if the field order describes interlaced video.
+    pub fn is_interlaced(self) -> bool {
+        !matches!(self, FieldOrder::Unknown | FieldOrder::Progressive)
+    }
+
+    /// Check if the top field is displayed first. The method returns None
+    /// for progressive video and unknown field order.
+    pub fn is_top_field_first(self) -> Option<bool> {
+        match self {
+            FieldOrder::TopFirst | FieldOrder::BottomCodedTopFirst => Some(true),
+            FieldOrder::BottomFirst | FieldOrder::TopCodedBottomFirst => Some(false),
+            _ => None,
+        }
+    }
+}
+
 /// An error indicating an unknown pixel format.
 #[derive(Debug, Copy, Clone)]
 pub struct UnknownPixelFormat;
@@ -116,6 +238,163 @@ impl PixelFormat {
             name.to_str().unwrap()
         }
     }
+
+    /// Get all pixel formats known to FFmpeg.
+    pub fn all() -> impl Iterator<Item = Self> {
+        (0..)
+            .map(Self)
+            .take_while(|format| unsafe { !ffw_get_pixel_format_name(format.0).is_null() })
+    }
+
+    /// Get the number of planes.
+    pub fn planes(self) -> usize {
+        unsafe { ffw_pixel_format_get_planes(self.into_raw()) as usize }
+    }
+
+    /// Get the number of components (e.g. 3 for `yuv420p` and 4 for
+    /// `rgba`).
+    pub fn components(self) -> usize {
+        unsafe { ffw_pixel_format_get_components(self.into_raw()) as usize }
+    }
+
+    /// Get the chroma subsampling as binary logarithms of the horizontal
+    /// and vertical subsampling factors (e.g. `(1, 1)` for `yuv420p` and
+    /// `(1, 0)` for `yuv422p`).
+    pub fn chroma_subsampling(self) -> (u32, u32) {
+        unsafe {
+            let w = ffw_pixel_format_get_log2_chroma_w(self.into_raw());
+            let h = ffw_pixel_format_get_log2_chroma_h(self.into_raw());
+
+            (w as u32, h as u32)
+        }
+    }
+
+    /// Get the maximum number of bits per component (e.g. 10 for
+    /// `yuv420p10le`).
+    pub fn bit_depth(self) -> u32 {
+        unsafe { ffw_pixel_format_get_bit_depth(self.into_raw()) as u32 }
+    }
+
+    /// Get the average number of bits per pixel (e.g. 12 for `yuv420p`).
+    pub fn bits_per_pixel(self) -> u32 {
+        unsafe { ffw_pixel_format_get_bits_per_pixel(self.into_raw()) as u32 }
+    }
+
+    /// Check if the format is an opaque hardware format (e.g. `vaapi` or
+    /// `cuda`).
+    pub fn is_hardware(self) -> bool {
+        self.has_flag(0)
+    }
+
+    /// Check if the format is an RGB-like format (including e.g. `gbrp`).
+    pub fn is_rgb(self) -> bool {
+        self.has_flag(1)
+    }
+
+    /// Check if the format is planar, i.e. at least one component is
+    /// stored in a separate plane (e.g. `yuv420p` and `nv12`).
+    pub fn is_planar(self) -> bool {
+        self.has_flag(2)
+    }
+
+    /// Check if the format has an alpha channel.
+    pub fn has_alpha(self) -> bool {
+        self.has_flag(3)
+    }
+
+    /// Check if the components are floating point values.
+    pub fn is_float(self) -> bool {
+        self.has_flag(4)
+    }
+
+    /// Check if the format uses a palette.
+    pub fn is_palette(self) -> bool {
+        self.has_flag(5)
+    }
+
+    /// Check if the components are stored in the big-endian order.
+    pub fn is_big_endian(self) -> bool {
+        self.has_flag(6)
+    }
+
+    /// Check if the pixels are packed bitwise (e.g. `monob`).
+    pub fn is_bitstream(self) -> bool {
+        self.has_flag(7)
+    }
+
+    /// Get the loss of a conversion from this format into a given format.
+    pub fn conversion_loss(self, target: Self) -> PixelFormatLoss {
+        let loss = unsafe {
+            ffw_pixel_format_get_loss(target.into_raw(), self.into_raw(), self.has_alpha() as _)
+        };
+
+        PixelFormatLoss(loss as u32)
+    }
+
+    /// Choose the best conversion target out of given formats (e.g. out of
+    /// formats supported by an encoder) and return it along with the
+    /// conversion loss. The method returns `None` if there are no
+    /// candidates.
+    pub fn best_conversion_target(self, candidates: &[Self]) -> Option<(Self, PixelFormatLoss)> {
+        let candidates = candidates.iter().map(|f| f.into_raw()).collect::<Vec<_>>();
+
+        let mut loss = 0;
+
+        let res = unsafe {
+            ffw_pixel_format_find_best(
+                candidates.as_ptr(),
+                candidates.len(),
+                self.into_raw(),
+                self.has_alpha() as _,
+                &mut loss,
+            )
+        };
+
+        if unsafe { ffw_pixel_format_is_none(res) != 0 } {
+            None
+        } else {
+            Some((Self(res), PixelFormatLoss(loss as u32)))
+        }
+    }
+
+    /// Check if a given descriptor flag is set.
+    fn has_flag(self, flag: c_int) -> bool {
+        unsafe { ffw_pixel_format_has_flag(self.into_raw(), flag) != 0 }
+    }
+}
+
+/// Information lost by a pixel format conversion.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct PixelFormatLoss(u32);
+
+impl PixelFormatLoss {
+    /// Chroma resolution.
+    pub const RESOLUTION: Self = Self(0x0001);
+    /// Bit depth.
+    pub const DEPTH: Self = Self(0x0002);
+    /// Color space (e.g. RGB to YUV).
+    pub const COLORSPACE: Self = Self(0x0004);
+    /// Alpha channel.
+    pub const ALPHA: Self = Self(0x0008);
+    /// Color quantization (e.g. conversion into a palette format).
+    pub const COLORQUANT: Self = Self(0x0010);
+    /// Chroma (i.e. conversion into a grayscale format).
+    pub const CHROMA: Self = Self(0x0020);
+
+    /// Get the raw loss flags.
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Check if the conversion is lossless.
+    pub const fn is_lossless(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Check if all given flags are set.
+    pub const fn contains(self, other: Self) -> bool {
+        (self.0 & other.0) == other.0
+    }
 }
 
 impl FromStr for PixelFormat {
@@ -141,6 +420,212 @@ pub fn get_pixel_format(name: &str) -> PixelFormat {
     PixelFormat::from_str(name).unwrap()
 }
 
+/// Frame side data type.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FrameSideDataType {
+    /// ATSC A53 Part 4 closed captions (CEA-608/708).
+    A53ClosedCaptions,
+    /// Display matrix (3x3 transformation matrix).
+    DisplayMatrix,
+    /// HDR mastering display metadata (SMPTE ST 2086).
+    MasteringDisplayMetadata,
+    /// HDR content light level (MaxCLL and MaxFALL).
+    ContentLightLevel,
+    /// ICC profile.
+    IccProfile,
+    /// HDR10+ dynamic metadata (SMPTE ST 2094-40).
+    DynamicHdrPlus,
+    /// SMPTE ST 12-1 timecodes.
+    S12mTimecode,
+    /// User data unregistered SEI messages.
+    SeiUnregistered,
+    /// Film grain parameters (e.g. AV1 film grain synthesis parameters).
+    FilmGrainParams,
+    /// Other side data type given by its raw value. It is used for side
+    /// data types not covered by the variants above, so that the side data
+    /// can be passed through.
+    Other(i32),
+}
+
+impl FrameSideDataType {
+    /// All side data types.
+    const ALL: [Self; 9] = [
+        Self::A53ClosedCaptions,
+        Self::DisplayMatrix,
+        Self::MasteringDisplayMetadata,
+        Self::ContentLightLevel,
+        Self::IccProfile,
+        Self::DynamicHdrPlus,
+        Self::S12mTimecode,
+        Self::SeiUnregistered,
+        Self::FilmGrainParams,
+    ];
+
+    /// Create a side data type from its raw representation.
+    fn from_raw(raw: c_int) -> Self {
+        let index = unsafe { ffw_frame_side_data_type_from_raw(raw) };
+
+        if index < 0 {
+            Self::Other(raw)
+        } else {
+            Self::ALL
+                .get(index as usize)
+                .copied()
+                .unwrap_or(Self::Other(raw))
+        }
+    }
+
+    /// Get the raw value (or None if the type is not supported by the
+    /// FFmpeg version in use).
+    fn into_raw(self) -> Option<c_int> {
+        if let Self::Other(raw) = self {
+            return Some(raw);
+        }
+
+        let index = Self::ALL.iter().position(|t| *t == self).unwrap();
+
+        let raw = unsafe { ffw_frame_side_data_type_to_raw(index as c_int) };
+
+        if raw < 0 {
+            None
+        } else {
+            Some(raw)
+        }
+    }
+
+    /// Check if the side data type is supported by the FFmpeg version in
+    /// use.
+    pub fn is_supported(self) -> bool {
+        self.into_raw().is_some()
+    }
+
+    /// Get the raw value or an error if the type is not supported.
+    fn try_into_raw(self) -> Result<c_int, Error> {
+        self.into_raw()
+            .ok_or_else(|| Error::new("frame side data type not supported by this FFmpeg version"))
+    }
+}
+
+/// Reference to the frame side data.
+pub struct FrameSideDataRef(());
+
+impl FrameSideDataRef {
+    /// Create a frame side data from its raw representation.
+    unsafe fn from_raw_ptr<'a>(ptr: *const c_void) -> &'a Self {
+        unsafe { &*(ptr as *const Self) }
+    }
+
+    /// Get raw pointer.
+    fn as_ptr(&self) -> *const c_void {
+        self as *const Self as _
+    }
+
+    /// Get data.
+    pub fn data(&self) -> &[u8] {
+        unsafe {
+            let data = ffw_frame_side_data_get_data(self.as_ptr());
+            let len = ffw_frame_side_data_get_size(self.as_ptr());
+
+            if data.is_null() {
+                &[]
+            } else {
+                slice::from_raw_parts(data, len)
+            }
+        }
+    }
+
+    /// Get data type. `FrameSideDataType::Other` is returned for side data
+    /// types that are not covered by the other variants.
+    pub fn data_type(&self) -> FrameSideDataType {
+        let raw = unsafe { ffw_frame_side_data_get_type(self.as_ptr()) };
+
+        FrameSideDataType::from_raw(raw)
+    }
+
+    /// Get name of the side data type.
+    pub fn name(&self) -> &'static str {
+        unsafe {
+            let raw = ffw_frame_side_data_get_type(self.as_ptr());
+            let ptr = ffw_frame_side_data_type_name(raw);
+
+            if ptr.is_null() {
+                return "unknown";
+            }
+
+            CStr::from_ptr(ptr as _).to_str().unwrap_or("unknown")
+        }
+    }
+}
+
+/// Iterator over frame side data.
+pub struct FrameSideDataIter<'a> {
+    frame: *const c_void,
+    index: usize,
+    len: usize,
+    phantom: PhantomData<&'a ()>,
+}
+
+impl FrameSideDataIter<'_> {
+    /// Create a new side data iterator for a given frame.
+    unsafe fn new(frame: *const c_void) -> Self {
+        Self {
+            frame,
+            index: 0,
+            len: ffw_frame_get_nb_side_data(frame),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a> Iterator for FrameSideDataIter<'a> {
+    type Item = &'a FrameSideDataRef;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index == self.len {
+            return None;
+        }
+
+        let side_data = unsafe {
+            FrameSideDataRef::from_raw_ptr(ffw_frame_get_side_data(self.frame, self.index))
+        };
+
+        self.index += 1;
+
+        Some(side_data)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let hint = self.len - self.index;
+        (hint, Some(hint))
+    }
+}
+
+impl ExactSizeIterator for FrameSideDataIter<'_> {}
+
+/// Add side data of a given type to a given frame.
+fn add_side_data(
+    frame: *mut c_void,
+    data_type: FrameSideDataType,
+    data: &[u8],
+) -> Result<(), Error> {
+    let raw = data_type.try_into_raw()?;
+
+    let ret = unsafe { ffw_frame_add_side_data(frame, raw, data.as_ptr(), data.len()) };
+
+    if ret < 0 {
+        Err(Error::from_raw_error_code(ret))
+    } else {
+        Ok(())
+    }
+}
+
+/// Remove all side data of a given type from a given frame.
+fn remove_side_data(frame: *mut c_void, data_type: FrameSideDataType) {
+    if let Some(raw) = data_type.into_raw() {
+        unsafe { ffw_frame_remove_side_data(frame, raw) }
+    }
+}
+
 /// Picture plane (i.e. a planar array of pixel components).
 pub struct Plane<'a> {
     frame: *mut c_void,
@@ -413,10 +898,64 @@ impl VideoFrameMut {
     }
 
     /// Set presentation timestamp.
-    pub fn with_pts(self, pts: Timestamp) -> Self {
+    pub fn with_pts(mut self, pts: Timestamp) -> Self {
+        self.set_pts(pts);
+        self
+    }
+
+    /// Set presentation timestamp (rescaled into the frame time base).
+    pub(crate) fn set_pts(&mut self, pts: Timestamp) {
         let pts = pts.with_time_base(self.time_base);
 
         unsafe { ffw_frame_set_pts(self.ptr, pts.timestamp()) }
+    }
+
+    /// Get the sample (pixel) aspect ratio (if known).
+    pub fn sample_aspect_ratio(&self) -> Option<Rational> {
+        let mut num = 0;
+        let mut den = 0;
+
+        unsafe { ffw_frame_get_sample_aspect_ratio(self.ptr, &mut num, &mut den) };
+
+        time::positive_rational(num, den)
+    }
+
+    /// Set the sample (pixel) aspect ratio.
+    pub fn with_sample_aspect_ratio(self, sar: Rational) -> Self {
+        unsafe { ffw_frame_set_sample_aspect_ratio(self.ptr, sar.num(), sar.den()) };
+        self
+    }
+
+    /// Check if the frame contains interlaced content.
+    pub fn is_interlaced(&self) -> bool {
+        unsafe { ffw_frame_is_interlaced(self.ptr) != 0 }
+    }
+
+    /// Check if the top field is displayed first. The value is meaningful
+    /// only for interlaced frames.
+    pub fn is_top_field_first(&self) -> bool {
+        unsafe { ffw_frame_is_top_field_first(self.ptr) != 0 }
+    }
+
+    /// Get the field order of the frame (either progressive, top field first
+    /// or bottom field first).
+    pub fn field_order(&self) -> FieldOrder {
+        if !self.is_interlaced() {
+            FieldOrder::Progressive
+        } else if self.is_top_field_first() {
+            FieldOrder::TopFirst
+        } else {
+            FieldOrder::BottomFirst
+        }
+    }
+
+    /// Set the interlacing flags of the frame according to a given field
+    /// order.
+    pub fn with_field_order(self, field_order: FieldOrder) -> Self {
+        let interlaced = field_order.is_interlaced();
+        let tff = field_order.is_top_field_first().unwrap_or(false);
+
+        unsafe { ffw_frame_set_interlaced(self.ptr, interlaced as _, tff as _) };
 
         self
     }
@@ -455,6 +994,35 @@ impl VideoFrameMut {
         PlanesMut::from(self)
     }
 
+    /// Get an iterator over all side data of the frame.
+    pub fn side_data(&self) -> FrameSideDataIter<'_> {
+        unsafe { FrameSideDataIter::new(self.ptr) }
+    }
+
+    /// Get side data of a given type.
+    pub fn get_side_data(&self, data_type: FrameSideDataType) -> Option<&FrameSideDataRef> {
+        self.side_data().find(|sd| sd.data_type() == data_type)
+    }
+
+    /// Add side data of a given type. The data is copied.
+    pub fn add_side_data(
+        &mut self,
+        data_type: FrameSideDataType,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        add_side_data(self.ptr, data_type, data)
+    }
+
+    /// Remove all side data of a given type.
+    pub fn remove_side_data(&mut self, data_type: FrameSideDataType) {
+        remove_side_data(self.ptr, data_type)
+    }
+
+    /// Get mutable raw pointer.
+    pub(crate) fn as_mut_ptr(&mut self) -> *mut c_void {
+        self.ptr
+    }
+
     /// Make the frame immutable.
     pub fn freeze(mut self) -> VideoFrame {
         let ptr = self.ptr;
@@ -489,6 +1057,135 @@ impl VideoFrame {
         Self { ptr, time_base }
     }
 
+    /// Create a video frame referencing external picture planes without
+    /// copying the data (e.g. frames produced by a camera SDK or a GPU
+    /// readback). The free callback is called exactly once when the frame
+    /// and all its clones are dropped (or immediately if the frame cannot be
+    /// created). The time base of the frame will be in microseconds.
+    ///
+    /// The frame is read-only. Converting it into `VideoFrameMut` will copy
+    /// the data.
+    ///
+    /// # Safety
+    /// Every plane pointer must be valid for reads of `line_size * lines`
+    /// bytes, where `lines` is the number of lines of the plane given by the
+    /// pixel format and the frame height. The memory must stay valid and
+    /// must not be modified until the free callback is called.
+    pub unsafe fn from_external_buffer<F>(
+        pixel_format: PixelFormat,
+        width: usize,
+        height: usize,
+        planes: &[*const u8],
+        line_sizes: &[usize],
+        free_callback: F,
+    ) -> Result<Self, Error>
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        if planes.len() != line_sizes.len() {
+            free_callback();
+
+            return Err(Error::new("number of planes and line sizes do not match"));
+        }
+
+        let callback: FreeCallback = Box::new(free_callback);
+
+        let opaque = Box::into_raw(Box::new(callback));
+
+        let mut ptr = ptr::null_mut();
+
+        let ret = ffw_frame_new_external(
+            pixel_format.into_raw(),
+            width as _,
+            height as _,
+            planes.as_ptr(),
+            line_sizes.as_ptr(),
+            planes.len(),
+            free_external_buffer,
+            opaque as _,
+            &mut ptr,
+        );
+
+        // the callback has been already called if the frame cannot be
+        // created
+        if ret < 0 {
+            return Err(Error::from_raw_error_code(ret));
+        }
+
+        let res = Self {
+            ptr,
+            time_base: TimeBase::MICROSECONDS,
+        };
+
+        Ok(res)
+    }
+
+    /// Create a video frame referencing picture planes stored in a given
+    /// buffer without copying the data. The planes are given as
+    /// `(offset, line_size)` pairs. The buffer is dropped when the frame and
+    /// all its clones are dropped. The time base of the frame will be in
+    /// microseconds.
+    pub fn from_buffer<T>(
+        pixel_format: PixelFormat,
+        width: usize,
+        height: usize,
+        buffer: T,
+        planes: &[(usize, usize)],
+    ) -> Result<Self, Error>
+    where
+        T: AsRef<[u8]> + Send + 'static,
+    {
+        // make sure that the data will not move
+        let buffer = Box::new(buffer);
+
+        let data = buffer.as_ref().as_ref();
+        let len = data.len();
+
+        if planes.iter().any(|&(offset, _)| offset >= len) {
+            return Err(Error::new("plane offset out of buffer bounds"));
+        }
+
+        let pointers = planes
+            .iter()
+            .map(|&(offset, _)| data[offset..].as_ptr())
+            .collect::<Vec<_>>();
+
+        let line_sizes = planes
+            .iter()
+            .map(|&(_, line_size)| line_size)
+            .collect::<Vec<_>>();
+
+        let frame = unsafe {
+            Self::from_external_buffer(
+                pixel_format,
+                width,
+                height,
+                &pointers,
+                &line_sizes,
+                move || drop(buffer),
+            )?
+        };
+
+        // the number of lines is known only after the frame is created
+        let valid = frame
+            .planes()
+            .iter()
+            .zip(planes)
+            .all(|(plane, &(offset, line_size))| {
+                line_size
+                    .checked_mul(plane.line_count())
+                    .and_then(|size| size.checked_add(offset))
+                    .map(|end| end <= len)
+                    .unwrap_or(false)
+            });
+
+        if valid {
+            Ok(frame)
+        } else {
+            Err(Error::new("plane out of buffer bounds"))
+        }
+    }
+
     /// Get frame pixel format.
     pub fn pixel_format(&self) -> PixelFormat {
         unsafe { PixelFormat::from_raw(ffw_frame_get_format(self.ptr)) }
@@ -551,6 +1248,124 @@ impl VideoFrame {
         self
     }
 
+    /// Get the sample (pixel) aspect ratio (if known).
+    pub fn sample_aspect_ratio(&self) -> Option<Rational> {
+        let mut num = 0;
+        let mut den = 0;
+
+        unsafe { ffw_frame_get_sample_aspect_ratio(self.ptr, &mut num, &mut den) };
+
+        time::positive_rational(num, den)
+    }
+
+    /// Set the sample (pixel) aspect ratio.
+    pub fn with_sample_aspect_ratio(self, sar: Rational) -> Self {
+        unsafe { ffw_frame_set_sample_aspect_ratio(self.ptr, sar.num(), sar.den()) };
+        self
+    }
+
+    /// Get the number of pixels to be cropped from the top, bottom, left
+    /// and right edge of the frame (in this order). Decoders crop the frames
+    /// on their own unless this is disabled using
+    /// `VideoDecoderBuilder::apply_cropping()`.
+    pub fn crop_fields(&self) -> (usize, usize, usize, usize) {
+        let mut top = 0;
+        let mut bottom = 0;
+        let mut left = 0;
+        let mut right = 0;
+
+        unsafe { ffw_frame_get_crop(self.ptr, &mut top, &mut bottom, &mut left, &mut right) };
+
+        (top, bottom, left, right)
+    }
+
+    /// Get the number of pixels to be cropped from the top edge.
+    pub fn crop_top(&self) -> usize {
+        self.crop_fields().0
+    }
+
+    /// Get the number of pixels to be cropped from the bottom edge.
+    pub fn crop_bottom(&self) -> usize {
+        self.crop_fields().1
+    }
+
+    /// Get the number of pixels to be cropped from the left edge.
+    pub fn crop_left(&self) -> usize {
+        self.crop_fields().2
+    }
+
+    /// Get the number of pixels to be cropped from the right edge.
+    pub fn crop_right(&self) -> usize {
+        self.crop_fields().3
+    }
+
+    /// Set the number of pixels to be cropped from the top, bottom, left and
+    /// right edge. The frame is not cropped until `apply_cropping()` is
+    /// called.
+    pub fn with_crop(self, top: usize, bottom: usize, left: usize, right: usize) -> Self {
+        unsafe { ffw_frame_set_crop(self.ptr, top, bottom, left, right) };
+        self
+    }
+
+    /// Crop the frame according to its crop fields. Only the plane pointers
+    /// and the frame dimensions are adjusted, no data are copied. The
+    /// cropping is exact, so the plane pointers may not be aligned
+    /// afterwards. The crop fields are reset to zero. The method fails if
+    /// the crop fields are out of the frame bounds or if the frame is a
+    /// hardware frame or uses a bitstream pixel format.
+    pub fn apply_cropping(self) -> Result<Self, Error> {
+        let format = self.pixel_format();
+
+        // FFmpeg would crop only the right and bottom edge of such frames
+        if format.is_hardware() || format.is_bitstream() {
+            return Err(Error::new(
+                "unable to crop the frame, unsupported pixel format",
+            ));
+        }
+
+        let ret = unsafe { ffw_frame_apply_cropping(self.ptr) };
+
+        if ret < 0 {
+            return Err(Error::from_raw_error_code(ret));
+        }
+
+        Ok(self)
+    }
+
+    /// Check if the frame contains interlaced content.
+    pub fn is_interlaced(&self) -> bool {
+        unsafe { ffw_frame_is_interlaced(self.ptr) != 0 }
+    }
+
+    /// Check if the top field is displayed first. The value is meaningful
+    /// only for interlaced frames.
+    pub fn is_top_field_first(&self) -> bool {
+        unsafe { ffw_frame_is_top_field_first(self.ptr) != 0 }
+    }
+
+    /// Get the field order of the frame (either progressive, top field first
+    /// or bottom field first).
+    pub fn field_order(&self) -> FieldOrder {
+        if !self.is_interlaced() {
+            FieldOrder::Progressive
+        } else if self.is_top_field_first() {
+            FieldOrder::TopFirst
+        } else {
+            FieldOrder::BottomFirst
+        }
+    }
+
+    /// Set the interlacing flags of the frame according to a given field
+    /// order.
+    pub fn with_field_order(self, field_order: FieldOrder) -> Self {
+        let interlaced = field_order.is_interlaced();
+        let tff = field_order.is_top_field_first().unwrap_or(false);
+
+        unsafe { ffw_frame_set_interlaced(self.ptr, interlaced as _, tff as _) };
+
+        self
+    }
+
     pub fn duration(&self) -> Option<Duration> {
         let duration = unsafe { ffw_frame_get_duration(self.ptr) };
 
@@ -573,16 +1388,183 @@ impl VideoFrame {
         unsafe { PictureType::from_raw(ffw_frame_get_picture_type(self.ptr)) }
     }
 
+    /// Set picture type. Encoders use it as a hint, e.g. setting
+    /// `PictureType::I` forces a keyframe.
+    pub fn with_picture_type(self, picture_type: PictureType) -> Self {
+        unsafe { ffw_frame_set_picture_type(self.ptr, picture_type as c_int) };
+        self
+    }
+
+    /// Set picture type. Encoders use it as a hint, e.g. setting
+    /// `PictureType::I` forces a keyframe.
+    pub fn set_picture_type(&mut self, picture_type: PictureType) {
+        unsafe { ffw_frame_set_picture_type(self.ptr, picture_type as c_int) };
+    }
+
+    /// Check if the frame has been flagged as corrupt by the decoder.
+    pub fn is_corrupt(&self) -> bool {
+        unsafe { ffw_frame_is_corrupt(self.ptr) != 0 }
+    }
+
+    /// Get an iterator over all side data of the frame.
+    pub fn side_data(&self) -> FrameSideDataIter<'_> {
+        unsafe { FrameSideDataIter::new(self.ptr) }
+    }
+
+    /// Get side data of a given type.
+    pub fn get_side_data(&self, data_type: FrameSideDataType) -> Option<&FrameSideDataRef> {
+        self.side_data().find(|sd| sd.data_type() == data_type)
+    }
+
+    /// Add side data of a given type. The data is copied. Side data are not
+    /// shared with clones of the frame.
+    pub fn add_side_data(
+        &mut self,
+        data_type: FrameSideDataType,
+        data: &[u8],
+    ) -> Result<(), Error> {
+        add_side_data(self.ptr, data_type, data)
+    }
+
+    /// Remove all side data of a given type.
+    pub fn remove_side_data(&mut self, data_type: FrameSideDataType) {
+        remove_side_data(self.ptr, data_type)
+    }
+
+    /// Get closed captions (raw A53 `cc_data` triplets) attached to the
+    /// frame (if any).
+    pub fn closed_captions(&self) -> Option<&[u8]> {
+        self.get_side_data(FrameSideDataType::A53ClosedCaptions)
+            .map(|sd| sd.data())
+    }
+
+    /// Attach given closed captions (raw A53 `cc_data` triplets) to the
+    /// frame. Closed captions already attached to the frame are replaced.
+    pub fn set_closed_captions(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.remove_side_data(FrameSideDataType::A53ClosedCaptions);
+        self.add_side_data(FrameSideDataType::A53ClosedCaptions, data)
+    }
+
+    /// Get all user data unregistered SEI messages attached to the frame.
+    pub fn unregistered_sei(&self) -> Vec<UnregisteredSei> {
+        self.side_data()
+            .filter(|sd| sd.data_type() == FrameSideDataType::SeiUnregistered)
+            .filter_map(|sd| UnregisteredSei::from_side_data(sd.data()))
+            .collect()
+    }
+
+    /// Attach a given user data unregistered SEI message to the frame.
+    pub fn add_unregistered_sei(&mut self, sei: &UnregisteredSei) -> Result<(), Error> {
+        self.add_side_data(FrameSideDataType::SeiUnregistered, &sei.to_side_data())
+    }
+
+    /// Get SMPTE ST 12-1 timecodes (in the binary SMPTE representation)
+    /// attached to the frame. There are at most three timecodes (one per
+    /// field or frame repetition).
+    pub fn s12m_timecodes(&self) -> Vec<u32> {
+        self.get_side_data(FrameSideDataType::S12mTimecode)
+            .map(|sd| parse_s12m_timecodes(sd.data()))
+            .unwrap_or_default()
+    }
+
+    /// Get the timecode of the frame (i.e. the first S12M timecode attached
+    /// to the frame). The frame rate is needed in order to decode frame
+    /// numbers above 30 fps.
+    pub fn timecode(&self, frame_rate: Rational) -> Option<Timecode> {
+        self.s12m_timecodes()
+            .first()
+            .map(|tc| Timecode::from_smpte(*tc, frame_rate))
+    }
+
+    /// Attach a given timecode to the frame as S12M timecode side data.
+    /// Timecodes already attached to the frame are replaced. Encoders
+    /// supporting it (e.g. the NVENC encoders with the `s12m_tc` option)
+    /// embed them into the output as time code SEI.
+    pub fn set_timecode(&mut self, timecode: Timecode, frame_rate: Rational) -> Result<(), Error> {
+        let mut data = Vec::with_capacity(16);
+
+        // the number of timecodes followed by (at most) three timecodes
+        for word in &[1, timecode.to_smpte(frame_rate), 0, 0] {
+            data.extend_from_slice(&word.to_ne_bytes());
+        }
+
+        self.remove_side_data(FrameSideDataType::S12mTimecode);
+        self.add_side_data(FrameSideDataType::S12mTimecode, &data)
+    }
+
+    /// Copy side data of a given type (or all side data if the type is
+    /// None) from a given frame. Existing side data of the copied types are
+    /// replaced. This can be used to carry e.g. HDR metadata or film grain
+    /// parameters from decoded frames to frames passed to an encoder.
+    pub fn copy_side_data(
+        &mut self,
+        src: &VideoFrame,
+        data_type: Option<FrameSideDataType>,
+    ) -> Result<(), Error> {
+        let raw = match data_type {
+            Some(t) => t.try_into_raw()?,
+            None => -1,
+        };
+
+        let ret = unsafe { ffw_frame_copy_side_data(self.ptr, src.ptr, raw) };
+
+        if ret < 0 {
+            Err(Error::from_raw_error_code(ret))
+        } else {
+            Ok(())
+        }
+    }
+
     /// Get raw pointer.
     pub(crate) fn as_ptr(&self) -> *const c_void {
         self.ptr
     }
 
+    /// Check if the frame data can be modified in place, i.e. the data is
+    /// not shared with any other frame (e.g. a reference frame kept by the
+    /// decoder).
+    pub fn is_writable(&self) -> bool {
+        unsafe { ffw_frame_is_writable(self.ptr) > 0 }
+    }
+
+    /// Make sure that the frame data is not shared with any other frame. The
+    /// data is copied only if it is shared. Converting the frame into
+    /// `VideoFrameMut` using `try_into_mut()` is guaranteed to succeed
+    /// afterwards.
+    pub fn make_writable(&mut self) -> Result<(), Error> {
+        let ret = unsafe { ffw_frame_make_writable(self.ptr) };
+
+        if ret < 0 {
+            Err(Error::from_raw_error_code(ret))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Create a copy of the frame that does not share any data with this
+    /// frame. Unlike `clone()`, the method always copies the data. Frame
+    /// properties and side data are copied as well.
+    pub fn clone_deep(&self) -> Result<Self, Error> {
+        let mut ptr = ptr::null_mut();
+
+        let ret = unsafe { ffw_frame_clone_deep(self.ptr, &mut ptr) };
+
+        if ret < 0 {
+            return Err(Error::from_raw_error_code(ret));
+        }
+
+        let res = Self {
+            ptr,
+            time_base: self.time_base,
+        };
+
+        Ok(res)
+    }
+
     /// Try to make this frame mutable. Returns VideoFrameMut if it can be made
     /// into mutable without copying the data, otherwise returns VideoFrame.
     pub fn try_into_mut(self) -> Result<VideoFrameMut, VideoFrame> {
-        let res = unsafe { ffw_frame_is_writable(self.ptr) };
-        if res > 0 {
+        if self.is_writable() {
             Ok(self.into_mut())
         } else {
             Err(self)
@@ -592,9 +1574,7 @@ impl VideoFrame {
     /// Make this frame mutable. This will copy the data if it is not already
     /// mutable.
     pub fn into_mut(mut self) -> VideoFrameMut {
-        let res = unsafe { ffw_frame_make_writable(self.ptr) };
-
-        if res < 0 {
+        if self.make_writable().is_err() {
             panic!("unable to make the frame mutable");
         }
 
@@ -609,6 +1589,50 @@ impl VideoFrame {
     }
 }
 
+#[cfg(feature = "ndarray")]
+impl VideoFrame {
+    /// Convert the frame into an RGB array of the shape
+    /// `(height, width, 3)` (i.e. the HWC layout). Frames of any pixel
+    /// format are converted into RGB automatically.
+    pub fn to_ndarray(&self) -> Result<ndarray::Array3<u8>, Error> {
+        let frame = convert(self, get_pixel_format("rgb24"))?;
+
+        let pixels = packed_pixels(&frame, 3);
+
+        let res = ndarray::Array3::from_shape_vec((frame.height(), frame.width(), 3), pixels)
+            .map_err(|_| Error::new("invalid frame size"))?;
+
+        Ok(res)
+    }
+
+    /// Create a video frame from an array of the shape
+    /// `(height, width, channels)` (i.e. the HWC layout). Arrays with 1
+    /// channel (grayscale), 3 channels (RGB) and 4 channels (RGBA) are
+    /// supported.
+    pub fn from_ndarray<S>(array: &ndarray::ArrayBase<S, ndarray::Ix3>) -> Result<Self, Error>
+    where
+        S: ndarray::Data<Elem = u8>,
+    {
+        let (height, width, channels) = array.dim();
+
+        let pixel_format = match channels {
+            1 => "gray",
+            3 => "rgb24",
+            4 => "rgba",
+            _ => return Err(Error::new("unsupported number of channels")),
+        };
+
+        let pixels = match array.as_slice() {
+            Some(pixels) => pixels.to_vec(),
+            None => array.iter().copied().collect(),
+        };
+
+        let res = from_packed_pixels(pixel_format, channels, width, height, &pixels);
+
+        Ok(res)
+    }
+}
+
 impl Clone for VideoFrame {
     fn clone(&self) -> Self {
         let ptr = unsafe { ffw_frame_clone(self.ptr) };
@@ -632,3 +1656,269 @@ impl Drop for VideoFrame {
 
 unsafe impl Send for VideoFrame {}
 unsafe impl Sync for VideoFrame {}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use super::{get_pixel_format, PixelFormat, PixelFormatLoss, VideoFrame, VideoFrameMut};
+
+    #[test]
+    fn test_pixel_format_descriptor() {
+        let yuv420p = get_pixel_format("yuv420p");
+
+        assert_eq!(yuv420p.planes(), 3);
+        assert_eq!(yuv420p.components(), 3);
+        assert_eq!(yuv420p.chroma_subsampling(), (1, 1));
+        assert_eq!(yuv420p.bit_depth(), 8);
+        assert_eq!(yuv420p.bits_per_pixel(), 12);
+        assert!(yuv420p.is_planar());
+        assert!(!yuv420p.is_rgb());
+        assert!(!yuv420p.has_alpha());
+
+        let rgba = get_pixel_format("rgba");
+
+        assert_eq!(rgba.planes(), 1);
+        assert_eq!(rgba.components(), 4);
+        assert_eq!(rgba.chroma_subsampling(), (0, 0));
+        assert_eq!(rgba.bits_per_pixel(), 32);
+        assert!(!rgba.is_planar());
+        assert!(rgba.is_rgb());
+        assert!(rgba.has_alpha());
+
+        assert_eq!(get_pixel_format("yuv422p").chroma_subsampling(), (1, 0));
+        assert_eq!(get_pixel_format("yuv420p10le").bit_depth(), 10);
+
+        assert!(get_pixel_format("vaapi").is_hardware());
+        assert!(get_pixel_format("grayf32le").is_float());
+        assert!(get_pixel_format("pal8").is_palette());
+        assert!(get_pixel_format("rgb48be").is_big_endian());
+        assert!(get_pixel_format("monob").is_bitstream());
+        assert!(!rgba.is_hardware());
+
+        assert!(PixelFormat::all().any(|format| format == yuv420p));
+    }
+
+    #[test]
+    fn test_conversion_loss() {
+        let yuv420p = get_pixel_format("yuv420p");
+
+        assert!(yuv420p.conversion_loss(yuv420p).is_lossless());
+
+        let loss = get_pixel_format("yuv444p").conversion_loss(yuv420p);
+
+        assert!(loss.contains(PixelFormatLoss::RESOLUTION));
+
+        let loss = get_pixel_format("rgba").conversion_loss(yuv420p);
+
+        assert!(loss.contains(PixelFormatLoss::ALPHA));
+        assert!(loss.contains(PixelFormatLoss::COLORSPACE));
+
+        let loss = get_pixel_format("yuv420p10le").conversion_loss(yuv420p);
+
+        assert!(loss.contains(PixelFormatLoss::DEPTH));
+
+        let loss = yuv420p.conversion_loss(get_pixel_format("gray"));
+
+        assert!(loss.contains(PixelFormatLoss::CHROMA));
+    }
+
+    #[test]
+    fn test_best_conversion_target() {
+        let candidates = [
+            get_pixel_format("yuv420p"),
+            get_pixel_format("rgba"),
+            get_pixel_format("gray"),
+        ];
+
+        let (format, loss) = get_pixel_format("rgb24")
+            .best_conversion_target(&candidates)
+            .unwrap();
+
+        assert!(format == get_pixel_format("rgba"));
+        assert!(loss.is_lossless());
+
+        let candidates = [get_pixel_format("rgb24"), get_pixel_format("yuv444p")];
+
+        let (format, loss) = get_pixel_format("yuv420p")
+            .best_conversion_target(&candidates)
+            .unwrap();
+
+        assert!(format == get_pixel_format("yuv444p"));
+        assert!(loss.is_lossless());
+
+        // a lossy conversion
+        let (format, loss) = get_pixel_format("rgba")
+            .best_conversion_target(&candidates)
+            .unwrap();
+
+        assert!(format == get_pixel_format("rgb24"));
+        assert!(loss.contains(PixelFormatLoss::ALPHA));
+
+        assert!(get_pixel_format("rgb24")
+            .best_conversion_target(&[])
+            .is_none());
+    }
+
+    #[test]
+    fn test_apply_cropping() {
+        let pixel_format = get_pixel_format("yuv420p");
+
+        let frame = VideoFrameMut::black(pixel_format, 64, 48)
+            .freeze()
+            .with_crop(4, 8, 10, 6);
+
+        assert_eq!(frame.crop_fields(), (4, 8, 10, 6));
+
+        let frame = frame.apply_cropping().unwrap();
+
+        assert_eq!(frame.width(), 48);
+        assert_eq!(frame.height(), 36);
+        assert_eq!(frame.crop_fields(), (0, 0, 0, 0));
+
+        let invalid = VideoFrameMut::black(pixel_format, 64, 48)
+            .freeze()
+            .with_crop(0, 0, 40, 40);
+
+        assert!(invalid.apply_cropping().is_err());
+    }
+
+    #[test]
+    fn test_external_buffer() {
+        let pixel_format = get_pixel_format("yuv420p");
+
+        let luma = [1u8; 16 * 8];
+        let cb = [2u8; 8 * 4];
+        let cr = [3u8; 8 * 4];
+
+        let planes = [luma.as_ptr(), cb.as_ptr(), cr.as_ptr()];
+
+        let freed = Arc::new(AtomicUsize::new(0));
+
+        let counter = freed.clone();
+
+        let frame = unsafe {
+            VideoFrame::from_external_buffer(pixel_format, 16, 8, &planes, &[16, 8, 8], move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+            })
+        }
+        .unwrap();
+
+        let clone = frame.clone();
+
+        drop(frame);
+
+        assert_eq!(freed.load(Ordering::SeqCst), 0);
+
+        {
+            let frame_planes = clone.planes();
+
+            assert_eq!(frame_planes[0].data(), &luma[..]);
+            assert_eq!(frame_planes[1].data(), &cb[..]);
+            assert_eq!(frame_planes[2].data(), &cr[..]);
+        }
+
+        // copying the frame must copy all planes
+        let copy = clone.clone().into_mut();
+
+        assert_eq!(copy.planes()[2].data(), &cr[..]);
+
+        drop(clone);
+
+        assert_eq!(freed.load(Ordering::SeqCst), 1);
+
+        drop(copy);
+
+        assert_eq!(freed.load(Ordering::SeqCst), 1);
+
+        // the callback is called even if the frame cannot be created
+        let counter = freed.clone();
+
+        let res = unsafe {
+            VideoFrame::from_external_buffer(
+                pixel_format,
+                16,
+                8,
+                &planes[..2],
+                &[16, 8],
+                move || {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                },
+            )
+        };
+
+        assert!(res.is_err());
+        assert_eq!(freed.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_copy_on_write() {
+        let pixel_format = get_pixel_format("yuv420p");
+
+        let mut frame = VideoFrameMut::black(pixel_format, 16, 8).freeze();
+
+        assert!(frame.is_writable());
+
+        let clone = frame.clone();
+
+        assert!(!frame.is_writable());
+        assert!(!clone.is_writable());
+
+        let deep = clone.clone_deep().unwrap();
+
+        assert!(deep.is_writable());
+        assert!(!clone.is_writable());
+        assert_eq!(deep.planes()[0].data(), clone.planes()[0].data());
+
+        let black = clone.planes()[0].data()[0];
+
+        frame.make_writable().unwrap();
+
+        assert!(frame.is_writable());
+
+        // the data has been copied, so the clone is not affected
+        let mut frame = frame.try_into_mut().ok().unwrap();
+
+        frame.planes_mut()[0].data_mut()[0] = 255;
+
+        assert_eq!(frame.planes()[0].data()[0], 255);
+        assert_eq!(clone.planes()[0].data()[0], black);
+
+        // the clone is not shared anymore
+        assert!(clone.is_writable());
+        assert!(clone.try_into_mut().is_ok());
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn test_ndarray() {
+        use ndarray::Array3;
+
+        let pixels = (0..18).map(|v| v * 10).collect::<Vec<u8>>();
+
+        let array = Array3::from_shape_vec((2, 3, 3), pixels.clone()).unwrap();
+
+        let frame = VideoFrame::from_ndarray(&array).unwrap();
+
+        assert!(frame.pixel_format() == get_pixel_format("rgb24"));
+        assert_eq!((frame.width(), frame.height()), (3, 2));
+
+        let array = frame.to_ndarray().unwrap();
+
+        assert_eq!(array.dim(), (2, 3, 3));
+        assert_eq!(array.as_slice(), Some(&pixels[..]));
+
+        let gray = Array3::from_shape_vec((2, 3, 1), vec![0u8; 6]).unwrap();
+
+        let frame = VideoFrame::from_ndarray(&gray).unwrap();
+
+        assert!(frame.pixel_format() == get_pixel_format("gray"));
+
+        let invalid = Array3::from_shape_vec((2, 3, 2), vec![0u8; 12]).unwrap();
+
+        assert!(VideoFrame::from_ndarray(&invalid).is_err());
+    }
+}
@@ -0,0 +1,395 @@
+//! Frame interpolation for frame rate conversion.
+
+use crate::{
+    codec::{
+        video::{filter::LazyVideoFilterGraph, VideoFrame},
+        CodecError,
+    },
+    time::Rational,
+    Error,
+};
+
+/// Interpolation mode.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum InterpolationMode {
+    /// Duplicate frames.
+    Duplicate,
+    /// Blend neighboring frames.
+    Blend,
+    /// Motion compensated interpolation.
+    MotionCompensated,
+}
+
+/// Motion compensation mode.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MotionCompensation {
+    /// Overlapped block motion compensation.
+    Overlapped,
+    /// Adaptive overlapped block motion compensation. The window weights
+    /// are adjusted according to the reliability of the motion vectors.
+    AdaptiveOverlapped,
+}
+
+/// Motion estimation mode.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MotionEstimationMode {
+    /// Bidirectional motion estimation. Motion vectors are estimated for
+    /// each source frame in both directions.
+    Bidirectional,
+    /// Bilateral motion estimation. Motion vectors are estimated directly
+    /// for the interpolated frame.
+    Bilateral,
+}
+
+/// Motion estimation (block search) algorithm.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SearchAlgorithm {
+    /// Exhaustive search.
+    Exhaustive,
+    /// Three step search.
+    ThreeStep,
+    /// Two dimensional logarithmic search.
+    TwoDimensionalLogarithmic,
+    /// New three step search.
+    NewThreeStep,
+    /// Four step search.
+    FourStep,
+    /// Diamond search.
+    Diamond,
+    /// Hexagon-based search.
+    Hexagon,
+    /// Enhanced predictive zonal search.
+    Epzs,
+    /// Uneven multi-hexagon search.
+    Umh,
+}
+
+impl SearchAlgorithm {
+    /// Get name of the algorithm as used by the `minterpolate` filter.
+    fn name(self) -> &'static str {
+        match self {
+            Self::Exhaustive => "esa",
+            Self::ThreeStep => "tss",
+            Self::TwoDimensionalLogarithmic => "tdls",
+            Self::NewThreeStep => "ntss",
+            Self::FourStep => "fss",
+            Self::Diamond => "ds",
+            Self::Hexagon => "hexbs",
+            Self::Epzs => "epzs",
+            Self::Umh => "umh",
+        }
+    }
+}
+
+/// Builder for the frame interpolator.
+pub struct FrameInterpolatorBuilder {
+    frame_rate: Rational,
+    mode: InterpolationMode,
+    motion_compensation: MotionCompensation,
+    motion_estimation: MotionEstimationMode,
+    search_algorithm: SearchAlgorithm,
+    block_size: u32,
+    search_range: u32,
+    variable_block_size: bool,
+    scene_change_threshold: Option<f64>,
+}
+
+impl FrameInterpolatorBuilder {
+    /// Create a new builder.
+    fn new() -> Self {
+        Self {
+            frame_rate: Rational::new(60, 1),
+            mode: InterpolationMode::MotionCompensated,
+            motion_compensation: MotionCompensation::Overlapped,
+            motion_estimation: MotionEstimationMode::Bilateral,
+            search_algorithm: SearchAlgorithm::Epzs,
+            block_size: 16,
+            search_range: 32,
+            variable_block_size: false,
+            scene_change_threshold: Some(10.0),
+        }
+    }
+
+    /// Set the output frame rate. The default is 60 fps.
+    pub fn frame_rate(mut self, frame_rate: Rational) -> Self {
+        self.frame_rate = frame_rate;
+        self
+    }
+
+    /// Set the interpolation mode. The default is
+    /// `InterpolationMode::MotionCompensated`.
+    pub fn mode(mut self, mode: InterpolationMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Set the motion compensation mode. The default is
+    /// `MotionCompensation::Overlapped`.
+    pub fn motion_compensation(mut self, mode: MotionCompensation) -> Self {
+        self.motion_compensation = mode;
+        self
+    }
+
+    /// Set the motion estimation mode. The default is
+    /// `MotionEstimationMode::Bilateral`.
+    pub fn motion_estimation(mut self, mode: MotionEstimationMode) -> Self {
+        self.motion_estimation = mode;
+        self
+    }
+
+    /// Set the block search algorithm. The default is
+    /// `SearchAlgorithm::Epzs`.
+    pub fn search_algorithm(mut self, algorithm: SearchAlgorithm) -> Self {
+        self.search_algorithm = algorithm;
+        self
+    }
+
+    /// Set the macroblock size in pixels. The default is 16.
+    pub fn block_size(mut self, size: u32) -> Self {
+        self.block_size = size;
+        self
+    }
+
+    /// Set the motion search range in pixels. The default is 32.
+    pub fn search_range(mut self, range: u32) -> Self {
+        self.search_range = range;
+        self
+    }
+
+    /// Enable or disable variable size block motion compensation. It is
+    /// disabled by default.
+    pub fn variable_block_size(mut self, enabled: bool) -> Self {
+        self.variable_block_size = enabled;
+        self
+    }
+
+    /// Set the scene change detection threshold (0 - 100). Frames at scene
+    /// changes are duplicated instead of being interpolated. The default is
+    /// 10. Use None to disable the scene change detection.
+    pub fn scene_change_threshold(mut self, threshold: Option<f64>) -> Self {
+        self.scene_change_threshold = threshold;
+        self
+    }
+
+    /// Build the frame interpolator.
+    pub fn build(self) -> FrameInterpolator {
+        FrameInterpolator {
+            filters: self.filters(),
+            graph: LazyVideoFilterGraph::new(),
+        }
+    }
+
+    /// Get the filter graph description.
+    fn filters(&self) -> String {
+        let mode = match self.mode {
+            InterpolationMode::Duplicate => "dup",
+            InterpolationMode::Blend => "blend",
+            InterpolationMode::MotionCompensated => "mci",
+        };
+
+        let mc_mode = match self.motion_compensation {
+            MotionCompensation::Overlapped => "obmc",
+            MotionCompensation::AdaptiveOverlapped => "aobmc",
+        };
+
+        let me_mode = match self.motion_estimation {
+            MotionEstimationMode::Bidirectional => "bidir",
+            MotionEstimationMode::Bilateral => "bilat",
+        };
+
+        let scd = if let Some(threshold) = self.scene_change_threshold {
+            format!("scd=fdiff:scd_threshold={}", threshold)
+        } else {
+            String::from("scd=none")
+        };
+
+        format!(
+            "minterpolate=fps={}/{}:mi_mode={}:mc_mode={}:me_mode={}:me={}:mb_size={}:search_param={}:vsbmc={}:{}",
+            self.frame_rate.num(),
+            self.frame_rate.den(),
+            mode,
+            mc_mode,
+            me_mode,
+            self.search_algorithm.name(),
+            self.block_size,
+            self.search_range,
+            self.variable_block_size as i32,
+            scd
+        )
+    }
+}
+
+/// Frame interpolator using the `minterpolate` filter. It can be used for
+/// frame rate up-conversion (e.g. 30 to 60 fps). The filter graph is created
+/// when the first frame is pushed. The time base of the output frames is
+/// given by the output frame rate. Note that the motion compensated
+/// interpolation is slow.
+///
+/// # Interpolator operation
+/// 1. Push a video frame to the interpolator.
+/// 2. Take all frames from the interpolator until you get None.
+/// 3. If there are more frames to be processed, continue with 1.
+/// 4. Flush the interpolator.
+/// 5. Take all frames from the interpolator until you get None.
+pub struct FrameInterpolator {
+    filters: String,
+    graph: LazyVideoFilterGraph,
+}
+
+impl FrameInterpolator {
+    /// Get a builder for the frame interpolator.
+    pub fn builder() -> FrameInterpolatorBuilder {
+        FrameInterpolatorBuilder::new()
+    }
+
+    /// Create a new frame interpolator with the default settings.
+    pub fn new() -> Self {
+        Self::builder().build()
+    }
+
+    /// Push a given frame to the interpolator.
+    ///
+    /// # Panics
+    /// The method panics if the operation is not expected (i.e. another
+    /// operation needs to be done).
+    pub fn push(&mut self, frame: VideoFrame) -> Result<(), Error> {
+        self.try_push(frame).map_err(|err| err.unwrap_inner())
+    }
+
+    /// Push a given frame to the interpolator.
+    pub fn try_push(&mut self, frame: VideoFrame) -> Result<(), CodecError> {
+        let filters = &self.filters;
+
+        self.graph.push(frame, |_| Ok(filters.clone()))?;
+
+        Ok(())
+    }
+
+    /// Flush the interpolator.
+    ///
+    /// # Panics
+    /// The method panics if the operation is not expected (i.e. another
+    /// operation needs to be done).
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.try_flush().map_err(|err| err.unwrap_inner())
+    }
+
+    /// Flush the interpolator.
+    pub fn try_flush(&mut self) -> Result<(), CodecError> {
+        self.graph.flush()?;
+
+        Ok(())
+    }
+
+    /// Take the next frame from the interpolator (if available).
+    pub fn take(&mut self) -> Result<Option<VideoFrame>, Error> {
+        self.graph.take()
+    }
+}
+
+impl Default for FrameInterpolator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FrameInterpolator, InterpolationMode, SearchAlgorithm};
+
+    use crate::{
+        codec::video::{frame::get_pixel_format, VideoFrame, VideoFrameMut},
+        time::{Rational, TimeBase, Timestamp},
+    };
+
+    /// Push a given number of black 64x48 frames (25 fps) into a given
+    /// interpolator and collect all output frames.
+    fn interpolate(mut interpolator: FrameInterpolator, frames: i64) -> Vec<VideoFrame> {
+        let time_base = TimeBase::new(1, 25);
+
+        let frame = VideoFrameMut::black(get_pixel_format("yuv420p"), 64, 48)
+            .with_time_base(time_base)
+            .freeze();
+
+        let mut res = Vec::new();
+
+        for index in 0..frames {
+            let frame = frame.clone().with_pts(Timestamp::new(index, time_base));
+
+            interpolator.push(frame).unwrap();
+
+            while let Some(frame) = interpolator.take().unwrap() {
+                res.push(frame);
+            }
+        }
+
+        interpolator.flush().unwrap();
+
+        while let Some(frame) = interpolator.take().unwrap() {
+            res.push(frame);
+        }
+
+        res
+    }
+
+    #[test]
+    fn test_filters() {
+        let builder = FrameInterpolator::builder();
+
+        assert_eq!(
+            builder.filters(),
+            "minterpolate=fps=60/1:mi_mode=mci:mc_mode=obmc:me_mode=bilat:me=epzs:mb_size=16:search_param=32:vsbmc=0:scd=fdiff:scd_threshold=10"
+        );
+
+        let builder = FrameInterpolator::builder()
+            .frame_rate(Rational::new(60000, 1001))
+            .mode(InterpolationMode::Blend)
+            .search_algorithm(SearchAlgorithm::Umh)
+            .variable_block_size(true)
+            .scene_change_threshold(None);
+
+        assert_eq!(
+            builder.filters(),
+            "minterpolate=fps=60000/1001:mi_mode=blend:mc_mode=obmc:me_mode=bilat:me=umh:mb_size=16:search_param=32:vsbmc=1:scd=none"
+        );
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let modes = [
+            InterpolationMode::Duplicate,
+            InterpolationMode::Blend,
+            InterpolationMode::MotionCompensated,
+        ];
+
+        for mode in modes {
+            let interpolator = FrameInterpolator::builder()
+                .frame_rate(Rational::new(50, 1))
+                .mode(mode)
+                .build();
+
+            let frames = interpolate(interpolator, 20);
+
+            // the filter needs a few frames of look-ahead, so the last
+            // input frames might not be fully covered
+            assert!(frames.len() >= 30);
+            assert!(frames.len() <= 40);
+
+            let first = frames[0].pts().timestamp();
+
+            for (index, frame) in frames.iter().enumerate() {
+                let time_base = frame.time_base();
+
+                assert_eq!(time_base.num(), 1);
+                assert_eq!(time_base.den(), 50);
+
+                // consecutive timestamps at 50 fps within the input range
+                assert_eq!(frame.pts().timestamp(), first + index as i64);
+                assert!(frame.pts().as_millis().unwrap() < 800);
+
+                assert_eq!(frame.width(), 64);
+                assert_eq!(frame.height(), 48);
+            }
+        }
+    }
+}
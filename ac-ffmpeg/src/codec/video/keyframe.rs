@@ -0,0 +1,358 @@
+//! Keyframe alignment across multiple renditions.
+
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use crate::{
+    codec::video::{frame::PictureType, VideoFrame},
+    packet::Packet,
+    time::Timestamp,
+};
+
+/// Maximum difference (in microseconds) between a forced keyframe
+/// timestamp and a packet timestamp that are still considered equal. It
+/// covers rounding errors caused by different encoder time bases.
+const TIMESTAMP_TOLERANCE: i64 = 1;
+
+/// Builder for the keyframe coordinator.
+pub struct KeyframeCoordinatorBuilder {
+    interval: Option<Duration>,
+    origin: Timestamp,
+}
+
+impl KeyframeCoordinatorBuilder {
+    /// Create a new builder.
+    fn new() -> Self {
+        Self {
+            interval: Some(Duration::from_secs(2)),
+            origin: Timestamp::null(),
+        }
+    }
+
+    /// Force a keyframe at the first frame of every interval of a given
+    /// length (typically the segment duration). The default is 2 seconds.
+    /// Use None to force keyframes only at explicitly requested points.
+    pub fn interval(mut self, interval: Option<Duration>) -> Self {
+        self.interval = interval.filter(|interval| !interval.is_zero());
+        self
+    }
+
+    /// Set the timestamp where the first interval starts. The default is
+    /// zero.
+    pub fn origin(mut self, origin: Timestamp) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// Build the coordinator.
+    pub fn build(self) -> KeyframeCoordinator {
+        let shared = Shared {
+            interval: self.interval.map(|interval| interval.as_micros() as i64),
+            origin: self.origin.as_micros().unwrap_or(0),
+            requested: BTreeSet::new(),
+            positions: Vec::new(),
+        };
+
+        KeyframeCoordinator {
+            shared: Arc::new(Mutex::new(shared)),
+        }
+    }
+}
+
+/// State shared by all renditions.
+struct Shared {
+    interval: Option<i64>,
+    origin: i64,
+    requested: BTreeSet<i64>,
+    positions: Vec<Option<i64>>,
+}
+
+impl Shared {
+    /// Get index of the interval containing a given timestamp.
+    fn interval_index(&self, pts: i64) -> Option<i64> {
+        self.interval
+            .map(|interval| (pts - self.origin).div_euclid(interval))
+    }
+
+    /// Check if there is a requested keyframe in the `(prev, pts]` range.
+    fn is_requested(&self, prev: Option<i64>, pts: i64) -> bool {
+        if let Some(prev) = prev {
+            self.requested.range((prev + 1)..=pts).next().is_some()
+        } else {
+            self.requested.range(..=pts).next().is_some()
+        }
+    }
+
+    /// Drop requested keyframes that have been passed by all renditions.
+    fn prune(&mut self) {
+        let min = self.positions.iter().copied().min().flatten();
+
+        if let Some(min) = min {
+            self.requested = self.requested.split_off(&min.saturating_add(1));
+        }
+    }
+}
+
+/// Keyframe coordinator. It forces keyframes at identical presentation
+/// timestamps across multiple parallel encoders (renditions of an ABR
+/// ladder), so that players can switch between the renditions seamlessly.
+///
+/// Every encoder gets its own `RenditionKeyframes` handle. The handle marks
+/// frames that must be encoded as keyframes before they are pushed to the
+/// encoder. The decision depends only on frame timestamps, so all renditions
+/// must be fed with frames having the same timestamps (i.e. the same frame
+/// rate). The handles can be moved to different threads.
+///
+/// Note that encoders may still insert additional keyframes on scene cuts.
+/// These keyframes are harmless for switching but they break segment
+/// boundaries of some packagers, so it is usually a good idea to disable
+/// scene cut detection (e.g. `sc_threshold=0` for libx264) and to make sure
+/// that the forced keyframes are IDR frames (e.g. `forced-idr=1` for
+/// libx264). Use the `check()` method of the handle to detect unexpected
+/// keyframes.
+#[derive(Clone)]
+pub struct KeyframeCoordinator {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl KeyframeCoordinator {
+    /// Get a builder for the keyframe coordinator.
+    pub fn builder() -> KeyframeCoordinatorBuilder {
+        KeyframeCoordinatorBuilder::new()
+    }
+
+    /// Create a new coordinator forcing keyframes in a given interval.
+    pub fn new(interval: Duration) -> Self {
+        Self::builder().interval(Some(interval)).build()
+    }
+
+    /// Create a handle for a new rendition. All handles should be created
+    /// before the first frame is processed.
+    pub fn rendition(&self) -> RenditionKeyframes {
+        let mut shared = self.shared.lock().unwrap();
+
+        let index = shared.positions.len();
+
+        shared.positions.push(None);
+
+        RenditionKeyframes {
+            shared: self.shared.clone(),
+            index,
+            last_pts: None,
+            last_interval: None,
+            expected: BTreeSet::new(),
+            unexpected_keyframes: 0,
+            missing_keyframes: 0,
+        }
+    }
+
+    /// Request a keyframe at a given timestamp in all renditions (e.g. at a
+    /// scene cut detected by the leading rendition or at an ad insertion
+    /// point). The request has no effect for renditions that have already
+    /// processed a frame with the timestamp.
+    pub fn request_keyframe(&self, pts: Timestamp) {
+        if let Some(pts) = pts.as_micros() {
+            self.shared.lock().unwrap().requested.insert(pts);
+        }
+    }
+}
+
+/// Keyframe handle of a single rendition.
+pub struct RenditionKeyframes {
+    shared: Arc<Mutex<Shared>>,
+    index: usize,
+    last_pts: Option<i64>,
+    last_interval: Option<i64>,
+    expected: BTreeSet<i64>,
+    unexpected_keyframes: u64,
+    missing_keyframes: u64,
+}
+
+impl RenditionKeyframes {
+    /// Check if a frame with a given presentation timestamp should be
+    /// encoded as a keyframe. The method must be called for every frame
+    /// in presentation order.
+    pub fn is_keyframe(&mut self, pts: Timestamp) -> bool {
+        let pts = match pts.as_micros() {
+            Some(pts) => pts,
+            None => return false,
+        };
+
+        let mut shared = self.shared.lock().unwrap();
+
+        let interval = shared.interval_index(pts);
+
+        let keyframe = self.last_pts.is_none()
+            || interval > self.last_interval
+            || shared.is_requested(self.last_pts, pts);
+
+        self.last_pts = Some(pts);
+        self.last_interval = interval;
+
+        shared.positions[self.index] = Some(pts);
+        shared.prune();
+
+        if keyframe {
+            self.expected.insert(pts);
+        }
+
+        keyframe
+    }
+
+    /// Prepare a given frame for encoding. The picture type is set to
+    /// `PictureType::I` for frames that must be encoded as keyframes and it
+    /// is cleared for all other frames, so that the encoder is not affected
+    /// by picture types coming from a decoder.
+    pub fn prepare(&mut self, frame: VideoFrame) -> VideoFrame {
+        if self.is_keyframe(frame.pts()) {
+            frame.with_picture_type(PictureType::I)
+        } else {
+            frame.with_picture_type(PictureType::None)
+        }
+    }
+
+    /// Check a given encoded packet. The method returns false if the packet
+    /// is a keyframe that has not been forced or if the packet should have
+    /// been a keyframe but it is not.
+    pub fn check(&mut self, packet: &Packet) -> bool {
+        let pts = match packet.pts().as_micros() {
+            Some(pts) => pts,
+            None => return true,
+        };
+
+        let expected = self
+            .expected
+            .range((pts - TIMESTAMP_TOLERANCE)..=(pts + TIMESTAMP_TOLERANCE))
+            .next()
+            .copied();
+
+        if let Some(expected) = expected {
+            self.expected.remove(&expected);
+        }
+
+        match (packet.is_key(), expected.is_some()) {
+            (true, false) => {
+                self.unexpected_keyframes += 1;
+
+                false
+            }
+            (false, true) => {
+                self.missing_keyframes += 1;
+
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Get the number of keyframes produced by the encoder without being
+    /// forced.
+    pub fn unexpected_keyframes(&self) -> u64 {
+        self.unexpected_keyframes
+    }
+
+    /// Get the number of forced keyframes that the encoder did not produce.
+    pub fn missing_keyframes(&self) -> u64 {
+        self.missing_keyframes
+    }
+}
+
+impl Drop for RenditionKeyframes {
+    fn drop(&mut self) {
+        // stop blocking pruning of the requested keyframes
+        if let Ok(mut shared) = self.shared.lock() {
+            shared.positions[self.index] = Some(i64::MAX);
+            shared.prune();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::KeyframeCoordinator;
+
+    use crate::{
+        packet::PacketMut,
+        time::{TimeBase, Timestamp},
+    };
+
+    fn pts(ms: i64) -> Timestamp {
+        Timestamp::from_millis(ms)
+    }
+
+    #[test]
+    fn test_interval() {
+        let coordinator = KeyframeCoordinator::new(Duration::from_secs(1));
+
+        let mut a = coordinator.rendition();
+        let mut b = coordinator.rendition();
+
+        let mut ka = Vec::new();
+        let mut kb = Vec::new();
+
+        for i in 0..75 {
+            // 30 fps
+            let t = i * 1000 / 30;
+
+            if a.is_keyframe(pts(t)) {
+                ka.push(t);
+            }
+
+            if b.is_keyframe(pts(t)) {
+                kb.push(t);
+            }
+        }
+
+        assert_eq!(ka, vec![0, 1000, 2000]);
+        assert_eq!(ka, kb);
+    }
+
+    #[test]
+    fn test_requested_keyframes() {
+        let coordinator = KeyframeCoordinator::builder().interval(None).build();
+
+        let mut a = coordinator.rendition();
+        let mut b = coordinator.rendition();
+
+        assert!(a.is_keyframe(pts(0)));
+        assert!(!a.is_keyframe(pts(40)));
+
+        // rendition "a" detected a scene cut at 80 ms, "b" is behind
+        coordinator.request_keyframe(pts(80));
+
+        assert!(a.is_keyframe(pts(80)));
+
+        assert!(b.is_keyframe(pts(0)));
+        assert!(!b.is_keyframe(pts(40)));
+        assert!(b.is_keyframe(pts(80)));
+        assert!(!b.is_keyframe(pts(120)));
+    }
+
+    #[test]
+    fn test_check() {
+        let coordinator = KeyframeCoordinator::new(Duration::from_secs(1));
+
+        let mut rendition = coordinator.rendition();
+
+        assert!(rendition.is_keyframe(pts(0)));
+        assert!(!rendition.is_keyframe(pts(500)));
+
+        let packet = |ms: i64, key: bool| {
+            PacketMut::new(0)
+                .with_time_base(TimeBase::new(1, 1000))
+                .with_raw_pts(ms)
+                .with_key_flag(key)
+                .freeze()
+        };
+
+        assert!(rendition.check(&packet(0, true)));
+        assert!(!rendition.check(&packet(500, true)));
+        assert_eq!(rendition.unexpected_keyframes(), 1);
+        assert_eq!(rendition.missing_keyframes(), 0);
+    }
+}
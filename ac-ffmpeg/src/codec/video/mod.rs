@@ -1,27 +1,131 @@
 //! Video decoder/encoder.
-
+//!
+//! # Software frame processing
+//!
+//! The frame processing utilities of this module (crop detection, fitting,
+//! geometric transformations, diagnostic overlays and sprite sheets) work
+//! only with frames stored in system memory. Hardware frames and bitstream
+//! pixel formats are not supported by any of them. Palette pixel formats
+//! are supported only by the geometric transformations and packed formats
+//! with macro pixels (e.g. YUYV) are supported only by the crop detector.
+
+#[cfg(feature = "avfilter")]
+mod filter;
+
+pub mod caption;
+pub(crate) mod convert;
+pub mod crop;
+#[cfg(feature = "avfilter")]
+pub mod deinterlace;
+pub mod fit;
 pub mod frame;
+#[cfg(feature = "avfilter")]
+pub mod interpolate;
+pub mod keyframe;
+pub mod overlay;
+pub mod pertitle;
+pub mod pool;
+pub mod poster;
+pub mod profile;
+#[cfg(feature = "avfilter")]
+pub mod retimer;
 pub mod scaler;
-
-use std::{ffi::CString, os::raw::c_void, ptr};
+pub mod scene;
+pub mod sei;
+pub mod sprite;
+pub mod transform;
+
+use std::{
+    ffi::{CStr, CString},
+    os::raw::c_void,
+    ptr,
+    time::Instant,
+};
 
 use crate::{
-    codec::{CodecError, CodecParameters, CodecTag, Decoder, Encoder, VideoCodecParameters},
+    codec::{
+        CodecError, CodecParameters, CodecTag, Compliance, Decoder, DecoderStatistics, Encoder,
+        VideoCodecParameters,
+    },
     format::stream::Stream,
     packet::Packet,
-    time::TimeBase,
-    Error,
+    time::{Rational, TimeBase},
+    Error, ErrorKind,
 };
 
+use self::profile::CodecSetting;
+
 pub use self::{
-    frame::{PixelFormat, VideoFrame, VideoFrameMut},
+    caption::ClosedCaptionExtractor,
+    crop::{CropDetector, CropDetectorBuilder, CropRect},
+    fit::{Color, FitMode},
+    frame::{
+        FieldOrder, FrameSideDataIter, FrameSideDataRef, FrameSideDataType, PictureType,
+        PixelFormat, PixelFormatLoss, VideoFrame, VideoFrameMut,
+    },
+    keyframe::{KeyframeCoordinator, KeyframeCoordinatorBuilder, RenditionKeyframes},
+    overlay::{DiagnosticOverlay, DiagnosticOverlayBuilder},
+    pertitle::{ComplexityAnalyzer, ComplexityAnalyzerBuilder, ComplexityReport, Rendition},
+    pool::VideoFramePool,
+    poster::{FrameAnalyzer, FrameStatistics, PosterSelector, PosterSelectorBuilder},
+    profile::{H264Level, H264Profile, HevcLevel, HevcProfile},
     scaler::{VideoFrameScaler, VideoFrameScalerBuilder},
+    scene::{SceneChange, VideoScene, VideoSceneBuilder},
+    sei::UnregisteredSei,
+    sprite::{SpriteSheetGenerator, SpriteSheetGeneratorBuilder},
+    transform::Transform,
+};
+
+#[cfg(feature = "avfilter")]
+pub use self::{
+    deinterlace::{DeinterlaceMode, Deinterlacer, DeinterlacerBuilder, DeinterlacerKind},
+    interpolate::{
+        FrameInterpolator, FrameInterpolatorBuilder, InterpolationMode, MotionCompensation,
+        MotionEstimationMode, SearchAlgorithm,
+    },
+    retimer::{VideoRetimer, VideoRetimerBuilder},
 };
 
+/// Selection of frames for which the decoder skips (a part of) the
+/// decoding. It corresponds to the `AVDiscard` values.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SkipFrame {
+    /// Do not skip anything (not even empty packets).
+    None,
+    /// Skip only useless packets (e.g. empty packets). This is the default.
+    Default,
+    /// Skip all non-reference frames.
+    NonRef,
+    /// Skip all bidirectional frames.
+    Bidir,
+    /// Skip all frames except keyframes and intra frames.
+    NonIntra,
+    /// Skip all frames except keyframes.
+    NonKey,
+    /// Skip all frames.
+    All,
+}
+
+impl SkipFrame {
+    /// Get name of the corresponding option value.
+    fn name(self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Default => "default",
+            Self::NonRef => "noref",
+            Self::Bidir => "bidir",
+            Self::NonIntra => "nointra",
+            Self::NonKey => "nokey",
+            Self::All => "all",
+        }
+    }
+}
+
 /// Builder for the video decoder.
 pub struct VideoDecoderBuilder {
     ptr: *mut c_void,
     time_base: TimeBase,
+    frame_pool: Option<VideoFramePool>,
 }
 
 impl VideoDecoderBuilder {
@@ -31,7 +135,11 @@ impl VideoDecoderBuilder {
 
         super::ffw_decoder_set_pkt_timebase(ptr, time_base.num() as _, time_base.den() as _);
 
-        Self { ptr, time_base }
+        Self {
+            ptr,
+            time_base,
+            frame_pool: None,
+        }
     }
 
     /// Create a new builder for a given codec.
@@ -41,7 +149,10 @@ impl VideoDecoderBuilder {
         let ptr = unsafe { super::ffw_decoder_new(codec.as_ptr() as _) };
 
         if ptr.is_null() {
-            return Err(Error::new("unknown codec"));
+            return Err(Error::with_kind(
+                ErrorKind::DecoderNotFound,
+                "unknown codec",
+            ));
         }
 
         unsafe { Ok(Self::from_raw_ptr(ptr)) }
@@ -93,6 +204,88 @@ impl VideoDecoderBuilder {
         self
     }
 
+    /// Allocate frame buffers from a given pool. Frame pooling is disabled
+    /// by default.
+    pub fn frame_pool(mut self, pool: &VideoFramePool) -> Self {
+        unsafe {
+            super::ffw_decoder_set_frame_pool(self.ptr, pool.as_ptr());
+        }
+
+        self.frame_pool = Some(pool.clone());
+        self
+    }
+
+    /// Enable or disable film grain synthesis (e.g. in AV1 decoders). It
+    /// is enabled by default. If disabled, decoders that support it output
+    /// frames without the synthesized grain and export the film grain
+    /// parameters as `FrameSideDataType::FilmGrainParams` side data
+    /// instead, so that the grain can be preserved when re-encoding.
+    pub fn film_grain(self, apply: bool) -> Self {
+        let value = if apply { "-film_grain" } else { "+film_grain" };
+
+        self.set_option("export_side_data", value)
+    }
+
+    /// Enable or disable cropping of the decoded frames according to the
+    /// cropping signaled in the bitstream (e.g. 1920x1080 pictures coded as
+    /// 1920x1088). If disabled, the decoder outputs frames of the coded
+    /// size with their crop fields set (see `VideoFrame::crop_fields()`)
+    /// and the frames can be cropped later using
+    /// `VideoFrame::apply_cropping()`. It is enabled by default.
+    pub fn apply_cropping(self, enabled: bool) -> Self {
+        self.set_option("apply_cropping", enabled as i32)
+    }
+
+    /// Skip decoding of selected frames. Skipped frames are not returned
+    /// by the decoder. For example, `SkipFrame::NonKey` makes the decoder
+    /// return only keyframes, which is much faster e.g. for thumbnailing
+    /// or scrubbing. The default is `SkipFrame::Default`.
+    pub fn skip_frame(self, skip: SkipFrame) -> Self {
+        self.set_option("skip_frame", skip.name())
+    }
+
+    /// Skip the loop filter for selected frames. The frames are still
+    /// returned but their quality may be lower. The default is
+    /// `SkipFrame::Default`.
+    pub fn skip_loop_filter(self, skip: SkipFrame) -> Self {
+        self.set_option("skip_loop_filter", skip.name())
+    }
+
+    /// Skip the IDCT/dequantization for selected frames. The frames are
+    /// still returned but their quality may be lower. The default is
+    /// `SkipFrame::Default`.
+    pub fn skip_idct(self, skip: SkipFrame) -> Self {
+        self.set_option("skip_idct", skip.name())
+    }
+
+    /// Enable or disable the low delay mode (`AV_CODEC_FLAG_LOW_DELAY`).
+    /// In the low delay mode, decoded frames are returned immediately
+    /// instead of waiting for the frame reordering, which assumes that the
+    /// stream has no B-frames. It is disabled by default.
+    pub fn low_delay(self, enable: bool) -> Self {
+        let value = if enable { "+low_delay" } else { "-low_delay" };
+
+        self.set_option("flags", value)
+    }
+
+    /// Allow or disallow speedups that are not compliant with the
+    /// specification (`AV_CODEC_FLAG2_FAST`). It is disabled by default.
+    pub fn fast(self, enable: bool) -> Self {
+        let value = if enable { "+fast" } else { "-fast" };
+
+        self.set_option("flags2", value)
+    }
+
+    /// Enable or disable frame threading. Frame threading adds a delay of
+    /// one frame per decoding thread, so it should be disabled for low
+    /// latency decoding (slice threading is still used if supported by
+    /// the decoder). It is enabled by default.
+    pub fn frame_threading(self, enable: bool) -> Self {
+        let value = if enable { "frame+slice" } else { "slice" };
+
+        self.set_option("thread_type", value)
+    }
+
     /// Set codec extradata.
     pub fn extradata<T>(self, data: Option<T>) -> Self
     where
@@ -135,6 +328,8 @@ impl VideoDecoderBuilder {
         let res = VideoDecoder {
             ptr,
             time_base: self.time_base,
+            statistics: DecoderStatistics::new(),
+            frame_pool: self.frame_pool.take(),
         };
 
         Ok(res)
@@ -154,6 +349,8 @@ unsafe impl Sync for VideoDecoderBuilder {}
 pub struct VideoDecoder {
     ptr: *mut c_void,
     time_base: TimeBase,
+    statistics: DecoderStatistics,
+    frame_pool: Option<VideoFramePool>,
 }
 
 impl VideoDecoder {
@@ -189,6 +386,16 @@ impl VideoDecoder {
     pub fn builder(codec: &str) -> Result<VideoDecoderBuilder, Error> {
         VideoDecoderBuilder::new(codec)
     }
+
+    /// Get decoder statistics.
+    pub fn statistics(&self) -> DecoderStatistics {
+        self.statistics
+    }
+
+    /// Get the frame pool used by the decoder (if any).
+    pub fn frame_pool(&self) -> Option<&VideoFramePool> {
+        self.frame_pool.as_ref()
+    }
 }
 
 impl Decoder for VideoDecoder {
@@ -210,7 +417,15 @@ impl Decoder for VideoDecoder {
     fn try_push(&mut self, packet: Packet) -> Result<(), CodecError> {
         let packet = packet.with_time_base(self.time_base);
 
-        unsafe {
+        let _span = trace_span!(
+            "video_decoder_push",
+            stream_index = packet.stream_index(),
+            pts = packet.pts().as_micros(),
+        );
+
+        let start = Instant::now();
+
+        let res = unsafe {
             match super::ffw_decoder_push_packet(self.ptr, packet.as_ptr()) {
                 1 => Ok(()),
                 0 => Err(CodecError::again(
@@ -218,7 +433,12 @@ impl Decoder for VideoDecoder {
                 )),
                 e => Err(CodecError::from_raw_error_code(e)),
             }
-        }
+        };
+
+        self.statistics
+            .record_push(packet.pts(), start.elapsed(), &res);
+
+        res
     }
 
     fn try_flush(&mut self) -> Result<(), CodecError> {
@@ -236,7 +456,9 @@ impl Decoder for VideoDecoder {
     fn take(&mut self) -> Result<Option<VideoFrame>, Error> {
         let mut fptr = ptr::null_mut();
 
-        unsafe {
+        let start = Instant::now();
+
+        let res = unsafe {
             match super::ffw_decoder_take_frame(self.ptr, &mut fptr) {
                 1 => {
                     if fptr.is_null() {
@@ -248,7 +470,20 @@ impl Decoder for VideoDecoder {
                 0 => Ok(None),
                 e => Err(Error::from_raw_error_code(e)),
             }
+        };
+
+        let frame_info = match &res {
+            Ok(Some(frame)) => Some((frame.pts(), frame.is_corrupt())),
+            _ => None,
+        };
+
+        if let Some((pts, _)) = frame_info {
+            trace_event!(pts = pts.as_micros(), "video frame decoded");
         }
+
+        self.statistics.record_take(start.elapsed(), frame_info);
+
+        res
     }
 }
 
@@ -270,6 +505,14 @@ pub struct VideoEncoderBuilder {
     format: Option<PixelFormat>,
     width: Option<usize>,
     height: Option<usize>,
+
+    profile: Option<CodecSetting>,
+    level: Option<CodecSetting>,
+    preset: Option<String>,
+    tune: Option<String>,
+
+    reproducible: bool,
+    auto_convert: bool,
 }
 
 impl VideoEncoderBuilder {
@@ -280,7 +523,10 @@ impl VideoEncoderBuilder {
         let ptr = unsafe { super::ffw_encoder_new(codec.as_ptr() as _) };
 
         if ptr.is_null() {
-            return Err(Error::new("unknown codec"));
+            return Err(Error::with_kind(
+                ErrorKind::EncoderNotFound,
+                "unknown codec",
+            ));
         }
 
         unsafe {
@@ -295,6 +541,14 @@ impl VideoEncoderBuilder {
             format: None,
             width: None,
             height: None,
+
+            profile: None,
+            level: None,
+            preset: None,
+            tune: None,
+
+            reproducible: false,
+            auto_convert: false,
         };
 
         Ok(res)
@@ -326,6 +580,14 @@ impl VideoEncoderBuilder {
             format: Some(pixel_format),
             width: Some(width),
             height: Some(height),
+
+            profile: None,
+            level: None,
+            preset: None,
+            tune: None,
+
+            reproducible: false,
+            auto_convert: false,
         };
 
         Ok(res)
@@ -383,6 +645,129 @@ impl VideoEncoderBuilder {
         self
     }
 
+    /// Set the sample (pixel) aspect ratio. It should be set for anamorphic
+    /// content, otherwise square pixels are assumed.
+    pub fn sample_aspect_ratio(self, sar: Rational) -> Self {
+        unsafe {
+            super::ffw_encoder_set_sample_aspect_ratio(self.ptr, sar.num() as _, sar.den() as _);
+        }
+
+        self
+    }
+
+    /// Set the nominal frame rate. Some encoders use it for rate control
+    /// and for signaling the frame rate in the bitstream (e.g. in the H.264
+    /// VUI), so it should be set whenever it is known. Note that it does not
+    /// affect the time base.
+    pub fn frame_rate(self, frame_rate: Rational) -> Self {
+        unsafe {
+            super::ffw_encoder_set_frame_rate(
+                self.ptr,
+                frame_rate.num() as _,
+                frame_rate.den() as _,
+            );
+        }
+
+        self
+    }
+
+    /// Set the field order. It should be set when encoding interlaced
+    /// content, so that the encoder can signal it in the bitstream.
+    pub fn field_order(self, field_order: FieldOrder) -> Self {
+        unsafe {
+            super::ffw_encoder_set_field_order(self.ptr, field_order as _);
+        }
+
+        self
+    }
+
+    /// Set the maximum number of consecutive B-frames. Zero disables
+    /// B-frames, which removes the frame reordering delay (e.g. for low
+    /// latency streaming). The default depends on the encoder.
+    pub fn max_b_frames(self, frames: u32) -> Self {
+        self.set_option("bf", frames)
+    }
+
+    /// Set the GOP size, i.e. the maximum distance between two keyframes
+    /// (in frames). The default depends on the encoder.
+    pub fn gop_size(self, frames: u32) -> Self {
+        self.set_option("g", frames)
+    }
+
+    /// Set the minimum distance between two keyframes (in frames). Setting
+    /// it to the GOP size together with disabling the scene change
+    /// detection results in a fixed GOP. The default depends on the encoder.
+    pub fn keyint_min(self, frames: u32) -> Self {
+        self.set_option("keyint_min", frames)
+    }
+
+    /// Set the scene change threshold. Encoders insert extra keyframes at
+    /// scene changes, zero disables the scene change detection. The value
+    /// range and the default depend on the encoder (e.g. 0 - 100 with the
+    /// default of 40 for libx264).
+    pub fn scene_change_threshold(self, threshold: i32) -> Self {
+        self.set_option("sc_threshold", threshold)
+    }
+
+    /// Set the H.264 profile. The build fails if the encoder is not an
+    /// H.264 encoder.
+    pub fn h264_profile(mut self, profile: H264Profile) -> Self {
+        self.profile = Some(profile.into());
+        self
+    }
+
+    /// Set the HEVC profile. The build fails if the encoder is not an HEVC
+    /// encoder.
+    pub fn hevc_profile(mut self, profile: HevcProfile) -> Self {
+        self.profile = Some(profile.into());
+        self
+    }
+
+    /// Set the H.264 level. The build fails if the encoder is not an H.264
+    /// encoder.
+    pub fn h264_level(mut self, level: H264Level) -> Self {
+        self.level = Some(level.into());
+        self
+    }
+
+    /// Set the HEVC level. The build fails if the encoder is not an HEVC
+    /// encoder.
+    pub fn hevc_level(mut self, level: HevcLevel) -> Self {
+        self.level = Some(level.into());
+        self
+    }
+
+    /// Set the encoder preset (e.g. "veryfast" for libx264 or "p4" for
+    /// NVENC). The value is passed to the encoder as it is. The build
+    /// fails if the encoder does not support presets.
+    pub fn preset(mut self, preset: &str) -> Self {
+        self.preset = Some(String::from(preset));
+        self
+    }
+
+    /// Set the encoder tune (e.g. "zerolatency" for libx264). The value is
+    /// passed to the encoder as it is. The build fails if the encoder does
+    /// not support tunes.
+    pub fn tune(mut self, tune: &str) -> Self {
+        self.tune = Some(String::from(tune));
+        self
+    }
+
+    /// Embed closed captions attached to the frames (see
+    /// `VideoFrame::closed_captions()`) into the bitstream. Only some
+    /// encoders support it (e.g. `libx264`, `libx265` or `h264_nvenc`) and
+    /// some of them enable it by default.
+    pub fn closed_captions(self, enabled: bool) -> Self {
+        self.set_option("a53cc", enabled as i32)
+    }
+
+    /// Embed user data unregistered SEI messages attached to the frames
+    /// (see `VideoFrame::unregistered_sei()`) into the bitstream. Only some
+    /// encoders support it (e.g. `libx264`, `libx265` or `h264_nvenc`).
+    pub fn unregistered_sei(self, enabled: bool) -> Self {
+        self.set_option("udu_sei", enabled as i32)
+    }
+
     /// Set codec tag.
     pub fn codec_tag(self, codec_tag: impl Into<CodecTag>) -> Self {
         unsafe {
@@ -392,6 +777,118 @@ impl VideoEncoderBuilder {
         self
     }
 
+    /// Make the encoder output reproducible, i.e. identical across runs and
+    /// machines (e.g. for golden file tests). The encoder will use
+    /// bit-exact algorithms only and it will run in a single thread. The
+    /// thread count cannot be overridden using encoder options in this
+    /// mode. Note that the output may still differ across FFmpeg and codec
+    /// library versions.
+    pub fn reproducible(mut self, reproducible: bool) -> Self {
+        self.reproducible = reproducible;
+        self
+    }
+
+    /// Set the level of compliance with the standards. Use
+    /// `Compliance::Experimental` in order to use experimental encoders.
+    /// The default is `Compliance::Normal`.
+    pub fn compliance(self, compliance: Compliance) -> Self {
+        self.set_option("strict", compliance.into_raw())
+    }
+
+    /// Convert pushed frames that do not match the pixel format or the
+    /// resolution of the encoder automatically using a scaler instead of
+    /// failing. Timestamps, picture types and side data of the frames are
+    /// kept. Automatic conversion is disabled by default.
+    pub fn auto_convert(mut self, enabled: bool) -> Self {
+        self.auto_convert = enabled;
+        self
+    }
+
+    /// Get pixel formats supported by the encoder (in the order of
+    /// preference). The list is empty if the encoder does not restrict the
+    /// pixel formats. Use `PixelFormat::best_conversion_target()` to pick
+    /// the one closest to the source format.
+    pub fn supported_pixel_formats(&self) -> Vec<PixelFormat> {
+        (0..)
+            .map(|index| unsafe { super::ffw_encoder_get_supported_pixel_format(self.ptr, index) })
+            .take_while(|&format| format >= 0)
+            .map(PixelFormat::from_raw)
+            .collect()
+    }
+
+    /// Get the preferred pixel format of the encoder (if the encoder
+    /// restricts the supported pixel formats).
+    pub(crate) fn default_pixel_format(&self) -> Option<PixelFormat> {
+        let format = unsafe { super::ffw_encoder_get_default_pixel_format(self.ptr) };
+
+        if format < 0 {
+            None
+        } else {
+            Some(PixelFormat::from_raw(format))
+        }
+    }
+
+    /// Get name of the codec (e.g. "h264").
+    fn codec_name(&self) -> &'static str {
+        unsafe {
+            let ptr = super::ffw_encoder_get_codec_name(self.ptr);
+
+            CStr::from_ptr(ptr as _).to_str().unwrap_or("unknown")
+        }
+    }
+
+    /// Check if the encoder has a given private option.
+    fn has_private_option(&self, name: &str) -> bool {
+        let name = CString::new(name).expect("invalid option name");
+
+        unsafe { super::ffw_encoder_has_private_option(self.ptr, name.as_ptr() as _) != 0 }
+    }
+
+    /// Validate the profile, level, preset and tune against the encoder and
+    /// set the corresponding options.
+    fn apply_codec_settings(mut self) -> Result<Self, Error> {
+        let codec = self.codec_name();
+
+        for (option, setting) in [("profile", self.profile), ("level", self.level)] {
+            let setting = match setting {
+                Some(setting) => setting,
+                None => continue,
+            };
+
+            if setting.codec != codec {
+                return Err(Error::new(format!(
+                    "{} {} is not applicable to the {} codec",
+                    setting.codec, option, codec
+                )));
+            }
+
+            // use the generic option if the encoder does not have a private one
+            self = if self.has_private_option(option) {
+                self.set_option(option, setting.name)
+            } else {
+                self.set_option(option, setting.idc)
+            };
+        }
+
+        for (option, value) in [("preset", self.preset.take()), ("tune", self.tune.take())] {
+            let value = match value {
+                Some(value) => value,
+                None => continue,
+            };
+
+            if !self.has_private_option(option) {
+                return Err(Error::new(format!(
+                    "the encoder does not support the {} option",
+                    option
+                )));
+            }
+
+            self = self.set_option(option, value);
+        }
+
+        Ok(self)
+    }
+
     /// Build the encoder.
     pub fn build(mut self) -> Result<VideoEncoder, Error> {
         let format = self
@@ -403,6 +900,12 @@ impl VideoEncoderBuilder {
 
         let tb = self.time_base;
 
+        self = self.apply_codec_settings()?;
+
+        if self.reproducible && unsafe { super::ffw_encoder_set_reproducible(self.ptr) } < 0 {
+            panic!("unable to allocate an option");
+        }
+
         unsafe {
             super::ffw_encoder_set_time_base(self.ptr, tb.num() as _, tb.den() as _);
             super::ffw_encoder_set_pixel_format(self.ptr, format.into_raw());
@@ -414,11 +917,25 @@ impl VideoEncoderBuilder {
             }
         }
 
+        // some encoders may change the time base
+        let tb = unsafe { super::get_encoder_time_base(self.ptr) };
+
         let ptr = self.ptr;
 
         self.ptr = ptr::null_mut();
 
-        let res = VideoEncoder { ptr, time_base: tb };
+        let res = VideoEncoder {
+            ptr,
+            time_base: tb,
+            force_keyframe: false,
+
+            format,
+            width,
+            height,
+
+            auto_convert: self.auto_convert,
+            scaler: None,
+        };
 
         Ok(res)
     }
@@ -437,6 +954,14 @@ unsafe impl Sync for VideoEncoderBuilder {}
 pub struct VideoEncoder {
     ptr: *mut c_void,
     time_base: TimeBase,
+    force_keyframe: bool,
+
+    format: PixelFormat,
+    width: usize,
+    height: usize,
+
+    auto_convert: bool,
+    scaler: Option<VideoFrameScaler>,
 }
 
 impl VideoEncoder {
@@ -451,6 +976,62 @@ impl VideoEncoder {
     pub fn builder(codec: &str) -> Result<VideoEncoderBuilder, Error> {
         VideoEncoderBuilder::new(codec)
     }
+
+    /// Get the encoder time base. It is the time base requested in the
+    /// builder unless the encoder adjusted it. Encoded packets carry this
+    /// time base, so they can be pushed to a muxer as they are, the muxer
+    /// will rescale them into the stream time base.
+    pub fn time_base(&self) -> TimeBase {
+        self.time_base
+    }
+
+    /// Force the next pushed frame to be encoded as a keyframe. It is
+    /// equivalent to setting `PictureType::I` on the frame. It can be used
+    /// e.g. by segmenters to start every segment with a keyframe. Note that
+    /// some encoders need an additional option to make the forced keyframes
+    /// IDR frames (e.g. `forced-idr` in case of libx264).
+    pub fn force_keyframe_next(&mut self) {
+        self.force_keyframe = true;
+    }
+
+    /// Convert a given frame into the pixel format and the resolution of the
+    /// encoder (if needed). The scaler is created again whenever the source
+    /// parameters change.
+    fn convert(&mut self, frame: VideoFrame) -> Result<VideoFrame, Error> {
+        if frame.pixel_format() == self.format
+            && frame.width() == self.width
+            && frame.height() == self.height
+        {
+            return Ok(frame);
+        }
+
+        let reuse = self
+            .scaler
+            .as_ref()
+            .map(|scaler| scaler.accepts(&frame))
+            .unwrap_or(false);
+
+        if !reuse {
+            let scaler = VideoFrameScaler::builder()
+                .source_pixel_format(frame.pixel_format())
+                .source_width(frame.width())
+                .source_height(frame.height())
+                .target_pixel_format(self.format)
+                .target_width(self.width)
+                .target_height(self.height)
+                .build()?;
+
+            self.scaler = Some(scaler);
+        }
+
+        let scaler = self.scaler.as_mut().unwrap();
+
+        let picture_type = frame.picture_type();
+
+        let res = scaler.scale(&frame)?.with_picture_type(picture_type);
+
+        Ok(res)
+    }
 }
 
 impl Encoder for VideoEncoder {
@@ -469,12 +1050,26 @@ impl Encoder for VideoEncoder {
         params.into_video_codec_parameters().unwrap()
     }
 
-    fn try_push(&mut self, frame: VideoFrame) -> Result<(), CodecError> {
-        let frame = frame.with_time_base(self.time_base);
+    fn try_push(&mut self, mut frame: VideoFrame) -> Result<(), CodecError> {
+        if self.auto_convert {
+            frame = self.convert(frame)?;
+        }
+
+        let mut frame = frame.with_time_base(self.time_base);
+
+        if self.force_keyframe {
+            frame = frame.with_picture_type(PictureType::I);
+        }
+
+        let _span = trace_span!("video_encoder_push", pts = frame.pts().as_micros());
 
         unsafe {
             match super::ffw_encoder_push_frame(self.ptr, frame.as_ptr()) {
-                1 => Ok(()),
+                1 => {
+                    self.force_keyframe = false;
+
+                    Ok(())
+                }
                 0 => Err(CodecError::again(
                     "all packets must be consumed before pushing a new frame",
                 )),
@@ -498,7 +1093,7 @@ impl Encoder for VideoEncoder {
     fn take(&mut self) -> Result<Option<Packet>, Error> {
         let mut pptr = ptr::null_mut();
 
-        unsafe {
+        let res = unsafe {
             match super::ffw_encoder_take_packet(self.ptr, &mut pptr) {
                 1 => {
                     if pptr.is_null() {
@@ -510,7 +1105,17 @@ impl Encoder for VideoEncoder {
                 0 => Ok(None),
                 e => Err(Error::from_raw_error_code(e)),
             }
+        };
+
+        if let Ok(Some(packet)) = &res {
+            trace_event!(
+                pts = packet.pts().as_micros(),
+                dts = packet.dts().as_micros(),
+                "video packet encoded"
+            );
         }
+
+        res
     }
 }
 
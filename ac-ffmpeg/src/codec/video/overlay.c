@@ -0,0 +1,157 @@
+#include <string.h>
+
+#include <libavutil/frame.h>
+#include <libavutil/pixdesc.h>
+#include <libswscale/swscale.h>
+
+int ffw_frame_draw_bitmap(
+    AVFrame* frame,
+    int x, int y,
+    const uint8_t* bitmap, int width, int height,
+    uint8_t fg_r, uint8_t fg_g, uint8_t fg_b,
+    uint8_t bg_r, uint8_t bg_g, uint8_t bg_b);
+
+// defined in pixel.c
+AVFrame* ffw_pixel_alloc_frame(int format, int width, int height);
+int ffw_pixel_get_size(const AVPixFmtDescriptor* desc, int plane);
+
+// Convert two RGB colors into a given pixel format. The left half of the
+// resulting 8x2 frame will contain the first color and the right half will
+// contain the second color.
+static AVFrame* convert_colors(
+    int format,
+    uint8_t r1, uint8_t g1, uint8_t b1,
+    uint8_t r2, uint8_t g2, uint8_t b2) {
+    struct SwsContext* sws;
+    AVFrame* rgb;
+    AVFrame* res;
+    uint8_t* line;
+    int x, y;
+
+    res = NULL;
+
+    if (!(rgb = ffw_pixel_alloc_frame(AV_PIX_FMT_RGB24, 8, 2))) {
+        return NULL;
+    }
+
+    for (y = 0; y < 2; y++) {
+        line = rgb->data[0] + y * rgb->linesize[0];
+
+        for (x = 0; x < 8; x++) {
+            line[3 * x] = x < 4 ? r1 : r2;
+            line[3 * x + 1] = x < 4 ? g1 : g2;
+            line[3 * x + 2] = x < 4 ? b1 : b2;
+        }
+    }
+
+    sws = sws_getContext(
+        8, 2, AV_PIX_FMT_RGB24,
+        8, 2, format,
+        SWS_POINT, NULL, NULL, NULL);
+
+    if (!sws) {
+        goto end;
+    }
+
+    if (!(res = ffw_pixel_alloc_frame(format, 8, 2))) {
+        goto end;
+    }
+
+    sws_scale(sws,
+        (const uint8_t* const*)rgb->data, rgb->linesize, 0, 2,
+        res->data, res->linesize);
+
+end:
+    sws_freeContext(sws);
+    av_frame_free(&rgb);
+
+    return res;
+}
+
+int ffw_frame_draw_bitmap(
+    AVFrame* frame,
+    int x, int y,
+    const uint8_t* bitmap, int width, int height,
+    uint8_t fg_r, uint8_t fg_g, uint8_t fg_b,
+    uint8_t bg_r, uint8_t bg_g, uint8_t bg_b) {
+    const AVPixFmtDescriptor* desc;
+    const uint8_t* color;
+    AVFrame* colors;
+    uint8_t* line;
+    int planes;
+    int pixel_size;
+    int log2_w, log2_h;
+    int pw, ph;
+    int px, py;
+    int bx, by;
+    int i;
+
+    if (!(desc = av_pix_fmt_desc_get(frame->format))) {
+        return AVERROR(EINVAL);
+    }
+
+    if (desc->flags & (AV_PIX_FMT_FLAG_BITSTREAM | AV_PIX_FMT_FLAG_HWACCEL | AV_PIX_FMT_FLAG_PAL)) {
+        return AVERROR(ENOSYS);
+    }
+
+    if ((planes = av_pix_fmt_count_planes(frame->format)) < 0) {
+        return planes;
+    }
+
+    for (i = 0; i < planes; i++) {
+        if (!ffw_pixel_get_size(desc, i)) {
+            return AVERROR(ENOSYS);
+        }
+    }
+
+    colors = convert_colors(frame->format, fg_r, fg_g, fg_b, bg_r, bg_g, bg_b);
+
+    if (!colors) {
+        return AVERROR(ENOMEM);
+    }
+
+    for (i = 0; i < planes; i++) {
+        pixel_size = ffw_pixel_get_size(desc, i);
+
+        if (i == 1 || i == 2) {
+            log2_w = desc->log2_chroma_w;
+            log2_h = desc->log2_chroma_h;
+        } else {
+            log2_w = 0;
+            log2_h = 0;
+        }
+
+        pw = AV_CEIL_RSHIFT(frame->width, log2_w);
+        ph = AV_CEIL_RSHIFT(frame->height, log2_h);
+
+        for (py = AV_CEIL_RSHIFT(y, log2_h); py < ph; py++) {
+            by = (py << log2_h) - y;
+
+            if (by >= height) {
+                break;
+            }
+
+            line = frame->data[i] + py * frame->linesize[i];
+
+            for (px = AV_CEIL_RSHIFT(x, log2_w); px < pw; px++) {
+                bx = (px << log2_w) - x;
+
+                if (bx >= width) {
+                    break;
+                }
+
+                if (bitmap[by * width + bx]) {
+                    color = colors->data[i];
+                } else {
+                    color = colors->data[i] + (4 >> log2_w) * pixel_size;
+                }
+
+                memcpy(line + px * pixel_size, color, pixel_size);
+            }
+        }
+    }
+
+    av_frame_free(&colors);
+
+    return 0;
+}
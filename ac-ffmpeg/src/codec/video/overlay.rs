@@ -0,0 +1,422 @@
+//! Diagnostic overlay rendering frame info onto video frames.
+
+use std::{
+    os::raw::{c_int, c_void},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    codec::video::{Color, VideoFrame, VideoFrameMut},
+    time::Timestamp,
+    Error,
+};
+
+extern "C" {
+    fn ffw_frame_draw_bitmap(
+        frame: *mut c_void,
+        x: c_int,
+        y: c_int,
+        bitmap: *const u8,
+        width: c_int,
+        height: c_int,
+        fg_r: u8,
+        fg_g: u8,
+        fg_b: u8,
+        bg_r: u8,
+        bg_g: u8,
+        bg_b: u8,
+    ) -> c_int;
+}
+
+/// Glyph width in pixels.
+const GLYPH_WIDTH: usize = 5;
+
+/// Glyph height in pixels.
+const GLYPH_HEIGHT: usize = 7;
+
+/// Character cell width (i.e. glyph width + spacing).
+const CELL_WIDTH: usize = GLYPH_WIDTH + 1;
+
+/// Character cell height (i.e. glyph height + spacing).
+const CELL_HEIGHT: usize = GLYPH_HEIGHT + 1;
+
+/// Padding around the rendered text.
+const PADDING: usize = 2;
+
+/// Get a 5x7 glyph for a given character. Each row is represented by the
+/// five least significant bits of the corresponding byte (the most
+/// significant of them being the leftmost pixel). Lowercase letters are
+/// rendered as uppercase and unknown characters are rendered as '?'.
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c.to_ascii_uppercase() {
+        '0' => [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e],
+        '1' => [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        '2' => [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f],
+        '3' => [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e],
+        '4' => [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02],
+        '5' => [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e],
+        '6' => [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e],
+        '7' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e],
+        '9' => [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c],
+        'A' => [0x0e, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11],
+        'B' => [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e],
+        'C' => [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e],
+        'D' => [0x1c, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1c],
+        'E' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f],
+        'F' => [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10],
+        'G' => [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f],
+        'H' => [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11],
+        'I' => [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f],
+        'M' => [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'P' => [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10],
+        'Q' => [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d],
+        'R' => [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11],
+        'S' => [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e],
+        'T' => [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a],
+        'X' => [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0a, 0x04, 0x04, 0x04],
+        'Z' => [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f],
+        ':' => [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x0c, 0x00],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c],
+        '-' => [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        '#' => [0x0a, 0x0a, 0x1f, 0x0a, 0x1f, 0x0a, 0x0a],
+        ' ' => [0x00; GLYPH_HEIGHT],
+        _ => [0x0e, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+    }
+}
+
+/// Text rendered into a bitmap mask. Non-zero values represent foreground
+/// pixels.
+struct Bitmap {
+    data: Vec<u8>,
+    width: usize,
+    height: usize,
+}
+
+impl Bitmap {
+    /// Render given lines of text using a given integer scale.
+    fn render<T>(lines: &[T], scale: usize) -> Self
+    where
+        T: AsRef<str>,
+    {
+        let columns = lines
+            .iter()
+            .map(|line| line.as_ref().chars().count())
+            .max()
+            .unwrap_or(0);
+
+        let width = (2 * PADDING + (columns * CELL_WIDTH).saturating_sub(1)) * scale;
+        let height = (2 * PADDING + (lines.len() * CELL_HEIGHT).saturating_sub(1)) * scale;
+
+        let mut data = vec![0u8; width * height];
+
+        for (row, line) in lines.iter().enumerate() {
+            for (column, c) in line.as_ref().chars().enumerate() {
+                let left = (PADDING + column * CELL_WIDTH) * scale;
+                let top = (PADDING + row * CELL_HEIGHT) * scale;
+
+                for (gy, bits) in glyph(c).iter().enumerate() {
+                    for gx in 0..GLYPH_WIDTH {
+                        if (bits >> (GLYPH_WIDTH - 1 - gx)) & 1 == 0 {
+                            continue;
+                        }
+
+                        for y in 0..scale {
+                            let offset = (top + gy * scale + y) * width + left + gx * scale;
+
+                            for pixel in &mut data[offset..offset + scale] {
+                                *pixel = 1;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Self {
+            data,
+            width,
+            height,
+        }
+    }
+}
+
+/// Format a given number of milliseconds as HH:MM:SS.mmm.
+fn format_millis(millis: i64) -> String {
+    let sign = if millis < 0 { "-" } else { "" };
+
+    let millis = millis.unsigned_abs();
+
+    format!(
+        "{}{:02}:{:02}:{:02}.{:03}",
+        sign,
+        millis / 3_600_000,
+        (millis / 60_000) % 60,
+        (millis / 1_000) % 60,
+        millis % 1_000
+    )
+}
+
+/// Builder for the diagnostic overlay.
+pub struct DiagnosticOverlayBuilder {
+    x: usize,
+    y: usize,
+    scale: usize,
+    foreground: Color,
+    background: Color,
+    pts: bool,
+    frame_number: bool,
+    stream_index: Option<usize>,
+    wall_clock: bool,
+}
+
+impl DiagnosticOverlayBuilder {
+    /// Create a new builder.
+    fn new() -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            scale: 2,
+            foreground: Color::WHITE,
+            background: Color::BLACK,
+            pts: true,
+            frame_number: true,
+            stream_index: None,
+            wall_clock: false,
+        }
+    }
+
+    /// Set position of the top left corner of the overlay within the frame.
+    /// The default is (0, 0).
+    pub fn position(mut self, x: usize, y: usize) -> Self {
+        self.x = x;
+        self.y = y;
+        self
+    }
+
+    /// Set text scale. A single font pixel will be rendered as a square of
+    /// a given size. The default is 2.
+    ///
+    /// # Panics
+    /// The method panics if the scale is zero.
+    pub fn scale(mut self, scale: usize) -> Self {
+        assert!(scale > 0);
+
+        self.scale = scale;
+        self
+    }
+
+    /// Set text color. The default is white.
+    pub fn foreground(mut self, color: Color) -> Self {
+        self.foreground = color;
+        self
+    }
+
+    /// Set background color. The default is black.
+    pub fn background(mut self, color: Color) -> Self {
+        self.background = color;
+        self
+    }
+
+    /// Show frame presentation timestamp (enabled by default).
+    pub fn show_pts(mut self, enabled: bool) -> Self {
+        self.pts = enabled;
+        self
+    }
+
+    /// Show frame number (enabled by default). Frames are counted by the
+    /// overlay itself, starting from zero.
+    pub fn show_frame_number(mut self, enabled: bool) -> Self {
+        self.frame_number = enabled;
+        self
+    }
+
+    /// Show a given stream index. (Frames do not carry any stream
+    /// identification, so the index needs to be provided by the caller.)
+    pub fn show_stream_index(mut self, index: usize) -> Self {
+        self.stream_index = Some(index);
+        self
+    }
+
+    /// Show the current wall-clock time in UTC (disabled by default).
+    pub fn show_wall_clock(mut self, enabled: bool) -> Self {
+        self.wall_clock = enabled;
+        self
+    }
+
+    /// Build the overlay.
+    pub fn build(self) -> DiagnosticOverlay {
+        DiagnosticOverlay {
+            x: self.x,
+            y: self.y,
+            scale: self.scale,
+            foreground: self.foreground,
+            background: self.background,
+            pts: self.pts,
+            frame_number: self.frame_number,
+            stream_index: self.stream_index,
+            wall_clock: self.wall_clock,
+            frames: 0,
+        }
+    }
+}
+
+/// Diagnostic overlay. It renders frame info (presentation timestamp, frame
+/// number, stream index and wall-clock time) directly into video frames
+/// using a built-in bitmap font. No external fonts or filters are needed.
+///
+/// The overlay is clipped to the frame area. See [supported pixel formats](crate::codec::video#software-frame-processing).
+pub struct DiagnosticOverlay {
+    x: usize,
+    y: usize,
+    scale: usize,
+    foreground: Color,
+    background: Color,
+    pts: bool,
+    frame_number: bool,
+    stream_index: Option<usize>,
+    wall_clock: bool,
+    frames: u64,
+}
+
+impl DiagnosticOverlay {
+    /// Get a builder for the overlay.
+    pub fn builder() -> DiagnosticOverlayBuilder {
+        DiagnosticOverlayBuilder::new()
+    }
+
+    /// Create a new overlay with the default settings.
+    pub fn new() -> Self {
+        Self::builder().build()
+    }
+
+    /// Render the overlay into a given frame. The frame will be copied if it
+    /// is not writable.
+    pub fn apply(&mut self, frame: VideoFrame) -> Result<VideoFrame, Error> {
+        let mut frame = frame.into_mut();
+
+        self.draw(&mut frame)?;
+
+        Ok(frame.freeze())
+    }
+
+    /// Render the overlay into a given mutable frame.
+    pub fn draw(&mut self, frame: &mut VideoFrameMut) -> Result<(), Error> {
+        let lines = self.lines(frame.pts());
+
+        self.frames += 1;
+
+        if lines.is_empty() {
+            return Ok(());
+        }
+
+        let bitmap = Bitmap::render(&lines, self.scale);
+
+        let ret = unsafe {
+            ffw_frame_draw_bitmap(
+                frame.as_mut_ptr(),
+                self.x as _,
+                self.y as _,
+                bitmap.data.as_ptr(),
+                bitmap.width as _,
+                bitmap.height as _,
+                self.foreground.red(),
+                self.foreground.green(),
+                self.foreground.blue(),
+                self.background.red(),
+                self.background.green(),
+                self.background.blue(),
+            )
+        };
+
+        if ret < 0 {
+            return Err(Error::new(
+                "unable to draw the overlay, unsupported pixel format",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Get text lines for the current frame.
+    fn lines(&self, pts: Timestamp) -> Vec<String> {
+        let mut res = Vec::new();
+
+        if self.pts {
+            if let Some(millis) = pts.as_millis() {
+                res.push(format!("PTS   {}", format_millis(millis)));
+            } else {
+                res.push(String::from("PTS   N/A"));
+            }
+        }
+
+        if self.frame_number {
+            res.push(format!("FRAME {}", self.frames));
+        }
+
+        if let Some(index) = self.stream_index {
+            res.push(format!("STREAM #{}", index));
+        }
+
+        if self.wall_clock {
+            let mut line = String::from("CLOCK ");
+
+            if let Ok(now) = SystemTime::now().duration_since(UNIX_EPOCH) {
+                let millis = (now.as_millis() % 86_400_000) as i64;
+
+                line.push_str(&format_millis(millis));
+            } else {
+                line.push_str("N/A");
+            }
+
+            res.push(line);
+        }
+
+        res
+    }
+}
+
+impl Default for DiagnosticOverlay {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_millis, Bitmap};
+
+    #[test]
+    fn test_format_millis() {
+        assert_eq!(format_millis(0), "00:00:00.000");
+        assert_eq!(format_millis(3_723_004), "01:02:03.004");
+        assert_eq!(format_millis(-1_500), "-00:00:01.500");
+    }
+
+    #[test]
+    fn test_render() {
+        let bitmap = Bitmap::render(&["1", "-1"], 2);
+
+        assert_eq!(bitmap.width, (2 * 2 + 11) * 2);
+        assert_eq!(bitmap.height, (2 * 2 + 15) * 2);
+        assert_eq!(bitmap.data.len(), bitmap.width * bitmap.height);
+
+        // top of the "1" glyph (0x04) in the first line
+        let offset = 2 * 2 * bitmap.width + 2 * 2;
+
+        assert_eq!(
+            &bitmap.data[offset..offset + 10],
+            &[0, 0, 0, 0, 1, 1, 0, 0, 0, 0]
+        );
+    }
+}
@@ -0,0 +1,449 @@
+//! Per-title encoding analysis.
+//!
+//! The complexity analyzer samples frames of the source and measures their
+//! spatial complexity (amount of detail) and temporal complexity (amount of
+//! motion between consecutive frames). The resulting report can be used to
+//! adjust bit rates and CRF values of an ABR ladder, so that simple content
+//! (e.g. animation or talking heads) is not encoded with more bits than it
+//! needs and complex content (e.g. sports) gets more bits.
+//!
+//! The analysis can run as a separate pass over the source or it can be
+//! plugged into a transcode pipeline using a video frame hook (see
+//! `codec::hook::FrameHooks`).
+//!
+//! # Example
+//! ```text
+//! let mut analyzer = ComplexityAnalyzer::new();
+//!
+//! while let Some(frame) = decoder.take()? {
+//!     analyzer.push(&frame)?;
+//! }
+//!
+//! let ladder = analyzer.report().recommend(&[
+//!     Rendition::new(1920, 1080, 6_000_000),
+//!     Rendition::new(1280, 720, 3_000_000).with_crf(23.0),
+//!     Rendition::new(640, 360, 800_000),
+//! ]);
+//!
+//! for rendition in ladder {
+//!     let encoder = rendition
+//!         .apply(VideoEncoder::builder("libx264")?)
+//!         .pixel_format(pixel_format)
+//!         .time_base(time_base)
+//!         .build()?;
+//!
+//!     ...
+//! }
+//! ```
+
+use std::time::Duration;
+
+use crate::{
+    codec::video::{
+        convert::{mean_absolute_difference, FrameConverter},
+        frame::get_pixel_format,
+        poster::analysis_size,
+        scaler::Algorithm,
+        VideoEncoderBuilder, VideoFrame,
+    },
+    Error,
+};
+
+/// Spatial complexity of average content.
+const REFERENCE_SPATIAL_COMPLEXITY: f64 = 0.04;
+
+/// Temporal complexity of average content.
+const REFERENCE_TEMPORAL_COMPLEXITY: f64 = 0.02;
+
+/// Minimum bit rate factor.
+const MIN_FACTOR: f64 = 0.5;
+
+/// Maximum bit rate factor.
+const MAX_FACTOR: f64 = 2.0;
+
+/// Number of CRF units needed to halve the bit rate (valid for x264 and
+/// x265).
+const CRF_PER_HALVING: f64 = 6.0;
+
+/// Maximum CRF value.
+const MAX_CRF: f64 = 51.0;
+
+/// Builder for the complexity analyzer.
+pub struct ComplexityAnalyzerBuilder {
+    interval: Duration,
+}
+
+impl ComplexityAnalyzerBuilder {
+    /// Create a new builder.
+    fn new() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+        }
+    }
+
+    /// Sample at most one frame (and the frame following it) per a given
+    /// interval. The default is one second. Use zero to analyze all frames.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Build the complexity analyzer.
+    pub fn build(self) -> ComplexityAnalyzer {
+        ComplexityAnalyzer {
+            interval: self.interval.as_micros() as i64,
+            next_sample: None,
+            converter: FrameConverter::new(get_pixel_format("gray"), Algorithm::Area),
+            previous: None,
+            spatial: 0.0,
+            spatial_samples: 0,
+            temporal: 0.0,
+            temporal_samples: 0,
+        }
+    }
+}
+
+/// Source complexity analyzer.
+///
+/// Every sampled frame is downscaled to a small grayscale image and its
+/// spatial complexity is measured as the mean absolute luma gradient. The
+/// temporal complexity is measured as the mean absolute luma difference
+/// between the sampled frame and the frame following it. Frames must be
+/// pushed in presentation order.
+pub struct ComplexityAnalyzer {
+    interval: i64,
+    next_sample: Option<i64>,
+    converter: FrameConverter,
+    previous: Option<Vec<u8>>,
+    spatial: f64,
+    spatial_samples: usize,
+    temporal: f64,
+    temporal_samples: usize,
+}
+
+impl ComplexityAnalyzer {
+    /// Get a builder for the complexity analyzer.
+    pub fn builder() -> ComplexityAnalyzerBuilder {
+        ComplexityAnalyzerBuilder::new()
+    }
+
+    /// Create a new complexity analyzer with the default settings.
+    pub fn new() -> Self {
+        Self::builder().build()
+    }
+
+    /// Push a given frame.
+    pub fn push(&mut self, frame: &VideoFrame) -> Result<(), Error> {
+        if let Some(previous) = self.previous.take() {
+            let (luma, _) = self.luma(frame)?;
+
+            // the resolution may change in the middle of the stream
+            if luma.len() == previous.len() {
+                self.temporal += temporal_complexity(&previous, &luma);
+                self.temporal_samples += 1;
+            }
+
+            return Ok(());
+        }
+
+        let time = frame.pts().as_micros();
+
+        let sample = match (time, self.next_sample) {
+            (Some(time), Some(next)) => time >= next,
+            _ => true,
+        };
+
+        if !sample {
+            return Ok(());
+        }
+
+        if let Some(time) = time {
+            self.next_sample = Some(time + self.interval);
+        }
+
+        let (luma, width) = self.luma(frame)?;
+
+        self.spatial += spatial_complexity(&luma, width);
+        self.spatial_samples += 1;
+
+        self.previous = Some(luma);
+
+        Ok(())
+    }
+
+    /// Get the complexity report for all frames pushed so far.
+    pub fn report(&self) -> ComplexityReport {
+        let spatial = if self.spatial_samples > 0 {
+            self.spatial / self.spatial_samples as f64
+        } else {
+            0.0
+        };
+
+        let temporal = if self.temporal_samples > 0 {
+            self.temporal / self.temporal_samples as f64
+        } else {
+            0.0
+        };
+
+        ComplexityReport {
+            spatial,
+            temporal,
+            samples: self.spatial_samples,
+        }
+    }
+
+    /// Get downscaled luma of a given frame (packed, without any padding)
+    /// and its width.
+    fn luma(&mut self, frame: &VideoFrame) -> Result<(Vec<u8>, usize), Error> {
+        let (width, height) = analysis_size(frame.width(), frame.height());
+
+        let res = self.converter.convert_packed(frame, width, height, 1)?;
+
+        Ok((res, width))
+    }
+}
+
+impl Default for ComplexityAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Result of the complexity analysis.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ComplexityReport {
+    spatial: f64,
+    temporal: f64,
+    samples: usize,
+}
+
+impl ComplexityReport {
+    /// Get the average spatial complexity (0.0 - 1.0).
+    pub fn spatial_complexity(&self) -> f64 {
+        self.spatial
+    }
+
+    /// Get the average temporal complexity (0.0 - 1.0).
+    pub fn temporal_complexity(&self) -> f64 {
+        self.temporal
+    }
+
+    /// Get the number of analyzed samples.
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    /// Get the bit rate factor of the content relative to average content.
+    /// The factor is calculated as:
+    ///
+    /// ```text
+    /// (spatial / 0.04 + temporal / 0.02) / 2
+    /// ```
+    ///
+    /// and it is clamped to 0.5 - 2.0. The factor is 1.0 if nothing has been
+    /// analyzed.
+    pub fn bit_rate_factor(&self) -> f64 {
+        if self.samples == 0 {
+            return 1.0;
+        }
+
+        let spatial = self.spatial / REFERENCE_SPATIAL_COMPLEXITY;
+        let temporal = self.temporal / REFERENCE_TEMPORAL_COMPLEXITY;
+
+        let factor = (spatial + temporal) / 2.0;
+
+        factor.clamp(MIN_FACTOR, MAX_FACTOR)
+    }
+
+    /// Adjust a given ladder of renditions. Bit rates and CRF values of the
+    /// given renditions are expected to be suitable for average content.
+    /// Bit rates are multiplied by the bit rate factor and CRF values are
+    /// adjusted by the same amount (assuming that 6 CRF units halve the bit
+    /// rate).
+    pub fn recommend(&self, ladder: &[Rendition]) -> Vec<Rendition> {
+        let factor = self.bit_rate_factor();
+
+        ladder
+            .iter()
+            .map(|rendition| rendition.adjust(factor))
+            .collect()
+    }
+}
+
+/// Single rendition of an ABR ladder.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rendition {
+    width: usize,
+    height: usize,
+    bit_rate: u64,
+    crf: Option<f64>,
+}
+
+impl Rendition {
+    /// Create a new rendition with a given resolution and a given target bit
+    /// rate.
+    pub fn new(width: usize, height: usize, bit_rate: u64) -> Self {
+        Self {
+            width,
+            height,
+            bit_rate,
+            crf: None,
+        }
+    }
+
+    /// Set the CRF value of the rendition.
+    pub fn with_crf(mut self, crf: f64) -> Self {
+        self.crf = Some(crf);
+        self
+    }
+
+    /// Get width of the rendition.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Get height of the rendition.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Get the target bit rate.
+    pub fn bit_rate(&self) -> u64 {
+        self.bit_rate
+    }
+
+    /// Get the CRF value (if any).
+    pub fn crf(&self) -> Option<f64> {
+        self.crf
+    }
+
+    /// Apply the rendition to a given encoder builder. The method sets the
+    /// resolution, the bit rate and the `crf` encoder option (if the CRF
+    /// value is set).
+    pub fn apply(&self, builder: VideoEncoderBuilder) -> VideoEncoderBuilder {
+        let builder = builder
+            .width(self.width)
+            .height(self.height)
+            .bit_rate(self.bit_rate);
+
+        if let Some(crf) = self.crf {
+            builder.set_option("crf", crf)
+        } else {
+            builder
+        }
+    }
+
+    /// Adjust the rendition using a given bit rate factor.
+    fn adjust(&self, factor: f64) -> Self {
+        let bit_rate = (self.bit_rate as f64 * factor).round() as u64;
+
+        let crf = self.crf.map(|crf| {
+            let crf = crf - CRF_PER_HALVING * factor.log2();
+
+            // round to 0.5
+            ((crf * 2.0).round() / 2.0).clamp(0.0, MAX_CRF)
+        });
+
+        Self {
+            width: self.width,
+            height: self.height,
+            bit_rate,
+            crf,
+        }
+    }
+}
+
+/// Calculate the mean absolute luma gradient of a given image (0.0 - 1.0).
+fn spatial_complexity(luma: &[u8], width: usize) -> f64 {
+    if width == 0 {
+        return 0.0;
+    }
+
+    let height = luma.len() / width;
+
+    let mut sum = 0u64;
+    let mut count = 0u64;
+
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * width + x;
+
+            let current = luma[i] as i32;
+
+            if x + 1 < width {
+                sum += (luma[i + 1] as i32 - current).unsigned_abs() as u64;
+                count += 1;
+            }
+
+            if y + 1 < height {
+                sum += (luma[i + width] as i32 - current).unsigned_abs() as u64;
+                count += 1;
+            }
+        }
+    }
+
+    if count == 0 {
+        0.0
+    } else {
+        sum as f64 / count as f64 / 255.0
+    }
+}
+
+/// Calculate the mean absolute difference of two images of the same size
+/// (0.0 - 1.0).
+fn temporal_complexity(a: &[u8], b: &[u8]) -> f64 {
+    mean_absolute_difference(a, b) / 255.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{spatial_complexity, temporal_complexity, ComplexityReport, Rendition};
+
+    #[test]
+    fn test_complexity() {
+        let flat = [128u8; 16];
+        let stripes = [0u8, 255].repeat(8);
+
+        assert_eq!(spatial_complexity(&flat, 4), 0.0);
+        assert_eq!(temporal_complexity(&flat, &flat), 0.0);
+
+        // horizontal neighbors differ, vertical neighbors do not
+        assert_eq!(spatial_complexity(&stripes, 4), 0.5);
+
+        assert!((temporal_complexity(&flat, &stripes) - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_recommendation() {
+        let ladder = [
+            Rendition::new(1920, 1080, 6_000_000),
+            Rendition::new(1280, 720, 3_000_000).with_crf(23.0),
+        ];
+
+        let simple = ComplexityReport {
+            spatial: 0.01,
+            temporal: 0.0,
+            samples: 10,
+        };
+
+        let complex = ComplexityReport {
+            spatial: 0.08,
+            temporal: 0.04,
+            samples: 10,
+        };
+
+        assert_eq!(simple.bit_rate_factor(), 0.5);
+        assert_eq!(complex.bit_rate_factor(), 2.0);
+
+        let simple = simple.recommend(&ladder);
+
+        assert_eq!(simple[0].bit_rate(), 3_000_000);
+        assert_eq!(simple[1].crf(), Some(29.0));
+
+        let complex = complex.recommend(&ladder);
+
+        assert_eq!(complex[0].bit_rate(), 12_000_000);
+        assert_eq!(complex[1].crf(), Some(17.0));
+        assert_eq!(complex[1].width(), 1280);
+    }
+}
@@ -0,0 +1,46 @@
+#include <libavutil/frame.h>
+#include <libavutil/pixdesc.h>
+
+AVFrame* ffw_pixel_alloc_frame(int format, int width, int height);
+int ffw_pixel_get_size(const AVPixFmtDescriptor* desc, int plane);
+
+// Allocate a new frame with a given pixel format and size (including its
+// buffers).
+AVFrame* ffw_pixel_alloc_frame(int format, int width, int height) {
+    AVFrame* frame = av_frame_alloc();
+
+    if (frame == NULL) {
+        return NULL;
+    }
+
+    frame->format = format;
+    frame->width = width;
+    frame->height = height;
+
+    if (av_frame_get_buffer(frame, 0) != 0) {
+        av_frame_free(&frame);
+    }
+
+    return frame;
+}
+
+// Get size of a single pixel in a given plane or 0 if the pixel size cannot
+// be determined (e.g. for packed YUV formats with macro pixels).
+int ffw_pixel_get_size(const AVPixFmtDescriptor* desc, int plane) {
+    int pixel_size = 0;
+    int i;
+
+    for (i = 0; i < desc->nb_components; i++) {
+        if (desc->comp[i].plane != plane) {
+            continue;
+        }
+
+        if (pixel_size && pixel_size != desc->comp[i].step) {
+            return 0;
+        }
+
+        pixel_size = desc->comp[i].step;
+    }
+
+    return pixel_size;
+}
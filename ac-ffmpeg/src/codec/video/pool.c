@@ -0,0 +1,204 @@
+#include <limits.h>
+
+#include <libavcodec/avcodec.h>
+#include <libavutil/buffer.h>
+#include <libavutil/common.h>
+#include <libavutil/frame.h>
+#include <libavutil/imgutils.h>
+#include <libavutil/pixdesc.h>
+
+// maximum stride alignment required by SIMD optimizations
+#define STRIDE_ALIGN 64
+
+// extra space reserved for decoders that need larger coded dimensions
+#define EXTRA_WIDTH 64
+#define EXTRA_HEIGHT 64
+
+typedef struct FramePool {
+    AVBufferPool* pools[4];
+    size_t sizes[4];
+    int planes;
+    int format;
+    int width;
+    int height;
+} FramePool;
+
+FramePool* ffw_frame_pool_new(int format, int width, int height);
+int ffw_frame_pool_get_buffer(AVCodecContext* avctx, AVFrame* frame, int flags);
+void ffw_frame_pool_free(FramePool* pool);
+
+static int ffw_frame_pool_get_layout(
+    int format,
+    int width,
+    int height,
+    int* linesize,
+    size_t* sizes) {
+    const AVPixFmtDescriptor* desc;
+    int planes;
+    int ret;
+    int i;
+    int h;
+    int s;
+
+    desc = av_pix_fmt_desc_get(format);
+
+    if (!desc || (desc->flags & (AV_PIX_FMT_FLAG_HWACCEL | AV_PIX_FMT_FLAG_PAL))) {
+        return AVERROR(EINVAL);
+    }
+
+    planes = av_pix_fmt_count_planes(format);
+
+    if (planes <= 0 || planes > 4) {
+        return AVERROR(EINVAL);
+    }
+
+    if ((ret = av_image_fill_linesizes(linesize, format, FFALIGN(width, STRIDE_ALIGN))) < 0) {
+        return ret;
+    }
+
+    for (i = 0; i < planes; i++) {
+        linesize[i] = FFALIGN(linesize[i], STRIDE_ALIGN);
+
+        s = (i == 1 || i == 2) ? desc->log2_chroma_h : 0;
+        h = (height + (1 << s) - 1) >> s;
+
+        // some SIMD functions may read a few bytes past the end of the
+        // plane
+        sizes[i] = (size_t)linesize[i] * h + 16 + STRIDE_ALIGN - 1;
+    }
+
+    for (i = planes; i < 4; i++) {
+        linesize[i] = 0;
+        sizes[i] = 0;
+    }
+
+    return planes;
+}
+
+FramePool* ffw_frame_pool_new(int format, int width, int height) {
+    FramePool* res;
+    int linesize[4];
+    size_t sizes[4];
+    int planes;
+    int i;
+
+    if (width <= 0 || height <= 0) {
+        return NULL;
+    }
+
+    planes = ffw_frame_pool_get_layout(
+        format,
+        width + EXTRA_WIDTH,
+        height + EXTRA_HEIGHT,
+        linesize,
+        sizes);
+
+    if (planes < 0) {
+        return NULL;
+    }
+
+    if (!(res = calloc(1, sizeof(FramePool)))) {
+        return NULL;
+    }
+
+    res->planes = planes;
+    res->format = format;
+    res->width = width;
+    res->height = height;
+
+    for (i = 0; i < planes; i++) {
+        if (sizes[i] > INT_MAX) {
+            goto err;
+        }
+
+        res->sizes[i] = sizes[i];
+
+        if (!(res->pools[i] = av_buffer_pool_init(sizes[i], NULL))) {
+            goto err;
+        }
+    }
+
+    return res;
+
+err:
+    ffw_frame_pool_free(res);
+
+    return NULL;
+}
+
+int ffw_frame_pool_get_buffer(AVCodecContext* avctx, AVFrame* frame, int flags) {
+    FramePool* pool;
+    int linesize[4];
+    size_t sizes[4];
+    int linesize_align[AV_NUM_DATA_POINTERS];
+    int planes;
+    int width;
+    int height;
+    int i;
+
+    pool = avctx->opaque;
+
+    if (!pool
+        || !(avctx->codec->capabilities & AV_CODEC_CAP_DR1)
+        || avctx->hw_frames_ctx
+        || frame->format != pool->format) {
+        return avcodec_default_get_buffer2(avctx, frame, flags);
+    }
+
+    width = frame->width;
+    height = frame->height;
+
+    avcodec_align_dimensions2(avctx, &width, &height, linesize_align);
+
+    planes = ffw_frame_pool_get_layout(frame->format, width, height, linesize, sizes);
+
+    if (planes != pool->planes) {
+        return avcodec_default_get_buffer2(avctx, frame, flags);
+    }
+
+    for (i = 0; i < planes; i++) {
+        if (sizes[i] > pool->sizes[i]) {
+            return avcodec_default_get_buffer2(avctx, frame, flags);
+        } else if (linesize_align[i] > 0 && (linesize[i] % linesize_align[i]) != 0) {
+            return avcodec_default_get_buffer2(avctx, frame, flags);
+        }
+    }
+
+    for (i = 0; i < planes; i++) {
+        if (!(frame->buf[i] = av_buffer_pool_get(pool->pools[i]))) {
+            goto err;
+        }
+
+        frame->data[i] = frame->buf[i]->data;
+        frame->linesize[i] = linesize[i];
+    }
+
+    frame->extended_data = frame->data;
+
+    return 0;
+
+err:
+    for (i = 0; i < 4; i++) {
+        av_buffer_unref(&frame->buf[i]);
+
+        frame->data[i] = NULL;
+        frame->linesize[i] = 0;
+    }
+
+    return AVERROR(ENOMEM);
+}
+
+void ffw_frame_pool_free(FramePool* pool) {
+    int i;
+
+    if (!pool) {
+        return;
+    }
+
+    // the buffer pools will be freed when all their buffers are returned
+    for (i = 0; i < 4; i++) {
+        av_buffer_pool_uninit(&pool->pools[i]);
+    }
+
+    free(pool);
+}
@@ -0,0 +1,163 @@
+//! Frame buffer pooling for video decoders.
+
+use std::{
+    os::raw::{c_int, c_void},
+    sync::Arc,
+};
+
+use crate::{codec::video::PixelFormat, Error};
+
+extern "C" {
+    fn ffw_frame_pool_new(format: c_int, width: c_int, height: c_int) -> *mut c_void;
+    fn ffw_frame_pool_free(pool: *mut c_void);
+}
+
+/// Owned raw frame pool.
+struct RawFramePool {
+    ptr: *mut c_void,
+}
+
+impl Drop for RawFramePool {
+    fn drop(&mut self) {
+        unsafe { ffw_frame_pool_free(self.ptr) }
+    }
+}
+
+unsafe impl Send for RawFramePool {}
+unsafe impl Sync for RawFramePool {}
+
+/// Pool of video frame buffers. Decoders using the pool reuse the picture
+/// buffers of frames that have been dropped instead of allocating new ones,
+/// which reduces allocator pressure in high-throughput applications.
+///
+/// The pool is created for a given pixel format and frame size. Decoded
+/// frames that do not fit into the pool buffers (e.g. after a resolution
+/// change) and frames of decoders that do not support custom buffers are
+/// allocated as usual. A single pool can be shared by multiple decoders
+/// (e.g. when a decoder is re-created after an input restart). The pool
+/// buffers are released once the pool, all decoders using it and all frames
+/// allocated from it are dropped.
+#[derive(Clone)]
+pub struct VideoFramePool {
+    inner: Arc<RawFramePool>,
+    pixel_format: PixelFormat,
+    width: usize,
+    height: usize,
+}
+
+impl VideoFramePool {
+    /// Create a new frame pool for a given pixel format and frame size.
+    pub fn new(pixel_format: PixelFormat, width: usize, height: usize) -> Result<Self, Error> {
+        let ptr = unsafe { ffw_frame_pool_new(pixel_format.into_raw(), width as _, height as _) };
+
+        if ptr.is_null() {
+            return Err(Error::new("unable to create a frame pool"));
+        }
+
+        let res = Self {
+            inner: Arc::new(RawFramePool { ptr }),
+            pixel_format,
+            width,
+            height,
+        };
+
+        Ok(res)
+    }
+
+    /// Get the pixel format of the pool.
+    pub fn pixel_format(&self) -> PixelFormat {
+        self.pixel_format
+    }
+
+    /// Get the frame width of the pool.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Get the frame height of the pool.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Get raw pointer.
+    pub(crate) fn as_ptr(&self) -> *mut c_void {
+        self.inner.ptr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::VideoFramePool;
+
+    use crate::{
+        codec::{
+            video::{frame::get_pixel_format, VideoDecoder, VideoFrame},
+            Decoder,
+        },
+        format::{demuxer::Demuxer, io::IO},
+        test_util::encode_video,
+    };
+
+    /// Decode 10 frames (64x48) using a given frame pool.
+    fn decode(pool: &VideoFramePool) -> Vec<VideoFrame> {
+        let data = encode_video(64, 48, 10, 5);
+
+        let mut demuxer = Demuxer::builder()
+            .build(IO::from_read_stream(Cursor::new(data)))
+            .unwrap()
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)
+            .unwrap();
+
+        let mut decoder = VideoDecoder::from_stream(&demuxer.streams()[0])
+            .unwrap()
+            .frame_pool(pool)
+            .build()
+            .unwrap();
+
+        let mut res = Vec::new();
+
+        while let Some(packet) = demuxer.take().unwrap() {
+            decoder.push(packet).unwrap();
+
+            while let Some(frame) = decoder.take().unwrap() {
+                res.push(frame);
+            }
+        }
+
+        decoder.flush().unwrap();
+
+        while let Some(frame) = decoder.take().unwrap() {
+            res.push(frame);
+        }
+
+        res
+    }
+
+    #[test]
+    fn test_frame_pool() {
+        let pixel_format = get_pixel_format("yuv420p");
+
+        let pool = VideoFramePool::new(pixel_format, 64, 48).unwrap();
+
+        let frames = decode(&pool);
+
+        // the frames outlive both the decoder and the pool
+        drop(pool);
+
+        assert_eq!(frames.len(), 10);
+
+        for frame in frames {
+            assert!(frame.pixel_format() == pixel_format);
+            assert_eq!((frame.width(), frame.height()), (64, 48));
+            assert!(frame.planes()[0].data().iter().all(|&b| b < 32));
+        }
+
+        // frames not fitting into the pool are allocated as usual
+        let pool = VideoFramePool::new(pixel_format, 32, 24).unwrap();
+
+        assert_eq!(decode(&pool).len(), 10);
+    }
+}
@@ -0,0 +1,419 @@
+//! Frame statistics and automated poster frame selection.
+
+use std::time::Duration;
+
+use crate::{
+    codec::video::{
+        convert::FrameConverter, fit::Color, frame::get_pixel_format, scaler::Algorithm, VideoFrame,
+    },
+    time::Timestamp,
+    Error,
+};
+
+/// Maximum width/height of the downscaled frame used for analysis.
+const ANALYSIS_SIZE: usize = 256;
+
+/// Number of histogram levels per color component used for dominant color
+/// detection.
+const COLOR_LEVELS: usize = 16;
+
+/// Statistics of a single video frame.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FrameStatistics {
+    brightness: f64,
+    contrast: f64,
+    sharpness: f64,
+    dominant_color: Color,
+}
+
+impl FrameStatistics {
+    /// Compute statistics of a given RGB24 image.
+    fn from_rgb(data: &[u8], stride: usize, width: usize, height: usize) -> Self {
+        let pixels = width * height;
+
+        if pixels == 0 {
+            return Self {
+                brightness: 0.0,
+                contrast: 0.0,
+                sharpness: 0.0,
+                dominant_color: Color::BLACK,
+            };
+        }
+
+        let shift = 8 - COLOR_LEVELS.trailing_zeros();
+
+        let mut luma = Vec::with_capacity(pixels);
+        let mut histogram = vec![(0usize, 0u64, 0u64, 0u64); COLOR_LEVELS.pow(3)];
+
+        for y in 0..height {
+            let line = &data[y * stride..y * stride + 3 * width];
+
+            for pixel in line.chunks_exact(3) {
+                let r = pixel[0] as usize;
+                let g = pixel[1] as usize;
+                let b = pixel[2] as usize;
+
+                luma.push((0.299 * r as f64 + 0.587 * g as f64 + 0.114 * b as f64) / 255.0);
+
+                let bin =
+                    ((r >> shift) * COLOR_LEVELS + (g >> shift)) * COLOR_LEVELS + (b >> shift);
+
+                let entry = &mut histogram[bin];
+
+                entry.0 += 1;
+                entry.1 += r as u64;
+                entry.2 += g as u64;
+                entry.3 += b as u64;
+            }
+        }
+
+        let brightness = luma.iter().sum::<f64>() / pixels as f64;
+
+        let variance = luma
+            .iter()
+            .map(|l| (l - brightness) * (l - brightness))
+            .sum::<f64>()
+            / pixels as f64;
+
+        // variance of the Laplacian
+        let mut laplacian = Vec::new();
+
+        for y in 1..height.saturating_sub(1) {
+            for x in 1..width.saturating_sub(1) {
+                let i = y * width + x;
+
+                laplacian.push(
+                    luma[i - 1] + luma[i + 1] + luma[i - width] + luma[i + width] - 4.0 * luma[i],
+                );
+            }
+        }
+
+        let sharpness = if laplacian.is_empty() {
+            0.0
+        } else {
+            let n = laplacian.len() as f64;
+            let mean = laplacian.iter().sum::<f64>() / n;
+
+            laplacian
+                .iter()
+                .map(|l| (l - mean) * (l - mean))
+                .sum::<f64>()
+                / n
+        };
+
+        let (count, r, g, b) = histogram
+            .into_iter()
+            .max_by_key(|entry| entry.0)
+            .unwrap_or_default();
+
+        let count = count.max(1) as u64;
+
+        let dominant_color = Color::rgb((r / count) as u8, (g / count) as u8, (b / count) as u8);
+
+        Self {
+            brightness,
+            contrast: variance.sqrt(),
+            sharpness,
+            dominant_color,
+        }
+    }
+
+    /// Get the average brightness (0.0 - 1.0).
+    pub fn brightness(&self) -> f64 {
+        self.brightness
+    }
+
+    /// Get contrast, i.e. standard deviation of the brightness.
+    pub fn contrast(&self) -> f64 {
+        self.contrast
+    }
+
+    /// Get sharpness, i.e. variance of the Laplacian of the brightness.
+    /// Blurry frames (e.g. motion blur or transitions) have low values.
+    pub fn sharpness(&self) -> f64 {
+        self.sharpness
+    }
+
+    /// Get the dominant color.
+    pub fn dominant_color(&self) -> Color {
+        self.dominant_color
+    }
+
+    /// Get the poster score of the frame. Higher is better. The score
+    /// prefers well exposed frames with a lot of detail and it is
+    /// calculated as:
+    ///
+    /// ```text
+    /// (1 - 2 * |brightness - 0.5|) * (contrast + sqrt(sharpness))
+    /// ```
+    pub fn score(&self) -> f64 {
+        let exposure = 1.0 - 2.0 * (self.brightness - 0.5).abs();
+
+        exposure * (self.contrast + self.sharpness.sqrt())
+    }
+}
+
+/// Frame analyzer. It computes statistics of video frames. Frames are
+/// downscaled before the analysis, so the cost of the analysis does not
+/// depend on the frame resolution.
+pub struct FrameAnalyzer {
+    converter: FrameConverter,
+}
+
+impl FrameAnalyzer {
+    /// Create a new frame analyzer.
+    pub fn new() -> Self {
+        Self {
+            converter: FrameConverter::new(get_pixel_format("rgb24"), Algorithm::Area),
+        }
+    }
+
+    /// Compute statistics of a given frame.
+    pub fn analyze(&mut self, frame: &VideoFrame) -> Result<FrameStatistics, Error> {
+        let (width, height) = analysis_size(frame.width(), frame.height());
+
+        let rgb = self.converter.convert(frame, width, height)?;
+
+        let planes = rgb.planes();
+        let plane = &planes[0];
+
+        let res =
+            FrameStatistics::from_rgb(plane.data(), plane.line_size(), rgb.width(), rgb.height());
+
+        Ok(res)
+    }
+}
+
+impl Default for FrameAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Get size of the downscaled frame used for analysis.
+pub(crate) fn analysis_size(width: usize, height: usize) -> (usize, usize) {
+    let max = width.max(height);
+
+    if max <= ANALYSIS_SIZE {
+        return (width.max(1), height.max(1));
+    }
+
+    let twidth = (width * ANALYSIS_SIZE + max / 2) / max;
+    let theight = (height * ANALYSIS_SIZE + max / 2) / max;
+
+    (twidth.max(1), theight.max(1))
+}
+
+/// Poster frame candidate.
+pub struct PosterCandidate {
+    frame: VideoFrame,
+    statistics: FrameStatistics,
+}
+
+impl PosterCandidate {
+    /// Get the frame.
+    pub fn frame(&self) -> &VideoFrame {
+        &self.frame
+    }
+
+    /// Get the frame statistics.
+    pub fn statistics(&self) -> &FrameStatistics {
+        &self.statistics
+    }
+
+    /// Take the frame.
+    pub fn into_frame(self) -> VideoFrame {
+        self.frame
+    }
+}
+
+/// Builder for the poster selector.
+pub struct PosterSelectorBuilder {
+    min_brightness: f64,
+    max_brightness: f64,
+    skip: Duration,
+    interval: Duration,
+}
+
+impl PosterSelectorBuilder {
+    /// Create a new builder.
+    fn new() -> Self {
+        Self {
+            min_brightness: 0.1,
+            max_brightness: 0.9,
+            skip: Duration::from_secs(0),
+            interval: Duration::from_secs(1),
+        }
+    }
+
+    /// Ignore frames with average brightness out of a given range (e.g.
+    /// black frames and fades). The default is 0.1 - 0.9.
+    pub fn brightness_range(mut self, min: f64, max: f64) -> Self {
+        self.min_brightness = min;
+        self.max_brightness = max;
+        self
+    }
+
+    /// Ignore frames with presentation timestamp lower than a given value
+    /// (e.g. intros and logos). The default is zero.
+    pub fn skip(mut self, duration: Duration) -> Self {
+        self.skip = duration;
+        self
+    }
+
+    /// Analyze at most one frame per a given interval. The default is one
+    /// second. Use zero to analyze all frames.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Build the poster selector.
+    pub fn build(self) -> PosterSelector {
+        PosterSelector {
+            min_brightness: self.min_brightness,
+            max_brightness: self.max_brightness,
+            skip: self.skip.as_micros() as i64,
+            interval: self.interval.as_micros() as i64,
+            next_sample: None,
+            analyzer: FrameAnalyzer::new(),
+            best: None,
+        }
+    }
+}
+
+/// Poster frame selector. It scores pushed video frames and keeps the best
+/// one (see `FrameStatistics::score()`). It can be used for automated cover
+/// selection. The selected frame can be encoded as an image using e.g. the
+/// `mjpeg` or `png` encoder.
+pub struct PosterSelector {
+    min_brightness: f64,
+    max_brightness: f64,
+    skip: i64,
+    interval: i64,
+    next_sample: Option<i64>,
+    analyzer: FrameAnalyzer,
+    best: Option<PosterCandidate>,
+}
+
+impl PosterSelector {
+    /// Get a builder for the poster selector.
+    pub fn builder() -> PosterSelectorBuilder {
+        PosterSelectorBuilder::new()
+    }
+
+    /// Create a new poster selector with the default settings.
+    pub fn new() -> Self {
+        Self::builder().build()
+    }
+
+    /// Push a given frame. The method returns statistics of the frame if
+    /// the frame has been analyzed.
+    pub fn push(&mut self, frame: &VideoFrame) -> Result<Option<FrameStatistics>, Error> {
+        let pts = frame.pts();
+
+        if !self.should_analyze(pts) {
+            return Ok(None);
+        }
+
+        if let Some(time) = pts.as_micros() {
+            self.next_sample = Some(time + self.interval);
+        }
+
+        let statistics = self.analyzer.analyze(frame)?;
+
+        let brightness = statistics.brightness();
+
+        if brightness < self.min_brightness || brightness > self.max_brightness {
+            return Ok(Some(statistics));
+        }
+
+        let better = self
+            .best
+            .as_ref()
+            .map(|best| statistics.score() > best.statistics.score())
+            .unwrap_or(true);
+
+        if better {
+            self.best = Some(PosterCandidate {
+                frame: frame.clone(),
+                statistics,
+            });
+        }
+
+        Ok(Some(statistics))
+    }
+
+    /// Get the best candidate found so far.
+    pub fn best(&self) -> Option<&PosterCandidate> {
+        self.best.as_ref()
+    }
+
+    /// Take the best candidate.
+    pub fn into_best(self) -> Option<PosterCandidate> {
+        self.best
+    }
+
+    /// Check if a frame with a given timestamp should be analyzed.
+    fn should_analyze(&self, pts: Timestamp) -> bool {
+        if let Some(time) = pts.as_micros() {
+            if time < self.skip {
+                return false;
+            }
+
+            self.next_sample.map(|next| time >= next).unwrap_or(true)
+        } else {
+            true
+        }
+    }
+}
+
+impl Default for PosterSelector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{analysis_size, FrameStatistics};
+
+    use crate::codec::video::Color;
+
+    #[test]
+    fn test_uniform_image() {
+        let data = [64u8, 128, 192].repeat(16);
+
+        let stats = FrameStatistics::from_rgb(&data, 12, 4, 4);
+
+        assert_eq!(stats.contrast(), 0.0);
+        assert_eq!(stats.sharpness(), 0.0);
+        assert_eq!(stats.dominant_color(), Color::rgb(64, 128, 192));
+    }
+
+    #[test]
+    fn test_checkerboard() {
+        let mut data = Vec::new();
+
+        for y in 0..8 {
+            for x in 0..8 {
+                let v = if (x + y) % 2 == 0 { 0 } else { 255 };
+
+                data.extend_from_slice(&[v, v, v]);
+            }
+        }
+
+        let stats = FrameStatistics::from_rgb(&data, 24, 8, 8);
+
+        assert!((stats.brightness() - 0.5).abs() < 0.01);
+        assert!(stats.contrast() > 0.49);
+        assert!(stats.sharpness() > 1.0);
+        assert!(stats.score() > 1.0);
+    }
+
+    #[test]
+    fn test_analysis_size() {
+        assert_eq!(analysis_size(1920, 1080), (256, 144));
+        assert_eq!(analysis_size(100, 50), (100, 50));
+    }
+}
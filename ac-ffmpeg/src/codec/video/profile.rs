@@ -0,0 +1,355 @@
+//! H.264 and HEVC profiles and levels.
+
+/// H.264 profile.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum H264Profile {
+    /// Baseline profile (no B-frames, no CABAC).
+    Baseline,
+    /// Main profile.
+    Main,
+    /// High profile (8-bit 4:2:0).
+    High,
+    /// High 10 profile (up to 10-bit 4:2:0).
+    High10,
+    /// High 4:2:2 profile (up to 10-bit 4:2:2).
+    High422,
+    /// High 4:4:4 Predictive profile (up to 14-bit 4:4:4).
+    High444,
+}
+
+impl H264Profile {
+    /// Get the profile name (as used by the encoder options).
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Baseline => "baseline",
+            Self::Main => "main",
+            Self::High => "high",
+            Self::High10 => "high10",
+            Self::High422 => "high422",
+            Self::High444 => "high444",
+        }
+    }
+
+    /// Get the `profile_idc` value.
+    pub fn idc(self) -> u32 {
+        match self {
+            Self::Baseline => 66,
+            Self::Main => 77,
+            Self::High => 100,
+            Self::High10 => 110,
+            Self::High422 => 122,
+            Self::High444 => 244,
+        }
+    }
+}
+
+/// HEVC profile.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum HevcProfile {
+    /// Main profile (8-bit 4:2:0).
+    Main,
+    /// Main 10 profile (up to 10-bit 4:2:0).
+    Main10,
+    /// Main Still Picture profile (a single 8-bit 4:2:0 picture).
+    MainStillPicture,
+}
+
+impl HevcProfile {
+    /// Get the profile name (as used by the encoder options).
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Main => "main",
+            Self::Main10 => "main10",
+            Self::MainStillPicture => "mainstillpicture",
+        }
+    }
+
+    /// Get the `general_profile_idc` value.
+    pub fn idc(self) -> u32 {
+        match self {
+            Self::Main => 1,
+            Self::Main10 => 2,
+            Self::MainStillPicture => 3,
+        }
+    }
+}
+
+/// H.264 level.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum H264Level {
+    /// Level 1.
+    L1,
+    /// Level 1b.
+    L1b,
+    /// Level 1.1.
+    L1_1,
+    /// Level 1.2.
+    L1_2,
+    /// Level 1.3.
+    L1_3,
+    /// Level 2.
+    L2,
+    /// Level 2.1.
+    L2_1,
+    /// Level 2.2.
+    L2_2,
+    /// Level 3.
+    L3,
+    /// Level 3.1.
+    L3_1,
+    /// Level 3.2.
+    L3_2,
+    /// Level 4.
+    L4,
+    /// Level 4.1.
+    L4_1,
+    /// Level 4.2.
+    L4_2,
+    /// Level 5.
+    L5,
+    /// Level 5.1.
+    L5_1,
+    /// Level 5.2.
+    L5_2,
+    /// Level 6.
+    L6,
+    /// Level 6.1.
+    L6_1,
+    /// Level 6.2.
+    L6_2,
+}
+
+impl H264Level {
+    /// Get the level name (e.g. "3.1").
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::L1 => "1",
+            Self::L1b => "1b",
+            Self::L1_1 => "1.1",
+            Self::L1_2 => "1.2",
+            Self::L1_3 => "1.3",
+            Self::L2 => "2",
+            Self::L2_1 => "2.1",
+            Self::L2_2 => "2.2",
+            Self::L3 => "3",
+            Self::L3_1 => "3.1",
+            Self::L3_2 => "3.2",
+            Self::L4 => "4",
+            Self::L4_1 => "4.1",
+            Self::L4_2 => "4.2",
+            Self::L5 => "5",
+            Self::L5_1 => "5.1",
+            Self::L5_2 => "5.2",
+            Self::L6 => "6",
+            Self::L6_1 => "6.1",
+            Self::L6_2 => "6.2",
+        }
+    }
+
+    /// Get the `level_idc` value (level multiplied by 10, level 1b is
+    /// signaled as 9).
+    pub fn idc(self) -> u32 {
+        match self {
+            Self::L1 => 10,
+            Self::L1b => 9,
+            Self::L1_1 => 11,
+            Self::L1_2 => 12,
+            Self::L1_3 => 13,
+            Self::L2 => 20,
+            Self::L2_1 => 21,
+            Self::L2_2 => 22,
+            Self::L3 => 30,
+            Self::L3_1 => 31,
+            Self::L3_2 => 32,
+            Self::L4 => 40,
+            Self::L4_1 => 41,
+            Self::L4_2 => 42,
+            Self::L5 => 50,
+            Self::L5_1 => 51,
+            Self::L5_2 => 52,
+            Self::L6 => 60,
+            Self::L6_1 => 61,
+            Self::L6_2 => 62,
+        }
+    }
+}
+
+/// HEVC level.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum HevcLevel {
+    /// Level 1.
+    L1,
+    /// Level 2.
+    L2,
+    /// Level 2.1.
+    L2_1,
+    /// Level 3.
+    L3,
+    /// Level 3.1.
+    L3_1,
+    /// Level 4.
+    L4,
+    /// Level 4.1.
+    L4_1,
+    /// Level 5.
+    L5,
+    /// Level 5.1.
+    L5_1,
+    /// Level 5.2.
+    L5_2,
+    /// Level 6.
+    L6,
+    /// Level 6.1.
+    L6_1,
+    /// Level 6.2.
+    L6_2,
+}
+
+impl HevcLevel {
+    /// Get the level name (e.g. "3.1").
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::L1 => "1",
+            Self::L2 => "2",
+            Self::L2_1 => "2.1",
+            Self::L3 => "3",
+            Self::L3_1 => "3.1",
+            Self::L4 => "4",
+            Self::L4_1 => "4.1",
+            Self::L5 => "5",
+            Self::L5_1 => "5.1",
+            Self::L5_2 => "5.2",
+            Self::L6 => "6",
+            Self::L6_1 => "6.1",
+            Self::L6_2 => "6.2",
+        }
+    }
+
+    /// Get the `general_level_idc` value (level multiplied by 30).
+    pub fn idc(self) -> u32 {
+        match self {
+            Self::L1 => 30,
+            Self::L2 => 60,
+            Self::L2_1 => 63,
+            Self::L3 => 90,
+            Self::L3_1 => 93,
+            Self::L4 => 120,
+            Self::L4_1 => 123,
+            Self::L5 => 150,
+            Self::L5_1 => 153,
+            Self::L5_2 => 156,
+            Self::L6 => 180,
+            Self::L6_1 => 183,
+            Self::L6_2 => 186,
+        }
+    }
+}
+
+/// Codec-specific profile or level setting.
+#[derive(Copy, Clone)]
+pub(crate) struct CodecSetting {
+    /// Name of the codec the setting applies to.
+    pub codec: &'static str,
+    /// Name of the setting (as used by the encoder private options).
+    pub name: &'static str,
+    /// Numeric value used if the encoder has no private option.
+    pub idc: u32,
+}
+
+impl From<H264Profile> for CodecSetting {
+    fn from(profile: H264Profile) -> Self {
+        Self {
+            codec: "h264",
+            name: profile.name(),
+            idc: profile.idc(),
+        }
+    }
+}
+
+impl From<HevcProfile> for CodecSetting {
+    fn from(profile: HevcProfile) -> Self {
+        Self {
+            codec: "hevc",
+            name: profile.name(),
+            idc: profile.idc(),
+        }
+    }
+}
+
+impl From<H264Level> for CodecSetting {
+    fn from(level: H264Level) -> Self {
+        Self {
+            codec: "h264",
+            name: level.name(),
+            idc: level.idc(),
+        }
+    }
+}
+
+impl From<HevcLevel> for CodecSetting {
+    fn from(level: HevcLevel) -> Self {
+        Self {
+            codec: "hevc",
+            name: level.name(),
+            idc: level.idc(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{H264Level, H264Profile, HevcProfile};
+
+    use crate::{
+        codec::video::{frame::get_pixel_format, VideoEncoder, VideoEncoderBuilder},
+        time::TimeBase,
+    };
+
+    /// Get a builder of a given encoder with all mandatory parameters set.
+    fn encoder(codec: &str) -> Option<VideoEncoderBuilder> {
+        let builder = VideoEncoder::builder(codec)
+            .ok()?
+            .pixel_format(get_pixel_format("yuv420p"))
+            .width(64)
+            .height(48)
+            .time_base(TimeBase::new(1, 25));
+
+        Some(builder)
+    }
+
+    #[test]
+    fn test_codec_settings() {
+        // settings of a different codec
+        assert!(encoder("mpeg4")
+            .unwrap()
+            .h264_profile(H264Profile::High)
+            .build()
+            .is_err());
+
+        assert!(encoder("mpeg4")
+            .unwrap()
+            .h264_level(H264Level::L4_1)
+            .build()
+            .is_err());
+
+        // the encoder does not support presets
+        assert!(encoder("mpeg4").unwrap().preset("fast").build().is_err());
+
+        // libx264 is an optional dependency of FFmpeg
+        if let Some(builder) = encoder("libx264") {
+            assert!(builder
+                .h264_profile(H264Profile::High)
+                .h264_level(H264Level::L4_1)
+                .preset("veryfast")
+                .tune("zerolatency")
+                .build()
+                .is_ok());
+
+            assert!(encoder("libx264")
+                .unwrap()
+                .hevc_profile(HevcProfile::Main)
+                .build()
+                .is_err());
+        }
+    }
+}
@@ -0,0 +1,413 @@
+//! Video speed change.
+
+use crate::{
+    codec::{
+        video::{filter::LazyVideoFilterGraph, interpolate::InterpolationMode, VideoFrame},
+        CodecError,
+    },
+    time::Timestamp,
+    Error,
+};
+
+/// Builder for the video retimer.
+pub struct VideoRetimerBuilder {
+    rate: f64,
+    frame_rate: Option<(u32, u32)>,
+    interpolation: InterpolationMode,
+    origin: Option<Timestamp>,
+}
+
+impl VideoRetimerBuilder {
+    /// Create a new builder.
+    fn new() -> Self {
+        Self {
+            rate: 1.0,
+            frame_rate: None,
+            interpolation: InterpolationMode::Duplicate,
+            origin: None,
+        }
+    }
+
+    /// Set the playback rate (e.g. 2.0 for double speed or 0.5 for half
+    /// speed). The default is 1.0.
+    pub fn rate(mut self, rate: f64) -> Self {
+        self.rate = rate;
+        self
+    }
+
+    /// Set constant output frame rate. If not set, the frames will only be
+    /// retimed and there will be no frames dropped or generated.
+    pub fn frame_rate(mut self, num: u32, den: u32) -> Self {
+        self.frame_rate = Some((num, den));
+        self
+    }
+
+    /// Set the method used for generating frames (i.e. dropping or
+    /// duplicating frames, blending neighboring frames or motion compensated
+    /// interpolation). The option has effect only if the output frame rate
+    /// is set. The default is `InterpolationMode::Duplicate`. Note that the
+    /// motion compensated interpolation gives the smoothest slow motion but
+    /// it is also very CPU intensive.
+    pub fn interpolation(mut self, interpolation: InterpolationMode) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    /// Set the timestamp that will stay unchanged after retiming. By default,
+    /// the first valid presentation timestamp is used. Pushing a frame
+    /// without a timestamp fails if the output frame rate is set and the
+    /// origin is not known yet.
+    pub fn origin(mut self, origin: Timestamp) -> Self {
+        self.origin = Some(origin);
+        self
+    }
+
+    /// Build the retimer.
+    pub fn build(self) -> Result<VideoRetimer, Error> {
+        if !self.rate.is_finite() || self.rate <= 0.0 {
+            return Err(Error::new("invalid playback rate"));
+        }
+
+        if let Some((num, den)) = self.frame_rate {
+            if num == 0 || den == 0 {
+                return Err(Error::new("invalid frame rate"));
+            }
+        }
+
+        let res = VideoRetimer {
+            rate: self.rate,
+            frame_rate: self.frame_rate,
+            interpolation: self.interpolation,
+            origin: self.origin.unwrap_or_else(Timestamp::null),
+            graph: LazyVideoFilterGraph::new(),
+            pending: None,
+        };
+
+        Ok(res)
+    }
+}
+
+/// Video retimer. It changes speed of a video by rescaling frame timestamps
+/// (an equivalent of the `setpts` filter) and, optionally, it converts the
+/// result into a constant frame rate using frame dropping/duplication,
+/// blending or motion compensated interpolation.
+///
+/// The retimed timestamp of an input timestamp `pts` is
+/// `origin + (pts - origin) / rate`. Use the `retime()` method to retime
+/// timestamps of other related components (e.g. subtitles) and the
+/// `PlaybackRate` audio filter with the same rate to keep audio in sync.
+///
+/// # Retimer operation
+/// 1. Push a video frame to the retimer.
+/// 2. Take all frames from the retimer until you get None.
+/// 3. If there are more frames to be processed, continue with 1.
+/// 4. Flush the retimer.
+/// 5. Take all frames from the retimer until you get None.
+pub struct VideoRetimer {
+    rate: f64,
+    frame_rate: Option<(u32, u32)>,
+    interpolation: InterpolationMode,
+    origin: Timestamp,
+    graph: LazyVideoFilterGraph,
+    pending: Option<VideoFrame>,
+}
+
+impl VideoRetimer {
+    /// Get a builder for the video retimer.
+    pub fn builder() -> VideoRetimerBuilder {
+        VideoRetimerBuilder::new()
+    }
+
+    /// Get the playback rate.
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// Get the origin timestamp. The timestamp will be null until the first
+    /// frame with a valid timestamp is pushed (unless it was set
+    /// explicitly).
+    pub fn origin(&self) -> Timestamp {
+        self.origin
+    }
+
+    /// Retime a given timestamp. The timestamp is returned unchanged if it is
+    /// null or if the origin is not known yet.
+    pub fn retime(&self, ts: Timestamp) -> Timestamp {
+        if ts.is_null() || self.origin.is_null() {
+            return ts;
+        }
+
+        let origin = self.origin.with_time_base(ts.time_base()).timestamp();
+
+        let delta = (ts.timestamp() - origin) as f64 / self.rate;
+
+        ts.with_raw_timestamp(origin + delta.round() as i64)
+    }
+
+    /// Push a given frame to the retimer.
+    ///
+    /// # Panics
+    /// The method panics if the operation is not expected (i.e. another
+    /// operation needs to be done).
+    pub fn push(&mut self, frame: VideoFrame) -> Result<(), Error> {
+        self.try_push(frame).map_err(|err| err.unwrap_inner())
+    }
+
+    /// Push a given frame to the retimer.
+    pub fn try_push(&mut self, frame: VideoFrame) -> Result<(), CodecError> {
+        if self.origin.is_null() {
+            self.origin = frame.pts();
+        }
+
+        if let Some(frame_rate) = self.frame_rate {
+            let origin = self.origin;
+            let rate = self.rate;
+            let interpolation = self.interpolation;
+
+            self.graph.push(frame, |frame| {
+                // the origin is used in the filter graph description
+                if origin.is_null() {
+                    return Err(Error::new(
+                        "unable to retime frames, the first frame has no timestamp",
+                    ));
+                }
+
+                let origin = origin.with_time_base(frame.time_base()).timestamp();
+
+                Ok(filters(origin, rate, frame_rate, interpolation))
+            })?;
+        } else if self.pending.is_some() {
+            return Err(CodecError::again(
+                "all frames must be consumed before pushing a new frame",
+            ));
+        } else {
+            let pts = self.retime(frame.pts());
+
+            self.pending = Some(frame.with_pts(pts));
+        }
+
+        Ok(())
+    }
+
+    /// Flush the retimer.
+    ///
+    /// # Panics
+    /// The method panics if the operation is not expected (i.e. another
+    /// operation needs to be done).
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.try_flush().map_err(|err| err.unwrap_inner())
+    }
+
+    /// Flush the retimer.
+    pub fn try_flush(&mut self) -> Result<(), CodecError> {
+        if self.graph.is_initialized() {
+            self.graph.flush()?;
+        } else if self.pending.is_some() {
+            return Err(CodecError::again(
+                "all frames must be consumed before flushing",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Take the next frame from the retimer (if available).
+    pub fn take(&mut self) -> Result<Option<VideoFrame>, Error> {
+        if self.graph.is_initialized() {
+            self.graph.take()
+        } else {
+            Ok(self.pending.take())
+        }
+    }
+}
+
+/// Get the filter graph description for a given origin (in the time base of
+/// the input frames), playback rate, output frame rate and interpolation
+/// mode.
+fn filters(
+    origin: i64,
+    rate: f64,
+    frame_rate: (u32, u32),
+    interpolation: InterpolationMode,
+) -> String {
+    let (num, den) = frame_rate;
+
+    let filter = match interpolation {
+        InterpolationMode::Duplicate => "fps",
+        InterpolationMode::Blend => "framerate",
+        InterpolationMode::MotionCompensated => "minterpolate",
+    };
+
+    let mut res = format!(
+        "setpts=({origin})+(PTS-({origin}))/{rate},{filter}=fps={num}/{den}",
+        origin = origin,
+        rate = rate,
+        filter = filter,
+        num = num,
+        den = den,
+    );
+
+    if interpolation == InterpolationMode::MotionCompensated {
+        res.push_str(":mi_mode=mci");
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VideoRetimer;
+
+    use crate::{
+        codec::video::{
+            frame::get_pixel_format, interpolate::InterpolationMode, VideoFrame, VideoFrameMut,
+        },
+        time::{TimeBase, Timestamp},
+    };
+
+    /// Create a black 32x16 frame with a given timestamp (25 fps).
+    fn frame(pts: i64) -> VideoFrame {
+        let time_base = TimeBase::new(1, 25);
+
+        VideoFrameMut::black(get_pixel_format("yuv420p"), 32, 16)
+            .with_time_base(time_base)
+            .with_pts(Timestamp::new(pts, time_base))
+            .freeze()
+    }
+
+    /// Push given frames to a given retimer, flush it and take all output
+    /// frames.
+    fn run(retimer: &mut VideoRetimer, frames: Vec<VideoFrame>) -> Vec<VideoFrame> {
+        let mut res = Vec::new();
+
+        for frame in frames {
+            retimer.push(frame).unwrap();
+
+            while let Some(frame) = retimer.take().unwrap() {
+                res.push(frame);
+            }
+        }
+
+        retimer.flush().unwrap();
+
+        while let Some(frame) = retimer.take().unwrap() {
+            res.push(frame);
+        }
+
+        res
+    }
+
+    #[test]
+    fn test_retime() {
+        let time_base = TimeBase::new(1, 1000);
+
+        let retimer = VideoRetimer::builder()
+            .rate(2.0)
+            .origin(Timestamp::from_micros(100_000))
+            .build()
+            .unwrap();
+
+        let ts = retimer.retime(Timestamp::new(300, time_base));
+
+        assert_eq!(ts.timestamp(), 200);
+
+        // timestamps before the origin
+        assert_eq!(retimer.retime(Timestamp::new(0, time_base)).timestamp(), 50);
+
+        assert!(retimer.retime(Timestamp::null()).is_null());
+
+        // the origin is not known yet
+        let retimer = VideoRetimer::builder().rate(2.0).build().unwrap();
+
+        assert!(retimer.origin().is_null());
+        assert_eq!(
+            retimer.retime(Timestamp::new(300, time_base)).timestamp(),
+            300
+        );
+    }
+
+    #[test]
+    fn test_invalid_settings() {
+        assert!(VideoRetimer::builder().rate(0.0).build().is_err());
+        assert!(VideoRetimer::builder().rate(f64::NAN).build().is_err());
+        assert!(VideoRetimer::builder().frame_rate(0, 1).build().is_err());
+        assert!(VideoRetimer::builder().frame_rate(25, 0).build().is_err());
+    }
+
+    #[test]
+    fn test_timestamps_only() {
+        let mut retimer = VideoRetimer::builder().rate(0.5).build().unwrap();
+
+        let frames = (10..20).map(frame).collect();
+
+        let res = run(&mut retimer, frames);
+
+        // the first timestamp is used as the origin
+        assert_eq!(retimer.origin().timestamp(), 10);
+
+        let pts = res
+            .iter()
+            .map(|frame| frame.pts().timestamp())
+            .collect::<Vec<_>>();
+
+        assert_eq!(pts, [10, 12, 14, 16, 18, 20, 22, 24, 26, 28]);
+
+        // frames must be taken before pushing
+        retimer.push(frame(20)).unwrap();
+
+        assert!(retimer.try_push(frame(21)).unwrap_err().is_again());
+        assert!(retimer.try_flush().unwrap_err().is_again());
+    }
+
+    #[test]
+    fn test_constant_frame_rate() {
+        for interpolation in [InterpolationMode::Duplicate, InterpolationMode::Blend] {
+            let mut retimer = VideoRetimer::builder()
+                .rate(2.0)
+                .frame_rate(25, 1)
+                .interpolation(interpolation)
+                .build()
+                .unwrap();
+
+            let frames = (0..20).map(frame).collect();
+
+            let res = run(&mut retimer, frames);
+
+            // double speed, i.e. roughly every other frame is dropped
+            assert!((9..=11).contains(&res.len()), "{}", res.len());
+
+            let pts = res
+                .iter()
+                .map(|frame| frame.pts().with_time_base(TimeBase::new(1, 25)))
+                .map(|pts| pts.timestamp())
+                .collect::<Vec<_>>();
+
+            assert_eq!(pts[0], 0);
+            assert!(pts.windows(2).all(|w| w[1] == w[0] + 1));
+        }
+    }
+
+    #[test]
+    fn test_missing_origin() {
+        let mut retimer = VideoRetimer::builder()
+            .rate(2.0)
+            .frame_rate(25, 1)
+            .build()
+            .unwrap();
+
+        let frame = frame(0).with_pts(Timestamp::null());
+
+        let err = retimer.try_push(frame.clone()).unwrap_err();
+
+        assert!(!err.is_again());
+
+        // the retimer can be used once the origin is known
+        let mut retimer = VideoRetimer::builder()
+            .frame_rate(25, 1)
+            .origin(Timestamp::from_micros(0))
+            .build()
+            .unwrap();
+
+        assert!(retimer.try_push(frame).is_ok());
+    }
+}
@@ -1,25 +1,42 @@
 #include <libavutil/frame.h>
+#include <libavutil/opt.h>
+#include <libavutil/rational.h>
 #include <libswscale/swscale.h>
 
+#include <limits.h>
 #include <stdlib.h>
 
 typedef struct FrameScaler {
     struct SwsContext* scale_context;
     struct AVFrame* frame;
+    struct AVFrame* cropped;
+
+    int crop_left;
+    int crop_top;
+    int crop_right;
+    int crop_bottom;
 
     int tformat;
     int twidth;
     int theight;
+
+    int threaded;
 } FrameScaler;
 
 FrameScaler* ffw_frame_scaler_new(
     int sformat, int swidth, int sheight,
+    int cx, int cy, int cwidth, int cheight,
     int tformat, int twidth, int theight,
-    int flags);
+    int flags, int threads);
 
 AVFrame* ffw_frame_scaler_scale(FrameScaler* scaler, const AVFrame* src);
+int ffw_frame_scaler_scale_into(FrameScaler* scaler, const AVFrame* src, AVFrame* dst);
 void ffw_frame_scaler_free(FrameScaler* scaler);
 int ffw_alg_id_to_flags(size_t id);
+int ffw_frame_copy_side_data(AVFrame* dst, const AVFrame* src, int raw);
+
+static int crop_and_scale_frame(FrameScaler* scaler, const AVFrame* src, AVFrame* dst);
+static int scale_frame(FrameScaler* scaler, const AVFrame* src, AVFrame* dst);
 
 static AVFrame* alloc_frame(int format, int width, int height) {
     AVFrame* frame = av_frame_alloc();
@@ -41,8 +58,9 @@ static AVFrame* alloc_frame(int format, int width, int height) {
 
 FrameScaler* ffw_frame_scaler_new(
     int sformat, int swidth, int sheight,
+    int cx, int cy, int cwidth, int cheight,
     int tformat, int twidth, int theight,
-    int flags) {
+    int flags, int threads) {
     FrameScaler* res = malloc(sizeof(FrameScaler));
     if (res == NULL) {
         return NULL;
@@ -50,13 +68,54 @@ FrameScaler* ffw_frame_scaler_new(
 
     res->scale_context = NULL;
     res->frame = NULL;
+    res->cropped = NULL;
+
+    res->crop_left = cx;
+    res->crop_top = cy;
+    res->crop_right = swidth - cx - cwidth;
+    res->crop_bottom = sheight - cy - cheight;
+
+    if (cx > 0 || cy > 0 || cwidth < swidth || cheight < sheight) {
+        if (!(res->cropped = av_frame_alloc())) {
+            goto err;
+        }
+    }
 
     res->tformat = tformat;
     res->twidth = twidth;
     res->theight = theight;
 
+    res->threaded = 0;
+
+#if LIBSWSCALE_VERSION_MAJOR >= 6
+    if (threads != 1) {
+        // the number of threads can be set only before the context is
+        // initialized
+        if (!(res->scale_context = sws_alloc_context())) {
+            goto err;
+        }
+
+        av_opt_set_int(res->scale_context, "srcw", cwidth, 0);
+        av_opt_set_int(res->scale_context, "srch", cheight, 0);
+        av_opt_set_int(res->scale_context, "src_format", sformat, 0);
+        av_opt_set_int(res->scale_context, "dstw", twidth, 0);
+        av_opt_set_int(res->scale_context, "dsth", theight, 0);
+        av_opt_set_int(res->scale_context, "dst_format", tformat, 0);
+        av_opt_set_int(res->scale_context, "sws_flags", flags, 0);
+        av_opt_set_int(res->scale_context, "threads", threads, 0);
+
+        if (sws_init_context(res->scale_context, NULL, NULL) < 0) {
+            goto err;
+        }
+
+        res->threaded = 1;
+
+        return res;
+    }
+#endif
+
     res->scale_context = sws_getContext(
-        swidth, sheight, sformat,
+        cwidth, cheight, sformat,
         twidth, theight, tformat,
         flags, NULL, NULL, NULL);
 
@@ -85,15 +144,95 @@ AVFrame* ffw_frame_scaler_scale(FrameScaler* scaler, const AVFrame* src) {
         }
     }
 
-    AVFrame* dst = scaler->frame;
+    if (crop_and_scale_frame(scaler, src, scaler->frame) < 0) {
+        return NULL;
+    }
+
+    return av_frame_clone(scaler->frame);
+}
+
+int ffw_frame_scaler_scale_into(FrameScaler* scaler, const AVFrame* src, AVFrame* dst) {
+    if (dst->format != scaler->tformat
+        || dst->width != scaler->twidth
+        || dst->height != scaler->theight) {
+        return AVERROR(EINVAL);
+    }
+
+    if (!av_frame_is_writable(dst)) {
+        return AVERROR(EINVAL);
+    }
+
+    return crop_and_scale_frame(scaler, src, dst);
+}
+
+static int crop_and_scale_frame(FrameScaler* scaler, const AVFrame* src, AVFrame* dst) {
+    AVFrame* cropped = scaler->cropped;
+    int ret;
+
+    if (!cropped) {
+        return scale_frame(scaler, src, dst);
+    }
+
+    // crop a new reference to the source frame, i.e. only the plane
+    // pointers are moved and no data are copied
+    if ((ret = av_frame_ref(cropped, src)) < 0) {
+        return ret;
+    }
+
+    cropped->crop_left = scaler->crop_left;
+    cropped->crop_top = scaler->crop_top;
+    cropped->crop_right = scaler->crop_right;
+    cropped->crop_bottom = scaler->crop_bottom;
+
+    if ((ret = av_frame_apply_cropping(cropped, AV_FRAME_CROP_UNALIGNED)) >= 0) {
+        ret = scale_frame(scaler, cropped, dst);
+    }
+
+    av_frame_unref(cropped);
+
+    return ret;
+}
+
+static int scale_frame(FrameScaler* scaler, const AVFrame* src, AVFrame* dst) {
+    int ret;
 
     dst->pts = src->pts;
 
-    sws_scale(scaler->scale_context,
+    // pass through the side data (e.g. HDR metadata or film grain
+    // parameters), the internal frame may still hold side data of the
+    // previous frame
+    while (dst->nb_side_data > 0) {
+        av_frame_remove_side_data(dst, dst->side_data[0]->type);
+    }
+
+    if ((ret = ffw_frame_copy_side_data(dst, src, -1)) != 0) {
+        return ret;
+    }
+
+    // keep the display aspect ratio
+    if (src->sample_aspect_ratio.num > 0 && src->sample_aspect_ratio.den > 0) {
+        av_reduce(
+            &dst->sample_aspect_ratio.num,
+            &dst->sample_aspect_ratio.den,
+            (int64_t)src->sample_aspect_ratio.num * src->width * dst->height,
+            (int64_t)src->sample_aspect_ratio.den * src->height * dst->width,
+            INT_MAX);
+    } else {
+        dst->sample_aspect_ratio = src->sample_aspect_ratio;
+    }
+
+#if LIBSWSCALE_VERSION_MAJOR >= 6
+    // only the frame API splits the frame into slices processed in parallel
+    if (scaler->threaded) {
+        return sws_scale_frame(scaler->scale_context, dst, src);
+    }
+#endif
+
+    ret = sws_scale(scaler->scale_context,
         (const uint8_t* const*)src->data, src->linesize, 0, src->height,
         dst->data, dst->linesize);
 
-    return av_frame_clone(dst);
+    return ret < 0 ? ret : 0;
 }
 
 void ffw_frame_scaler_free(FrameScaler* scaler) {
@@ -102,6 +241,7 @@ void ffw_frame_scaler_free(FrameScaler* scaler) {
     }
 
     av_frame_free(&scaler->frame);
+    av_frame_free(&scaler->cropped);
     sws_freeContext(scaler->scale_context);
     free(scaler);
 }
\ No newline at end of file
@@ -3,7 +3,7 @@
 use std::os::raw::{c_int, c_void};
 
 use crate::{
-    codec::video::{PixelFormat, VideoFrame},
+    codec::video::{PixelFormat, VideoFrame, VideoFrameMut},
     Error,
 };
 
@@ -12,13 +12,23 @@ extern "C" {
         sformat: c_int,
         swidth: c_int,
         sheight: c_int,
+        cx: c_int,
+        cy: c_int,
+        cwidth: c_int,
+        cheight: c_int,
         tformat: c_int,
         twidth: c_int,
         theight: c_int,
         flags: c_int,
+        threads: c_int,
     ) -> *mut c_void;
 
     fn ffw_frame_scaler_scale(scaler: *mut c_void, src: *const c_void) -> *mut c_void;
+    fn ffw_frame_scaler_scale_into(
+        scaler: *mut c_void,
+        src: *const c_void,
+        dst: *mut c_void,
+    ) -> c_int;
 
     fn ffw_frame_scaler_free(scaler: *mut c_void);
 }
@@ -46,11 +56,14 @@ pub struct VideoFrameScalerBuilder {
     swidth: c_int,
     sheight: c_int,
 
+    crop: Option<(usize, usize, usize, usize)>,
+
     tformat: Option<c_int>,
     twidth: c_int,
     theight: c_int,
 
     flags: c_int,
+    threads: usize,
 }
 
 impl VideoFrameScalerBuilder {
@@ -65,11 +78,14 @@ impl VideoFrameScalerBuilder {
             swidth: 0,
             sheight: 0,
 
+            crop: None,
+
             tformat: None,
             twidth: 0,
             theight: 0,
 
             flags,
+            threads: 1,
         }
     }
 
@@ -91,6 +107,17 @@ impl VideoFrameScalerBuilder {
         self
     }
 
+    /// Scale only a given region of the source frames (i.e. crop and scale
+    /// the frames in one pass). The region is given by the position of its
+    /// top left corner and its dimensions. The position must be aligned to
+    /// the chroma subsampling of the source pixel format (e.g. it must be
+    /// even for `yuv420p`). The source frames are not copied. The default
+    /// is the whole frame.
+    pub fn source_crop(mut self, x: usize, y: usize, width: usize, height: usize) -> Self {
+        self.crop = Some((x, y, width, height));
+        self
+    }
+
     /// Set target pixel format. The default is equal to the source format.
     pub fn target_pixel_format(mut self, format: PixelFormat) -> Self {
         self.tformat = Some(format.into_raw());
@@ -116,6 +143,15 @@ impl VideoFrameScalerBuilder {
         self
     }
 
+    /// Set the number of threads used for scaling a single frame. The frame
+    /// is split into slices scaled in parallel. Zero means the number of
+    /// available CPUs. The default is 1. Multi-threaded scaling requires
+    /// FFmpeg 5.0 or newer, the option is ignored with older versions.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
     /// Build the video frame scaler.
     pub fn build(self) -> Result<VideoFrameScaler, Error> {
         let tformat = self.tformat.unwrap_or(self.sformat);
@@ -134,15 +170,50 @@ impl VideoFrameScalerBuilder {
             return Err(Error::new("invalid target height"));
         }
 
+        let swidth = self.swidth as usize;
+        let sheight = self.sheight as usize;
+
+        let (cx, cy, cwidth, cheight) = self.crop.unwrap_or((0, 0, swidth, sheight));
+
+        let right = cx.checked_add(cwidth).filter(|&right| right <= swidth);
+        let bottom = cy.checked_add(cheight).filter(|&bottom| bottom <= sheight);
+
+        if cwidth < 1 || cheight < 1 || right.is_none() || bottom.is_none() {
+            return Err(Error::new("invalid source crop"));
+        }
+
+        if self.crop.is_some() {
+            let format = PixelFormat::from_raw(self.sformat);
+
+            if format.is_hardware() || format.is_bitstream() {
+                return Err(Error::new(
+                    "source crop is not supported for the source pixel format",
+                ));
+            }
+
+            let (log2_w, log2_h) = format.chroma_subsampling();
+
+            if (cx & ((1 << log2_w) - 1)) != 0 || (cy & ((1 << log2_h) - 1)) != 0 {
+                return Err(Error::new(
+                    "source crop is not aligned to the chroma subsampling",
+                ));
+            }
+        }
+
         let ptr = unsafe {
             ffw_frame_scaler_new(
                 self.sformat,
                 self.swidth,
                 self.sheight,
+                cx as _,
+                cy as _,
+                cwidth as _,
+                cheight as _,
                 tformat,
                 self.twidth,
                 self.theight,
                 self.flags,
+                self.threads as _,
             )
         };
 
@@ -156,6 +227,10 @@ impl VideoFrameScalerBuilder {
             sformat: PixelFormat::from_raw(self.sformat),
             swidth: self.swidth as _,
             sheight: self.sheight as _,
+
+            tformat: PixelFormat::from_raw(tformat),
+            twidth: self.twidth as _,
+            theight: self.theight as _,
         };
 
         Ok(res)
@@ -169,6 +244,10 @@ pub struct VideoFrameScaler {
     sformat: PixelFormat,
     swidth: usize,
     sheight: usize,
+
+    tformat: PixelFormat,
+    twidth: usize,
+    theight: usize,
 }
 
 impl VideoFrameScaler {
@@ -179,13 +258,9 @@ impl VideoFrameScaler {
 
     /// Scale a given frame.
     pub fn scale(&mut self, frame: &VideoFrame) -> Result<VideoFrame, Error> {
-        if self.swidth != frame.width() {
-            return Err(Error::new("frame width does not match"));
-        } else if self.sheight != frame.height() {
-            return Err(Error::new("frame height does not match"));
-        } else if self.sformat != frame.pixel_format() {
-            return Err(Error::new("frame pixel format does not match"));
-        }
+        self.check_source(frame)?;
+
+        let _span = trace_span!("video_frame_scaler_scale", pts = frame.pts().as_micros());
 
         let res = unsafe { ffw_frame_scaler_scale(self.ptr, frame.as_ptr()) };
 
@@ -197,6 +272,72 @@ impl VideoFrameScaler {
 
         Ok(frame)
     }
+
+    /// Scale a given frame into a given existing frame instead of allocating
+    /// a new one. The target frame must have the target pixel format and
+    /// dimensions of the scaler. Its timestamp, side data and sample aspect
+    /// ratio are replaced, its time base is kept.
+    ///
+    /// # Example
+    /// ```text
+    /// let mut target = VideoFrameMut::black(PixelFormat::from_str("yuv420p")?, 1280, 720);
+    ///
+    /// while let Some(frame) = decoder.take()? {
+    ///     scaler.scale_into(&frame, &mut target)?;
+    ///
+    ///     ...
+    /// }
+    /// ```
+    pub fn scale_into(
+        &mut self,
+        frame: &VideoFrame,
+        target: &mut VideoFrameMut,
+    ) -> Result<(), Error> {
+        self.check_source(frame)?;
+
+        if self.twidth != target.width() {
+            return Err(Error::new("target frame width does not match"));
+        } else if self.theight != target.height() {
+            return Err(Error::new("target frame height does not match"));
+        } else if self.tformat != target.pixel_format() {
+            return Err(Error::new("target frame pixel format does not match"));
+        }
+
+        let _span = trace_span!(
+            "video_frame_scaler_scale_into",
+            pts = frame.pts().as_micros()
+        );
+
+        let ret =
+            unsafe { ffw_frame_scaler_scale_into(self.ptr, frame.as_ptr(), target.as_mut_ptr()) };
+
+        if ret < 0 {
+            return Err(Error::from_raw_error_code(ret));
+        }
+
+        // the timestamp was copied as it is
+        target.set_pts(frame.pts());
+
+        Ok(())
+    }
+
+    /// Check if a given frame matches the source parameters of the scaler.
+    fn check_source(&self, frame: &VideoFrame) -> Result<(), Error> {
+        if self.swidth != frame.width() {
+            Err(Error::new("frame width does not match"))
+        } else if self.sheight != frame.height() {
+            Err(Error::new("frame height does not match"))
+        } else if self.sformat != frame.pixel_format() {
+            Err(Error::new("frame pixel format does not match"))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Check if a given frame matches the source parameters of the scaler.
+    pub(crate) fn accepts(&self, frame: &VideoFrame) -> bool {
+        self.check_source(frame).is_ok()
+    }
 }
 
 impl Drop for VideoFrameScaler {
@@ -207,3 +348,194 @@ impl Drop for VideoFrameScaler {
 
 unsafe impl Send for VideoFrameScaler {}
 unsafe impl Sync for VideoFrameScaler {}
+
+#[cfg(test)]
+mod tests {
+    use super::VideoFrameScaler;
+
+    use crate::{
+        codec::video::{frame::get_pixel_format, VideoFrame, VideoFrameMut},
+        time::Timestamp,
+    };
+
+    /// Create an 8x4 grayscale frame with the left half set to 50 and the
+    /// right half set to 200.
+    fn gray_frame() -> VideoFrame {
+        let mut frame = VideoFrameMut::black(get_pixel_format("gray"), 8, 4);
+
+        {
+            let mut planes = frame.planes_mut();
+            let plane = &mut planes[0];
+            let stride = plane.line_size();
+            let data = plane.data_mut();
+
+            for y in 0..4 {
+                data[y * stride..y * stride + 4].fill(50);
+                data[y * stride + 4..y * stride + 8].fill(200);
+            }
+        }
+
+        frame.freeze()
+    }
+
+    /// Create a 64x48 yuv420p frame with a luma gradient.
+    fn gradient_frame() -> VideoFrame {
+        let mut frame = VideoFrameMut::black(get_pixel_format("yuv420p"), 64, 48);
+
+        {
+            let mut planes = frame.planes_mut();
+            let plane = &mut planes[0];
+            let stride = plane.line_size();
+            let data = plane.data_mut();
+
+            for y in 0..48 {
+                for x in 0..64 {
+                    data[y * stride + x] = (16 + 3 * x + y) as u8;
+                }
+            }
+        }
+
+        frame.freeze()
+    }
+
+    /// Get the packed content of the first plane of a given frame with a
+    /// given number of bytes per pixel.
+    fn packed_data(frame: &VideoFrame, bytes_per_pixel: usize) -> Vec<u8> {
+        let planes = frame.planes();
+        let plane = &planes[0];
+        let stride = plane.line_size();
+        let data = plane.data();
+
+        let width = frame.width() * bytes_per_pixel;
+
+        (0..frame.height())
+            .flat_map(|y| data[y * stride..y * stride + width].iter().copied())
+            .collect()
+    }
+
+    #[test]
+    fn test_source_crop() {
+        let gray = get_pixel_format("gray");
+
+        let mut scaler = VideoFrameScaler::builder()
+            .source_pixel_format(gray)
+            .source_width(8)
+            .source_height(4)
+            .source_crop(4, 0, 4, 4)
+            .target_width(2)
+            .target_height(2)
+            .build()
+            .unwrap();
+
+        let res = scaler.scale(&gray_frame()).unwrap();
+
+        assert_eq!(res.width(), 2);
+        assert_eq!(res.height(), 2);
+
+        let planes = res.planes();
+        let plane = &planes[0];
+        let stride = plane.line_size();
+        let data = plane.data();
+
+        for y in 0..2 {
+            assert_eq!(&data[y * stride..y * stride + 2], &[200, 200]);
+        }
+    }
+
+    #[test]
+    fn test_invalid_source_crop() {
+        let builder = || {
+            VideoFrameScaler::builder()
+                .source_pixel_format(get_pixel_format("yuv420p"))
+                .source_width(8)
+                .source_height(4)
+                .target_width(2)
+                .target_height(2)
+        };
+
+        assert!(builder().source_crop(6, 0, 4, 4).build().is_err());
+        assert!(builder().source_crop(usize::MAX, 0, 4, 4).build().is_err());
+        assert!(builder().source_crop(0, 1, 8, usize::MAX).build().is_err());
+        assert!(builder().source_crop(0, 0, 0, 4).build().is_err());
+
+        // not aligned to the chroma subsampling
+        assert!(builder().source_crop(1, 0, 4, 4).build().is_err());
+
+        assert!(builder().source_crop(2, 2, 4, 2).build().is_ok());
+    }
+
+    #[test]
+    fn test_threads() {
+        let scale = |threads| {
+            let mut scaler = VideoFrameScaler::builder()
+                .source_pixel_format(get_pixel_format("yuv420p"))
+                .source_width(64)
+                .source_height(48)
+                .target_pixel_format(get_pixel_format("rgb24"))
+                .target_width(32)
+                .target_height(24)
+                .threads(threads)
+                .build()
+                .unwrap();
+
+            let res = scaler.scale(&gradient_frame()).unwrap();
+
+            assert_eq!(res.width(), 32);
+            assert_eq!(res.height(), 24);
+
+            packed_data(&res, 3)
+        };
+
+        let expected = scale(1);
+
+        // the result does not depend on the number of threads
+        assert_eq!(scale(4), expected);
+        assert_eq!(scale(0), expected);
+    }
+
+    #[test]
+    fn test_scale_into() {
+        let rgb24 = get_pixel_format("rgb24");
+
+        let mut scaler = VideoFrameScaler::builder()
+            .source_pixel_format(get_pixel_format("yuv420p"))
+            .source_width(64)
+            .source_height(48)
+            .target_pixel_format(rgb24)
+            .target_width(32)
+            .target_height(24)
+            .build()
+            .unwrap();
+
+        let frame = gradient_frame();
+
+        let expected = packed_data(&scaler.scale(&frame).unwrap(), 3);
+
+        let mut target = VideoFrameMut::black(rgb24, 32, 24);
+
+        // the same target frame can be used repeatedly
+        for pts in [40_000, 80_000] {
+            let frame = frame.clone().with_pts(Timestamp::from_micros(pts));
+
+            scaler.scale_into(&frame, &mut target).unwrap();
+
+            assert_eq!(target.pts().as_micros(), Some(pts));
+        }
+
+        assert_eq!(packed_data(&target.freeze(), 3), expected);
+
+        // mismatched target frames
+        let mut target = VideoFrameMut::black(rgb24, 32, 16);
+
+        assert!(scaler.scale_into(&frame, &mut target).is_err());
+
+        let mut target = VideoFrameMut::black(get_pixel_format("yuv420p"), 32, 24);
+
+        assert!(scaler.scale_into(&frame, &mut target).is_err());
+
+        // mismatched source frame
+        let mut target = VideoFrameMut::black(rgb24, 32, 24);
+
+        assert!(scaler.scale_into(&gray_frame(), &mut target).is_err());
+    }
+}
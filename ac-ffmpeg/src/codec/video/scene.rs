@@ -0,0 +1,251 @@
+//! Scene change detection.
+
+use std::time::Duration;
+
+use crate::{
+    codec::video::{
+        convert::{mean_absolute_difference, FrameConverter},
+        frame::get_pixel_format,
+        poster::analysis_size,
+        scaler::Algorithm,
+        VideoFrame,
+    },
+    time::Timestamp,
+    Error,
+};
+
+/// Builder for the scene change detector.
+pub struct VideoSceneBuilder {
+    threshold: f64,
+    min_interval: Duration,
+}
+
+impl VideoSceneBuilder {
+    /// Create a new builder.
+    fn new() -> Self {
+        Self {
+            threshold: 0.4,
+            min_interval: Duration::from_secs(0),
+        }
+    }
+
+    /// Set the scene score threshold (0.0 - 1.0). Frames with a higher
+    /// score are reported as scene cuts. The default is 0.4.
+    pub fn threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Set the minimum interval between two reported scene cuts. Cuts
+    /// closer to the previous cut are ignored. The default is zero (i.e.
+    /// all cuts are reported).
+    pub fn min_interval(mut self, interval: Duration) -> Self {
+        self.min_interval = interval;
+        self
+    }
+
+    /// Build the scene change detector.
+    pub fn build(self) -> VideoScene {
+        VideoScene {
+            threshold: self.threshold,
+            min_interval: self.min_interval.as_micros() as i64,
+            converter: FrameConverter::new(get_pixel_format("rgb24"), Algorithm::Area),
+            previous: None,
+            previous_mafd: None,
+            last_cut: None,
+            score: 0.0,
+        }
+    }
+}
+
+/// Scene cut.
+#[derive(Debug, Copy, Clone)]
+pub struct SceneChange {
+    pts: Timestamp,
+    score: f64,
+}
+
+impl SceneChange {
+    /// Get presentation timestamp of the first frame of the new scene.
+    pub fn pts(&self) -> Timestamp {
+        self.pts
+    }
+
+    /// Get the scene score of the frame (0.0 - 1.0).
+    pub fn score(&self) -> f64 {
+        self.score
+    }
+}
+
+/// Scene change detector. It scores consecutive frames the same way as the
+/// `scene` variable of the `select` filter does and it reports frames with
+/// a score above a given threshold, i.e. it is an equivalent of
+/// `select=gt(scene,0.4)`. It can be used e.g. for thumbnailing or for
+/// placing segment boundaries at scene cuts.
+///
+/// # Example
+/// ```text
+/// let mut detector = VideoScene::builder()
+///     .threshold(0.3)
+///     .min_interval(Duration::from_secs(1))
+///     .build();
+///
+/// while let Some(frame) = decoder.take()? {
+///     if let Some(cut) = detector.push(&frame)? {
+///         println!("scene cut at {:?} (score: {})", cut.pts(), cut.score());
+///     }
+/// }
+/// ```
+///
+/// Frames are downscaled before the analysis, so the cost of the analysis
+/// does not depend on the frame resolution. Frames must be pushed in
+/// presentation order.
+pub struct VideoScene {
+    threshold: f64,
+    min_interval: i64,
+    converter: FrameConverter,
+    previous: Option<Vec<u8>>,
+    previous_mafd: Option<f64>,
+    last_cut: Option<i64>,
+    score: f64,
+}
+
+impl VideoScene {
+    /// Get a builder for the scene change detector.
+    pub fn builder() -> VideoSceneBuilder {
+        VideoSceneBuilder::new()
+    }
+
+    /// Create a new scene change detector with the default settings.
+    pub fn new() -> Self {
+        Self::builder().build()
+    }
+
+    /// Push a given frame. A scene cut is returned if the frame starts a
+    /// new scene.
+    pub fn push(&mut self, frame: &VideoFrame) -> Result<Option<SceneChange>, Error> {
+        let rgb = self.rgb(frame)?;
+
+        Ok(self.push_rgb(rgb, frame.pts()))
+    }
+
+    /// Get the scene score of the last pushed frame (0.0 - 1.0). It can be
+    /// used for custom decisions (e.g. an adaptive threshold).
+    pub fn score(&self) -> f64 {
+        self.score
+    }
+
+    /// Reset the detector. The next frame won't be reported as a scene cut.
+    pub fn reset(&mut self) {
+        self.previous = None;
+        self.previous_mafd = None;
+        self.last_cut = None;
+        self.score = 0.0;
+    }
+
+    /// Process a given downscaled RGB image (packed, without any padding).
+    fn push_rgb(&mut self, rgb: Vec<u8>, pts: Timestamp) -> Option<SceneChange> {
+        let previous = self.previous.replace(rgb);
+        let current = self.previous.as_deref().unwrap_or_default();
+
+        // the resolution may change in the middle of the stream
+        let mafd = match previous {
+            Some(previous) if previous.len() == current.len() => {
+                mean_absolute_difference(&previous, current)
+            }
+            _ => {
+                self.previous_mafd = None;
+                self.score = 0.0;
+
+                return None;
+            }
+        };
+
+        self.score = scene_score(mafd, self.previous_mafd);
+        self.previous_mafd = Some(mafd);
+
+        if self.score <= self.threshold {
+            return None;
+        }
+
+        let time = pts.as_micros();
+
+        if let (Some(time), Some(last)) = (time, self.last_cut) {
+            if time < last + self.min_interval {
+                return None;
+            }
+        }
+
+        if time.is_some() {
+            self.last_cut = time;
+        }
+
+        let res = SceneChange {
+            pts,
+            score: self.score,
+        };
+
+        Some(res)
+    }
+
+    /// Get downscaled RGB image of a given frame (packed, without any
+    /// padding).
+    fn rgb(&mut self, frame: &VideoFrame) -> Result<Vec<u8>, Error> {
+        let (width, height) = analysis_size(frame.width(), frame.height());
+
+        self.converter.convert_packed(frame, width, height, 3)
+    }
+}
+
+impl Default for VideoScene {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Calculate the scene score from the mean absolute difference of the
+/// current frame and the mean absolute difference of the previous frame.
+/// Using the smaller of the difference and its change suppresses high
+/// scores during continuous motion.
+fn scene_score(mafd: f64, previous_mafd: Option<f64>) -> f64 {
+    let diff = match previous_mafd {
+        Some(previous) => (mafd - previous).abs(),
+        None => mafd,
+    };
+
+    (mafd.min(diff) / 100.0).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::VideoScene;
+
+    use crate::time::Timestamp;
+
+    #[test]
+    fn test_scene_detection() {
+        let mut detector = VideoScene::builder()
+            .min_interval(Duration::from_secs(1))
+            .build();
+
+        let dark = vec![16u8; 48];
+        let bright = vec![235u8; 48];
+
+        let cuts = [&dark, &dark, &bright, &bright, &dark, &dark, &bright]
+            .iter()
+            .enumerate()
+            .filter_map(|(i, image)| {
+                let pts = Timestamp::from_millis(i as i64 * 400);
+
+                detector.push_rgb(image.to_vec(), pts)
+            })
+            .map(|cut| cut.pts().as_millis().unwrap())
+            .collect::<Vec<_>>();
+
+        // the cut at 1600 ms is too close to the previous one
+        assert_eq!(cuts, vec![800, 2400]);
+        assert_eq!(detector.score(), 1.0);
+    }
+}
@@ -0,0 +1,110 @@
+//! H.264/HEVC SEI messages.
+//!
+//! Decoders attach user data unregistered SEI messages to decoded frames as
+//! `FrameSideDataType::SeiUnregistered` side data and SMPTE timecodes (from
+//! the picture timing or time code SEI) as `FrameSideDataType::S12mTimecode`
+//! side data. Encoders supporting it (e.g. `libx264`, `libx265` or the NVENC
+//! encoders) embed user data unregistered SEI messages attached to input
+//! frames if enabled using `VideoEncoderBuilder::unregistered_sei()`.
+
+/// Length of the UUID identifying the user data unregistered SEI payload.
+const UUID_LEN: usize = 16;
+
+/// User data unregistered SEI message.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct UnregisteredSei {
+    uuid: [u8; UUID_LEN],
+    payload: Vec<u8>,
+}
+
+impl UnregisteredSei {
+    /// Create a new SEI message with a given UUID and payload.
+    pub fn new<T>(uuid: [u8; UUID_LEN], payload: T) -> Self
+    where
+        T: Into<Vec<u8>>,
+    {
+        Self {
+            uuid,
+            payload: payload.into(),
+        }
+    }
+
+    /// Parse the SEI message from given side data (the UUID followed by the
+    /// payload).
+    pub(crate) fn from_side_data(data: &[u8]) -> Option<Self> {
+        if data.len() < UUID_LEN {
+            return None;
+        }
+
+        let mut uuid = [0u8; UUID_LEN];
+
+        uuid.copy_from_slice(&data[..UUID_LEN]);
+
+        let res = Self {
+            uuid,
+            payload: data[UUID_LEN..].to_vec(),
+        };
+
+        Some(res)
+    }
+
+    /// Get the side data representation of the message.
+    pub(crate) fn to_side_data(&self) -> Vec<u8> {
+        let mut res = Vec::with_capacity(UUID_LEN + self.payload.len());
+
+        res.extend_from_slice(&self.uuid);
+        res.extend_from_slice(&self.payload);
+
+        res
+    }
+
+    /// Get the UUID identifying the payload.
+    pub fn uuid(&self) -> &[u8; UUID_LEN] {
+        &self.uuid
+    }
+
+    /// Get the payload.
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+}
+
+/// Parse given S12M timecode side data into a list of SMPTE ST 12-1 binary
+/// timecodes.
+pub(crate) fn parse_s12m_timecodes(data: &[u8]) -> Vec<u32> {
+    let mut words = data
+        .chunks_exact(4)
+        .map(|chunk| u32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]));
+
+    // the first word contains the number of timecodes (at most 3)
+    let count = words.next().unwrap_or(0).min(3) as usize;
+
+    words.take(count).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_s12m_timecodes, UnregisteredSei};
+
+    #[test]
+    fn test_unregistered_sei() {
+        let sei = UnregisteredSei::new([7; 16], &b"telemetry"[..]);
+
+        let data = sei.to_side_data();
+
+        assert_eq!(data.len(), 25);
+        assert_eq!(UnregisteredSei::from_side_data(&data), Some(sei));
+        assert_eq!(UnregisteredSei::from_side_data(&data[..15]), None);
+    }
+
+    #[test]
+    fn test_s12m_timecodes() {
+        let data = [2u32, 0x1234_5678, 0x0102_0304, 0xdead_beef]
+            .iter()
+            .flat_map(|word| word.to_ne_bytes())
+            .collect::<Vec<_>>();
+
+        assert_eq!(parse_s12m_timecodes(&data), vec![0x1234_5678, 0x0102_0304]);
+        assert!(parse_s12m_timecodes(&[]).is_empty());
+    }
+}
@@ -0,0 +1,80 @@
+#include <string.h>
+
+#include <libavutil/frame.h>
+#include <libavutil/pixdesc.h>
+
+int ffw_frame_blit(AVFrame* dst, const AVFrame* src, int x, int y);
+
+// defined in pixel.c
+int ffw_pixel_get_size(const AVPixFmtDescriptor* desc, int plane);
+
+// Copy a given source frame into a given destination frame at a given
+// position. Both frames must have the same pixel format. The copied area is
+// clipped to the destination frame.
+int ffw_frame_blit(AVFrame* dst, const AVFrame* src, int x, int y) {
+    const AVPixFmtDescriptor* desc;
+    int planes;
+    int pixel_size;
+    int log2_w, log2_h;
+    int width, height;
+    int dx, dy;
+    int i, row;
+
+    if (dst->format != src->format) {
+        return AVERROR(EINVAL);
+    }
+
+    if (!(desc = av_pix_fmt_desc_get(src->format))) {
+        return AVERROR(EINVAL);
+    }
+
+    if (desc->flags & (AV_PIX_FMT_FLAG_BITSTREAM | AV_PIX_FMT_FLAG_HWACCEL | AV_PIX_FMT_FLAG_PAL)) {
+        return AVERROR(ENOSYS);
+    }
+
+    if ((planes = av_pix_fmt_count_planes(src->format)) < 0) {
+        return planes;
+    }
+
+    for (i = 0; i < planes; i++) {
+        if (!ffw_pixel_get_size(desc, i)) {
+            return AVERROR(ENOSYS);
+        }
+    }
+
+    if (x >= dst->width || y >= dst->height) {
+        return 0;
+    }
+
+    for (i = 0; i < planes; i++) {
+        pixel_size = ffw_pixel_get_size(desc, i);
+
+        if (i == 1 || i == 2) {
+            log2_w = desc->log2_chroma_w;
+            log2_h = desc->log2_chroma_h;
+        } else {
+            log2_w = 0;
+            log2_h = 0;
+        }
+
+        dx = x >> log2_w;
+        dy = y >> log2_h;
+
+        width = FFMIN(
+            AV_CEIL_RSHIFT(src->width, log2_w),
+            AV_CEIL_RSHIFT(dst->width, log2_w) - dx);
+
+        height = FFMIN(
+            AV_CEIL_RSHIFT(src->height, log2_h),
+            AV_CEIL_RSHIFT(dst->height, log2_h) - dy);
+
+        for (row = 0; row < height; row++) {
+            memcpy(
+                dst->data[i] + (dy + row) * dst->linesize[i] + dx * pixel_size,
+                src->data[i] + row * src->linesize[i],
+                width * pixel_size);
+        }
+    }
+
+    return 0;
+}
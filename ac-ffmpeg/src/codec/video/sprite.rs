@@ -0,0 +1,507 @@
+//! Thumbnail sprite sheets (storyboards) for web players.
+
+use std::{
+    collections::VecDeque,
+    fmt::Write,
+    os::raw::{c_int, c_void},
+    time::Duration,
+};
+
+use crate::{
+    codec::video::{
+        fit::{self, Color, FitMode},
+        PixelFormat, VideoFrame, VideoFrameMut,
+    },
+    Error,
+};
+
+extern "C" {
+    fn ffw_frame_blit(dst: *mut c_void, src: *const c_void, x: c_int, y: c_int) -> c_int;
+}
+
+/// Single cue of a sprite sheet storyboard. It maps a time interval to an
+/// area of a sprite sheet.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SpriteCue {
+    sheet: usize,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    start: Duration,
+    end: Duration,
+}
+
+impl SpriteCue {
+    /// Get index of the sprite sheet containing the thumbnail.
+    pub fn sheet(&self) -> usize {
+        self.sheet
+    }
+
+    /// Get horizontal offset of the thumbnail within the sprite sheet.
+    pub fn x(&self) -> usize {
+        self.x
+    }
+
+    /// Get vertical offset of the thumbnail within the sprite sheet.
+    pub fn y(&self) -> usize {
+        self.y
+    }
+
+    /// Get thumbnail width.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Get thumbnail height.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Get start of the time interval represented by the thumbnail.
+    pub fn start(&self) -> Duration {
+        self.start
+    }
+
+    /// Get end of the time interval represented by the thumbnail.
+    pub fn end(&self) -> Duration {
+        self.end
+    }
+}
+
+/// Sprite sheet image.
+pub struct SpriteSheet {
+    index: usize,
+    tiles: usize,
+    frame: VideoFrame,
+}
+
+impl SpriteSheet {
+    /// Get index of the sprite sheet.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Get the number of thumbnails in the sprite sheet.
+    pub fn tiles(&self) -> usize {
+        self.tiles
+    }
+
+    /// Get the sprite sheet image.
+    pub fn frame(&self) -> &VideoFrame {
+        &self.frame
+    }
+
+    /// Take the sprite sheet image.
+    pub fn into_frame(self) -> VideoFrame {
+        self.frame
+    }
+}
+
+/// Builder for the sprite sheet generator.
+pub struct SpriteSheetGeneratorBuilder {
+    tile_width: usize,
+    tile_height: usize,
+    columns: usize,
+    rows: usize,
+    interval: Duration,
+    fit_mode: FitMode,
+    background: Color,
+}
+
+impl SpriteSheetGeneratorBuilder {
+    /// Create a new builder.
+    fn new() -> Self {
+        Self {
+            tile_width: 160,
+            tile_height: 90,
+            columns: 10,
+            rows: 10,
+            interval: Duration::from_secs(10),
+            fit_mode: FitMode::Contain,
+            background: Color::BLACK,
+        }
+    }
+
+    /// Set thumbnail size. Even dimensions are recommended for pixel formats
+    /// with chroma subsampling. The default is 160x90.
+    pub fn tile_size(mut self, width: usize, height: usize) -> Self {
+        self.tile_width = width;
+        self.tile_height = height;
+        self
+    }
+
+    /// Set number of thumbnail columns and rows of a single sprite sheet.
+    /// The default is 10x10.
+    pub fn grid(mut self, columns: usize, rows: usize) -> Self {
+        self.columns = columns;
+        self.rows = rows;
+        self
+    }
+
+    /// Take one thumbnail every given interval. The default is 10 seconds.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Set the method of fitting frames into thumbnails. The default is
+    /// `FitMode::Contain`.
+    pub fn fit_mode(mut self, mode: FitMode) -> Self {
+        self.fit_mode = mode;
+        self
+    }
+
+    /// Set color used for padding thumbnails (see `FitMode::Contain`). The
+    /// default is black.
+    pub fn background(mut self, color: Color) -> Self {
+        self.background = color;
+        self
+    }
+
+    /// Build the generator.
+    pub fn build(self) -> Result<SpriteSheetGenerator, Error> {
+        if self.tile_width < 1 || self.tile_height < 1 {
+            return Err(Error::new("invalid tile size"));
+        } else if self.columns < 1 || self.rows < 1 {
+            return Err(Error::new("invalid grid size"));
+        }
+
+        let interval = self.interval.as_micros() as i64;
+
+        if interval < 1 {
+            return Err(Error::new("invalid interval"));
+        }
+
+        let res = SpriteSheetGenerator {
+            tile_width: self.tile_width,
+            tile_height: self.tile_height,
+            columns: self.columns,
+            rows: self.rows,
+            interval,
+            fit_mode: self.fit_mode,
+            background: self.background,
+            next_sample: 0,
+            sheet: None,
+            sheet_index: 0,
+            tiles: 0,
+            ready: VecDeque::new(),
+            cues: Vec::new(),
+        };
+
+        Ok(res)
+    }
+}
+
+/// Sprite sheet generator. It samples pushed video frames in regular
+/// intervals, scales them into thumbnails and tiles them into sprite sheet
+/// images. It also collects the corresponding cues which can be rendered
+/// into a WebVTT storyboard used by web players for seek previews.
+///
+/// # Usage
+/// 1. Push all decoded frames of the video stream using the `push()`
+///    method. Frames without a timestamp are ignored.
+/// 2. Take finished sprite sheets using the `take()` method and encode
+///    them (e.g. using a JPEG encoder).
+/// 3. Call `flush()` at the end of the stream and take the remaining
+///    (partially filled) sprite sheet.
+/// 4. Render the storyboard using the `webvtt()` method.
+///
+/// The sprite sheets have the same pixel format as the pushed frames. See
+/// [supported pixel formats](crate::codec::video#software-frame-processing).
+pub struct SpriteSheetGenerator {
+    tile_width: usize,
+    tile_height: usize,
+    columns: usize,
+    rows: usize,
+    interval: i64,
+    fit_mode: FitMode,
+    background: Color,
+    next_sample: i64,
+    sheet: Option<VideoFrameMut>,
+    sheet_index: usize,
+    tiles: usize,
+    ready: VecDeque<SpriteSheet>,
+    cues: Vec<SpriteCue>,
+}
+
+impl SpriteSheetGenerator {
+    /// Get a builder for the generator.
+    pub fn builder() -> SpriteSheetGeneratorBuilder {
+        SpriteSheetGeneratorBuilder::new()
+    }
+
+    /// Push a given frame.
+    pub fn push(&mut self, frame: &VideoFrame) -> Result<(), Error> {
+        let time = match frame.pts().as_micros() {
+            Some(t) if t >= self.next_sample => t,
+            _ => return Ok(()),
+        };
+
+        let start = time / self.interval * self.interval;
+
+        let tile = fit::fit(
+            frame,
+            self.tile_width,
+            self.tile_height,
+            self.fit_mode,
+            self.background,
+        )?;
+
+        let columns = self.columns;
+        let tile_width = self.tile_width;
+        let tile_height = self.tile_height;
+
+        let x = (self.tiles % columns) * tile_width;
+        let y = (self.tiles / columns) * tile_height;
+
+        let sheet = self.current_sheet(frame.pixel_format());
+
+        let ret = unsafe { ffw_frame_blit(sheet.as_mut_ptr(), tile.as_ptr(), x as _, y as _) };
+
+        if ret < 0 {
+            return Err(Error::new(
+                "unable to create a thumbnail, unsupported or inconsistent pixel format",
+            ));
+        }
+
+        self.next_sample = start + self.interval;
+
+        self.cues.push(SpriteCue {
+            sheet: self.sheet_index,
+            x,
+            y,
+            width: tile_width,
+            height: tile_height,
+            start: Duration::from_micros(start as u64),
+            end: Duration::from_micros((start + self.interval) as u64),
+        });
+
+        self.tiles += 1;
+
+        if self.tiles >= self.columns * self.rows {
+            self.finish_sheet();
+        }
+
+        Ok(())
+    }
+
+    /// Finish the current (partially filled) sprite sheet.
+    pub fn flush(&mut self) {
+        self.finish_sheet();
+    }
+
+    /// Take the next finished sprite sheet.
+    pub fn take(&mut self) -> Option<SpriteSheet> {
+        self.ready.pop_front()
+    }
+
+    /// Get all cues collected so far.
+    pub fn cues(&self) -> &[SpriteCue] {
+        &self.cues
+    }
+
+    /// Render the collected cues as a WebVTT storyboard. The closure is
+    /// expected to return URL of the sprite sheet image with a given index.
+    ///
+    /// # Example
+    /// ```text
+    /// let vtt = generator.webvtt(|index| format!("sprite_{}.jpg", index));
+    /// ```
+    pub fn webvtt<F>(&self, image_url: F) -> String
+    where
+        F: FnMut(usize) -> String,
+    {
+        render_webvtt(&self.cues, image_url)
+    }
+
+    /// Get the sprite sheet currently being filled.
+    fn current_sheet(&mut self, pixel_format: PixelFormat) -> &mut VideoFrameMut {
+        let width = self.columns * self.tile_width;
+        let height = self.rows * self.tile_height;
+
+        self.sheet
+            .get_or_insert_with(|| VideoFrameMut::black(pixel_format, width, height))
+    }
+
+    /// Move the current sprite sheet (if any) into the queue of finished
+    /// sheets.
+    fn finish_sheet(&mut self) {
+        if let Some(sheet) = self.sheet.take() {
+            self.ready.push_back(SpriteSheet {
+                index: self.sheet_index,
+                tiles: self.tiles,
+                frame: sheet.freeze(),
+            });
+
+            self.sheet_index += 1;
+            self.tiles = 0;
+        }
+    }
+}
+
+/// Render given cues as a WebVTT storyboard.
+fn render_webvtt<F>(cues: &[SpriteCue], mut image_url: F) -> String
+where
+    F: FnMut(usize) -> String,
+{
+    let mut res = String::from("WEBVTT\n");
+
+    for cue in cues {
+        let _ = write!(
+            res,
+            "\n{} --> {}\n{}#xywh={},{},{},{}\n",
+            format_time(cue.start),
+            format_time(cue.end),
+            image_url(cue.sheet),
+            cue.x,
+            cue.y,
+            cue.width,
+            cue.height
+        );
+    }
+
+    res
+}
+
+/// Format a given time as a WebVTT timestamp.
+fn format_time(time: Duration) -> String {
+    let millis = time.as_millis();
+
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        (millis / 60_000) % 60,
+        (millis / 1_000) % 60,
+        millis % 1_000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{render_webvtt, SpriteCue, SpriteSheetGenerator};
+
+    use crate::{
+        codec::video::{frame::get_pixel_format, VideoFrameMut},
+        time::Timestamp,
+    };
+
+    #[test]
+    fn test_webvtt() {
+        let cues = [
+            SpriteCue {
+                sheet: 0,
+                x: 0,
+                y: 0,
+                width: 160,
+                height: 90,
+                start: Duration::from_secs(0),
+                end: Duration::from_secs(10),
+            },
+            SpriteCue {
+                sheet: 1,
+                x: 160,
+                y: 90,
+                width: 160,
+                height: 90,
+                start: Duration::from_secs(3_610),
+                end: Duration::from_millis(3_620_500),
+            },
+        ];
+
+        let vtt = render_webvtt(&cues, |index| format!("sprite_{}.jpg", index));
+
+        let expected = "WEBVTT\n\
+            \n\
+            00:00:00.000 --> 00:00:10.000\n\
+            sprite_0.jpg#xywh=0,0,160,90\n\
+            \n\
+            01:00:10.000 --> 01:00:20.500\n\
+            sprite_1.jpg#xywh=160,90,160,90\n";
+
+        assert_eq!(vtt, expected);
+    }
+
+    #[test]
+    fn test_generator() {
+        let pixel_format = get_pixel_format("yuv420p");
+
+        let frame = VideoFrameMut::black(pixel_format, 64, 48).freeze();
+
+        let mut generator = SpriteSheetGenerator::builder()
+            .tile_size(16, 12)
+            .grid(3, 2)
+            .interval(Duration::from_secs(1))
+            .build()
+            .unwrap();
+
+        // frames without a timestamp are ignored
+        generator.push(&frame).unwrap();
+
+        let mut sheets = Vec::new();
+
+        // 12.5 seconds of video at 2 fps
+        for index in 0..25 {
+            let frame = frame.clone().with_pts(Timestamp::from_millis(index * 500));
+
+            generator.push(&frame).unwrap();
+
+            while let Some(sheet) = generator.take() {
+                sheets.push(sheet);
+            }
+        }
+
+        assert_eq!(sheets.len(), 2);
+
+        generator.flush();
+
+        while let Some(sheet) = generator.take() {
+            sheets.push(sheet);
+        }
+
+        assert_eq!(sheets.len(), 3);
+
+        let tiles = sheets.iter().map(|sheet| sheet.tiles()).collect::<Vec<_>>();
+
+        assert_eq!(tiles, [6, 6, 1]);
+
+        for (index, sheet) in sheets.iter().enumerate() {
+            let frame = sheet.frame();
+
+            assert_eq!(sheet.index(), index);
+            assert_eq!(frame.width(), 48);
+            assert_eq!(frame.height(), 24);
+            assert!(frame.pixel_format() == pixel_format);
+        }
+
+        let cues = generator.cues();
+
+        assert_eq!(cues.len(), 13);
+
+        let vtt = generator.webvtt(|index| format!("sprite_{}.jpg", index));
+
+        let targets = vtt
+            .lines()
+            .filter(|line| line.contains("#xywh="))
+            .collect::<Vec<_>>();
+
+        assert_eq!(targets.len(), cues.len());
+
+        for (index, (cue, target)) in cues.iter().zip(targets).enumerate() {
+            let tile = index % 6;
+
+            let x = (tile % 3) * 16;
+            let y = (tile / 3) * 12;
+
+            assert_eq!(cue.sheet(), index / 6);
+            assert_eq!(cue.start(), Duration::from_secs(index as u64));
+            assert_eq!(cue.end(), Duration::from_secs(index as u64 + 1));
+
+            let expected = format!("sprite_{}.jpg#xywh={},{},16,12", index / 6, x, y);
+
+            assert_eq!(target, expected);
+        }
+    }
+}
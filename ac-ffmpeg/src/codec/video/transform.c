@@ -0,0 +1,171 @@
+#include <string.h>
+
+#include <libavutil/frame.h>
+#include <libavutil/pixdesc.h>
+
+#define TRANSFORM_HFLIP         0
+#define TRANSFORM_VFLIP         1
+#define TRANSFORM_ROTATE_90     2
+#define TRANSFORM_ROTATE_180    3
+#define TRANSFORM_ROTATE_270    4
+#define TRANSFORM_TRANSPOSE     5
+
+AVFrame* ffw_frame_transform(const AVFrame* src, int transform);
+
+// defined in pixel.c
+int ffw_pixel_get_size(const AVPixFmtDescriptor* desc, int plane);
+
+static int swaps_dimensions(int transform) {
+    switch (transform) {
+        case TRANSFORM_ROTATE_90:
+        case TRANSFORM_ROTATE_270:
+        case TRANSFORM_TRANSPOSE:
+            return 1;
+        default:
+            return 0;
+    }
+}
+
+static void transform_plane(
+    uint8_t* dst,
+    int dst_linesize,
+    int dst_width,
+    int dst_height,
+    const uint8_t* src,
+    int src_linesize,
+    int src_width,
+    int src_height,
+    int pixel_size,
+    int transform) {
+    int dx, dy, sx, sy;
+
+    for (dy = 0; dy < dst_height; dy++) {
+        for (dx = 0; dx < dst_width; dx++) {
+            switch (transform) {
+                case TRANSFORM_HFLIP:
+                    sx = src_width - 1 - dx;
+                    sy = dy;
+                    break;
+                case TRANSFORM_VFLIP:
+                    sx = dx;
+                    sy = src_height - 1 - dy;
+                    break;
+                case TRANSFORM_ROTATE_90:
+                    sx = dy;
+                    sy = src_height - 1 - dx;
+                    break;
+                case TRANSFORM_ROTATE_180:
+                    sx = src_width - 1 - dx;
+                    sy = src_height - 1 - dy;
+                    break;
+                case TRANSFORM_ROTATE_270:
+                    sx = src_width - 1 - dy;
+                    sy = dx;
+                    break;
+                default:
+                    sx = dy;
+                    sy = dx;
+                    break;
+            }
+
+            memcpy(
+                dst + dy * dst_linesize + dx * pixel_size,
+                src + sy * src_linesize + sx * pixel_size,
+                pixel_size);
+        }
+    }
+}
+
+AVFrame* ffw_frame_transform(const AVFrame* src, int transform) {
+    const AVPixFmtDescriptor* desc;
+    AVFrame* dst;
+    int planes;
+    int pixel_size;
+    int log2_w, log2_h;
+    int i;
+
+    if (!(desc = av_pix_fmt_desc_get(src->format))) {
+        return NULL;
+    }
+
+    if (desc->flags & (AV_PIX_FMT_FLAG_BITSTREAM | AV_PIX_FMT_FLAG_HWACCEL)) {
+        return NULL;
+    }
+
+    // transposing would change the chroma subsampling
+    if (swaps_dimensions(transform) && desc->log2_chroma_w != desc->log2_chroma_h) {
+        return NULL;
+    }
+
+    if ((planes = av_pix_fmt_count_planes(src->format)) < 0) {
+        return NULL;
+    }
+
+    for (i = 0; i < planes; i++) {
+        if (!ffw_pixel_get_size(desc, i)) {
+            return NULL;
+        }
+    }
+
+    if (!(dst = av_frame_alloc())) {
+        return NULL;
+    }
+
+    dst->format = src->format;
+
+    if (swaps_dimensions(transform)) {
+        dst->width = src->height;
+        dst->height = src->width;
+    } else {
+        dst->width = src->width;
+        dst->height = src->height;
+    }
+
+    if (av_frame_get_buffer(dst, 0) != 0) {
+        goto err;
+    }
+
+    if (av_frame_copy_props(dst, src) != 0) {
+        goto err;
+    }
+
+    if (swaps_dimensions(transform) && dst->sample_aspect_ratio.num) {
+        dst->sample_aspect_ratio = av_inv_q(dst->sample_aspect_ratio);
+    }
+
+    for (i = 0; i < planes; i++) {
+        pixel_size = ffw_pixel_get_size(desc, i);
+
+        if (i == 1 || i == 2) {
+            log2_w = desc->log2_chroma_w;
+            log2_h = desc->log2_chroma_h;
+        } else {
+            log2_w = 0;
+            log2_h = 0;
+        }
+
+        transform_plane(
+            dst->data[i],
+            dst->linesize[i],
+            AV_CEIL_RSHIFT(dst->width, log2_w),
+            AV_CEIL_RSHIFT(dst->height, log2_h),
+            src->data[i],
+            src->linesize[i],
+            AV_CEIL_RSHIFT(src->width, log2_w),
+            AV_CEIL_RSHIFT(src->height, log2_h),
+            pixel_size,
+            transform);
+    }
+
+    // copy the palette
+    if (desc->flags & AV_PIX_FMT_FLAG_PAL) {
+        memcpy(dst->data[1], src->data[1], 4 * 256);
+    }
+
+    return dst;
+
+err:
+    av_frame_free(&dst);
+
+    return NULL;
+}
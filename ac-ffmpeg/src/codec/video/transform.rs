@@ -0,0 +1,212 @@
+//! Geometric video frame transformations.
+
+use std::os::raw::{c_int, c_void};
+
+use crate::{codec::video::VideoFrame, Error};
+
+extern "C" {
+    fn ffw_frame_transform(frame: *const c_void, transform: c_int) -> *mut c_void;
+}
+
+/// Geometric frame transformation.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Transform {
+    /// Flip the frame horizontally (mirror).
+    HorizontalFlip,
+    /// Flip the frame vertically.
+    VerticalFlip,
+    /// Rotate the frame by 90 degrees clockwise.
+    Rotate90,
+    /// Rotate the frame by 180 degrees.
+    Rotate180,
+    /// Rotate the frame by 270 degrees clockwise (i.e. 90 degrees
+    /// counterclockwise).
+    Rotate270,
+    /// Transpose the frame (i.e. flip it along its main diagonal).
+    Transpose,
+}
+
+impl Transform {
+    /// Get a transformation rotating frames clockwise by a given angle in
+    /// degrees. The angle must be a multiple of 90 degrees (negative values
+    /// are allowed). The method returns None for angles that are multiples of
+    /// 360 degrees (i.e. no transformation needed) and for angles that are
+    /// not multiples of 90 degrees.
+    pub fn from_rotation(degrees: i32) -> Option<Self> {
+        match degrees.rem_euclid(360) {
+            90 => Some(Self::Rotate90),
+            180 => Some(Self::Rotate180),
+            270 => Some(Self::Rotate270),
+            _ => None,
+        }
+    }
+
+    /// Check if the transformation swaps frame width and height.
+    pub fn swaps_dimensions(self) -> bool {
+        matches!(self, Self::Rotate90 | Self::Rotate270 | Self::Transpose)
+    }
+
+    /// Get the internal raw representation.
+    fn into_raw(self) -> c_int {
+        match self {
+            Self::HorizontalFlip => 0,
+            Self::VerticalFlip => 1,
+            Self::Rotate90 => 2,
+            Self::Rotate180 => 3,
+            Self::Rotate270 => 4,
+            Self::Transpose => 5,
+        }
+    }
+}
+
+impl VideoFrame {
+    /// Apply a given geometric transformation. The method returns a new frame.
+    /// All frame properties (e.g. timestamps) are preserved.
+    ///
+    /// Transformations swapping frame dimensions are not supported for pixel
+    /// formats with different horizontal and vertical chroma subsampling
+    /// (e.g. YUV 4:2:2). See
+    /// [supported pixel formats](crate::codec::video#software-frame-processing).
+    pub fn transform(&self, transform: Transform) -> Result<VideoFrame, Error> {
+        let ptr = unsafe { ffw_frame_transform(self.as_ptr(), transform.into_raw()) };
+
+        if ptr.is_null() {
+            return Err(Error::new(
+                "unable to transform the frame, unsupported pixel format",
+            ));
+        }
+
+        let frame = unsafe { VideoFrame::from_raw_ptr(ptr, self.time_base()) };
+
+        Ok(frame)
+    }
+
+    /// Flip the frame horizontally.
+    pub fn hflip(&self) -> Result<VideoFrame, Error> {
+        self.transform(Transform::HorizontalFlip)
+    }
+
+    /// Flip the frame vertically.
+    pub fn vflip(&self) -> Result<VideoFrame, Error> {
+        self.transform(Transform::VerticalFlip)
+    }
+
+    /// Transpose the frame.
+    pub fn transpose(&self) -> Result<VideoFrame, Error> {
+        self.transform(Transform::Transpose)
+    }
+
+    /// Rotate the frame clockwise by a given angle in degrees. The angle must
+    /// be a multiple of 90 degrees. If the angle is a multiple of 360
+    /// degrees, a shallow copy of the frame is returned.
+    pub fn rotate(&self, degrees: i32) -> Result<VideoFrame, Error> {
+        if degrees % 90 != 0 {
+            return Err(Error::new("rotation must be a multiple of 90 degrees"));
+        }
+
+        if let Some(transform) = Transform::from_rotation(degrees) {
+            self.transform(transform)
+        } else {
+            Ok(self.clone())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Transform;
+
+    use crate::{
+        codec::video::{frame::get_pixel_format, VideoFrame, VideoFrameMut},
+        time::Timestamp,
+    };
+
+    /// Create a 3x2 grayscale frame with pixels 1, 2, 3 in the first row
+    /// and 4, 5, 6 in the second row.
+    fn gray_frame() -> VideoFrame {
+        let mut frame = VideoFrameMut::black(get_pixel_format("gray"), 3, 2);
+
+        {
+            let mut planes = frame.planes_mut();
+            let plane = &mut planes[0];
+            let stride = plane.line_size();
+            let data = plane.data_mut();
+
+            data[..3].copy_from_slice(&[1, 2, 3]);
+            data[stride..stride + 3].copy_from_slice(&[4, 5, 6]);
+        }
+
+        frame.with_pts(Timestamp::from_millis(40)).freeze()
+    }
+
+    /// Get the pixels of a given grayscale frame row by row.
+    fn pixels(frame: &VideoFrame) -> Vec<u8> {
+        let planes = frame.planes();
+        let plane = &planes[0];
+        let stride = plane.line_size();
+        let data = plane.data();
+
+        (0..frame.height())
+            .flat_map(|y| data[y * stride..y * stride + frame.width()].to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn test_transform() {
+        let frame = gray_frame();
+
+        let expected: [(Transform, usize, usize, [u8; 6]); 6] = [
+            (Transform::HorizontalFlip, 3, 2, [3, 2, 1, 6, 5, 4]),
+            (Transform::VerticalFlip, 3, 2, [4, 5, 6, 1, 2, 3]),
+            (Transform::Rotate90, 2, 3, [4, 1, 5, 2, 6, 3]),
+            (Transform::Rotate180, 3, 2, [6, 5, 4, 3, 2, 1]),
+            (Transform::Rotate270, 2, 3, [3, 6, 2, 5, 1, 4]),
+            (Transform::Transpose, 2, 3, [1, 4, 2, 5, 3, 6]),
+        ];
+
+        for (transform, width, height, data) in expected {
+            let res = frame.transform(transform).unwrap();
+
+            assert_eq!(res.width(), width);
+            assert_eq!(res.height(), height);
+            assert_eq!(res.pts(), frame.pts());
+            assert_eq!(pixels(&res), data);
+        }
+    }
+
+    #[test]
+    fn test_chroma_planes() {
+        let frame = VideoFrameMut::black(get_pixel_format("yuv420p"), 64, 32).freeze();
+
+        let res = frame.transform(Transform::Rotate90).unwrap();
+
+        assert_eq!(res.width(), 32);
+        assert_eq!(res.height(), 64);
+
+        // the chroma planes must be rotated as well
+        assert_eq!(res.planes()[1].data()[0], 128);
+
+        let frame = VideoFrameMut::black(get_pixel_format("yuv422p"), 64, 32).freeze();
+
+        assert!(frame.transform(Transform::Rotate90).is_err());
+        assert!(frame.transform(Transform::HorizontalFlip).is_ok());
+    }
+
+    #[test]
+    fn test_rotate() {
+        let frame = gray_frame();
+
+        assert_eq!(pixels(&frame.rotate(0).unwrap()), pixels(&frame));
+        assert_eq!(pixels(&frame.rotate(-360).unwrap()), pixels(&frame));
+        assert_eq!(
+            pixels(&frame.rotate(-90).unwrap()),
+            pixels(&frame.transform(Transform::Rotate270).unwrap())
+        );
+
+        assert!(frame.rotate(45).is_err());
+
+        assert_eq!(Transform::from_rotation(450), Some(Transform::Rotate90));
+        assert_eq!(Transform::from_rotation(720), None);
+        assert_eq!(Transform::from_rotation(30), None);
+    }
+}
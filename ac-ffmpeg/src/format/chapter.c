@@ -0,0 +1,33 @@
+#include <libavformat/avformat.h>
+
+int64_t ffw_chapter_get_id(const AVChapter* chapter);
+void ffw_chapter_get_time_base(const AVChapter* chapter, uint32_t* num, uint32_t* den);
+int64_t ffw_chapter_get_start(const AVChapter* chapter);
+int64_t ffw_chapter_get_end(const AVChapter* chapter);
+const AVDictionaryEntry* ffw_chapter_get_metadata_entry(const AVChapter* chapter, const AVDictionaryEntry* prev);
+int ffw_chapter_set_metadata(AVChapter* chapter, const char* key, const char* value);
+
+int64_t ffw_chapter_get_id(const AVChapter* chapter) {
+    return chapter->id;
+}
+
+void ffw_chapter_get_time_base(const AVChapter* chapter, uint32_t* num, uint32_t* den) {
+    *num = chapter->time_base.num;
+    *den = chapter->time_base.den;
+}
+
+int64_t ffw_chapter_get_start(const AVChapter* chapter) {
+    return chapter->start;
+}
+
+int64_t ffw_chapter_get_end(const AVChapter* chapter) {
+    return chapter->end;
+}
+
+const AVDictionaryEntry* ffw_chapter_get_metadata_entry(const AVChapter* chapter, const AVDictionaryEntry* prev) {
+    return av_dict_get(chapter->metadata, "", prev, AV_DICT_IGNORE_SUFFIX);
+}
+
+int ffw_chapter_set_metadata(AVChapter* chapter, const char* key, const char* value) {
+    return av_dict_set(&chapter->metadata, key, value, 0);
+}
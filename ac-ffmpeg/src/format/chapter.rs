@@ -0,0 +1,155 @@
+//! Chapters.
+
+use std::{
+    ffi::CStr,
+    os::raw::{c_char, c_void},
+    ptr,
+};
+
+use crate::time::{TimeBase, Timestamp};
+
+extern "C" {
+    fn ffw_chapter_get_id(chapter: *const c_void) -> i64;
+    fn ffw_chapter_get_time_base(chapter: *const c_void, num: *mut u32, den: *mut u32);
+    fn ffw_chapter_get_start(chapter: *const c_void) -> i64;
+    fn ffw_chapter_get_end(chapter: *const c_void) -> i64;
+    fn ffw_chapter_get_metadata_entry(chapter: *const c_void, prev: *const c_void)
+        -> *const c_void;
+
+    fn ffw_stream_get_metadata_entry_key(entry: *const c_void) -> *const c_char;
+    fn ffw_stream_get_metadata_entry_value(entry: *const c_void) -> *const c_char;
+}
+
+/// Media chapter.
+#[derive(Debug, Clone)]
+pub struct Chapter {
+    id: i64,
+    start: Timestamp,
+    end: Timestamp,
+    metadata: Vec<(String, String)>,
+}
+
+impl Chapter {
+    /// Create a new chapter with a given ID, start and end. The chapter end
+    /// will be rescaled into the time base of the chapter start.
+    pub fn new(id: i64, start: Timestamp, end: Timestamp) -> Self {
+        Self {
+            id,
+            start,
+            end: end.with_time_base(start.time_base()),
+            metadata: Vec::new(),
+        }
+    }
+
+    /// Create a chapter from its raw representation.
+    pub(crate) unsafe fn from_raw_ptr(ptr: *const c_void) -> Self {
+        let mut num = 0;
+        let mut den = 0;
+
+        ffw_chapter_get_time_base(ptr, &mut num, &mut den);
+
+        let time_base = TimeBase::new(num, den);
+
+        let start = Timestamp::new(ffw_chapter_get_start(ptr), time_base);
+        let end = Timestamp::new(ffw_chapter_get_end(ptr), time_base);
+
+        let mut metadata = Vec::new();
+
+        let mut entry = ptr::null();
+
+        loop {
+            entry = ffw_chapter_get_metadata_entry(ptr, entry);
+
+            if entry.is_null() {
+                break;
+            }
+
+            let key = CStr::from_ptr(ffw_stream_get_metadata_entry_key(entry));
+            let value = CStr::from_ptr(ffw_stream_get_metadata_entry_value(entry));
+
+            metadata.push((
+                key.to_string_lossy().into_owned(),
+                value.to_string_lossy().into_owned(),
+            ));
+        }
+
+        Self {
+            id: ffw_chapter_get_id(ptr),
+            start,
+            end,
+            metadata,
+        }
+    }
+
+    /// Get the chapter ID.
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    /// Get the chapter time base.
+    pub fn time_base(&self) -> TimeBase {
+        self.start.time_base()
+    }
+
+    /// Get the chapter start.
+    pub fn start(&self) -> Timestamp {
+        self.start
+    }
+
+    /// Get the chapter end.
+    pub fn end(&self) -> Timestamp {
+        self.end
+    }
+
+    /// Get the chapter title (if any).
+    pub fn title(&self) -> Option<&str> {
+        self.get_metadata("title")
+    }
+
+    /// Set the chapter title.
+    pub fn with_title<V>(self, title: V) -> Self
+    where
+        V: ToString,
+    {
+        self.with_metadata("title", title)
+    }
+
+    /// Get chapter metadata.
+    pub fn metadata(&self) -> &[(String, String)] {
+        &self.metadata
+    }
+
+    /// Get a chapter metadata entry.
+    pub fn get_metadata(&self, key: &str) -> Option<&str> {
+        self.metadata
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Set a chapter metadata entry. Any existing entry with the same key
+    /// will be replaced.
+    pub fn with_metadata<V>(mut self, key: &str, value: V) -> Self
+    where
+        V: ToString,
+    {
+        let value = value.to_string();
+
+        if let Some(entry) = self.metadata.iter_mut().find(|(k, _)| k == key) {
+            entry.1 = value;
+        } else {
+            self.metadata.push((key.to_string(), value));
+        }
+
+        self
+    }
+
+    /// Remove all metadata entries for which a given predicate returns
+    /// false.
+    pub(crate) fn retain_metadata<F>(&mut self, f: F)
+    where
+        F: FnMut(&(String, String)) -> bool,
+    {
+        self.metadata.retain(f);
+    }
+}
@@ -0,0 +1,571 @@
+//! Clocks for live pipelines.
+//!
+//! The `Clock` trait abstracts the source of time used for pacing the
+//! output (an equivalent of the FFmpeg `-re` flag), for stamping packets
+//! with the wall-clock time and for measuring drift between media
+//! timestamps and the clock. Live pipelines can be disciplined to a house
+//! clock (e.g. PTP) using the `ReferenceClock` or a custom implementation
+//! of the trait.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{packet::Packet, time::Timestamp};
+
+/// Default maximum timestamp gap of the pacer (in microseconds). Larger
+/// gaps are considered to be timestamp discontinuities.
+const DEFAULT_MAX_GAP: i64 = 10_000_000;
+
+/// Smoothing factor of the rate estimate of the reference clock.
+const RATE_SMOOTHING: f64 = 0.1;
+
+/// Maximum rate deviation of the reference clock from the local clock.
+const MAX_RATE_DEVIATION: f64 = 0.001;
+
+/// A source of time.
+pub trait Clock: Send + Sync {
+    /// Get the current time. The epoch depends on the clock, wall clocks
+    /// should use the Unix epoch.
+    fn now(&self) -> Timestamp;
+}
+
+impl<T> Clock for Arc<T>
+where
+    T: Clock + ?Sized,
+{
+    fn now(&self) -> Timestamp {
+        (**self).now()
+    }
+}
+
+impl<T> Clock for &T
+where
+    T: Clock + ?Sized,
+{
+    fn now(&self) -> Timestamp {
+        (**self).now()
+    }
+}
+
+/// The system wall clock. The time is in microseconds since the Unix epoch.
+/// Note that the system clock may jump (e.g. when it is adjusted by NTP).
+#[derive(Debug, Default, Copy, Clone)]
+pub struct SystemClock;
+
+impl SystemClock {
+    /// Create a new system clock.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Timestamp {
+        Timestamp::from_system_time(SystemTime::now())
+    }
+}
+
+/// Monotonic clock counting time since its creation.
+#[derive(Debug, Copy, Clone)]
+pub struct MonotonicClock {
+    epoch: Instant,
+}
+
+impl MonotonicClock {
+    /// Create a new monotonic clock starting at zero.
+    pub fn new() -> Self {
+        Self {
+            epoch: Instant::now(),
+        }
+    }
+
+    /// Get the clock time corresponding to a given instant.
+    fn time_at(&self, instant: Instant) -> i64 {
+        if instant >= self.epoch {
+            instant.duration_since(self.epoch).as_micros() as i64
+        } else {
+            -(self.epoch.duration_since(instant).as_micros() as i64)
+        }
+    }
+}
+
+impl Default for MonotonicClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now(&self) -> Timestamp {
+        Timestamp::from_micros(self.time_at(Instant::now()))
+    }
+}
+
+/// State of the reference clock.
+struct ReferenceState {
+    local: i64,
+    reference: i64,
+    rate: f64,
+}
+
+/// Clock following an external reference (e.g. PTP time read from a PTP
+/// daemon or a hardware clock). The reference time is extrapolated between
+/// updates using the local monotonic clock and the estimated rate of the
+/// reference clock. The system clock is used until the first update.
+///
+/// The clock can be shared between threads (e.g. using `Arc`), so it can be
+/// updated by one thread and used by the others.
+pub struct ReferenceClock {
+    local: MonotonicClock,
+    state: Mutex<Option<ReferenceState>>,
+}
+
+impl ReferenceClock {
+    /// Create a new reference clock.
+    pub fn new() -> Self {
+        Self {
+            local: MonotonicClock::new(),
+            state: Mutex::new(None),
+        }
+    }
+
+    /// Update the clock with a given reference time.
+    pub fn update(&self, reference: Timestamp) {
+        self.update_at(reference, Instant::now())
+    }
+
+    /// Update the clock with a given reference time valid at a given
+    /// instant.
+    pub fn update_at(&self, reference: Timestamp, instant: Instant) {
+        let reference = match reference.as_micros() {
+            Some(reference) => reference,
+            None => return,
+        };
+
+        let local = self.local.time_at(instant);
+
+        let mut state = self.state.lock().unwrap();
+
+        let rate = match state.as_ref() {
+            Some(previous) if local > previous.local => {
+                let measured =
+                    (reference - previous.reference) as f64 / (local - previous.local) as f64;
+
+                // ignore steps of the reference clock
+                if (measured - 1.0).abs() > MAX_RATE_DEVIATION {
+                    previous.rate
+                } else {
+                    previous.rate + RATE_SMOOTHING * (measured - previous.rate)
+                }
+            }
+            Some(previous) => previous.rate,
+            None => 1.0,
+        };
+
+        *state = Some(ReferenceState {
+            local,
+            reference,
+            rate,
+        });
+    }
+
+    /// Get the estimated rate of the reference clock relative to the local
+    /// monotonic clock (e.g. 1.00001 if the reference clock is 10 ppm
+    /// faster).
+    pub fn rate(&self) -> f64 {
+        self.state
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|state| state.rate)
+            .unwrap_or(1.0)
+    }
+
+    /// Get the reference time corresponding to a given instant.
+    fn time_at(&self, instant: Instant) -> Timestamp {
+        let state = self.state.lock().unwrap();
+
+        if let Some(state) = state.as_ref() {
+            let elapsed = (self.local.time_at(instant) - state.local) as f64;
+
+            Timestamp::from_micros(state.reference + (elapsed * state.rate).round() as i64)
+        } else {
+            Timestamp::from_system_time(SystemTime::now())
+        }
+    }
+}
+
+impl Default for ReferenceClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ReferenceClock {
+    fn now(&self) -> Timestamp {
+        self.time_at(Instant::now())
+    }
+}
+
+/// Pacer releasing media at the real-time rate given by a clock. It is an
+/// equivalent of the FFmpeg `-re` flag and it can be used e.g. for
+/// streaming files as live streams.
+///
+/// The first timestamp is released immediately (unless there is an initial
+/// burst) and every following timestamp is released when the clock
+/// advances by the same amount as the media timestamps. Timestamp
+/// discontinuities (jumps backward or forward by more than the maximum
+/// gap) re-anchor the pacer.
+///
+/// # Example
+/// ```text
+/// let mut pacer = Pacer::new(SystemClock::new());
+///
+/// while let Some(packet) = demuxer.take()? {
+///     pacer.wait(packet.dts());
+///
+///     muxer.push(packet)?;
+/// }
+/// ```
+pub struct Pacer<C> {
+    clock: C,
+    speed: f64,
+    initial_burst: i64,
+    max_gap: i64,
+    anchor: Option<(i64, i64)>,
+    last: Option<(i64, i64)>,
+}
+
+impl<C> Pacer<C>
+where
+    C: Clock,
+{
+    /// Create a new pacer using a given clock.
+    pub fn new(clock: C) -> Self {
+        Self {
+            clock,
+            speed: 1.0,
+            initial_burst: 0,
+            max_gap: DEFAULT_MAX_GAP,
+            anchor: None,
+            last: None,
+        }
+    }
+
+    /// Set the playback speed (e.g. 2.0 releases the media twice as fast).
+    /// The default is 1.0.
+    pub fn with_speed(mut self, speed: f64) -> Self {
+        if speed > 0.0 {
+            self.speed = speed;
+        }
+
+        self
+    }
+
+    /// Release a given amount of media at the beginning immediately. There
+    /// is no initial burst by default.
+    pub fn with_initial_burst(mut self, burst: Duration) -> Self {
+        self.initial_burst = burst.as_micros() as i64;
+        self
+    }
+
+    /// Set the maximum timestamp gap. Timestamps jumping forward by more
+    /// than this or backward by more than this are considered to be
+    /// discontinuities. The default is 10 seconds.
+    pub fn with_max_gap(mut self, gap: Duration) -> Self {
+        self.max_gap = gap.as_micros() as i64;
+        self
+    }
+
+    /// Get the time remaining until media with a given timestamp should be
+    /// released. Null timestamps are released immediately.
+    pub fn delay(&mut self, timestamp: Timestamp) -> Duration {
+        let now = match self.now() {
+            Some(now) => now,
+            None => return Duration::from_secs(0),
+        };
+
+        match self.release_time(timestamp, now) {
+            Some(release) if release > now => Duration::from_micros((release - now) as u64),
+            _ => Duration::from_secs(0),
+        }
+    }
+
+    /// Block the current thread until media with a given timestamp should be
+    /// released.
+    pub fn wait(&mut self, timestamp: Timestamp) {
+        let delay = self.delay(timestamp);
+
+        if delay > Duration::from_secs(0) {
+            thread::sleep(delay);
+        }
+    }
+
+    /// Reset the pacer. The next timestamp will be released immediately
+    /// (including the initial burst).
+    pub fn reset(&mut self) {
+        self.anchor = None;
+        self.last = None;
+    }
+
+    /// Get the current clock time in microseconds.
+    pub(crate) fn now(&self) -> Option<i64> {
+        self.clock.now().as_micros()
+    }
+
+    /// Get the clock time (in microseconds) of the last release.
+    pub(crate) fn last_release_time(&self) -> Option<i64> {
+        self.last.map(|(_, release)| release)
+    }
+
+    /// Get the clock time (in microseconds) when media with a given
+    /// timestamp should be released. None is returned for null timestamps.
+    pub(crate) fn release_time(&mut self, timestamp: Timestamp, now: i64) -> Option<i64> {
+        let media = timestamp.as_micros()?;
+
+        let initial_burst = (self.initial_burst as f64 / self.speed) as i64;
+
+        let (media_start, clock_start) = *self.anchor.get_or_insert((media, now - initial_burst));
+
+        let mut release = clock_start + ((media - media_start) as f64 / self.speed) as i64;
+
+        let last_media = self.last.map(|(media, _)| media).unwrap_or(media);
+
+        if media < last_media - self.max_gap || release - now > self.max_gap {
+            // timestamp discontinuity
+            release = self.last_release_time().unwrap_or(now).max(now);
+
+            self.anchor = Some((media, release));
+        }
+
+        self.last = Some((media, release));
+
+        Some(release)
+    }
+}
+
+/// Packet stamper replacing packet timestamps with the current clock time.
+/// It is an equivalent of the `use_wallclock_as_timestamps` demuxer option.
+/// Both PTS and DTS are set to the clock time, so it should not be used for
+/// streams with B-frames.
+pub struct ClockStamper<C> {
+    clock: C,
+    last: Option<Timestamp>,
+}
+
+impl<C> ClockStamper<C>
+where
+    C: Clock,
+{
+    /// Create a new stamper using a given clock.
+    pub fn new(clock: C) -> Self {
+        Self { clock, last: None }
+    }
+
+    /// Stamp a given packet. The timestamps are in the packet time base and
+    /// they are always increasing.
+    pub fn stamp(&mut self, packet: Packet) -> Packet {
+        let time_base = packet.time_base();
+
+        let mut ts = self.clock.now().with_time_base(time_base);
+
+        if let Some(last) = self.last {
+            let last = last.with_time_base(time_base);
+
+            if ts.timestamp() <= last.timestamp() {
+                ts = last.with_raw_timestamp(last.timestamp() + 1);
+            }
+        }
+
+        self.last = Some(ts);
+
+        packet.with_pts(ts).with_dts(ts)
+    }
+}
+
+/// Drift meter comparing the progress of media timestamps with the progress
+/// of a clock. It can be used for detecting inputs running faster or slower
+/// than the house clock.
+pub struct DriftMeter<C> {
+    clock: C,
+    window: i64,
+    samples: VecDeque<(i64, i64)>,
+}
+
+impl<C> DriftMeter<C>
+where
+    C: Clock,
+{
+    /// Create a new drift meter using a given clock. The drift rate is
+    /// estimated from samples within a given time window.
+    pub fn new(clock: C, window: Duration) -> Self {
+        Self {
+            clock,
+            window: window.as_micros() as i64,
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// Report a given media timestamp (e.g. DTS of a received packet).
+    pub fn push(&mut self, timestamp: Timestamp) {
+        if let Some(now) = self.clock.now().as_micros() {
+            self.push_at(timestamp, now);
+        }
+    }
+
+    /// Report a given media timestamp received at a given clock time (in
+    /// microseconds).
+    fn push_at(&mut self, timestamp: Timestamp, now: i64) {
+        let media = match timestamp.as_micros() {
+            Some(media) => media,
+            None => return,
+        };
+
+        self.samples.push_back((now, media));
+
+        while let Some((time, _)) = self.samples.front() {
+            if now - *time > self.window {
+                self.samples.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Get the drift within the window, i.e. how much the media timestamps
+    /// advanced more than the clock (negative values mean that the media is
+    /// slower than the clock).
+    pub fn drift(&self) -> Option<f64> {
+        let (first_time, first_media) = *self.samples.front()?;
+        let (last_time, last_media) = *self.samples.back()?;
+
+        let drift = (last_media - first_media) - (last_time - first_time);
+
+        Some(drift as f64 / 1_000_000.0)
+    }
+
+    /// Get the drift rate in parts per million estimated using linear
+    /// regression of the samples within the window.
+    pub fn drift_ppm(&self) -> Option<f64> {
+        if self.samples.len() < 2 {
+            return None;
+        }
+
+        let (t0, m0) = *self.samples.front()?;
+
+        let n = self.samples.len() as f64;
+
+        let mut sum_x = 0.0;
+        let mut sum_y = 0.0;
+        let mut sum_xx = 0.0;
+        let mut sum_xy = 0.0;
+
+        for (time, media) in &self.samples {
+            let x = (time - t0) as f64;
+            let y = (media - m0) as f64;
+
+            sum_x += x;
+            sum_y += y;
+            sum_xx += x * x;
+            sum_xy += x * y;
+        }
+
+        let denominator = n * sum_xx - sum_x * sum_x;
+
+        if denominator <= 0.0 {
+            return None;
+        }
+
+        let slope = (n * sum_xy - sum_x * sum_y) / denominator;
+
+        Some((slope - 1.0) * 1_000_000.0)
+    }
+
+    /// Drop all samples.
+    pub fn reset(&mut self) {
+        self.samples.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{DriftMeter, Pacer, ReferenceClock};
+
+    use crate::{test_util::ManualClock, time::Timestamp};
+
+    #[test]
+    fn test_pacer() {
+        let clock = ManualClock::new(1_000_000);
+
+        let mut pacer = Pacer::new(&clock);
+
+        assert_eq!(
+            pacer.delay(Timestamp::from_millis(500)),
+            Duration::from_secs(0)
+        );
+        assert_eq!(
+            pacer.delay(Timestamp::from_millis(540)),
+            Duration::from_millis(40)
+        );
+
+        clock.set(1_100_000);
+
+        assert_eq!(
+            pacer.delay(Timestamp::from_millis(540)),
+            Duration::from_secs(0)
+        );
+
+        // discontinuity
+        assert_eq!(
+            pacer.delay(Timestamp::from_secs(100)),
+            Duration::from_secs(0)
+        );
+        assert_eq!(
+            pacer.delay(Timestamp::from_millis(100_020)),
+            Duration::from_millis(20)
+        );
+    }
+
+    #[test]
+    fn test_drift_meter() {
+        let clock = ManualClock::new(0);
+
+        let mut meter = DriftMeter::new(&clock, Duration::from_secs(10));
+
+        // the media runs 100 ppm faster than the clock
+        for i in 0..=100 {
+            meter.push_at(Timestamp::from_micros(i * 100_010), i * 100_000);
+        }
+
+        assert!((meter.drift().unwrap() - 0.001).abs() < 1e-9);
+        assert!((meter.drift_ppm().unwrap() - 100.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_reference_clock() {
+        let clock = ReferenceClock::new();
+
+        let start = Instant::now();
+
+        // the reference clock runs 100 ppm faster
+        for i in 0..100 {
+            let t = start + Duration::from_secs(i);
+
+            clock.update_at(Timestamp::from_micros(i as i64 * 1_000_100), t);
+        }
+
+        assert!((clock.rate() - 1.0001).abs() < 1e-5);
+
+        let t = start + Duration::from_secs(100);
+
+        let now = clock.time_at(t).as_micros().unwrap();
+
+        assert!((now - 100_010_000).abs() < 10);
+    }
+}
@@ -0,0 +1,386 @@
+//! Concatenation of inputs with compatible codec parameters.
+//!
+//! The FFmpeg `concat` demuxer can be used as well. It reads a listing
+//! file and the referenced files are opened by FFmpeg itself. Absolute
+//! paths and URLs are accepted only if the `safe` option is set to 0:
+//!
+//! ```text
+//! let demuxer = Demuxer::builder()
+//!     .input_format(InputFormat::find_by_name("concat"))
+//!     .set_option("safe", 0)
+//!     .build_from_url("playlist.txt")?;
+//! ```
+
+use std::io::{Read, Write};
+
+use crate::{
+    codec::CodecParameters,
+    format::{
+        demuxer::Demuxer,
+        extract::reset_codec_tag,
+        io::IO,
+        muxer::{Muxer, OutputFormat},
+        stream::Stream,
+    },
+    packet::Packet,
+    time::{Rounding, TimeBase, Timestamp},
+    Error,
+};
+
+/// Timestamp adjuster for concatenated inputs. The first input starts at
+/// zero and every following input starts where the previous one ended.
+/// All inputs must have the same streams (in the same order) with
+/// compatible codec parameters, i.e. the same codecs, resolutions, pixel
+/// formats, sample rates, sample formats, channel counts and extradata.
+///
+/// # Usage
+/// 1. Add an input.
+/// 2. Push all packets of the input and pass the returned packets to a
+///    muxer.
+/// 3. If there are more inputs, continue with 1.
+pub struct Concatenator {
+    streams: Option<Vec<CodecParameters>>,
+    input_start: Option<i64>,
+    input_offset: i64,
+    end: i64,
+}
+
+impl Concatenator {
+    /// Create a new concatenator.
+    pub fn new() -> Self {
+        Self {
+            streams: None,
+            input_start: None,
+            input_offset: 0,
+            end: 0,
+        }
+    }
+
+    /// Start a new input with given streams. The method returns an error if
+    /// the streams are not compatible with the streams of the first input.
+    pub fn add_input(&mut self, streams: &[Stream]) -> Result<(), Error> {
+        let params = streams
+            .iter()
+            .map(|stream| stream.codec_parameters())
+            .collect();
+
+        let start = streams
+            .iter()
+            .filter_map(|stream| stream.start_time().as_micros())
+            .min();
+
+        self.start_input(params, start)
+    }
+
+    /// Start a new input with given codec parameters and a given start time
+    /// in microseconds. The start time will be taken from the first packet
+    /// if it is not known.
+    fn start_input(
+        &mut self,
+        params: Vec<CodecParameters>,
+        start: Option<i64>,
+    ) -> Result<(), Error> {
+        if let Some(streams) = self.streams.as_ref() {
+            if streams.len() != params.len() {
+                return Err(Error::new(
+                    "unable to concatenate inputs with different number of streams",
+                ));
+            }
+
+            let compatible = streams
+                .iter()
+                .zip(params.iter())
+                .all(|(a, b)| is_compatible(a, b));
+
+            if !compatible {
+                return Err(Error::new(
+                    "unable to concatenate inputs with incompatible codec parameters",
+                ));
+            }
+        } else {
+            self.streams = Some(params);
+        }
+
+        self.input_start = start;
+        self.input_offset = self.end;
+
+        Ok(())
+    }
+
+    /// Adjust timestamps of a given packet of the current input.
+    pub fn push(&mut self, packet: Packet) -> Packet {
+        let time_base = packet.time_base();
+
+        let pts = packet.pts();
+        let dts = packet.dts();
+
+        let first = if dts.is_null() { pts } else { dts };
+
+        let start = match self.input_start {
+            Some(start) => start,
+            None => match first.as_micros() {
+                Some(start) => *self.input_start.get_or_insert(start),
+                None => return packet,
+            },
+        };
+
+        // the same shift is used for all packets with the same time base,
+        // so the timestamp order is preserved
+        let shift = TimeBase::MICROSECONDS.rescale_rnd(
+            self.input_offset - start,
+            time_base,
+            Rounding::NearInfinity,
+        );
+
+        let pts = shift_timestamp(pts, shift);
+        let dts = shift_timestamp(dts, shift);
+
+        let last = if pts.is_null() || (!dts.is_null() && dts > pts) {
+            dts
+        } else {
+            pts
+        };
+
+        if !last.is_null() {
+            let end = last.timestamp() + packet.raw_duration().max(1);
+            let end = time_base.rescale_rnd(end, TimeBase::MICROSECONDS, Rounding::Up);
+
+            self.end = self.end.max(end);
+        }
+
+        packet.with_pts(pts).with_dts(dts)
+    }
+
+    /// Get the end of the concatenated output so far.
+    pub fn end(&self) -> Timestamp {
+        Timestamp::from_micros(self.end)
+    }
+}
+
+impl Default for Concatenator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Concatenate given inputs into a given output. All streams of all inputs
+/// are copied into the output. The inputs are opened one by one and they
+/// must be compatible (see `Concatenator` for details).
+///
+/// # Example
+/// ```text
+/// let inputs = vec![
+///     IO::from_seekable_read_stream(File::open("intro.mp4")?),
+///     IO::from_seekable_read_stream(File::open("movie.mp4")?),
+/// ];
+///
+/// let output = IO::from_seekable_write_stream(File::create("output.mp4")?);
+///
+/// let format = OutputFormat::find_by_name("mp4").unwrap();
+///
+/// concat(inputs, output, format)?;
+/// ```
+pub fn concat<R, W>(inputs: Vec<IO<R>>, output: IO<W>, format: OutputFormat) -> Result<IO<W>, Error>
+where
+    R: Read,
+    W: Write,
+{
+    let mut concatenator = Concatenator::new();
+
+    let mut output = Some(output);
+    let mut format = Some(format);
+
+    let mut muxer: Option<Muxer<W>> = None;
+
+    for input in inputs {
+        let mut demuxer = Demuxer::builder()
+            .build(input)?
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)?;
+
+        concatenator.add_input(demuxer.streams())?;
+
+        if muxer.is_none() {
+            let mut builder = Muxer::builder();
+
+            for stream in demuxer.streams() {
+                builder.add_stream(&reset_codec_tag(stream.codec_parameters()))?;
+            }
+
+            let res = builder.build(output.take().unwrap(), format.take().unwrap())?;
+
+            muxer = Some(res);
+        }
+
+        let muxer = muxer.as_mut().unwrap();
+
+        while let Some(packet) = demuxer.take()? {
+            muxer.push(concatenator.push(packet))?;
+        }
+    }
+
+    let mut muxer = muxer.ok_or_else(|| Error::new("no inputs to concatenate"))?;
+
+    muxer.flush()?;
+    muxer.close()
+}
+
+/// Shift a given timestamp (if not null).
+fn shift_timestamp(ts: Timestamp, shift: i64) -> Timestamp {
+    if ts.is_null() {
+        ts
+    } else {
+        ts.with_raw_timestamp(ts.timestamp() + shift)
+    }
+}
+
+/// Check if two streams with given codec parameters can be concatenated.
+fn is_compatible(a: &CodecParameters, b: &CodecParameters) -> bool {
+    if a.decoder_name() != b.decoder_name()
+        || a.encoder_name() != b.encoder_name()
+        || a.extradata() != b.extradata()
+    {
+        return false;
+    }
+
+    if let (Some(a), Some(b)) = (a.as_audio_codec_parameters(), b.as_audio_codec_parameters()) {
+        a.sample_rate() == b.sample_rate()
+            && a.sample_format() == b.sample_format()
+            && a.channel_layout().channels() == b.channel_layout().channels()
+    } else if let (Some(a), Some(b)) =
+        (a.as_video_codec_parameters(), b.as_video_codec_parameters())
+    {
+        a.width() == b.width() && a.height() == b.height() && a.pixel_format() == b.pixel_format()
+    } else {
+        a.media_type() as i32 == b.media_type() as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_compatible, Concatenator};
+
+    use crate::{
+        codec::{
+            audio::{frame::get_sample_format, ChannelLayout},
+            video::frame::get_pixel_format,
+            AudioCodecParameters, CodecParameters, VideoCodecParameters,
+        },
+        test_util::packet,
+        time::{TimeBase, Timestamp},
+    };
+
+    #[test]
+    fn test_timestamp_adjustment() {
+        let mut concatenator = Concatenator::new();
+
+        concatenator.start_input(Vec::new(), None).unwrap();
+
+        assert_eq!(
+            concatenator
+                .push(
+                    packet(0, TimeBase::new(1, 1000), 1000)
+                        .with_raw_duration(40)
+                        .freeze()
+                )
+                .pts()
+                .timestamp(),
+            0
+        );
+        assert_eq!(
+            concatenator
+                .push(
+                    packet(0, TimeBase::new(1, 1000), 1040)
+                        .with_raw_duration(40)
+                        .freeze()
+                )
+                .pts()
+                .timestamp(),
+            40
+        );
+
+        assert_eq!(concatenator.end(), Timestamp::from_millis(80));
+
+        concatenator.start_input(Vec::new(), Some(0)).unwrap();
+
+        assert_eq!(
+            concatenator
+                .push(
+                    packet(0, TimeBase::new(1, 1000), 0)
+                        .with_raw_duration(40)
+                        .freeze()
+                )
+                .dts()
+                .timestamp(),
+            80
+        );
+        assert_eq!(
+            concatenator
+                .push(
+                    packet(0, TimeBase::new(1, 1000), 40)
+                        .with_raw_duration(40)
+                        .freeze()
+                )
+                .dts()
+                .timestamp(),
+            120
+        );
+
+        assert_eq!(concatenator.end(), Timestamp::from_millis(160));
+    }
+
+    fn video(pixel_format: &str, extradata: Option<&[u8]>) -> CodecParameters {
+        VideoCodecParameters::builder("mpeg4")
+            .unwrap()
+            .width(64)
+            .height(48)
+            .pixel_format(get_pixel_format(pixel_format))
+            .extradata(extradata)
+            .build()
+            .into()
+    }
+
+    fn audio(sample_format: &str) -> CodecParameters {
+        AudioCodecParameters::builder("pcm_s16le")
+            .unwrap()
+            .sample_rate(8000)
+            .sample_format(get_sample_format(sample_format))
+            .channel_layout(&ChannelLayout::from_channels(1).unwrap())
+            .build()
+            .into()
+    }
+
+    #[test]
+    fn test_compatibility() {
+        assert!(is_compatible(
+            &video("yuv420p", None),
+            &video("yuv420p", None)
+        ));
+        assert!(!is_compatible(
+            &video("yuv420p", None),
+            &video("yuv444p", None)
+        ));
+        assert!(!is_compatible(
+            &video("yuv420p", Some(&[1, 2, 3])),
+            &video("yuv420p", Some(&[1, 2, 4]))
+        ));
+        assert!(!is_compatible(
+            &video("yuv420p", None),
+            &video("yuv420p", Some(&[1, 2, 3]))
+        ));
+
+        assert!(is_compatible(&audio("s16"), &audio("s16")));
+        assert!(!is_compatible(&audio("s16"), &audio("s16p")));
+        assert!(!is_compatible(&audio("s16"), &video("yuv420p", None)));
+
+        let mut concatenator = Concatenator::new();
+
+        concatenator
+            .start_input(vec![video("yuv420p", None)], None)
+            .unwrap();
+
+        assert!(concatenator
+            .start_input(vec![video("yuv444p", None)], None)
+            .is_err());
+    }
+}
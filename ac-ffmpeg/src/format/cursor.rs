@@ -0,0 +1,486 @@
+//! Frame-accurate navigation in a video stream.
+
+use std::collections::BTreeMap;
+
+use crate::{
+    codec::{
+        video::{VideoDecoder, VideoFrame},
+        Decoder,
+    },
+    format::{
+        demuxer::{DemuxerWithStreamInfo, SeekTarget},
+        stream::Discard,
+    },
+    time::{TimeBase, Timestamp},
+    Error,
+};
+
+/// Maximum number of attempts to find a frame preceding the current one.
+const MAX_BACKWARD_SEEKS: usize = 16;
+
+/// Builder for the frame cursor.
+pub struct FrameCursorBuilder {
+    stream_index: Option<usize>,
+    cache_size: usize,
+}
+
+impl FrameCursorBuilder {
+    /// Create a new builder.
+    fn new() -> Self {
+        Self {
+            stream_index: None,
+            cache_size: 64,
+        }
+    }
+
+    /// Set index of the video stream. The first video stream is used by
+    /// default.
+    pub fn stream_index(mut self, index: usize) -> Self {
+        self.stream_index = Some(index);
+        self
+    }
+
+    /// Set the maximum number of decoded frames kept in the cache. Stepping
+    /// backward within the cache does not require any decoding. The cache
+    /// should be able to hold at least one GOP, otherwise every backward
+    /// step requires decoding the GOP again. The default is 64 frames.
+    pub fn cache_size(mut self, frames: usize) -> Self {
+        self.cache_size = frames.max(1);
+        self
+    }
+
+    /// Build the frame cursor for a given demuxer. All other streams of the
+    /// demuxer will be discarded. The cursor is positioned at the first
+    /// frame.
+    pub fn build<T>(self, mut demuxer: DemuxerWithStreamInfo<T>) -> Result<FrameCursor<T>, Error> {
+        let index = match self.stream_index {
+            Some(index) if index < demuxer.streams().len() => index,
+            Some(_) => return Err(Error::new("stream index out of range")),
+            None => demuxer
+                .streams()
+                .iter()
+                .position(|stream| stream.codec_parameters().is_video_codec())
+                .ok_or_else(|| Error::new("no video stream found"))?,
+        };
+
+        let stream = &demuxer.streams()[index];
+
+        if !stream.codec_parameters().is_video_codec() {
+            return Err(Error::new("not a video stream"));
+        }
+
+        let time_base = stream.time_base();
+        let start = stream.start_time().with_time_base(time_base);
+
+        let decoder = VideoDecoder::from_stream(stream)?.build()?;
+
+        for (i, stream) in demuxer.streams_mut().iter_mut().enumerate() {
+            if i != index {
+                stream.set_discard(Discard::All);
+            }
+        }
+
+        let mut res = FrameCursor {
+            demuxer,
+            stream_index: index,
+            time_base,
+            start: if start.is_null() {
+                None
+            } else {
+                Some(start.timestamp())
+            },
+            decoder,
+            eof: false,
+            head: None,
+            cache: BTreeMap::new(),
+            cache_size: self.cache_size,
+            current: None,
+        };
+
+        res.step_forward()?;
+
+        Ok(res)
+    }
+}
+
+/// Frame cursor. It allows stepping through the frames of a video stream in
+/// both directions and seeking to a particular frame. It can be used e.g.
+/// for building a frame-accurate review player.
+///
+/// Decoded frames are cached. Stepping backward past the beginning of the
+/// cache seeks to the preceding keyframe and decodes the whole GOP again.
+/// Reverse playback can be implemented by repeated backward stepping. If
+/// the cache can hold a whole GOP, every GOP is decoded only once, however,
+/// the decoding happens in bursts, so reverse playback should be limited to
+/// reduced rates for long-GOP content.
+///
+/// All positions are presentation timestamps of the frames.
+pub struct FrameCursor<T> {
+    demuxer: DemuxerWithStreamInfo<T>,
+    stream_index: usize,
+    time_base: TimeBase,
+    start: Option<i64>,
+    decoder: VideoDecoder,
+    eof: bool,
+    head: Option<i64>,
+    cache: BTreeMap<i64, VideoFrame>,
+    cache_size: usize,
+    current: Option<i64>,
+}
+
+impl<T> FrameCursor<T> {
+    /// Get a builder for the frame cursor.
+    pub fn builder() -> FrameCursorBuilder {
+        FrameCursorBuilder::new()
+    }
+
+    /// Create a new frame cursor for the first video stream of a given
+    /// demuxer.
+    pub fn new(demuxer: DemuxerWithStreamInfo<T>) -> Result<Self, Error> {
+        Self::builder().build(demuxer)
+    }
+
+    /// Get the current frame.
+    pub fn current(&self) -> Option<&VideoFrame> {
+        self.current.and_then(|ts| self.cache.get(&ts))
+    }
+
+    /// Get time base of the video stream (and the frame timestamps).
+    pub fn time_base(&self) -> TimeBase {
+        self.time_base
+    }
+
+    /// Move to the next frame. The method returns None (and the position
+    /// does not change) if the current frame is the last one.
+    pub fn step_forward(&mut self) -> Result<Option<&VideoFrame>, Error> {
+        let next = match self.current {
+            Some(current) => self.cache.range(current + 1..).next().map(|(ts, _)| *ts),
+            None => self.cache.keys().next().copied(),
+        };
+
+        if let Some(next) = next {
+            self.current = Some(next);
+        } else if let Some(next) = self.decode_after_cache()? {
+            self.current = Some(next);
+        } else {
+            return Ok(None);
+        }
+
+        self.evict();
+
+        Ok(self.current())
+    }
+
+    /// Move to the previous frame. The method returns None (and the
+    /// position does not change) if the current frame is the first one.
+    pub fn step_backward(&mut self) -> Result<Option<&VideoFrame>, Error> {
+        let current = match self.current {
+            Some(current) => current,
+            None => return Ok(None),
+        };
+
+        let previous = self.cache.range(..current).next_back().map(|(ts, _)| *ts);
+
+        if let Some(previous) = previous {
+            self.current = Some(previous);
+        } else if let Some(previous) = self.decode_before_cache()? {
+            self.current = Some(previous);
+        } else {
+            return Ok(None);
+        }
+
+        self.evict();
+
+        Ok(self.current())
+    }
+
+    /// Move to the frame displayed at a given time, i.e. the last frame with
+    /// presentation timestamp lower or equal to a given timestamp (or the
+    /// first frame if there is no such frame).
+    pub fn seek(&mut self, timestamp: Timestamp) -> Result<Option<&VideoFrame>, Error> {
+        let target = timestamp.with_time_base(self.time_base);
+
+        if target.is_null() {
+            return Err(Error::new("null timestamp"));
+        }
+
+        let target = target.timestamp();
+
+        let first = self.cache.keys().next().copied();
+        let last = self.cache.keys().next_back().copied();
+
+        // the cache always contains a contiguous sequence of frames
+        let cached = match (first, last) {
+            (Some(first), Some(last)) => {
+                first <= target && (target <= last || self.is_at_end(last))
+            }
+            _ => false,
+        };
+
+        if !cached {
+            self.cache.clear();
+            self.reset(target)?;
+
+            while let Some((ts, frame)) = self.decode_next()? {
+                self.cache.insert(ts, frame);
+
+                evict(&mut self.cache, ts.min(target), self.cache_size);
+
+                if ts > target {
+                    break;
+                }
+            }
+        }
+
+        self.current = self
+            .cache
+            .range(..=target)
+            .next_back()
+            .or_else(|| self.cache.iter().next())
+            .map(|(ts, _)| *ts);
+
+        self.evict();
+
+        Ok(self.current())
+    }
+
+    /// Check if a given timestamp is the last frame of the stream.
+    fn is_at_end(&self, ts: i64) -> bool {
+        self.eof && self.head == Some(ts)
+    }
+
+    /// Decode the frame following the last cached frame and return its
+    /// timestamp.
+    fn decode_after_cache(&mut self) -> Result<Option<i64>, Error> {
+        let last = match self.cache.keys().next_back().copied() {
+            Some(last) => last,
+            None => {
+                return match self.decode_next()? {
+                    Some((ts, frame)) => {
+                        self.cache.insert(ts, frame);
+
+                        Ok(Some(ts))
+                    }
+                    None => Ok(None),
+                };
+            }
+        };
+
+        if self.head != Some(last) {
+            // the decoder is not positioned right after the last cached frame
+            self.reset(last)?;
+        } else if self.eof {
+            return Ok(None);
+        }
+
+        while let Some((ts, frame)) = self.decode_next()? {
+            self.cache.insert(ts, frame);
+
+            evict(&mut self.cache, ts, self.cache_size);
+
+            if ts > last {
+                return Ok(Some(ts));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Decode frames preceding the first cached frame and return timestamp
+    /// of the frame right before the first cached frame.
+    fn decode_before_cache(&mut self) -> Result<Option<i64>, Error> {
+        let first = match self.cache.keys().next().copied() {
+            Some(first) => first,
+            None => return Ok(None),
+        };
+
+        // start with one second before the first cached frame
+        let mut offset = (self.time_base.den() as i64 / self.time_base.num().max(1) as i64).max(1);
+
+        for _ in 0..MAX_BACKWARD_SEEKS {
+            let target = first - offset;
+
+            self.reset(target)?;
+
+            // frames following the first cached frame are not inserted, the
+            // demuxer may have skipped some frames if there is no keyframe
+            // before the target; the last decoded frame is the candidate for
+            // the new position, so the cache is trimmed around it as the GOP
+            // may not fit into the cache
+            while let Some((ts, frame)) = self.decode_next()? {
+                if ts >= first {
+                    break;
+                }
+
+                self.cache.insert(ts, frame);
+
+                evict(&mut self.cache, ts, self.cache_size);
+            }
+
+            if let Some((ts, _)) = self.cache.range(..first).next_back() {
+                return Ok(Some(*ts));
+            }
+
+            // there is nothing before the start of the stream
+            if matches!(self.start, Some(start) if target <= start) {
+                break;
+            }
+
+            offset *= 2;
+        }
+
+        Ok(None)
+    }
+
+    /// Seek to the keyframe preceding a given timestamp and reset the
+    /// decoder.
+    fn reset(&mut self, target: i64) -> Result<(), Error> {
+        let target = Timestamp::new(target, self.time_base);
+
+        self.demuxer.seek_to_timestamp(target, SeekTarget::UpTo)?;
+
+        let stream = &self.demuxer.streams()[self.stream_index];
+
+        self.decoder = VideoDecoder::from_stream(stream)?.build()?;
+        self.eof = false;
+        self.head = None;
+
+        Ok(())
+    }
+
+    /// Decode the next frame and return it together with its timestamp.
+    /// None is returned at the end of the stream.
+    fn decode_next(&mut self) -> Result<Option<(i64, VideoFrame)>, Error> {
+        loop {
+            if let Some(frame) = self.decoder.take()? {
+                let pts = frame.pts();
+
+                let ts = if pts.is_null() {
+                    frame.best_effort_timestamp()
+                } else {
+                    pts
+                };
+
+                // frames without any timestamp cannot be addressed
+                if ts.is_null() {
+                    continue;
+                }
+
+                let ts = ts.with_time_base(self.time_base).timestamp();
+
+                self.head = Some(ts);
+
+                return Ok(Some((ts, frame)));
+            } else if self.eof {
+                return Ok(None);
+            }
+
+            if let Some(packet) = self.demuxer.take()? {
+                if packet.stream_index() == self.stream_index {
+                    self.decoder.push(packet)?;
+                }
+            } else {
+                self.decoder.flush()?;
+                self.eof = true;
+            }
+        }
+    }
+
+    /// Drop cached frames that are farthest from the current position.
+    fn evict(&mut self) {
+        if let Some(current) = self.current {
+            evict(&mut self.cache, current, self.cache_size);
+        }
+    }
+}
+
+/// Drop cached items that are farthest from a given position until the
+/// cache contains at most `limit` items.
+fn evict<V>(cache: &mut BTreeMap<i64, V>, current: i64, limit: usize) {
+    while cache.len() > limit {
+        let before = cache.range(..current).count();
+        let after = cache.range(current + 1..).count();
+
+        let ts = if before > after {
+            cache.keys().next().copied()
+        } else {
+            cache.keys().next_back().copied()
+        };
+
+        if let Some(ts) = ts {
+            cache.remove(&ts);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{evict, FrameCursor, FrameCursorBuilder};
+
+    use crate::{
+        format::demuxer::Demuxer,
+        test_util,
+        time::{TimeBase, Timestamp},
+    };
+
+    #[test]
+    fn test_evict() {
+        let mut cache = (0..10).map(|ts| (ts, ())).collect::<BTreeMap<_, _>>();
+
+        evict(&mut cache, 2, 5);
+
+        assert_eq!(cache.keys().copied().collect::<Vec<_>>(), [0, 1, 2, 3, 4]);
+
+        evict(&mut cache, 4, 2);
+
+        assert_eq!(cache.keys().copied().collect::<Vec<_>>(), [3, 4]);
+
+        // the position does not have to be cached
+        evict(&mut cache, 10, 1);
+
+        assert_eq!(cache.keys().copied().collect::<Vec<_>>(), [4]);
+    }
+
+    #[test]
+    fn test_cache_size() {
+        let data = test_util::encode_video(64, 64, 50, 10);
+
+        let demuxer = Demuxer::builder()
+            .build_from_bytes(&data)
+            .unwrap()
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)
+            .unwrap();
+
+        let mut cursor = FrameCursorBuilder::new()
+            .cache_size(4)
+            .build(demuxer)
+            .unwrap();
+
+        let frame_time_base = TimeBase::new(1, 25);
+
+        let position = |cursor: &FrameCursor<_>| {
+            cursor
+                .current()
+                .map(|frame| frame.pts().with_time_base(frame_time_base).timestamp())
+        };
+
+        cursor
+            .seek(Timestamp::new(35, frame_time_base))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(position(&cursor), Some(35));
+        assert!(cursor.cache.len() <= 4);
+
+        // step back over two GOP boundaries
+        for expected in (12..35).rev() {
+            cursor.step_backward().unwrap().unwrap();
+
+            assert_eq!(position(&cursor), Some(expected));
+            assert!(cursor.cache.len() <= 4);
+        }
+    }
+}
@@ -0,0 +1,365 @@
+//! Safe cut points for stream copy segmentation.
+
+use std::time::Duration;
+
+use crate::{packet::Packet, time::Timestamp};
+
+/// Direction of the cut point search.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CutDirection {
+    /// Find the last cut point at or before the target.
+    Before,
+    /// Find the first cut point at or after the target.
+    After,
+    /// Find the cut point closest to the target.
+    Nearest,
+}
+
+/// Summary of cut points of a single stream.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct CutPointReport {
+    keyframes: usize,
+    open_gop_keyframes: usize,
+    cut_points: usize,
+    max_interval: Option<Duration>,
+    avg_interval: Option<Duration>,
+}
+
+impl CutPointReport {
+    /// Get the number of keyframes.
+    pub fn keyframes(&self) -> usize {
+        self.keyframes
+    }
+
+    /// Get the number of keyframes followed by leading pictures referencing
+    /// the previous group of pictures (open GOP). Cutting at these
+    /// keyframes would produce broken frames at the segment start.
+    pub fn open_gop_keyframes(&self) -> usize {
+        self.open_gop_keyframes
+    }
+
+    /// Get the number of safe cut points.
+    pub fn cut_points(&self) -> usize {
+        self.cut_points
+    }
+
+    /// Get the maximum interval between two consecutive safe cut points.
+    pub fn max_interval(&self) -> Option<Duration> {
+        self.max_interval
+    }
+
+    /// Get the average interval between two consecutive safe cut points.
+    pub fn avg_interval(&self) -> Option<Duration> {
+        self.avg_interval
+    }
+
+    /// Get the achievable cut precision, i.e. the maximum distance between
+    /// an arbitrary cut target and the closest preceding safe cut point.
+    pub fn precision(&self) -> Option<Duration> {
+        self.max_interval
+    }
+}
+
+/// Keyframe waiting for the end of its group of pictures.
+struct Candidate {
+    pts: Timestamp,
+    micros: i64,
+    open: bool,
+}
+
+/// Cut point state of a single stream.
+#[derive(Default)]
+struct StreamCutPoints {
+    candidate: Option<Candidate>,
+    cut_points: Vec<(Timestamp, i64)>,
+    keyframes: usize,
+    open_gop_keyframes: usize,
+}
+
+impl StreamCutPoints {
+    /// Process a given packet.
+    fn push(&mut self, packet: &Packet) {
+        let pts = packet.pts();
+
+        let micros = match pts.as_micros() {
+            Some(micros) => micros,
+            None => return,
+        };
+
+        if packet.is_key() {
+            self.finish();
+
+            self.keyframes += 1;
+
+            self.candidate = Some(Candidate {
+                pts,
+                micros,
+                open: false,
+            });
+        } else if let Some(candidate) = self.candidate.as_mut() {
+            // a leading picture displayed before the keyframe
+            if micros < candidate.micros {
+                candidate.open = true;
+            }
+        }
+    }
+
+    /// Finish the current group of pictures.
+    fn finish(&mut self) {
+        if let Some(candidate) = self.candidate.take() {
+            if candidate.open {
+                self.open_gop_keyframes += 1;
+            } else {
+                self.cut_points.push((candidate.pts, candidate.micros));
+            }
+        }
+    }
+
+    /// Find a cut point.
+    fn find(&self, target: i64, direction: CutDirection) -> Option<Timestamp> {
+        let index = self
+            .cut_points
+            .partition_point(|&(_, micros)| micros <= target);
+
+        let before = index.checked_sub(1).map(|i| self.cut_points[i]);
+
+        let after = if before.map(|(_, micros)| micros == target) == Some(true) {
+            before
+        } else {
+            self.cut_points.get(index).copied()
+        };
+
+        let res = match direction {
+            CutDirection::Before => before,
+            CutDirection::After => after,
+            CutDirection::Nearest => match (before, after) {
+                (Some(b), Some(a)) if (a.1 - target) < (target - b.1) => Some(a),
+                (Some(b), _) => Some(b),
+                (None, a) => a,
+            },
+        };
+
+        res.map(|(pts, _)| pts)
+    }
+
+    /// Create a report.
+    fn report(&self) -> CutPointReport {
+        let intervals = self
+            .cut_points
+            .windows(2)
+            .map(|w| (w[1].1 - w[0].1).max(0) as u64)
+            .collect::<Vec<_>>();
+
+        let max_interval = intervals.iter().copied().max().map(Duration::from_micros);
+
+        let avg_interval = if intervals.is_empty() {
+            None
+        } else {
+            let sum = intervals.iter().sum::<u64>();
+
+            Some(Duration::from_micros(sum / intervals.len() as u64))
+        };
+
+        CutPointReport {
+            keyframes: self.keyframes,
+            open_gop_keyframes: self.open_gop_keyframes,
+            cut_points: self.cut_points.len(),
+            max_interval,
+            avg_interval,
+        }
+    }
+}
+
+/// Finder of safe cut points for stream copy segmentation. A safe cut point
+/// is a keyframe that is not followed (in decoding order) by leading
+/// pictures, i.e. pictures displayed before the keyframe that may reference
+/// the previous group of pictures. Cutting a stream copy at any other packet
+/// would produce undecodable frames at the segment start.
+///
+/// # Usage
+/// 1. Push all packets (e.g. from the first pass over the input or from a
+///    look-ahead buffer) in decoding order.
+/// 2. Flush the finder to evaluate the last group of pictures of every
+///    stream.
+/// 3. Find cut points closest to the required segment boundaries and check
+///    the achievable precision of every stream.
+///
+/// Audio streams usually have every packet flagged as a keyframe, so every
+/// packet is a safe cut point.
+#[derive(Default)]
+pub struct CutPointFinder {
+    streams: Vec<StreamCutPoints>,
+}
+
+impl CutPointFinder {
+    /// Create a new cut point finder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Push a given packet.
+    pub fn push(&mut self, packet: &Packet) {
+        let index = packet.stream_index();
+
+        if index >= self.streams.len() {
+            self.streams.resize_with(index + 1, Default::default);
+        }
+
+        self.streams[index].push(packet);
+    }
+
+    /// Evaluate the last group of pictures of every stream. Packets pushed
+    /// after flushing start a new group of pictures only if they are
+    /// keyframes.
+    pub fn flush(&mut self) {
+        for stream in &mut self.streams {
+            stream.finish();
+        }
+    }
+
+    /// Get all safe cut points of a given stream found so far.
+    pub fn cut_points(&self, stream_index: usize) -> Vec<Timestamp> {
+        self.streams
+            .get(stream_index)
+            .map(|stream| stream.cut_points.iter().map(|&(pts, _)| pts).collect())
+            .unwrap_or_default()
+    }
+
+    /// Find a safe cut point of a given stream close to a given target.
+    pub fn find(
+        &self,
+        stream_index: usize,
+        target: Timestamp,
+        direction: CutDirection,
+    ) -> Option<Timestamp> {
+        let target = target.as_micros()?;
+
+        self.streams.get(stream_index)?.find(target, direction)
+    }
+
+    /// Get the cut point report of a given stream.
+    pub fn report(&self, stream_index: usize) -> Option<CutPointReport> {
+        self.streams.get(stream_index).map(|stream| stream.report())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{CutDirection, CutPointFinder};
+
+    use crate::{
+        test_util::packet,
+        time::{TimeBase, Timestamp},
+    };
+
+    #[test]
+    fn test_open_gop() {
+        let mut finder = CutPointFinder::new();
+
+        // closed GOP: I0 P120 B40 B80
+        finder.push(
+            &packet(0, TimeBase::new(1, 1000), 0)
+                .with_key_flag(true)
+                .freeze(),
+        );
+        finder.push(
+            &packet(0, TimeBase::new(1, 1000), 120)
+                .with_key_flag(false)
+                .freeze(),
+        );
+        finder.push(
+            &packet(0, TimeBase::new(1, 1000), 40)
+                .with_key_flag(false)
+                .freeze(),
+        );
+        finder.push(
+            &packet(0, TimeBase::new(1, 1000), 80)
+                .with_key_flag(false)
+                .freeze(),
+        );
+
+        // open GOP: I280 B160 B200 B240 (leading pictures)
+        finder.push(
+            &packet(0, TimeBase::new(1, 1000), 280)
+                .with_key_flag(true)
+                .freeze(),
+        );
+        finder.push(
+            &packet(0, TimeBase::new(1, 1000), 160)
+                .with_key_flag(false)
+                .freeze(),
+        );
+        finder.push(
+            &packet(0, TimeBase::new(1, 1000), 200)
+                .with_key_flag(false)
+                .freeze(),
+        );
+        finder.push(
+            &packet(0, TimeBase::new(1, 1000), 240)
+                .with_key_flag(false)
+                .freeze(),
+        );
+
+        // closed GOP
+        finder.push(
+            &packet(0, TimeBase::new(1, 1000), 1000)
+                .with_key_flag(true)
+                .freeze(),
+        );
+        finder.push(
+            &packet(0, TimeBase::new(1, 1000), 1040)
+                .with_key_flag(false)
+                .freeze(),
+        );
+
+        finder.flush();
+
+        let points = finder
+            .cut_points(0)
+            .iter()
+            .map(|pts| pts.as_millis().unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(points, vec![0, 1000]);
+
+        let report = finder.report(0).unwrap();
+
+        assert_eq!(report.keyframes(), 3);
+        assert_eq!(report.open_gop_keyframes(), 1);
+        assert_eq!(report.precision(), Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_find() {
+        let mut finder = CutPointFinder::new();
+
+        for i in 0..5 {
+            finder.push(
+                &packet(0, TimeBase::new(1, 1000), i * 2000)
+                    .with_key_flag(true)
+                    .freeze(),
+            );
+            finder.push(
+                &packet(0, TimeBase::new(1, 1000), i * 2000 + 40)
+                    .with_key_flag(false)
+                    .freeze(),
+            );
+        }
+
+        finder.flush();
+
+        let find = |ms: i64, direction: CutDirection| {
+            finder
+                .find(0, Timestamp::from_millis(ms), direction)
+                .and_then(|pts| pts.as_millis())
+        };
+
+        assert_eq!(find(4500, CutDirection::Before), Some(4000));
+        assert_eq!(find(4500, CutDirection::After), Some(6000));
+        assert_eq!(find(5500, CutDirection::Nearest), Some(6000));
+        assert_eq!(find(4000, CutDirection::After), Some(4000));
+        assert_eq!(find(9000, CutDirection::After), None);
+    }
+}
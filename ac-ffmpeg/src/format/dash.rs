@@ -0,0 +1,335 @@
+//! MPEG-DASH muxer.
+//!
+//! This is a wrapper around the FFmpeg `dash` muxer. The DASH muxer writes
+//! the manifest and the segments on its own. By default, they are written
+//! into files (or other URLs) relative to the manifest URL. An output
+//! handler can be used for writing them using custom IO instead (e.g. for
+//! uploading them into an object storage).
+
+use std::{
+    io::{self, Sink, Write},
+    time::Duration,
+};
+
+use crate::{
+    codec::CodecParameters,
+    format::{
+        io::IO,
+        muxer::{Muxer, MuxerBuilder, OutputFormat, OutputHandler},
+    },
+    packet::Packet,
+    Error,
+};
+
+/// Prefix of the manifest URL used with output handlers. The DASH muxer
+/// writes the manifest into a temporary file and renames it afterwards if
+/// the manifest URL is a local file, which would bypass the handler. The
+/// prefix makes the URL protocol unknown and it is removed from all URLs
+/// passed to the handler.
+const HANDLER_URL_PREFIX: &str = "ffw-output:";
+
+/// Builder for the DASH muxer.
+pub struct DashMuxerBuilder {
+    builder: MuxerBuilder,
+    handler: Option<OutputHandler>,
+}
+
+impl DashMuxerBuilder {
+    /// Create a new builder.
+    fn new() -> Self {
+        Self {
+            builder: Muxer::builder(),
+            handler: None,
+        }
+    }
+
+    /// Add a new stream with given parameters and return index of the new
+    /// stream.
+    pub fn add_stream(&mut self, params: &CodecParameters) -> Result<usize, Error> {
+        self.builder.add_stream(params)
+    }
+
+    /// Set a DASH muxer option.
+    pub fn set_option<V>(mut self, name: &str, value: V) -> Self
+    where
+        V: ToString,
+    {
+        self.builder = self.builder.set_option(name, value);
+        self
+    }
+
+    /// Set the target segment duration. The default is 5 seconds.
+    pub fn segment_duration(self, duration: Duration) -> Self {
+        let duration = format!("{}.{:06}", duration.as_secs(), duration.subsec_micros());
+
+        self.set_option("seg_duration", duration)
+    }
+
+    /// Set the number of segments kept in the manifest. Zero means keeping
+    /// all segments (i.e. a static manifest). The default is 0.
+    pub fn window_size(self, segments: usize) -> Self {
+        self.set_option("window_size", segments)
+    }
+
+    /// Set the number of segments kept outside of the manifest before they
+    /// are removed. It is used only together with `window_size()`. The
+    /// default is 5.
+    pub fn extra_window_size(self, segments: usize) -> Self {
+        self.set_option("extra_window_size", segments)
+    }
+
+    /// Set the name template of initialization segments. The default is
+    /// `init-stream$RepresentationID$.$ext$`.
+    pub fn init_segment_name(self, template: &str) -> Self {
+        self.set_option("init_seg_name", template)
+    }
+
+    /// Set the name template of media segments. The default is
+    /// `chunk-stream$RepresentationID$-$Number%05d$.$ext$`.
+    pub fn media_segment_name(self, template: &str) -> Self {
+        self.set_option("media_seg_name", template)
+    }
+
+    /// Use `SegmentTemplate` instead of `SegmentList` in the manifest. It is
+    /// enabled by default.
+    pub fn use_template(self, enabled: bool) -> Self {
+        self.set_option("use_template", enabled as i32)
+    }
+
+    /// Use `SegmentTimeline` in `SegmentTemplate`. It is enabled by default.
+    pub fn use_timeline(self, enabled: bool) -> Self {
+        self.set_option("use_timeline", enabled as i32)
+    }
+
+    /// Set a handler for writing the manifest and the segments. The handler
+    /// is called with the URL of every output the muxer opens (relative to
+    /// the manifest URL) and it returns a writer for the output. The writer
+    /// is flushed and dropped once the output is complete. The handler is
+    /// called from the thread using the muxer. Outputs are written into
+    /// files (or other URLs) if no handler is set.
+    ///
+    /// Note that the muxer opens the manifest repeatedly (every time it
+    /// gets updated) and it expects the old content to be replaced. Removal
+    /// of old segments (see `window_size()`) is not passed to the handler.
+    pub fn output_handler<F, W>(mut self, mut handler: F) -> Self
+    where
+        F: FnMut(&str) -> io::Result<W> + Send + 'static,
+        W: Write + Send + 'static,
+    {
+        let handler: OutputHandler = Box::new(move |url| {
+            let url = url.strip_prefix(HANDLER_URL_PREFIX).unwrap_or(url);
+
+            let output: Box<dyn Write + Send> = Box::new(handler(url)?);
+
+            Ok(output)
+        });
+
+        self.handler = Some(handler);
+        self
+    }
+
+    /// Build the DASH muxer. The manifest will be written to a given URL
+    /// (e.g. `live/manifest.mpd`), the segments will be written next to
+    /// it.
+    pub fn build(mut self, url: &str) -> Result<DashMuxer, Error> {
+        let format = OutputFormat::find_by_name("dash")
+            .ok_or_else(|| Error::new("the DASH muxer is not available"))?;
+
+        let mut res = DashMuxer {
+            muxer: None,
+            handler: None,
+        };
+
+        let url = if let Some(handler) = self.handler {
+            let handler = Box::into_raw(Box::new(handler));
+
+            res.handler = Some(handler);
+
+            self.builder.set_output_handler(handler);
+
+            format!("{}{}", HANDLER_URL_PREFIX, url)
+        } else {
+            String::from(url)
+        };
+
+        // the DASH muxer opens the outputs on its own, the IO is not used
+        let muxer = self
+            .builder
+            .set_option("url", url)
+            .build(IO::from_write_stream(io::sink()), format)?;
+
+        res.muxer = Some(muxer);
+
+        Ok(res)
+    }
+}
+
+/// DASH muxer.
+///
+/// # Example
+/// ```text
+/// let mut builder = DashMuxer::builder()
+///     .segment_duration(Duration::from_secs(4))
+///     .window_size(5)
+///     .media_segment_name("chunk-$RepresentationID$-$Number%05d$.$ext$")
+///     .output_handler(|url| Ok(storage.create(url)?));
+///
+/// builder.add_stream(&video_params)?;
+/// builder.add_stream(&audio_params)?;
+///
+/// let mut muxer = builder.build("live/manifest.mpd")?;
+///
+/// while let Some(packet) = source.next()? {
+///     muxer.push(packet)?;
+/// }
+///
+/// muxer.close()?;
+/// ```
+///
+/// Packets must be pushed in the decoding order and interleaved across
+/// streams.
+pub struct DashMuxer {
+    muxer: Option<Muxer<Sink>>,
+    handler: Option<*mut OutputHandler>,
+}
+
+impl DashMuxer {
+    /// Get a DASH muxer builder.
+    pub fn builder() -> DashMuxerBuilder {
+        DashMuxerBuilder::new()
+    }
+
+    /// Push a given packet to the muxer.
+    pub fn push(&mut self, packet: Packet) -> Result<(), Error> {
+        self.muxer_mut().push(packet)
+    }
+
+    /// Flush the muxer.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.muxer_mut().flush()
+    }
+
+    /// Close the muxer. This will write the last segment and the final
+    /// manifest.
+    pub fn close(mut self) -> Result<(), Error> {
+        let mut muxer = self.muxer.take().unwrap();
+
+        muxer.flush()?;
+        muxer.close().map(|_| ())
+    }
+
+    /// Get the inner muxer.
+    fn muxer_mut(&mut self) -> &mut Muxer<Sink> {
+        self.muxer.as_mut().unwrap()
+    }
+}
+
+impl Drop for DashMuxer {
+    fn drop(&mut self) {
+        // the muxer must be dropped first as it may still use the handler
+        self.muxer = None;
+
+        if let Some(handler) = self.handler.take() {
+            unsafe {
+                drop(Box::from_raw(handler));
+            }
+        }
+    }
+}
+
+unsafe impl Send for DashMuxer {}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        io::{self, Cursor, Write},
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use super::DashMuxer;
+
+    use crate::{
+        format::{demuxer::Demuxer, extract::reset_codec_tag, io::IO},
+        test_util::encode_video,
+    };
+
+    /// Outputs written by the DASH muxer.
+    type Outputs = Arc<Mutex<HashMap<String, Vec<u8>>>>;
+
+    /// Writer appending data to a given output.
+    struct OutputWriter {
+        url: String,
+        outputs: Outputs,
+    }
+
+    impl Write for OutputWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let mut outputs = self.outputs.lock().unwrap();
+
+            outputs.get_mut(&self.url).unwrap().extend_from_slice(buf);
+
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_output_handler() {
+        let data = encode_video(64, 48, 50, 10);
+
+        let mut demuxer = Demuxer::builder()
+            .build(IO::from_read_stream(Cursor::new(data)))
+            .unwrap()
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)
+            .unwrap();
+
+        let outputs = Outputs::default();
+
+        let mut builder = DashMuxer::builder()
+            .segment_duration(Duration::from_secs(1))
+            .output_handler({
+                let outputs = outputs.clone();
+
+                move |url| {
+                    // the old content is replaced
+                    outputs
+                        .lock()
+                        .unwrap()
+                        .insert(String::from(url), Vec::new());
+
+                    let writer = OutputWriter {
+                        url: String::from(url),
+                        outputs: outputs.clone(),
+                    };
+
+                    Ok(writer)
+                }
+            });
+
+        builder
+            .add_stream(&reset_codec_tag(demuxer.streams()[0].codec_parameters()))
+            .unwrap();
+
+        let mut muxer = builder.build("live/manifest.mpd").unwrap();
+
+        while let Some(packet) = demuxer.take().unwrap() {
+            muxer.push(packet).unwrap();
+        }
+
+        muxer.close().unwrap();
+
+        let outputs = outputs.lock().unwrap();
+
+        let manifest = String::from_utf8_lossy(&outputs["live/manifest.mpd"]).into_owned();
+
+        assert!(manifest.contains("<MPD"));
+        assert!(!outputs["live/init-stream0.m4s"].is_empty());
+        assert!(!outputs["live/chunk-stream0-00001.m4s"].is_empty());
+    }
+}
@@ -1,3 +1,5 @@
+#include <string.h>
+
 #include <libavformat/avformat.h>
 #include <libavutil/opt.h>
 #include <libavutil/avstring.h>
@@ -44,26 +46,78 @@ AVInputFormat* ffw_guess_input_format(
     return (AVInputFormat*)res;
 }
 
+const AVInputFormat* ffw_probe_input_format(const uint8_t* data, int size) {
+    const AVInputFormat* res;
+    AVProbeData pd;
+    uint8_t* buf;
+    int score;
+
+    // the probe buffer must be padded with zeros
+    if (!(buf = av_mallocz(size + AVPROBE_PADDING_SIZE))) {
+        return NULL;
+    }
+
+    memcpy(buf, data, size);
+
+    memset(&pd, 0, sizeof(pd));
+
+    pd.filename = "";
+    pd.buf = buf;
+    pd.buf_size = size;
+
+    score = 0;
+
+    res = av_probe_input_format3(&pd, 1, &score);
+
+    av_free(buf);
+
+    return res;
+}
+
 const char* ffw_input_format_name(const AVInputFormat* input_format) {
     return input_format->name;
 }
 
+typedef int protocol_open_t(void*, const char*, AVIOContext**);
+typedef int protocol_close_t(void*, AVIOContext*);
+
 typedef struct Demuxer {
     AVFormatContext* fc;
     AVDictionary* options;
     AVPacket* packet;
+
+    int (*io_open)(AVFormatContext*, AVIOContext**, const char*, int, AVDictionary**);
+#if LIBAVFORMAT_VERSION_MAJOR >= 60
+    int (*io_close2)(AVFormatContext*, AVIOContext*);
+#else
+    void (*io_close)(AVFormatContext*, AVIOContext*);
+#endif
+
+    protocol_open_t* protocol_open;
+    protocol_close_t* protocol_close;
+    void* protocol_opaque;
 } Demuxer;
 
 Demuxer* ffw_demuxer_new();
 int ffw_demuxer_init(Demuxer* demuxer, AVIOContext* io_context, AVInputFormat* format);
 int ffw_demuxer_set_initial_option(Demuxer* demuxer, const char* key, const char* value);
 int ffw_demuxer_set_option(Demuxer* demuxer, const char* key, const char* value);
+void ffw_demuxer_set_protocol_handler(Demuxer* demuxer, protocol_open_t* open, protocol_close_t* close, void* opaque);
 int ffw_demuxer_find_stream_info(Demuxer* demuxer, int64_t max_analyze_duration);
+int ffw_demuxer_find_stream_info_fast(Demuxer* demuxer, int64_t max_analyze_duration, int64_t max_probe_size);
 unsigned ffw_demuxer_get_nb_streams(const Demuxer* demuxer);
 AVStream* ffw_demuxer_get_stream(Demuxer* demuxer, unsigned stream_index);
 const AVInputFormat* ffw_demuxer_get_input_format(const Demuxer* demuxer);
+int64_t ffw_demuxer_get_duration(const Demuxer* demuxer);
+int64_t ffw_demuxer_get_bit_rate(const Demuxer* demuxer);
+int ffw_demuxer_get_duration_estimation_method(const Demuxer* demuxer);
+const AVDictionaryEntry* ffw_demuxer_get_metadata_entry(const Demuxer* demuxer, const AVDictionaryEntry* prev);
+unsigned ffw_demuxer_get_nb_chapters(const Demuxer* demuxer);
+const AVChapter* ffw_demuxer_get_chapter(const Demuxer* demuxer, unsigned index);
 int ffw_demuxer_read_frame(Demuxer* demuxer, AVPacket** packet, uint32_t* tb_num, uint32_t* tb_den);
 int ffw_demuxer_seek(Demuxer* demuxer, int64_t timestamp, int seek_by, int seek_target);
+char* ffw_demuxer_get_io_option(const Demuxer* demuxer, const char* name);
+void ffw_demuxer_free_io_option(char* value);
 void ffw_demuxer_free(Demuxer* demuxer);
 
 Demuxer* ffw_demuxer_new() {
@@ -126,6 +180,82 @@ int ffw_demuxer_set_option(Demuxer* demuxer, const char* key, const char* value)
     return av_opt_set(demuxer->fc, key, value, AV_OPT_SEARCH_CHILDREN);
 }
 
+static int ffw_demuxer_io_open(
+    AVFormatContext* fc,
+    AVIOContext** pb,
+    const char* url,
+    int flags,
+    AVDictionary** options) {
+    Demuxer* demuxer = fc->opaque;
+    int ret;
+
+    ret = demuxer->protocol_open(demuxer->protocol_opaque, url, pb);
+    if (ret < 0) {
+        return ret;
+    } else if (ret == 0) {
+        return demuxer->io_open(fc, pb, url, flags, options);
+    }
+
+    // the input IO context gets closed using avio_close() instead of
+    // io_close, so we need to make sure that it won't touch our context
+    if (pb == &fc->pb) {
+        fc->flags |= AVFMT_FLAG_CUSTOM_IO;
+    }
+
+    return 0;
+}
+
+static int ffw_demuxer_close_io(AVFormatContext* fc, AVIOContext* pb) {
+    Demuxer* demuxer = fc->opaque;
+
+    if (pb && demuxer->protocol_close(demuxer->protocol_opaque, pb)) {
+        return 0;
+    }
+
+#if LIBAVFORMAT_VERSION_MAJOR >= 60
+    return demuxer->io_close2(fc, pb);
+#else
+    demuxer->io_close(fc, pb);
+
+    return 0;
+#endif
+}
+
+#if LIBAVFORMAT_VERSION_MAJOR >= 60
+static int ffw_demuxer_io_close2(AVFormatContext* fc, AVIOContext* pb) {
+    return ffw_demuxer_close_io(fc, pb);
+}
+#else
+static void ffw_demuxer_io_close(AVFormatContext* fc, AVIOContext* pb) {
+    ffw_demuxer_close_io(fc, pb);
+}
+#endif
+
+void ffw_demuxer_set_protocol_handler(
+    Demuxer* demuxer,
+    protocol_open_t* open,
+    protocol_close_t* close,
+    void* opaque) {
+    demuxer->protocol_open = open;
+    demuxer->protocol_close = close;
+    demuxer->protocol_opaque = opaque;
+
+    // keep the original functions for URLs not handled by the protocol
+    // handler
+    demuxer->io_open = demuxer->fc->io_open;
+
+#if LIBAVFORMAT_VERSION_MAJOR >= 60
+    demuxer->io_close2 = demuxer->fc->io_close2;
+    demuxer->fc->io_close2 = ffw_demuxer_io_close2;
+#else
+    demuxer->io_close = demuxer->fc->io_close;
+    demuxer->fc->io_close = ffw_demuxer_io_close;
+#endif
+
+    demuxer->fc->opaque = demuxer;
+    demuxer->fc->io_open = ffw_demuxer_io_open;
+}
+
 int ffw_demuxer_find_stream_info(Demuxer* demuxer, int64_t max_analyze_duration) {
     AVRational micro;
     AVRational dst;
@@ -136,9 +266,33 @@ int ffw_demuxer_find_stream_info(Demuxer* demuxer, int64_t max_analyze_duration)
     dst.num = 1;
     dst.den = AV_TIME_BASE;
 
-    max_analyze_duration = av_rescale_q(max_analyze_duration, micro, dst);
+    // keep the value set using the demuxer options if there is no override
+    if (max_analyze_duration > 0) {
+        demuxer->fc->max_analyze_duration = av_rescale_q(max_analyze_duration, micro, dst);
+    }
+
+    return avformat_find_stream_info(demuxer->fc, NULL);
+}
+
+int ffw_demuxer_find_stream_info_fast(Demuxer* demuxer, int64_t max_analyze_duration, int64_t max_probe_size) {
+    int64_t duration = av_rescale_q(max_analyze_duration, av_make_q(1, 1000000), AV_TIME_BASE_Q);
+
+    // keep tighter limits set using the demuxer options
+    if (demuxer->fc->max_analyze_duration <= 0 || demuxer->fc->max_analyze_duration > duration) {
+        demuxer->fc->max_analyze_duration = duration;
+    }
+
+    if (demuxer->fc->probesize > max_probe_size) {
+        demuxer->fc->probesize = max_probe_size;
+    }
+
+    // do not wait for additional frames just to estimate the frame rate
+    demuxer->fc->fps_probe_size = 0;
 
-    demuxer->fc->max_analyze_duration = max_analyze_duration;
+#if LIBAVFORMAT_VERSION_INT >= AV_VERSION_INT(58, 19, 100)
+    // do not read the end of the input just to get the exact duration
+    demuxer->fc->skip_estimate_duration_from_pts = 1;
+#endif
 
     return avformat_find_stream_info(demuxer->fc, NULL);
 }
@@ -155,6 +309,39 @@ const AVInputFormat* ffw_demuxer_get_input_format(const Demuxer* demuxer) {
     return demuxer->fc->iformat;
 }
 
+int64_t ffw_demuxer_get_duration(const Demuxer* demuxer) {
+    if (demuxer->fc->duration == AV_NOPTS_VALUE) {
+        return -1;
+    }
+
+    return av_rescale_q(demuxer->fc->duration, AV_TIME_BASE_Q, av_make_q(1, 1000000));
+}
+
+int64_t ffw_demuxer_get_bit_rate(const Demuxer* demuxer) {
+    return demuxer->fc->bit_rate;
+}
+
+int ffw_demuxer_get_duration_estimation_method(const Demuxer* demuxer) {
+    switch (demuxer->fc->duration_estimation_method) {
+        case AVFMT_DURATION_FROM_PTS: return 0;
+        case AVFMT_DURATION_FROM_STREAM: return 1;
+        case AVFMT_DURATION_FROM_BITRATE: return 2;
+        default: return -1;
+    }
+}
+
+const AVDictionaryEntry* ffw_demuxer_get_metadata_entry(const Demuxer* demuxer, const AVDictionaryEntry* prev) {
+    return av_dict_get(demuxer->fc->metadata, "", prev, AV_DICT_IGNORE_SUFFIX);
+}
+
+unsigned ffw_demuxer_get_nb_chapters(const Demuxer* demuxer) {
+    return demuxer->fc->nb_chapters;
+}
+
+const AVChapter* ffw_demuxer_get_chapter(const Demuxer* demuxer, unsigned index) {
+    return demuxer->fc->chapters[index];
+}
+
 int ffw_demuxer_read_frame(Demuxer* demuxer, AVPacket** packet, uint32_t* tb_num, uint32_t* tb_den) {
     AVStream* stream;
     AVPacket* res;
@@ -234,4 +421,24 @@ void ffw_demuxer_free(Demuxer* demuxer) {
 
 const char* ffw_demuxer_get_format_names(Demuxer* demuxer) {
     return demuxer->fc->iformat->name;
-}
\ No newline at end of file
+}
+
+char* ffw_demuxer_get_io_option(const Demuxer* demuxer, const char* name) {
+    uint8_t* res = NULL;
+
+    // custom IO contexts do not have any protocol options (and they cannot
+    // be searched for them in some FFmpeg versions)
+    if (!demuxer->fc->pb || (demuxer->fc->flags & AVFMT_FLAG_CUSTOM_IO)) {
+        return NULL;
+    }
+
+    if (av_opt_get(demuxer->fc->pb, name, AV_OPT_SEARCH_CHILDREN, &res) < 0) {
+        return NULL;
+    }
+
+    return (char*)res;
+}
+
+void ffw_demuxer_free_io_option(char* value) {
+    av_free(value);
+}
@@ -4,7 +4,7 @@ use std::{
     borrow::{Borrow, BorrowMut},
     convert::TryInto,
     ffi::{CStr, CString},
-    io::Read,
+    io::{Cursor, Read},
     ops::{Deref, DerefMut},
     os::raw::{c_char, c_int, c_uint, c_void},
     ptr,
@@ -12,12 +12,27 @@ use std::{
 };
 
 use crate::{
-    format::{io::IO, stream::Stream},
+    codec::{
+        audio::{frame::get_sample_format, ChannelLayoutRef, SampleFormat},
+        AudioCodecParameters,
+    },
+    format::{
+        chapter::Chapter,
+        http::{HttpOptions, HttpResponse},
+        io::IO,
+        muxer::OutputFormat,
+        protocol::{self, Protocol, ProtocolRegistry},
+        stream::Stream,
+    },
     packet::Packet,
     time::{TimeBase, Timestamp},
     Error,
 };
 
+type ProtocolOpenCallback =
+    extern "C" fn(opaque: *mut c_void, url: *const c_char, io_context: *mut *mut c_void) -> c_int;
+type ProtocolCloseCallback = extern "C" fn(opaque: *mut c_void, io_context: *mut c_void) -> c_int;
+
 extern "C" {
     fn ffw_guess_input_format(
         short_name: *const c_char,
@@ -46,10 +61,28 @@ extern "C" {
         key: *const c_char,
         value: *const c_char,
     ) -> c_int;
+    fn ffw_demuxer_set_protocol_handler(
+        demuxer: *mut c_void,
+        open: ProtocolOpenCallback,
+        close: ProtocolCloseCallback,
+        opaque: *mut c_void,
+    );
     fn ffw_demuxer_find_stream_info(demuxer: *mut c_void, max_analyze_duration: i64) -> c_int;
+    fn ffw_demuxer_find_stream_info_fast(
+        demuxer: *mut c_void,
+        max_analyze_duration: i64,
+        max_probe_size: i64,
+    ) -> c_int;
     fn ffw_demuxer_get_nb_streams(demuxer: *const c_void) -> c_uint;
     fn ffw_demuxer_get_stream(demuxer: *mut c_void, index: c_uint) -> *mut c_void;
     fn ffw_demuxer_get_input_format(demuxer: *const c_void) -> *const c_void;
+    fn ffw_demuxer_get_duration(demuxer: *const c_void) -> i64;
+    fn ffw_demuxer_get_bit_rate(demuxer: *const c_void) -> i64;
+    fn ffw_demuxer_get_duration_estimation_method(demuxer: *const c_void) -> c_int;
+    fn ffw_demuxer_get_metadata_entry(demuxer: *const c_void, prev: *const c_void)
+        -> *const c_void;
+    fn ffw_demuxer_get_nb_chapters(demuxer: *const c_void) -> c_uint;
+    fn ffw_demuxer_get_chapter(demuxer: *const c_void, index: c_uint) -> *const c_void;
     fn ffw_demuxer_read_frame(
         demuxer: *mut c_void,
         packet: *mut *mut c_void,
@@ -64,9 +97,22 @@ extern "C" {
     ) -> c_int;
     fn ffw_demuxer_free(demuxer: *mut c_void);
     fn ffw_demuxer_get_format_names(demuxer: *mut c_void) -> *const c_char;
+    fn ffw_demuxer_get_io_option(demuxer: *const c_void, name: *const c_char) -> *mut c_char;
+    fn ffw_demuxer_free_io_option(value: *mut c_char);
+    fn ffw_probe_input_format(data: *const u8, size: c_int) -> *const c_void;
     fn ffw_input_format_name(input_format: *const c_void) -> *const c_char;
+
+    fn ffw_stream_get_metadata_entry_key(entry: *const c_void) -> *const c_char;
+    fn ffw_stream_get_metadata_entry_value(entry: *const c_void) -> *const c_char;
 }
 
+/// Maximum duration of the input analyzed by
+/// `Demuxer::find_stream_info_fast()`.
+const FAST_ANALYZE_DURATION: Duration = Duration::from_millis(500);
+
+/// Maximum number of bytes read by `Demuxer::find_stream_info_fast()`.
+const FAST_PROBE_SIZE: i64 = 256 * 1024;
+
 /// Seek type/mode.
 enum SeekType {
     Time,
@@ -110,10 +156,206 @@ impl SeekTarget {
     }
 }
 
+/// Source of the input duration.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DurationSource {
+    /// The duration was calculated from packet timestamps.
+    Timestamps,
+    /// The duration was taken from stream durations (e.g. from the container
+    /// headers).
+    Stream,
+    /// The duration was estimated from the bit rate and the size of the
+    /// input.
+    BitRate,
+}
+
+impl DurationSource {
+    /// Create a duration source from the value returned by
+    /// `ffw_demuxer_get_duration_estimation_method`.
+    fn from_raw(v: c_int) -> Option<Self> {
+        match v {
+            0 => Some(Self::Timestamps),
+            1 => Some(Self::Stream),
+            2 => Some(Self::BitRate),
+            _ => None,
+        }
+    }
+
+    /// Check if the duration is only a rough estimate.
+    pub fn is_estimate(self) -> bool {
+        self == Self::BitRate
+    }
+}
+
+/// PCM sample format of a raw audio input or output.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PcmFormat {
+    U8,
+    S8,
+    S16LE,
+    S16BE,
+    S24LE,
+    S24BE,
+    S32LE,
+    S32BE,
+    F32LE,
+    F32BE,
+    F64LE,
+    F64BE,
+    ALaw,
+    MuLaw,
+}
+
+impl PcmFormat {
+    /// Get name of the corresponding FFmpeg demuxer.
+    fn demuxer_name(self) -> &'static str {
+        match self {
+            Self::U8 => "u8",
+            Self::S8 => "s8",
+            Self::S16LE => "s16le",
+            Self::S16BE => "s16be",
+            Self::S24LE => "s24le",
+            Self::S24BE => "s24be",
+            Self::S32LE => "s32le",
+            Self::S32BE => "s32be",
+            Self::F32LE => "f32le",
+            Self::F32BE => "f32be",
+            Self::F64LE => "f64le",
+            Self::F64BE => "f64be",
+            Self::ALaw => "alaw",
+            Self::MuLaw => "mulaw",
+        }
+    }
+
+    /// Get name of the corresponding PCM codec (e.g. `pcm_s16le`).
+    pub fn codec_name(self) -> &'static str {
+        match self {
+            Self::U8 => "pcm_u8",
+            Self::S8 => "pcm_s8",
+            Self::S16LE => "pcm_s16le",
+            Self::S16BE => "pcm_s16be",
+            Self::S24LE => "pcm_s24le",
+            Self::S24BE => "pcm_s24be",
+            Self::S32LE => "pcm_s32le",
+            Self::S32BE => "pcm_s32be",
+            Self::F32LE => "pcm_f32le",
+            Self::F32BE => "pcm_f32be",
+            Self::F64LE => "pcm_f64le",
+            Self::F64BE => "pcm_f64be",
+            Self::ALaw => "pcm_alaw",
+            Self::MuLaw => "pcm_mulaw",
+        }
+    }
+
+    /// Get the sample format expected by the PCM encoder.
+    pub fn sample_format(self) -> SampleFormat {
+        let name = match self {
+            Self::U8 | Self::S8 => "u8",
+            Self::S16LE | Self::S16BE | Self::ALaw | Self::MuLaw => "s16",
+            Self::S24LE | Self::S24BE | Self::S32LE | Self::S32BE => "s32",
+            Self::F32LE | Self::F32BE => "flt",
+            Self::F64LE | Self::F64BE => "dbl",
+        };
+
+        get_sample_format(name)
+    }
+
+    /// Get the raw (headerless) output format for this PCM format.
+    pub fn output_format(self) -> Option<OutputFormat> {
+        OutputFormat::find_by_name(self.demuxer_name())
+    }
+
+    /// Get codec parameters of a raw PCM stream with a given sample rate
+    /// and channel layout. The parameters can be used for creating the PCM
+    /// encoder and for adding the stream into the raw PCM muxer.
+    ///
+    /// # Example
+    /// ```text
+    /// let format = PcmFormat::S16LE;
+    ///
+    /// let params = format.codec_parameters(16_000, &ChannelLayout::from_channels(1).unwrap())?;
+    ///
+    /// let encoder = AudioEncoder::from_codec_parameters(&params)?.build()?;
+    ///
+    /// let mut builder = Muxer::builder();
+    ///
+    /// builder.add_stream(&params.into())?;
+    ///
+    /// let muxer = builder.build(io, format.output_format().unwrap())?;
+    /// ```
+    pub fn codec_parameters(
+        self,
+        sample_rate: u32,
+        channel_layout: &ChannelLayoutRef,
+    ) -> Result<AudioCodecParameters, Error> {
+        let res = AudioCodecParameters::builder(self.codec_name())?
+            .sample_format(self.sample_format())
+            .sample_rate(sample_rate)
+            .channel_layout(channel_layout)
+            .build();
+
+        Ok(res)
+    }
+}
+
+/// Headerless (raw elementary stream) input with parameters that cannot be
+/// detected from the input itself.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RawInput {
+    /// H.264 Annex B byte stream with an optional frame rate (numerator,
+    /// denominator). FFmpeg assumes 25 fps if the frame rate is not set.
+    H264 { frame_rate: Option<(u32, u32)> },
+    /// H.265 Annex B byte stream with an optional frame rate (numerator,
+    /// denominator). FFmpeg assumes 25 fps if the frame rate is not set.
+    Hevc { frame_rate: Option<(u32, u32)> },
+    /// AAC in ADTS.
+    Aac,
+    /// Raw PCM audio.
+    Pcm {
+        format: PcmFormat,
+        sample_rate: u32,
+        channels: u32,
+    },
+}
+
+impl RawInput {
+    /// Get name of the corresponding FFmpeg demuxer.
+    fn demuxer_name(&self) -> &'static str {
+        match self {
+            Self::H264 { .. } => "h264",
+            Self::Hevc { .. } => "hevc",
+            Self::Aac => "aac",
+            Self::Pcm { format, .. } => format.demuxer_name(),
+        }
+    }
+
+    /// Apply the input parameters to a given demuxer builder.
+    fn apply(&self, builder: DemuxerBuilder) -> DemuxerBuilder {
+        match *self {
+            Self::H264 { frame_rate } | Self::Hevc { frame_rate } => {
+                if let Some((num, den)) = frame_rate {
+                    builder.set_option("framerate", format!("{}/{}", num, den))
+                } else {
+                    builder
+                }
+            }
+            Self::Aac => builder,
+            Self::Pcm {
+                sample_rate,
+                channels,
+                ..
+            } => builder
+                .set_option("sample_rate", sample_rate)
+                .set_option("channels", channels),
+        }
+    }
+}
+
 /// Demuxer builder.
 pub struct DemuxerBuilder {
     ptr: *mut c_void,
     input_format: Option<InputFormat>,
+    protocols: Box<ProtocolRegistry>,
 }
 
 impl DemuxerBuilder {
@@ -128,6 +370,7 @@ impl DemuxerBuilder {
         DemuxerBuilder {
             ptr,
             input_format: None,
+            protocols: Box::default(),
         }
     }
 
@@ -150,12 +393,63 @@ impl DemuxerBuilder {
         self
     }
 
+    /// Set the maximum number of bytes read from the input in order to
+    /// detect the input format and stream parameters. Lower values reduce
+    /// the startup latency of live inputs at the cost of less reliable
+    /// detection.
+    pub fn probe_size(self, bytes: u64) -> DemuxerBuilder {
+        self.set_option("probesize", bytes)
+    }
+
+    /// Set the maximum duration of the input analyzed by
+    /// `Demuxer::find_stream_info()`. The value can be still overridden by
+    /// passing `max_analyze_duration` to `find_stream_info()`.
+    pub fn analyze_duration(self, duration: Duration) -> DemuxerBuilder {
+        self.set_option("analyzeduration", duration.as_micros())
+    }
+
+    /// Set the number of frames analyzed in order to detect the frame rate.
+    pub fn fps_probe_size(self, frames: u32) -> DemuxerBuilder {
+        self.set_option("fpsprobesize", frames)
+    }
+
     /// Set input format. If the input format is not set, it will be guessed from the input.
     pub fn input_format(mut self, format: Option<InputFormat>) -> DemuxerBuilder {
         self.input_format = format;
         self
     }
 
+    /// Set options of HTTP(S) inputs.
+    pub fn http_options(self, options: &HttpOptions) -> Result<DemuxerBuilder, Error> {
+        let res = options
+            .to_options()?
+            .into_iter()
+            .fold(self, |builder, (name, value)| {
+                builder.set_option(name, value)
+            });
+
+        Ok(res)
+    }
+
+    /// Register a custom protocol. URLs with the protocol scheme opened by
+    /// the demuxer (including the URL passed to `build_from_url()`) will be
+    /// opened using the protocol instead of FFmpeg. A protocol registered
+    /// later replaces an earlier one with the same name.
+    pub fn protocol(mut self, protocol: Protocol) -> DemuxerBuilder {
+        self.protocols.register(protocol);
+        self
+    }
+
+    /// Set a headerless input format together with its parameters.
+    pub fn raw_input(self, input: RawInput) -> Result<DemuxerBuilder, Error> {
+        let format = InputFormat::find_by_name(input.demuxer_name())
+            .ok_or_else(|| Error::new("unknown input format"))?;
+
+        let builder = input.apply(self.input_format(Some(format)));
+
+        Ok(builder)
+    }
+
     /// Build the demuxer.
     ///
     /// # Arguments
@@ -172,6 +466,8 @@ impl DemuxerBuilder {
             .map(|f| f.ptr)
             .unwrap_or(ptr::null_mut());
 
+        self.set_protocol_handler();
+
         let ret = unsafe { ffw_demuxer_init(self.ptr, io_context_ptr, format_ptr) };
 
         if ret < 0 {
@@ -182,11 +478,24 @@ impl DemuxerBuilder {
 
         self.ptr = ptr::null_mut();
 
-        let res = Demuxer { ptr, io };
+        let res = Demuxer {
+            ptr,
+            io,
+            _protocols: std::mem::take(&mut self.protocols),
+        };
 
         Ok(res)
     }
 
+    /// Build a demuxer reading from a given in-memory input. The input is
+    /// not copied as a whole, the demuxer reads it directly from the slice
+    /// (through the IO buffer) and it can seek within it. The input format
+    /// is detected by the demuxer (reading only the probe size from the
+    /// beginning of the input) if not set.
+    pub fn build_from_bytes(self, data: &[u8]) -> Result<Demuxer<Cursor<&[u8]>>, Error> {
+        self.build(IO::from_seekable_read_stream(Cursor::new(data)))
+    }
+
     /// Build demuxer from  url
     pub fn build_from_url(mut self, url: &str) -> Result<Demuxer<()>, Error> {
         let url = CString::new(url).expect("invalid url string");
@@ -197,6 +506,8 @@ impl DemuxerBuilder {
             .map(|f| f.ptr)
             .unwrap_or(ptr::null_mut());
 
+        self.set_protocol_handler();
+
         let ret = unsafe { ffw_demuxer_init_from_url(self.ptr, url.as_ptr(), format_ptr) };
 
         if ret < 0 {
@@ -210,10 +521,30 @@ impl DemuxerBuilder {
         let res = Demuxer {
             ptr,
             io: IO::new_empty(),
+            _protocols: std::mem::take(&mut self.protocols),
         };
 
         Ok(res)
     }
+
+    /// Pass URLs opened by the demuxer to the registered protocols (if
+    /// any).
+    fn set_protocol_handler(&mut self) {
+        if self.protocols.is_empty() {
+            return;
+        }
+
+        let opaque = self.protocols.as_mut() as *mut ProtocolRegistry;
+
+        unsafe {
+            ffw_demuxer_set_protocol_handler(
+                self.ptr,
+                protocol::protocol_open,
+                protocol::protocol_close,
+                opaque as _,
+            )
+        }
+    }
 }
 
 impl Drop for DemuxerBuilder {
@@ -229,6 +560,8 @@ unsafe impl Sync for DemuxerBuilder {}
 pub struct Demuxer<T> {
     ptr: *mut c_void,
     io: IO<T>,
+    // NOTE: the protocol registry must be dropped after the demuxer context
+    _protocols: Box<ProtocolRegistry>,
 }
 
 impl Demuxer<()> {
@@ -236,6 +569,38 @@ impl Demuxer<()> {
     pub fn builder() -> DemuxerBuilder {
         DemuxerBuilder::new()
     }
+
+    /// Get a demuxer builder for a given headerless input.
+    ///
+    /// # Example
+    /// ```text
+    /// ...
+    ///
+    /// let demuxer = Demuxer::raw_builder(RawInput::H264 {
+    ///     frame_rate: Some((30, 1)),
+    /// })?
+    /// .build(io)?;
+    ///
+    /// ...
+    /// ```
+    pub fn raw_builder(input: RawInput) -> Result<DemuxerBuilder, Error> {
+        DemuxerBuilder::new().raw_input(input)
+    }
+}
+
+impl<'a> Demuxer<Cursor<&'a [u8]>> {
+    /// Create a demuxer reading from a given in-memory input using the
+    /// default options.
+    ///
+    /// # Example
+    /// ```text
+    /// let demuxer = Demuxer::from_bytes(&upload)?
+    ///     .find_stream_info(None)
+    ///     .map_err(|(_, err)| err)?;
+    /// ```
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self, Error> {
+        DemuxerBuilder::new().build_from_bytes(data)
+    }
 }
 
 impl<T> Demuxer<T> {
@@ -273,6 +638,13 @@ impl<T> Demuxer<T> {
         } else {
             let packet = unsafe { Packet::from_raw_ptr(pptr, TimeBase::new(tb_num, tb_den)) };
 
+            trace_event!(
+                stream_index = packet.stream_index(),
+                pts = packet.pts().as_micros(),
+                dts = packet.dts().as_micros(),
+                "packet demuxed"
+            );
+
             Ok(Some(packet))
         }
     }
@@ -325,7 +697,8 @@ impl<T> Demuxer<T> {
     }
 
     /// Try to find stream info. Optionally, you can pass `max_analyze_duration` which tells FFmpeg
-    /// how far it should look for stream info.
+    /// how far it should look for stream info. If it is not set, the value set using
+    /// `DemuxerBuilder::analyze_duration()` (or the FFmpeg default) will be used.
     pub fn find_stream_info(
         self,
         max_analyze_duration: Option<Duration>,
@@ -342,6 +715,35 @@ impl<T> Demuxer<T> {
             return Err((self, Error::from_raw_error_code(ret)));
         }
 
+        Ok(self.into_demuxer_with_stream_info())
+    }
+
+    /// Find stream info quickly. Only the container headers and a small part
+    /// of the input (at most 256 kB or 0.5 seconds) are analyzed, the frame
+    /// rate is not estimated from additional frames and the end of the input
+    /// is not read in order to get the exact duration. Tighter limits set
+    /// using `DemuxerBuilder::probe_size()` and
+    /// `DemuxerBuilder::analyze_duration()` are kept.
+    ///
+    /// The method is meant for indexers that need to open a large number of
+    /// files. Some stream parameters may be missing and the duration may be
+    /// only an estimate (see `Demuxer::duration_source()`).
+    pub fn find_stream_info_fast(self) -> Result<DemuxerWithStreamInfo<T>, (Self, Error)> {
+        let max_analyze_duration = FAST_ANALYZE_DURATION.as_micros() as i64;
+
+        let ret = unsafe {
+            ffw_demuxer_find_stream_info_fast(self.ptr, max_analyze_duration, FAST_PROBE_SIZE)
+        };
+
+        if ret < 0 {
+            return Err((self, Error::from_raw_error_code(ret)));
+        }
+
+        Ok(self.into_demuxer_with_stream_info())
+    }
+
+    /// Collect the streams found by `avformat_find_stream_info()`.
+    fn into_demuxer_with_stream_info(self) -> DemuxerWithStreamInfo<T> {
         let stream_count = unsafe { ffw_demuxer_get_nb_streams(self.ptr) };
 
         let mut streams = Vec::with_capacity(stream_count as usize);
@@ -360,12 +762,110 @@ impl<T> Demuxer<T> {
             streams.push(stream);
         }
 
-        let res = DemuxerWithStreamInfo {
+        DemuxerWithStreamInfo {
             inner: self,
             streams,
-        };
+        }
+    }
 
-        Ok(res)
+    /// Get duration of the input (if known).
+    pub fn duration(&self) -> Option<Duration> {
+        let micros = unsafe { ffw_demuxer_get_duration(self.ptr) };
+
+        if micros < 0 {
+            None
+        } else {
+            Some(Duration::from_micros(micros as u64))
+        }
+    }
+
+    /// Get the source of the input duration (if known).
+    pub fn duration_source(&self) -> Option<DurationSource> {
+        self.duration()?;
+
+        let method = unsafe { ffw_demuxer_get_duration_estimation_method(self.ptr) };
+
+        DurationSource::from_raw(method)
+    }
+
+    /// Get total bit rate of the input in bits per second (if known).
+    pub fn bit_rate(&self) -> Option<u64> {
+        let bit_rate = unsafe { ffw_demuxer_get_bit_rate(self.ptr) };
+
+        if bit_rate > 0 {
+            Some(bit_rate as u64)
+        } else {
+            None
+        }
+    }
+
+    /// Get container metadata.
+    pub fn metadata(&self) -> Vec<(&str, &str)> {
+        let mut res = Vec::new();
+
+        let mut entry = ptr::null();
+
+        loop {
+            unsafe {
+                entry = ffw_demuxer_get_metadata_entry(self.ptr, entry);
+
+                if entry.is_null() {
+                    break;
+                }
+
+                let key = CStr::from_ptr(ffw_stream_get_metadata_entry_key(entry));
+                let value = CStr::from_ptr(ffw_stream_get_metadata_entry_value(entry));
+
+                if let (Ok(key), Ok(value)) = (key.to_str(), value.to_str()) {
+                    res.push((key, value));
+                }
+            }
+        }
+
+        res
+    }
+
+    /// Get chapters of the input.
+    pub fn chapters(&self) -> Vec<Chapter> {
+        let count = unsafe { ffw_demuxer_get_nb_chapters(self.ptr) };
+
+        (0..count)
+            .map(|index| unsafe { Chapter::from_raw_ptr(ffw_demuxer_get_chapter(self.ptr, index)) })
+            .collect()
+    }
+
+    /// Get information about the HTTP response if the input was opened from
+    /// an HTTP(S) URL.
+    pub fn http_response(&self) -> Option<HttpResponse> {
+        let location = self.get_io_option("location")?;
+
+        let res = HttpResponse::new(
+            self.get_io_option("mime_type"),
+            Some(location),
+            self.get_io_option("cookies"),
+            self.get_io_option("icy_metadata_headers"),
+        );
+
+        Some(res)
+    }
+
+    /// Get a given option of the input protocol.
+    fn get_io_option(&self, name: &str) -> Option<String> {
+        let name = CString::new(name).unwrap();
+
+        unsafe {
+            let ptr = ffw_demuxer_get_io_option(self.ptr, name.as_ptr());
+
+            if ptr.is_null() {
+                return None;
+            }
+
+            let res = CStr::from_ptr(ptr).to_string_lossy().into_owned();
+
+            ffw_demuxer_free_io_option(ptr);
+
+            Some(res)
+        }
     }
 
     /// Gets names (comma-separated) for the format we're demuxing
@@ -425,6 +925,12 @@ impl<T> DemuxerWithStreamInfo<T> {
         &self.streams
     }
 
+    /// Get mutable references to the streams (e.g. in order to set the
+    /// stream discard mode).
+    pub fn streams_mut(&mut self) -> &mut [Stream] {
+        &mut self.streams
+    }
+
     /// Get the underlying demuxer.
     pub fn into_demuxer(self) -> Demuxer<T> {
         self.inner
@@ -520,6 +1026,24 @@ impl InputFormat {
         Some(res)
     }
 
+    /// Try to detect an input format from a given chunk of input data. The
+    /// chunk should contain the beginning of the input. Detection is more
+    /// reliable for larger chunks (a few kilobytes should be enough for most
+    /// formats).
+    pub fn probe(data: &[u8]) -> Option<InputFormat> {
+        let size = data.len().min(i32::MAX as usize - 64);
+
+        let ptr = unsafe { ffw_probe_input_format(data.as_ptr(), size as _) };
+
+        if ptr.is_null() {
+            return None;
+        }
+
+        let res = InputFormat { ptr: ptr as _ };
+
+        Some(res)
+    }
+
     pub fn name(&self) -> &str {
         unsafe {
             CStr::from_ptr(ffw_input_format_name(self.ptr))
@@ -531,3 +1055,166 @@ impl InputFormat {
 
 unsafe impl Send for InputFormat {}
 unsafe impl Sync for InputFormat {}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::{Demuxer, DemuxerWithStreamInfo, DurationSource, PcmFormat, RawInput, SeekTarget};
+
+    use crate::{
+        format::io::IO,
+        test_util::{encode_h264, encode_video},
+        time::Timestamp,
+    };
+
+    /// Get the number of packets remaining in a given demuxer.
+    fn count_packets<T>(demuxer: &mut DemuxerWithStreamInfo<T>) -> usize {
+        let mut res = 0;
+
+        while demuxer.take().unwrap().is_some() {
+            res += 1;
+        }
+
+        res
+    }
+
+    #[test]
+    fn test_find_stream_info_fast() {
+        let data = encode_video(64, 48, 50, 10);
+
+        let mut demuxer = Demuxer::builder()
+            .build(IO::from_seekable_read_stream(Cursor::new(data)))
+            .unwrap()
+            .find_stream_info_fast()
+            .map_err(|(_, err)| err)
+            .unwrap();
+
+        assert_eq!(demuxer.streams().len(), 1);
+        assert_eq!(demuxer.duration_source(), Some(DurationSource::Stream));
+
+        // the analyzed packets are not lost
+        assert_eq!(count_packets(&mut demuxer), 50);
+    }
+
+    #[test]
+    fn test_from_bytes() {
+        let data = encode_video(64, 48, 50, 10);
+
+        let mut demuxer = Demuxer::from_bytes(&data)
+            .unwrap()
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)
+            .unwrap();
+
+        // the format is detected by the demuxer
+        assert_eq!(demuxer.input_format().name(), "matroska,webm");
+
+        assert_eq!(count_packets(&mut demuxer), 50);
+
+        // seek back to the keyframe at 1.2 s
+        demuxer
+            .seek_to_timestamp(Timestamp::from_millis(1300), SeekTarget::UpTo)
+            .unwrap();
+
+        let packet = demuxer.take().unwrap().unwrap();
+
+        assert!(packet.is_key());
+        assert_eq!(packet.pts().as_millis(), Some(1200));
+
+        // the remaining packets
+        assert_eq!(count_packets(&mut demuxer), 19);
+
+        // the same using a builder
+        let mut demuxer = Demuxer::builder()
+            .build_from_bytes(&data)
+            .unwrap()
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)
+            .unwrap();
+
+        assert_eq!(count_packets(&mut demuxer), 50);
+    }
+
+    #[test]
+    fn test_raw_pcm() {
+        // 0.5 s of 16-bit stereo PCM at 16 kHz
+        let data = vec![0; 32_000];
+
+        let input = RawInput::Pcm {
+            format: PcmFormat::S16LE,
+            sample_rate: 16_000,
+            channels: 2,
+        };
+
+        let mut demuxer = Demuxer::raw_builder(input)
+            .unwrap()
+            .build(IO::from_read_stream(Cursor::new(data)))
+            .unwrap()
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)
+            .unwrap();
+
+        assert_eq!(demuxer.streams().len(), 1);
+
+        let params = demuxer.streams()[0].codec_parameters();
+
+        assert_eq!(params.codec_name(), "pcm_s16le");
+
+        let params = params.into_audio_codec_parameters().unwrap();
+
+        assert_eq!(params.sample_rate(), 16_000);
+        assert_eq!(params.channel_layout().channels(), 2);
+        assert_eq!(params.sample_format(), PcmFormat::S16LE.sample_format());
+
+        let mut size = 0;
+
+        while let Some(packet) = demuxer.take().unwrap() {
+            size += packet.data().len();
+        }
+
+        assert_eq!(size, 32_000);
+    }
+
+    #[test]
+    fn test_raw_h264() {
+        let packets = match encode_h264(25) {
+            Some(packets) => packets,
+            None => return,
+        };
+
+        let data = packets
+            .iter()
+            .flat_map(|packet| packet.data().iter().copied())
+            .collect::<Vec<_>>();
+
+        let input = RawInput::H264 {
+            frame_rate: Some((50, 1)),
+        };
+
+        let mut demuxer = Demuxer::raw_builder(input)
+            .unwrap()
+            .build(IO::from_read_stream(Cursor::new(data)))
+            .unwrap()
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)
+            .unwrap();
+
+        let stream = &demuxer.streams()[0];
+
+        let params = stream.codec_parameters();
+
+        assert_eq!(params.codec_name(), "h264");
+
+        let params = params.into_video_codec_parameters().unwrap();
+
+        assert_eq!(params.width(), 64);
+        assert_eq!(params.height(), 48);
+
+        let frame_rate = stream.avg_frame_rate().unwrap();
+
+        assert_eq!((frame_rate.num(), frame_rate.den()), (50, 1));
+
+        assert_eq!(count_packets(&mut demuxer), 25);
+    }
+}
@@ -0,0 +1,163 @@
+#include <string.h>
+
+#include <libavutil/encryption_info.h>
+#include <libavutil/mem.h>
+
+AVEncryptionInfo* ffw_encryption_info_from_side_data(const uint8_t* data, size_t size);
+AVEncryptionInfo* ffw_encryption_info_new(uint32_t scheme, uint32_t crypt_byte_block, uint32_t skip_byte_block, const uint8_t* key_id, uint32_t key_id_size, const uint8_t* iv, uint32_t iv_size, uint32_t subsample_count);
+void ffw_encryption_info_get_pattern(const AVEncryptionInfo* info, uint32_t* scheme, uint32_t* crypt_byte_block, uint32_t* skip_byte_block);
+const uint8_t* ffw_encryption_info_get_key_id(const AVEncryptionInfo* info, uint32_t* size);
+const uint8_t* ffw_encryption_info_get_iv(const AVEncryptionInfo* info, uint32_t* size);
+uint32_t ffw_encryption_info_get_subsample_count(const AVEncryptionInfo* info);
+void ffw_encryption_info_get_subsample(const AVEncryptionInfo* info, uint32_t index, uint32_t* clear_bytes, uint32_t* protected_bytes);
+void ffw_encryption_info_set_subsample(AVEncryptionInfo* info, uint32_t index, uint32_t clear_bytes, uint32_t protected_bytes);
+uint8_t* ffw_encryption_info_to_side_data(const AVEncryptionInfo* info, size_t* size);
+void ffw_encryption_info_free(AVEncryptionInfo* info);
+
+AVEncryptionInitInfo* ffw_encryption_init_info_from_side_data(const uint8_t* data, size_t size);
+AVEncryptionInitInfo* ffw_encryption_init_info_new(const uint8_t* system_id, uint32_t system_id_size, uint32_t key_id_count, uint32_t key_id_size, const uint8_t* data, uint32_t data_size);
+const AVEncryptionInitInfo* ffw_encryption_init_info_get_next(const AVEncryptionInitInfo* info);
+void ffw_encryption_init_info_set_next(AVEncryptionInitInfo* info, AVEncryptionInitInfo* next);
+const uint8_t* ffw_encryption_init_info_get_system_id(const AVEncryptionInitInfo* info, uint32_t* size);
+uint32_t ffw_encryption_init_info_get_key_id_count(const AVEncryptionInitInfo* info);
+const uint8_t* ffw_encryption_init_info_get_key_id(const AVEncryptionInitInfo* info, uint32_t index, uint32_t* size);
+void ffw_encryption_init_info_set_key_id(AVEncryptionInitInfo* info, uint32_t index, const uint8_t* key_id);
+const uint8_t* ffw_encryption_init_info_get_data(const AVEncryptionInitInfo* info, uint32_t* size);
+uint8_t* ffw_encryption_init_info_to_side_data(const AVEncryptionInitInfo* info, size_t* size);
+void ffw_encryption_init_info_free(AVEncryptionInitInfo* info);
+
+void ffw_encryption_side_data_free(uint8_t* data);
+
+AVEncryptionInfo* ffw_encryption_info_from_side_data(const uint8_t* data, size_t size) {
+    return av_encryption_info_get_side_data(data, size);
+}
+
+AVEncryptionInfo* ffw_encryption_info_new(uint32_t scheme, uint32_t crypt_byte_block, uint32_t skip_byte_block, const uint8_t* key_id, uint32_t key_id_size, const uint8_t* iv, uint32_t iv_size, uint32_t subsample_count) {
+    AVEncryptionInfo* info = av_encryption_info_alloc(subsample_count, key_id_size, iv_size);
+    if (!info) {
+        return NULL;
+    }
+
+    info->scheme = scheme;
+    info->crypt_byte_block = crypt_byte_block;
+    info->skip_byte_block = skip_byte_block;
+
+    if (key_id_size > 0) {
+        memcpy(info->key_id, key_id, key_id_size);
+    }
+
+    if (iv_size > 0) {
+        memcpy(info->iv, iv, iv_size);
+    }
+
+    return info;
+}
+
+void ffw_encryption_info_get_pattern(const AVEncryptionInfo* info, uint32_t* scheme, uint32_t* crypt_byte_block, uint32_t* skip_byte_block) {
+    *scheme = info->scheme;
+    *crypt_byte_block = info->crypt_byte_block;
+    *skip_byte_block = info->skip_byte_block;
+}
+
+const uint8_t* ffw_encryption_info_get_key_id(const AVEncryptionInfo* info, uint32_t* size) {
+    *size = info->key_id_size;
+
+    return info->key_id;
+}
+
+const uint8_t* ffw_encryption_info_get_iv(const AVEncryptionInfo* info, uint32_t* size) {
+    *size = info->iv_size;
+
+    return info->iv;
+}
+
+uint32_t ffw_encryption_info_get_subsample_count(const AVEncryptionInfo* info) {
+    return info->subsample_count;
+}
+
+void ffw_encryption_info_get_subsample(const AVEncryptionInfo* info, uint32_t index, uint32_t* clear_bytes, uint32_t* protected_bytes) {
+    *clear_bytes = info->subsamples[index].bytes_of_clear_data;
+    *protected_bytes = info->subsamples[index].bytes_of_protected_data;
+}
+
+void ffw_encryption_info_set_subsample(AVEncryptionInfo* info, uint32_t index, uint32_t clear_bytes, uint32_t protected_bytes) {
+    info->subsamples[index].bytes_of_clear_data = clear_bytes;
+    info->subsamples[index].bytes_of_protected_data = protected_bytes;
+}
+
+uint8_t* ffw_encryption_info_to_side_data(const AVEncryptionInfo* info, size_t* size) {
+    return av_encryption_info_add_side_data(info, size);
+}
+
+void ffw_encryption_info_free(AVEncryptionInfo* info) {
+    av_encryption_info_free(info);
+}
+
+AVEncryptionInitInfo* ffw_encryption_init_info_from_side_data(const uint8_t* data, size_t size) {
+    return av_encryption_init_info_get_side_data(data, size);
+}
+
+AVEncryptionInitInfo* ffw_encryption_init_info_new(const uint8_t* system_id, uint32_t system_id_size, uint32_t key_id_count, uint32_t key_id_size, const uint8_t* data, uint32_t data_size) {
+    AVEncryptionInitInfo* info = av_encryption_init_info_alloc(system_id_size, key_id_count, key_id_size, data_size);
+    if (!info) {
+        return NULL;
+    }
+
+    if (system_id_size > 0) {
+        memcpy(info->system_id, system_id, system_id_size);
+    }
+
+    if (data_size > 0) {
+        memcpy(info->data, data, data_size);
+    }
+
+    return info;
+}
+
+const AVEncryptionInitInfo* ffw_encryption_init_info_get_next(const AVEncryptionInitInfo* info) {
+    return info->next;
+}
+
+void ffw_encryption_init_info_set_next(AVEncryptionInitInfo* info, AVEncryptionInitInfo* next) {
+    info->next = next;
+}
+
+const uint8_t* ffw_encryption_init_info_get_system_id(const AVEncryptionInitInfo* info, uint32_t* size) {
+    *size = info->system_id_size;
+
+    return info->system_id;
+}
+
+uint32_t ffw_encryption_init_info_get_key_id_count(const AVEncryptionInitInfo* info) {
+    return info->num_key_ids;
+}
+
+const uint8_t* ffw_encryption_init_info_get_key_id(const AVEncryptionInitInfo* info, uint32_t index, uint32_t* size) {
+    *size = info->key_id_size;
+
+    return info->key_ids[index];
+}
+
+void ffw_encryption_init_info_set_key_id(AVEncryptionInitInfo* info, uint32_t index, const uint8_t* key_id) {
+    if (info->key_id_size > 0) {
+        memcpy(info->key_ids[index], key_id, info->key_id_size);
+    }
+}
+
+const uint8_t* ffw_encryption_init_info_get_data(const AVEncryptionInitInfo* info, uint32_t* size) {
+    *size = info->data_size;
+
+    return info->data;
+}
+
+uint8_t* ffw_encryption_init_info_to_side_data(const AVEncryptionInitInfo* info, size_t* size) {
+    return av_encryption_init_info_add_side_data(info, size);
+}
+
+void ffw_encryption_init_info_free(AVEncryptionInitInfo* info) {
+    av_encryption_init_info_free(info);
+}
+
+void ffw_encryption_side_data_free(uint8_t* data) {
+    av_free(data);
+}
@@ -0,0 +1,566 @@
+//! Common encryption (CENC) metadata.
+//!
+//! Demuxers of encrypted inputs (e.g. the MP4 demuxer) attach the
+//! encryption parameters of every sample as `SideDataType::EncryptionInfo`
+//! packet side data and the key system initialization data (`pssh` boxes)
+//! as `SideDataType::EncryptionInitInfo` side data. The types in this module
+//! can be used for parsing and creating the side data.
+//!
+//! Encrypted fragmented MP4 can be written using
+//! `MuxerBuilder::cenc_encryption()`.
+//!
+//! # Example
+//! ```text
+//! while let Some(packet) = demuxer.take()? {
+//!     if let Some(side_data) = packet.get_side_data(SideDataType::EncryptionInfo) {
+//!         let info = EncryptionInfo::from_side_data(side_data.data())?;
+//!
+//!         println!("key ID: {:02x?}", info.key_id());
+//!     }
+//! }
+//! ```
+
+use std::{convert::TryFrom, os::raw::c_void, slice};
+
+use crate::Error;
+
+extern "C" {
+    fn ffw_encryption_info_from_side_data(data: *const u8, size: usize) -> *mut c_void;
+    fn ffw_encryption_info_new(
+        scheme: u32,
+        crypt_byte_block: u32,
+        skip_byte_block: u32,
+        key_id: *const u8,
+        key_id_size: u32,
+        iv: *const u8,
+        iv_size: u32,
+        subsample_count: u32,
+    ) -> *mut c_void;
+    fn ffw_encryption_info_get_pattern(
+        info: *const c_void,
+        scheme: *mut u32,
+        crypt_byte_block: *mut u32,
+        skip_byte_block: *mut u32,
+    );
+    fn ffw_encryption_info_get_key_id(info: *const c_void, size: *mut u32) -> *const u8;
+    fn ffw_encryption_info_get_iv(info: *const c_void, size: *mut u32) -> *const u8;
+    fn ffw_encryption_info_get_subsample_count(info: *const c_void) -> u32;
+    fn ffw_encryption_info_get_subsample(
+        info: *const c_void,
+        index: u32,
+        clear_bytes: *mut u32,
+        protected_bytes: *mut u32,
+    );
+    fn ffw_encryption_info_set_subsample(
+        info: *mut c_void,
+        index: u32,
+        clear_bytes: u32,
+        protected_bytes: u32,
+    );
+    fn ffw_encryption_info_to_side_data(info: *const c_void, size: *mut usize) -> *mut u8;
+    fn ffw_encryption_info_free(info: *mut c_void);
+
+    fn ffw_encryption_init_info_from_side_data(data: *const u8, size: usize) -> *mut c_void;
+    fn ffw_encryption_init_info_new(
+        system_id: *const u8,
+        system_id_size: u32,
+        key_id_count: u32,
+        key_id_size: u32,
+        data: *const u8,
+        data_size: u32,
+    ) -> *mut c_void;
+    fn ffw_encryption_init_info_get_next(info: *const c_void) -> *const c_void;
+    fn ffw_encryption_init_info_set_next(info: *mut c_void, next: *mut c_void);
+    fn ffw_encryption_init_info_get_system_id(info: *const c_void, size: *mut u32) -> *const u8;
+    fn ffw_encryption_init_info_get_key_id_count(info: *const c_void) -> u32;
+    fn ffw_encryption_init_info_get_key_id(
+        info: *const c_void,
+        index: u32,
+        size: *mut u32,
+    ) -> *const u8;
+    fn ffw_encryption_init_info_set_key_id(info: *mut c_void, index: u32, key_id: *const u8);
+    fn ffw_encryption_init_info_get_data(info: *const c_void, size: *mut u32) -> *const u8;
+    fn ffw_encryption_init_info_to_side_data(info: *const c_void, size: *mut usize) -> *mut u8;
+    fn ffw_encryption_init_info_free(info: *mut c_void);
+
+    fn ffw_encryption_side_data_free(data: *mut u8);
+}
+
+/// Encryption scheme (ISO/IEC 23001-7).
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum EncryptionScheme {
+    /// AES-CTR full sample/subsample encryption (`cenc`).
+    Cenc,
+    /// AES-CTR pattern encryption (`cens`).
+    Cens,
+    /// AES-CBC full sample/subsample encryption (`cbc1`).
+    Cbc1,
+    /// AES-CBC pattern encryption (`cbcs`).
+    Cbcs,
+    /// Other scheme given by its four character code.
+    Other(u32),
+}
+
+impl EncryptionScheme {
+    /// Create an encryption scheme from its four character code.
+    pub fn from_fourcc(fourcc: u32) -> Self {
+        match &fourcc.to_be_bytes() {
+            b"cenc" => Self::Cenc,
+            b"cens" => Self::Cens,
+            b"cbc1" => Self::Cbc1,
+            b"cbcs" => Self::Cbcs,
+            _ => Self::Other(fourcc),
+        }
+    }
+
+    /// Get the four character code of the scheme.
+    pub fn fourcc(self) -> u32 {
+        let fourcc = match self {
+            Self::Cenc => b"cenc",
+            Self::Cens => b"cens",
+            Self::Cbc1 => b"cbc1",
+            Self::Cbcs => b"cbcs",
+            Self::Other(fourcc) => return fourcc,
+        };
+
+        u32::from_be_bytes(*fourcc)
+    }
+}
+
+/// Subsample, i.e. a range of clear data followed by a range of protected
+/// data.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Subsample {
+    /// Number of unencrypted bytes.
+    pub clear_bytes: u32,
+    /// Number of encrypted bytes.
+    pub protected_bytes: u32,
+}
+
+/// Encryption parameters of a single sample.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct EncryptionInfo {
+    scheme: EncryptionScheme,
+    crypt_byte_block: u32,
+    skip_byte_block: u32,
+    key_id: Vec<u8>,
+    iv: Vec<u8>,
+    subsamples: Vec<Subsample>,
+}
+
+impl EncryptionInfo {
+    /// Create new encryption info. The whole sample is encrypted unless
+    /// subsamples are added.
+    pub fn new(scheme: EncryptionScheme, key_id: &[u8], iv: &[u8]) -> Self {
+        Self {
+            scheme,
+            crypt_byte_block: 0,
+            skip_byte_block: 0,
+            key_id: key_id.to_vec(),
+            iv: iv.to_vec(),
+            subsamples: Vec::new(),
+        }
+    }
+
+    /// Set the encryption pattern, i.e. the number of encrypted and skipped
+    /// 16-byte blocks (used by the pattern encryption schemes).
+    pub fn with_pattern(mut self, crypt_byte_block: u32, skip_byte_block: u32) -> Self {
+        self.crypt_byte_block = crypt_byte_block;
+        self.skip_byte_block = skip_byte_block;
+        self
+    }
+
+    /// Add a given subsample.
+    pub fn with_subsample(mut self, subsample: Subsample) -> Self {
+        self.subsamples.push(subsample);
+        self
+    }
+
+    /// Parse given `SideDataType::EncryptionInfo` packet side data.
+    pub fn from_side_data(data: &[u8]) -> Result<Self, Error> {
+        let ptr = unsafe { ffw_encryption_info_from_side_data(data.as_ptr(), data.len()) };
+
+        if ptr.is_null() {
+            return Err(Error::new("invalid encryption side data"));
+        }
+
+        let info = RawEncryptionInfo { ptr };
+
+        Ok(info.to_encryption_info())
+    }
+
+    /// Serialize the encryption info as `SideDataType::EncryptionInfo`
+    /// packet side data.
+    pub fn to_side_data(&self) -> Vec<u8> {
+        let subsample_count =
+            u32::try_from(self.subsamples.len()).expect("too many encryption subsamples");
+
+        let ptr = unsafe {
+            ffw_encryption_info_new(
+                self.scheme.fourcc(),
+                self.crypt_byte_block,
+                self.skip_byte_block,
+                self.key_id.as_ptr(),
+                u32::try_from(self.key_id.len()).expect("key ID too long"),
+                self.iv.as_ptr(),
+                u32::try_from(self.iv.len()).expect("initialization vector too long"),
+                subsample_count,
+            )
+        };
+
+        if ptr.is_null() {
+            panic!("unable to allocate encryption info");
+        }
+
+        let info = RawEncryptionInfo { ptr };
+
+        for (index, subsample) in self.subsamples.iter().enumerate() {
+            unsafe {
+                ffw_encryption_info_set_subsample(
+                    info.ptr,
+                    index as u32,
+                    subsample.clear_bytes,
+                    subsample.protected_bytes,
+                );
+            }
+        }
+
+        let mut size = 0;
+
+        let data = unsafe { ffw_encryption_info_to_side_data(info.ptr, &mut size) };
+
+        unsafe { take_side_data(data, size) }
+    }
+
+    /// Get the encryption scheme.
+    pub fn scheme(&self) -> EncryptionScheme {
+        self.scheme
+    }
+
+    /// Get the number of encrypted 16-byte blocks in the pattern.
+    pub fn crypt_byte_block(&self) -> u32 {
+        self.crypt_byte_block
+    }
+
+    /// Get the number of unencrypted 16-byte blocks in the pattern.
+    pub fn skip_byte_block(&self) -> u32 {
+        self.skip_byte_block
+    }
+
+    /// Get the key ID.
+    pub fn key_id(&self) -> &[u8] {
+        &self.key_id
+    }
+
+    /// Get the initialization vector.
+    pub fn iv(&self) -> &[u8] {
+        &self.iv
+    }
+
+    /// Get the subsamples. The whole sample is encrypted if there are no
+    /// subsamples.
+    pub fn subsamples(&self) -> &[Subsample] {
+        &self.subsamples
+    }
+}
+
+/// Key system initialization data (i.e. content of a `pssh` box).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct EncryptionInitInfo {
+    system_id: Vec<u8>,
+    key_ids: Vec<Vec<u8>>,
+    data: Vec<u8>,
+}
+
+impl EncryptionInitInfo {
+    /// Create new initialization data for a given key system ID.
+    pub fn new(system_id: &[u8], data: &[u8]) -> Self {
+        Self {
+            system_id: system_id.to_vec(),
+            key_ids: Vec::new(),
+            data: data.to_vec(),
+        }
+    }
+
+    /// Add a given key ID. All key IDs must have the same size.
+    pub fn with_key_id(mut self, key_id: &[u8]) -> Self {
+        self.key_ids.push(key_id.to_vec());
+        self
+    }
+
+    /// Parse given `SideDataType::EncryptionInitInfo` side data. The side
+    /// data may contain initialization data for multiple key systems.
+    pub fn from_side_data(data: &[u8]) -> Result<Vec<Self>, Error> {
+        let ptr = unsafe { ffw_encryption_init_info_from_side_data(data.as_ptr(), data.len()) };
+
+        if ptr.is_null() {
+            return Err(Error::new("invalid encryption init side data"));
+        }
+
+        let infos = RawEncryptionInitInfo { ptr };
+
+        let mut res = Vec::new();
+
+        let mut current = infos.ptr as *const c_void;
+
+        while !current.is_null() {
+            res.push(unsafe { Self::from_raw(current) });
+
+            current = unsafe { ffw_encryption_init_info_get_next(current) };
+        }
+
+        Ok(res)
+    }
+
+    /// Serialize given initialization data as
+    /// `SideDataType::EncryptionInitInfo` side data. At least one item must
+    /// be given.
+    pub fn to_side_data(infos: &[Self]) -> Result<Vec<u8>, Error> {
+        let mut head = RawEncryptionInitInfo {
+            ptr: std::ptr::null_mut(),
+        };
+
+        // build the linked list from the end so that we can always prepend
+        // the next item
+        for info in infos.iter().rev() {
+            let ptr = info.to_raw()?;
+
+            unsafe {
+                ffw_encryption_init_info_set_next(ptr, head.ptr);
+            }
+
+            head.ptr = ptr;
+        }
+
+        if head.ptr.is_null() {
+            return Err(Error::new("no encryption init info given"));
+        }
+
+        let mut size = 0;
+
+        let data = unsafe { ffw_encryption_init_info_to_side_data(head.ptr, &mut size) };
+
+        Ok(unsafe { take_side_data(data, size) })
+    }
+
+    /// Create the initialization data from a raw `AVEncryptionInitInfo`
+    /// (ignoring the next items).
+    unsafe fn from_raw(ptr: *const c_void) -> Self {
+        let mut system_id_size = 0;
+        let mut data_size = 0;
+
+        let system_id = ffw_encryption_init_info_get_system_id(ptr, &mut system_id_size);
+        let data = ffw_encryption_init_info_get_data(ptr, &mut data_size);
+
+        let key_id_count = ffw_encryption_init_info_get_key_id_count(ptr);
+
+        let key_ids = (0..key_id_count)
+            .map(|index| {
+                let mut size = 0;
+
+                let key_id = ffw_encryption_init_info_get_key_id(ptr, index, &mut size);
+
+                raw_bytes(key_id, size)
+            })
+            .collect();
+
+        Self {
+            system_id: raw_bytes(system_id, system_id_size),
+            key_ids,
+            data: raw_bytes(data, data_size),
+        }
+    }
+
+    /// Create a raw `AVEncryptionInitInfo` from the initialization data.
+    fn to_raw(&self) -> Result<*mut c_void, Error> {
+        let key_id_size = self.key_ids.first().map(|id| id.len()).unwrap_or(0);
+
+        if self.key_ids.iter().any(|id| id.len() != key_id_size) {
+            return Err(Error::new("all key IDs must have the same size"));
+        }
+
+        let too_large = |_| Error::new("encryption init info too large");
+
+        let system_id_size = u32::try_from(self.system_id.len()).map_err(too_large)?;
+        let key_id_count = u32::try_from(self.key_ids.len()).map_err(too_large)?;
+        let key_id_size = u32::try_from(key_id_size).map_err(too_large)?;
+        let data_size = u32::try_from(self.data.len()).map_err(too_large)?;
+
+        let ptr = unsafe {
+            ffw_encryption_init_info_new(
+                self.system_id.as_ptr(),
+                system_id_size,
+                key_id_count,
+                key_id_size,
+                self.data.as_ptr(),
+                data_size,
+            )
+        };
+
+        if ptr.is_null() {
+            panic!("unable to allocate encryption init info");
+        }
+
+        for (index, key_id) in self.key_ids.iter().enumerate() {
+            unsafe {
+                ffw_encryption_init_info_set_key_id(ptr, index as u32, key_id.as_ptr());
+            }
+        }
+
+        Ok(ptr)
+    }
+
+    /// Get the key system ID.
+    pub fn system_id(&self) -> &[u8] {
+        &self.system_id
+    }
+
+    /// Get the key IDs.
+    pub fn key_ids(&self) -> &[Vec<u8>] {
+        &self.key_ids
+    }
+
+    /// Get the key system specific data.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// Owned `AVEncryptionInfo`.
+struct RawEncryptionInfo {
+    ptr: *mut c_void,
+}
+
+impl RawEncryptionInfo {
+    /// Copy the content into an `EncryptionInfo`.
+    fn to_encryption_info(&self) -> EncryptionInfo {
+        let mut scheme = 0;
+        let mut crypt_byte_block = 0;
+        let mut skip_byte_block = 0;
+        let mut key_id_size = 0;
+        let mut iv_size = 0;
+
+        unsafe {
+            ffw_encryption_info_get_pattern(
+                self.ptr,
+                &mut scheme,
+                &mut crypt_byte_block,
+                &mut skip_byte_block,
+            );
+        }
+
+        let key_id = unsafe { ffw_encryption_info_get_key_id(self.ptr, &mut key_id_size) };
+        let iv = unsafe { ffw_encryption_info_get_iv(self.ptr, &mut iv_size) };
+
+        let subsample_count = unsafe { ffw_encryption_info_get_subsample_count(self.ptr) };
+
+        let subsamples = (0..subsample_count)
+            .map(|index| {
+                let mut clear_bytes = 0;
+                let mut protected_bytes = 0;
+
+                unsafe {
+                    ffw_encryption_info_get_subsample(
+                        self.ptr,
+                        index,
+                        &mut clear_bytes,
+                        &mut protected_bytes,
+                    );
+                }
+
+                Subsample {
+                    clear_bytes,
+                    protected_bytes,
+                }
+            })
+            .collect();
+
+        EncryptionInfo {
+            scheme: EncryptionScheme::from_fourcc(scheme),
+            crypt_byte_block,
+            skip_byte_block,
+            key_id: unsafe { raw_bytes(key_id, key_id_size) },
+            iv: unsafe { raw_bytes(iv, iv_size) },
+            subsamples,
+        }
+    }
+}
+
+impl Drop for RawEncryptionInfo {
+    fn drop(&mut self) {
+        unsafe { ffw_encryption_info_free(self.ptr) }
+    }
+}
+
+/// Owned list of `AVEncryptionInitInfo`.
+struct RawEncryptionInitInfo {
+    ptr: *mut c_void,
+}
+
+impl Drop for RawEncryptionInitInfo {
+    fn drop(&mut self) {
+        unsafe { ffw_encryption_init_info_free(self.ptr) }
+    }
+}
+
+/// Copy a given raw byte array.
+unsafe fn raw_bytes(ptr: *const u8, size: u32) -> Vec<u8> {
+    if ptr.is_null() || size == 0 {
+        Vec::new()
+    } else {
+        slice::from_raw_parts(ptr, size as usize).to_vec()
+    }
+}
+
+/// Copy and free side data allocated by FFmpeg.
+unsafe fn take_side_data(data: *mut u8, size: usize) -> Vec<u8> {
+    if data.is_null() {
+        panic!("unable to allocate encryption side data");
+    }
+
+    let res = slice::from_raw_parts(data, size).to_vec();
+
+    ffw_encryption_side_data_free(data);
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EncryptionInfo, EncryptionInitInfo, EncryptionScheme, Subsample};
+
+    #[test]
+    fn test_encryption_info() {
+        let info = EncryptionInfo::new(EncryptionScheme::Cbcs, &[1; 16], &[2; 16])
+            .with_pattern(1, 9)
+            .with_subsample(Subsample {
+                clear_bytes: 5,
+                protected_bytes: 100,
+            });
+
+        let data = info.to_side_data();
+
+        assert_eq!(EncryptionInfo::from_side_data(&data).unwrap(), info);
+        assert!(EncryptionInfo::from_side_data(&data[..data.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_encryption_init_info() {
+        let infos = [
+            EncryptionInitInfo::new(&[3; 16], b"pssh data")
+                .with_key_id(&[1; 16])
+                .with_key_id(&[2; 16]),
+            EncryptionInitInfo::new(&[4; 16], &[]),
+        ];
+
+        let data = EncryptionInitInfo::to_side_data(&infos).unwrap();
+
+        assert_eq!(EncryptionInitInfo::from_side_data(&data).unwrap(), infos);
+
+        let invalid = [EncryptionInitInfo::new(&[3; 16], &[])
+            .with_key_id(&[1; 16])
+            .with_key_id(&[2; 8])];
+
+        assert!(EncryptionInitInfo::to_side_data(&invalid).is_err());
+    }
+}
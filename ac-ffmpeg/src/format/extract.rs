@@ -0,0 +1,746 @@
+//! Audio-only and video-only track extraction.
+
+use std::io::{Read, Write};
+
+use crate::{
+    codec::{
+        audio::{AudioDecoder, AudioEncoder, AudioFrame, AudioResampler},
+        hook::FrameHooks,
+        video::{VideoDecoder, VideoEncoder, VideoFrameScaler},
+        AudioCodecParametersBuilder, CodecParameters, Decoder, Encoder,
+        VideoCodecParametersBuilder,
+    },
+    format::{
+        demuxer::{Demuxer, DemuxerWithStreamInfo},
+        io::IO,
+        muxer::{Muxer, MuxerBuilder, OutputFormat},
+        preserve::PreservationPolicy,
+        stream::Discard,
+    },
+    time::TimeBase,
+    Error,
+};
+
+/// Codec policy of the track extraction.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum CodecPolicy {
+    /// Always copy the stream, fail if the codec is not supported by the
+    /// output format.
+    Copy,
+    /// Copy the stream if the codec is supported by the output format,
+    /// transcode it using a given encoder otherwise.
+    CopyOrTranscode(String),
+    /// Always transcode the stream using a given encoder.
+    Transcode(String),
+}
+
+/// Options of the track extraction.
+///
+/// # Example
+/// ```text
+/// ...
+///
+/// let options = ExtractOptions::new(CodecPolicy::CopyOrTranscode(String::from("aac")))
+///     .preservation(PreservationPolicy::default().with_chapters(false));
+/// ```
+pub struct ExtractOptions {
+    policy: CodecPolicy,
+    preservation: PreservationPolicy,
+    hooks: FrameHooks,
+}
+
+impl ExtractOptions {
+    /// Create new extraction options with a given codec policy, the default
+    /// preservation policy and no frame hooks.
+    pub fn new(policy: CodecPolicy) -> Self {
+        Self {
+            policy,
+            preservation: PreservationPolicy::default(),
+            hooks: FrameHooks::new(),
+        }
+    }
+
+    /// Propagate metadata, side data, chapters and dispositions according
+    /// to a given preservation policy.
+    pub fn preservation(mut self, policy: PreservationPolicy) -> Self {
+        self.preservation = policy;
+        self
+    }
+
+    /// Pass all decoded frames through given frame hooks. The hooks are
+    /// called only if the stream is transcoded, use `CodecPolicy::Transcode`
+    /// to make sure that every frame is passed to the hooks. See the
+    /// `codec::hook` module for the ordering and threading guarantees.
+    pub fn hooks(mut self, hooks: FrameHooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+}
+
+/// Extract the first audio stream of a given input into a given output.
+/// All other streams are discarded by the demuxer, so their packets are not
+/// even read.
+///
+/// # Example
+/// ```text
+/// ...
+///
+/// let input = IO::from_seekable_read_stream(File::open("movie.mkv")?);
+/// let output = IO::from_seekable_write_stream(File::create("audio.m4a")?);
+///
+/// let format = OutputFormat::find_by_name("ipod").unwrap();
+///
+/// let options = ExtractOptions::new(CodecPolicy::CopyOrTranscode(String::from("aac")));
+///
+/// extract_audio(input, output, format, options)?;
+///
+/// ...
+/// ```
+pub fn extract_audio<R, W>(
+    input: IO<R>,
+    output: IO<W>,
+    format: OutputFormat,
+    mut options: ExtractOptions,
+) -> Result<IO<W>, Error>
+where
+    R: Read,
+    W: Write,
+{
+    let (demuxer, index) = open(input, true)?;
+
+    let params = demuxer.streams()[index].codec_parameters();
+
+    let preservation = &options.preservation;
+
+    match encoder_name(&format, &params, &options.policy)? {
+        None => copy(demuxer, index, params, output, format, preservation),
+        Some(encoder) => transcode_audio(
+            demuxer,
+            index,
+            encoder,
+            output,
+            format,
+            preservation,
+            &mut options.hooks,
+        ),
+    }
+}
+
+/// Extract the first video stream of a given input into a given output.
+/// All other streams are discarded by the demuxer, so their packets are not
+/// even read.
+pub fn extract_video<R, W>(
+    input: IO<R>,
+    output: IO<W>,
+    format: OutputFormat,
+    mut options: ExtractOptions,
+) -> Result<IO<W>, Error>
+where
+    R: Read,
+    W: Write,
+{
+    let (demuxer, index) = open(input, false)?;
+
+    let params = demuxer.streams()[index].codec_parameters();
+
+    let preservation = &options.preservation;
+
+    match encoder_name(&format, &params, &options.policy)? {
+        None => copy(demuxer, index, params, output, format, preservation),
+        Some(encoder) => transcode_video(
+            demuxer,
+            index,
+            encoder,
+            output,
+            format,
+            preservation,
+            &mut options.hooks,
+        ),
+    }
+}
+
+/// Open a given input, find the first audio/video stream and discard all
+/// other streams.
+pub(crate) fn open<R>(input: IO<R>, audio: bool) -> Result<(DemuxerWithStreamInfo<R>, usize), Error>
+where
+    R: Read,
+{
+    let mut demuxer = Demuxer::builder()
+        .build(input)?
+        .find_stream_info(None)
+        .map_err(|(_, err)| err)?;
+
+    let index = demuxer
+        .streams()
+        .iter()
+        .position(|stream| {
+            let params = stream.codec_parameters();
+
+            if audio {
+                params.is_audio_codec()
+            } else {
+                params.is_video_codec()
+            }
+        })
+        .ok_or_else(|| {
+            if audio {
+                Error::new("no audio stream found")
+            } else {
+                Error::new("no video stream found")
+            }
+        })?;
+
+    for (i, stream) in demuxer.streams_mut().iter_mut().enumerate() {
+        if i != index {
+            stream.set_discard(Discard::All);
+        }
+    }
+
+    Ok((demuxer, index))
+}
+
+/// Get name of the encoder to be used or None if the stream should be
+/// copied.
+pub(crate) fn encoder_name<'a>(
+    format: &OutputFormat,
+    params: &CodecParameters,
+    policy: &'a CodecPolicy,
+) -> Result<Option<&'a str>, Error> {
+    match policy {
+        CodecPolicy::Copy if format.supports_codec(params) => Ok(None),
+        CodecPolicy::Copy => Err(Error::new(
+            "unable to copy the stream, codec not supported by the output format",
+        )),
+        CodecPolicy::CopyOrTranscode(_) if format.supports_codec(params) => Ok(None),
+        CodecPolicy::CopyOrTranscode(encoder) => Ok(Some(encoder)),
+        CodecPolicy::Transcode(encoder) => Ok(Some(encoder)),
+    }
+}
+
+/// Create a single-stream muxer builder and propagate metadata, side data,
+/// chapters and dispositions of a given input stream.
+fn muxer_builder<R>(
+    demuxer: &DemuxerWithStreamInfo<R>,
+    index: usize,
+    params: &CodecParameters,
+    preservation: &PreservationPolicy,
+) -> Result<MuxerBuilder, Error> {
+    let mut builder = Muxer::builder();
+
+    builder.add_stream(params)?;
+
+    preservation.apply_stream(&demuxer.streams()[index], &mut builder.streams_mut()[0])?;
+    preservation.apply_container(demuxer, builder)
+}
+
+/// Copy packets of a given stream into a new single-stream output.
+fn copy<R, W>(
+    mut demuxer: DemuxerWithStreamInfo<R>,
+    index: usize,
+    params: CodecParameters,
+    output: IO<W>,
+    format: OutputFormat,
+    preservation: &PreservationPolicy,
+) -> Result<IO<W>, Error>
+where
+    R: Read,
+    W: Write,
+{
+    let params = reset_codec_tag(params);
+
+    let builder = muxer_builder(&demuxer, index, &params, preservation)?;
+
+    let mut muxer = builder.build(output, format)?;
+
+    while let Some(packet) = demuxer.take()? {
+        if packet.stream_index() == index {
+            muxer.push(packet.with_stream_index(0))?;
+        }
+    }
+
+    muxer.flush()?;
+    muxer.close()
+}
+
+/// Reset the codec tag of given audio/video codec parameters. The codec tag
+/// of the input container may not be valid in the output container, this
+/// lets the muxer choose one.
+pub(crate) fn reset_codec_tag(params: CodecParameters) -> CodecParameters {
+    if let Some(params) = params.as_audio_codec_parameters() {
+        AudioCodecParametersBuilder::from(params.clone())
+            .codec_tag(0)
+            .build()
+            .into()
+    } else if let Some(params) = params.as_video_codec_parameters() {
+        VideoCodecParametersBuilder::from(params.clone())
+            .codec_tag(0)
+            .build()
+            .into()
+    } else {
+        params
+    }
+}
+
+/// Decode a given audio stream, pass the decoded frames through given hooks
+/// and encode them using a given encoder.
+fn transcode_audio<R, W>(
+    mut demuxer: DemuxerWithStreamInfo<R>,
+    index: usize,
+    encoder: &str,
+    output: IO<W>,
+    format: OutputFormat,
+    preservation: &PreservationPolicy,
+    hooks: &mut FrameHooks,
+) -> Result<IO<W>, Error>
+where
+    R: Read,
+    W: Write,
+{
+    let mut decoder = AudioDecoder::from_stream(&demuxer.streams()[index])?.build()?;
+
+    let mut state: Option<(AudioResampler, AudioEncoder, Muxer<W>)> = None;
+    let mut output = Some(output);
+    let mut format = Some(format);
+
+    let mut eof = false;
+
+    loop {
+        if let Some(packet) = demuxer.take()? {
+            if packet.stream_index() != index {
+                continue;
+            }
+
+            decoder.push(packet)?;
+        } else {
+            decoder.flush()?;
+
+            eof = true;
+        }
+
+        while let Some(frame) = decoder.take()? {
+            let frame = hooks.audio_frame(frame)?;
+
+            if state.is_none() {
+                let mut builder = AudioEncoder::builder(encoder)?;
+
+                let sample_format = builder
+                    .default_sample_format()
+                    .unwrap_or_else(|| frame.sample_format());
+
+                let sample_rate = builder.closest_sample_rate(frame.sample_rate());
+
+                let channel_layout = builder.closest_channel_layout(frame.channel_layout());
+
+                let format = format.take().unwrap();
+
+                if format.requires_global_header() {
+                    builder = builder.set_option("flags", "+global_header");
+                }
+
+                let encoder = builder
+                    .sample_format(sample_format)
+                    .sample_rate(sample_rate)
+                    .channel_layout(channel_layout)
+                    .time_base(TimeBase::new(1, sample_rate as _))
+                    .build()?;
+
+                let resampler = resampler(&frame, &encoder)?;
+
+                let builder = muxer_builder(
+                    &demuxer,
+                    index,
+                    &encoder.codec_parameters().into(),
+                    preservation,
+                )?;
+
+                let muxer = builder.build(output.take().unwrap(), format)?;
+
+                state = Some((resampler, encoder, muxer));
+            }
+
+            let (resampler, encoder, muxer) = state.as_mut().unwrap();
+
+            // the channel layout, sample format or sample rate may change in
+            // the middle of the stream, all samples buffered in the current
+            // resampler must be encoded before replacing it
+            if !resampler.accepts(&frame) {
+                resampler.flush()?;
+
+                encode_resampled(resampler, encoder, muxer)?;
+
+                *resampler = self::resampler(&frame, encoder)?;
+            }
+
+            resampler.push(frame)?;
+
+            encode_resampled(resampler, encoder, muxer)?;
+        }
+
+        if eof {
+            break;
+        }
+    }
+
+    let (mut resampler, mut encoder, mut muxer) =
+        state.ok_or_else(|| Error::new("no audio frames decoded"))?;
+
+    resampler.flush()?;
+
+    encode_resampled(&mut resampler, &mut encoder, &mut muxer)?;
+
+    encoder.flush()?;
+
+    while let Some(packet) = encoder.take()? {
+        muxer.push(packet.with_stream_index(0))?;
+    }
+
+    muxer.flush()?;
+    muxer.close()
+}
+
+/// Create a resampler converting frames with the same format as a given
+/// frame into the format of a given encoder.
+fn resampler(frame: &AudioFrame, encoder: &AudioEncoder) -> Result<AudioResampler, Error> {
+    let params = encoder.codec_parameters();
+
+    AudioResampler::builder()
+        .source_channel_layout(frame.channel_layout().to_owned())
+        .source_sample_format(frame.sample_format())
+        .source_sample_rate(frame.sample_rate())
+        .target_channel_layout(params.channel_layout().to_owned())
+        .target_sample_format(params.sample_format())
+        .target_sample_rate(params.sample_rate())
+        .target_frame_samples(encoder.samples_per_frame())
+        .build()
+}
+
+/// Encode all frames available in a given resampler and push the encoded
+/// packets into a given muxer.
+fn encode_resampled<W>(
+    resampler: &mut AudioResampler,
+    encoder: &mut AudioEncoder,
+    muxer: &mut Muxer<W>,
+) -> Result<(), Error>
+where
+    W: Write,
+{
+    while let Some(frame) = resampler.take()? {
+        encoder.push(frame)?;
+
+        while let Some(packet) = encoder.take()? {
+            muxer.push(packet.with_stream_index(0))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Decode a given video stream, pass the decoded frames through given hooks
+/// and encode them using a given encoder.
+fn transcode_video<R, W>(
+    mut demuxer: DemuxerWithStreamInfo<R>,
+    index: usize,
+    encoder: &str,
+    output: IO<W>,
+    format: OutputFormat,
+    preservation: &PreservationPolicy,
+    hooks: &mut FrameHooks,
+) -> Result<IO<W>, Error>
+where
+    R: Read,
+    W: Write,
+{
+    let stream = &demuxer.streams()[index];
+
+    let time_base = stream.time_base();
+    let frame_rate = stream.avg_frame_rate();
+
+    let mut decoder = VideoDecoder::from_stream(stream)?.build()?;
+
+    let mut state: Option<(Option<VideoFrameScaler>, VideoEncoder, Muxer<W>)> = None;
+    let mut output = Some(output);
+    let mut format = Some(format);
+
+    let mut eof = false;
+
+    loop {
+        if let Some(packet) = demuxer.take()? {
+            if packet.stream_index() != index {
+                continue;
+            }
+
+            decoder.push(packet)?;
+        } else {
+            decoder.flush()?;
+
+            eof = true;
+        }
+
+        while let Some(frame) = decoder.take()? {
+            let frame = hooks.video_frame(frame)?;
+
+            if state.is_none() {
+                let mut builder = VideoEncoder::builder(encoder)?;
+
+                let pixel_format = builder
+                    .default_pixel_format()
+                    .unwrap_or_else(|| frame.pixel_format());
+
+                let format = format.take().unwrap();
+
+                if format.requires_global_header() {
+                    builder = builder.set_option("flags", "+global_header");
+                }
+
+                if let Some(sar) = frame.sample_aspect_ratio() {
+                    builder = builder.sample_aspect_ratio(sar);
+                }
+
+                if let Some(frame_rate) = frame_rate {
+                    builder = builder.frame_rate(frame_rate);
+                }
+
+                let encoder = builder
+                    .pixel_format(pixel_format)
+                    .width(frame.width())
+                    .height(frame.height())
+                    .time_base(time_base)
+                    .build()?;
+
+                let scaler = if pixel_format != frame.pixel_format() {
+                    let scaler = VideoFrameScaler::builder()
+                        .source_pixel_format(frame.pixel_format())
+                        .source_width(frame.width())
+                        .source_height(frame.height())
+                        .target_pixel_format(pixel_format)
+                        .target_width(frame.width())
+                        .target_height(frame.height())
+                        .build()?;
+
+                    Some(scaler)
+                } else {
+                    None
+                };
+
+                let builder = muxer_builder(
+                    &demuxer,
+                    index,
+                    &encoder.codec_parameters().into(),
+                    preservation,
+                )?;
+
+                let muxer = builder.build(output.take().unwrap(), format)?;
+
+                state = Some((scaler, encoder, muxer));
+            }
+
+            let (scaler, encoder, muxer) = state.as_mut().unwrap();
+
+            let frame = if let Some(scaler) = scaler.as_mut() {
+                scaler.scale(&frame)?
+            } else {
+                frame
+            };
+
+            encoder.push(frame)?;
+
+            while let Some(packet) = encoder.take()? {
+                muxer.push(packet.with_stream_index(0))?;
+            }
+        }
+
+        if eof {
+            break;
+        }
+    }
+
+    let (_, mut encoder, mut muxer) = state.ok_or_else(|| Error::new("no video frames decoded"))?;
+
+    encoder.flush()?;
+
+    while let Some(packet) = encoder.take()? {
+        muxer.push(packet.with_stream_index(0))?;
+    }
+
+    muxer.flush()?;
+    muxer.close()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Cursor,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use super::{extract_audio, extract_video, CodecPolicy, ExtractOptions};
+
+    use crate::{
+        codec::{
+            audio::{AudioDecoder, AudioFrameMut, ChannelLayout},
+            hook::FrameHooks,
+            Decoder,
+        },
+        format::{demuxer::Demuxer, io::IO, muxer::OutputFormat},
+        test_util::{encode_audio, encode_video},
+    };
+
+    /// Get a new in-memory output.
+    fn output() -> IO<Cursor<Vec<u8>>> {
+        IO::from_seekable_write_stream(Cursor::new(Vec::new()))
+    }
+
+    /// Get the Matroska output format.
+    fn matroska() -> OutputFormat {
+        OutputFormat::find_by_name("matroska").unwrap()
+    }
+
+    /// Get the number of streams and the number of packets of a given
+    /// in-memory file.
+    fn count_packets(data: Vec<u8>) -> (usize, usize) {
+        let mut demuxer = Demuxer::builder()
+            .build(IO::from_read_stream(Cursor::new(data)))
+            .unwrap()
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)
+            .unwrap();
+
+        let streams = demuxer.streams().len();
+
+        let mut packets = 0;
+
+        while demuxer.take().unwrap().is_some() {
+            packets += 1;
+        }
+
+        (streams, packets)
+    }
+
+    #[test]
+    fn test_extract_video_copy() {
+        let input = IO::from_read_stream(Cursor::new(encode_video(64, 48, 10, 5)));
+
+        let output = extract_video(
+            input,
+            output(),
+            matroska(),
+            ExtractOptions::new(CodecPolicy::Copy),
+        )
+        .unwrap();
+
+        let data = output.into_stream().into_inner();
+
+        assert_eq!(count_packets(data), (1, 10));
+    }
+
+    #[test]
+    fn test_extract_video_transcode() {
+        let input = IO::from_read_stream(Cursor::new(encode_video(64, 48, 10, 5)));
+
+        let frames = Arc::new(AtomicUsize::new(0));
+
+        let counter = frames.clone();
+
+        let hooks = FrameHooks::new().with_video_hook(move |frame| {
+            counter.fetch_add(1, Ordering::SeqCst);
+
+            Ok(frame)
+        });
+
+        let options =
+            ExtractOptions::new(CodecPolicy::Transcode(String::from("mpeg4"))).hooks(hooks);
+
+        let output = extract_video(input, output(), matroska(), options).unwrap();
+
+        let data = output.into_stream().into_inner();
+
+        assert_eq!(frames.load(Ordering::SeqCst), 10);
+        assert_eq!(count_packets(data), (1, 10));
+    }
+
+    #[test]
+    fn test_missing_stream() {
+        let input = IO::from_read_stream(Cursor::new(encode_video(64, 48, 10, 5)));
+
+        let res = extract_audio(
+            input,
+            output(),
+            matroska(),
+            ExtractOptions::new(CodecPolicy::Copy),
+        );
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_channel_layout_change() {
+        let input = IO::from_read_stream(Cursor::new(encode_audio(10)));
+
+        let mut frames = 0;
+
+        // switch to stereo in the middle of the stream
+        let hooks = FrameHooks::new().with_audio_hook(move |frame| {
+            frames += 1;
+
+            if frames <= 5 {
+                return Ok(frame);
+            }
+
+            let stereo = ChannelLayout::from_channels(2).unwrap();
+
+            let res = AudioFrameMut::silence(
+                &stereo,
+                frame.sample_format(),
+                frame.sample_rate(),
+                frame.samples(),
+            )
+            .with_time_base(frame.time_base())
+            .with_pts(frame.pts())
+            .freeze();
+
+            Ok(res)
+        });
+
+        let options =
+            ExtractOptions::new(CodecPolicy::Transcode(String::from("pcm_s16le"))).hooks(hooks);
+
+        let output = extract_audio(input, output(), matroska(), options).unwrap();
+
+        let data = output.into_stream().into_inner();
+
+        let mut demuxer = Demuxer::builder()
+            .build(IO::from_read_stream(Cursor::new(data)))
+            .unwrap()
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)
+            .unwrap();
+
+        let mut decoder = AudioDecoder::from_stream(&demuxer.streams()[0])
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let mut samples = 0;
+
+        while let Some(packet) = demuxer.take().unwrap() {
+            decoder.push(packet).unwrap();
+
+            while let Some(frame) = decoder.take().unwrap() {
+                assert_eq!(frame.channel_layout().channels(), 1);
+
+                samples += frame.samples();
+            }
+        }
+
+        decoder.flush().unwrap();
+
+        while let Some(frame) = decoder.take().unwrap() {
+            samples += frame.samples();
+        }
+
+        assert_eq!(samples, 8000);
+    }
+}
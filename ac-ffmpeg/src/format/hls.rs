@@ -0,0 +1,684 @@
+//! HLS muxer with AES-128 encryption.
+//!
+//! This is a wrapper around the FFmpeg `hls` muxer. The HLS muxer writes
+//! the playlist and the segments into files (or other URLs) on its own.
+//!
+//! Segments can be encrypted using AES-128 (i.e. whole segments are
+//! encrypted, `METHOD=AES-128`). The key can be rotated during muxing
+//! using a key rotation handler. Note that SAMPLE-AES is not supported by
+//! the FFmpeg HLS muxer.
+//!
+//! # Example
+//! ```text
+//! let key = HlsKey::new(key_bytes, "https://keys.example.com/live/1")
+//!     .iv(IvPolicy::Fixed(iv_bytes));
+//!
+//! let mut builder = HlsMuxer::builder()
+//!     .segment_duration(Duration::from_secs(6))
+//!     .playlist_size(10)
+//!     .encryption(key)
+//!     .key_rotation(|segment| {
+//!         if segment % 100 == 0 {
+//!             Some(key_server.new_key(segment))
+//!         } else {
+//!             None
+//!         }
+//!     });
+//!
+//! builder.add_stream(&video_params)?;
+//!
+//! let mut muxer = builder.build("live/playlist.m3u8")?;
+//! ```
+
+use std::{
+    collections::hash_map::RandomState,
+    ffi::CStr,
+    fmt::Write as _,
+    fs::{self, DirBuilder, OpenOptions},
+    hash::{BuildHasher, Hasher},
+    io::{self, Sink, Write},
+    os::raw::{c_char, c_void},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, SystemTime},
+};
+
+#[cfg(unix)]
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+
+use crate::{
+    codec::{CodecParameters, OtherCodecParameters},
+    format::{
+        id3::{Id3Frame, Id3Tag},
+        io::IO,
+        muxer::{Muxer, MuxerBuilder, OutputFormat},
+    },
+    packet::Packet,
+    time::Timestamp,
+    Error,
+};
+
+/// Counter used for generating unique names of the key directories.
+static KEY_DIRS: AtomicUsize = AtomicUsize::new(0);
+
+/// Default name of the fMP4 initialization segment.
+const DEFAULT_INIT_SEGMENT: &str = "init.mp4";
+
+/// Initialization vector policy.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum IvPolicy {
+    /// Use the media sequence number of every segment as the IV (the IV is
+    /// not written into the playlist, it is implied by the HLS
+    /// specification).
+    SequenceNumber,
+    /// Use a given IV for all segments encrypted using the key.
+    Fixed([u8; 16]),
+}
+
+/// Source of the key data.
+#[derive(Debug, Clone)]
+enum KeySource {
+    Bytes([u8; 16]),
+    File(PathBuf),
+}
+
+/// AES-128 encryption key.
+#[derive(Debug, Clone)]
+pub struct HlsKey {
+    source: KeySource,
+    uri: String,
+    iv: IvPolicy,
+}
+
+impl HlsKey {
+    /// Create a new key from given key bytes. The key will be served to
+    /// players from a given URI (written into the playlist).
+    pub fn new(key: [u8; 16], uri: &str) -> Self {
+        Self {
+            source: KeySource::Bytes(key),
+            uri: String::from(uri),
+            iv: IvPolicy::SequenceNumber,
+        }
+    }
+
+    /// Create a new key loaded from a given file containing the 16 key
+    /// bytes. The key will be served to players from a given URI.
+    pub fn from_file<P>(path: P, uri: &str) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self {
+            source: KeySource::File(path.as_ref().to_path_buf()),
+            uri: String::from(uri),
+            iv: IvPolicy::SequenceNumber,
+        }
+    }
+
+    /// Set the IV policy. The default is `IvPolicy::SequenceNumber`.
+    pub fn iv(mut self, iv: IvPolicy) -> Self {
+        self.iv = iv;
+        self
+    }
+}
+
+/// Key rotation handler.
+type KeyRotationHandler = Box<dyn FnMut(usize) -> Option<HlsKey> + Send>;
+
+/// Builder for the HLS muxer.
+pub struct HlsMuxerBuilder {
+    builder: MuxerBuilder,
+    flags: Vec<String>,
+    key: Option<HlsKey>,
+    rotation: Option<KeyRotationHandler>,
+    id3_stream: Option<usize>,
+    init_segment: String,
+}
+
+impl HlsMuxerBuilder {
+    /// Create a new builder.
+    fn new() -> Self {
+        Self {
+            builder: Muxer::builder(),
+            flags: Vec::new(),
+            key: None,
+            rotation: None,
+            id3_stream: None,
+            init_segment: String::from(DEFAULT_INIT_SEGMENT),
+        }
+    }
+
+    /// Add a new stream with given parameters and return index of the new
+    /// stream.
+    pub fn add_stream(&mut self, params: &CodecParameters) -> Result<usize, Error> {
+        self.builder.add_stream(params)
+    }
+
+    /// Add a timed ID3 metadata stream and return its index. The metadata
+    /// can be sent using `HlsMuxer::send_id3()`.
+    pub fn add_id3_stream(&mut self) -> Result<usize, Error> {
+        let params = OtherCodecParameters::data("timed_id3")?;
+
+        let index = self.builder.add_stream(&params.into())?;
+
+        self.id3_stream = Some(index);
+
+        Ok(index)
+    }
+
+    /// Set an HLS muxer option. Values of the `hls_flags` option are
+    /// accumulated (the flags are combined with flags needed for key
+    /// rotation).
+    pub fn set_option<V>(mut self, name: &str, value: V) -> Self
+    where
+        V: ToString,
+    {
+        if name == "hls_flags" {
+            let flags = value.to_string();
+
+            if flags.starts_with(['+', '-']) {
+                self.flags.push(flags);
+            } else {
+                self.flags.push(format!("+{}", flags));
+            }
+        } else {
+            if name == "hls_fmp4_init_filename" {
+                self.init_segment = value.to_string();
+            }
+
+            self.builder = self.builder.set_option(name, value);
+        }
+
+        self
+    }
+
+    /// Set the target segment duration. The default is 2 seconds.
+    pub fn segment_duration(self, duration: Duration) -> Self {
+        let duration = format!("{}.{:06}", duration.as_secs(), duration.subsec_micros());
+
+        self.set_option("hls_time", duration)
+    }
+
+    /// Set the maximum number of segments in the playlist. Zero means
+    /// keeping all segments. The default is 5.
+    pub fn playlist_size(self, segments: usize) -> Self {
+        self.set_option("hls_list_size", segments)
+    }
+
+    /// Set the segment name pattern (e.g. `segment-%05d.ts`). The pattern
+    /// is derived from the playlist URL by default.
+    pub fn segment_name(self, pattern: &str) -> Self {
+        self.set_option("hls_segment_filename", pattern)
+    }
+
+    /// Encrypt the segments using a given AES-128 key.
+    pub fn encryption(mut self, key: HlsKey) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    /// Set a key rotation handler. The handler is called with the index of
+    /// every upcoming media segment (starting from 2, the first segment is
+    /// encrypted using the key passed to `encryption()`) and it can return
+    /// a new key for the segment and all subsequent segments. The handler
+    /// is called from the thread using the muxer, at the time the previous
+    /// segment is opened (the muxer reads the key before opening the
+    /// segment). The fMP4 initialization segment is not counted. The
+    /// handler is used only if the encryption is enabled.
+    pub fn key_rotation<F>(mut self, handler: F) -> Self
+    where
+        F: FnMut(usize) -> Option<HlsKey> + Send + 'static,
+    {
+        self.rotation = Some(Box::new(handler));
+        self
+    }
+
+    /// Build the HLS muxer. The playlist will be written to a given URL
+    /// (e.g. `live/playlist.m3u8`), the segments will be written next to it
+    /// unless a different segment name pattern is set.
+    pub fn build(mut self, url: &str) -> Result<HlsMuxer, Error> {
+        let format = OutputFormat::find_by_name("hls")
+            .ok_or_else(|| Error::new("the HLS muxer is not available"))?;
+
+        let mut res = HlsMuxer {
+            muxer: None,
+            keys: None,
+            id3_stream: self.id3_stream,
+        };
+
+        if let Some(key) = self.key.take() {
+            let mut files = KeyFiles::new().map_err(|err| {
+                Error::new(format!("unable to create the key directory: {}", err))
+            })?;
+
+            files
+                .write(&key)
+                .map_err(|err| Error::new(format!("unable to write the key info file: {}", err)))?;
+
+            self.builder = self
+                .builder
+                .set_option("hls_key_info_file", files.info.to_string_lossy());
+
+            let rotation = self.rotation.take();
+            let rotate = rotation.is_some();
+
+            let keys = Box::into_raw(Box::new(KeyState::new(files, rotation, &self.init_segment)));
+
+            res.keys = Some(keys);
+
+            if rotate {
+                self.flags.push(String::from("+periodic_rekey"));
+
+                self.builder
+                    .set_io_open_callback(output_opened, keys as *mut c_void);
+            }
+        }
+
+        if !self.flags.is_empty() {
+            self.builder = self.builder.set_option("hls_flags", self.flags.join(""));
+        }
+
+        // the HLS muxer opens the outputs on its own, the IO is not used
+        let muxer = self
+            .builder
+            .set_option("url", url)
+            .build(IO::from_write_stream(io::sink()), format)?;
+
+        res.muxer = Some(muxer);
+
+        res.check_key_error()?;
+
+        Ok(res)
+    }
+}
+
+/// HLS muxer.
+///
+/// Packets must be pushed in the decoding order and interleaved across
+/// streams.
+pub struct HlsMuxer {
+    muxer: Option<Muxer<Sink>>,
+    keys: Option<*mut KeyState>,
+    id3_stream: Option<usize>,
+}
+
+impl HlsMuxer {
+    /// Get an HLS muxer builder.
+    pub fn builder() -> HlsMuxerBuilder {
+        HlsMuxerBuilder::new()
+    }
+
+    /// Push a given packet to the muxer.
+    pub fn push(&mut self, packet: Packet) -> Result<(), Error> {
+        self.muxer_mut().push(packet)?;
+        self.check_key_error()
+    }
+
+    /// Send given ID3 frames with a given timestamp using the timed ID3
+    /// stream (see `HlsMuxerBuilder::add_id3_stream()`). The metadata are
+    /// interleaved with other packets, so the timestamp should be close to
+    /// timestamps of the packets being pushed.
+    pub fn send_id3(&mut self, timestamp: Timestamp, frames: &[Id3Frame]) -> Result<(), Error> {
+        let stream = self
+            .id3_stream
+            .ok_or_else(|| Error::new("there is no timed ID3 stream"))?;
+
+        let tag = frames
+            .iter()
+            .cloned()
+            .fold(Id3Tag::new(), |tag, frame| tag.with_frame(frame));
+
+        let packet = tag.to_packet(timestamp)?.with_stream_index(stream);
+
+        self.push(packet)
+    }
+
+    /// Flush the muxer.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.muxer_mut().flush()?;
+        self.check_key_error()
+    }
+
+    /// Close the muxer. This will write the last segment and the final
+    /// playlist.
+    pub fn close(mut self) -> Result<(), Error> {
+        let mut muxer = self.muxer.take().unwrap();
+
+        muxer.flush()?;
+        muxer.close()?;
+
+        self.check_key_error()
+    }
+
+    /// Get the inner muxer.
+    fn muxer_mut(&mut self) -> &mut Muxer<Sink> {
+        self.muxer.as_mut().unwrap()
+    }
+
+    /// Return the last key rotation error (if any).
+    fn check_key_error(&mut self) -> Result<(), Error> {
+        let keys = match self.keys {
+            Some(keys) => unsafe { &mut *keys },
+            None => return Ok(()),
+        };
+
+        if let Some(err) = keys.error.take() {
+            Err(Error::new(format!("unable to rotate the key: {}", err)))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Drop for HlsMuxer {
+    fn drop(&mut self) {
+        // the muxer must be dropped first as it may still use the key state
+        self.muxer = None;
+
+        if let Some(keys) = self.keys.take() {
+            unsafe {
+                drop(Box::from_raw(keys));
+            }
+        }
+    }
+}
+
+unsafe impl Send for HlsMuxer {}
+
+/// Key rotation state.
+struct KeyState {
+    files: KeyFiles,
+    rotation: Option<KeyRotationHandler>,
+    init_segment: String,
+    segments: usize,
+    error: Option<io::Error>,
+}
+
+impl KeyState {
+    /// Create a new key rotation state.
+    fn new(files: KeyFiles, rotation: Option<KeyRotationHandler>, init_segment: &str) -> Self {
+        Self {
+            files,
+            rotation,
+            init_segment: String::from(init_segment),
+            segments: 0,
+            error: None,
+        }
+    }
+
+    /// Process a given URL opened by the HLS muxer. If it is a media
+    /// segment, the key for the next segment is requested from the
+    /// rotation handler because the muxer reads the key info file before
+    /// it opens a segment.
+    fn output_opened(&mut self, url: &str) {
+        let url = url.strip_prefix("crypto:").unwrap_or(url);
+
+        // the muxer also opens the playlists, the init segment and reads
+        // the key files
+        if url.contains(".m3u8") || url.ends_with(&self.init_segment) || self.files.contains(url) {
+            return;
+        }
+
+        self.segments += 1;
+
+        let next = self.segments + 1;
+
+        let key = self.rotation.as_mut().and_then(|rotation| rotation(next));
+
+        if let Some(key) = key {
+            if let Err(err) = self.files.write(&key) {
+                self.error = Some(err);
+            }
+        }
+    }
+}
+
+/// Private temporary directory containing the key info file and the key
+/// files read by the HLS muxer. The directory has an unpredictable name and
+/// it is accessible only by the current user. Key files are deleted as soon
+/// as they are replaced by a new key.
+struct KeyFiles {
+    dir: PathBuf,
+    info: PathBuf,
+    key: Option<PathBuf>,
+    files: usize,
+}
+
+impl KeyFiles {
+    /// Create a new private key directory.
+    fn new() -> io::Result<Self> {
+        let tmp = std::env::temp_dir();
+
+        let mut attempts = 0;
+
+        let dir = loop {
+            let dir = tmp.join(format!("ac-ffmpeg-hls-{}", random_name()));
+
+            let mut builder = DirBuilder::new();
+
+            #[cfg(unix)]
+            builder.mode(0o700);
+
+            match builder.create(&dir) {
+                Ok(()) => break dir,
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists && attempts < 8 => {
+                    attempts += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        };
+
+        let res = Self {
+            info: dir.join("key.keyinfo"),
+            dir,
+            key: None,
+            files: 0,
+        };
+
+        Ok(res)
+    }
+
+    /// Write a given key. The key info file is replaced atomically and the
+    /// previous key file is deleted.
+    fn write(&mut self, key: &HlsKey) -> io::Result<()> {
+        let (key_file, key_path) = match &key.source {
+            KeySource::Bytes(bytes) => {
+                let path = self.create_file("key", bytes)?;
+
+                (Some(path.clone()), path)
+            }
+            KeySource::File(path) => (None, path.clone()),
+        };
+
+        let info = key_info(key, &key_path.to_string_lossy());
+
+        let tmp = self.create_file("keyinfo", info.as_bytes())?;
+
+        fs::rename(&tmp, &self.info)?;
+
+        if let Some(old) = std::mem::replace(&mut self.key, key_file) {
+            let _ = fs::remove_file(old);
+        }
+
+        Ok(())
+    }
+
+    /// Create a new file readable only by the current user containing given
+    /// data.
+    fn create_file(&mut self, extension: &str, data: &[u8]) -> io::Result<PathBuf> {
+        self.files += 1;
+
+        let path = self.dir.join(format!("{}.{}", self.files, extension));
+
+        let mut options = OpenOptions::new();
+
+        options.write(true).create_new(true);
+
+        #[cfg(unix)]
+        options.mode(0o600);
+
+        options.open(&path)?.write_all(data)?;
+
+        Ok(path)
+    }
+
+    /// Check if a given URL is one of the key files.
+    fn contains(&self, url: &str) -> bool {
+        Path::new(url).starts_with(&self.dir)
+    }
+}
+
+impl Drop for KeyFiles {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Generate a random name for a temporary directory.
+fn random_name() -> String {
+    // the hasher keys are random
+    let mut hasher = RandomState::new().build_hasher();
+
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|t| t.as_nanos())
+        .unwrap_or(0);
+
+    hasher.write_u32(process::id());
+    hasher.write_usize(KEY_DIRS.fetch_add(1, Ordering::Relaxed));
+    hasher.write_u128(nanos);
+
+    format!("{:016x}", hasher.finish())
+}
+
+/// Create content of the key info file for a given key stored in a given
+/// file.
+fn key_info(key: &HlsKey, key_path: &str) -> String {
+    let mut res = format!("{}\n{}\n", key.uri, key_path);
+
+    if let IvPolicy::Fixed(iv) = key.iv {
+        for b in iv.iter() {
+            let _ = write!(res, "{:02x}", b);
+        }
+
+        res.push('\n');
+    }
+
+    res
+}
+
+/// Rotate the key if needed whenever the HLS muxer opens a new segment.
+extern "C" fn output_opened(opaque: *mut c_void, url: *const c_char) {
+    let state = unsafe { &mut *(opaque as *mut KeyState) };
+
+    let url = unsafe { CStr::from_ptr(url) };
+
+    state.output_opened(&url.to_string_lossy());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        sync::{Arc, Mutex},
+    };
+
+    use super::{key_info, HlsKey, IvPolicy, KeyFiles, KeyState};
+
+    #[test]
+    fn test_key_info() {
+        let key = HlsKey::new([0; 16], "https://example.com/key");
+
+        assert_eq!(
+            key_info(&key, "/tmp/key"),
+            "https://example.com/key\n/tmp/key\n"
+        );
+
+        let mut iv = [0; 16];
+
+        iv[15] = 0xab;
+
+        let key = key.iv(IvPolicy::Fixed(iv));
+
+        assert_eq!(
+            key_info(&key, "/tmp/key"),
+            "https://example.com/key\n/tmp/key\n000000000000000000000000000000ab\n"
+        );
+    }
+
+    #[test]
+    fn test_key_rotation_index() {
+        let requested = Arc::new(Mutex::new(Vec::new()));
+        let handler_requested = requested.clone();
+
+        let rotation = Box::new(move |segment: usize| {
+            handler_requested.lock().unwrap().push(segment);
+
+            if segment == 3 {
+                Some(HlsKey::new([3; 16], "https://example.com/key/3"))
+            } else {
+                None
+            }
+        });
+
+        let mut files = KeyFiles::new().unwrap();
+
+        files
+            .write(&HlsKey::new([1; 16], "https://example.com/key/1"))
+            .unwrap();
+
+        let info = files.info.clone();
+        let key = files.key.clone().unwrap();
+
+        let mut state = KeyState::new(files, Some(rotation), "init.mp4");
+
+        state.output_opened("live/playlist.m3u8.tmp");
+        state.output_opened("live/init.mp4");
+        state.output_opened(&info.to_string_lossy());
+        state.output_opened("crypto:live/playlist0.ts");
+
+        assert_eq!(*requested.lock().unwrap(), vec![2]);
+
+        state.output_opened("crypto:live/playlist1.ts");
+
+        assert_eq!(*requested.lock().unwrap(), vec![2, 3]);
+
+        // the key for the third segment is in place when the muxer reads
+        // the key info file before opening the segment
+        let content = fs::read_to_string(&info).unwrap();
+
+        assert!(content.starts_with("https://example.com/key/3\n"));
+
+        // the previous key file has been deleted
+        assert!(!key.exists());
+
+        let dir = state.files.dir.clone();
+
+        drop(state);
+
+        assert!(!dir.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_key_file_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mut files = KeyFiles::new().unwrap();
+
+        files
+            .write(&HlsKey::new([1; 16], "https://example.com/key"))
+            .unwrap();
+
+        let dir_mode = fs::metadata(&files.dir).unwrap().permissions().mode();
+        let key_mode = fs::metadata(files.key.as_ref().unwrap())
+            .unwrap()
+            .permissions()
+            .mode();
+        let info_mode = fs::metadata(&files.info).unwrap().permissions().mode();
+
+        assert_eq!(dir_mode & 0o777, 0o700);
+        assert_eq!(key_mode & 0o777, 0o600);
+        assert_eq!(info_mode & 0o777, 0o600);
+    }
+}
@@ -0,0 +1,337 @@
+//! HTTP input options.
+//!
+//! # Example
+//! ```text
+//! let options = HttpOptions::new()
+//!     .header("Authorization", &format!("Bearer {}", token))
+//!     .user_agent("my-ingest/1.0")
+//!     .cookie("session", &session_id, "cdn.example.com");
+//!
+//! let demuxer = Demuxer::builder()
+//!     .http_options(&options)?
+//!     .build_from_url("https://cdn.example.com/live/stream.m3u8")?;
+//!
+//! if let Some(response) = demuxer.http_response() {
+//!     println!("content type: {:?}", response.mime_type());
+//! }
+//! ```
+
+use crate::Error;
+
+/// Options of HTTP(S) inputs. The options are used for all HTTP requests
+/// made by a demuxer (including e.g. requests for HLS segments).
+#[derive(Debug, Clone, Default)]
+pub struct HttpOptions {
+    headers: Vec<(String, String)>,
+    user_agent: Option<String>,
+    referer: Option<String>,
+    cookies: Vec<String>,
+    tls_verify: Option<bool>,
+    ca_file: Option<String>,
+    cert_file: Option<String>,
+    key_file: Option<String>,
+}
+
+impl HttpOptions {
+    /// Create new HTTP options with all values set to the FFmpeg defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a custom request header.
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((String::from(name), String::from(value)));
+        self
+    }
+
+    /// Set the `User-Agent` header.
+    pub fn user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = Some(String::from(user_agent));
+        self
+    }
+
+    /// Set the `Referer` header.
+    pub fn referer(mut self, referer: &str) -> Self {
+        self.referer = Some(String::from(referer));
+        self
+    }
+
+    /// Add a cookie sent with requests to a given domain (including its
+    /// subdomains).
+    pub fn cookie(mut self, name: &str, value: &str, domain: &str) -> Self {
+        let cookie = format!("{}={}; path=/; domain={}", name, value, domain);
+
+        self.cookies.push(cookie);
+        self
+    }
+
+    /// Enable or disable verification of the server TLS certificate. FFmpeg
+    /// does not verify the certificates by default.
+    pub fn tls_verify(mut self, enabled: bool) -> Self {
+        self.tls_verify = Some(enabled);
+        self
+    }
+
+    /// Set a file with trusted CA certificates used for the TLS certificate
+    /// verification.
+    pub fn ca_file(mut self, path: &str) -> Self {
+        self.ca_file = Some(String::from(path));
+        self
+    }
+
+    /// Set a client TLS certificate file.
+    pub fn cert_file(mut self, path: &str) -> Self {
+        self.cert_file = Some(String::from(path));
+        self
+    }
+
+    /// Set a private key file of the client TLS certificate.
+    pub fn key_file(mut self, path: &str) -> Self {
+        self.key_file = Some(String::from(path));
+        self
+    }
+
+    /// Get the corresponding FFmpeg options.
+    pub(crate) fn to_options(&self) -> Result<Vec<(&'static str, String)>, Error> {
+        let mut res = Vec::new();
+
+        if !self.headers.is_empty() {
+            let mut headers = String::new();
+
+            for (name, value) in &self.headers {
+                if name.is_empty() || name.contains(|c| c == ':' || is_line_break(c)) {
+                    return Err(Error::new("invalid HTTP header name"));
+                } else if value.contains(is_line_break) {
+                    return Err(Error::new("invalid HTTP header value"));
+                }
+
+                headers.push_str(&format!("{}: {}\r\n", name, value));
+            }
+
+            res.push(("headers", headers));
+        }
+
+        let strings = [
+            ("user_agent", &self.user_agent),
+            ("referer", &self.referer),
+            ("ca_file", &self.ca_file),
+            ("cert_file", &self.cert_file),
+            ("key_file", &self.key_file),
+        ];
+
+        for (name, value) in strings.iter() {
+            if let Some(value) = value {
+                if value.contains(is_line_break) {
+                    return Err(Error::new(format!("invalid value of the {} option", name)));
+                }
+
+                res.push((*name, value.clone()));
+            }
+        }
+
+        if !self.cookies.is_empty() {
+            if self.cookies.iter().any(|c| c.contains(is_line_break)) {
+                return Err(Error::new("invalid HTTP cookie"));
+            }
+
+            res.push(("cookies", self.cookies.join("\n")));
+        }
+
+        if let Some(enabled) = self.tls_verify {
+            res.push(("tls_verify", String::from(if enabled { "1" } else { "0" })));
+        }
+
+        Ok(res)
+    }
+}
+
+/// Information about the HTTP response of an input.
+///
+/// Note that FFmpeg does not keep the status code and the full set of
+/// headers of successful responses. Failed requests can be recognized using
+/// `HttpError::from_error()`.
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    mime_type: Option<String>,
+    location: Option<String>,
+    cookies: Vec<String>,
+    icy_headers: Vec<(String, String)>,
+}
+
+impl HttpResponse {
+    /// Create a new response from given protocol option values.
+    pub(crate) fn new(
+        mime_type: Option<String>,
+        location: Option<String>,
+        cookies: Option<String>,
+        icy_headers: Option<String>,
+    ) -> Self {
+        let cookies = cookies
+            .as_deref()
+            .map(parse_lines)
+            .unwrap_or_default()
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        let icy_headers = icy_headers
+            .as_deref()
+            .map(parse_headers)
+            .unwrap_or_default();
+
+        Self {
+            mime_type: mime_type.filter(|v| !v.is_empty()),
+            location: location.filter(|v| !v.is_empty()),
+            cookies,
+            icy_headers,
+        }
+    }
+
+    /// Get the value of the `Content-Type` header.
+    pub fn mime_type(&self) -> Option<&str> {
+        self.mime_type.as_deref()
+    }
+
+    /// Get the final URL of the input (i.e. after following all redirects).
+    pub fn location(&self) -> Option<&str> {
+        self.location.as_deref()
+    }
+
+    /// Get all cookies (in the `Set-Cookie` format), i.e. the cookies set
+    /// using `HttpOptions` together with cookies received from the server.
+    pub fn cookies(&self) -> &[String] {
+        &self.cookies
+    }
+
+    /// Get `Icy-*` headers of the response (sent by Shoutcast/Icecast
+    /// servers).
+    pub fn icy_headers(&self) -> &[(String, String)] {
+        &self.icy_headers
+    }
+}
+
+/// HTTP error returned by FFmpeg.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum HttpError {
+    /// 400 Bad Request.
+    BadRequest,
+    /// 401 Unauthorized.
+    Unauthorized,
+    /// 403 Forbidden.
+    Forbidden,
+    /// 404 Not Found.
+    NotFound,
+    /// Other 4xx status code.
+    OtherClientError,
+    /// 5xx status code.
+    ServerError,
+}
+
+impl HttpError {
+    /// Get the HTTP error corresponding to a given error (if any).
+    pub fn from_error(error: &Error) -> Option<Self> {
+        let res = match error.raw_error_code()? {
+            AVERROR_HTTP_BAD_REQUEST => Self::BadRequest,
+            AVERROR_HTTP_UNAUTHORIZED => Self::Unauthorized,
+            AVERROR_HTTP_FORBIDDEN => Self::Forbidden,
+            AVERROR_HTTP_NOT_FOUND => Self::NotFound,
+            AVERROR_HTTP_OTHER_4XX => Self::OtherClientError,
+            AVERROR_HTTP_SERVER_ERROR => Self::ServerError,
+            _ => return None,
+        };
+
+        Some(res)
+    }
+
+    /// Get the HTTP status code if it is known exactly.
+    pub fn status_code(self) -> Option<u16> {
+        match self {
+            Self::BadRequest => Some(400),
+            Self::Unauthorized => Some(401),
+            Self::Forbidden => Some(403),
+            Self::NotFound => Some(404),
+            Self::OtherClientError | Self::ServerError => None,
+        }
+    }
+}
+
+const AVERROR_HTTP_BAD_REQUEST: i32 = crate::fferrtag(0xf8, b'4', b'0', b'0');
+const AVERROR_HTTP_UNAUTHORIZED: i32 = crate::fferrtag(0xf8, b'4', b'0', b'1');
+const AVERROR_HTTP_FORBIDDEN: i32 = crate::fferrtag(0xf8, b'4', b'0', b'3');
+const AVERROR_HTTP_NOT_FOUND: i32 = crate::fferrtag(0xf8, b'4', b'0', b'4');
+const AVERROR_HTTP_OTHER_4XX: i32 = crate::fferrtag(0xf8, b'4', b'X', b'X');
+const AVERROR_HTTP_SERVER_ERROR: i32 = crate::fferrtag(0xf8, b'5', b'X', b'X');
+
+/// Check if a given character is a line break.
+fn is_line_break(c: char) -> bool {
+    c == '\r' || c == '\n'
+}
+
+/// Split a given string into non-empty lines.
+fn parse_lines(s: &str) -> Vec<&str> {
+    s.split(is_line_break)
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// Parse given `Name: value` header lines.
+fn parse_headers(s: &str) -> Vec<(String, String)> {
+    parse_lines(s)
+        .into_iter()
+        .filter_map(|line| {
+            let (name, value) = line.split_once(':')?;
+
+            Some((String::from(name.trim()), String::from(value.trim())))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_headers, HttpOptions};
+
+    #[test]
+    fn test_options() {
+        let options = HttpOptions::new()
+            .header("Authorization", "Bearer abc")
+            .header("X-Foo", "bar")
+            .cookie("session", "123", "example.com")
+            .tls_verify(true)
+            .to_options()
+            .unwrap();
+
+        assert_eq!(
+            options,
+            vec![
+                (
+                    "headers",
+                    String::from("Authorization: Bearer abc\r\nX-Foo: bar\r\n")
+                ),
+                (
+                    "cookies",
+                    String::from("session=123; path=/; domain=example.com")
+                ),
+                ("tls_verify", String::from("1")),
+            ]
+        );
+
+        assert!(HttpOptions::new()
+            .header("X-Foo", "bar\r\nX-Injected: 1")
+            .to_options()
+            .is_err());
+    }
+
+    #[test]
+    fn test_header_parsing() {
+        let headers = parse_headers("icy-name: Radio\nicy-br:128\n\n");
+
+        assert_eq!(
+            headers,
+            vec![
+                (String::from("icy-name"), String::from("Radio")),
+                (String::from("icy-br"), String::from("128")),
+            ]
+        );
+    }
+}
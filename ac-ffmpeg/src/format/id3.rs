@@ -0,0 +1,450 @@
+//! Timed ID3 metadata.
+//!
+//! Timed ID3 metadata are carried in data streams with the `timed_id3`
+//! codec (e.g. in MPEG-TS and HLS). Each packet contains a single ID3v2
+//! tag.
+//!
+//! # Example
+//! ```text
+//! let mut builder = Muxer::builder();
+//!
+//! builder.add_stream(&video_params)?;
+//!
+//! let id3_stream = builder.add_stream(&OtherCodecParameters::data("timed_id3")?.into())?;
+//!
+//! let mut muxer = builder.build(io, OutputFormat::find_by_name("mpegts").unwrap())?;
+//!
+//! ...
+//!
+//! let tag = Id3Tag::new()
+//!     .with_frame(Id3Frame::text("TIT2", "Halftime"))
+//!     .with_frame(Id3Frame::user_text("event", "score-update"));
+//!
+//! muxer.push(tag.to_packet(pts)?.with_stream_index(id3_stream))?;
+//! ```
+
+use std::convert::TryInto;
+
+use crate::{
+    packet::{Packet, PacketMut},
+    time::Timestamp,
+    Error,
+};
+
+/// ID3 frame.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Id3Frame {
+    /// Text information frame (e.g. `TIT2`), except `TXXX`.
+    Text {
+        /// Frame ID.
+        id: [u8; 4],
+        /// Value.
+        value: String,
+    },
+    /// User defined text information frame (`TXXX`).
+    UserText {
+        /// Description.
+        description: String,
+        /// Value.
+        value: String,
+    },
+    /// Private frame (`PRIV`).
+    Private {
+        /// Owner identifier (e.g.
+        /// `com.apple.streaming.transportStreamTimestamp`).
+        owner: String,
+        /// Private data.
+        data: Vec<u8>,
+    },
+    /// Other frame in the raw form.
+    Other {
+        /// Frame ID.
+        id: [u8; 4],
+        /// Frame data.
+        data: Vec<u8>,
+    },
+}
+
+impl Id3Frame {
+    /// Create a new text information frame.
+    ///
+    /// # Panics
+    /// The method panics if the ID does not consist of four characters.
+    pub fn text(id: &str, value: &str) -> Self {
+        let id = id.as_bytes().try_into().expect("invalid ID3 frame ID");
+
+        Self::Text {
+            id,
+            value: String::from(value),
+        }
+    }
+
+    /// Create a new user defined text information frame.
+    pub fn user_text(description: &str, value: &str) -> Self {
+        Self::UserText {
+            description: String::from(description),
+            value: String::from(value),
+        }
+    }
+
+    /// Create a new private frame.
+    pub fn private(owner: &str, data: &[u8]) -> Self {
+        Self::Private {
+            owner: String::from(owner),
+            data: data.to_vec(),
+        }
+    }
+
+    /// Get the frame ID.
+    pub fn id(&self) -> [u8; 4] {
+        match self {
+            Self::Text { id, .. } => *id,
+            Self::UserText { .. } => *b"TXXX",
+            Self::Private { .. } => *b"PRIV",
+            Self::Other { id, .. } => *id,
+        }
+    }
+
+    /// Parse a frame with a given ID.
+    fn parse(id: [u8; 4], data: &[u8]) -> Result<Self, Error> {
+        let res = match &id {
+            b"TXXX" => {
+                let (encoding, data) = split_encoding(data)?;
+                let (description, data) = read_string(encoding, data)?;
+                let (value, _) = read_string(encoding, data)?;
+
+                Self::UserText { description, value }
+            }
+            b"PRIV" => {
+                let end = data
+                    .iter()
+                    .position(|&b| b == 0)
+                    .ok_or_else(|| Error::new("invalid ID3 private frame"))?;
+
+                Self::Private {
+                    owner: decode_latin1(&data[..end]),
+                    data: data[end + 1..].to_vec(),
+                }
+            }
+            [b'T', ..] => {
+                let (encoding, data) = split_encoding(data)?;
+                let (value, _) = read_string(encoding, data)?;
+
+                Self::Text { id, value }
+            }
+            _ => Self::Other {
+                id,
+                data: data.to_vec(),
+            },
+        };
+
+        Ok(res)
+    }
+
+    /// Get the frame data. Text is encoded using UTF-8.
+    fn data(&self) -> Vec<u8> {
+        let mut res = Vec::new();
+
+        match self {
+            Self::Text { value, .. } => {
+                res.push(ENCODING_UTF8);
+                res.extend_from_slice(value.as_bytes());
+            }
+            Self::UserText { description, value } => {
+                res.push(ENCODING_UTF8);
+                res.extend_from_slice(description.as_bytes());
+                res.push(0);
+                res.extend_from_slice(value.as_bytes());
+            }
+            Self::Private { owner, data } => {
+                res.extend_from_slice(owner.as_bytes());
+                res.push(0);
+                res.extend_from_slice(data);
+            }
+            Self::Other { data, .. } => res.extend_from_slice(data),
+        }
+
+        res
+    }
+}
+
+/// ID3v2 tag.
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct Id3Tag {
+    frames: Vec<Id3Frame>,
+}
+
+impl Id3Tag {
+    /// Create a new empty tag.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a given frame.
+    pub fn with_frame(mut self, frame: Id3Frame) -> Self {
+        self.frames.push(frame);
+        self
+    }
+
+    /// Get the frames.
+    pub fn frames(&self) -> &[Id3Frame] {
+        &self.frames
+    }
+
+    /// Take the frames.
+    pub fn into_frames(self) -> Vec<Id3Frame> {
+        self.frames
+    }
+
+    /// Parse a tag from the data of a given packet.
+    pub fn from_packet(packet: &Packet) -> Result<Self, Error> {
+        Self::from_bytes(packet.data())
+    }
+
+    /// Parse a given ID3v2.3 or ID3v2.4 tag. Unsynchronized tags are not
+    /// supported.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, Error> {
+        if data.len() < 10 || &data[..3] != b"ID3" {
+            return Err(Error::new("not an ID3v2 tag"));
+        }
+
+        let version = data[3];
+
+        if version != 3 && version != 4 {
+            return Err(Error::new("unsupported ID3v2 version"));
+        }
+
+        let flags = data[5];
+
+        if flags & 0x80 != 0 {
+            return Err(Error::new("unsynchronized ID3 tags are not supported"));
+        }
+
+        let size = read_synchsafe(&data[6..10])? as usize;
+
+        let mut data = data
+            .get(10..10 + size)
+            .ok_or_else(|| Error::new("truncated ID3 tag"))?;
+
+        // skip the extended header
+        if flags & 0x40 != 0 {
+            let size = data
+                .get(..4)
+                .ok_or_else(|| Error::new("truncated ID3 tag"))?;
+
+            let size = if version == 4 {
+                read_synchsafe(size)? as usize
+            } else {
+                read_u32(size) as usize + 4
+            };
+
+            data = data
+                .get(size..)
+                .ok_or_else(|| Error::new("truncated ID3 tag"))?;
+        }
+
+        let mut res = Self::new();
+
+        // the rest can be padding
+        while data.len() >= 10 && data[0] != 0 {
+            let mut id = [0; 4];
+
+            id.copy_from_slice(&data[..4]);
+
+            let size = if version == 4 {
+                read_synchsafe(&data[4..8])?
+            } else {
+                read_u32(&data[4..8])
+            };
+
+            let frame = data
+                .get(10..10 + size as usize)
+                .ok_or_else(|| Error::new("truncated ID3 frame"))?;
+
+            res.frames.push(Id3Frame::parse(id, frame)?);
+
+            data = &data[10 + size as usize..];
+        }
+
+        Ok(res)
+    }
+
+    /// Serialize the tag as an ID3v2.4 tag.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let mut frames = Vec::new();
+
+        for frame in &self.frames {
+            let data = frame.data();
+
+            frames.extend_from_slice(&frame.id());
+            frames.extend_from_slice(&write_synchsafe(data.len())?);
+            frames.extend_from_slice(&[0, 0]);
+            frames.extend_from_slice(&data);
+        }
+
+        let mut res = Vec::with_capacity(10 + frames.len());
+
+        res.extend_from_slice(b"ID3");
+        res.extend_from_slice(&[4, 0, 0]);
+        res.extend_from_slice(&write_synchsafe(frames.len())?);
+        res.extend_from_slice(&frames);
+
+        Ok(res)
+    }
+
+    /// Create a data packet containing the tag. The packet will have a
+    /// given presentation timestamp.
+    pub fn to_packet(&self, pts: Timestamp) -> Result<Packet, Error> {
+        let packet = PacketMut::from(self.to_bytes()?)
+            .with_time_base(pts.time_base())
+            .with_pts(pts)
+            .with_dts(pts)
+            .freeze();
+
+        Ok(packet)
+    }
+}
+
+const ENCODING_LATIN1: u8 = 0;
+const ENCODING_UTF16: u8 = 1;
+const ENCODING_UTF16BE: u8 = 2;
+const ENCODING_UTF8: u8 = 3;
+
+/// Split a given text frame into the encoding and the text.
+fn split_encoding(data: &[u8]) -> Result<(u8, &[u8]), Error> {
+    let (&encoding, data) = data
+        .split_first()
+        .ok_or_else(|| Error::new("invalid ID3 text frame"))?;
+
+    if encoding > ENCODING_UTF8 {
+        return Err(Error::new("unknown ID3 text encoding"));
+    }
+
+    Ok((encoding, data))
+}
+
+/// Read a (possibly null-terminated) string with a given encoding. The
+/// method returns the string and the remaining data.
+fn read_string(encoding: u8, data: &[u8]) -> Result<(String, &[u8]), Error> {
+    if encoding == ENCODING_UTF16 || encoding == ENCODING_UTF16BE {
+        let end = data
+            .chunks_exact(2)
+            .position(|c| c == [0, 0])
+            .map(|i| i * 2)
+            .unwrap_or(data.len() & !1);
+
+        let rest = data.get(end + 2..).unwrap_or(&[]);
+
+        let mut text = &data[..end];
+
+        let mut big_endian = encoding == ENCODING_UTF16BE;
+
+        if encoding == ENCODING_UTF16 && text.len() >= 2 {
+            big_endian = text[..2] == [0xfe, 0xff];
+
+            if text[..2] == [0xfe, 0xff] || text[..2] == [0xff, 0xfe] {
+                text = &text[2..];
+            }
+        }
+
+        let units = text.chunks_exact(2).map(|c| {
+            if big_endian {
+                u16::from_be_bytes([c[0], c[1]])
+            } else {
+                u16::from_le_bytes([c[0], c[1]])
+            }
+        });
+
+        let text = char::decode_utf16(units)
+            .collect::<Result<String, _>>()
+            .map_err(|_| Error::new("invalid ID3 UTF-16 text"))?;
+
+        Ok((text, rest))
+    } else {
+        let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+
+        let rest = data.get(end + 1..).unwrap_or(&[]);
+
+        let text = if encoding == ENCODING_LATIN1 {
+            decode_latin1(&data[..end])
+        } else {
+            String::from_utf8(data[..end].to_vec())
+                .map_err(|_| Error::new("invalid ID3 UTF-8 text"))?
+        };
+
+        Ok((text, rest))
+    }
+}
+
+/// Decode a given ISO-8859-1 string.
+fn decode_latin1(data: &[u8]) -> String {
+    data.iter().map(|&b| b as char).collect()
+}
+
+/// Read a big-endian 32-bit integer.
+fn read_u32(data: &[u8]) -> u32 {
+    u32::from_be_bytes([data[0], data[1], data[2], data[3]])
+}
+
+/// Read a given synchsafe integer.
+fn read_synchsafe(data: &[u8]) -> Result<u32, Error> {
+    if data.iter().any(|&b| b & 0x80 != 0) {
+        return Err(Error::new("invalid ID3 synchsafe integer"));
+    }
+
+    let res = data.iter().fold(0, |res, &b| (res << 7) | b as u32);
+
+    Ok(res)
+}
+
+/// Encode a given size as a synchsafe integer.
+fn write_synchsafe(size: usize) -> Result<[u8; 4], Error> {
+    if size >= 1 << 28 {
+        return Err(Error::new("ID3 tag too large"));
+    }
+
+    let res = [
+        (size >> 21) as u8 & 0x7f,
+        (size >> 14) as u8 & 0x7f,
+        (size >> 7) as u8 & 0x7f,
+        size as u8 & 0x7f,
+    ];
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Id3Frame, Id3Tag};
+
+    #[test]
+    fn test_roundtrip() {
+        let tag = Id3Tag::new()
+            .with_frame(Id3Frame::text("TIT2", "Příliš žluťoučký kůň"))
+            .with_frame(Id3Frame::user_text("event", "goal"))
+            .with_frame(Id3Frame::private(
+                "com.apple.streaming.transportStreamTimestamp",
+                &[0, 0, 0, 0, 0, 0, 0x23, 0x28],
+            ));
+
+        let data = tag.to_bytes().unwrap();
+
+        assert_eq!(&data[..10], b"ID3\x04\x00\x00\x00\x00\x00\x7c");
+        assert_eq!(Id3Tag::from_bytes(&data).unwrap(), tag);
+    }
+
+    #[test]
+    fn test_v23() {
+        // ID3v2.3 tag with a UTF-16 TXXX frame and padding
+        let mut data = b"ID3\x03\x00\x00\x00\x00\x00\x26".to_vec();
+
+        data.extend_from_slice(
+            b"TXXX\x00\x00\x00\x0b\x00\x00\x01\xff\xfea\x00\x00\x00\xff\xfeb\x00",
+        );
+        data.extend_from_slice(&[0; 17]);
+
+        let tag = Id3Tag::from_bytes(&data).unwrap();
+
+        assert_eq!(tag.frames(), &[Id3Frame::user_text("a", "b")]);
+    }
+}
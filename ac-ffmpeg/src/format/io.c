@@ -1,3 +1,4 @@
+#include <libavformat/avformat.h>
 #include <libavformat/avio.h>
 #include <libavutil/mem.h>
 
@@ -9,6 +10,15 @@ int ffw_io_is_avseek_size(int whence) {
     return whence & AVSEEK_SIZE;
 }
 
+int ffw_io_get_seek_whence(int whence) {
+    switch (whence & ~AVSEEK_FORCE) {
+        case SEEK_SET: return 0;
+        case SEEK_CUR: return 1;
+        case SEEK_END: return 2;
+        default: return -1;
+    }
+}
+
 AVIOContext * ffw_io_context_new(
     int buffer_size,
     int write_flag,
@@ -49,3 +59,11 @@ void ffw_io_context_free(AVIOContext* context) {
 
     avio_context_free(&context);
 }
+
+int64_t ffw_io_context_get_bytes_written(const AVIOContext* context) {
+#if LIBAVFORMAT_VERSION_MAJOR >= 60
+    return context->bytes_written;
+#else
+    return context->written;
+#endif
+}
@@ -14,6 +14,7 @@ type SeekCallback = extern "C" fn(opaque: *mut c_void, offset: i64, whence: c_in
 
 extern "C" {
     fn ffw_io_is_avseek_size(whence: c_int) -> c_int;
+    fn ffw_io_get_seek_whence(whence: c_int) -> c_int;
 
     fn ffw_io_context_new(
         buffer_size: c_int,
@@ -24,6 +25,7 @@ extern "C" {
         seek: Option<SeekCallback>,
     ) -> *mut c_void;
     fn ffw_io_context_free(context: *mut c_void);
+    fn ffw_io_context_get_bytes_written(context: *const c_void) -> i64;
 }
 
 /// IO context.
@@ -81,7 +83,12 @@ where
     let seek = if is_avseek_size {
         get_seekable_length(input)
     } else {
-        input.seek(SeekFrom::Start(offset as u64))
+        match unsafe { ffw_io_get_seek_whence(whence) } {
+            0 => input.seek(SeekFrom::Start(offset as u64)),
+            1 => input.seek(SeekFrom::Current(offset)),
+            2 => input.seek(SeekFrom::End(offset)),
+            _ => Err(io::Error::from(io::ErrorKind::InvalidInput)),
+        }
     };
 
     match seek {
@@ -93,6 +100,17 @@ where
     }
 }
 
+/// Convert a given IO error into an FFmpeg error code.
+pub(crate) fn get_error_code(err: &io::Error) -> c_int {
+    if let Some(code) = err.raw_os_error() {
+        unsafe { crate::ffw_error_from_posix(code as _) }
+    } else if err.kind() == io::ErrorKind::WouldBlock {
+        unsafe { crate::ffw_error_would_block() }
+    } else {
+        unsafe { crate::ffw_error_unknown() }
+    }
+}
+
 /// A ReadPacketCallback function for the IO.
 extern "C" fn io_read_packet<T>(opaque: *mut c_void, buffer: *mut u8, buffer_size: c_int) -> c_int
 where
@@ -112,15 +130,7 @@ where
                 unsafe { crate::ffw_error_eof() }
             }
         }
-        Err(err) => {
-            if let Some(code) = err.raw_os_error() {
-                unsafe { crate::ffw_error_from_posix(code as _) }
-            } else if err.kind() == io::ErrorKind::WouldBlock {
-                unsafe { crate::ffw_error_would_block() }
-            } else {
-                unsafe { crate::ffw_error_unknown() }
-            }
-        }
+        Err(err) => get_error_code(&err),
     }
 }
 
@@ -134,34 +144,22 @@ where
     let output = unsafe { &mut *output_ptr };
 
     if !buffer.is_null() && buffer_size > 0 {
-        let buffer = unsafe { slice::from_raw_parts(buffer, buffer_size as usize) };
-
-        match output.write(buffer) {
-            Ok(n) => {
-                if n > 0 {
-                    n as c_int
-                } else {
-                    unsafe { crate::ffw_error_eof() }
-                }
-            }
-            Err(err) => {
-                if let Some(code) = err.raw_os_error() {
-                    unsafe { crate::ffw_error_from_posix(code as _) }
-                } else if err.kind() == io::ErrorKind::WouldBlock {
-                    unsafe { crate::ffw_error_would_block() }
-                } else {
-                    unsafe { crate::ffw_error_unknown() }
-                }
+        let mut buffer = unsafe { slice::from_raw_parts(buffer, buffer_size as usize) };
+
+        // AVIO does not retry short writes, so we need to write everything
+        // here (otherwise the remaining data would be lost)
+        while !buffer.is_empty() {
+            match output.write(buffer) {
+                Ok(0) => return unsafe { crate::ffw_error_eof() },
+                Ok(n) => buffer = &buffer[n..],
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+                Err(err) => return get_error_code(&err),
             }
         }
+
+        buffer_size
     } else if let Err(err) = output.flush() {
-        if let Some(code) = err.raw_os_error() {
-            unsafe { crate::ffw_error_from_posix(code) }
-        } else if err.kind() == io::ErrorKind::WouldBlock {
-            unsafe { crate::ffw_error_would_block() }
-        } else {
-            unsafe { crate::ffw_error_unknown() }
-        }
+        get_error_code(&err)
     } else {
         0
     }
@@ -225,6 +223,16 @@ impl<T> IO<T> {
         self.io_context.as_mut().unwrap()
     }
 
+    /// Get the number of bytes written into the underlying stream so far.
+    /// Data still buffered in the IO context are not included.
+    pub fn bytes_written(&self) -> u64 {
+        self.io_context
+            .as_ref()
+            .map(|context| unsafe { ffw_io_context_get_bytes_written(context.ptr) })
+            .unwrap_or(0)
+            .max(0) as u64
+    }
+
     /// Get reference to the underlying stream.
     pub fn stream(&self) -> &T {
         self.stream.as_ref()
@@ -275,7 +283,16 @@ impl<T> IO<T>
 where
     T: Write + Seek,
 {
-    /// Create a new IO from a given stream.
+    /// Create a new IO from a given seekable stream.
+    ///
+    /// Seekable output is required by muxers that need to rewrite already
+    /// written data (e.g. the non-fragmented MP4 and MOV muxers updating
+    /// their headers once all packets are written). Use e.g. a `File` or a
+    /// `Cursor<Vec<u8>>` for writing into memory. The stream can be taken
+    /// back from the IO returned by `Muxer::close()`.
+    ///
+    /// Note that the `faststart` flag of the MP4/MOV muxers is not
+    /// supported with custom IO as it requires reading the output back.
     pub fn from_seekable_write_stream(stream: T) -> Self {
         Self::new(stream, None, Some(io_write_packet::<T>), Some(io_seek::<T>))
     }
@@ -310,3 +327,99 @@ impl Write for MemWriter {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{self, Cursor, Write},
+        os::raw::c_void,
+    };
+
+    use super::{io_seek, io_write_packet};
+
+    /// Writer accepting at most a given number of bytes per call. Every
+    /// other call is interrupted.
+    struct ShortWriter {
+        data: Vec<u8>,
+        max_len: usize,
+        interrupt: bool,
+    }
+
+    impl Write for ShortWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.interrupt = !self.interrupt;
+
+            if self.interrupt {
+                return Err(io::Error::from(io::ErrorKind::Interrupted));
+            }
+
+            let len = buf.len().min(self.max_len);
+
+            self.data.extend_from_slice(&buf[..len]);
+
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Seek in a given cursor using the IO callback.
+    fn seek(cursor: &mut Cursor<Vec<u8>>, offset: i64, whence: i32) -> i64 {
+        io_seek::<Cursor<Vec<u8>>>(cursor as *mut _ as *mut c_void, offset, whence)
+    }
+
+    #[test]
+    fn test_seek() {
+        let mut cursor = Cursor::new(vec![0; 100]);
+
+        // SEEK_SET, SEEK_CUR and SEEK_END
+        assert_eq!(seek(&mut cursor, 10, 0), 10);
+        assert_eq!(seek(&mut cursor, 20, 1), 30);
+        assert_eq!(seek(&mut cursor, -5, 1), 25);
+        assert_eq!(seek(&mut cursor, -10, 2), 90);
+        assert_eq!(seek(&mut cursor, 0, 2), 100);
+
+        // AVSEEK_SIZE does not change the position
+        assert_eq!(seek(&mut cursor, 0, 1), 100);
+        assert_eq!(seek(&mut cursor, 40, 0), 40);
+        assert_eq!(seek(&mut cursor, 0, 0x10000), 100);
+        assert_eq!(cursor.position(), 40);
+
+        // invalid seeks
+        assert!(seek(&mut cursor, -50, 0) < 0);
+        assert!(seek(&mut cursor, 0, 3) < 0);
+    }
+
+    #[test]
+    fn test_short_writes() {
+        let mut writer = ShortWriter {
+            data: Vec::new(),
+            max_len: 3,
+            interrupt: false,
+        };
+
+        let mut data = (0..100).collect::<Vec<u8>>();
+
+        let res = io_write_packet::<ShortWriter>(
+            &mut writer as *mut _ as *mut c_void,
+            data.as_mut_ptr(),
+            data.len() as _,
+        );
+
+        assert_eq!(res, 100);
+        assert_eq!(writer.data, data);
+
+        // a writer that does not accept any data
+        writer.max_len = 0;
+
+        let res = io_write_packet::<ShortWriter>(
+            &mut writer as *mut _ as *mut c_void,
+            data.as_mut_ptr(),
+            data.len() as _,
+        );
+
+        assert!(res < 0);
+    }
+}
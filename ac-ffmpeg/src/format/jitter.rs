@@ -0,0 +1,377 @@
+//! Jitter buffer for live inputs.
+
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    time::{Duration, Instant},
+};
+
+use crate::packet::Packet;
+
+/// Policy for packets arriving after their scheduled release time.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum LatePacketPolicy {
+    /// Drop late packets.
+    Drop,
+    /// Release late packets immediately.
+    Pass,
+    /// Release late packets immediately and re-synchronize the buffer clock,
+    /// so that the following packets are delayed by the target delay
+    /// relative to the late packet. This effectively increases the delay of
+    /// the whole stream.
+    Resync,
+}
+
+/// Builder for the jitter buffer.
+pub struct JitterBufferBuilder {
+    target_delay: Duration,
+    max_delay: Duration,
+    late_packet_policy: LatePacketPolicy,
+}
+
+impl JitterBufferBuilder {
+    /// Create a new builder.
+    fn new() -> Self {
+        Self {
+            target_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(2),
+            late_packet_policy: LatePacketPolicy::Resync,
+        }
+    }
+
+    /// Set the target delay, i.e. the time reserve for packets delayed by
+    /// the network. The default is 200 ms.
+    pub fn target_delay(mut self, delay: Duration) -> Self {
+        self.target_delay = delay;
+        self
+    }
+
+    /// Set the maximum delay. Packets that would have to be buffered for
+    /// longer than this or packets that are late by more than this are
+    /// considered to be timestamp discontinuities and the buffer clock is
+    /// re-synchronized. The default is 2 seconds.
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// Set the late packet policy. The default is
+    /// `LatePacketPolicy::Resync`.
+    pub fn late_packet_policy(mut self, policy: LatePacketPolicy) -> Self {
+        self.late_packet_policy = policy;
+        self
+    }
+
+    /// Build the jitter buffer.
+    pub fn build(self) -> JitterBuffer {
+        JitterBuffer {
+            target_delay: self.target_delay.as_micros() as i64,
+            max_delay: self.max_delay.as_micros() as i64,
+            late_packet_policy: self.late_packet_policy,
+            epoch: None,
+            offset: None,
+            sequence: 0,
+            queue: BinaryHeap::new(),
+            late_packets: 0,
+            dropped_packets: 0,
+        }
+    }
+}
+
+/// Buffered packet.
+struct Entry {
+    deadline: i64,
+    sequence: u64,
+    packet: Packet,
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // reversed in order to get a min-heap
+        (other.deadline, other.sequence).cmp(&(self.deadline, self.sequence))
+    }
+}
+
+/// Jitter buffer. It is meant to be put between a demuxer of a live network
+/// input (e.g. RTSP or SRT) and decoders. The buffer absorbs bursts and
+/// delay variations of the incoming packets and releases them in a steady
+/// pace given by their timestamps delayed by the target delay.
+///
+/// # Usage
+/// 1. Push packets as they arrive from the demuxer.
+/// 2. Take all packets that are ready to be released and pass them to
+///    decoders. Use the `next_release()` method to find out when the next
+///    packet will be ready.
+/// 3. Call `flush()` at the end of the stream and take the remaining
+///    packets.
+///
+/// Packets are released in decoding order. Packets without a timestamp are
+/// released as soon as possible.
+pub struct JitterBuffer {
+    target_delay: i64,
+    max_delay: i64,
+    late_packet_policy: LatePacketPolicy,
+    epoch: Option<Instant>,
+    offset: Option<i64>,
+    sequence: u64,
+    queue: BinaryHeap<Entry>,
+    late_packets: u64,
+    dropped_packets: u64,
+}
+
+impl JitterBuffer {
+    /// Get a builder for the jitter buffer.
+    pub fn builder() -> JitterBufferBuilder {
+        JitterBufferBuilder::new()
+    }
+
+    /// Create a new jitter buffer with the default settings.
+    pub fn new() -> Self {
+        Self::builder().build()
+    }
+
+    /// Push a given packet. The method returns false if the packet was
+    /// dropped.
+    pub fn push(&mut self, packet: Packet) -> bool {
+        self.push_at(packet, Instant::now())
+    }
+
+    /// Push a given packet that arrived at a given time. The method returns
+    /// false if the packet was dropped.
+    pub fn push_at(&mut self, packet: Packet, now: Instant) -> bool {
+        let now = self.micros(now);
+
+        let timestamp = packet
+            .dts()
+            .as_micros()
+            .or_else(|| packet.pts().as_micros());
+
+        let deadline = if let Some(timestamp) = timestamp {
+            let offset = *self.offset.get_or_insert(now - timestamp);
+
+            let deadline = timestamp + offset + self.target_delay;
+
+            let lateness = now - deadline;
+
+            if lateness > self.max_delay || (deadline - now) > self.max_delay {
+                // timestamp discontinuity
+                self.offset = Some(now - timestamp);
+
+                now + self.target_delay
+            } else if lateness > 0 {
+                self.late_packets += 1;
+
+                match self.late_packet_policy {
+                    LatePacketPolicy::Drop => {
+                        self.dropped_packets += 1;
+
+                        return false;
+                    }
+                    LatePacketPolicy::Pass => now,
+                    LatePacketPolicy::Resync => {
+                        self.offset = Some(offset + lateness);
+
+                        now
+                    }
+                }
+            } else {
+                deadline
+            }
+        } else {
+            now
+        };
+
+        self.sequence += 1;
+
+        self.queue.push(Entry {
+            deadline,
+            sequence: self.sequence,
+            packet,
+        });
+
+        true
+    }
+
+    /// Take the next packet that is ready to be released.
+    pub fn take(&mut self) -> Option<Packet> {
+        self.take_at(Instant::now())
+    }
+
+    /// Take the next packet that is ready to be released at a given time.
+    pub fn take_at(&mut self, now: Instant) -> Option<Packet> {
+        let now = self.micros(now);
+
+        if self.queue.peek()?.deadline > now {
+            return None;
+        }
+
+        self.queue.pop().map(|entry| entry.packet)
+    }
+
+    /// Get the time when the next packet will be ready to be released (if
+    /// any).
+    pub fn next_release(&self) -> Option<Instant> {
+        let epoch = self.epoch?;
+        let deadline = self.queue.peek()?.deadline;
+
+        Some(epoch + Duration::from_micros(deadline.max(0) as u64))
+    }
+
+    /// Release all buffered packets immediately.
+    pub fn flush(&mut self) {
+        let entries = std::mem::take(&mut self.queue).into_sorted_vec();
+
+        // the vector is sorted in the reversed order
+        for (sequence, mut entry) in entries.into_iter().rev().enumerate() {
+            entry.deadline = i64::MIN;
+            entry.sequence = sequence as u64;
+
+            self.queue.push(entry);
+        }
+    }
+
+    /// Drop all buffered packets and reset the buffer clock.
+    pub fn reset(&mut self) {
+        self.queue.clear();
+        self.offset = None;
+    }
+
+    /// Get the number of buffered packets.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Check if the buffer is empty.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Get the number of packets that arrived after their scheduled release
+    /// time.
+    pub fn late_packets(&self) -> u64 {
+        self.late_packets
+    }
+
+    /// Get the number of dropped packets.
+    pub fn dropped_packets(&self) -> u64 {
+        self.dropped_packets
+    }
+
+    /// Convert a given instant into microseconds since the buffer epoch.
+    fn micros(&mut self, instant: Instant) -> i64 {
+        let epoch = *self.epoch.get_or_insert(instant);
+
+        if instant >= epoch {
+            instant.duration_since(epoch).as_micros() as i64
+        } else {
+            -(epoch.duration_since(instant).as_micros() as i64)
+        }
+    }
+}
+
+impl Default for JitterBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{JitterBuffer, LatePacketPolicy};
+
+    use crate::{test_util::packet, time::TimeBase};
+
+    #[test]
+    fn test_burst() {
+        let mut buffer = JitterBuffer::builder()
+            .target_delay(Duration::from_millis(100))
+            .build();
+
+        let start = Instant::now();
+
+        // three packets 40 ms apart arriving at once
+        for i in 0..3 {
+            assert!(buffer.push_at(
+                packet(0, TimeBase::MICROSECONDS, i * 40_000).freeze(),
+                start
+            ));
+        }
+
+        assert!(buffer.take_at(start).is_none());
+        assert_eq!(
+            buffer.next_release(),
+            Some(start + Duration::from_millis(100))
+        );
+
+        let t = start + Duration::from_millis(100);
+
+        assert_eq!(buffer.take_at(t).map(|p| p.dts().timestamp()), Some(0));
+        assert!(buffer.take_at(t).is_none());
+
+        let t = start + Duration::from_millis(180);
+
+        assert!(buffer.take_at(t).is_some());
+        assert!(buffer.take_at(t).is_some());
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_late_packets() {
+        let mut buffer = JitterBuffer::builder()
+            .target_delay(Duration::from_millis(100))
+            .late_packet_policy(LatePacketPolicy::Drop)
+            .build();
+
+        let start = Instant::now();
+
+        assert!(buffer.push_at(packet(0, TimeBase::MICROSECONDS, 0).freeze(), start));
+        assert!(!buffer.push_at(
+            packet(0, TimeBase::MICROSECONDS, 40_000).freeze(),
+            start + Duration::from_millis(150)
+        ));
+        assert!(buffer.push_at(
+            packet(0, TimeBase::MICROSECONDS, 80_000).freeze(),
+            start + Duration::from_millis(150)
+        ));
+
+        assert_eq!(buffer.late_packets(), 1);
+        assert_eq!(buffer.dropped_packets(), 1);
+        assert_eq!(buffer.len(), 2);
+    }
+
+    #[test]
+    fn test_flush() {
+        let mut buffer = JitterBuffer::new();
+
+        let start = Instant::now();
+
+        buffer.push_at(packet(0, TimeBase::MICROSECONDS, 40_000).freeze(), start);
+        buffer.push_at(packet(0, TimeBase::MICROSECONDS, 0).freeze(), start);
+
+        assert!(buffer.take_at(start).is_none());
+
+        buffer.flush();
+
+        assert_eq!(buffer.take_at(start).map(|p| p.dts().timestamp()), Some(0));
+        assert_eq!(
+            buffer.take_at(start).map(|p| p.dts().timestamp()),
+            Some(40_000)
+        );
+    }
+}
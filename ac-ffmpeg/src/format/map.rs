@@ -0,0 +1,428 @@
+//! Stream mapping (similar to the `-map` option of the `ffmpeg` tool).
+//!
+//! # Example
+//! ```text
+//! // first video stream + all English audio streams
+//! let map = StreamMap::new()
+//!     .select(StreamSelector::video().first())
+//!     .select(StreamSelector::audio().language("eng"))
+//!     .exclude(StreamSelector::all().disposition(Disposition::COMMENT));
+//!
+//! let mut builder = Muxer::builder();
+//!
+//! let mapping = map.apply(demuxer.streams(), &mut builder)?;
+//!
+//! ...
+//!
+//! while let Some(packet) = demuxer.take()? {
+//!     for &index in mapping.outputs(packet.stream_index()) {
+//!         muxer.push(packet.clone().with_stream_index(index))?;
+//!     }
+//! }
+//! ```
+
+use std::collections::HashMap;
+
+use crate::{
+    codec::MediaType,
+    format::{
+        extract::reset_codec_tag,
+        muxer::MuxerBuilder,
+        preserve::PreservationPolicy,
+        stream::{Disposition, Stream},
+    },
+    Error,
+};
+
+/// Properties of a stream relevant for the stream selection.
+struct StreamInfo {
+    media_type: MediaType,
+    disposition: Disposition,
+    metadata: HashMap<String, String>,
+}
+
+impl From<&Stream> for StreamInfo {
+    fn from(stream: &Stream) -> Self {
+        let metadata = stream
+            .metadata_dict()
+            .into_iter()
+            .map(|(k, v)| (k.to_lowercase(), String::from(v)))
+            .collect();
+
+        Self {
+            media_type: stream.codec_parameters().media_type(),
+            disposition: stream.disposition(),
+            metadata,
+        }
+    }
+}
+
+/// Stream selector. All conditions of the selector must be met in order to
+/// match a stream.
+#[derive(Debug, Clone)]
+pub struct StreamSelector {
+    index: Option<usize>,
+    media_type: Option<MediaType>,
+    disposition: Option<Disposition>,
+    metadata: Vec<(String, String)>,
+    nth: Option<usize>,
+    optional: bool,
+}
+
+impl StreamSelector {
+    /// Create a new selector matching all streams.
+    pub fn all() -> Self {
+        Self {
+            index: None,
+            media_type: None,
+            disposition: None,
+            metadata: Vec::new(),
+            nth: None,
+            optional: false,
+        }
+    }
+
+    /// Create a new selector matching a stream with a given index.
+    pub fn index(index: usize) -> Self {
+        let mut res = Self::all();
+
+        res.index = Some(index);
+        res
+    }
+
+    /// Create a new selector matching all streams of a given media type.
+    pub fn media_type(media_type: MediaType) -> Self {
+        let mut res = Self::all();
+
+        res.media_type = Some(media_type);
+        res
+    }
+
+    /// Create a new selector matching all video streams.
+    pub fn video() -> Self {
+        Self::media_type(MediaType::Video)
+    }
+
+    /// Create a new selector matching all audio streams.
+    pub fn audio() -> Self {
+        Self::media_type(MediaType::Audio)
+    }
+
+    /// Create a new selector matching all subtitle streams.
+    pub fn subtitle() -> Self {
+        Self::media_type(MediaType::Subtitle)
+    }
+
+    /// Create a new selector matching all data streams.
+    pub fn data() -> Self {
+        Self::media_type(MediaType::Data)
+    }
+
+    /// Match only streams with a given language (compared
+    /// case-insensitively with the `language` metadata entry).
+    pub fn language(self, language: &str) -> Self {
+        self.metadata("language", language)
+    }
+
+    /// Match only streams with a given metadata entry. The key and the
+    /// value are compared case-insensitively.
+    pub fn metadata(mut self, key: &str, value: &str) -> Self {
+        self.metadata
+            .push((key.to_lowercase(), value.to_lowercase()));
+        self
+    }
+
+    /// Match only streams having all given disposition flags.
+    pub fn disposition(mut self, disposition: Disposition) -> Self {
+        let current = self.disposition.unwrap_or_else(Disposition::empty);
+
+        self.disposition = Some(current | disposition);
+        self
+    }
+
+    /// Match only the n-th (starting from zero) stream out of the streams
+    /// meeting all other conditions.
+    pub fn nth(mut self, n: usize) -> Self {
+        self.nth = Some(n);
+        self
+    }
+
+    /// Match only the first stream out of the streams meeting all other
+    /// conditions.
+    pub fn first(self) -> Self {
+        self.nth(0)
+    }
+
+    /// Do not fail if the selector does not match any stream.
+    pub fn optional(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+
+    /// Check if the selector is optional.
+    pub fn is_optional(&self) -> bool {
+        self.optional
+    }
+
+    /// Get indices of streams matching the selector.
+    pub fn matches(&self, streams: &[Stream]) -> Vec<usize> {
+        let streams = streams.iter().map(StreamInfo::from).collect::<Vec<_>>();
+
+        self.find(&streams)
+    }
+
+    /// Get indices of given streams matching the selector.
+    fn find(&self, streams: &[StreamInfo]) -> Vec<usize> {
+        let mut res = streams
+            .iter()
+            .enumerate()
+            .filter(|(index, stream)| self.matches_stream(*index, stream))
+            .map(|(index, _)| index);
+
+        if let Some(n) = self.nth {
+            res.nth(n).into_iter().collect()
+        } else {
+            res.collect()
+        }
+    }
+
+    /// Check if a given stream meets all conditions of the selector
+    /// (except the n-th stream condition).
+    fn matches_stream(&self, index: usize, stream: &StreamInfo) -> bool {
+        if let Some(expected) = self.index {
+            if index != expected {
+                return false;
+            }
+        }
+
+        if let Some(media_type) = self.media_type {
+            if stream.media_type != media_type {
+                return false;
+            }
+        }
+
+        if let Some(disposition) = self.disposition {
+            if !stream.disposition.contains(disposition) {
+                return false;
+            }
+        }
+
+        self.metadata.iter().all(|(key, value)| {
+            stream
+                .metadata
+                .get(key)
+                .map(|v| v.to_lowercase() == *value)
+                .unwrap_or(false)
+        })
+    }
+}
+
+/// Stream map. It consists of selectors choosing input streams and
+/// selectors excluding them. Output streams are created in the order of
+/// the selectors, streams matched by a single selector keep their relative
+/// order. A stream matched by multiple selectors is mapped multiple times.
+#[derive(Debug, Clone)]
+pub struct StreamMap {
+    selectors: Vec<StreamSelector>,
+    exclusions: Vec<StreamSelector>,
+    preservation: PreservationPolicy,
+}
+
+impl StreamMap {
+    /// Create a new empty stream map.
+    pub fn new() -> Self {
+        Self {
+            selectors: Vec::new(),
+            exclusions: Vec::new(),
+            preservation: PreservationPolicy::default(),
+        }
+    }
+
+    /// Map streams matching a given selector.
+    pub fn select(mut self, selector: StreamSelector) -> Self {
+        self.selectors.push(selector);
+        self
+    }
+
+    /// Do not map streams matching a given selector (regardless of the
+    /// order of selectors and exclusions).
+    pub fn exclude(mut self, selector: StreamSelector) -> Self {
+        self.exclusions.push(selector);
+        self
+    }
+
+    /// Set the policy for propagating metadata, side data and dispositions
+    /// into the output streams. Everything is preserved by default.
+    pub fn preservation(mut self, policy: PreservationPolicy) -> Self {
+        self.preservation = policy;
+        self
+    }
+
+    /// Check if the map is empty (i.e. it does not contain any selectors).
+    pub fn is_empty(&self) -> bool {
+        self.selectors.is_empty()
+    }
+
+    /// Get indices of input streams in the order of output streams. The
+    /// method fails if a non-optional selector does not match any stream.
+    pub fn resolve(&self, streams: &[Stream]) -> Result<Vec<usize>, Error> {
+        let streams = streams.iter().map(StreamInfo::from).collect::<Vec<_>>();
+
+        self.resolve_streams(&streams)
+    }
+
+    /// Add the mapped input streams to a given muxer builder. Codec tags of
+    /// the input streams are reset, so that the muxer can choose tags valid
+    /// in the output format.
+    pub fn apply(
+        &self,
+        streams: &[Stream],
+        builder: &mut MuxerBuilder,
+    ) -> Result<StreamMapping, Error> {
+        let mut mapping = StreamMapping::new();
+
+        for input_index in self.resolve(streams)? {
+            let stream = &streams[input_index];
+
+            let output_index = builder.add_stream(&reset_codec_tag(stream.codec_parameters()))?;
+
+            self.preservation
+                .apply_stream(stream, &mut builder.streams_mut()[output_index])?;
+
+            mapping.add(input_index, output_index);
+        }
+
+        Ok(mapping)
+    }
+
+    /// Get indices of given input streams in the order of output streams.
+    fn resolve_streams(&self, streams: &[StreamInfo]) -> Result<Vec<usize>, Error> {
+        let excluded = self
+            .exclusions
+            .iter()
+            .flat_map(|selector| selector.find(streams))
+            .collect::<Vec<_>>();
+
+        let mut res = Vec::new();
+
+        for selector in &self.selectors {
+            let selected = selector.find(streams);
+
+            if selected.is_empty() && !selector.optional {
+                return Err(Error::new("stream selector does not match any stream"));
+            }
+
+            res.extend(
+                selected
+                    .into_iter()
+                    .filter(|index| !excluded.contains(index)),
+            );
+        }
+
+        Ok(res)
+    }
+}
+
+impl Default for StreamMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Mapping between input and output stream indices.
+#[derive(Debug, Clone, Default)]
+pub struct StreamMapping {
+    inputs: Vec<usize>,
+    outputs: HashMap<usize, Vec<usize>>,
+}
+
+impl StreamMapping {
+    /// Create a new empty mapping.
+    fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a given pair of input and output streams.
+    fn add(&mut self, input: usize, output: usize) {
+        self.inputs.push(input);
+        self.outputs.entry(input).or_default().push(output);
+    }
+
+    /// Get indices of output streams created from a given input stream.
+    pub fn outputs(&self, input: usize) -> &[usize] {
+        self.outputs.get(&input).map(|v| &v[..]).unwrap_or(&[])
+    }
+
+    /// Get index of the input stream of a given output stream.
+    pub fn input(&self, output: usize) -> Option<usize> {
+        self.inputs.get(output).copied()
+    }
+
+    /// Check if a given input stream is mapped.
+    pub fn is_mapped(&self, input: usize) -> bool {
+        self.outputs.contains_key(&input)
+    }
+
+    /// Get the number of output streams.
+    pub fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    /// Check if there are no output streams.
+    pub fn is_empty(&self) -> bool {
+        self.inputs.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{StreamInfo, StreamMap, StreamSelector};
+
+    use crate::{codec::MediaType, format::stream::Disposition};
+
+    fn stream(media_type: MediaType, language: &str, disposition: Disposition) -> StreamInfo {
+        let mut metadata = HashMap::new();
+
+        metadata.insert(String::from("language"), String::from(language));
+
+        StreamInfo {
+            media_type,
+            disposition,
+            metadata,
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        let streams = [
+            stream(MediaType::Audio, "eng", Disposition::DEFAULT),
+            stream(MediaType::Video, "und", Disposition::DEFAULT),
+            stream(MediaType::Video, "und", Disposition::empty()),
+            stream(MediaType::Audio, "ger", Disposition::empty()),
+            stream(MediaType::Audio, "ENG", Disposition::COMMENT),
+            stream(MediaType::Subtitle, "eng", Disposition::empty()),
+        ];
+
+        let map = StreamMap::new()
+            .select(StreamSelector::video().first())
+            .select(StreamSelector::audio().language("eng"));
+
+        assert_eq!(map.resolve_streams(&streams).unwrap(), vec![1, 0, 4]);
+
+        let map = map.exclude(StreamSelector::all().disposition(Disposition::COMMENT));
+
+        assert_eq!(map.resolve_streams(&streams).unwrap(), vec![1, 0]);
+
+        let map = StreamMap::new()
+            .select(StreamSelector::index(5))
+            .select(StreamSelector::data().optional());
+
+        assert_eq!(map.resolve_streams(&streams).unwrap(), vec![5]);
+
+        let map = StreamMap::new().select(StreamSelector::video().nth(2));
+
+        assert!(map.resolve_streams(&streams).is_err());
+    }
+}
@@ -0,0 +1,142 @@
+//! In-memory muxer output.
+
+use std::io::Cursor;
+
+use crate::{
+    format::{
+        io::IO,
+        muxer::{Muxer, MuxerBuilder, OutputFormat},
+    },
+    packet::Packet,
+    Error,
+};
+
+/// Muxer collecting its output into a growable in-memory buffer. The
+/// buffer is seekable, so any output format can be used (including formats
+/// that rewrite their headers at the end, e.g. non-fragmented MP4).
+///
+/// # Example
+/// ```text
+/// let mut builder = Muxer::builder();
+///
+/// builder.add_stream(&encoder.codec_parameters().into())?;
+///
+/// let mut muxer = MemoryMuxer::new(builder, OutputFormat::find_by_name("mp4").unwrap())?;
+///
+/// while let Some(packet) = encoder.take()? {
+///     muxer.push(packet.with_stream_index(0))?;
+/// }
+///
+/// let mp4 = muxer.finish()?;
+/// ```
+pub struct MemoryMuxer {
+    muxer: Muxer<Cursor<Vec<u8>>>,
+}
+
+impl MemoryMuxer {
+    /// Create a new in-memory muxer using a given muxer builder and output
+    /// format.
+    pub fn new(builder: MuxerBuilder, format: OutputFormat) -> Result<Self, Error> {
+        let io = IO::from_seekable_write_stream(Cursor::new(Vec::new()));
+
+        let res = Self {
+            muxer: builder.build(io, format)?,
+        };
+
+        Ok(res)
+    }
+
+    /// Push a given packet to the muxer.
+    pub fn push(&mut self, packet: Packet) -> Result<(), Error> {
+        self.muxer.push(packet)
+    }
+
+    /// Flush the muxer.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.muxer.flush()
+    }
+
+    /// Get the output written so far. Data still buffered by the muxer are
+    /// not included and already written data may be still rewritten by the
+    /// muxer.
+    pub fn data(&self) -> &[u8] {
+        self.muxer.io().stream().get_ref()
+    }
+
+    /// Get the underlying muxer.
+    pub fn muxer(&self) -> &Muxer<Cursor<Vec<u8>>> {
+        &self.muxer
+    }
+
+    /// Finish the muxer and take the complete output.
+    pub fn finish(self) -> Result<Vec<u8>, Error> {
+        let cursor = self.muxer.finish()?;
+
+        Ok(cursor.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::MemoryMuxer;
+
+    use crate::{
+        format::{
+            demuxer::Demuxer,
+            extract::reset_codec_tag,
+            io::IO,
+            muxer::{Muxer, OutputFormat},
+        },
+        test_util::encode_video,
+    };
+
+    #[test]
+    fn test_memory_muxer() {
+        let data = encode_video(64, 48, 10, 5);
+
+        let mut demuxer = Demuxer::builder()
+            .build(IO::from_read_stream(Cursor::new(data)))
+            .unwrap()
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)
+            .unwrap();
+
+        let mut builder = Muxer::builder();
+
+        builder
+            .add_stream(&reset_codec_tag(demuxer.streams()[0].codec_parameters()))
+            .unwrap();
+
+        // non-fragmented MP4 needs a seekable output
+        let mut muxer =
+            MemoryMuxer::new(builder, OutputFormat::find_by_name("mp4").unwrap()).unwrap();
+
+        while let Some(packet) = demuxer.take().unwrap() {
+            muxer.push(packet).unwrap();
+        }
+
+        muxer.flush().unwrap();
+
+        assert!(!muxer.data().is_empty());
+        assert!(muxer.muxer().stream_time_base(0).is_some());
+
+        let output = muxer.finish().unwrap();
+
+        let mut demuxer = Demuxer::builder()
+            .build(IO::from_read_stream(Cursor::new(output)))
+            .unwrap()
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)
+            .unwrap();
+
+        let mut packets = 0;
+
+        while demuxer.take().unwrap().is_some() {
+            packets += 1;
+        }
+
+        assert_eq!(packets, 10);
+    }
+}
@@ -1,6 +1,38 @@
 //! Media container handling.
 
+pub mod chapter;
+pub mod clock;
+pub mod concat;
+pub mod cursor;
+pub mod cut;
+pub mod dash;
 pub mod demuxer;
+pub mod encryption;
+pub mod extract;
+pub mod hls;
+pub mod http;
+pub mod id3;
 pub mod io;
+pub mod jitter;
+pub mod map;
+pub mod memory;
+pub mod mpegts;
 pub mod muxer;
+pub mod pacing;
+pub mod preroll;
+pub mod preserve;
+pub mod probe;
+pub mod protocol;
+pub mod recorder;
+pub mod remux;
+pub mod repair;
+pub mod rtp;
+pub mod scte35;
+pub mod sdp;
+pub mod segment;
+pub mod still;
 pub mod stream;
+pub mod track;
+pub mod watchdog;
+
+pub use self::probe::{probe, probe_bytes};
@@ -0,0 +1,121 @@
+//! MPEG-TS muxer options.
+
+use std::time::Duration;
+
+use crate::format::muxer::MuxerBuilder;
+
+/// Typed options of the MPEG-TS muxer. Options that are not set keep the
+/// FFmpeg defaults.
+///
+/// # Example
+/// ```text
+/// let options = MpegTsOptions::new()
+///     .transport_stream_id(0x0101)
+///     .service_id(0x0001)
+///     .pat_period(Duration::from_millis(100))
+///     .pcr_period(Duration::from_millis(20));
+///
+/// let builder = options.apply(Muxer::builder());
+/// ```
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct MpegTsOptions {
+    transport_stream_id: Option<u16>,
+    service_id: Option<u16>,
+    pes_payload_size: Option<u32>,
+    pat_period: Option<Duration>,
+    sdt_period: Option<Duration>,
+    pcr_period: Option<Duration>,
+}
+
+impl MpegTsOptions {
+    /// Create a new set of options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the transport stream ID (`mpegts_transport_stream_id`).
+    pub fn transport_stream_id(mut self, id: u16) -> Self {
+        self.transport_stream_id = Some(id);
+        self
+    }
+
+    /// Set the service ID (`mpegts_service_id`), also known as the program
+    /// number.
+    pub fn service_id(mut self, id: u16) -> Self {
+        self.service_id = Some(id);
+        self
+    }
+
+    /// Set the minimum PES packet payload size in bytes
+    /// (`pes_payload_size`).
+    pub fn pes_payload_size(mut self, size: u32) -> Self {
+        self.pes_payload_size = Some(size);
+        self
+    }
+
+    /// Set the maximum time between PAT/PMT tables (`pat_period`).
+    pub fn pat_period(mut self, period: Duration) -> Self {
+        self.pat_period = Some(period);
+        self
+    }
+
+    /// Set the maximum time between SDT tables (`sdt_period`).
+    pub fn sdt_period(mut self, period: Duration) -> Self {
+        self.sdt_period = Some(period);
+        self
+    }
+
+    /// Set the PCR retransmission interval (`pcr_period`). The interval is
+    /// rounded down to milliseconds.
+    pub fn pcr_period(mut self, period: Duration) -> Self {
+        self.pcr_period = Some(period);
+        self
+    }
+
+    /// Apply the options to a given muxer builder.
+    pub fn apply(&self, mut builder: MuxerBuilder) -> MuxerBuilder {
+        if let Some(id) = self.transport_stream_id {
+            builder = builder.set_option("mpegts_transport_stream_id", id);
+        }
+
+        if let Some(id) = self.service_id {
+            builder = builder.set_option("mpegts_service_id", id);
+        }
+
+        if let Some(size) = self.pes_payload_size {
+            builder = builder.set_option("pes_payload_size", size);
+        }
+
+        if let Some(period) = self.pat_period {
+            builder = builder.set_option("pat_period", seconds(period));
+        }
+
+        if let Some(period) = self.sdt_period {
+            builder = builder.set_option("sdt_period", seconds(period));
+        }
+
+        if let Some(period) = self.pcr_period {
+            builder = builder.set_option("pcr_period", period.as_millis());
+        }
+
+        builder
+    }
+}
+
+/// Format a given duration as a number of seconds.
+fn seconds(duration: Duration) -> String {
+    format!("{}.{:06}", duration.as_secs(), duration.subsec_micros())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::seconds;
+
+    #[test]
+    fn test_seconds() {
+        assert_eq!(seconds(Duration::from_millis(100)), "0.100000");
+        assert_eq!(seconds(Duration::from_micros(2_000_001)), "2.000001");
+    }
+}
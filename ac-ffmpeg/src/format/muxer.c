@@ -3,6 +3,7 @@
 #include <libavcodec/avcodec.h>
 #include <libavutil/opt.h>
 
+#include <limits.h>
 #include <stdlib.h>
 
 const AVOutputFormat* ffw_guess_output_format(
@@ -12,23 +13,57 @@ const AVOutputFormat* ffw_guess_output_format(
     return av_guess_format(short_name, file_name, mime_type);
 }
 
+int ffw_output_format_query_codec(const AVOutputFormat* format, const AVCodecParameters* params) {
+    return avformat_query_codec(format, params->codec_id, FF_COMPLIANCE_NORMAL);
+}
+
+const char* ffw_output_format_get_name(const AVOutputFormat* format) {
+    return format->name;
+}
+
+int ffw_output_format_requires_global_header(const AVOutputFormat* format) {
+    return (format->flags & AVFMT_GLOBALHEADER) ? 1 : 0;
+}
+
+typedef void io_open_callback_t(void*, const char*);
+
+typedef void* output_open_t(void*, const char*);
+typedef int output_write_t(void*, const uint8_t*, int);
+typedef int output_close_t(void*);
+
 typedef struct Muxer {
     AVFormatContext* fc;
     AVDictionary* options;
     int initialized;
+
+    int (*io_open)(AVFormatContext*, AVIOContext**, const char*, int, AVDictionary**);
+    io_open_callback_t* io_open_callback;
+    void* io_open_opaque;
+
+    output_open_t* output_open;
+    output_write_t* output_write;
+    output_close_t* output_close;
+    void* output_opaque;
 } Muxer;
 
 Muxer* ffw_muxer_new();
 unsigned ffw_muxer_get_nb_streams(const Muxer*);
 AVStream* ffw_muxer_get_stream(Muxer* muxer, unsigned stream_index);
+void ffw_muxer_get_stream_time_base(const Muxer*, unsigned, uint32_t*, uint32_t*);
+int ffw_muxer_get_dts_mode(const Muxer*, unsigned);
 int ffw_muxer_new_stream(Muxer*, const AVCodecParameters*);
 int ffw_muxer_init(Muxer*, AVIOContext*, const AVOutputFormat*);
 int ffw_muxer_get_option(Muxer*, const char*, uint8_t**);
 int ffw_muxer_set_initial_option(Muxer*, const char*, const char*);
 int ffw_muxer_set_option(Muxer*, const char*, const char*);
 int ffw_muxer_set_metadata(Muxer*, const char*, const char*);
+void ffw_muxer_set_reproducible(Muxer*);
+void ffw_muxer_set_io_open_callback(Muxer*, io_open_callback_t*, void*);
+void ffw_muxer_set_output_handler(Muxer*, output_open_t*, output_write_t*, output_close_t*, void*);
+int ffw_muxer_new_chapter(Muxer*, int64_t, uint32_t, uint32_t, int64_t, int64_t, AVChapter**);
 int ffw_muxer_write_frame(Muxer*, AVPacket*, uint32_t, uint32_t);
 int ffw_muxer_interleaved_write_frame(Muxer*, AVPacket*, uint32_t, uint32_t);
+int ffw_muxer_create_sdp(Muxer**, int, char*, int);
 int ffw_muxer_free(Muxer*);
 
 Muxer* ffw_muxer_new() {
@@ -40,6 +75,13 @@ Muxer* ffw_muxer_new() {
     muxer->fc = NULL;
     muxer->options = NULL;
     muxer->initialized = 0;
+    muxer->io_open = NULL;
+    muxer->io_open_callback = NULL;
+    muxer->io_open_opaque = NULL;
+    muxer->output_open = NULL;
+    muxer->output_write = NULL;
+    muxer->output_close = NULL;
+    muxer->output_opaque = NULL;
 
     muxer->fc = avformat_alloc_context();
     if (muxer->fc == NULL) {
@@ -62,6 +104,36 @@ AVStream* ffw_muxer_get_stream(Muxer* muxer, unsigned stream_index) {
     return muxer->fc->streams[stream_index];
 }
 
+void ffw_muxer_get_stream_time_base(
+    const Muxer* muxer,
+    unsigned stream_index,
+    uint32_t* num,
+    uint32_t* den) {
+    AVStream* stream = muxer->fc->streams[stream_index];
+
+    *num = stream->time_base.num;
+    *den = stream->time_base.den;
+}
+
+int ffw_muxer_get_dts_mode(const Muxer* muxer, unsigned stream_index) {
+    AVStream* stream = muxer->fc->streams[stream_index];
+
+    enum AVMediaType media_type = stream->codecpar->codec_type;
+
+    int flags = muxer->fc->oformat->flags;
+
+    // these are the rules used by libavformat
+    if (flags & AVFMT_NOTIMESTAMPS) {
+        return 0;
+    } else if (flags & AVFMT_TS_NONSTRICT) {
+        return 1;
+    } else if (media_type == AVMEDIA_TYPE_SUBTITLE || media_type == AVMEDIA_TYPE_DATA) {
+        return 1;
+    }
+
+    return 2;
+}
+
 int ffw_muxer_new_stream(Muxer* muxer, const AVCodecParameters* params) {
     AVStream* s;
     int ret;
@@ -127,6 +199,188 @@ int ffw_muxer_set_metadata(Muxer* muxer, const char* key, const char* value) {
     return av_dict_set(&muxer->fc->metadata, key, value, 0);
 }
 
+static int ffw_muxer_io_open(
+    AVFormatContext* fc,
+    AVIOContext** pb,
+    const char* url,
+    int flags,
+    AVDictionary** options) {
+    Muxer* muxer = fc->opaque;
+    int ret;
+
+    ret = muxer->io_open(fc, pb, url, flags, options);
+
+    if (ret >= 0 && muxer->io_open_callback) {
+        muxer->io_open_callback(muxer->io_open_opaque, url);
+    }
+
+    return ret;
+}
+
+void ffw_muxer_set_io_open_callback(Muxer* muxer, io_open_callback_t* callback, void* opaque) {
+    // keep the original function, we only need to be notified about new
+    // outputs (e.g. segments of the segment muxer)
+    if (!muxer->io_open) {
+        muxer->io_open = muxer->fc->io_open;
+    }
+
+    muxer->io_open_callback = callback;
+    muxer->io_open_opaque = opaque;
+
+    muxer->fc->opaque = muxer;
+    muxer->fc->io_open = ffw_muxer_io_open;
+}
+
+static int ffw_muxer_open_output(
+    AVFormatContext* fc,
+    AVIOContext** pb,
+    const char* url,
+    int flags,
+    AVDictionary** options) {
+    Muxer* muxer = fc->opaque;
+    unsigned char* buffer;
+    void* output;
+
+    if (!(flags & AVIO_FLAG_WRITE)) {
+        return AVERROR(EINVAL);
+    }
+
+    output = muxer->output_open(muxer->output_opaque, url);
+    if (output == NULL) {
+        return AVERROR(EIO);
+    }
+
+    buffer = av_malloc(4096);
+    if (buffer == NULL) {
+        goto err;
+    }
+
+    *pb = avio_alloc_context(buffer, 4096, 1, output, NULL, muxer->output_write, NULL);
+    if (*pb == NULL) {
+        goto err;
+    }
+
+    return 0;
+
+err:
+    av_free(buffer);
+
+    muxer->output_close(output);
+
+    return AVERROR(ENOMEM);
+}
+
+static int ffw_muxer_close_output(Muxer* muxer, AVIOContext* pb) {
+    int ret;
+
+    if (pb == NULL) {
+        return 0;
+    }
+
+    avio_flush(pb);
+
+    ret = pb->error;
+
+    if (muxer->output_close(pb->opaque) < 0 && ret >= 0) {
+        ret = AVERROR(EIO);
+    }
+
+    av_freep(&pb->buffer);
+    avio_context_free(&pb);
+
+    return ret;
+}
+
+#if LIBAVFORMAT_VERSION_MAJOR >= 60
+static int ffw_muxer_io_close2(AVFormatContext* fc, AVIOContext* pb) {
+    return ffw_muxer_close_output(fc->opaque, pb);
+}
+#else
+static void ffw_muxer_io_close(AVFormatContext* fc, AVIOContext* pb) {
+    ffw_muxer_close_output(fc->opaque, pb);
+}
+#endif
+
+void ffw_muxer_set_output_handler(
+    Muxer* muxer,
+    output_open_t* open,
+    output_write_t* write,
+    output_close_t* close,
+    void* opaque) {
+    muxer->output_open = open;
+    muxer->output_write = write;
+    muxer->output_close = close;
+    muxer->output_opaque = opaque;
+
+    // all outputs opened by the muxer will be passed to the handler, the
+    // io_open callback (if any) will still be notified
+    muxer->io_open = ffw_muxer_open_output;
+
+    muxer->fc->opaque = muxer;
+    muxer->fc->io_open = ffw_muxer_io_open;
+
+#if LIBAVFORMAT_VERSION_MAJOR >= 60
+    muxer->fc->io_close2 = ffw_muxer_io_close2;
+#else
+    muxer->fc->io_close = ffw_muxer_io_close;
+#endif
+}
+
+void ffw_muxer_set_reproducible(Muxer* muxer) {
+    // drop all creation times, the muxer will use zero instead of the
+    // current time
+    av_dict_set(&muxer->fc->metadata, "creation_time", NULL, 0);
+
+    for (unsigned int j = 0; j < muxer->fc->nb_streams; j++) {
+        av_dict_set(&muxer->fc->streams[j]->metadata, "creation_time", NULL, 0);
+    }
+
+    muxer->fc->flags |= AVFMT_FLAG_BITEXACT;
+}
+
+int ffw_muxer_new_chapter(
+    Muxer* muxer,
+    int64_t id,
+    uint32_t tb_num,
+    uint32_t tb_den,
+    int64_t start,
+    int64_t end,
+    AVChapter** res) {
+    AVChapter** chapters;
+    AVChapter* chapter;
+
+    if (muxer->fc->nb_chapters >= INT_MAX / sizeof(AVChapter*)) {
+        return AVERROR(ERANGE);
+    }
+
+    // the chapters are freed by avformat_free_context()
+    chapters = av_realloc_array(
+        muxer->fc->chapters,
+        muxer->fc->nb_chapters + 1,
+        sizeof(AVChapter*));
+
+    if (!chapters) {
+        return AVERROR(ENOMEM);
+    }
+
+    muxer->fc->chapters = chapters;
+
+    if (!(chapter = av_mallocz(sizeof(AVChapter)))) {
+        return AVERROR(ENOMEM);
+    }
+
+    chapter->id = id;
+    chapter->time_base = av_make_q(tb_num, tb_den);
+    chapter->start = start;
+    chapter->end = end;
+
+    muxer->fc->chapters[muxer->fc->nb_chapters++] = chapter;
+
+    *res = chapter;
+
+    return 0;
+}
+
 static int ffw_rescale_packet_timestamps(Muxer* muxer, AVPacket* packet, uint32_t src_tb_num, uint32_t src_tb_den) {
     AVStream* stream;
     AVRational src_tb;
@@ -139,7 +393,7 @@ static int ffw_rescale_packet_timestamps(Muxer* muxer, AVPacket* packet, uint32_
 
     stream_index = packet->stream_index;
 
-    if (stream_index > muxer->fc->nb_streams) {
+    if (stream_index >= muxer->fc->nb_streams) {
         return AVERROR(EINVAL);
     }
 
@@ -173,6 +427,27 @@ int ffw_muxer_interleaved_write_frame(Muxer* muxer, AVPacket* packet, uint32_t t
     return av_interleaved_write_frame(muxer->fc, packet);
 }
 
+int ffw_muxer_create_sdp(Muxer** muxers, int count, char* buffer, int size) {
+    AVFormatContext** contexts;
+    int i;
+    int ret;
+
+    contexts = malloc(count * sizeof(AVFormatContext*));
+    if (contexts == NULL) {
+        return AVERROR(ENOMEM);
+    }
+
+    for (i = 0; i < count; i++) {
+        contexts[i] = muxers[i]->fc;
+    }
+
+    ret = av_sdp_create(contexts, count, buffer, size);
+
+    free(contexts);
+
+    return ret;
+}
+
 int ffw_muxer_free(Muxer* muxer) {
     int ret = 0;
 
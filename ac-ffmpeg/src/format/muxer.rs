@@ -1,19 +1,36 @@
 //! A/V muxer.
 
 use std::{
-    ffi::CString,
-    io::Write,
+    ffi::{CStr, CString},
+    fmt::{self, Display, Formatter},
+    io::{self, Write},
     os::raw::{c_char, c_int, c_uint, c_void},
     ptr,
+    time::Duration,
 };
 
 use crate::{
-    codec::CodecParameters,
-    format::{io::IO, stream::Stream},
-    packet::Packet,
-    Error,
+    codec::{CodecParameters, Compliance, MediaType},
+    format::{chapter::Chapter, io::IO, stream::Stream},
+    packet::{Packet, PacketMut},
+    time::{Rounding, TimeBase, Timestamp},
+    Error, ErrorKind,
 };
 
+/// Callback invoked when the muxer opens a new output.
+pub(crate) type IoOpenCallback = extern "C" fn(opaque: *mut c_void, url: *const c_char);
+
+/// Handler of outputs opened by the muxer itself (e.g. segments and
+/// manifests). It is called with the output URL and it returns a writer
+/// for the output. The writer is flushed and dropped once the muxer closes
+/// the output.
+pub(crate) type OutputHandler = Box<dyn FnMut(&str) -> io::Result<Box<dyn Write + Send>> + Send>;
+
+type OutputOpenCallback = extern "C" fn(opaque: *mut c_void, url: *const c_char) -> *mut c_void;
+type OutputWriteCallback =
+    extern "C" fn(output: *mut c_void, buffer: *const u8, buffer_size: c_int) -> c_int;
+type OutputCloseCallback = extern "C" fn(output: *mut c_void) -> c_int;
+
 extern "C" {
     fn ffw_guess_output_format(
         short_name: *const c_char,
@@ -21,9 +38,20 @@ extern "C" {
         mime_type: *const c_char,
     ) -> *const c_void;
 
+    fn ffw_output_format_query_codec(format: *const c_void, params: *const c_void) -> c_int;
+    fn ffw_output_format_get_name(format: *const c_void) -> *const c_char;
+    fn ffw_output_format_requires_global_header(format: *const c_void) -> c_int;
+
     fn ffw_muxer_new() -> *mut c_void;
     fn ffw_muxer_get_nb_streams(muxer: *const c_void) -> c_uint;
     fn ffw_muxer_get_stream(muxer: *mut c_void, stream_index: c_uint) -> *mut c_void;
+    fn ffw_muxer_get_stream_time_base(
+        muxer: *const c_void,
+        stream_index: c_uint,
+        num: *mut u32,
+        den: *mut u32,
+    );
+    fn ffw_muxer_get_dts_mode(muxer: *const c_void, stream_index: c_uint) -> c_int;
     fn ffw_muxer_new_stream(muxer: *mut c_void, params: *const c_void) -> c_int;
     fn ffw_muxer_init(muxer: *mut c_void, io_context: *mut c_void, format: *const c_void) -> c_int;
     fn ffw_muxer_set_initial_option(
@@ -50,7 +78,179 @@ extern "C" {
         tb_num: u32,
         tb_den: u32,
     ) -> c_int;
+    fn ffw_muxer_new_chapter(
+        muxer: *mut c_void,
+        id: i64,
+        tb_num: u32,
+        tb_den: u32,
+        start: i64,
+        end: i64,
+        chapter: *mut *mut c_void,
+    ) -> c_int;
+    fn ffw_muxer_set_io_open_callback(
+        muxer: *mut c_void,
+        callback: IoOpenCallback,
+        opaque: *mut c_void,
+    );
+    fn ffw_muxer_set_output_handler(
+        muxer: *mut c_void,
+        open: OutputOpenCallback,
+        write: OutputWriteCallback,
+        close: OutputCloseCallback,
+        opaque: *mut c_void,
+    );
+    fn ffw_muxer_set_reproducible(muxer: *mut c_void);
     fn ffw_muxer_free(muxer: *mut c_void) -> c_int;
+
+    fn ffw_chapter_set_metadata(
+        chapter: *mut c_void,
+        key: *const c_char,
+        value: *const c_char,
+    ) -> c_int;
+}
+
+/// Details of a packet rejected by the muxer.
+#[derive(Debug, Copy, Clone)]
+pub enum PacketError {
+    /// The packet references a stream that does not exist.
+    InvalidStreamIndex {
+        /// Stream index of the packet.
+        stream_index: usize,
+        /// Number of streams of the muxer.
+        streams: usize,
+    },
+    /// The packet DTS is lower than the DTS of the previous packet in the
+    /// same stream. Most output formats do not accept equal DTS values
+    /// either. Note that distinct timestamps may become equal after being
+    /// rescaled into the stream time base. The timestamps are in the stream
+    /// time base.
+    NonMonotonicDts {
+        /// Stream index of the packet.
+        stream_index: usize,
+        /// DTS of the previous packet.
+        previous: Timestamp,
+        /// DTS of the rejected packet.
+        current: Timestamp,
+    },
+    /// The packet PTS is lower than its DTS. The timestamps are in the
+    /// stream time base.
+    PtsBeforeDts {
+        /// Stream index of the packet.
+        stream_index: usize,
+        /// PTS of the packet.
+        pts: Timestamp,
+        /// DTS of the packet.
+        dts: Timestamp,
+    },
+}
+
+impl PacketError {
+    /// Get stream index of the rejected packet.
+    pub fn stream_index(&self) -> usize {
+        match *self {
+            Self::InvalidStreamIndex { stream_index, .. } => stream_index,
+            Self::NonMonotonicDts { stream_index, .. } => stream_index,
+            Self::PtsBeforeDts { stream_index, .. } => stream_index,
+        }
+    }
+}
+
+impl Display for PacketError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match self {
+            Self::InvalidStreamIndex {
+                stream_index,
+                streams,
+            } => write!(
+                f,
+                "invalid stream index {} (the muxer has {} streams)",
+                stream_index, streams
+            ),
+            Self::NonMonotonicDts {
+                stream_index,
+                previous,
+                current,
+            } => write!(
+                f,
+                "non-monotonic DTS in stream {}: previous {}, current {} (time base {}/{})",
+                stream_index,
+                previous.timestamp(),
+                current.timestamp(),
+                current.time_base().num(),
+                current.time_base().den()
+            ),
+            Self::PtsBeforeDts {
+                stream_index,
+                pts,
+                dts,
+            } => write!(
+                f,
+                "PTS {} lower than DTS {} in stream {} (time base {}/{})",
+                pts.timestamp(),
+                dts.timestamp(),
+                stream_index,
+                dts.time_base().num(),
+                dts.time_base().den()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PacketError {}
+
+/// DTS validation rule of a single stream.
+#[derive(Copy, Clone)]
+enum DtsMode {
+    /// The output format ignores timestamps.
+    Ignore,
+    /// DTS must not decrease.
+    NonDecreasing,
+    /// DTS must increase.
+    Increasing,
+}
+
+impl DtsMode {
+    /// Get the DTS mode corresponding to a given raw value.
+    fn from_raw(v: c_int) -> Self {
+        match v {
+            0 => Self::Ignore,
+            1 => Self::NonDecreasing,
+            _ => Self::Increasing,
+        }
+    }
+}
+
+/// Interleaving policy for sparse streams (subtitle and data streams).
+///
+/// The interleaving muxer writes a packet only once it has a packet from
+/// every stream, so it has to buffer the other streams while a sparse stream
+/// has no packets. By default, the buffering is limited only by the maximum
+/// interleave delta (see `MuxerBuilder::max_interleave_delta()`).
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SparseStreamPolicy {
+    /// Wait for packets of the sparse streams. This is the default.
+    Wait,
+    /// Push empty heartbeat packets into sparse streams that have not
+    /// received any packet for a given interval (measured by timestamps of
+    /// the other streams). The heartbeat packets let the muxer write the
+    /// buffered packets, so the buffering is limited to roughly twice the
+    /// interval.
+    ///
+    /// A heartbeat is placed one interval behind the other streams, so
+    /// packets of the sparse streams must not lag behind the other streams
+    /// by more than the interval. Such packets will be rejected as non
+    /// monotonic. Some muxers (e.g. MP4/MOV) drop empty packets, make sure
+    /// that the output format tolerates them.
+    Heartbeat(Duration),
+}
+
+/// Timestamp validation state of a single stream.
+struct StreamState {
+    time_base: TimeBase,
+    dts_mode: DtsMode,
+    last_dts: Option<i64>,
+    sparse: bool,
+    last_activity: Option<i64>,
 }
 
 /// Muxer builder.
@@ -58,6 +258,8 @@ pub struct MuxerBuilder {
     ptr: *mut c_void,
     streams: Vec<Stream>,
     interleaved: bool,
+    reproducible: bool,
+    sparse_stream_policy: SparseStreamPolicy,
 }
 
 impl MuxerBuilder {
@@ -73,6 +275,8 @@ impl MuxerBuilder {
             ptr,
             streams: Vec::new(),
             interleaved: false,
+            reproducible: false,
+            sparse_stream_policy: SparseStreamPolicy::Wait,
         }
     }
 
@@ -163,6 +367,44 @@ impl MuxerBuilder {
         self
     }
 
+    /// Add a given chapter. Note that not all output formats support
+    /// chapters.
+    pub fn add_chapter(&mut self, chapter: &Chapter) -> Result<(), Error> {
+        let time_base = chapter.time_base();
+
+        let mut ptr = ptr::null_mut();
+
+        let ret = unsafe {
+            ffw_muxer_new_chapter(
+                self.ptr,
+                chapter.id(),
+                time_base.num(),
+                time_base.den(),
+                chapter.start().timestamp(),
+                chapter.end().timestamp(),
+                &mut ptr,
+            )
+        };
+
+        if ret < 0 {
+            return Err(Error::from_raw_error_code(ret));
+        }
+
+        for (key, value) in chapter.metadata() {
+            let key = CString::new(key.as_str()).map_err(|_| Error::new("invalid metadata key"))?;
+            let value =
+                CString::new(value.as_str()).map_err(|_| Error::new("invalid metadata value"))?;
+
+            let ret = unsafe { ffw_chapter_set_metadata(ptr, key.as_ptr(), value.as_ptr()) };
+
+            if ret < 0 {
+                return Err(Error::from_raw_error_code(ret));
+            }
+        }
+
+        Ok(())
+    }
+
     /// Set the muxer to do the interleaving automatically. It is disabled by
     /// default.
     pub fn interleaved(mut self, interleaved: bool) -> MuxerBuilder {
@@ -170,6 +412,83 @@ impl MuxerBuilder {
         self
     }
 
+    /// Set the maximum difference between timestamps of the first and the
+    /// last packet in the interleaving queue. The muxer writes the buffered
+    /// packets without waiting for streams that have no packets once the
+    /// difference is exceeded. Zero means waiting indefinitely. The FFmpeg
+    /// default is 10 seconds.
+    pub fn max_interleave_delta(self, delta: Duration) -> MuxerBuilder {
+        self.set_option("max_interleave_delta", delta.as_micros())
+    }
+
+    /// Set the interleaving policy for sparse streams. It is used only if
+    /// the automatic interleaving is enabled. The default is
+    /// `SparseStreamPolicy::Wait`.
+    pub fn sparse_stream_policy(mut self, policy: SparseStreamPolicy) -> MuxerBuilder {
+        self.sparse_stream_policy = policy;
+        self
+    }
+
+    /// Make the muxer output reproducible, i.e. identical across runs and
+    /// machines (e.g. for golden file tests). The muxer will not write any
+    /// creation times, library versions or other volatile information.
+    /// Streams are always written in the order they were added. It is
+    /// disabled by default.
+    ///
+    /// Use reproducible encoders as well in order to get a reproducible
+    /// output of a transcoding pipeline.
+    pub fn reproducible(mut self, reproducible: bool) -> MuxerBuilder {
+        self.reproducible = reproducible;
+        self
+    }
+
+    /// Set the level of compliance with the standards. Use
+    /// `Compliance::Experimental` in order to mux codecs that are only
+    /// experimentally supported by the output format. The default is
+    /// `Compliance::Normal`.
+    pub fn compliance(self, compliance: Compliance) -> MuxerBuilder {
+        self.set_option("strict", compliance.into_raw())
+    }
+
+    /// Encrypt all streams using the `cenc` scheme (AES-CTR) with a given
+    /// key and key ID. It is supported only by the MP4/MOV muxers. Use e.g.
+    /// the `movflags` option set to `+frag_keyframe+empty_moov` in order to
+    /// get fragmented MP4 suitable for DASH/CMAF.
+    pub fn cenc_encryption(self, key: &[u8; 16], key_id: &[u8; 16]) -> MuxerBuilder {
+        let hex = |bytes: &[u8; 16]| {
+            bytes
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>()
+        };
+
+        self.set_option("encryption_scheme", "cenc-aes-ctr")
+            .set_option("encryption_key", hex(key))
+            .set_option("encryption_kid", hex(key_id))
+    }
+
+    /// Set a callback that will be invoked whenever the muxer opens a new
+    /// output using its URL (e.g. a new segment of the segment muxer). The
+    /// callback is invoked from within the muxer calls.
+    pub(crate) fn set_io_open_callback(&mut self, callback: IoOpenCallback, opaque: *mut c_void) {
+        unsafe { ffw_muxer_set_io_open_callback(self.ptr, callback, opaque) }
+    }
+
+    /// Pass all outputs opened by the muxer using their URLs to a given
+    /// handler instead of letting FFmpeg open them. The handler must
+    /// outlive the muxer.
+    pub(crate) fn set_output_handler(&mut self, handler: *mut OutputHandler) {
+        unsafe {
+            ffw_muxer_set_output_handler(
+                self.ptr,
+                output_open,
+                output_write,
+                output_close,
+                handler as *mut c_void,
+            )
+        }
+    }
+
     /// Build the muxer.
     ///
     /// # Arguments
@@ -182,12 +501,55 @@ impl MuxerBuilder {
         let io_context_ptr = io.io_context_mut().as_mut_ptr();
         let format_ptr = format.ptr;
 
+        if self.reproducible {
+            unsafe { ffw_muxer_set_reproducible(self.ptr) };
+        }
+
         let ret = unsafe { ffw_muxer_init(self.ptr, io_context_ptr, format_ptr) };
 
         if ret < 0 {
             return Err(Error::from_raw_error_code(ret));
         }
 
+        let count = unsafe { ffw_muxer_get_nb_streams(self.ptr) };
+
+        let streams = (0..count)
+            .map(|index| unsafe {
+                let mut num = 0;
+                let mut den = 0;
+
+                // the muxer may change the stream time base during
+                // initialization
+                ffw_muxer_get_stream_time_base(self.ptr, index as _, &mut num, &mut den);
+
+                let sparse = self
+                    .streams
+                    .get(index as usize)
+                    .map(|stream| {
+                        matches!(
+                            stream.codec_parameters().media_type(),
+                            MediaType::Subtitle | MediaType::Data
+                        )
+                    })
+                    .unwrap_or(false);
+
+                StreamState {
+                    time_base: TimeBase::new(num, den),
+                    dts_mode: DtsMode::from_raw(ffw_muxer_get_dts_mode(self.ptr, index as _)),
+                    last_dts: None,
+                    sparse,
+                    last_activity: None,
+                }
+            })
+            .collect();
+
+        let heartbeat_interval = match self.sparse_stream_policy {
+            SparseStreamPolicy::Heartbeat(interval) if self.interleaved => {
+                Some(interval.as_micros() as i64)
+            }
+            _ => None,
+        };
+
         let muxer_ptr = self.ptr;
 
         self.ptr = ptr::null_mut();
@@ -196,6 +558,8 @@ impl MuxerBuilder {
             ptr: muxer_ptr,
             io: Some(io),
             interleaved: self.interleaved,
+            streams,
+            heartbeat_interval,
         };
 
         Ok(res)
@@ -218,6 +582,8 @@ pub struct Muxer<T> {
     ptr: *mut c_void,
     io: Option<IO<T>>,
     interleaved: bool,
+    streams: Vec<StreamState>,
+    heartbeat_interval: Option<i64>,
 }
 
 impl Muxer<()> {
@@ -228,6 +594,11 @@ impl Muxer<()> {
 }
 
 impl<T> Muxer<T> {
+    /// Get raw pointer to the underlying muxer.
+    pub(crate) fn as_ptr(&self) -> *const c_void {
+        self.ptr
+    }
+
     /// Set an option.
     pub fn set_option<V>(&mut self, name: &str, value: V) -> Result<(), Error>
     where
@@ -246,13 +617,96 @@ impl<T> Muxer<T> {
         }
     }
 
-    /// Mux a given packet. The packet pts and dts are expected to be in
-    /// microseconds. They will be automatically rescaled to match the time
-    /// base of the corresponding stream.
-    pub fn push(&mut self, mut packet: Packet) -> Result<(), Error> {
-        let nb_streams = unsafe { ffw_muxer_get_nb_streams(self.ptr) as usize };
+    /// Get the time base of a given stream. The output format may choose a
+    /// different time base than the one set in the builder when the muxer
+    /// is initialized. This is the final one.
+    pub fn stream_time_base(&self, stream_index: usize) -> Option<TimeBase> {
+        self.streams
+            .get(stream_index)
+            .map(|stream| stream.time_base)
+    }
+
+    /// Mux a given packet. The packet timestamps will be automatically
+    /// rescaled from the packet time base into the final time base of the
+    /// corresponding stream (see `stream_time_base()`), so packets coming
+    /// from encoders, demuxers or other sources can be pushed as they are
+    /// as long as they carry the right time base.
+    ///
+    /// The packet is validated before it is passed to FFmpeg. Packets with
+    /// an invalid stream index or invalid timestamps are rejected with an
+    /// error of kind `ErrorKind::InvalidPacket` (see `Error::packet_error()`
+    /// for the details). The muxer remains usable after such an error, so
+    /// the caller may drop or fix the packet and continue.
+    pub fn push(&mut self, packet: Packet) -> Result<(), Error> {
+        let stream_index = packet.stream_index();
+
+        let dts = packet.dts();
+
+        let _span = trace_span!(
+            "muxer_push",
+            stream_index = stream_index,
+            pts = packet.pts().as_micros(),
+            dts = dts.as_micros(),
+        );
+
+        let ts = if dts.is_null() { packet.pts() } else { dts };
+
+        self.write(packet)?;
+
+        if let (Some(interval), Some(ts)) = (self.heartbeat_interval, ts.as_micros()) {
+            self.heartbeat(stream_index, ts, interval)?;
+        }
+
+        Ok(())
+    }
+
+    /// Track activity of the sparse streams after writing a packet with a
+    /// given timestamp (in microseconds) into a given stream and push
+    /// heartbeat packets into the sparse streams that are lagging behind.
+    fn heartbeat(&mut self, stream_index: usize, ts: i64, interval: i64) -> Result<(), Error> {
+        let stream = &mut self.streams[stream_index];
+
+        if stream.sparse {
+            stream.last_activity = Some(stream.last_activity.unwrap_or(ts).max(ts));
+
+            return Ok(());
+        }
+
+        let heartbeat = ts - interval;
+
+        for index in 0..self.streams.len() {
+            let stream = &self.streams[index];
+
+            let active = matches!(stream.last_activity, Some(last) if heartbeat < last + interval);
+
+            if !stream.sparse || active {
+                continue;
+            }
+
+            let packet = PacketMut::new(0)
+                .with_stream_index(index)
+                .with_time_base(TimeBase::MICROSECONDS)
+                .with_raw_pts(heartbeat)
+                .with_raw_dts(heartbeat)
+                .freeze();
+
+            match self.write(packet) {
+                Ok(()) => (),
+                // heartbeats conflicting with already written packets are
+                // not needed
+                Err(err) if err.kind() == ErrorKind::InvalidPacket => (),
+                Err(err) => return Err(err),
+            }
+
+            self.streams[index].last_activity = Some(heartbeat);
+        }
+
+        Ok(())
+    }
 
-        assert!(packet.stream_index() < nb_streams);
+    /// Validate and write a given packet.
+    fn write(&mut self, mut packet: Packet) -> Result<(), Error> {
+        let dts = self.validate(&packet)?;
 
         let tb = packet.time_base();
 
@@ -265,10 +719,71 @@ impl<T> Muxer<T> {
         };
 
         if ret < 0 {
-            Err(Error::from_raw_error_code(ret))
-        } else {
-            Ok(())
+            return Err(Error::from_raw_error_code(ret));
         }
+
+        if let Some(dts) = dts {
+            self.streams[packet.stream_index()].last_dts = Some(dts);
+        }
+
+        Ok(())
+    }
+
+    /// Validate a given packet and return its DTS in the stream time base
+    /// (if it should be remembered).
+    fn validate(&self, packet: &Packet) -> Result<Option<i64>, PacketError> {
+        let stream_index = packet.stream_index();
+
+        let stream = self
+            .streams
+            .get(stream_index)
+            .ok_or(PacketError::InvalidStreamIndex {
+                stream_index,
+                streams: self.streams.len(),
+            })?;
+
+        if let DtsMode::Ignore = stream.dts_mode {
+            return Ok(None);
+        }
+
+        // use the same rounding as av_packet_rescale_ts()
+        let pts = packet
+            .pts()
+            .rescale_rnd(stream.time_base, Rounding::NearInfinity);
+        let dts = packet
+            .dts()
+            .rescale_rnd(stream.time_base, Rounding::NearInfinity);
+
+        if !pts.is_null() && !dts.is_null() && pts.timestamp() < dts.timestamp() {
+            return Err(PacketError::PtsBeforeDts {
+                stream_index,
+                pts,
+                dts,
+            });
+        }
+
+        if dts.is_null() {
+            return Ok(None);
+        }
+
+        if let Some(previous) = stream.last_dts {
+            let current = dts.timestamp();
+
+            let valid = match stream.dts_mode {
+                DtsMode::Increasing => current > previous,
+                _ => current >= previous,
+            };
+
+            if !valid {
+                return Err(PacketError::NonMonotonicDts {
+                    stream_index,
+                    previous: Timestamp::new(previous, stream.time_base),
+                    current: dts,
+                });
+            }
+        }
+
+        Ok(Some(dts.timestamp()))
     }
 
     /// Flush the muxer.
@@ -288,7 +803,9 @@ impl<T> Muxer<T> {
         }
     }
 
-    /// Close the muxer and take the underlying IO.
+    /// Close the muxer and take the underlying IO. The trailer is written
+    /// but the underlying writer is not flushed. Use `finish()` to get the
+    /// underlying writer with all data flushed.
     pub fn close(mut self) -> Result<IO<T>, Error> {
         let ret = unsafe { ffw_muxer_free(self.ptr) };
 
@@ -301,6 +818,26 @@ impl<T> Muxer<T> {
         }
     }
 
+    /// Finish the muxer and take the underlying writer. The method flushes
+    /// the interleaving queue, writes the trailer and flushes the writer.
+    /// Unlike dropping the muxer, it reports all errors, so it should be
+    /// preferred whenever the output must be complete (e.g. when writing
+    /// into a `Vec<u8>` or a file).
+    pub fn finish(mut self) -> Result<T, Error>
+    where
+        T: Write,
+    {
+        self.flush()?;
+
+        let mut writer = self.close()?.into_stream();
+
+        writer
+            .flush()
+            .map_err(|err| Error::with_kind(ErrorKind::Io, err))?;
+
+        Ok(writer)
+    }
+
     /// Get reference to the underlying IO.
     pub fn io(&self) -> &IO<T> {
         self.io.as_ref().unwrap()
@@ -325,6 +862,44 @@ impl<T> Drop for Muxer<T> {
 unsafe impl<T> Send for Muxer<T> where T: Send {}
 unsafe impl<T> Sync for Muxer<T> where T: Sync {}
 
+/// Open an output using a given output handler.
+extern "C" fn output_open(opaque: *mut c_void, url: *const c_char) -> *mut c_void {
+    let handler = unsafe { &mut *(opaque as *mut OutputHandler) };
+
+    let url = unsafe { CStr::from_ptr(url) };
+
+    match handler(&url.to_string_lossy()) {
+        Ok(output) => Box::into_raw(Box::new(output)) as *mut c_void,
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Write given data into a given output.
+extern "C" fn output_write(output: *mut c_void, buffer: *const u8, buffer_size: c_int) -> c_int {
+    let output = unsafe { &mut *(output as *mut Box<dyn Write + Send>) };
+
+    if buffer.is_null() || buffer_size <= 0 {
+        return 0;
+    }
+
+    let buffer = unsafe { std::slice::from_raw_parts(buffer, buffer_size as usize) };
+
+    match output.write_all(buffer) {
+        Ok(()) => buffer_size,
+        Err(_) => unsafe { crate::ffw_error_unknown() },
+    }
+}
+
+/// Flush and drop a given output.
+extern "C" fn output_close(output: *mut c_void) -> c_int {
+    let mut output = unsafe { Box::from_raw(output as *mut Box<dyn Write + Send>) };
+
+    match output.flush() {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
+}
+
 /// FFmpeg output format.
 pub struct OutputFormat {
     ptr: *const c_void,
@@ -380,7 +955,238 @@ impl OutputFormat {
 
         Some(res)
     }
+
+    /// Get the short name of the output format.
+    pub fn name(&self) -> &'static str {
+        unsafe {
+            let name = ffw_output_format_get_name(self.ptr);
+
+            CStr::from_ptr(name as _)
+                .to_str()
+                .expect("invalid format name")
+        }
+    }
+
+    /// Check if the output format can contain a given codec. The method
+    /// returns false only if the format is known not to support the codec.
+    pub fn supports_codec(&self, params: &CodecParameters) -> bool {
+        unsafe { ffw_output_format_query_codec(self.ptr, params.as_ptr()) != 0 }
+    }
+
+    /// Check if encoders should put codec headers into extradata (i.e.
+    /// they should be opened with the `global_header` flag) rather than
+    /// into every keyframe.
+    pub fn requires_global_header(&self) -> bool {
+        unsafe { ffw_output_format_requires_global_header(self.ptr) != 0 }
+    }
 }
 
 unsafe impl Send for OutputFormat {}
 unsafe impl Sync for OutputFormat {}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, time::Duration};
+
+    use super::{Muxer, OutputFormat, PacketError, SparseStreamPolicy};
+
+    use crate::{
+        codec::{
+            video::frame::get_pixel_format, CodecParameters, SubtitleCodecParameters,
+            VideoCodecParameters,
+        },
+        format::{demuxer::Demuxer, io::IO},
+        test_util::packet,
+        time::TimeBase,
+        ErrorKind,
+    };
+
+    /// Get parameters of a 64x48 MPEG-4 video stream.
+    fn video_params() -> CodecParameters {
+        VideoCodecParameters::builder("mpeg4")
+            .unwrap()
+            .pixel_format(get_pixel_format("yuv420p"))
+            .width(64)
+            .height(48)
+            .build()
+            .into()
+    }
+
+    /// Create a new in-memory muxer with a single video stream.
+    fn muxer(format: &str) -> Muxer<Cursor<Vec<u8>>> {
+        let mut builder = Muxer::builder();
+
+        builder.add_stream(&video_params()).unwrap();
+
+        let io = IO::from_seekable_write_stream(Cursor::new(Vec::new()));
+
+        builder
+            .build(io, OutputFormat::find_by_name(format).unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_packet_validation() {
+        let tb = TimeBase::new(1, 1000);
+
+        let mut muxer = muxer("matroska");
+
+        let err = muxer
+            .push(packet(10, tb, 0).with_stream_index(1).freeze())
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::InvalidPacket);
+        assert!(matches!(
+            err.packet_error(),
+            Some(PacketError::InvalidStreamIndex {
+                stream_index: 1,
+                streams: 1,
+            })
+        ));
+
+        muxer.push(packet(10, tb, 40).freeze()).unwrap();
+
+        // Matroska accepts equal DTS values
+        muxer.push(packet(10, tb, 40).freeze()).unwrap();
+
+        let err = muxer.push(packet(10, tb, 0).freeze()).unwrap_err();
+
+        assert!(matches!(
+            err.packet_error(),
+            Some(PacketError::NonMonotonicDts {
+                stream_index: 0,
+                ..
+            })
+        ));
+
+        let err = muxer
+            .push(packet(10, tb, 80).with_raw_pts(60).freeze())
+            .unwrap_err();
+
+        assert!(matches!(
+            err.packet_error(),
+            Some(PacketError::PtsBeforeDts {
+                stream_index: 0,
+                ..
+            })
+        ));
+
+        // the muxer is still usable
+        muxer.push(packet(10, tb, 80).freeze()).unwrap();
+        muxer.flush().unwrap();
+
+        assert!(muxer.close().is_ok());
+    }
+
+    #[test]
+    fn test_strictly_increasing_dts() {
+        let mut muxer = muxer("mpegts");
+
+        let tb = TimeBase::MICROSECONDS;
+
+        muxer.push(packet(10, tb, 40_000).freeze()).unwrap();
+
+        let err = muxer.push(packet(10, tb, 40_000).freeze()).unwrap_err();
+
+        assert!(matches!(
+            err.packet_error(),
+            Some(PacketError::NonMonotonicDts { .. })
+        ));
+
+        // the timestamps are equal after being rescaled into 1/90000
+        let err = muxer.push(packet(10, tb, 40_005).freeze()).unwrap_err();
+
+        assert!(matches!(
+            err.packet_error(),
+            Some(PacketError::NonMonotonicDts { .. })
+        ));
+
+        muxer.push(packet(10, tb, 80_000).freeze()).unwrap();
+    }
+
+    #[test]
+    fn test_heartbeat() {
+        let tb = TimeBase::new(1, 1000);
+
+        let mut builder = Muxer::builder()
+            .interleaved(true)
+            .sparse_stream_policy(SparseStreamPolicy::Heartbeat(Duration::from_secs(1)));
+
+        builder.add_stream(&video_params()).unwrap();
+
+        let subtitles = SubtitleCodecParameters::new("subrip").unwrap();
+
+        builder.add_stream(&subtitles.into()).unwrap();
+
+        let io = IO::from_seekable_write_stream(Cursor::new(Vec::new()));
+
+        let mut muxer = builder
+            .build(io, OutputFormat::find_by_name("matroska").unwrap())
+            .unwrap();
+
+        let push_video = |muxer: &mut Muxer<_>, start: i64, end: i64| {
+            for ts in (start..end).step_by(40) {
+                let packet = packet(10, tb, ts).with_key_flag(true).freeze();
+
+                muxer.push(packet).unwrap();
+            }
+        };
+
+        // heartbeats are placed one second behind the video while the
+        // subtitle stream is idle
+        push_video(&mut muxer, 2000, 3040);
+
+        assert_eq!(muxer.streams[1].last_activity, Some(2_000_000));
+
+        let subtitle = packet(10, tb, 3000)
+            .with_stream_index(1)
+            .with_raw_duration(500)
+            .freeze();
+
+        muxer.push(subtitle).unwrap();
+
+        // no heartbeats while the subtitle stream is active
+        push_video(&mut muxer, 3040, 5000);
+
+        assert_eq!(muxer.streams[1].last_activity, Some(3_000_000));
+
+        push_video(&mut muxer, 5000, 5040);
+
+        assert_eq!(muxer.streams[1].last_activity, Some(4_000_000));
+
+        let data = muxer.finish().unwrap().into_inner();
+
+        let mut demuxer = Demuxer::builder()
+            .build(IO::from_read_stream(Cursor::new(data)))
+            .unwrap()
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)
+            .unwrap();
+
+        let mut video_packets = 0;
+        let mut subtitle_packets = 0;
+
+        let mut last_dts = [None, None];
+
+        while let Some(packet) = demuxer.take().unwrap() {
+            let index = packet.stream_index();
+
+            if index == 0 {
+                video_packets += 1;
+            } else {
+                subtitle_packets += 1;
+            }
+
+            let dts = packet.dts().as_micros().unwrap();
+
+            if let Some(last) = last_dts[index] {
+                assert!(dts >= last);
+            }
+
+            last_dts[index] = Some(dts);
+        }
+
+        assert_eq!(video_packets, 76);
+        assert!(subtitle_packets >= 1);
+    }
+}
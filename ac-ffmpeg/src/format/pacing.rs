@@ -0,0 +1,318 @@
+//! Real-time output pacing.
+
+use std::{collections::VecDeque, thread, time::Duration};
+
+use crate::{
+    codec::{audio::AudioFrame, video::VideoFrame},
+    format::clock::{Clock, Pacer, SystemClock},
+    packet::Packet,
+    time::Timestamp,
+};
+
+/// Media that can be paced.
+pub trait Paced {
+    /// Get the timestamp used for pacing.
+    fn pacing_timestamp(&self) -> Timestamp;
+}
+
+impl Paced for Packet {
+    fn pacing_timestamp(&self) -> Timestamp {
+        let dts = self.dts();
+
+        if dts.is_null() {
+            self.pts()
+        } else {
+            dts
+        }
+    }
+}
+
+impl Paced for VideoFrame {
+    fn pacing_timestamp(&self) -> Timestamp {
+        self.pts()
+    }
+}
+
+impl Paced for AudioFrame {
+    fn pacing_timestamp(&self) -> Timestamp {
+        self.pts()
+    }
+}
+
+/// Builder for the pacing stage.
+pub struct PacingBuilder {
+    speed: f64,
+    initial_burst: Duration,
+    max_gap: Duration,
+}
+
+impl PacingBuilder {
+    /// Create a new builder.
+    fn new() -> Self {
+        Self {
+            speed: 1.0,
+            initial_burst: Duration::from_secs(0),
+            max_gap: Duration::from_secs(10),
+        }
+    }
+
+    /// Set the release speed relative to the real time (e.g. 2.0 releases
+    /// the media twice as fast). The default is 1.0.
+    pub fn speed(mut self, speed: f64) -> Self {
+        if speed > 0.0 {
+            self.speed = speed;
+        }
+
+        self
+    }
+
+    /// Release a given amount of media at the beginning immediately. It
+    /// can be used for filling buffers of the downstream server or player
+    /// in order to reduce the startup time. There is no initial burst by
+    /// default.
+    pub fn initial_burst(mut self, burst: Duration) -> Self {
+        self.initial_burst = burst;
+        self
+    }
+
+    /// Set the maximum timestamp gap. Timestamps jumping forward by more
+    /// than this or backward by more than this are considered to be
+    /// discontinuities and the pacing is re-synchronized. The default is
+    /// 10 seconds.
+    pub fn max_gap(mut self, gap: Duration) -> Self {
+        self.max_gap = gap;
+        self
+    }
+
+    /// Build the pacing stage using the system clock.
+    pub fn build<T>(self) -> Pacing<T, SystemClock> {
+        self.build_with_clock(SystemClock::new())
+    }
+
+    /// Build the pacing stage using a given clock.
+    pub fn build_with_clock<T, C>(self, clock: C) -> Pacing<T, C>
+    where
+        C: Clock,
+    {
+        let pacer = Pacer::new(clock)
+            .with_speed(self.speed)
+            .with_initial_burst(self.initial_burst)
+            .with_max_gap(self.max_gap);
+
+        Pacing {
+            pacer,
+            queue: VecDeque::new(),
+        }
+    }
+}
+
+/// Pacing stage. It releases packets or frames according to their
+/// timestamps against a clock, i.e. it is an equivalent of the FFmpeg `-re`
+/// flag. It is needed e.g. when replaying files into live outputs (RTMP,
+/// SRT, etc.), so that the downstream servers are not flooded.
+///
+/// The media is released in the order it was pushed. Packets are paced
+/// using their DTS, frames are paced using their PTS. Media without a
+/// timestamp is released together with the preceding media.
+///
+/// # Example
+/// ```text
+/// let mut pacing = Pacing::new();
+///
+/// while let Some(packet) = demuxer.take()? {
+///     pacing.push(packet);
+///
+///     while let Some(packet) = pacing.take() {
+///         muxer.push(packet)?;
+///     }
+///
+///     // the pacing stage keeps at most one second of media
+///     while pacing.delay().unwrap_or_default() > Duration::from_secs(1) {
+///         muxer.push(pacing.wait().unwrap())?;
+///     }
+/// }
+///
+/// pacing.flush();
+///
+/// while let Some(packet) = pacing.wait() {
+///     muxer.push(packet)?;
+/// }
+/// ```
+pub struct Pacing<T, C = SystemClock> {
+    pacer: Pacer<C>,
+    queue: VecDeque<(i64, T)>,
+}
+
+impl<T> Pacing<T, SystemClock> {
+    /// Get a builder for the pacing stage.
+    pub fn builder() -> PacingBuilder {
+        PacingBuilder::new()
+    }
+
+    /// Create a new pacing stage with the default settings using the system
+    /// clock.
+    pub fn new() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl<T> Default for Pacing<T, SystemClock> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, C> Pacing<T, C>
+where
+    T: Paced,
+    C: Clock,
+{
+    /// Push a given packet or frame.
+    pub fn push(&mut self, item: T) {
+        let now = self.now();
+
+        let deadline = self.deadline(item.pacing_timestamp(), now);
+
+        self.queue.push_back((deadline, item));
+    }
+
+    /// Take the next packet or frame if it is ready to be released.
+    pub fn take(&mut self) -> Option<T> {
+        let (deadline, _) = self.queue.front()?;
+
+        if *deadline > self.now() {
+            return None;
+        }
+
+        self.queue.pop_front().map(|(_, item)| item)
+    }
+
+    /// Block the current thread until the next packet or frame is ready to
+    /// be released and take it. None is returned if the stage is empty.
+    pub fn wait(&mut self) -> Option<T> {
+        while let Some(delay) = self.delay() {
+            if delay == Duration::from_secs(0) {
+                break;
+            }
+
+            thread::sleep(delay);
+        }
+
+        self.queue.pop_front().map(|(_, item)| item)
+    }
+
+    /// Get the time remaining until the next packet or frame is ready to be
+    /// released (if any).
+    pub fn delay(&self) -> Option<Duration> {
+        let (deadline, _) = self.queue.front()?;
+
+        let delay = (*deadline - self.now()).max(0);
+
+        Some(Duration::from_micros(delay as u64))
+    }
+
+    /// Release all buffered packets or frames immediately.
+    pub fn flush(&mut self) {
+        for (deadline, _) in &mut self.queue {
+            *deadline = i64::MIN;
+        }
+    }
+
+    /// Drop all buffered packets or frames and reset the pacing. The next
+    /// packet or frame will be released immediately (including the initial
+    /// burst).
+    pub fn reset(&mut self) {
+        self.queue.clear();
+        self.pacer.reset();
+    }
+
+    /// Get the number of buffered packets or frames.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Check if the stage is empty.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Get the current clock time in microseconds.
+    fn now(&self) -> i64 {
+        self.pacer.now().unwrap_or(0)
+    }
+
+    /// Calculate the release deadline of media with a given timestamp.
+    /// Media without a timestamp is released together with the preceding
+    /// media.
+    fn deadline(&mut self, timestamp: Timestamp, now: i64) -> i64 {
+        self.pacer
+            .release_time(timestamp, now)
+            .or_else(|| self.pacer.last_release_time())
+            .unwrap_or(now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Paced, Pacing};
+
+    use crate::{test_util::ManualClock, time::Timestamp};
+
+    impl Paced for i64 {
+        fn pacing_timestamp(&self) -> Timestamp {
+            Timestamp::from_millis(*self)
+        }
+    }
+
+    #[test]
+    fn test_pacing() {
+        let clock = ManualClock::new(0);
+
+        let mut pacing = Pacing::<i64>::builder().build_with_clock(&clock);
+
+        for ts in &[1_000, 1_040, 1_080] {
+            pacing.push(*ts);
+        }
+
+        assert_eq!(pacing.take(), Some(1_000));
+        assert_eq!(pacing.take(), None);
+        assert_eq!(pacing.delay(), Some(Duration::from_millis(40)));
+
+        clock.set(80_000);
+
+        assert_eq!(pacing.take(), Some(1_040));
+        assert_eq!(pacing.take(), Some(1_080));
+
+        // discontinuity
+        pacing.push(100_000);
+        pacing.push(100_040);
+
+        assert_eq!(pacing.take(), Some(100_000));
+        assert_eq!(pacing.delay(), Some(Duration::from_millis(40)));
+    }
+
+    #[test]
+    fn test_initial_burst() {
+        let clock = ManualClock::new(0);
+
+        let mut pacing = Pacing::<i64>::builder()
+            .initial_burst(Duration::from_secs(1))
+            .speed(2.0)
+            .build_with_clock(&clock);
+
+        for ts in (0..20).map(|i| i * 100) {
+            pacing.push(ts);
+        }
+
+        let mut released = 0;
+
+        while pacing.take().is_some() {
+            released += 1;
+        }
+
+        assert_eq!(released, 11);
+        assert_eq!(pacing.delay(), Some(Duration::from_millis(50)));
+    }
+}
@@ -0,0 +1,266 @@
+//! Pre-roll buffer for event-triggered recording.
+
+use std::{collections::VecDeque, io::Write, time::Duration};
+
+use crate::{format::muxer::Muxer, packet::Packet, Error};
+
+/// Builder for the pre-roll buffer.
+pub struct PreRollBufferBuilder {
+    duration: Duration,
+    key_stream: Option<usize>,
+    max_bytes: Option<usize>,
+}
+
+impl PreRollBufferBuilder {
+    /// Create a new builder.
+    fn new() -> Self {
+        Self {
+            duration: Duration::from_secs(10),
+            key_stream: None,
+            max_bytes: None,
+        }
+    }
+
+    /// Set the minimum amount of media retained in the buffer. The default
+    /// is 10 seconds.
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Set index of the stream whose keyframes define the points where the
+    /// buffered data can start (typically the video stream). If not set,
+    /// the key flag of packets of all streams is used.
+    pub fn key_stream(mut self, stream_index: Option<usize>) -> Self {
+        self.key_stream = stream_index;
+        self
+    }
+
+    /// Set the maximum total size of the buffered packets. The oldest data
+    /// will be dropped if the limit is exceeded even if the buffer would not
+    /// cover the requested duration. There is no limit by default.
+    pub fn max_bytes(mut self, bytes: Option<usize>) -> Self {
+        self.max_bytes = bytes;
+        self
+    }
+
+    /// Build the buffer.
+    pub fn build(self) -> PreRollBuffer {
+        PreRollBuffer {
+            duration: self.duration.as_micros() as i64,
+            key_stream: self.key_stream,
+            max_bytes: self.max_bytes,
+            gops: VecDeque::new(),
+            bytes: 0,
+        }
+    }
+}
+
+/// Group of packets starting with a keyframe.
+struct Gop {
+    start: Option<i64>,
+    bytes: usize,
+    packets: Vec<Packet>,
+}
+
+/// Pre-roll ring buffer. It continuously retains the last N seconds of
+/// pushed packets. When an external trigger fires (e.g. motion detection),
+/// the buffered packets can be written into a new muxer, so that the
+/// recording contains also the moments before the event.
+///
+/// The buffered data always starts with a keyframe, so it may cover
+/// slightly more than the requested duration. Packets pushed before the
+/// first keyframe are dropped.
+pub struct PreRollBuffer {
+    duration: i64,
+    key_stream: Option<usize>,
+    max_bytes: Option<usize>,
+    gops: VecDeque<Gop>,
+    bytes: usize,
+}
+
+impl PreRollBuffer {
+    /// Get a builder for the pre-roll buffer.
+    pub fn builder() -> PreRollBufferBuilder {
+        PreRollBufferBuilder::new()
+    }
+
+    /// Create a new pre-roll buffer retaining a given amount of media.
+    pub fn new(duration: Duration) -> Self {
+        Self::builder().duration(duration).build()
+    }
+
+    /// Push a given packet.
+    pub fn push(&mut self, packet: Packet) {
+        let key = packet.is_key()
+            && self
+                .key_stream
+                .map(|index| packet.stream_index() == index)
+                .unwrap_or(true);
+
+        let timestamp = packet
+            .dts()
+            .as_micros()
+            .or_else(|| packet.pts().as_micros());
+
+        if key {
+            self.gops.push_back(Gop {
+                start: timestamp,
+                bytes: 0,
+                packets: Vec::new(),
+            });
+
+            if let Some(current) = timestamp {
+                self.trim(current);
+            }
+        }
+
+        let size = packet.data().len();
+
+        if let Some(gop) = self.gops.back_mut() {
+            gop.bytes += size;
+            gop.packets.push(packet);
+
+            self.bytes += size;
+        }
+
+        if let Some(max) = self.max_bytes {
+            while self.bytes > max && self.gops.len() > 1 {
+                self.pop_front();
+            }
+        }
+    }
+
+    /// Get the buffered packets in the order they were pushed.
+    pub fn packets(&self) -> impl Iterator<Item = &Packet> {
+        self.gops.iter().flat_map(|gop| gop.packets.iter())
+    }
+
+    /// Get the number of buffered packets.
+    pub fn len(&self) -> usize {
+        self.gops.iter().map(|gop| gop.packets.len()).sum()
+    }
+
+    /// Check if the buffer is empty.
+    pub fn is_empty(&self) -> bool {
+        self.gops.iter().all(|gop| gop.packets.is_empty())
+    }
+
+    /// Get the total size of the buffered packets.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Drop all buffered packets.
+    pub fn clear(&mut self) {
+        self.gops.clear();
+        self.bytes = 0;
+    }
+
+    /// Take all buffered packets and clear the buffer.
+    pub fn drain(&mut self) -> Vec<Packet> {
+        let res = std::mem::take(&mut self.gops)
+            .into_iter()
+            .flat_map(|gop| gop.packets)
+            .collect();
+
+        self.bytes = 0;
+
+        res
+    }
+
+    /// Write all buffered packets into a given muxer and clear the buffer.
+    /// The muxer is expected to have the same streams as the input.
+    pub fn flush_into<W>(&mut self, muxer: &mut Muxer<W>) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        for packet in self.drain() {
+            muxer.push(packet)?;
+        }
+
+        Ok(())
+    }
+
+    /// Drop groups of packets that are not needed to cover the requested
+    /// duration.
+    fn trim(&mut self, current: i64) {
+        while self.gops.len() > 1 {
+            let next = self.gops[1].start;
+
+            match next {
+                Some(start) if (current - start) >= self.duration => self.pop_front(),
+                _ => break,
+            }
+        }
+    }
+
+    /// Drop the oldest group of packets.
+    fn pop_front(&mut self) {
+        if let Some(gop) = self.gops.pop_front() {
+            self.bytes -= gop.bytes;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::PreRollBuffer;
+
+    use crate::{test_util::packet, time::TimeBase};
+
+    #[test]
+    fn test_retention() {
+        let mut buffer = PreRollBuffer::new(Duration::from_secs(2));
+
+        // no keyframe yet
+        buffer.push(
+            packet(10, TimeBase::MICROSECONDS, 0)
+                .with_key_flag(false)
+                .freeze(),
+        );
+
+        assert!(buffer.is_empty());
+
+        // one keyframe per second, 4 packets per second
+        for i in 0..20 {
+            buffer.push(
+                packet(10, TimeBase::MICROSECONDS, 1_000_000 + i * 250_000)
+                    .with_key_flag(i % 4 == 0)
+                    .freeze(),
+            );
+        }
+
+        let first = buffer.packets().next().map(|p| p.dts().timestamp());
+
+        // last packet is at 5.75 s, so the buffer must start at 3 s
+        assert_eq!(first, Some(3_000_000));
+        assert_eq!(buffer.len(), 12);
+        assert_eq!(buffer.bytes(), 120);
+
+        let packets = buffer.drain();
+
+        assert_eq!(packets.len(), 12);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_max_bytes() {
+        let mut buffer = PreRollBuffer::builder()
+            .duration(Duration::from_secs(60))
+            .max_bytes(Some(25))
+            .build();
+
+        for i in 0..6 {
+            buffer.push(
+                packet(10, TimeBase::MICROSECONDS, i * 1_000_000)
+                    .with_key_flag(i % 2 == 0)
+                    .freeze(),
+            );
+        }
+
+        assert_eq!(buffer.len(), 2);
+    }
+}
@@ -0,0 +1,432 @@
+//! Metadata, side data, chapter and disposition preservation.
+
+use crate::{
+    format::{
+        demuxer::Demuxer,
+        muxer::MuxerBuilder,
+        stream::{Disposition, Stream},
+    },
+    packet::SideDataType,
+    Error,
+};
+
+/// Rule deciding which items are propagated from an input to an output.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum PreservationRule<T> {
+    /// Propagate all items.
+    PreserveAll,
+    /// Propagate only the listed items.
+    Allowlist(Vec<T>),
+    /// Do not propagate any items.
+    Strip,
+}
+
+impl<T> PreservationRule<T>
+where
+    T: PartialEq,
+{
+    /// Check if a given item should be propagated.
+    pub fn allows(&self, item: &T) -> bool {
+        match self {
+            Self::PreserveAll => true,
+            Self::Allowlist(items) => items.contains(item),
+            Self::Strip => false,
+        }
+    }
+}
+
+impl PreservationRule<String> {
+    /// Check if a metadata entry with a given key should be propagated. Keys
+    /// are compared case-insensitively.
+    pub fn allows_key(&self, key: &str) -> bool {
+        match self {
+            Self::PreserveAll => true,
+            Self::Allowlist(keys) => keys.iter().any(|k| k.eq_ignore_ascii_case(key)),
+            Self::Strip => false,
+        }
+    }
+}
+
+impl PreservationRule<Disposition> {
+    /// Get the part of a given disposition that should be propagated.
+    pub fn filter(&self, disposition: Disposition) -> Disposition {
+        match self {
+            Self::PreserveAll => disposition,
+            Self::Allowlist(flags) => {
+                let mask = flags
+                    .iter()
+                    .fold(Disposition::empty(), |mask, &flag| mask | flag);
+
+                Disposition::from_bits(disposition.bits() & mask.bits())
+            }
+            Self::Strip => Disposition::empty(),
+        }
+    }
+}
+
+/// Policy controlling which metadata, side data, chapters and dispositions
+/// are propagated from an input to an output. The same metadata rule is
+/// used for container, stream and chapter metadata. Only stream side data
+/// is affected by the policy, packet side data is passed through as is.
+///
+/// # Example
+/// ```text
+/// let policy = PreservationPolicy::preserve_all()
+///     .with_metadata(PreservationRule::Allowlist(vec![
+///         String::from("title"),
+///         String::from("language"),
+///     ]))
+///     .with_chapters(false);
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PreservationPolicy {
+    metadata: PreservationRule<String>,
+    side_data: PreservationRule<SideDataType>,
+    dispositions: PreservationRule<Disposition>,
+    chapters: bool,
+}
+
+impl PreservationPolicy {
+    /// Create a policy preserving everything.
+    pub fn preserve_all() -> Self {
+        Self {
+            metadata: PreservationRule::PreserveAll,
+            side_data: PreservationRule::PreserveAll,
+            dispositions: PreservationRule::PreserveAll,
+            chapters: true,
+        }
+    }
+
+    /// Create a policy stripping everything.
+    pub fn strip_all() -> Self {
+        Self {
+            metadata: PreservationRule::Strip,
+            side_data: PreservationRule::Strip,
+            dispositions: PreservationRule::Strip,
+            chapters: false,
+        }
+    }
+
+    /// Set the metadata rule.
+    pub fn with_metadata(mut self, rule: PreservationRule<String>) -> Self {
+        self.metadata = rule;
+        self
+    }
+
+    /// Set the stream side data rule.
+    pub fn with_side_data(mut self, rule: PreservationRule<SideDataType>) -> Self {
+        self.side_data = rule;
+        self
+    }
+
+    /// Set the disposition rule.
+    pub fn with_dispositions(mut self, rule: PreservationRule<Disposition>) -> Self {
+        self.dispositions = rule;
+        self
+    }
+
+    /// Enable or disable chapter propagation.
+    pub fn with_chapters(mut self, chapters: bool) -> Self {
+        self.chapters = chapters;
+        self
+    }
+
+    /// Get the metadata rule.
+    pub fn metadata(&self) -> &PreservationRule<String> {
+        &self.metadata
+    }
+
+    /// Get the stream side data rule.
+    pub fn side_data(&self) -> &PreservationRule<SideDataType> {
+        &self.side_data
+    }
+
+    /// Get the disposition rule.
+    pub fn dispositions(&self) -> &PreservationRule<Disposition> {
+        &self.dispositions
+    }
+
+    /// Check if chapters should be propagated.
+    pub fn chapters(&self) -> bool {
+        self.chapters
+    }
+
+    /// Propagate container metadata and chapters from a given demuxer into
+    /// a given muxer builder.
+    pub fn apply_container<T>(
+        &self,
+        input: &Demuxer<T>,
+        mut output: MuxerBuilder,
+    ) -> Result<MuxerBuilder, Error> {
+        for (key, value) in input.metadata() {
+            if self.metadata.allows_key(key) {
+                output = output.set_metadata(key, value);
+            }
+        }
+
+        if self.chapters {
+            for mut chapter in input.chapters() {
+                chapter.retain_metadata(|(key, _)| self.metadata.allows_key(key));
+
+                output.add_chapter(&chapter)?;
+            }
+        }
+
+        Ok(output)
+    }
+
+    /// Propagate metadata, side data and disposition from a given input
+    /// stream into a given output stream.
+    pub fn apply_stream(&self, input: &Stream, output: &mut Stream) -> Result<(), Error> {
+        let mut metadata = input.metadata_dict().into_iter().collect::<Vec<_>>();
+
+        // keep the output stable
+        metadata.sort_unstable();
+
+        for (key, value) in metadata {
+            if self.metadata.allows_key(key) {
+                output.set_metadata(key, value);
+            }
+        }
+
+        for side_data in input.side_data() {
+            if self.side_data.allows(&side_data.data_type()) {
+                output.add_side_data(side_data.data_type(), side_data.data())?;
+            }
+        }
+
+        output.set_disposition(self.dispositions.filter(input.disposition()));
+
+        Ok(())
+    }
+}
+
+impl Default for PreservationPolicy {
+    fn default() -> Self {
+        Self::preserve_all()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::{PreservationPolicy, PreservationRule};
+
+    use crate::{
+        format::{
+            chapter::Chapter,
+            demuxer::{Demuxer, DemuxerWithStreamInfo},
+            io::IO,
+            muxer::{Muxer, MuxerBuilder, OutputFormat},
+            stream::Disposition,
+        },
+        test_util::encode_video,
+        time::Timestamp,
+    };
+
+    type Input = DemuxerWithStreamInfo<Cursor<Vec<u8>>>;
+
+    /// Open a given in-memory file.
+    fn demux(data: Vec<u8>) -> Input {
+        Demuxer::builder()
+            .build(IO::from_seekable_read_stream(Cursor::new(data)))
+            .unwrap()
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)
+            .unwrap()
+    }
+
+    /// Remux a given in-memory file into Matroska. The output muxer can be
+    /// configured using a given closure.
+    fn remux<F>(data: Vec<u8>, configure: F) -> Vec<u8>
+    where
+        F: FnOnce(&Input, MuxerBuilder) -> MuxerBuilder,
+    {
+        let mut demuxer = demux(data);
+
+        let mut builder = Muxer::builder();
+
+        for stream in demuxer.streams() {
+            builder.add_stream(&stream.codec_parameters()).unwrap();
+        }
+
+        let builder = configure(&demuxer, builder);
+
+        let io = IO::from_seekable_write_stream(Cursor::new(Vec::new()));
+
+        let mut muxer = builder
+            .build(io, OutputFormat::find_by_name("matroska").unwrap())
+            .unwrap();
+
+        while let Some(packet) = demuxer.take().unwrap() {
+            muxer.push(packet).unwrap();
+        }
+
+        muxer.finish().unwrap().into_inner()
+    }
+
+    /// Create a Matroska file with container metadata, chapters, stream
+    /// metadata and stream disposition.
+    fn source() -> Vec<u8> {
+        remux(encode_video(64, 48, 50, 10), |_, builder| {
+            let mut builder = builder
+                .set_metadata("title", "Source")
+                .set_metadata("comment", "container comment");
+
+            let intro = Chapter::new(1, Timestamp::from_millis(0), Timestamp::from_millis(800))
+                .with_title("Intro");
+
+            let main = Chapter::new(2, Timestamp::from_millis(800), Timestamp::from_millis(2000))
+                .with_title("Main")
+                .with_metadata("comment", "chapter comment");
+
+            builder.add_chapter(&intro).unwrap();
+            builder.add_chapter(&main).unwrap();
+
+            let stream = &mut builder.streams_mut()[0];
+
+            stream.set_metadata("language", "ger");
+            stream.set_metadata("title", "Video");
+            stream.set_metadata("comment", "stream comment");
+            stream.set_disposition(Disposition::FORCED | Disposition::COMMENT);
+
+            builder
+        })
+    }
+
+    /// Remux a given file using a given preservation policy.
+    fn apply(data: Vec<u8>, policy: &PreservationPolicy) -> Input {
+        let output = remux(data, |input, builder| {
+            let mut builder = policy.apply_container(input, builder).unwrap();
+
+            policy
+                .apply_stream(&input.streams()[0], &mut builder.streams_mut()[0])
+                .unwrap();
+
+            builder
+        });
+
+        demux(output)
+    }
+
+    /// Get a container metadata entry.
+    fn get_metadata<'a>(input: &'a Input, key: &str) -> Option<&'a str> {
+        input
+            .metadata()
+            .into_iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v)
+    }
+
+    #[test]
+    fn test_metadata_rule() {
+        let rule = PreservationRule::Allowlist(vec![String::from("title")]);
+
+        assert!(rule.allows_key("title"));
+        assert!(rule.allows_key("TITLE"));
+        assert!(!rule.allows_key("encoder"));
+        assert!(!PreservationRule::<String>::Strip.allows_key("title"));
+    }
+
+    #[test]
+    fn test_disposition_rule() {
+        let disposition = Disposition::DEFAULT | Disposition::COMMENT;
+
+        let rule = PreservationRule::Allowlist(vec![Disposition::DEFAULT]);
+
+        assert_eq!(rule.filter(disposition), Disposition::DEFAULT);
+        assert_eq!(
+            PreservationRule::PreserveAll.filter(disposition),
+            disposition
+        );
+        assert_eq!(
+            PreservationRule::Strip.filter(disposition),
+            Disposition::empty()
+        );
+    }
+
+    #[test]
+    fn test_preserve_all() {
+        let input = apply(source(), &PreservationPolicy::preserve_all());
+
+        assert_eq!(get_metadata(&input, "title"), Some("Source"));
+        assert_eq!(get_metadata(&input, "comment"), Some("container comment"));
+
+        let chapters = input.chapters();
+
+        assert_eq!(chapters.len(), 2);
+
+        assert_eq!(chapters[0].start().as_millis(), Some(0));
+        assert_eq!(chapters[0].end().as_millis(), Some(800));
+        assert_eq!(chapters[0].title(), Some("Intro"));
+
+        assert_eq!(chapters[1].start().as_millis(), Some(800));
+        assert_eq!(chapters[1].end().as_millis(), Some(2000));
+        assert_eq!(chapters[1].title(), Some("Main"));
+
+        let stream = &input.streams()[0];
+
+        assert_eq!(stream.get_metadata("language"), Some("ger"));
+        assert_eq!(stream.get_metadata("title"), Some("Video"));
+        assert_eq!(stream.get_metadata("comment"), Some("stream comment"));
+
+        let disposition = stream.disposition();
+
+        assert!(disposition.contains(Disposition::FORCED));
+        assert!(disposition.contains(Disposition::COMMENT));
+    }
+
+    #[test]
+    fn test_allowlist() {
+        let policy = PreservationPolicy::preserve_all()
+            .with_metadata(PreservationRule::Allowlist(vec![
+                String::from("title"),
+                String::from("language"),
+            ]))
+            .with_dispositions(PreservationRule::Allowlist(vec![Disposition::FORCED]));
+
+        let input = apply(source(), &policy);
+
+        assert_eq!(get_metadata(&input, "title"), Some("Source"));
+        assert_eq!(get_metadata(&input, "comment"), None);
+
+        let chapters = input.chapters();
+
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[0].title(), Some("Intro"));
+        assert_eq!(chapters[1].title(), Some("Main"));
+        assert_eq!(chapters[1].get_metadata("comment"), None);
+
+        let stream = &input.streams()[0];
+
+        assert_eq!(stream.get_metadata("language"), Some("ger"));
+        assert_eq!(stream.get_metadata("title"), Some("Video"));
+        assert_eq!(stream.get_metadata("comment"), None);
+
+        let disposition = stream.disposition();
+
+        assert!(disposition.contains(Disposition::FORCED));
+        assert!(!disposition.contains(Disposition::COMMENT));
+    }
+
+    #[test]
+    fn test_strip_all() {
+        let input = apply(source(), &PreservationPolicy::strip_all());
+
+        assert_eq!(get_metadata(&input, "title"), None);
+        assert_eq!(get_metadata(&input, "comment"), None);
+
+        assert!(input.chapters().is_empty());
+
+        let stream = &input.streams()[0];
+
+        assert_eq!(stream.get_metadata("title"), None);
+        assert_eq!(stream.get_metadata("comment"), None);
+
+        let disposition = stream.disposition();
+
+        assert!(!disposition.contains(Disposition::FORCED));
+        assert!(!disposition.contains(Disposition::COMMENT));
+    }
+}
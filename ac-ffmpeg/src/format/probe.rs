@@ -0,0 +1,441 @@
+//! Fast metadata-only media inspection.
+
+use std::{
+    io::{Cursor, Read},
+    time::Duration,
+};
+
+use crate::{
+    codec::CodecParameters,
+    format::{
+        demuxer::{Demuxer, DemuxerBuilder, DurationSource, InputFormat},
+        io::IO,
+        stream::Stream,
+    },
+    time::{Rational, TimeBase, Timestamp},
+    Error,
+};
+
+/// Information about a single stream.
+pub struct StreamInfo {
+    index: usize,
+    stream_id: i32,
+    time_base: TimeBase,
+    start_time: Timestamp,
+    duration: Timestamp,
+    frames: Option<u64>,
+    frame_rate: Option<Rational>,
+    frame_rate_estimated: bool,
+    codec_parameters: CodecParameters,
+}
+
+impl StreamInfo {
+    /// Collect info about a given stream.
+    fn new(index: usize, stream: &Stream) -> Self {
+        let codec_parameters = stream.codec_parameters();
+
+        let avg_frame_rate = stream.avg_frame_rate();
+
+        let (frame_rate, frame_rate_estimated) = if !codec_parameters.is_video_codec() {
+            (None, false)
+        } else if avg_frame_rate.is_some() {
+            (avg_frame_rate, false)
+        } else {
+            (stream.real_frame_rate_rational(), true)
+        };
+
+        Self {
+            index,
+            stream_id: stream.stream_id(),
+            time_base: stream.time_base(),
+            start_time: stream.start_time(),
+            duration: stream.duration(),
+            frames: stream.frames(),
+            frame_rate,
+            frame_rate_estimated,
+            codec_parameters,
+        }
+    }
+
+    /// Get index of the stream within the container.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Get the container-specific stream id.
+    pub fn stream_id(&self) -> i32 {
+        self.stream_id
+    }
+
+    /// Get stream time base.
+    pub fn time_base(&self) -> TimeBase {
+        self.time_base
+    }
+
+    /// Get the pts of the first frame of the stream in presentation order.
+    pub fn start_time(&self) -> Timestamp {
+        self.start_time
+    }
+
+    /// Get the duration of the stream.
+    pub fn duration(&self) -> Timestamp {
+        self.duration
+    }
+
+    /// Get the number of frames in the stream (if known).
+    pub fn frames(&self) -> Option<u64> {
+        self.frames
+    }
+
+    /// Get the average frame rate of a video stream (if known).
+    pub fn frame_rate(&self) -> Option<Rational> {
+        self.frame_rate
+    }
+
+    /// Check if the frame rate is only guessed from the stream timestamps
+    /// (i.e. the average frame rate is not known).
+    pub fn is_frame_rate_estimated(&self) -> bool {
+        self.frame_rate_estimated
+    }
+
+    /// Get codec parameters.
+    pub fn codec_parameters(&self) -> &CodecParameters {
+        &self.codec_parameters
+    }
+
+    /// Check if the codec parameters are complete, i.e. if the resolution
+    /// and the pixel format of a video stream or the sample rate, the
+    /// channel layout and the sample format of an audio stream are known.
+    /// Parameters may be incomplete if the fast mode is used.
+    pub fn is_complete(&self) -> bool {
+        if let Some(params) = self.codec_parameters.as_video_codec_parameters() {
+            params.width() > 0 && params.height() > 0 && params.pixel_format().into_raw() >= 0
+        } else if let Some(params) = self.codec_parameters.as_audio_codec_parameters() {
+            params.sample_rate() > 0
+                && params.channel_layout().channels() > 0
+                && params.sample_format().into_raw() >= 0
+        } else {
+            true
+        }
+    }
+}
+
+/// Result of media probing.
+pub struct MediaInfo {
+    format_name: String,
+    duration: Option<Duration>,
+    duration_source: Option<DurationSource>,
+    bit_rate: Option<u64>,
+    streams: Vec<StreamInfo>,
+}
+
+impl MediaInfo {
+    /// Get name of the container format.
+    pub fn format_name(&self) -> &str {
+        &self.format_name
+    }
+
+    /// Get the estimated duration of the input.
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    /// Get the source of the duration (if known).
+    pub fn duration_source(&self) -> Option<DurationSource> {
+        self.duration_source
+    }
+
+    /// Check if the duration is only a rough estimate (i.e. it was derived
+    /// from the bit rate).
+    pub fn is_duration_estimated(&self) -> bool {
+        self.duration_source
+            .map(|source| source.is_estimate())
+            .unwrap_or(false)
+    }
+
+    /// Get the estimated total bit rate in bits per second.
+    pub fn bit_rate(&self) -> Option<u64> {
+        self.bit_rate
+    }
+
+    /// Get information about individual streams.
+    pub fn streams(&self) -> &[StreamInfo] {
+        &self.streams
+    }
+}
+
+/// Builder for media probing.
+pub struct ProbeBuilder {
+    probe_size: Option<u64>,
+    analyze_duration: Option<Duration>,
+    input_format: Option<InputFormat>,
+    fast: bool,
+}
+
+impl ProbeBuilder {
+    /// Create a new builder.
+    fn new() -> Self {
+        Self {
+            probe_size: None,
+            analyze_duration: None,
+            input_format: None,
+            fast: false,
+        }
+    }
+
+    /// Set the maximum number of bytes read in order to detect the input
+    /// format and stream parameters. The FFmpeg default will be used if not
+    /// set.
+    pub fn probe_size(mut self, bytes: u64) -> Self {
+        self.probe_size = Some(bytes);
+        self
+    }
+
+    /// Set the maximum duration of the input analyzed in order to detect
+    /// stream parameters. The FFmpeg default will be used if not set.
+    pub fn analyze_duration(mut self, duration: Duration) -> Self {
+        self.analyze_duration = Some(duration);
+        self
+    }
+
+    /// Set input format. If the input format is not set, it will be guessed
+    /// from the input.
+    pub fn input_format(mut self, format: Option<InputFormat>) -> Self {
+        self.input_format = format;
+        self
+    }
+
+    /// Enable or disable the fast mode. In the fast mode, only the container
+    /// headers and a small part of the input are analyzed (see
+    /// `Demuxer::find_stream_info_fast()`). It is meant for indexers that
+    /// need to probe a large number of files. Use
+    /// `MediaInfo::is_duration_estimated()`, `StreamInfo::is_complete()` and
+    /// `StreamInfo::is_frame_rate_estimated()` to find out which fields are
+    /// not reliable. The fast mode is disabled by default.
+    pub fn fast(mut self, fast: bool) -> Self {
+        self.fast = fast;
+        self
+    }
+
+    /// Probe a given input.
+    pub fn probe<T>(self, io: IO<T>) -> Result<MediaInfo, Error>
+    where
+        T: Read,
+    {
+        let fast = self.fast;
+
+        let builder = self.into_demuxer_builder();
+
+        let demuxer = builder.build(io)?;
+
+        inspect(demuxer, fast)
+    }
+
+    /// Probe a given in-memory input.
+    pub fn probe_bytes(mut self, data: &[u8]) -> Result<MediaInfo, Error> {
+        if self.input_format.is_none() {
+            self.input_format = InputFormat::probe(data);
+        }
+
+        self.probe(IO::from_seekable_read_stream(Cursor::new(data)))
+    }
+
+    /// Probe a given URL.
+    pub fn probe_url(self, url: &str) -> Result<MediaInfo, Error> {
+        let fast = self.fast;
+
+        let builder = self.into_demuxer_builder();
+
+        let demuxer = builder.build_from_url(url)?;
+
+        inspect(demuxer, fast)
+    }
+
+    /// Create a demuxer builder with the probing options applied.
+    fn into_demuxer_builder(self) -> DemuxerBuilder {
+        let mut builder = Demuxer::builder().input_format(self.input_format);
+
+        if let Some(probe_size) = self.probe_size {
+            builder = builder.probe_size(probe_size);
+        }
+
+        if let Some(duration) = self.analyze_duration {
+            builder = builder.analyze_duration(duration);
+        }
+
+        builder
+    }
+}
+
+/// Find stream info of a given demuxer and collect the results.
+fn inspect<T>(demuxer: Demuxer<T>, fast: bool) -> Result<MediaInfo, Error> {
+    let demuxer = if fast {
+        demuxer.find_stream_info_fast()
+    } else {
+        demuxer.find_stream_info(None)
+    }
+    .map_err(|(_, err)| err)?;
+
+    let streams = demuxer
+        .streams()
+        .iter()
+        .enumerate()
+        .map(|(index, stream)| StreamInfo::new(index, stream))
+        .collect();
+
+    let res = MediaInfo {
+        format_name: demuxer.input_format().name().to_string(),
+        duration: demuxer.duration(),
+        duration_source: demuxer.duration_source(),
+        bit_rate: demuxer.bit_rate(),
+        streams,
+    };
+
+    Ok(res)
+}
+
+/// Get a builder for media probing.
+pub fn builder() -> ProbeBuilder {
+    ProbeBuilder::new()
+}
+
+/// Probe a given input using the default settings. Only the container
+/// headers and the beginning of the input are read, no packets are
+/// decoded.
+///
+/// # Example
+/// ```text
+/// ...
+///
+/// let io = IO::from_seekable_read_stream(File::open("movie.mkv")?);
+///
+/// let info = probe(io)?;
+///
+/// println!("{}: {:?}", info.format_name(), info.duration());
+///
+/// for stream in info.streams() {
+///     ...
+/// }
+///
+/// ...
+/// ```
+pub fn probe<T>(io: IO<T>) -> Result<MediaInfo, Error>
+where
+    T: Read,
+{
+    ProbeBuilder::new().probe(io)
+}
+
+/// Probe a given in-memory input using the default settings.
+pub fn probe_bytes(data: &[u8]) -> Result<MediaInfo, Error> {
+    ProbeBuilder::new().probe_bytes(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, time::Duration};
+
+    use super::{builder, probe, probe_bytes, MediaInfo};
+
+    use crate::{
+        format::{
+            demuxer::{DurationSource, InputFormat},
+            io::IO,
+        },
+        test_util::encode_video,
+    };
+
+    /// Check media info of the `encode_video(64, 48, 50, 10)` output.
+    fn check_media_info(info: &MediaInfo) {
+        assert_eq!(info.format_name(), "matroska,webm");
+
+        // 50 frames at 25 fps
+        let duration = info.duration().unwrap();
+
+        assert!(duration >= Duration::from_millis(1900));
+        assert!(duration <= Duration::from_millis(2100));
+
+        assert_eq!(info.streams().len(), 1);
+
+        let stream = &info.streams()[0];
+
+        assert_eq!(stream.index(), 0);
+        assert!(stream.is_complete());
+
+        let params = stream
+            .codec_parameters()
+            .as_video_codec_parameters()
+            .unwrap();
+
+        assert_eq!(params.width(), 64);
+        assert_eq!(params.height(), 48);
+    }
+
+    #[test]
+    fn test_probe() {
+        let data = encode_video(64, 48, 50, 10);
+
+        let info = probe(IO::from_read_stream(Cursor::new(data.clone()))).unwrap();
+
+        check_media_info(&info);
+
+        let info = probe(IO::from_seekable_read_stream(Cursor::new(data.clone()))).unwrap();
+
+        check_media_info(&info);
+
+        let info = probe_bytes(&data).unwrap();
+
+        check_media_info(&info);
+    }
+
+    #[test]
+    fn test_fast_mode() {
+        let data = encode_video(64, 48, 50, 10);
+
+        let full = builder().probe_bytes(&data).unwrap();
+        let fast = builder().fast(true).probe_bytes(&data).unwrap();
+
+        check_media_info(&full);
+        check_media_info(&fast);
+
+        // the duration is stored in the Matroska header
+        assert_eq!(full.duration_source(), Some(DurationSource::Stream));
+        assert_eq!(fast.duration_source(), Some(DurationSource::Stream));
+
+        assert!(!full.is_duration_estimated());
+        assert!(!fast.is_duration_estimated());
+
+        // the average frame rate is calculated from the analyzed frames
+        let stream = &full.streams()[0];
+
+        let frame_rate = stream.frame_rate().unwrap();
+
+        assert!(!stream.is_frame_rate_estimated());
+        assert!((frame_rate.num() as f64 / frame_rate.den() as f64 - 25.0).abs() < 0.01);
+
+        // there are not enough frames analyzed in the fast mode
+        assert!(fast.streams()[0].is_frame_rate_estimated());
+    }
+
+    #[test]
+    fn test_estimated_duration() {
+        // headerless PCM (mono, 44.1 kHz by default), there is nothing but
+        // the file size and the bit rate
+        let data = vec![0; 16_000];
+
+        for fast in [false, true] {
+            let info = builder()
+                .input_format(InputFormat::find_by_name("s16le"))
+                .fast(fast)
+                .probe_bytes(&data)
+                .unwrap();
+
+            assert_eq!(info.duration_source(), Some(DurationSource::BitRate));
+            assert!(info.is_duration_estimated());
+
+            // there is nothing to be estimated for audio streams
+            assert!(!info.streams()[0].is_frame_rate_estimated());
+            assert!(info.streams()[0].frame_rate().is_none());
+        }
+    }
+}
@@ -0,0 +1,382 @@
+//! Custom URL protocols.
+//!
+//! A protocol handler resolves URLs with a given scheme (e.g.
+//! `s3://bucket/key`) using Rust code. Protocol handlers registered in a
+//! demuxer builder are used for the input URL itself as well as for all
+//! other URLs opened by the demuxer (e.g. segments referenced from
+//! playlists).
+//!
+//! # Example
+//! ```text
+//! let protocol = Protocol::new("s3", move |url| {
+//!     let object = bucket.get_object(url.strip_prefix("s3://").unwrap())?;
+//!
+//!     Ok(ProtocolStream::from_seekable_read_stream(object))
+//! });
+//!
+//! let demuxer = Demuxer::builder()
+//!     .protocol(protocol)
+//!     .build_from_url("s3://bucket/video.mp4")?;
+//! ```
+//!
+//! Note that the HLS and DASH demuxers accept only `file`, `http` and
+//! `https` URLs in playlists/manifests. Register a handler for the `http`
+//! or `https` scheme in order to serve nested URLs of these demuxers.
+
+use std::{
+    ffi::CStr,
+    io::{self, Read, Seek, Write},
+    os::raw::{c_char, c_int, c_void},
+};
+
+use crate::format::io::{self as avio, IO};
+
+/// Type-erased IO of a protocol stream.
+trait RawIO: Send {
+    /// Get pointer to the underlying AVIO context.
+    fn io_context_ptr(&mut self) -> *mut c_void;
+}
+
+impl<T> RawIO for IO<T>
+where
+    T: Send,
+{
+    fn io_context_ptr(&mut self) -> *mut c_void {
+        self.io_context_mut().as_mut_ptr()
+    }
+}
+
+/// Stream returned by a protocol handler. The capabilities of the stream
+/// (reading, writing and seeking) depend on the constructor used.
+pub struct ProtocolStream {
+    io: Box<dyn RawIO>,
+}
+
+impl ProtocolStream {
+    /// Create a new protocol stream from a given reader.
+    pub fn from_read_stream<T>(stream: T) -> Self
+    where
+        T: Read + Send + 'static,
+    {
+        Self::new(IO::from_read_stream(stream))
+    }
+
+    /// Create a new protocol stream from a given seekable reader.
+    pub fn from_seekable_read_stream<T>(stream: T) -> Self
+    where
+        T: Read + Seek + Send + 'static,
+    {
+        Self::new(IO::from_seekable_read_stream(stream))
+    }
+
+    /// Create a new protocol stream from a given writer.
+    pub fn from_write_stream<T>(stream: T) -> Self
+    where
+        T: Write + Send + 'static,
+    {
+        Self::new(IO::from_write_stream(stream))
+    }
+
+    /// Create a new protocol stream from a given seekable writer.
+    pub fn from_seekable_write_stream<T>(stream: T) -> Self
+    where
+        T: Write + Seek + Send + 'static,
+    {
+        Self::new(IO::from_seekable_write_stream(stream))
+    }
+
+    /// Create a new protocol stream from a given IO.
+    fn new<T>(io: IO<T>) -> Self
+    where
+        T: Send + 'static,
+    {
+        Self { io: Box::new(io) }
+    }
+}
+
+/// Function opening streams of a protocol.
+type OpenFn = Box<dyn FnMut(&str) -> io::Result<ProtocolStream> + Send>;
+
+/// User-defined protocol.
+pub struct Protocol {
+    name: String,
+    open: OpenFn,
+}
+
+impl Protocol {
+    /// Create a new protocol with a given name (URL scheme, e.g. `s3`). The
+    /// open function is called with the full URL of every stream to be
+    /// opened using the protocol.
+    pub fn new<F>(name: &str, open: F) -> Self
+    where
+        F: FnMut(&str) -> io::Result<ProtocolStream> + Send + 'static,
+    {
+        Self {
+            name: String::from(name),
+            open: Box::new(open),
+        }
+    }
+
+    /// Get the protocol name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Check if a given URL belongs to this protocol.
+    fn matches(&self, url: &str) -> bool {
+        let scheme = url.split(':').next().unwrap_or("");
+
+        scheme.len() < url.len() && scheme.eq_ignore_ascii_case(&self.name)
+    }
+}
+
+/// Collection of registered protocols and their currently open streams.
+#[derive(Default)]
+pub(crate) struct ProtocolRegistry {
+    protocols: Vec<Protocol>,
+    streams: Vec<(usize, ProtocolStream)>,
+}
+
+impl ProtocolRegistry {
+    /// Register a given protocol. It replaces any previously registered
+    /// protocol with the same name.
+    pub fn register(&mut self, protocol: Protocol) {
+        self.protocols
+            .retain(|p| !p.name.eq_ignore_ascii_case(&protocol.name));
+
+        self.protocols.push(protocol);
+    }
+
+    /// Check if there are no registered protocols.
+    pub fn is_empty(&self) -> bool {
+        self.protocols.is_empty()
+    }
+
+    /// Open a given URL and return pointer to the AVIO context of the
+    /// stream or `None` if the URL does not belong to any registered
+    /// protocol.
+    fn open(&mut self, url: &str) -> Option<io::Result<*mut c_void>> {
+        let protocol = self.protocols.iter_mut().find(|p| p.matches(url))?;
+
+        let res = (protocol.open)(url).map(|mut stream| {
+            let ptr = stream.io.io_context_ptr();
+
+            self.streams.push((ptr as usize, stream));
+
+            ptr
+        });
+
+        Some(res)
+    }
+
+    /// Close a stream with a given AVIO context. The method returns `false`
+    /// if the context does not belong to any stream opened by this
+    /// registry.
+    fn close(&mut self, io_context: *mut c_void) -> bool {
+        let key = io_context as usize;
+
+        if let Some(index) = self.streams.iter().position(|(ptr, _)| *ptr == key) {
+            self.streams.swap_remove(index);
+
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Open a given URL using a protocol registry. The function returns 1 if
+/// the URL was opened, 0 if the URL does not belong to any registered
+/// protocol and a negative error code on error.
+pub(crate) extern "C" fn protocol_open(
+    opaque: *mut c_void,
+    url: *const c_char,
+    io_context: *mut *mut c_void,
+) -> c_int {
+    let registry = unsafe { &mut *(opaque as *mut ProtocolRegistry) };
+
+    let url = unsafe { CStr::from_ptr(url) };
+
+    match registry.open(&url.to_string_lossy()) {
+        Some(Ok(ptr)) => {
+            unsafe { *io_context = ptr };
+
+            1
+        }
+        Some(Err(err)) => avio::get_error_code(&err),
+        None => 0,
+    }
+}
+
+/// Close a given AVIO context using a protocol registry. The function
+/// returns 1 if the context was closed and 0 if it does not belong to the
+/// registry.
+pub(crate) extern "C" fn protocol_close(opaque: *mut c_void, io_context: *mut c_void) -> c_int {
+    let registry = unsafe { &mut *(opaque as *mut ProtocolRegistry) };
+
+    c_int::from(registry.close(io_context))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::{self, Cursor, Read, Seek, SeekFrom},
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use super::{Protocol, ProtocolStream};
+
+    use crate::{
+        format::{demuxer::Demuxer, io::IO, segment::SegmentMuxer},
+        test_util::encode_video,
+    };
+
+    /// In-memory stream recording its URL when it gets dropped.
+    struct TrackedStream {
+        inner: Cursor<Vec<u8>>,
+        url: String,
+        closed: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Read for TrackedStream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.inner.read(buf)
+        }
+    }
+
+    impl Seek for TrackedStream {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    impl Drop for TrackedStream {
+        fn drop(&mut self) {
+            self.closed.lock().unwrap().push(self.url.clone());
+        }
+    }
+
+    #[test]
+    fn test_url_matching() {
+        let protocol = Protocol::new("s3", |_| Err(io::Error::from(io::ErrorKind::NotFound)));
+
+        assert!(protocol.matches("s3://bucket/key"));
+        assert!(protocol.matches("S3://bucket/key"));
+        assert!(!protocol.matches("s3"));
+        assert!(!protocol.matches("s3x://bucket/key"));
+        assert!(!protocol.matches("https://s3.amazonaws.com/bucket/key"));
+    }
+
+    #[test]
+    fn test_hls_input() {
+        let dir = std::env::temp_dir().join(format!("ac-ffmpeg-protocol-{}", std::process::id()));
+
+        fs::create_dir_all(&dir).unwrap();
+
+        let pattern = dir.join("segment-%03d.ts");
+        let playlist = dir.join("playlist.m3u8");
+
+        let mut demuxer = Demuxer::builder()
+            .build(IO::from_read_stream(Cursor::new(encode_video(
+                64, 48, 50, 25,
+            ))))
+            .unwrap()
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)
+            .unwrap();
+
+        let mut builder = SegmentMuxer::builder()
+            .segment_format("mpegts")
+            .segment_duration(Duration::from_secs(1))
+            .set_option("segment_list", playlist.to_str().unwrap())
+            .set_option("segment_list_type", "m3u8");
+
+        builder
+            .add_stream(&demuxer.streams()[0].codec_parameters())
+            .unwrap();
+
+        let mut muxer = builder.build(pattern.to_str().unwrap()).unwrap();
+
+        while let Some(packet) = demuxer.take().unwrap() {
+            muxer.push(packet).unwrap();
+        }
+
+        muxer.flush().unwrap();
+        muxer.close().unwrap();
+
+        let opened = Arc::new(Mutex::new(Vec::new()));
+        let closed = Arc::new(Mutex::new(Vec::new()));
+
+        let protocol = {
+            let dir = dir.clone();
+            let opened = opened.clone();
+            let closed = closed.clone();
+
+            Protocol::new("http", move |url| {
+                opened.lock().unwrap().push(url.to_string());
+
+                let name = url
+                    .strip_prefix("http://media.test/")
+                    .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))?;
+
+                let stream = TrackedStream {
+                    inner: Cursor::new(fs::read(dir.join(name))?),
+                    url: url.to_string(),
+                    closed: closed.clone(),
+                };
+
+                Ok(ProtocolStream::from_seekable_read_stream(stream))
+            })
+        };
+
+        // persistent and parallel HTTP connections would bypass the
+        // protocol
+        let mut demuxer = Demuxer::builder()
+            .protocol(protocol)
+            .set_option("http_persistent", 0)
+            .set_option("http_multiple", 0)
+            .build_from_url("http://media.test/playlist.m3u8")
+            .unwrap()
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)
+            .unwrap();
+
+        let mut packets = 0;
+
+        while demuxer.take().unwrap().is_some() {
+            packets += 1;
+        }
+
+        // the segments are closed while reading
+        let segments = closed.lock().unwrap().clone();
+
+        drop(demuxer);
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(packets, 50);
+
+        let playlist = String::from("http://media.test/playlist.m3u8");
+        let first = String::from("http://media.test/segment-000.ts");
+        let second = String::from("http://media.test/segment-001.ts");
+
+        let mut opened = opened.lock().unwrap().clone();
+        let mut closed = closed.lock().unwrap().clone();
+
+        assert_eq!(opened.first(), Some(&playlist));
+        assert!(opened.contains(&first));
+        assert!(opened.contains(&second));
+
+        assert!(segments.contains(&first));
+        assert!(!segments.contains(&playlist));
+
+        // all streams are closed once the demuxer is dropped
+        opened.sort();
+        closed.sort();
+
+        assert_eq!(opened, closed);
+        assert_eq!(opened.last(), Some(&second));
+    }
+}
@@ -0,0 +1,837 @@
+//! Long-running recording with file rotation.
+
+use std::{
+    fmt::Write as _,
+    fs::{self, File},
+    io::Write,
+    marker::PhantomData,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    codec::CodecParameters,
+    format::{
+        io::IO,
+        muxer::{Muxer, OutputFormat},
+        preroll::PreRollBuffer,
+    },
+    packet::Packet,
+    time::Timestamp,
+    Error, ErrorKind,
+};
+
+/// Information about a single recorded segment (file).
+#[derive(Debug, Clone)]
+pub struct SegmentInfo {
+    index: usize,
+    start_time: SystemTime,
+    end_time: Option<SystemTime>,
+    first_timestamp: Option<i64>,
+    last_timestamp: Option<i64>,
+    packets: u64,
+    bytes: u64,
+    error: Option<String>,
+}
+
+impl SegmentInfo {
+    /// Create info for a new segment.
+    fn new(index: usize) -> Self {
+        Self {
+            index,
+            start_time: SystemTime::now(),
+            end_time: None,
+            first_timestamp: None,
+            last_timestamp: None,
+            packets: 0,
+            bytes: 0,
+            error: None,
+        }
+    }
+
+    /// Get index of the segment.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Get wall-clock time when the segment was opened.
+    pub fn start_time(&self) -> SystemTime {
+        self.start_time
+    }
+
+    /// Get wall-clock time when the segment was closed (if it has been
+    /// closed already).
+    pub fn end_time(&self) -> Option<SystemTime> {
+        self.end_time
+    }
+
+    /// Get timestamp of the first packet in the segment (as pushed into the
+    /// recorder).
+    pub fn first_timestamp(&self) -> Timestamp {
+        self.first_timestamp
+            .map(Timestamp::from_micros)
+            .unwrap_or_else(Timestamp::null)
+    }
+
+    /// Get timestamp of the last packet in the segment (as pushed into the
+    /// recorder).
+    pub fn last_timestamp(&self) -> Timestamp {
+        self.last_timestamp
+            .map(Timestamp::from_micros)
+            .unwrap_or_else(Timestamp::null)
+    }
+
+    /// Get duration of the segment given by packet timestamps.
+    pub fn duration(&self) -> Duration {
+        match (self.first_timestamp, self.last_timestamp) {
+            (Some(first), Some(last)) if last > first => {
+                Duration::from_micros((last - first) as u64)
+            }
+            _ => Duration::from_secs(0),
+        }
+    }
+
+    /// Get the number of packets written into the segment.
+    pub fn packets(&self) -> u64 {
+        self.packets
+    }
+
+    /// Get the total size of packets written into the segment.
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Get the error that interrupted the segment (if any).
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Render the segment info as a JSON object. It can be used as sidecar
+    /// metadata of the recorded file.
+    pub fn to_json(&self) -> String {
+        let mut res = String::new();
+
+        let _ = write!(
+            res,
+            "{{\"index\":{},\"start_time\":{}",
+            self.index,
+            json_time(Some(self.start_time))
+        );
+
+        let _ = write!(res, ",\"end_time\":{}", json_time(self.end_time));
+        let _ = write!(res, ",\"duration\":{:.6}", self.duration().as_secs_f64());
+        let _ = write!(res, ",\"packets\":{}", self.packets);
+        let _ = write!(res, ",\"bytes\":{}", self.bytes);
+
+        if let Some(err) = self.error.as_ref() {
+            let _ = write!(res, ",\"error\":\"{}\"}}", json_escape(err));
+        } else {
+            res.push_str(",\"error\":null}");
+        }
+
+        res
+    }
+}
+
+/// Format a given wall-clock time as a JSON number (seconds since the Unix
+/// epoch) or null.
+fn json_time(time: Option<SystemTime>) -> String {
+    time.and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| format!("{:.3}", d.as_secs_f64()))
+        .unwrap_or_else(|| String::from("null"))
+}
+
+/// Escape a given string to be used within a JSON string.
+fn json_escape(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(res, "\\u{:04x}", c as u32);
+            }
+            c => res.push(c),
+        }
+    }
+
+    res
+}
+
+/// Destination of recorded segments.
+pub trait SegmentSink {
+    type Writer: Write;
+
+    /// Open output for a new segment.
+    fn open(&mut self, segment: &SegmentInfo) -> Result<(IO<Self::Writer>, OutputFormat), Error>;
+
+    /// Finish a given segment. The IO is missing if the muxer could not be
+    /// closed. Sidecar metadata of the segment can be written here.
+    fn close(&mut self, segment: &SegmentInfo, io: Option<IO<Self::Writer>>) -> Result<(), Error>;
+}
+
+/// Segment sink writing segments into files in a given directory. Files are
+/// named `<prefix><index>.<extension>` and each file is accompanied with a
+/// JSON sidecar file `<prefix><index>.json` (see `SegmentInfo::to_json()`).
+pub struct FileSink {
+    directory: PathBuf,
+    prefix: String,
+    format: String,
+    extension: String,
+    sidecar: bool,
+}
+
+impl FileSink {
+    /// Create a new file sink. Files will be written using a given output
+    /// format (e.g. `mp4` or `mpegts`) and they will have a given extension.
+    pub fn new<P>(directory: P, prefix: &str, format: &str, extension: &str) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self {
+            directory: directory.into(),
+            prefix: prefix.to_string(),
+            format: format.to_string(),
+            extension: extension.to_string(),
+            sidecar: true,
+        }
+    }
+
+    /// Enable or disable the JSON sidecar files. They are enabled by
+    /// default.
+    pub fn sidecar(mut self, enabled: bool) -> Self {
+        self.sidecar = enabled;
+        self
+    }
+
+    /// Get path of a file belonging to a given segment.
+    fn path(&self, segment: &SegmentInfo, extension: &str) -> PathBuf {
+        let name = format!("{}{:06}.{}", self.prefix, segment.index, extension);
+
+        self.directory.join(name)
+    }
+}
+
+impl SegmentSink for FileSink {
+    type Writer = File;
+
+    fn open(&mut self, segment: &SegmentInfo) -> Result<(IO<File>, OutputFormat), Error> {
+        let format = OutputFormat::find_by_name(&self.format)
+            .ok_or_else(|| Error::new("unknown output format"))?;
+
+        let file = File::create(self.path(segment, &self.extension)).map_err(io_error)?;
+
+        Ok((IO::from_seekable_write_stream(file), format))
+    }
+
+    fn close(&mut self, segment: &SegmentInfo, io: Option<IO<File>>) -> Result<(), Error> {
+        if let Some(io) = io {
+            io.into_stream().sync_all().map_err(io_error)?;
+        }
+
+        if self.sidecar {
+            fs::write(self.path(segment, "json"), segment.to_json()).map_err(io_error)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Convert a given IO error into an error.
+fn io_error(err: std::io::Error) -> Error {
+    Error::with_kind(ErrorKind::Io, err)
+}
+
+/// Method of cutting the recording into segments.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum CutMode {
+    /// Cut exactly at a keyframe, i.e. every packet is written into exactly
+    /// one segment.
+    Keyframe,
+    /// Start every new segment with (at least) a given amount of media from
+    /// the end of the previous segment. The overlap always starts at a
+    /// keyframe.
+    Overlap(Duration),
+}
+
+/// Builder for the recorder.
+pub struct RecorderBuilder<S> {
+    streams: Vec<CodecParameters>,
+    max_duration: Option<Duration>,
+    max_size: Option<u64>,
+    cut_mode: CutMode,
+    reset_timestamps: bool,
+    recover: bool,
+    sink: PhantomData<S>,
+}
+
+impl<S> RecorderBuilder<S>
+where
+    S: SegmentSink,
+{
+    /// Create a new builder.
+    fn new() -> Self {
+        Self {
+            streams: Vec::new(),
+            max_duration: Some(Duration::from_secs(600)),
+            max_size: None,
+            cut_mode: CutMode::Keyframe,
+            reset_timestamps: true,
+            recover: true,
+            sink: PhantomData,
+        }
+    }
+
+    /// Add a new stream with given parameters and return index of the new
+    /// stream. The same streams will be created in every segment.
+    pub fn add_stream(&mut self, params: &CodecParameters) -> usize {
+        self.streams.push(params.clone());
+        self.streams.len() - 1
+    }
+
+    /// Rotate files after a given duration. The default is 10 minutes. Use
+    /// None to disable time-based rotation.
+    pub fn max_duration(mut self, duration: Option<Duration>) -> Self {
+        self.max_duration = duration;
+        self
+    }
+
+    /// Rotate files after a given number of bytes. Size-based rotation is
+    /// disabled by default.
+    pub fn max_size(mut self, bytes: Option<u64>) -> Self {
+        self.max_size = bytes;
+        self
+    }
+
+    /// Set the cut mode. The default is `CutMode::Keyframe`.
+    pub fn cut_mode(mut self, mode: CutMode) -> Self {
+        self.cut_mode = mode;
+        self
+    }
+
+    /// Shift timestamps of every segment, so that every segment starts at
+    /// zero. This is enabled by default.
+    pub fn reset_timestamps(mut self, reset: bool) -> Self {
+        self.reset_timestamps = reset;
+        self
+    }
+
+    /// Recover from sink and muxer errors. If enabled (the default), a
+    /// failed segment is closed and a new one is opened at the next
+    /// keyframe. Otherwise, the error is returned to the caller.
+    pub fn recover(mut self, recover: bool) -> Self {
+        self.recover = recover;
+        self
+    }
+
+    /// Build the recorder.
+    pub fn build(self, sink: S) -> Result<Recorder<S>, Error> {
+        if self.streams.is_empty() {
+            return Err(Error::new("no streams"));
+        }
+
+        let key_stream = self.streams.iter().position(|s| s.is_video_codec());
+
+        let overlap = match self.cut_mode {
+            CutMode::Keyframe => None,
+            CutMode::Overlap(duration) => {
+                let buffer = PreRollBuffer::builder()
+                    .duration(duration)
+                    .key_stream(key_stream)
+                    .build();
+
+                Some(buffer)
+            }
+        };
+
+        let res = Recorder {
+            sink,
+            streams: self.streams,
+            key_stream,
+            max_duration: self.max_duration.map(|d| d.as_micros() as i64),
+            max_size: self.max_size,
+            reset_timestamps: self.reset_timestamps,
+            recover: self.recover,
+            current: None,
+            next_index: 0,
+            overlap,
+            errors: 0,
+            last_error: None,
+        };
+
+        Ok(res)
+    }
+}
+
+/// Segment currently being recorded.
+struct ActiveSegment<W> {
+    muxer: Muxer<W>,
+    info: SegmentInfo,
+    offset: Option<i64>,
+}
+
+/// Long-running recorder. It writes pushed packets into a sequence of
+/// segments (files) and rotates them on a given time/size schedule. New
+/// segments always start at a keyframe of the first video stream (or at any
+/// packet if there is no video stream). It is meant for CCTV/NVR-style
+/// persistence of live streams.
+///
+/// # Usage
+/// 1. Create a builder, add streams and build the recorder with a segment
+///    sink (e.g. `FileSink`).
+/// 2. Push all demuxed packets (with stream indices matching the added
+///    streams). Packets preceding the first keyframe are dropped.
+/// 3. Call `close()` at the end to finish the last segment.
+pub struct Recorder<S>
+where
+    S: SegmentSink,
+{
+    sink: S,
+    streams: Vec<CodecParameters>,
+    key_stream: Option<usize>,
+    max_duration: Option<i64>,
+    max_size: Option<u64>,
+    reset_timestamps: bool,
+    recover: bool,
+    current: Option<ActiveSegment<S::Writer>>,
+    next_index: usize,
+    overlap: Option<PreRollBuffer>,
+    errors: u64,
+    last_error: Option<Error>,
+}
+
+impl<S> Recorder<S>
+where
+    S: SegmentSink,
+{
+    /// Get a builder for the recorder.
+    pub fn builder() -> RecorderBuilder<S> {
+        RecorderBuilder::new()
+    }
+
+    /// Push a given packet.
+    pub fn push(&mut self, packet: Packet) -> Result<(), Error> {
+        if packet.stream_index() >= self.streams.len() {
+            return Err(Error::new("invalid stream index"));
+        }
+
+        let is_cut_point = self
+            .key_stream
+            .map(|index| packet.stream_index() == index && packet.is_key())
+            .unwrap_or(true);
+
+        let timestamp = packet
+            .dts()
+            .as_micros()
+            .or_else(|| packet.pts().as_micros());
+
+        if is_cut_point {
+            let rotate = self
+                .current
+                .as_ref()
+                .map(|segment| self.should_rotate(&segment.info, timestamp))
+                .unwrap_or(true);
+
+            if rotate {
+                let res = self.rotate();
+
+                self.handle_result(res)?;
+            }
+        }
+
+        if self.current.is_some() {
+            let res = self.write(packet.clone());
+
+            self.handle_result(res)?;
+        }
+
+        if let Some(overlap) = self.overlap.as_mut() {
+            overlap.push(packet);
+        }
+
+        Ok(())
+    }
+
+    /// Finish the current segment and take the sink.
+    pub fn close(mut self) -> Result<S, Error> {
+        self.finish_segment(None)?;
+
+        Ok(self.sink)
+    }
+
+    /// Get info about the segment currently being recorded (if any).
+    pub fn current_segment(&self) -> Option<&SegmentInfo> {
+        self.current.as_ref().map(|segment| &segment.info)
+    }
+
+    /// Get the number of errors that the recorder recovered from.
+    pub fn errors(&self) -> u64 {
+        self.errors
+    }
+
+    /// Get the last error that the recorder recovered from (if any).
+    pub fn last_error(&self) -> Option<&Error> {
+        self.last_error.as_ref()
+    }
+
+    /// Get the segment sink.
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    /// Get the segment sink.
+    pub fn sink_mut(&mut self) -> &mut S {
+        &mut self.sink
+    }
+
+    /// Check if a given segment should be finished before writing a packet
+    /// with a given timestamp.
+    fn should_rotate(&self, info: &SegmentInfo, timestamp: Option<i64>) -> bool {
+        if let Some(max) = self.max_size {
+            if info.bytes >= max {
+                return true;
+            }
+        }
+
+        if let Some(max) = self.max_duration {
+            if let (Some(first), Some(current)) = (info.first_timestamp, timestamp) {
+                if current - first >= max {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Finish the current segment (if any) and start a new one.
+    fn rotate(&mut self) -> Result<(), Error> {
+        self.finish_segment(None)?;
+
+        let info = SegmentInfo::new(self.next_index);
+
+        self.next_index += 1;
+
+        let (io, format) = self.sink.open(&info)?;
+
+        let mut builder = Muxer::builder();
+
+        for params in &self.streams {
+            builder.add_stream(params)?;
+        }
+
+        let muxer = builder.build(io, format)?;
+
+        self.current = Some(ActiveSegment {
+            muxer,
+            info,
+            offset: None,
+        });
+
+        if let Some(overlap) = self.overlap.take() {
+            let res = overlap
+                .packets()
+                .try_for_each(|packet| self.write(packet.clone()));
+
+            self.overlap = Some(overlap);
+
+            res?;
+        }
+
+        Ok(())
+    }
+
+    /// Write a given packet into the current segment.
+    fn write(&mut self, packet: Packet) -> Result<(), Error> {
+        let segment = self.current.as_mut().unwrap();
+
+        let timestamp = packet
+            .dts()
+            .as_micros()
+            .or_else(|| packet.pts().as_micros());
+
+        let info = &mut segment.info;
+
+        if let Some(timestamp) = timestamp {
+            info.first_timestamp.get_or_insert(timestamp);
+            info.last_timestamp = Some(timestamp);
+        }
+
+        info.packets += 1;
+        info.bytes += packet.data().len() as u64;
+
+        let packet = if self.reset_timestamps {
+            let offset = *segment.offset.get_or_insert(timestamp.unwrap_or(0));
+
+            shift_packet(packet, offset)
+        } else {
+            packet
+        };
+
+        segment.muxer.push(packet)
+    }
+
+    /// Finish the current segment (if any).
+    fn finish_segment(&mut self, error: Option<&Error>) -> Result<(), Error> {
+        let segment = match self.current.take() {
+            Some(segment) => segment,
+            None => return Ok(()),
+        };
+
+        let mut muxer = segment.muxer;
+        let mut info = segment.info;
+
+        let io = match error {
+            Some(err) => {
+                info.error = Some(err.to_string());
+
+                muxer.close().ok()
+            }
+            None => {
+                let res = muxer.flush().and_then(|_| muxer.close());
+
+                match res {
+                    Ok(io) => Some(io),
+                    Err(err) => {
+                        info.error = Some(err.to_string());
+
+                        None
+                    }
+                }
+            }
+        };
+
+        info.end_time = Some(SystemTime::now());
+
+        self.sink.close(&info, io)
+    }
+
+    /// Handle result of a sink/muxer operation.
+    fn handle_result(&mut self, res: Result<(), Error>) -> Result<(), Error> {
+        let err = match res {
+            Ok(()) => return Ok(()),
+            Err(err) => err,
+        };
+
+        if !self.recover {
+            return Err(err);
+        }
+
+        self.errors += 1;
+
+        // the segment will be reopened at the next keyframe
+        let _ = self.finish_segment(Some(&err));
+
+        self.last_error = Some(err);
+
+        Ok(())
+    }
+}
+
+/// Shift timestamps of a given packet by a given offset in microseconds.
+fn shift_packet(packet: Packet, offset: i64) -> Packet {
+    let pts = packet.pts();
+    let dts = packet.dts();
+
+    packet
+        .with_pts(shift_timestamp(pts, offset))
+        .with_dts(shift_timestamp(dts, offset))
+}
+
+/// Shift a given timestamp by a given offset in microseconds.
+fn shift_timestamp(timestamp: Timestamp, offset: i64) -> Timestamp {
+    if timestamp.is_null() {
+        return timestamp;
+    }
+
+    let offset = Timestamp::from_micros(offset)
+        .with_time_base(timestamp.time_base())
+        .timestamp();
+
+    timestamp.with_raw_timestamp(timestamp.timestamp() - offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, time::Duration};
+
+    use super::{json_escape, CutMode, Recorder, RecorderBuilder, SegmentInfo, SegmentSink};
+
+    use crate::{
+        codec::CodecParameters,
+        format::{demuxer::Demuxer, io::IO, muxer::OutputFormat},
+        packet::Packet,
+        test_util::encode_video,
+        Error,
+    };
+
+    /// In-memory segment sink.
+    #[derive(Default)]
+    struct MemorySink {
+        segments: Vec<SegmentInfo>,
+        fail: Option<usize>,
+    }
+
+    impl SegmentSink for MemorySink {
+        type Writer = Cursor<Vec<u8>>;
+
+        fn open(
+            &mut self,
+            segment: &SegmentInfo,
+        ) -> Result<(IO<Self::Writer>, OutputFormat), Error> {
+            if Some(segment.index()) == self.fail {
+                return Err(Error::new("unable to open segment"));
+            }
+
+            let io = IO::from_seekable_write_stream(Cursor::new(Vec::new()));
+
+            Ok((io, OutputFormat::find_by_name("matroska").unwrap()))
+        }
+
+        fn close(
+            &mut self,
+            segment: &SegmentInfo,
+            io: Option<IO<Self::Writer>>,
+        ) -> Result<(), Error> {
+            assert!(io.is_some());
+
+            self.segments.push(segment.clone());
+
+            Ok(())
+        }
+    }
+
+    /// Get codec parameters and packets of 2 seconds of video (25 fps) with
+    /// a keyframe every 10 frames.
+    fn input() -> (CodecParameters, Vec<Packet>) {
+        let data = encode_video(64, 48, 50, 10);
+
+        let mut demuxer = Demuxer::builder()
+            .build(IO::from_read_stream(Cursor::new(data)))
+            .unwrap()
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)
+            .unwrap();
+
+        let params = demuxer.streams()[0].codec_parameters();
+
+        let mut packets = Vec::new();
+
+        while let Some(packet) = demuxer.take().unwrap() {
+            packets.push(packet);
+        }
+
+        (params, packets)
+    }
+
+    /// Record a given input using a given builder and sink and get the
+    /// number of packets in every segment.
+    fn record(builder: RecorderBuilder<MemorySink>, sink: MemorySink) -> Vec<u64> {
+        let (params, packets) = input();
+
+        let mut builder = builder;
+
+        builder.add_stream(&params);
+
+        let mut recorder = builder.build(sink).unwrap();
+
+        for packet in packets {
+            recorder.push(packet).unwrap();
+        }
+
+        let sink = recorder.close().unwrap();
+
+        sink.segments
+            .iter()
+            .map(|segment| segment.packets())
+            .collect()
+    }
+
+    #[test]
+    fn test_json_escape() {
+        assert_eq!(json_escape("a\"b\\c\nd\u{1}"), "a\\\"b\\\\c\\nd\\u0001");
+    }
+
+    #[test]
+    fn test_duration_rotation() {
+        let builder = Recorder::builder().max_duration(Some(Duration::from_secs(1)));
+
+        // the first keyframe after 1 s is at 1.2 s
+        assert_eq!(record(builder, MemorySink::default()), [30, 20]);
+    }
+
+    #[test]
+    fn test_size_rotation() {
+        let builder = Recorder::builder().max_duration(None).max_size(Some(1));
+
+        assert_eq!(record(builder, MemorySink::default()), [10; 5]);
+    }
+
+    #[test]
+    fn test_overlap() {
+        let builder = Recorder::builder()
+            .max_duration(Some(Duration::from_secs(1)))
+            .cut_mode(CutMode::Overlap(Duration::from_millis(100)));
+
+        // the overlap buffer is trimmed only at keyframes, so every new
+        // segment starts with the last two GOPs of the previous one and the
+        // second segment reaches its limit already at 1.6 s
+        assert_eq!(record(builder, MemorySink::default()), [30, 30, 30]);
+    }
+
+    #[test]
+    fn test_recovery() {
+        let (params, packets) = input();
+
+        let sink = MemorySink {
+            segments: Vec::new(),
+            fail: Some(1),
+        };
+
+        let mut builder = Recorder::builder().max_duration(None).max_size(Some(1));
+
+        builder.add_stream(&params);
+
+        let mut recorder = builder.build(sink).unwrap();
+
+        for packet in packets.iter().cloned() {
+            recorder.push(packet).unwrap();
+        }
+
+        assert_eq!(recorder.errors(), 1);
+        assert!(recorder.last_error().is_some());
+
+        let sink = recorder.close().unwrap();
+
+        // the second GOP is lost
+        let indices = sink
+            .segments
+            .iter()
+            .map(|segment| segment.index())
+            .collect::<Vec<_>>();
+
+        assert_eq!(indices, [0, 2, 3, 4]);
+
+        // without recovery, the error is returned
+        let sink = MemorySink {
+            segments: Vec::new(),
+            fail: Some(1),
+        };
+
+        let mut builder = Recorder::builder().max_duration(None).max_size(Some(1));
+
+        builder.add_stream(&params);
+
+        let mut recorder = builder.recover(false).build(sink).unwrap();
+
+        let res = packets
+            .into_iter()
+            .try_for_each(|packet| recorder.push(packet));
+
+        assert!(res.is_err());
+    }
+}
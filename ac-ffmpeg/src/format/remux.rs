@@ -0,0 +1,418 @@
+//! Stream copy (remuxing).
+
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+};
+
+use crate::{
+    codec::bsf::BitstreamFilter,
+    format::{
+        demuxer::Demuxer,
+        extract::reset_codec_tag,
+        io::IO,
+        map::StreamMap,
+        muxer::{Muxer, OutputFormat},
+        preserve::PreservationPolicy,
+        stream::Discard,
+    },
+    packet::Packet,
+    Error,
+};
+
+/// Remux a given input into a given output, i.e. copy all audio, video and
+/// subtitle streams supported by the output format without transcoding.
+///
+/// # Example
+/// ```text
+/// ...
+///
+/// let input = IO::from_seekable_read_stream(File::open("movie.mkv")?);
+/// let output = IO::from_seekable_write_stream(File::create("movie.mp4")?);
+///
+/// remux(input, output, OutputFormat::find_by_name("mp4").unwrap())?;
+///
+/// ...
+/// ```
+pub fn remux<R, W>(input: IO<R>, output: IO<W>, format: OutputFormat) -> Result<IO<W>, Error>
+where
+    R: Read,
+    W: Write,
+{
+    Remux::new().run(input, output, format)
+}
+
+/// Stream copy with stream selection and bitstream filters.
+///
+/// Packet timestamps are rescaled from the input stream time bases into
+/// the time bases chosen by the muxer. Codec tags of the input streams are
+/// reset, so that the muxer can choose tags valid in the output format.
+///
+/// # Example
+/// ```text
+/// ...
+///
+/// let output = Remux::new()
+///     .stream(0)
+///     .stream(2)
+///     .bitstream_filter(0, "h264_mp4toannexb")
+///     .run(input, output, OutputFormat::find_by_name("mpegts").unwrap())?;
+///
+/// ...
+/// ```
+#[derive(Clone)]
+pub struct Remux {
+    streams: Vec<usize>,
+    data: bool,
+    map: StreamMap,
+    filters: Vec<(usize, String)>,
+    preservation: PreservationPolicy,
+}
+
+impl Remux {
+    /// Create a new remux with the default settings.
+    pub fn new() -> Self {
+        Self {
+            streams: Vec::new(),
+            data: false,
+            map: StreamMap::new(),
+            filters: Vec::new(),
+            preservation: PreservationPolicy::default(),
+        }
+    }
+
+    /// Select a given input stream. The output streams are created in the
+    /// order of selection. A stream selected multiple times (using this
+    /// method or using the stream map) is copied into multiple output
+    /// streams. If no stream is selected, all audio, video and subtitle
+    /// streams supported by the output format are copied.
+    pub fn stream(mut self, index: usize) -> Self {
+        self.streams.push(index);
+        self
+    }
+
+    /// Copy also data streams (e.g. KLV metadata or SCTE-35 messages) if no
+    /// stream is selected explicitly. Packets of the data streams are
+    /// passed through as they are.
+    pub fn data_streams(mut self, enabled: bool) -> Self {
+        self.data = enabled;
+        self
+    }
+
+    /// Select input streams using a given stream map. The mapped streams
+    /// are added after the streams selected using `stream()`. Note that the
+    /// preservation policy of the map is ignored, use `preservation()`
+    /// instead.
+    pub fn map(mut self, map: StreamMap) -> Self {
+        self.map = map;
+        self
+    }
+
+    /// Apply a given bitstream filter (e.g. `h264_mp4toannexb` or
+    /// `aac_adtstoasc`) to a given input stream. Multiple filters applied to
+    /// the same stream are chained in the order they were added. If the
+    /// input stream is copied into multiple output streams, every output
+    /// stream gets its own chain.
+    pub fn bitstream_filter(mut self, stream: usize, filter: &str) -> Self {
+        self.filters.push((stream, String::from(filter)));
+        self
+    }
+
+    /// Set the policy for propagating metadata, side data, chapters and
+    /// dispositions. Everything is preserved by default.
+    pub fn preservation(mut self, policy: PreservationPolicy) -> Self {
+        self.preservation = policy;
+        self
+    }
+
+    /// Remux a given input into a given output.
+    pub fn run<R, W>(
+        self,
+        input: IO<R>,
+        output: IO<W>,
+        format: OutputFormat,
+    ) -> Result<IO<W>, Error>
+    where
+        R: Read,
+        W: Write,
+    {
+        let mut demuxer = Demuxer::builder()
+            .build(input)?
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)?;
+
+        let input_streams = demuxer.streams();
+
+        let selected = if self.streams.is_empty() && self.map.is_empty() {
+            input_streams
+                .iter()
+                .enumerate()
+                .filter(|(_, stream)| {
+                    let params = stream.codec_parameters();
+
+                    let media = params.is_audio_codec()
+                        || params.is_video_codec()
+                        || params.is_subtitle_codec()
+                        || (self.data && params.is_data_codec());
+
+                    media && format.supports_codec(&params)
+                })
+                .map(|(index, _)| index)
+                .collect::<Vec<_>>()
+        } else {
+            let mut selected = self.streams.clone();
+
+            selected.extend(self.map.resolve(input_streams)?);
+            selected
+        };
+
+        if selected.is_empty() {
+            return Err(Error::new("no streams to be remuxed"));
+        }
+
+        for (stream, _) in &self.filters {
+            if !selected.contains(stream) {
+                return Err(Error::new("bitstream filter set for an unselected stream"));
+            }
+        }
+
+        let mut builder = Muxer::builder();
+
+        // output stream indices of every selected input stream
+        let mut mapping = HashMap::<usize, Vec<usize>>::new();
+
+        // filter chains of the output streams
+        let mut chains = Vec::new();
+
+        for (output_index, &input_index) in selected.iter().enumerate() {
+            let stream = input_streams
+                .get(input_index)
+                .ok_or_else(|| Error::new("invalid stream index"))?;
+
+            let mut params = stream.codec_parameters();
+
+            let mut filters = Vec::new();
+
+            for (_, name) in self.filters.iter().filter(|(s, _)| *s == input_index) {
+                let bsf = BitstreamFilter::builder(name)?
+                    .input_codec_parameters(&params)
+                    .input_time_base(stream.time_base())
+                    .output_time_base(stream.time_base())
+                    .build()?;
+
+                params = bsf.output_codec_parameters();
+
+                filters.push(bsf);
+            }
+
+            builder.add_stream(&reset_codec_tag(params))?;
+
+            self.preservation
+                .apply_stream(stream, &mut builder.streams_mut()[output_index])?;
+
+            mapping.entry(input_index).or_default().push(output_index);
+
+            chains.push(filters);
+        }
+
+        for (index, stream) in demuxer.streams_mut().iter_mut().enumerate() {
+            if !mapping.contains_key(&index) {
+                stream.set_discard(Discard::All);
+            }
+        }
+
+        let builder = self.preservation.apply_container(&demuxer, builder)?;
+
+        let mut muxer = builder.build(output, format)?;
+
+        let mut packets = Vec::new();
+
+        while let Some(packet) = demuxer.take()? {
+            let outputs = match mapping.get(&packet.stream_index()) {
+                Some(outputs) => outputs,
+                None => continue,
+            };
+
+            for &output_index in outputs {
+                filter(
+                    &mut chains[output_index],
+                    Some(packet.clone()),
+                    &mut packets,
+                )?;
+
+                for packet in packets.drain(..) {
+                    muxer.push(packet.with_stream_index(output_index))?;
+                }
+            }
+        }
+
+        for (output_index, filters) in chains.iter_mut().enumerate() {
+            filter(filters, None, &mut packets)?;
+
+            for packet in packets.drain(..) {
+                muxer.push(packet.with_stream_index(output_index))?;
+            }
+        }
+
+        muxer.flush()?;
+        muxer.close()
+    }
+}
+
+impl Default for Remux {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Pass a given packet through a given chain of bitstream filters and put
+/// the resulting packets into a given buffer. The chain is flushed if there
+/// is no packet.
+fn filter(
+    filters: &mut [BitstreamFilter],
+    packet: Option<Packet>,
+    output: &mut Vec<Packet>,
+) -> Result<(), Error> {
+    let (first, rest) = match filters.split_first_mut() {
+        Some(filters) => filters,
+        None => {
+            output.extend(packet);
+
+            return Ok(());
+        }
+    };
+
+    let flush = packet.is_none();
+
+    if let Some(packet) = packet {
+        first.push(packet)?;
+    } else {
+        first.flush()?;
+    }
+
+    while let Some(packet) = first.take()? {
+        filter(rest, Some(packet), output)?;
+    }
+
+    if flush {
+        filter(rest, None, output)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::{remux, Remux};
+
+    use crate::{
+        format::{
+            demuxer::Demuxer,
+            io::IO,
+            map::{StreamMap, StreamSelector},
+            muxer::OutputFormat,
+        },
+        test_util::encode_video,
+    };
+
+    /// Get an in-memory input containing 10 video frames.
+    fn input() -> IO<Cursor<Vec<u8>>> {
+        IO::from_read_stream(Cursor::new(encode_video(64, 48, 10, 5)))
+    }
+
+    /// Get a new in-memory output.
+    fn output() -> IO<Cursor<Vec<u8>>> {
+        IO::from_seekable_write_stream(Cursor::new(Vec::new()))
+    }
+
+    /// Get the Matroska output format.
+    fn matroska() -> OutputFormat {
+        OutputFormat::find_by_name("matroska").unwrap()
+    }
+
+    /// Get the number of packets in every stream of a given output.
+    fn count_packets(output: IO<Cursor<Vec<u8>>>) -> Vec<usize> {
+        let data = output.into_stream().into_inner();
+
+        let mut demuxer = Demuxer::builder()
+            .build(IO::from_read_stream(Cursor::new(data)))
+            .unwrap()
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)
+            .unwrap();
+
+        let mut res = vec![0; demuxer.streams().len()];
+
+        while let Some(packet) = demuxer.take().unwrap() {
+            res[packet.stream_index()] += 1;
+        }
+
+        res
+    }
+
+    #[test]
+    fn test_remux() {
+        let res = remux(input(), output(), matroska()).unwrap();
+
+        assert_eq!(count_packets(res), [10]);
+    }
+
+    #[test]
+    fn test_multiple_selection() {
+        let res = Remux::new()
+            .stream(0)
+            .stream(0)
+            .run(input(), output(), matroska())
+            .unwrap();
+
+        assert_eq!(count_packets(res), [10, 10]);
+
+        // the map is consistent with the explicit selection
+        let map = StreamMap::new()
+            .select(StreamSelector::video())
+            .select(StreamSelector::all());
+
+        let res = Remux::new()
+            .stream(0)
+            .map(map)
+            .run(input(), output(), matroska())
+            .unwrap();
+
+        assert_eq!(count_packets(res), [10, 10, 10]);
+    }
+
+    #[test]
+    fn test_bitstream_filter() {
+        let res = Remux::new()
+            .stream(0)
+            .stream(0)
+            .bitstream_filter(0, "null")
+            .run(input(), output(), matroska())
+            .unwrap();
+
+        assert_eq!(count_packets(res), [10, 10]);
+    }
+
+    #[test]
+    fn test_invalid_selection() {
+        assert!(Remux::new()
+            .stream(1)
+            .run(input(), output(), matroska())
+            .is_err());
+
+        assert!(Remux::new()
+            .stream(0)
+            .bitstream_filter(1, "null")
+            .run(input(), output(), matroska())
+            .is_err());
+
+        let map = StreamMap::new().select(StreamSelector::audio());
+
+        assert!(Remux::new()
+            .map(map)
+            .run(input(), output(), matroska())
+            .is_err());
+    }
+}
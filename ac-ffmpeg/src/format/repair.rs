@@ -0,0 +1,921 @@
+//! Recovery of damaged recordings.
+//!
+//! Recordings interrupted by a crash or a power loss are usually truncated.
+//! Matroska, MPEG-TS and fragmented MP4 files remain readable up to the
+//! last complete packet, so they can be repaired by remuxing all packets
+//! that can still be read (see `repair()`). The muxer writes a new index
+//! (cues, moov atom) of the output.
+//!
+//! A regular MP4 file without the moov atom cannot be opened at all because
+//! the codec parameters and the sample table are stored in the moov atom.
+//! The video track of such a file can be recovered using
+//! `repair_with_reference()` and a healthy file recorded with the same
+//! settings.
+
+use std::{
+    io::{self, Read, Write},
+    time::Duration,
+};
+
+use crate::{
+    format::{
+        demuxer::Demuxer,
+        extract::{open, reset_codec_tag},
+        io::IO,
+        muxer::{Muxer, OutputFormat},
+    },
+    packet::{Packet, PacketMut},
+    time::{Rational, TimeBase},
+    Error, ErrorKind,
+};
+
+/// Maximum size of a NAL unit accepted while scanning the media data.
+const MAX_NAL_UNIT_SIZE: usize = 16 << 20;
+
+/// Size of the chunks read from the damaged file.
+const READ_CHUNK_SIZE: usize = 1 << 20;
+
+/// Options of the recovery.
+#[derive(Debug, Clone)]
+pub struct RepairOptions {
+    max_errors: usize,
+    frame_rate: Option<Rational>,
+}
+
+impl RepairOptions {
+    /// Create new options with the default settings.
+    pub fn new() -> Self {
+        Self {
+            max_errors: 100,
+            frame_rate: None,
+        }
+    }
+
+    /// Set the maximum number of consecutive invalid data errors. Corrupted
+    /// packets are skipped and the recovery stops if there are more
+    /// consecutive errors. The default is 100.
+    pub fn max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = max_errors;
+        self
+    }
+
+    /// Set frame rate of the recovered video. It is used only by
+    /// `repair_with_reference()`. The frame rate of the reference file is
+    /// used by default.
+    pub fn frame_rate(mut self, frame_rate: Rational) -> Self {
+        self.frame_rate = Some(frame_rate);
+        self
+    }
+}
+
+impl Default for RepairOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Summary of the recovery.
+#[derive(Debug, Clone)]
+pub struct RepairReport {
+    packets: u64,
+    dropped_packets: u64,
+    skipped_bytes: u64,
+    errors: u64,
+    start: Option<i64>,
+    end: Option<i64>,
+}
+
+impl RepairReport {
+    /// Create a new empty report.
+    fn new() -> Self {
+        Self {
+            packets: 0,
+            dropped_packets: 0,
+            skipped_bytes: 0,
+            errors: 0,
+            start: None,
+            end: None,
+        }
+    }
+
+    /// Get the number of recovered packets.
+    pub fn packets(&self) -> u64 {
+        self.packets
+    }
+
+    /// Get the number of packets that were read but could not be used
+    /// (e.g. packets without timestamps or video packets preceding the
+    /// first keyframe).
+    pub fn dropped_packets(&self) -> u64 {
+        self.dropped_packets
+    }
+
+    /// Get the number of bytes of the media data that could not be
+    /// recovered. It is set only by `repair_with_reference()`.
+    pub fn skipped_bytes(&self) -> u64 {
+        self.skipped_bytes
+    }
+
+    /// Get the number of read errors.
+    pub fn errors(&self) -> u64 {
+        self.errors
+    }
+
+    /// Get duration of the recovered media.
+    pub fn duration(&self) -> Duration {
+        match (self.start, self.end) {
+            (Some(start), Some(end)) if end > start => Duration::from_micros((end - start) as u64),
+            _ => Duration::from_secs(0),
+        }
+    }
+
+    /// Add a given recovered packet.
+    fn add_packet(&mut self, packet: &Packet) {
+        self.packets += 1;
+
+        let pts = packet.pts();
+
+        if let Some(start) = pts.as_micros() {
+            let end = (pts + packet.duration().unwrap_or_default())
+                .as_micros()
+                .unwrap_or(start);
+
+            self.start = Some(self.start.map_or(start, |s| s.min(start)));
+            self.end = Some(self.end.map_or(end, |e| e.max(end)));
+        }
+    }
+}
+
+/// Recover all readable packets of a given damaged input and remux them
+/// into a given output. Corrupted packets are skipped, missing or non
+/// monotonic timestamps are fixed and video streams start with a keyframe.
+///
+/// # Example
+/// ```text
+/// let input = IO::from_seekable_read_stream(File::open("crashed.mkv")?);
+/// let output = IO::from_seekable_write_stream(File::create("repaired.mkv")?);
+///
+/// let format = OutputFormat::find_by_name("matroska").unwrap();
+///
+/// let (_, report) = repair(input, output, format, &RepairOptions::new())?;
+///
+/// println!("recovered {:?}", report.duration());
+/// ```
+pub fn repair<R, W>(
+    input: IO<R>,
+    output: IO<W>,
+    format: OutputFormat,
+    options: &RepairOptions,
+) -> Result<(IO<W>, RepairReport), Error>
+where
+    R: Read,
+    W: Write,
+{
+    let mut demuxer = Demuxer::builder()
+        .set_option("fflags", "+genpts+discardcorrupt")
+        .build(input)?
+        .find_stream_info(None)
+        .map_err(|(_, err)| err)?;
+
+    let mut builder = Muxer::builder().interleaved(true);
+
+    let mut streams = Vec::new();
+
+    for stream in demuxer.streams() {
+        let params = stream.codec_parameters();
+
+        streams.push(TimestampRepair::new(params.is_video_codec()));
+
+        builder.add_stream(&reset_codec_tag(params))?;
+    }
+
+    let mut muxer = builder.build(output, format)?;
+
+    let mut report = RepairReport::new();
+
+    let mut errors = 0;
+
+    loop {
+        let packet = match demuxer.take() {
+            Ok(Some(packet)) => packet,
+            Ok(None) => break,
+            Err(err) => {
+                report.errors += 1;
+
+                // anything else than invalid data means that there is
+                // nothing more to be read
+                if err.kind() == ErrorKind::InvalidData && errors < options.max_errors {
+                    errors += 1;
+                    continue;
+                } else {
+                    break;
+                }
+            }
+        };
+
+        errors = 0;
+
+        // streams that were not known when the muxer was created
+        let stream = match streams.get_mut(packet.stream_index()) {
+            Some(stream) => stream,
+            None => {
+                report.dropped_packets += 1;
+                continue;
+            }
+        };
+
+        if let Some(packet) = stream.push(packet) {
+            report.add_packet(&packet);
+
+            muxer.push(packet)?;
+        } else {
+            report.dropped_packets += 1;
+        }
+    }
+
+    muxer.flush()?;
+
+    let output = muxer.close()?;
+
+    Ok((output, report))
+}
+
+/// Recover the video track of a damaged MP4/MOV file without the moov atom
+/// and remux it into a given output. The codec parameters and the frame rate
+/// are taken from the first video stream of a given reference file that
+/// must be recorded by the same device with the same settings.
+///
+/// The media data is scanned for H.264/HEVC NAL units. Everything else
+/// (e.g. interleaved audio samples) is skipped. The original timestamps are
+/// stored in the moov atom, so constant frame rate is assumed and the
+/// presentation order of B-frames cannot be restored. The recovery is
+/// therefore intended mainly for streams without B-frames, which is the
+/// case of most cameras and phones.
+///
+/// # Example
+/// ```text
+/// let reference = IO::from_seekable_read_stream(File::open("healthy.mp4")?);
+/// let damaged = File::open("crashed.mp4")?;
+/// let output = IO::from_seekable_write_stream(File::create("repaired.mp4")?);
+///
+/// let format = OutputFormat::find_by_name("mp4").unwrap();
+///
+/// let (_, report) =
+///     repair_with_reference(reference, damaged, output, format, &RepairOptions::new())?;
+/// ```
+pub fn repair_with_reference<Q, R, W>(
+    reference: IO<Q>,
+    damaged: R,
+    output: IO<W>,
+    format: OutputFormat,
+    options: &RepairOptions,
+) -> Result<(IO<W>, RepairReport), Error>
+where
+    Q: Read,
+    R: Read,
+    W: Write,
+{
+    let (demuxer, index) = open(reference, false)?;
+
+    let stream = &demuxer.streams()[index];
+
+    let params = stream.codec_parameters();
+
+    let codec = match params.decoder_name() {
+        Some("h264") => NalCodec::H264,
+        Some("hevc") => NalCodec::Hevc,
+        _ => return Err(Error::new("only H.264 and HEVC video can be recovered")),
+    };
+
+    let length_size = params
+        .as_video_codec_parameters()
+        .and_then(|params| params.extradata())
+        .and_then(|extradata| codec.length_size(extradata))
+        .ok_or_else(|| Error::new("the reference stream does not use length-prefixed NAL units"))?;
+
+    let frame_rate = options
+        .frame_rate
+        .or_else(|| stream.avg_frame_rate())
+        .filter(|rate| rate.num() > 0 && rate.den() > 0)
+        .ok_or_else(|| Error::new("unknown frame rate"))?;
+
+    let time_base = TimeBase::new(frame_rate.den() as _, frame_rate.num() as _);
+
+    let mut builder = Muxer::builder();
+
+    builder.add_stream(&reset_codec_tag(params))?;
+
+    let mut muxer = builder.build(output, format)?;
+
+    let mut reader = NalUnitReader::new(damaged, codec, length_size)?;
+
+    let mut assembler = AccessUnitAssembler::new(codec, length_size);
+
+    let mut report = RepairReport::new();
+
+    let mut frames = 0;
+
+    let mut push = |access_unit: AccessUnit, report: &mut RepairReport| {
+        // the first access unit may be incomplete and the decoding cannot
+        // start without a keyframe anyway
+        if frames == 0 && !access_unit.key {
+            report.dropped_packets += 1;
+            return Ok(());
+        }
+
+        let packet = PacketMut::from(&access_unit.data)
+            .with_time_base(time_base)
+            .with_raw_pts(frames)
+            .with_raw_dts(frames)
+            .with_raw_duration(1)
+            .with_key_flag(access_unit.key)
+            .freeze();
+
+        frames += 1;
+
+        report.add_packet(&packet);
+
+        muxer.push(packet)
+    };
+
+    while let Some(nal_unit) = reader.next()? {
+        if let Some(access_unit) = assembler.push(&nal_unit) {
+            push(access_unit, &mut report)?;
+        }
+    }
+
+    if let Some(access_unit) = assembler.flush() {
+        push(access_unit, &mut report)?;
+    }
+
+    report.skipped_bytes = reader.skipped_bytes();
+
+    muxer.flush()?;
+
+    let output = muxer.close()?;
+
+    Ok((output, report))
+}
+
+/// Timestamp repair of a single stream.
+struct TimestampRepair {
+    wait_for_keyframe: bool,
+    last_dts: Option<i64>,
+    next_dts: Option<i64>,
+}
+
+impl TimestampRepair {
+    /// Create a new timestamp repair. Packets preceding the first keyframe
+    /// will be dropped if `wait_for_keyframe` is set.
+    fn new(wait_for_keyframe: bool) -> Self {
+        Self {
+            wait_for_keyframe,
+            last_dts: None,
+            next_dts: None,
+        }
+    }
+
+    /// Fix timestamps of a given packet. None is returned if the packet
+    /// cannot be used.
+    fn push(&mut self, packet: Packet) -> Option<Packet> {
+        if self.wait_for_keyframe {
+            if !packet.is_key() {
+                return None;
+            }
+
+            self.wait_for_keyframe = false;
+        }
+
+        let pts = packet.pts();
+        let dts = packet.dts();
+
+        let mut dts = if !dts.is_null() {
+            dts.timestamp()
+        } else if let Some(next) = self.next_dts {
+            next
+        } else if !pts.is_null() {
+            pts.timestamp()
+        } else {
+            return None;
+        };
+
+        if let Some(last) = self.last_dts {
+            if dts <= last {
+                dts = last + 1;
+            }
+        }
+
+        let pts = if pts.is_null() || pts.timestamp() < dts {
+            dts
+        } else {
+            pts.timestamp()
+        };
+
+        self.last_dts = Some(dts);
+        self.next_dts = Some(dts + packet.raw_duration().max(1));
+
+        let packet = packet.with_raw_pts(pts).with_raw_dts(dts);
+
+        Some(packet)
+    }
+}
+
+/// Video codecs that can be recovered from the raw media data.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum NalCodec {
+    H264,
+    Hevc,
+}
+
+impl NalCodec {
+    /// Get the NAL unit length size from a given extradata (avcC/hvcC).
+    fn length_size(self, extradata: &[u8]) -> Option<usize> {
+        let offset = match self {
+            Self::H264 => 4,
+            Self::Hevc => 21,
+        };
+
+        if extradata.len() > offset && extradata[0] == 1 {
+            Some((extradata[offset] & 3) as usize + 1)
+        } else {
+            None
+        }
+    }
+
+    /// Get size of the NAL unit header.
+    fn header_size(self) -> usize {
+        match self {
+            Self::H264 => 1,
+            Self::Hevc => 2,
+        }
+    }
+
+    /// Get NAL unit type from a given NAL unit header.
+    fn nal_unit_type(self, header: &[u8]) -> u8 {
+        match self {
+            Self::H264 => header[0] & 0x1f,
+            Self::Hevc => (header[0] >> 1) & 0x3f,
+        }
+    }
+
+    /// Check if a given NAL unit header is valid.
+    fn is_valid_header(self, header: &[u8]) -> bool {
+        if header[0] & 0x80 != 0 {
+            return false;
+        }
+
+        let nal_unit_type = self.nal_unit_type(header);
+
+        match self {
+            Self::H264 => {
+                let ref_idc = header[0] >> 5;
+
+                match nal_unit_type {
+                    1..=4 | 13 | 14 | 15 | 19 | 20 => true,
+                    5 | 7 | 8 => ref_idc != 0,
+                    6 | 9..=12 => ref_idc == 0,
+                    _ => false,
+                }
+            }
+            Self::Hevc => {
+                let layer_id = ((header[0] & 1) << 5) | (header[1] >> 3);
+                let temporal_id = header[1] & 7;
+
+                layer_id == 0
+                    && temporal_id != 0
+                    && matches!(nal_unit_type, 0..=9 | 16..=21 | 32..=40)
+            }
+        }
+    }
+
+    /// Check if a given NAL unit type is a coded slice.
+    fn is_vcl(self, nal_unit_type: u8) -> bool {
+        match self {
+            Self::H264 => matches!(nal_unit_type, 1..=5),
+            Self::Hevc => nal_unit_type < 32,
+        }
+    }
+
+    /// Check if a given NAL unit type is a slice of a keyframe.
+    fn is_key(self, nal_unit_type: u8) -> bool {
+        match self {
+            Self::H264 => nal_unit_type == 5,
+            Self::Hevc => matches!(nal_unit_type, 16..=21),
+        }
+    }
+
+    /// Check if a NAL unit of a given type following a coded slice starts
+    /// a new access unit.
+    fn is_access_unit_prefix(self, nal_unit_type: u8) -> bool {
+        match self {
+            Self::H264 => matches!(nal_unit_type, 6..=9 | 14..=18),
+            Self::Hevc => matches!(nal_unit_type, 32..=35 | 39),
+        }
+    }
+
+    /// Check if a given coded slice NAL unit (without the length prefix) is
+    /// the first slice of a picture.
+    fn is_first_slice(self, nal_unit: &[u8]) -> bool {
+        // both first_mb_in_slice equal to zero (H.264) and
+        // first_slice_segment_in_pic_flag (HEVC) start with a single set bit
+        nal_unit
+            .get(self.header_size())
+            .map(|b| b & 0x80 != 0)
+            .unwrap_or(false)
+    }
+}
+
+/// A single video frame.
+struct AccessUnit {
+    data: Vec<u8>,
+    key: bool,
+}
+
+/// Assembler of NAL units into access units.
+struct AccessUnitAssembler {
+    codec: NalCodec,
+    length_size: usize,
+    data: Vec<u8>,
+    has_slice: bool,
+    key: bool,
+}
+
+impl AccessUnitAssembler {
+    /// Create a new assembler.
+    fn new(codec: NalCodec, length_size: usize) -> Self {
+        Self {
+            codec,
+            length_size,
+            data: Vec::new(),
+            has_slice: false,
+            key: false,
+        }
+    }
+
+    /// Push a given length-prefixed NAL unit. The previous access unit is
+    /// returned if the NAL unit starts a new one.
+    fn push(&mut self, nal_unit: &[u8]) -> Option<AccessUnit> {
+        let payload = &nal_unit[self.length_size..];
+
+        let nal_unit_type = self.codec.nal_unit_type(payload);
+
+        let is_vcl = self.codec.is_vcl(nal_unit_type);
+
+        let starts_new = self.has_slice
+            && ((is_vcl && self.codec.is_first_slice(payload))
+                || (!is_vcl && self.codec.is_access_unit_prefix(nal_unit_type)));
+
+        let res = if starts_new { self.flush() } else { None };
+
+        self.data.extend_from_slice(nal_unit);
+
+        if is_vcl {
+            self.has_slice = true;
+            self.key |= self.codec.is_key(nal_unit_type);
+        }
+
+        res
+    }
+
+    /// Take the current access unit (if it contains any coded slice).
+    fn flush(&mut self) -> Option<AccessUnit> {
+        let data = std::mem::take(&mut self.data);
+
+        let has_slice = std::mem::replace(&mut self.has_slice, false);
+        let key = std::mem::replace(&mut self.key, false);
+
+        if has_slice {
+            Some(AccessUnit { data, key })
+        } else {
+            None
+        }
+    }
+}
+
+/// Reader of length-prefixed NAL units from the mdat box of a damaged
+/// MP4/MOV file.
+struct NalUnitReader<R> {
+    input: io::Take<R>,
+    codec: NalCodec,
+    length_size: usize,
+    buffer: Vec<u8>,
+    position: usize,
+    eof: bool,
+    synced: bool,
+    skipped: u64,
+}
+
+impl<R> NalUnitReader<R>
+where
+    R: Read,
+{
+    /// Create a new reader. The input is positioned at the beginning of the
+    /// mdat box payload.
+    fn new(mut input: R, codec: NalCodec, length_size: usize) -> Result<Self, Error> {
+        let size = find_mdat(&mut input)?;
+
+        let res = Self {
+            input: input.take(size.unwrap_or(u64::MAX)),
+            codec,
+            length_size,
+            buffer: Vec::new(),
+            position: 0,
+            eof: false,
+            synced: false,
+            skipped: 0,
+        };
+
+        Ok(res)
+    }
+
+    /// Get the number of bytes that did not belong to any NAL unit.
+    fn skipped_bytes(&self) -> u64 {
+        self.skipped
+    }
+
+    /// Read the next NAL unit (including the length prefix).
+    fn next(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        let min_size = self.length_size + self.codec.header_size();
+
+        loop {
+            if !self.fill(min_size)? {
+                self.skip(self.buffer.len() - self.position);
+
+                return Ok(None);
+            }
+
+            let size = match self.check(0) {
+                Some(size) => size,
+                None => {
+                    // not a NAL unit, try the next byte
+                    self.synced = false;
+                    self.skip(1);
+                    continue;
+                }
+            };
+
+            let available = self.fill(size + min_size)?;
+
+            // the NAL unit is accepted if the next one follows as well, the
+            // chain of NAL units may end only at the end of a chunk
+            let next_valid = if available {
+                self.check(size).is_some()
+            } else {
+                self.buffer.len() - self.position >= size
+            };
+
+            if !self.synced && !next_valid {
+                self.skip(1);
+                continue;
+            }
+
+            if self.buffer.len() - self.position < size {
+                // truncated NAL unit at the end of the input
+                self.skip(self.buffer.len() - self.position);
+
+                return Ok(None);
+            }
+
+            let nal_unit = self.buffer[self.position..self.position + size].to_vec();
+
+            self.position += size;
+            self.synced = next_valid;
+
+            return Ok(Some(nal_unit));
+        }
+    }
+
+    /// Check if there is a valid NAL unit at a given offset from the current
+    /// position and return its size including the length prefix. The buffer
+    /// must contain the length prefix and the NAL unit header.
+    fn check(&self, offset: usize) -> Option<usize> {
+        let data = &self.buffer[self.position + offset..];
+
+        let length = data[..self.length_size]
+            .iter()
+            .fold(0usize, |acc, b| (acc << 8) | *b as usize);
+
+        if length < self.codec.header_size() || length > MAX_NAL_UNIT_SIZE {
+            return None;
+        }
+
+        if !self.codec.is_valid_header(&data[self.length_size..]) {
+            return None;
+        }
+
+        Some(self.length_size + length)
+    }
+
+    /// Skip a given number of bytes.
+    fn skip(&mut self, bytes: usize) {
+        self.position += bytes;
+        self.skipped += bytes as u64;
+    }
+
+    /// Make sure that the buffer contains at least a given number of bytes
+    /// following the current position. The method returns false if there
+    /// is not enough data.
+    fn fill(&mut self, bytes: usize) -> Result<bool, Error> {
+        while self.buffer.len() - self.position < bytes {
+            if self.eof {
+                return Ok(false);
+            }
+
+            if self.position > 0 {
+                self.buffer.drain(..self.position);
+                self.position = 0;
+            }
+
+            let len = self.buffer.len();
+
+            self.buffer.resize(len + READ_CHUNK_SIZE, 0);
+
+            match self.input.read(&mut self.buffer[len..]) {
+                Ok(read) => {
+                    self.buffer.truncate(len + read);
+
+                    if read == 0 {
+                        self.eof = true;
+                    }
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {
+                    self.buffer.truncate(len);
+                }
+                Err(err) => {
+                    self.buffer.truncate(len);
+
+                    return Err(io_error(err));
+                }
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+/// Skip all top-level boxes preceding the mdat box and return size of the
+/// mdat box payload (if known).
+fn find_mdat<R>(input: &mut R) -> Result<Option<u64>, Error>
+where
+    R: Read,
+{
+    loop {
+        let mut header = [0u8; 8];
+
+        input.read_exact(&mut header).map_err(|err| {
+            if err.kind() == io::ErrorKind::UnexpectedEof {
+                Error::new("no mdat box found")
+            } else {
+                io_error(err)
+            }
+        })?;
+
+        let mut size = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as u64;
+        let mut header_size = 8;
+
+        if size == 1 {
+            let mut largesize = [0u8; 8];
+
+            input.read_exact(&mut largesize).map_err(io_error)?;
+
+            size = u64::from_be_bytes(largesize);
+            header_size = 16;
+        }
+
+        if &header[4..] == b"mdat" {
+            // the size of an unfinished mdat box is often not set
+            if size < header_size {
+                return Ok(None);
+            } else {
+                return Ok(Some(size - header_size));
+            }
+        } else if size < header_size {
+            return Err(Error::new("invalid box size"));
+        }
+
+        let skip = size - header_size;
+
+        let skipped = io::copy(&mut input.take(skip), &mut io::sink()).map_err(io_error)?;
+
+        if skipped < skip {
+            return Err(Error::new("no mdat box found"));
+        }
+    }
+}
+
+/// Convert a given IO error.
+fn io_error(err: io::Error) -> Error {
+    Error::with_kind(ErrorKind::Io, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AccessUnitAssembler, NalCodec, NalUnitReader, TimestampRepair};
+
+    use crate::{
+        test_util::packet,
+        time::{TimeBase, Timestamp},
+    };
+
+    fn nal_unit(header: u8, first_byte: u8, size: usize) -> Vec<u8> {
+        let mut res = (size as u32).to_be_bytes().to_vec();
+
+        res.push(header);
+        res.push(first_byte);
+        res.resize(4 + size, 0x55);
+        res
+    }
+
+    #[test]
+    fn test_timestamp_repair() {
+        let mut repair = TimestampRepair::new(true);
+
+        let res = repair
+            .push(
+                packet(0, TimeBase::new(1, 1000), 0)
+                    .with_raw_duration(40)
+                    .with_key_flag(true)
+                    .freeze(),
+            )
+            .unwrap();
+
+        assert_eq!(res.dts().timestamp(), 0);
+
+        // missing DTS
+        let res = repair
+            .push(
+                packet(0, TimeBase::new(1, 1000), 40)
+                    .with_dts(Timestamp::null())
+                    .with_raw_duration(40)
+                    .with_key_flag(true)
+                    .freeze(),
+            )
+            .unwrap();
+
+        assert_eq!(res.dts().timestamp(), 40);
+
+        // non monotonic DTS and PTS lower than DTS
+        let res = repair
+            .push(
+                packet(0, TimeBase::new(1, 1000), 20)
+                    .with_raw_dts(40)
+                    .with_raw_duration(40)
+                    .with_key_flag(true)
+                    .freeze(),
+            )
+            .unwrap();
+
+        assert_eq!(res.dts().timestamp(), 41);
+        assert_eq!(res.pts().timestamp(), 41);
+    }
+
+    #[test]
+    fn test_nal_unit_reader() {
+        let sps = nal_unit(0x67, 0x64, 10);
+        let idr = nal_unit(0x65, 0x88, 100);
+        let slice = nal_unit(0x41, 0x9a, 50);
+
+        let mut data = Vec::new();
+
+        data.extend_from_slice(&[0, 0, 0, 16]);
+        data.extend_from_slice(b"ftypisom");
+        data.extend_from_slice(&[0; 4]);
+
+        // unfinished mdat box
+        data.extend_from_slice(&[0, 0, 0, 0]);
+        data.extend_from_slice(b"mdat");
+
+        data.extend_from_slice(&sps);
+        data.extend_from_slice(&idr);
+
+        // audio samples
+        data.extend_from_slice(&[0xff; 37]);
+
+        data.extend_from_slice(&slice);
+        data.extend_from_slice(&slice);
+
+        let mut reader = NalUnitReader::new(&data[..], NalCodec::H264, 4).unwrap();
+
+        let mut assembler = AccessUnitAssembler::new(NalCodec::H264, 4);
+
+        let mut frames = Vec::new();
+
+        while let Some(nal_unit) = reader.next().unwrap() {
+            if let Some(frame) = assembler.push(&nal_unit) {
+                frames.push(frame);
+            }
+        }
+
+        frames.extend(assembler.flush());
+
+        assert_eq!(reader.skipped_bytes(), 37);
+
+        assert_eq!(frames.len(), 3);
+
+        assert!(frames[0].key);
+        assert_eq!(frames[0].data.len(), sps.len() + idr.len());
+
+        assert!(!frames[1].key);
+        assert_eq!(frames[2].data, slice);
+    }
+}
@@ -0,0 +1,395 @@
+//! RTP packetization.
+
+use std::{
+    collections::VecDeque,
+    io::{self, Write},
+};
+
+use crate::{
+    codec::CodecParameters,
+    format::{
+        io::IO,
+        muxer::{Muxer, OutputFormat},
+        sdp,
+    },
+    packet::Packet,
+    Error,
+};
+
+/// Size of the IO buffer. RTP packets must fit into the buffer, otherwise
+/// they would be split into multiple writes.
+const IO_BUFFER_SIZE: usize = 4096;
+
+/// Minimum RTP packet size accepted by the RTP muxer.
+const MIN_PACKET_SIZE: usize = 13;
+
+/// Builder for the RTP packetizer.
+pub struct RtpPacketizerBuilder {
+    payload_type: Option<u8>,
+    ssrc: Option<u32>,
+    sequence_number: Option<u16>,
+    cname: Option<String>,
+    max_packet_size: usize,
+    url: Option<String>,
+    rtcp: bool,
+}
+
+impl RtpPacketizerBuilder {
+    /// Create a new builder.
+    fn new() -> Self {
+        Self {
+            payload_type: None,
+            ssrc: None,
+            sequence_number: None,
+            cname: None,
+            max_packet_size: 1200,
+            url: None,
+            rtcp: true,
+        }
+    }
+
+    /// Set the RTP payload type. By default, the static payload type of
+    /// the codec is used if there is one, the first dynamic payload type
+    /// (96) is used otherwise.
+    pub fn payload_type(mut self, payload_type: u8) -> Self {
+        self.payload_type = Some(payload_type & 0x7f);
+        self
+    }
+
+    /// Set the RTP synchronization source identifier. A random one is used
+    /// by default.
+    pub fn ssrc(mut self, ssrc: u32) -> Self {
+        self.ssrc = Some(ssrc);
+        self
+    }
+
+    /// Set the initial sequence number. A random one is used by default.
+    pub fn sequence_number(mut self, sequence_number: u16) -> Self {
+        self.sequence_number = Some(sequence_number);
+        self
+    }
+
+    /// Set the canonical name used in RTCP sender reports.
+    pub fn cname(mut self, cname: &str) -> Self {
+        self.cname = Some(String::from(cname));
+        self
+    }
+
+    /// Set the maximum size of RTP packets including the RTP header (13 -
+    /// 4096 bytes). The default is 1200 bytes, which fits into the MTU of
+    /// most networks even with the additional SRTP/TURN overhead.
+    pub fn max_packet_size(mut self, size: usize) -> Self {
+        self.max_packet_size = size;
+        self
+    }
+
+    /// Set the RTP destination URL (e.g. `rtp://127.0.0.1:5004`). It is
+    /// used only for the connection information in the SDP.
+    pub fn url(mut self, url: &str) -> Self {
+        self.url = Some(String::from(url));
+        self
+    }
+
+    /// Enable or disable RTCP sender reports. If enabled, the sender
+    /// reports are returned together with the RTP packets (they can be
+    /// recognized using `RtpPacket::is_rtcp()`). It is enabled by default.
+    pub fn rtcp(mut self, enabled: bool) -> Self {
+        self.rtcp = enabled;
+        self
+    }
+
+    /// Build an RTP packetizer for a stream with given codec parameters.
+    pub fn build(self, params: &CodecParameters) -> Result<RtpPacketizer, Error> {
+        if self.max_packet_size < MIN_PACKET_SIZE || self.max_packet_size > IO_BUFFER_SIZE {
+            return Err(Error::new("invalid maximum RTP packet size"));
+        }
+
+        let format = OutputFormat::find_by_name("rtp")
+            .ok_or_else(|| Error::new("RTP muxer not available"))?;
+
+        let mut builder = Muxer::builder().set_option("packetsize", self.max_packet_size);
+
+        if let Some(payload_type) = self.payload_type {
+            builder = builder.set_option("payload_type", payload_type);
+        }
+
+        if let Some(ssrc) = self.ssrc {
+            // the option is a signed integer
+            builder = builder.set_option("ssrc", ssrc as i32);
+        }
+
+        if let Some(sequence_number) = self.sequence_number {
+            builder = builder.set_option("seq", sequence_number);
+        }
+
+        if let Some(cname) = self.cname {
+            builder = builder.set_option("cname", cname);
+        }
+
+        if let Some(url) = self.url {
+            builder = builder.set_option("url", url);
+        }
+
+        if !self.rtcp {
+            builder = builder.set_option("rtpflags", "+skip_rtcp");
+        }
+
+        builder.add_stream(params)?;
+
+        let io = IO::from_write_stream(PacketQueue::default());
+
+        let muxer = builder.build(io, format)?;
+
+        let res = RtpPacketizer { muxer };
+
+        Ok(res)
+    }
+}
+
+/// RTP or RTCP packet.
+#[derive(Debug, Clone)]
+pub struct RtpPacket {
+    data: Vec<u8>,
+}
+
+impl RtpPacket {
+    /// Get the packet data.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Take the packet data.
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Check if this is an RTCP packet (e.g. a sender report) rather than
+    /// an RTP packet.
+    pub fn is_rtcp(&self) -> bool {
+        is_rtcp(&self.data)
+    }
+}
+
+/// RTP packetizer. It wraps the `rtp` muxer and it turns media packets of
+/// a single stream into individual RTP packets, so that they can be passed
+/// into an existing RTP/WebRTC stack instead of being sent over UDP by
+/// FFmpeg.
+///
+/// # Example
+/// ```text
+/// let mut packetizer = RtpPacketizer::builder()
+///     .payload_type(102)
+///     .ssrc(0x1234_5678)
+///     .build(&encoder.codec_parameters().into())?;
+///
+/// while let Some(packet) = encoder.take()? {
+///     packetizer.push(packet.with_stream_index(0))?;
+///
+///     while let Some(rtp) = packetizer.take() {
+///         if !rtp.is_rtcp() {
+///             track.write_rtp(rtp.data())?;
+///         }
+///     }
+/// }
+/// ```
+///
+/// Use one packetizer per stream. The SDP describing the stream can be
+/// obtained using the `sdp()` method.
+pub struct RtpPacketizer {
+    muxer: Muxer<PacketQueue>,
+}
+
+impl RtpPacketizer {
+    /// Get a builder for the RTP packetizer.
+    pub fn builder() -> RtpPacketizerBuilder {
+        RtpPacketizerBuilder::new()
+    }
+
+    /// Push a given media packet. The stream index of the packet must be
+    /// zero.
+    pub fn push(&mut self, packet: Packet) -> Result<(), Error> {
+        self.muxer.push(packet)
+    }
+
+    /// Take the next RTP/RTCP packet.
+    pub fn take(&mut self) -> Option<RtpPacket> {
+        self.muxer.io_mut().stream_mut().packets.pop_front()
+    }
+
+    /// Create an SDP describing the RTP stream.
+    pub fn sdp(&self) -> Result<String, Error> {
+        sdp::create(&[&self.muxer])
+    }
+
+    /// Get the underlying muxer. It can be used e.g. for creating a single
+    /// SDP for multiple packetizers using `sdp::create()`.
+    pub fn muxer(&self) -> &Muxer<PacketQueue> {
+        &self.muxer
+    }
+}
+
+/// Writer splitting the RTP muxer output into individual packets. The RTP
+/// muxer flushes the IO after every packet, so every write contains exactly
+/// one packet.
+#[derive(Default)]
+pub struct PacketQueue {
+    packets: VecDeque<RtpPacket>,
+}
+
+impl Write for PacketQueue {
+    fn write(&mut self, buffer: &[u8]) -> Result<usize, io::Error> {
+        let packet = RtpPacket {
+            data: buffer.to_vec(),
+        };
+
+        self.packets.push_back(packet);
+
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> Result<(), io::Error> {
+        Ok(())
+    }
+}
+
+/// Check if given packet data is an RTCP packet (RFC 5761, section 4).
+fn is_rtcp(data: &[u8]) -> bool {
+    data.len() >= 2 && (192..=223).contains(&data[1])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::{is_rtcp, RtpPacketizer};
+
+    use crate::{
+        format::{demuxer::Demuxer, extract::reset_codec_tag, io::IO},
+        test_util::encode_video,
+    };
+
+    #[test]
+    fn test_rtcp_detection() {
+        // RTP packet with payload type 96 and the marker bit set
+        assert!(!is_rtcp(&[0x80, 0xe0, 0x00, 0x01]));
+
+        // RTCP sender report
+        assert!(is_rtcp(&[0x80, 200, 0x00, 0x06]));
+
+        assert!(!is_rtcp(&[0x80]));
+    }
+
+    #[test]
+    fn test_packetization() {
+        let data = encode_video(320, 240, 20, 5);
+
+        let mut demuxer = Demuxer::builder()
+            .build(IO::from_read_stream(Cursor::new(data)))
+            .unwrap()
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)
+            .unwrap();
+
+        let params = reset_codec_tag(demuxer.streams()[0].codec_parameters());
+
+        let mut packetizer = RtpPacketizer::builder()
+            .payload_type(102)
+            .ssrc(0x1234_5678)
+            .sequence_number(65530)
+            .max_packet_size(100)
+            .build(&params)
+            .unwrap();
+
+        let mut media_packets = 0;
+        let mut media_size = 0;
+
+        let mut rtp_packets = Vec::new();
+
+        while let Some(packet) = demuxer.take().unwrap() {
+            media_packets += 1;
+            media_size += packet.data().len();
+
+            packetizer.push(packet).unwrap();
+
+            while let Some(packet) = packetizer.take() {
+                rtp_packets.push(packet);
+            }
+        }
+
+        assert_eq!(media_packets, 20);
+
+        let mut rtcp_packets = 0;
+        let mut payload_size = 0;
+        let mut sequence_number = None;
+
+        for packet in rtp_packets.iter() {
+            let data = packet.data();
+
+            assert!(data.len() <= 100);
+            assert!(data.len() >= 12);
+
+            // RTP version
+            assert_eq!(data[0] >> 6, 2);
+
+            if packet.is_rtcp() {
+                rtcp_packets += 1;
+                continue;
+            }
+
+            assert_eq!(data[1] & 0x7f, 102);
+            assert_eq!(&data[8..12], &[0x12, 0x34, 0x56, 0x78]);
+
+            let current = u16::from_be_bytes([data[2], data[3]]);
+
+            if let Some(previous) = sequence_number {
+                assert_eq!(current, u16::wrapping_add(previous, 1));
+            } else {
+                assert_eq!(current, 65530);
+            }
+
+            sequence_number = Some(current);
+
+            // there is no payload header for MPEG-4 part 2
+            payload_size += data.len() - 12;
+        }
+
+        // the sender report is sent before the first RTP packet
+        assert!(rtcp_packets > 0);
+        assert!(rtp_packets[0].is_rtcp());
+
+        // the keyframes do not fit into a single RTP packet
+        assert!(rtp_packets.len() - rtcp_packets > media_packets);
+        assert_eq!(payload_size, media_size);
+
+        // the sequence number wrapped around
+        assert!(sequence_number.unwrap() < 65530);
+
+        let sdp = packetizer.sdp().unwrap();
+
+        assert!(sdp.starts_with("v=0"));
+        assert!(sdp.contains("m=video "));
+        assert!(sdp.contains("a=rtpmap:102 MP4V-ES/90000"));
+    }
+
+    #[test]
+    fn test_invalid_packet_size() {
+        let data = encode_video(64, 48, 1, 1);
+
+        let demuxer = Demuxer::builder()
+            .build(IO::from_read_stream(Cursor::new(data)))
+            .unwrap()
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)
+            .unwrap();
+
+        let params = reset_codec_tag(demuxer.streams()[0].codec_parameters());
+
+        for size in [12, 4097] {
+            let res = RtpPacketizer::builder()
+                .max_packet_size(size)
+                .build(&params);
+
+            assert!(res.is_err());
+        }
+    }
+}
@@ -0,0 +1,1064 @@
+//! SCTE-35 splice information.
+//!
+//! The MPEG-TS demuxer exposes SCTE-35 PIDs as data streams with the
+//! `scte_35` codec. Each packet of such a stream contains a single splice
+//! info section.
+//!
+//! # Example
+//! ```text
+//! while let Some(packet) = demuxer.take()? {
+//!     if packet.stream_index() == scte35_stream {
+//!         let section = SpliceInfoSection::from_packet(&packet)?;
+//!
+//!         if let SpliceCommand::Insert(insert) = &section.command {
+//!             println!("splice at {:?}", section.splice_time());
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! Sections can be injected into data streams created using
+//! `OtherCodecParameters::data("scte_35")`:
+//! ```text
+//! let section = SpliceInfoSection::new(SpliceCommand::Insert(SpliceInsert::new(1)));
+//!
+//! muxer.push(section.to_packet(pts)?.with_stream_index(scte35_stream))?;
+//! ```
+//! Note that it depends on the output format whether (and how) the sections
+//! are carried. MPEG-TS outputs should be checked for the SCTE-35 stream
+//! type (0x86) with the FFmpeg version in use.
+
+use crate::{
+    packet::{Packet, PacketMut},
+    time::{TimeBase, Timestamp},
+    Error,
+};
+
+/// Time base of all SCTE-35 timestamps and durations.
+pub const SCTE35_TIME_BASE: TimeBase = TimeBase::new(1, 90_000);
+
+const TABLE_ID: u8 = 0xfc;
+
+const PTS_MASK: u64 = 0x1_ffff_ffff;
+
+const CUEI: u32 = 0x4355_4549;
+
+/// Splice info section.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SpliceInfoSection {
+    /// Offset (in the 90 kHz time base) added to all splice times.
+    pub pts_adjustment: u64,
+    /// Authorization tier (12 bits, 0xfff means no tier).
+    pub tier: u16,
+    /// Splice command.
+    pub command: SpliceCommand,
+    /// Splice descriptors.
+    pub descriptors: Vec<SpliceDescriptor>,
+}
+
+impl SpliceInfoSection {
+    /// Create a new section with a given command, no PTS adjustment, no
+    /// tier and no descriptors.
+    pub fn new(command: SpliceCommand) -> Self {
+        Self {
+            pts_adjustment: 0,
+            tier: 0xfff,
+            command,
+            descriptors: Vec::new(),
+        }
+    }
+
+    /// Add a given descriptor.
+    pub fn with_descriptor(mut self, descriptor: SpliceDescriptor) -> Self {
+        self.descriptors.push(descriptor);
+        self
+    }
+
+    /// Parse a splice info section from the data of a given packet.
+    pub fn from_packet(packet: &Packet) -> Result<Self, Error> {
+        Self::from_bytes(packet.data())
+    }
+
+    /// Parse a given splice info section. Encrypted sections are not
+    /// supported.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, Error> {
+        let mut reader = BitReader::new(data);
+
+        if reader.read(8)? as u8 != TABLE_ID {
+            return Err(Error::new("not a splice info section"));
+        }
+
+        reader.skip(4)?;
+
+        let section_length = reader.read(12)? as usize;
+
+        let data = data
+            .get(..3 + section_length)
+            .ok_or_else(|| Error::new("truncated splice info section"))?;
+
+        if section_length < 4 || crc32(data) != 0 {
+            return Err(Error::new("invalid splice info section CRC"));
+        }
+
+        let mut reader = BitReader::new(&data[3..data.len() - 4]);
+
+        if reader.read(8)? != 0 {
+            return Err(Error::new("unsupported splice info section version"));
+        }
+
+        if reader.read_flag()? {
+            return Err(Error::new(
+                "encrypted splice info sections are not supported",
+            ));
+        }
+
+        reader.skip(6)?;
+
+        let pts_adjustment = reader.read(33)?;
+
+        reader.skip(8)?;
+
+        let tier = reader.read(12)? as u16;
+        let command_length = reader.read(12)? as usize;
+        let command_type = reader.read(8)? as u8;
+
+        // the length may be unspecified (0xfff) in legacy sections
+        let command_data = if command_length == 0xfff {
+            reader.remaining()
+        } else {
+            reader.read_bytes(command_length)?
+        };
+
+        let (command, consumed) = SpliceCommand::parse(command_type, command_data)?;
+
+        if command_length == 0xfff {
+            reader.read_bytes(consumed)?;
+        }
+
+        let descriptor_loop_length = reader.read(16)? as usize;
+
+        let mut descriptors = Vec::new();
+
+        let mut loop_reader = BitReader::new(reader.read_bytes(descriptor_loop_length)?);
+
+        while !loop_reader.is_empty() {
+            let tag = loop_reader.read(8)? as u8;
+            let length = loop_reader.read(8)? as usize;
+            let data = loop_reader.read_bytes(length)?;
+
+            descriptors.push(SpliceDescriptor::parse(tag, data)?);
+        }
+
+        let res = Self {
+            pts_adjustment,
+            tier,
+            command,
+            descriptors,
+        };
+
+        Ok(res)
+    }
+
+    /// Serialize the section (including the CRC).
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        let mut command = BitWriter::new();
+
+        let command_type = self.command.write(&mut command)?;
+
+        let command = command.finish();
+
+        let mut descriptors = BitWriter::new();
+
+        for descriptor in &self.descriptors {
+            descriptor.write(&mut descriptors)?;
+        }
+
+        let descriptors = descriptors.finish();
+
+        // from the protocol version to the CRC
+        let section_length = 11 + command.len() + 2 + descriptors.len() + 4;
+
+        if section_length > 0xffd || command.len() >= 0xfff || descriptors.len() > 0xffff {
+            return Err(Error::new("splice info section too long"));
+        }
+
+        let mut writer = BitWriter::new();
+
+        writer.write(8, TABLE_ID as u64);
+        writer.write_flag(false);
+        writer.write_flag(false);
+        writer.write(2, 0x3);
+        writer.write(12, section_length as u64);
+        writer.write(8, 0);
+        writer.write_flag(false);
+        writer.write(6, 0);
+        writer.write(33, self.pts_adjustment & PTS_MASK);
+        // CW index (not used for unencrypted sections)
+        writer.write(8, 0xff);
+        writer.write(12, (self.tier & 0xfff) as u64);
+        writer.write(12, command.len() as u64);
+        writer.write(8, command_type as u64);
+        writer.write_bytes(&command);
+        writer.write(16, descriptors.len() as u64);
+        writer.write_bytes(&descriptors);
+
+        let mut res = writer.finish();
+
+        let crc = crc32(&res);
+
+        res.extend_from_slice(&crc.to_be_bytes());
+
+        Ok(res)
+    }
+
+    /// Create a data packet containing the section. The packet will have a
+    /// given presentation timestamp.
+    pub fn to_packet(&self, pts: Timestamp) -> Result<Packet, Error> {
+        let packet = PacketMut::from(self.to_bytes()?)
+            .with_time_base(pts.time_base())
+            .with_pts(pts)
+            .with_dts(pts)
+            .freeze();
+
+        Ok(packet)
+    }
+
+    /// Get the PTS adjustment as a timestamp.
+    pub fn pts_adjustment(&self) -> Timestamp {
+        Timestamp::new(self.pts_adjustment as i64, SCTE35_TIME_BASE)
+    }
+
+    /// Get the splice time of the command (if any) with the PTS adjustment
+    /// applied. Component splice times of splice inserts are not taken
+    /// into account.
+    pub fn splice_time(&self) -> Option<Timestamp> {
+        let time = match &self.command {
+            SpliceCommand::Insert(insert) if !insert.cancel => insert.splice_time?,
+            SpliceCommand::TimeSignal(time) => (*time)?,
+            _ => return None,
+        };
+
+        let time = (time + self.pts_adjustment) & PTS_MASK;
+
+        Some(Timestamp::new(time as i64, SCTE35_TIME_BASE))
+    }
+}
+
+/// Splice command.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SpliceCommand {
+    /// Splice null (e.g. a heartbeat or a carrier of descriptors).
+    Null,
+    /// Splice insert.
+    Insert(SpliceInsert),
+    /// Time signal with an optional splice time (in the 90 kHz time base).
+    /// The meaning of the signal is given by its descriptors.
+    TimeSignal(Option<u64>),
+    /// Bandwidth reservation.
+    BandwidthReservation,
+    /// Other command (e.g. splice schedule or a private command) in the raw
+    /// form.
+    Other {
+        /// Command type.
+        command_type: u8,
+        /// Command data.
+        data: Vec<u8>,
+    },
+}
+
+impl SpliceCommand {
+    /// Parse a given command. The method returns the command and the number
+    /// of bytes consumed.
+    fn parse(command_type: u8, data: &[u8]) -> Result<(Self, usize), Error> {
+        let mut reader = BitReader::new(data);
+
+        let res = match command_type {
+            0x00 => Self::Null,
+            0x05 => Self::Insert(SpliceInsert::parse(&mut reader)?),
+            0x06 => Self::TimeSignal(read_splice_time(&mut reader)?),
+            0x07 => Self::BandwidthReservation,
+            _ => {
+                let res = Self::Other {
+                    command_type,
+                    data: data.to_vec(),
+                };
+
+                return Ok((res, data.len()));
+            }
+        };
+
+        Ok((res, reader.position()))
+    }
+
+    /// Write the command and return its type.
+    fn write(&self, writer: &mut BitWriter) -> Result<u8, Error> {
+        let res = match self {
+            Self::Null => 0x00,
+            Self::Insert(insert) => {
+                insert.write(writer)?;
+
+                0x05
+            }
+            Self::TimeSignal(time) => {
+                write_splice_time(writer, *time);
+
+                0x06
+            }
+            Self::BandwidthReservation => 0x07,
+            Self::Other { command_type, data } => {
+                writer.write_bytes(data);
+
+                *command_type
+            }
+        };
+
+        Ok(res)
+    }
+}
+
+/// Splice insert command.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SpliceInsert {
+    /// Splice event ID.
+    pub event_id: u32,
+    /// The command cancels a previously sent event with the same ID. All
+    /// other fields are ignored if set.
+    pub cancel: bool,
+    /// The splice point is an opportunity to exit from the network feed
+    /// (i.e. a break start), a return to the network feed otherwise.
+    pub out_of_network: bool,
+    /// Splice at the nearest opportunity (the splice times are ignored).
+    pub immediate: bool,
+    /// Program splice time (in the 90 kHz time base). It is used only if
+    /// there are no components and the splice is not immediate.
+    pub splice_time: Option<u64>,
+    /// Component splices. The program is spliced as a whole if empty.
+    pub components: Vec<SpliceComponent>,
+    /// Duration of the break.
+    pub break_duration: Option<BreakDuration>,
+    /// Unique program ID.
+    pub unique_program_id: u16,
+    /// Avail number.
+    pub avail_num: u8,
+    /// Expected number of avails.
+    pub avails_expected: u8,
+}
+
+impl SpliceInsert {
+    /// Create a new immediate program splice out of the network with a
+    /// given event ID.
+    pub fn new(event_id: u32) -> Self {
+        Self {
+            event_id,
+            cancel: false,
+            out_of_network: true,
+            immediate: true,
+            splice_time: None,
+            components: Vec::new(),
+            break_duration: None,
+            unique_program_id: 0,
+            avail_num: 0,
+            avails_expected: 0,
+        }
+    }
+
+    /// Parse the command.
+    fn parse(reader: &mut BitReader) -> Result<Self, Error> {
+        let mut res = Self::new(reader.read(32)? as u32);
+
+        res.cancel = reader.read_flag()?;
+
+        reader.skip(7)?;
+
+        if res.cancel {
+            return Ok(res);
+        }
+
+        res.out_of_network = reader.read_flag()?;
+
+        let program_splice = reader.read_flag()?;
+        let has_duration = reader.read_flag()?;
+
+        res.immediate = reader.read_flag()?;
+
+        reader.skip(4)?;
+
+        if program_splice && !res.immediate {
+            res.splice_time = read_splice_time(reader)?;
+        }
+
+        if !program_splice {
+            let count = reader.read(8)?;
+
+            for _ in 0..count {
+                let tag = reader.read(8)? as u8;
+
+                let splice_time = if res.immediate {
+                    None
+                } else {
+                    read_splice_time(reader)?
+                };
+
+                res.components.push(SpliceComponent { tag, splice_time });
+            }
+        }
+
+        if has_duration {
+            res.break_duration = Some(BreakDuration::parse(reader)?);
+        }
+
+        res.unique_program_id = reader.read(16)? as u16;
+        res.avail_num = reader.read(8)? as u8;
+        res.avails_expected = reader.read(8)? as u8;
+
+        Ok(res)
+    }
+
+    /// Write the command.
+    fn write(&self, writer: &mut BitWriter) -> Result<(), Error> {
+        writer.write(32, self.event_id as u64);
+        writer.write_flag(self.cancel);
+        writer.write(7, 0x7f);
+
+        if self.cancel {
+            return Ok(());
+        }
+
+        let program_splice = self.components.is_empty();
+
+        writer.write_flag(self.out_of_network);
+        writer.write_flag(program_splice);
+        writer.write_flag(self.break_duration.is_some());
+        writer.write_flag(self.immediate);
+        writer.write(4, 0xf);
+
+        if program_splice && !self.immediate {
+            write_splice_time(writer, self.splice_time);
+        }
+
+        if !program_splice {
+            if self.components.len() > 0xff {
+                return Err(Error::new("too many splice components"));
+            }
+
+            writer.write(8, self.components.len() as u64);
+
+            for component in &self.components {
+                writer.write(8, component.tag as u64);
+
+                if !self.immediate {
+                    write_splice_time(writer, component.splice_time);
+                }
+            }
+        }
+
+        if let Some(duration) = self.break_duration {
+            duration.write(writer);
+        }
+
+        writer.write(16, self.unique_program_id as u64);
+        writer.write(8, self.avail_num as u64);
+        writer.write(8, self.avails_expected as u64);
+
+        Ok(())
+    }
+}
+
+/// Component splice.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SpliceComponent {
+    /// Component tag (it identifies the elementary stream).
+    pub tag: u8,
+    /// Splice time of the component (in the 90 kHz time base).
+    pub splice_time: Option<u64>,
+}
+
+/// Break duration.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct BreakDuration {
+    /// Return to the network feed automatically at the end of the break.
+    pub auto_return: bool,
+    /// Duration in the 90 kHz time base.
+    pub duration: u64,
+}
+
+impl BreakDuration {
+    /// Parse the break duration.
+    fn parse(reader: &mut BitReader) -> Result<Self, Error> {
+        let auto_return = reader.read_flag()?;
+
+        reader.skip(6)?;
+
+        let res = Self {
+            auto_return,
+            duration: reader.read(33)?,
+        };
+
+        Ok(res)
+    }
+
+    /// Write the break duration.
+    fn write(self, writer: &mut BitWriter) {
+        writer.write_flag(self.auto_return);
+        writer.write(6, 0x3f);
+        writer.write(33, self.duration & PTS_MASK);
+    }
+}
+
+/// Splice descriptor.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SpliceDescriptor {
+    /// Segmentation descriptor.
+    Segmentation(SegmentationDescriptor),
+    /// Other descriptor in the raw form.
+    Other {
+        /// Descriptor tag.
+        tag: u8,
+        /// Identifier (e.g. "CUEI" for descriptors defined by SCTE-35).
+        identifier: u32,
+        /// Descriptor data following the identifier.
+        data: Vec<u8>,
+    },
+}
+
+impl SpliceDescriptor {
+    /// Parse a descriptor with a given tag.
+    fn parse(tag: u8, data: &[u8]) -> Result<Self, Error> {
+        let mut reader = BitReader::new(data);
+
+        let identifier = reader.read(32)? as u32;
+
+        let res = if tag == 0x02 && identifier == CUEI {
+            Self::Segmentation(SegmentationDescriptor::parse(&mut reader)?)
+        } else {
+            Self::Other {
+                tag,
+                identifier,
+                data: reader.remaining().to_vec(),
+            }
+        };
+
+        Ok(res)
+    }
+
+    /// Write the descriptor.
+    fn write(&self, writer: &mut BitWriter) -> Result<(), Error> {
+        let mut data = BitWriter::new();
+
+        let tag = match self {
+            Self::Segmentation(descriptor) => {
+                data.write(32, CUEI as u64);
+
+                descriptor.write(&mut data)?;
+
+                0x02
+            }
+            Self::Other {
+                tag,
+                identifier,
+                data: raw,
+            } => {
+                data.write(32, *identifier as u64);
+                data.write_bytes(raw);
+
+                *tag
+            }
+        };
+
+        let data = data.finish();
+
+        if data.len() > 0xff {
+            return Err(Error::new("splice descriptor too long"));
+        }
+
+        writer.write(8, tag as u64);
+        writer.write(8, data.len() as u64);
+        writer.write_bytes(&data);
+
+        Ok(())
+    }
+}
+
+/// Segmentation descriptor.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SegmentationDescriptor {
+    /// Segmentation event ID.
+    pub event_id: u32,
+    /// The descriptor cancels a previously sent event with the same ID. All
+    /// other fields are ignored if set.
+    pub cancel: bool,
+    /// Delivery restrictions (if any).
+    pub delivery_restrictions: Option<DeliveryRestrictions>,
+    /// Component offsets. The segmentation applies to the whole program if
+    /// empty.
+    pub components: Vec<SegmentationComponent>,
+    /// Segmentation duration (in the 90 kHz time base).
+    pub duration: Option<u64>,
+    /// Type of the unique program ID.
+    pub upid_type: u8,
+    /// Unique program ID.
+    pub upid: Vec<u8>,
+    /// Segmentation type ID (see the associated constants).
+    pub segmentation_type_id: u8,
+    /// Segment number.
+    pub segment_num: u8,
+    /// Expected number of segments.
+    pub segments_expected: u8,
+    /// Sub-segment number and the expected number of sub-segments. They
+    /// may be present only for placement opportunity starts (and they are
+    /// missing in sections following older versions of the standard).
+    pub sub_segments: Option<(u8, u8)>,
+}
+
+impl SegmentationDescriptor {
+    pub const PROGRAM_START: u8 = 0x10;
+    pub const PROGRAM_END: u8 = 0x11;
+    pub const CHAPTER_START: u8 = 0x20;
+    pub const CHAPTER_END: u8 = 0x21;
+    pub const BREAK_START: u8 = 0x22;
+    pub const BREAK_END: u8 = 0x23;
+    pub const PROVIDER_ADVERTISEMENT_START: u8 = 0x30;
+    pub const PROVIDER_ADVERTISEMENT_END: u8 = 0x31;
+    pub const DISTRIBUTOR_ADVERTISEMENT_START: u8 = 0x32;
+    pub const DISTRIBUTOR_ADVERTISEMENT_END: u8 = 0x33;
+    pub const PROVIDER_PLACEMENT_OPPORTUNITY_START: u8 = 0x34;
+    pub const PROVIDER_PLACEMENT_OPPORTUNITY_END: u8 = 0x35;
+    pub const DISTRIBUTOR_PLACEMENT_OPPORTUNITY_START: u8 = 0x36;
+    pub const DISTRIBUTOR_PLACEMENT_OPPORTUNITY_END: u8 = 0x37;
+
+    /// Create a new program segmentation descriptor with a given event ID
+    /// and segmentation type ID.
+    pub fn new(event_id: u32, segmentation_type_id: u8) -> Self {
+        Self {
+            event_id,
+            cancel: false,
+            delivery_restrictions: None,
+            components: Vec::new(),
+            duration: None,
+            upid_type: 0,
+            upid: Vec::new(),
+            segmentation_type_id,
+            segment_num: 0,
+            segments_expected: 0,
+            sub_segments: None,
+        }
+    }
+
+    /// Get the segmentation duration (if any) as a timestamp.
+    pub fn duration(&self) -> Option<Timestamp> {
+        self.duration
+            .map(|duration| Timestamp::new(duration as i64, SCTE35_TIME_BASE))
+    }
+
+    /// Parse the descriptor (following the identifier).
+    fn parse(reader: &mut BitReader) -> Result<Self, Error> {
+        let event_id = reader.read(32)? as u32;
+        let cancel = reader.read_flag()?;
+
+        reader.skip(7)?;
+
+        let mut res = Self::new(event_id, 0);
+
+        res.cancel = cancel;
+
+        if cancel {
+            return Ok(res);
+        }
+
+        let program_segmentation = reader.read_flag()?;
+        let has_duration = reader.read_flag()?;
+        let delivery_not_restricted = reader.read_flag()?;
+
+        if delivery_not_restricted {
+            reader.skip(5)?;
+        } else {
+            let restrictions = DeliveryRestrictions {
+                web_delivery_allowed: reader.read_flag()?,
+                no_regional_blackout: reader.read_flag()?,
+                archive_allowed: reader.read_flag()?,
+                device_restrictions: reader.read(2)? as u8,
+            };
+
+            res.delivery_restrictions = Some(restrictions);
+        }
+
+        if !program_segmentation {
+            let count = reader.read(8)?;
+
+            for _ in 0..count {
+                let tag = reader.read(8)? as u8;
+
+                reader.skip(7)?;
+
+                let pts_offset = reader.read(33)?;
+
+                res.components
+                    .push(SegmentationComponent { tag, pts_offset });
+            }
+        }
+
+        if has_duration {
+            res.duration = Some(reader.read(40)?);
+        }
+
+        res.upid_type = reader.read(8)? as u8;
+
+        let upid_length = reader.read(8)? as usize;
+
+        res.upid = reader.read_bytes(upid_length)?.to_vec();
+        res.segmentation_type_id = reader.read(8)? as u8;
+        res.segment_num = reader.read(8)? as u8;
+        res.segments_expected = reader.read(8)? as u8;
+
+        // the sub-segment fields are missing in older versions of the
+        // standard
+        if has_sub_segments(res.segmentation_type_id) && !reader.is_empty() {
+            let num = reader.read(8)? as u8;
+            let expected = reader.read(8)? as u8;
+
+            res.sub_segments = Some((num, expected));
+        }
+
+        Ok(res)
+    }
+
+    /// Write the descriptor (following the identifier).
+    fn write(&self, writer: &mut BitWriter) -> Result<(), Error> {
+        writer.write(32, self.event_id as u64);
+        writer.write_flag(self.cancel);
+        writer.write(7, 0x7f);
+
+        if self.cancel {
+            return Ok(());
+        }
+
+        writer.write_flag(self.components.is_empty());
+        writer.write_flag(self.duration.is_some());
+
+        if let Some(restrictions) = self.delivery_restrictions {
+            writer.write_flag(false);
+            writer.write_flag(restrictions.web_delivery_allowed);
+            writer.write_flag(restrictions.no_regional_blackout);
+            writer.write_flag(restrictions.archive_allowed);
+            writer.write(2, (restrictions.device_restrictions & 0x3) as u64);
+        } else {
+            writer.write_flag(true);
+            writer.write(5, 0x1f);
+        }
+
+        if !self.components.is_empty() {
+            if self.components.len() > 0xff {
+                return Err(Error::new("too many segmentation components"));
+            }
+
+            writer.write(8, self.components.len() as u64);
+
+            for component in &self.components {
+                writer.write(8, component.tag as u64);
+                writer.write(7, 0x7f);
+                writer.write(33, component.pts_offset & PTS_MASK);
+            }
+        }
+
+        if let Some(duration) = self.duration {
+            writer.write(40, duration & 0xff_ffff_ffff);
+        }
+
+        if self.upid.len() > 0xff {
+            return Err(Error::new("segmentation UPID too long"));
+        }
+
+        writer.write(8, self.upid_type as u64);
+        writer.write(8, self.upid.len() as u64);
+        writer.write_bytes(&self.upid);
+        writer.write(8, self.segmentation_type_id as u64);
+        writer.write(8, self.segment_num as u64);
+        writer.write(8, self.segments_expected as u64);
+
+        if let Some((num, expected)) = self.sub_segments {
+            if !has_sub_segments(self.segmentation_type_id) {
+                return Err(Error::new(
+                    "sub-segments are not allowed for the segmentation type",
+                ));
+            }
+
+            writer.write(8, num as u64);
+            writer.write(8, expected as u64);
+        }
+
+        Ok(())
+    }
+}
+
+/// Delivery restrictions of a segment.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct DeliveryRestrictions {
+    /// Delivery via the Internet is allowed.
+    pub web_delivery_allowed: bool,
+    /// There is no regional blackout.
+    pub no_regional_blackout: bool,
+    /// Recording is allowed.
+    pub archive_allowed: bool,
+    /// Device restrictions (2 bits).
+    pub device_restrictions: u8,
+}
+
+/// Segmentation component offset.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SegmentationComponent {
+    /// Component tag (it identifies the elementary stream).
+    pub tag: u8,
+    /// Offset from the splice time (in the 90 kHz time base).
+    pub pts_offset: u64,
+}
+
+/// Check if descriptors with a given segmentation type ID contain the
+/// sub-segment fields.
+fn has_sub_segments(segmentation_type_id: u8) -> bool {
+    matches!(
+        segmentation_type_id,
+        0x30 | 0x32 | 0x34 | 0x36 | 0x38 | 0x3a | 0x44 | 0x46
+    )
+}
+
+/// Read the `splice_time()` structure.
+fn read_splice_time(reader: &mut BitReader) -> Result<Option<u64>, Error> {
+    if reader.read_flag()? {
+        reader.skip(6)?;
+
+        Ok(Some(reader.read(33)?))
+    } else {
+        reader.skip(7)?;
+
+        Ok(None)
+    }
+}
+
+/// Write the `splice_time()` structure.
+fn write_splice_time(writer: &mut BitWriter, time: Option<u64>) {
+    if let Some(time) = time {
+        writer.write_flag(true);
+        writer.write(6, 0x3f);
+        writer.write(33, time & PTS_MASK);
+    } else {
+        writer.write_flag(false);
+        writer.write(7, 0x7f);
+    }
+}
+
+/// Calculate the MPEG-2 CRC-32 of given data.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffff_u32;
+
+    for &b in data {
+        crc ^= (b as u32) << 24;
+
+        for _ in 0..8 {
+            if crc & 0x8000_0000 != 0 {
+                crc = (crc << 1) ^ 0x04c1_1db7;
+            } else {
+                crc <<= 1;
+            }
+        }
+    }
+
+    crc
+}
+
+/// MSB-first bit reader.
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    /// Create a new reader.
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    /// Read a given number of bits (at most 64).
+    fn read(&mut self, bits: usize) -> Result<u64, Error> {
+        if self.position + bits > self.data.len() * 8 {
+            return Err(Error::new("truncated splice info section"));
+        }
+
+        let mut res = 0;
+
+        for _ in 0..bits {
+            let byte = self.data[self.position >> 3];
+            let bit = (byte >> (7 - (self.position & 7))) & 1;
+
+            res = (res << 1) | bit as u64;
+
+            self.position += 1;
+        }
+
+        Ok(res)
+    }
+
+    /// Read a single bit flag.
+    fn read_flag(&mut self) -> Result<bool, Error> {
+        Ok(self.read(1)? != 0)
+    }
+
+    /// Skip a given number of bits.
+    fn skip(&mut self, bits: usize) -> Result<(), Error> {
+        self.read(bits).map(|_| ())
+    }
+
+    /// Read a given number of bytes. The reader must be byte aligned.
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        debug_assert_eq!(self.position & 7, 0);
+
+        let start = self.position >> 3;
+
+        let res = self
+            .data
+            .get(start..start + len)
+            .ok_or_else(|| Error::new("truncated splice info section"))?;
+
+        self.position += len * 8;
+
+        Ok(res)
+    }
+
+    /// Get the remaining bytes. The reader must be byte aligned.
+    fn remaining(&self) -> &'a [u8] {
+        &self.data[self.position >> 3..]
+    }
+
+    /// Get the number of bytes consumed so far.
+    fn position(&self) -> usize {
+        (self.position + 7) >> 3
+    }
+
+    /// Check if there is no data left.
+    fn is_empty(&self) -> bool {
+        self.position >= self.data.len() * 8
+    }
+}
+
+/// MSB-first bit writer.
+struct BitWriter {
+    data: Vec<u8>,
+    position: usize,
+}
+
+impl BitWriter {
+    /// Create a new writer.
+    fn new() -> Self {
+        Self {
+            data: Vec::new(),
+            position: 0,
+        }
+    }
+
+    /// Write given number of least significant bits of a given value.
+    fn write(&mut self, bits: usize, value: u64) {
+        for i in (0..bits).rev() {
+            if self.position & 7 == 0 {
+                self.data.push(0);
+            }
+
+            let bit = ((value >> i) & 1) as u8;
+
+            let last = self.data.len() - 1;
+
+            self.data[last] |= bit << (7 - (self.position & 7));
+
+            self.position += 1;
+        }
+    }
+
+    /// Write a single bit flag.
+    fn write_flag(&mut self, flag: bool) {
+        self.write(1, flag as u64);
+    }
+
+    /// Write given bytes.
+    fn write_bytes(&mut self, data: &[u8]) {
+        for &b in data {
+            self.write(8, b as u64);
+        }
+    }
+
+    /// Finish the writer (padding the last byte with zeros).
+    fn finish(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        BreakDuration, SegmentationDescriptor, SpliceCommand, SpliceDescriptor, SpliceInfoSection,
+        SpliceInsert,
+    };
+
+    #[test]
+    fn test_time_signal() {
+        // time_signal sample from the SCTE-35 specification (provider
+        // placement opportunity start)
+        let data = [
+            0xfc, 0x30, 0x34, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xf0, 0x05, 0x06,
+            0xfe, 0x72, 0xbd, 0x00, 0x50, 0x00, 0x1e, 0x02, 0x1c, 0x43, 0x55, 0x45, 0x49, 0x48,
+            0x00, 0x00, 0x8e, 0x7f, 0xcf, 0x00, 0x01, 0xa5, 0x99, 0xb0, 0x08, 0x08, 0x00, 0x00,
+            0x00, 0x00, 0x2c, 0xa0, 0xa1, 0x8a, 0x34, 0x02, 0x00, 0x9a, 0xc9, 0xd1, 0x7e,
+        ];
+
+        let section = SpliceInfoSection::from_bytes(&data).unwrap();
+
+        assert_eq!(
+            section.command,
+            SpliceCommand::TimeSignal(Some(0x72bd_0050))
+        );
+        assert_eq!(section.descriptors.len(), 1);
+
+        if let SpliceDescriptor::Segmentation(descriptor) = &section.descriptors[0] {
+            assert_eq!(descriptor.event_id, 0x4800_008e);
+            assert_eq!(descriptor.duration, Some(0x01a5_99b0));
+            assert_eq!(descriptor.upid_type, 0x08);
+            assert_eq!(descriptor.upid, [0, 0, 0, 0, 0x2c, 0xa0, 0xa1, 0x8a]);
+            assert_eq!(
+                descriptor.segmentation_type_id,
+                SegmentationDescriptor::PROVIDER_PLACEMENT_OPPORTUNITY_START
+            );
+            assert_eq!(descriptor.segment_num, 2);
+            assert_eq!(descriptor.sub_segments, None);
+        } else {
+            panic!("segmentation descriptor expected");
+        }
+
+        assert_eq!(section.to_bytes().unwrap(), data);
+    }
+
+    #[test]
+    fn test_splice_insert() {
+        let mut insert = SpliceInsert::new(42);
+
+        insert.immediate = false;
+        insert.splice_time = Some(900_000);
+        insert.break_duration = Some(BreakDuration {
+            auto_return: true,
+            duration: 30 * 90_000,
+        });
+
+        let mut section = SpliceInfoSection::new(SpliceCommand::Insert(insert));
+
+        section.pts_adjustment = 0x1_ffff_ffff;
+
+        let data = section.to_bytes().unwrap();
+
+        let parsed = SpliceInfoSection::from_bytes(&data).unwrap();
+
+        assert_eq!(parsed, section);
+        assert_eq!(parsed.splice_time().unwrap().timestamp(), 899_999);
+
+        let mut corrupted = data;
+
+        corrupted[10] ^= 1;
+
+        assert!(SpliceInfoSection::from_bytes(&corrupted).is_err());
+    }
+}
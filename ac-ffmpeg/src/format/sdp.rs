@@ -0,0 +1,159 @@
+//! SDP generation.
+
+use std::os::raw::{c_char, c_int, c_void};
+
+use crate::{format::muxer::Muxer, Error};
+
+extern "C" {
+    fn ffw_muxer_create_sdp(
+        muxers: *const *const c_void,
+        count: c_int,
+        buffer: *mut c_char,
+        size: c_int,
+    ) -> c_int;
+}
+
+/// Initial size of the SDP buffer.
+const INITIAL_SIZE: usize = 4096;
+
+/// Maximum size of the SDP buffer.
+const MAX_SIZE: usize = 1 << 20;
+
+/// Create an SDP describing RTP sessions of given muxers. The muxers are
+/// expected to use the `rtp` output format and their URLs (i.e. the `url`
+/// muxer option) should be set to the RTP destinations (e.g.
+/// `rtp://127.0.0.1:5004`), so that the SDP contains the connection
+/// information. Multiple muxers (e.g. one per stream) are described by a
+/// single session description.
+///
+/// # Example
+/// ```text
+/// let video = Muxer::builder()
+///     .set_option("url", "rtp://127.0.0.1:5004")
+///     ...
+///     .build(video_io, OutputFormat::find_by_name("rtp").unwrap())?;
+///
+/// let audio = Muxer::builder()
+///     .set_option("url", "rtp://127.0.0.1:5006")
+///     ...
+///     .build(audio_io, OutputFormat::find_by_name("rtp").unwrap())?;
+///
+/// let sdp = sdp::create(&[&video, &audio])?;
+/// ```
+pub fn create<T>(muxers: &[&Muxer<T>]) -> Result<String, Error> {
+    if muxers.is_empty() {
+        return Err(Error::new("no muxers given"));
+    }
+
+    let muxers = muxers
+        .iter()
+        .map(|muxer| muxer.as_ptr())
+        .collect::<Vec<_>>();
+
+    let mut size = INITIAL_SIZE;
+
+    loop {
+        let mut buffer = vec![0u8; size];
+
+        let ret = unsafe {
+            ffw_muxer_create_sdp(
+                muxers.as_ptr(),
+                muxers.len() as _,
+                buffer.as_mut_ptr() as _,
+                size as _,
+            )
+        };
+
+        if ret < 0 {
+            return Err(Error::from_raw_error_code(ret));
+        }
+
+        let len = buffer.iter().position(|&b| b == 0).unwrap_or(size);
+
+        // the SDP gets silently truncated if the buffer is too small
+        if (len + 1) < size || size >= MAX_SIZE {
+            buffer.truncate(len);
+
+            return String::from_utf8(buffer).map_err(|_| Error::new("invalid SDP"));
+        }
+
+        size *= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        codec::{video::frame::get_pixel_format, CodecParameters, VideoCodecParameters},
+        format::{
+            io::IO,
+            muxer::{Muxer, OutputFormat},
+        },
+    };
+
+    use super::INITIAL_SIZE;
+
+    /// Create an RTP muxer with a single MPEG-4 video stream sending data
+    /// to a given port.
+    fn rtp_muxer(port: u16) -> Muxer<Vec<u8>> {
+        let params: CodecParameters = VideoCodecParameters::builder("mpeg4")
+            .unwrap()
+            .pixel_format(get_pixel_format("yuv420p"))
+            .width(64)
+            .height(48)
+            .build()
+            .into();
+
+        let mut builder = Muxer::builder().set_option("url", format!("rtp://127.0.0.1:{}", port));
+
+        builder.add_stream(&params).unwrap();
+
+        builder
+            .build(
+                IO::from_write_stream(Vec::new()),
+                OutputFormat::find_by_name("rtp").unwrap(),
+            )
+            .unwrap()
+    }
+
+    /// Count lines of a given SDP starting with a given prefix.
+    fn count_lines(sdp: &str, prefix: &str) -> usize {
+        sdp.lines().filter(|line| line.starts_with(prefix)).count()
+    }
+
+    #[test]
+    fn test_create() {
+        assert!(super::create::<Vec<u8>>(&[]).is_err());
+
+        let muxer = rtp_muxer(5004);
+
+        let sdp = super::create(&[&muxer]).unwrap();
+
+        assert!(sdp.starts_with("v=0"));
+        assert_eq!(count_lines(&sdp, "m=video 5004 "), 1);
+        assert_eq!(count_lines(&sdp, "a=rtpmap:"), 1);
+        assert!(sdp.contains("127.0.0.1"));
+    }
+
+    #[test]
+    fn test_large_sdp() {
+        // every media description takes at least about 80 bytes, so the
+        // SDP does not fit into the initial buffer
+        let muxers = (0..100)
+            .map(|index| rtp_muxer(5000 + 2 * index))
+            .collect::<Vec<_>>();
+
+        let muxers = muxers.iter().collect::<Vec<_>>();
+
+        let sdp = super::create(&muxers).unwrap();
+
+        assert!(sdp.len() > INITIAL_SIZE);
+        assert!(sdp.starts_with("v=0"));
+        assert_eq!(count_lines(&sdp, "m=video "), 100);
+        assert_eq!(count_lines(&sdp, "a=rtpmap:"), 100);
+
+        // the last media description must not be truncated
+        assert!(sdp.contains("m=video 5198 "));
+        assert!(sdp.ends_with('\n'));
+    }
+}
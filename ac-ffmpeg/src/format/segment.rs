@@ -0,0 +1,501 @@
+//! Segment muxer with segment notifications.
+//!
+//! This is a wrapper around the FFmpeg `segment` muxer. The segment muxer
+//! writes the segments into files (or other URLs) on its own. Segments are
+//! cut by duration at keyframes of the first video stream (or any stream
+//! if there is no video). The FFmpeg segment muxer cannot cut segments by
+//! size, use the `Recorder` from the `format::recorder` module if you need
+//! size limits.
+//!
+//! Segment boundaries are not reported by the segment muxer directly. They
+//! are derived from the packets pushed into the muxer at the moment when the
+//! segment muxer opens a new segment, so the reported start and end times
+//! are approximate. The segment muxer may, for example, still buffer a few
+//! packets of the previous segment when it switches to the next one. Only
+//! URLs matching the segment URL pattern are reported as segments, other
+//! files opened by the segment muxer (e.g. the `segment_list` file) are
+//! ignored.
+
+use std::{
+    ffi::CStr,
+    io::{self, Sink},
+    os::raw::{c_char, c_void},
+    time::Duration,
+};
+
+use crate::{
+    codec::CodecParameters,
+    format::{
+        io::IO,
+        muxer::{Muxer, MuxerBuilder, OutputFormat},
+    },
+    packet::Packet,
+    time::Timestamp,
+    Error,
+};
+
+/// Segment event.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SegmentEvent {
+    /// A new segment has been opened.
+    Opened,
+    /// A segment has been closed.
+    Closed,
+}
+
+/// Segment event handler.
+type SegmentHandler = Box<dyn FnMut(SegmentEvent, &Segment) + Send>;
+
+/// Information about a single segment.
+#[derive(Debug, Clone)]
+pub struct Segment {
+    index: usize,
+    url: String,
+    start: Timestamp,
+    end: Timestamp,
+}
+
+impl Segment {
+    /// Get index of the segment.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Get URL of the segment.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Get approximate timestamp of the first packet in the segment (as
+    /// pushed into the muxer).
+    pub fn start(&self) -> Timestamp {
+        self.start
+    }
+
+    /// Get approximate end of the segment (as given by the packets pushed
+    /// into the muxer). The timestamp is null until the segment is closed.
+    pub fn end(&self) -> Timestamp {
+        self.end
+    }
+}
+
+/// Builder for the segment muxer.
+pub struct SegmentMuxerBuilder {
+    builder: MuxerBuilder,
+    start_number: usize,
+    handler: Option<SegmentHandler>,
+}
+
+impl SegmentMuxerBuilder {
+    /// Create a new builder.
+    fn new() -> Self {
+        Self {
+            builder: Muxer::builder(),
+            start_number: 0,
+            handler: None,
+        }
+    }
+
+    /// Add a new stream with given parameters and return index of the new
+    /// stream.
+    pub fn add_stream(&mut self, params: &CodecParameters) -> Result<usize, Error> {
+        self.builder.add_stream(params)
+    }
+
+    /// Set a segment muxer option.
+    pub fn set_option<V>(mut self, name: &str, value: V) -> Self
+    where
+        V: ToString,
+    {
+        self.builder = self.builder.set_option(name, value);
+        self
+    }
+
+    /// Set format of the segments (e.g. `mpegts` or `mp4`). The format is
+    /// guessed from the URL pattern by default.
+    pub fn segment_format(self, format: &str) -> Self {
+        self.set_option("segment_format", format)
+    }
+
+    /// Set the target segment duration. The default is 2 seconds.
+    pub fn segment_duration(self, duration: Duration) -> Self {
+        let duration = format!("{}.{:06}", duration.as_secs(), duration.subsec_micros());
+
+        self.set_option("segment_time", duration)
+    }
+
+    /// Make timestamps of every segment start from zero. It is disabled by
+    /// default.
+    pub fn reset_timestamps(self, reset: bool) -> Self {
+        self.set_option("reset_timestamps", reset as i32)
+    }
+
+    /// Set index of the first segment. The default is 0.
+    pub fn start_number(mut self, index: usize) -> Self {
+        self.start_number = index;
+        self.set_option("segment_start_number", index)
+    }
+
+    /// Set a handler that will be called whenever a segment is opened or
+    /// closed. The handler is called from the thread using the muxer, right
+    /// after the muxer call that caused the event. A segment is always
+    /// closed before the next one is opened.
+    pub fn segment_handler<F>(mut self, handler: F) -> Self
+    where
+        F: FnMut(SegmentEvent, &Segment) + Send + 'static,
+    {
+        self.handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Build the segment muxer. Segments will be written to URLs given by a
+    /// given pattern containing a segment index placeholder (e.g.
+    /// `segment-%05d.ts`).
+    pub fn build(mut self, pattern: &str) -> Result<SegmentMuxer, Error> {
+        let format = OutputFormat::find_by_name("segment")
+            .ok_or_else(|| Error::new("the segment muxer is not available"))?;
+
+        let opened = Box::into_raw(Box::new(Vec::new()));
+
+        self.builder
+            .set_io_open_callback(segment_opened, opened as *mut c_void);
+
+        let mut res = SegmentMuxer {
+            muxer: None,
+            pattern: pattern.to_string(),
+            opened,
+            handler: self.handler,
+            next_index: self.start_number,
+            current: None,
+            reported: false,
+            last_ts: Timestamp::null(),
+        };
+
+        // the segment muxer opens the segments on its own, the IO is not used
+        let muxer = self
+            .builder
+            .set_option("url", pattern)
+            .build(IO::from_write_stream(io::sink()), format)?;
+
+        res.muxer = Some(muxer);
+
+        // the first segment is opened during initialization but it is reported
+        // along with the first packet
+        res.process_events(Timestamp::null());
+
+        Ok(res)
+    }
+}
+
+/// Segment muxer.
+///
+/// Packets must be pushed in the decoding order and interleaved across
+/// streams.
+pub struct SegmentMuxer {
+    muxer: Option<Muxer<Sink>>,
+    pattern: String,
+    opened: *mut Vec<String>,
+    handler: Option<SegmentHandler>,
+    next_index: usize,
+    current: Option<Segment>,
+    reported: bool,
+    last_ts: Timestamp,
+}
+
+impl SegmentMuxer {
+    /// Get a segment muxer builder.
+    pub fn builder() -> SegmentMuxerBuilder {
+        SegmentMuxerBuilder::new()
+    }
+
+    /// Push a given packet to the muxer.
+    pub fn push(&mut self, packet: Packet) -> Result<(), Error> {
+        let pts = packet.pts();
+        let dts = packet.dts();
+
+        let ts = if pts.is_null() { dts } else { pts };
+
+        let end = match packet.duration() {
+            Some(duration) if !ts.is_null() => ts + duration,
+            _ => ts,
+        };
+
+        self.muxer_mut().push(packet)?;
+
+        self.process_events(ts);
+
+        if !end.is_null() && (self.last_ts.is_null() || end > self.last_ts) {
+            self.last_ts = end;
+        }
+
+        Ok(())
+    }
+
+    /// Flush the muxer.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.muxer_mut().flush()?;
+
+        self.process_events(self.last_ts);
+
+        Ok(())
+    }
+
+    /// Close the muxer. This will close the last segment.
+    pub fn close(mut self) -> Result<(), Error> {
+        let muxer = self.muxer.take().unwrap();
+
+        let res = muxer.close();
+
+        self.process_events(self.last_ts);
+        self.close_current(self.last_ts);
+
+        res.map(|_| ())
+    }
+
+    /// Get the current segment (if any).
+    pub fn current_segment(&self) -> Option<&Segment> {
+        self.current.as_ref()
+    }
+
+    /// Get the inner muxer.
+    fn muxer_mut(&mut self) -> &mut Muxer<Sink> {
+        self.muxer.as_mut().unwrap()
+    }
+
+    /// Report all segments opened by the last muxer call. A given timestamp
+    /// is the timestamp of the packet that caused the segment switch.
+    fn process_events(&mut self, ts: Timestamp) {
+        let opened = unsafe { std::mem::take(&mut *self.opened) };
+
+        for url in opened {
+            // the segment muxer opens other files as well (e.g. segment lists)
+            if !matches_pattern(&self.pattern, &url) {
+                continue;
+            }
+
+            self.close_current(ts);
+
+            let segment = Segment {
+                index: self.next_index,
+                url,
+                start: ts,
+                end: Timestamp::null(),
+            };
+
+            self.next_index += 1;
+
+            self.current = Some(segment);
+        }
+
+        // segments are reported as opened once their start is known
+        if !self.reported && !ts.is_null() {
+            if let Some(mut segment) = self.current.take() {
+                segment.start = ts;
+
+                self.report(SegmentEvent::Opened, &segment);
+
+                self.current = Some(segment);
+                self.reported = true;
+            }
+        }
+    }
+
+    /// Close the current segment (if any) at a given timestamp.
+    fn close_current(&mut self, end: Timestamp) {
+        if let Some(mut segment) = self.current.take() {
+            if !self.reported {
+                segment.start = end;
+
+                self.report(SegmentEvent::Opened, &segment);
+            }
+
+            segment.end = end;
+
+            self.report(SegmentEvent::Closed, &segment);
+        }
+
+        self.reported = false;
+    }
+
+    /// Pass a given event to the handler.
+    fn report(&mut self, event: SegmentEvent, segment: &Segment) {
+        if let Some(handler) = self.handler.as_mut() {
+            handler(event, segment);
+        }
+    }
+}
+
+impl Drop for SegmentMuxer {
+    fn drop(&mut self) {
+        // the muxer must be dropped first as it may still use the URL buffer
+        self.muxer = None;
+
+        unsafe {
+            drop(Box::from_raw(self.opened));
+        }
+    }
+}
+
+unsafe impl Send for SegmentMuxer {}
+
+/// Remember a given URL opened by the segment muxer.
+extern "C" fn segment_opened(opaque: *mut c_void, url: *const c_char) {
+    let opened = unsafe { &mut *(opaque as *mut Vec<String>) };
+
+    let url = unsafe { CStr::from_ptr(url) };
+
+    opened.push(url.to_string_lossy().into_owned());
+}
+
+/// Check if a given URL could have been created from a given segment URL
+/// pattern by replacing its segment index placeholder (e.g. `%05d`) with a
+/// number. Patterns containing other placeholders (e.g. `strftime`
+/// patterns) match any URL.
+fn matches_pattern(pattern: &str, url: &str) -> bool {
+    let mut prefix = String::new();
+    let mut suffix = String::new();
+    let mut placeholder = false;
+
+    let mut chars = pattern.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        let literal = if placeholder {
+            &mut suffix
+        } else {
+            &mut prefix
+        };
+
+        if c != '%' {
+            literal.push(c);
+            continue;
+        }
+
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            literal.push('%');
+            continue;
+        }
+
+        while matches!(chars.peek(), Some(c) if c.is_ascii_digit()) {
+            chars.next();
+        }
+
+        if placeholder || chars.next() != Some('d') {
+            return true;
+        }
+
+        placeholder = true;
+    }
+
+    if !placeholder {
+        return url == prefix;
+    }
+
+    if url.len() <= prefix.len() + suffix.len()
+        || !url.starts_with(&prefix)
+        || !url.ends_with(&suffix)
+    {
+        return false;
+    }
+
+    url[prefix.len()..url.len() - suffix.len()]
+        .bytes()
+        .all(|b| b.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::Cursor,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use super::{matches_pattern, SegmentEvent, SegmentMuxer};
+
+    use crate::{
+        format::{demuxer::Demuxer, io::IO},
+        test_util::encode_video,
+    };
+
+    #[test]
+    fn test_pattern_matching() {
+        assert!(matches_pattern("segment-%05d.ts", "segment-00001.ts"));
+        assert!(matches_pattern("segment-%d.ts", "segment-123.ts"));
+        assert!(matches_pattern("100%%-%03d.ts", "100%-001.ts"));
+
+        assert!(!matches_pattern("segment-%05d.ts", "segment-.ts"));
+        assert!(!matches_pattern("segment-%05d.ts", "segment-0000a.ts"));
+        assert!(!matches_pattern("segment-%05d.ts", "segments.m3u8"));
+        assert!(!matches_pattern("segment-%05d.ts", "segment-00001.ts.tmp"));
+
+        // strftime patterns cannot be matched
+        assert!(matches_pattern("segment-%Y%m%d.ts", "segments.m3u8"));
+    }
+
+    #[test]
+    fn test_segment_events() {
+        let dir = std::env::temp_dir().join(format!("ac-ffmpeg-segment-{}", std::process::id()));
+
+        fs::create_dir_all(&dir).unwrap();
+
+        let pattern = dir.join("segment-%03d.mkv");
+        let list = dir.join("segments.csv");
+
+        let pattern = pattern.to_str().unwrap();
+        let list = list.to_str().unwrap();
+
+        let input = IO::from_read_stream(Cursor::new(encode_video(64, 48, 50, 25)));
+
+        let mut demuxer = Demuxer::builder()
+            .build(input)
+            .unwrap()
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)
+            .unwrap();
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        let sink = events.clone();
+
+        let mut builder = SegmentMuxer::builder()
+            .segment_format("matroska")
+            .segment_duration(Duration::from_secs(1))
+            .set_option("segment_list", list)
+            .segment_handler(move |event, segment| {
+                sink.lock()
+                    .unwrap()
+                    .push((event, segment.url().to_string()));
+            });
+
+        builder
+            .add_stream(&demuxer.streams()[0].codec_parameters())
+            .unwrap();
+
+        let mut muxer = builder.build(pattern).unwrap();
+
+        while let Some(packet) = demuxer.take().unwrap() {
+            muxer.push(packet).unwrap();
+        }
+
+        muxer.flush().unwrap();
+        muxer.close().unwrap();
+
+        let events = events.lock().unwrap().clone();
+
+        fs::remove_dir_all(&dir).unwrap();
+
+        let first = pattern.replace("%03d", "000");
+        let second = pattern.replace("%03d", "001");
+
+        let expected = [
+            (SegmentEvent::Opened, first.clone()),
+            (SegmentEvent::Closed, first),
+            (SegmentEvent::Opened, second.clone()),
+            (SegmentEvent::Closed, second),
+        ];
+
+        assert_eq!(events, expected);
+    }
+}
@@ -0,0 +1,405 @@
+//! Video generation from a still image and an audio track.
+
+use std::{
+    io::{Read, Write},
+    time::Duration,
+};
+
+use crate::{
+    codec::{
+        audio::AudioTranscoder,
+        video::{frame::get_pixel_format, VideoEncoder, VideoFrame, VideoFrameScaler},
+        AudioCodecParameters, Encoder,
+    },
+    format::{
+        extract::{encoder_name, open, reset_codec_tag, CodecPolicy},
+        io::IO,
+        muxer::{Muxer, OutputFormat},
+    },
+    packet::Packet,
+    time::{Rounding, TimeBase, Timestamp},
+    Error,
+};
+
+/// Options of the still image video generation.
+#[derive(Debug, Clone)]
+pub struct StillImageOptions {
+    video_encoder: String,
+    audio_policy: CodecPolicy,
+    frame_rate: u32,
+    keyframe_interval: Duration,
+    size: Option<(usize, usize)>,
+}
+
+impl StillImageOptions {
+    /// Create new options with the default settings.
+    pub fn new() -> Self {
+        Self {
+            video_encoder: String::from("libx264"),
+            audio_policy: CodecPolicy::CopyOrTranscode(String::from("aac")),
+            frame_rate: 1,
+            keyframe_interval: Duration::from_secs(10),
+            size: None,
+        }
+    }
+
+    /// Set the video encoder. The default is `libx264`. The `stillimage`
+    /// tuning is used if the encoder is `libx264`.
+    pub fn video_encoder(mut self, encoder: &str) -> Self {
+        self.video_encoder = String::from(encoder);
+        self
+    }
+
+    /// Set codec policy for the audio track. The default is to copy the
+    /// audio if possible and transcode it into AAC otherwise.
+    pub fn audio_policy(mut self, policy: CodecPolicy) -> Self {
+        self.audio_policy = policy;
+        self
+    }
+
+    /// Set the video frame rate. The image does not change, so a low frame
+    /// rate is used to save bits and CPU time. The default is 1 fps.
+    pub fn frame_rate(mut self, frame_rate: u32) -> Self {
+        self.frame_rate = frame_rate.max(1);
+        self
+    }
+
+    /// Set the keyframe interval. It limits the seeking granularity. The
+    /// default is 10 seconds.
+    pub fn keyframe_interval(mut self, interval: Duration) -> Self {
+        self.keyframe_interval = interval;
+        self
+    }
+
+    /// Scale the image to a given size. The image is not scaled by default.
+    /// The size is always rounded down to even numbers, as required by the
+    /// 4:2:0 chroma subsampling.
+    pub fn size(mut self, width: usize, height: usize) -> Self {
+        self.size = Some((width, height));
+        self
+    }
+}
+
+impl Default for StillImageOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Create a video from a given still image and the first audio stream of a
+/// given input. The image is repeated for the whole duration of the audio
+/// track. The image can be decoded e.g. from a JPEG/PNG file using the
+/// demuxer and a video decoder.
+///
+/// # Example
+/// ```text
+/// let audio = IO::from_seekable_read_stream(File::open("episode.mp3")?);
+/// let output = IO::from_seekable_write_stream(File::create("episode.mp4")?);
+///
+/// let format = OutputFormat::find_by_name("mp4").unwrap();
+///
+/// let options = StillImageOptions::new().size(1280, 720);
+///
+/// still_image_video(&artwork, audio, output, format, &options)?;
+/// ```
+pub fn still_image_video<R, W>(
+    image: &VideoFrame,
+    audio: IO<R>,
+    output: IO<W>,
+    format: OutputFormat,
+    options: &StillImageOptions,
+) -> Result<IO<W>, Error>
+where
+    R: Read,
+    W: Write,
+{
+    let (mut demuxer, index) = open(audio, true)?;
+
+    let params = demuxer.streams()[index].codec_parameters();
+
+    let mut transcoder = match encoder_name(&format, &params, &options.audio_policy)? {
+        Some(encoder) => {
+            let input = params
+                .into_audio_codec_parameters()
+                .ok_or_else(|| Error::new("not an audio stream"))?;
+
+            let output = AudioCodecParameters::builder(encoder)?
+                .sample_format(input.sample_format())
+                .sample_rate(input.sample_rate())
+                .channel_layout(input.channel_layout())
+                .build();
+
+            let mut builder = AudioTranscoder::builder(input, output)?;
+
+            if format.requires_global_header() {
+                builder = builder.set_encoder_option("flags", "+global_header");
+            }
+
+            Some(builder.build()?)
+        }
+        None => None,
+    };
+
+    let audio_params = match transcoder.as_ref() {
+        Some(transcoder) => transcoder.codec_parameters().into(),
+        None => reset_codec_tag(demuxer.streams()[index].codec_parameters()),
+    };
+
+    let video = StillImageEncoder::new(image, options, &format)?;
+
+    let mut builder = Muxer::builder().interleaved(true);
+
+    builder.add_stream(&video.encoder.codec_parameters().into())?;
+    builder.add_stream(&audio_params)?;
+
+    let muxer = builder.build(output, format)?;
+
+    let mut muxer = StillImageMuxer {
+        muxer,
+        video,
+        end: Timestamp::null(),
+    };
+
+    while let Some(packet) = demuxer.take()? {
+        if packet.stream_index() != index {
+            continue;
+        }
+
+        if let Some(transcoder) = transcoder.as_mut() {
+            transcoder.push(packet)?;
+
+            while let Some(packet) = transcoder.take()? {
+                muxer.push_audio(packet)?;
+            }
+        } else {
+            muxer.push_audio(packet)?;
+        }
+    }
+
+    if let Some(transcoder) = transcoder.as_mut() {
+        transcoder.flush()?;
+
+        while let Some(packet) = transcoder.take()? {
+            muxer.push_audio(packet)?;
+        }
+    }
+
+    muxer.close()
+}
+
+/// Encoder repeating a single image.
+struct StillImageEncoder {
+    encoder: VideoEncoder,
+    frame: VideoFrame,
+    time_base: TimeBase,
+    next_frame: i64,
+}
+
+impl StillImageEncoder {
+    /// Create a new encoder for a given image.
+    fn new(
+        image: &VideoFrame,
+        options: &StillImageOptions,
+        format: &OutputFormat,
+    ) -> Result<Self, Error> {
+        let (width, height) = options.size.unwrap_or((image.width(), image.height()));
+
+        let width = (width & !1).max(2);
+        let height = (height & !1).max(2);
+
+        let time_base = TimeBase::new(1, options.frame_rate as _);
+
+        let gop = (options.keyframe_interval.as_secs_f64() * options.frame_rate as f64).round();
+
+        let mut builder = VideoEncoder::builder(&options.video_encoder)?;
+
+        let pixel_format = builder
+            .default_pixel_format()
+            .unwrap_or_else(|| get_pixel_format("yuv420p"));
+
+        if options.video_encoder == "libx264" {
+            builder = builder.tune("stillimage");
+        }
+
+        if format.requires_global_header() {
+            builder = builder.set_option("flags", "+global_header");
+        }
+
+        let mut scaler = VideoFrameScaler::builder()
+            .source_pixel_format(image.pixel_format())
+            .source_width(image.width())
+            .source_height(image.height())
+            .target_pixel_format(pixel_format)
+            .target_width(width)
+            .target_height(height)
+            .build()?;
+
+        let frame = scaler.scale(image)?.with_time_base(time_base);
+
+        // the scaler keeps the display aspect ratio
+        if let Some(sar) = frame.sample_aspect_ratio() {
+            builder = builder.sample_aspect_ratio(sar);
+        }
+
+        let encoder = builder
+            .pixel_format(pixel_format)
+            .width(width)
+            .height(height)
+            .time_base(time_base)
+            .gop_size(gop.max(1.0) as u32)
+            .build()?;
+
+        let res = Self {
+            encoder,
+            frame,
+            time_base,
+            next_frame: 0,
+        };
+
+        Ok(res)
+    }
+
+    /// Encode all frames starting before a given timestamp and push the
+    /// resulting packets into a given muxer.
+    fn encode_until<W>(&mut self, ts: Timestamp, muxer: &mut Muxer<W>) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        let ts = ts.rescale_rnd(self.time_base, Rounding::Up);
+
+        while self.next_frame < ts.timestamp() || self.next_frame == 0 {
+            let frame = self
+                .frame
+                .clone()
+                .with_pts(Timestamp::new(self.next_frame, self.time_base));
+
+            self.encoder.push(frame)?;
+
+            self.next_frame += 1;
+
+            while let Some(packet) = self.encoder.take()? {
+                muxer.push(packet.with_stream_index(0))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Muxer combining the image frames with the audio track.
+struct StillImageMuxer<W> {
+    muxer: Muxer<W>,
+    video: StillImageEncoder,
+    end: Timestamp,
+}
+
+impl<W> StillImageMuxer<W>
+where
+    W: Write,
+{
+    /// Push a given audio packet together with all video frames preceding
+    /// it.
+    fn push_audio(&mut self, packet: Packet) -> Result<(), Error> {
+        let pts = packet.pts();
+
+        let ts = if pts.is_null() { packet.dts() } else { pts };
+
+        if !ts.is_null() {
+            let end = match packet.duration() {
+                Some(duration) => ts + duration,
+                None => ts,
+            };
+
+            if self.end.is_null() || end > self.end {
+                self.end = end;
+            }
+
+            self.video.encode_until(ts, &mut self.muxer)?;
+        }
+
+        self.muxer.push(packet.with_stream_index(1))
+    }
+
+    /// Cover the rest of the audio track with video frames and close the
+    /// muxer.
+    fn close(mut self) -> Result<IO<W>, Error> {
+        let end = if self.end.is_null() {
+            Timestamp::new(0, self.video.time_base)
+        } else {
+            self.end
+        };
+
+        self.video.encode_until(end, &mut self.muxer)?;
+
+        self.video.encoder.flush()?;
+
+        while let Some(packet) = self.video.encoder.take()? {
+            self.muxer.push(packet.with_stream_index(0))?;
+        }
+
+        self.muxer.flush()?;
+        self.muxer.close()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::{still_image_video, StillImageOptions};
+
+    use crate::{
+        codec::video::{frame::get_pixel_format, VideoFrameMut},
+        format::{demuxer::Demuxer, extract::CodecPolicy, io::IO, muxer::OutputFormat},
+        test_util::encode_audio,
+    };
+
+    #[test]
+    fn test_still_image_video() {
+        let image = VideoFrameMut::black(get_pixel_format("rgb24"), 33, 17).freeze();
+
+        // 1 second of audio
+        let audio = IO::from_seekable_read_stream(Cursor::new(encode_audio(10)));
+        let output = IO::from_seekable_write_stream(Cursor::new(Vec::new()));
+
+        let format = OutputFormat::find_by_name("matroska").unwrap();
+
+        let options = StillImageOptions::new()
+            .video_encoder("mpeg4")
+            .audio_policy(CodecPolicy::Copy)
+            .frame_rate(2);
+
+        let output = still_image_video(&image, audio, output, format, &options).unwrap();
+
+        let data = output.into_stream().into_inner();
+
+        let mut demuxer = Demuxer::builder()
+            .build(IO::from_read_stream(Cursor::new(data)))
+            .unwrap()
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)
+            .unwrap();
+
+        let streams = demuxer.streams();
+
+        assert_eq!(streams.len(), 2);
+
+        let video = streams[0]
+            .codec_parameters()
+            .into_video_codec_parameters()
+            .unwrap();
+
+        // the size is rounded down to even numbers
+        assert_eq!((video.width(), video.height()), (32, 16));
+
+        assert!(streams[1].codec_parameters().is_audio_codec());
+
+        let mut packets = [0; 2];
+
+        while let Some(packet) = demuxer.take().unwrap() {
+            packets[packet.stream_index()] += 1;
+        }
+
+        assert_eq!(packets, [2, 10]);
+    }
+}
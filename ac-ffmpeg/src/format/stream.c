@@ -1,12 +1,17 @@
 #include <libavformat/avformat.h>
+#include <libavutil/display.h>
+
+#include <math.h>
+#include <string.h>
 
 void ffw_stream_get_time_base(const AVStream* stream, uint32_t* num, uint32_t* den);
 int64_t ffw_stream_get_start_time(const AVStream* stream);
 int64_t ffw_stream_get_duration(const AVStream* stream);
 int64_t ffw_stream_get_nb_frames(const AVStream* stream);
-double ffw_stream_get_avg_framerate(const AVStream* stream);
 AVCodecParameters* ffw_stream_get_codec_parameters(const AVStream* stream);
 int ffw_stream_set_metadata(AVStream* stream, const char* key, const char* value);
+double ffw_display_matrix_get_rotation(const uint8_t* data, size_t size);
+void ffw_display_matrix_init(uint8_t* data, double angle);
 
 void ffw_stream_get_time_base(const AVStream* stream, uint32_t* num, uint32_t* den) {
     *num = stream->time_base.num;
@@ -34,6 +39,27 @@ double ffw_stream_get_r_frame_rate(const AVStream* stream) {
     return av_q2d(stream->r_frame_rate);
 }
 
+void ffw_stream_get_r_frame_rate_q(const AVStream* stream, int* num, int* den) {
+    *num = stream->r_frame_rate.num;
+    *den = stream->r_frame_rate.den;
+}
+
+void ffw_stream_get_avg_frame_rate(const AVStream* stream, int* num, int* den) {
+    *num = stream->avg_frame_rate.num;
+    *den = stream->avg_frame_rate.den;
+}
+
+void ffw_stream_get_sample_aspect_ratio(const AVStream* stream, int* num, int* den) {
+    AVRational sar = stream->sample_aspect_ratio;
+
+    if (sar.num <= 0 || sar.den <= 0) {
+        sar = stream->codecpar->sample_aspect_ratio;
+    }
+
+    *num = sar.num;
+    *den = sar.den;
+}
+
 int ffw_stream_get_id(const AVStream* stream) {
     return stream->id;
 }
@@ -96,3 +122,39 @@ int ffw_stream_add_side_data(AVStream* stream, enum AVPacketSideDataType data_ty
 
     return ret;
 }
+
+int ffw_stream_get_discard(const AVStream* stream) {
+    return stream->discard;
+}
+
+void ffw_stream_set_discard(AVStream* stream, int discard) {
+    stream->discard = discard;
+}
+
+int ffw_stream_get_disposition(const AVStream* stream) {
+    return stream->disposition;
+}
+
+void ffw_stream_set_disposition(AVStream* stream, int disposition) {
+    stream->disposition = disposition;
+}
+
+double ffw_display_matrix_get_rotation(const uint8_t* data, size_t size) {
+    int32_t matrix[9];
+
+    if (size < sizeof(matrix)) {
+        return NAN;
+    }
+
+    memcpy(matrix, data, sizeof(matrix));
+
+    return av_display_rotation_get(matrix);
+}
+
+void ffw_display_matrix_init(uint8_t* data, double angle) {
+    int32_t matrix[9];
+
+    av_display_rotation_set(matrix, angle);
+
+    memcpy(data, matrix, sizeof(matrix));
+}
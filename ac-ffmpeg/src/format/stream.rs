@@ -3,6 +3,7 @@
 use std::{
     collections::HashMap,
     ffi::{CStr, CString},
+    ops::{BitOr, BitOrAssign},
     os::raw::{c_char, c_int, c_void},
     ptr,
 };
@@ -10,7 +11,8 @@ use std::{
 use crate::{
     codec::CodecParameters,
     packet::{SideDataRef, SideDataType},
-    time::{TimeBase, Timestamp},
+    time::{self, Rational, TimeBase, Timestamp},
+    timecode::Timecode,
     Error,
 };
 
@@ -21,6 +23,9 @@ extern "C" {
     fn ffw_stream_get_duration(stream: *const c_void) -> i64;
     fn ffw_stream_get_nb_frames(stream: *const c_void) -> i64;
     fn ffw_stream_get_r_frame_rate(stream: *const c_void) -> f64;
+    fn ffw_stream_get_r_frame_rate_q(stream: *const c_void, num: *mut c_int, den: *mut c_int);
+    fn ffw_stream_get_avg_frame_rate(stream: *const c_void, num: *mut c_int, den: *mut c_int);
+    fn ffw_stream_get_sample_aspect_ratio(stream: *const c_void, num: *mut c_int, den: *mut c_int);
     fn ffw_stream_get_codec_parameters(stream: *const c_void) -> *mut c_void;
     fn ffw_stream_get_id(stream: *const c_void) -> c_int;
     fn ffw_stream_set_metadata(
@@ -46,6 +51,158 @@ extern "C" {
         data: *const u8,
         size: usize,
     ) -> c_int;
+    fn ffw_stream_get_discard(stream: *const c_void) -> c_int;
+    fn ffw_stream_set_discard(stream: *mut c_void, discard: c_int);
+    fn ffw_stream_get_disposition(stream: *const c_void) -> c_int;
+    fn ffw_stream_set_disposition(stream: *mut c_void, disposition: c_int);
+
+    fn ffw_display_matrix_get_rotation(data: *const u8, size: usize) -> f64;
+    fn ffw_display_matrix_init(data: *mut u8, angle: f64);
+}
+
+/// Size of the display matrix side data (3x3 32-bit integers).
+const DISPLAY_MATRIX_SIZE: usize = 36;
+
+/// Stream discard mode. It tells the demuxer which packets of a given stream
+/// can be dropped at the container level (i.e. without passing them to the
+/// caller).
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Discard {
+    /// Do not discard anything.
+    None,
+    /// Discard useless packets (e.g. zero-size packets). This is the default.
+    Default,
+    /// Discard all non-reference packets.
+    NonRef,
+    /// Discard all bidirectional packets.
+    Bidirectional,
+    /// Discard all non-intra packets.
+    NonIntra,
+    /// Discard all packets except keyframes.
+    NonKey,
+    /// Discard all packets (i.e. ignore the stream).
+    All,
+}
+
+impl Discard {
+    /// Create a discard mode from its raw representation.
+    fn from_raw(value: c_int) -> Self {
+        match value {
+            v if v < 0 => Self::None,
+            0 => Self::Default,
+            v if v <= 8 => Self::NonRef,
+            v if v <= 16 => Self::Bidirectional,
+            v if v <= 24 => Self::NonIntra,
+            v if v <= 32 => Self::NonKey,
+            _ => Self::All,
+        }
+    }
+
+    /// Get the raw representation.
+    fn into_raw(self) -> c_int {
+        match self {
+            Self::None => -16,
+            Self::Default => 0,
+            Self::NonRef => 8,
+            Self::Bidirectional => 16,
+            Self::NonIntra => 24,
+            Self::NonKey => 32,
+            Self::All => 48,
+        }
+    }
+}
+
+/// Stream disposition flags.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Default)]
+pub struct Disposition(u32);
+
+impl Disposition {
+    /// The stream should be chosen by default among other streams of the
+    /// same type.
+    pub const DEFAULT: Self = Self(0x0001);
+    /// The stream is not in the original language.
+    pub const DUB: Self = Self(0x0002);
+    /// The stream is in the original language.
+    pub const ORIGINAL: Self = Self(0x0004);
+    /// The stream is a commentary track.
+    pub const COMMENT: Self = Self(0x0008);
+    /// The stream contains song lyrics.
+    pub const LYRICS: Self = Self(0x0010);
+    /// The stream contains karaoke audio.
+    pub const KARAOKE: Self = Self(0x0020);
+    /// Subtitles of the stream should be displayed even if the user did not
+    /// explicitly ask for them.
+    pub const FORCED: Self = Self(0x0040);
+    /// The stream is intended for hearing impaired audiences.
+    pub const HEARING_IMPAIRED: Self = Self(0x0080);
+    /// The stream is intended for visually impaired audiences.
+    pub const VISUAL_IMPAIRED: Self = Self(0x0100);
+    /// The audio stream contains music and sound effects without voice.
+    pub const CLEAN_EFFECTS: Self = Self(0x0200);
+    /// The stream is a single attached picture (e.g. a cover art).
+    pub const ATTACHED_PIC: Self = Self(0x0400);
+    /// The stream is sparse and contains thumbnail images.
+    pub const TIMED_THUMBNAILS: Self = Self(0x0800);
+    /// The subtitle stream contains captions.
+    pub const CAPTIONS: Self = Self(0x10000);
+    /// The stream contains a description of the video content.
+    pub const DESCRIPTIONS: Self = Self(0x20000);
+    /// The subtitle stream contains time-aligned metadata.
+    pub const METADATA: Self = Self(0x40000);
+    /// The stream is intended to be mixed with another stream before
+    /// presentation.
+    pub const DEPENDENT: Self = Self(0x80000);
+    /// The video stream contains still images.
+    pub const STILL_IMAGE: Self = Self(0x100000);
+
+    /// Get an empty disposition.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Create disposition flags from their raw representation.
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    /// Get the raw representation.
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Check if there are no flags set.
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Check if all given flags are set.
+    pub const fn contains(self, other: Self) -> bool {
+        (self.0 & other.0) == other.0
+    }
+
+    /// Set given flags.
+    pub const fn with(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    /// Clear given flags.
+    pub const fn without(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+}
+
+impl BitOr for Disposition {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        self.with(rhs)
+    }
+}
+
+impl BitOrAssign for Disposition {
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = self.with(rhs);
+    }
 }
 
 /// Stream.
@@ -110,6 +267,10 @@ impl Stream {
         }
     }
 
+    /// Get the real base frame rate of the stream (i.e. the lowest frame
+    /// rate with which all timestamps can be represented accurately) as a
+    /// floating point number. Use `real_frame_rate_rational()` to get the
+    /// exact value.
     pub fn real_frame_rate(&self) -> Option<f64> {
         let fps = unsafe { ffw_stream_get_r_frame_rate(self.ptr) };
 
@@ -120,6 +281,48 @@ impl Stream {
         }
     }
 
+    /// Get the real base frame rate of the stream (i.e. the lowest frame
+    /// rate with which all timestamps can be represented accurately).
+    pub fn real_frame_rate_rational(&self) -> Option<Rational> {
+        let mut num = 0;
+        let mut den = 0;
+
+        unsafe { ffw_stream_get_r_frame_rate_q(self.ptr, &mut num, &mut den) };
+
+        time::positive_rational(num, den)
+    }
+
+    /// Get the average frame rate of the stream.
+    pub fn avg_frame_rate(&self) -> Option<Rational> {
+        let mut num = 0;
+        let mut den = 0;
+
+        unsafe { ffw_stream_get_avg_frame_rate(self.ptr, &mut num, &mut den) };
+
+        time::positive_rational(num, den)
+    }
+
+    /// Get the sample (pixel) aspect ratio. The container-level value is
+    /// preferred, the codec-level value is used if the container does not
+    /// specify any.
+    pub fn sample_aspect_ratio(&self) -> Option<Rational> {
+        let mut num = 0;
+        let mut den = 0;
+
+        unsafe { ffw_stream_get_sample_aspect_ratio(self.ptr, &mut num, &mut den) };
+
+        time::positive_rational(num, den)
+    }
+
+    /// Get the display aspect ratio of a video stream.
+    pub fn display_aspect_ratio(&self) -> Option<Rational> {
+        let params = self.codec_parameters().into_video_codec_parameters()?;
+
+        let sar = self.sample_aspect_ratio().unwrap_or(Rational::new(1, 1));
+
+        time::display_aspect_ratio(params.width(), params.height(), sar)
+    }
+
     /// Get codec parameters.
     pub fn codec_parameters(&self) -> CodecParameters {
         unsafe {
@@ -196,11 +399,47 @@ impl Stream {
         res
     }
 
+    /// Get the start timecode of the stream (the `timecode` metadata entry
+    /// set e.g. by the MOV/MP4 and MXF demuxers).
+    pub fn timecode(&self) -> Option<Timecode> {
+        self.get_metadata("timecode")
+            .and_then(|timecode| timecode.parse().ok())
+    }
+
+    /// Set the start timecode of the stream. Muxers supporting it (e.g.
+    /// MOV/MP4 or MXF) write the timecode into the output.
+    pub fn set_timecode(&mut self, timecode: Timecode) {
+        self.set_metadata("timecode", timecode);
+    }
+
     /// Set stream id.
     pub fn set_stream_id(&mut self, id: i32) {
         unsafe { ffw_stream_set_id(self.ptr, id as c_int) };
     }
 
+    /// Get the stream discard mode.
+    pub fn discard(&self) -> Discard {
+        Discard::from_raw(unsafe { ffw_stream_get_discard(self.ptr) })
+    }
+
+    /// Set the stream discard mode. The demuxer will skip the discarded
+    /// packets at the container level, so setting `Discard::All` for unused
+    /// streams saves the cost of reading and passing around their packets.
+    /// Note that some demuxers may not honor the setting.
+    pub fn set_discard(&mut self, discard: Discard) {
+        unsafe { ffw_stream_set_discard(self.ptr, discard.into_raw()) }
+    }
+
+    /// Get the stream disposition.
+    pub fn disposition(&self) -> Disposition {
+        Disposition::from_bits(unsafe { ffw_stream_get_disposition(self.ptr) as u32 })
+    }
+
+    /// Set the stream disposition.
+    pub fn set_disposition(&mut self, disposition: Disposition) {
+        unsafe { ffw_stream_set_disposition(self.ptr, disposition.bits() as c_int) }
+    }
+
     /// Get stream side data.
     pub fn side_data(&self) -> SideDataIter<'_> {
         let len = unsafe { ffw_stream_get_nb_side_data(self.ptr) };
@@ -214,9 +453,10 @@ impl Stream {
 
     /// Add stream side data.
     pub fn add_side_data(&mut self, data_type: SideDataType, data: &[u8]) -> Result<(), Error> {
-        let ret = unsafe {
-            ffw_stream_add_side_data(self.ptr, data_type.into_raw(), data.as_ptr(), data.len())
-        };
+        let data_type = data_type.try_into_raw()?;
+
+        let ret =
+            unsafe { ffw_stream_add_side_data(self.ptr, data_type, data.as_ptr(), data.len()) };
 
         if ret < 0 {
             return Err(Error::from_raw_error_code(ret));
@@ -224,6 +464,49 @@ impl Stream {
 
         Ok(())
     }
+
+    /// Get the clockwise rotation in degrees (0 - 360) that needs to be
+    /// applied to the video frames in order to display them correctly. The
+    /// rotation is taken from the display matrix side data (written e.g. by
+    /// phones into MP4 files). None is returned if there is no display
+    /// matrix. Flipping is not reflected in the returned value.
+    ///
+    /// The frames can be rotated using `VideoFrame::rotate()`:
+    ///
+    /// ```text
+    /// let degrees = stream.rotation().unwrap_or(0.0).round() as i32;
+    ///
+    /// let frame = frame.rotate(degrees)?;
+    /// ```
+    pub fn rotation(&self) -> Option<f64> {
+        let side_data = self
+            .side_data()
+            .find(|sd| sd.data_type() == SideDataType::DisplayMatrix)?;
+
+        let data = side_data.data();
+
+        let angle = unsafe { ffw_display_matrix_get_rotation(data.as_ptr(), data.len()) };
+
+        if angle.is_nan() {
+            return None;
+        }
+
+        // the display matrix angle is counterclockwise
+        Some((360.0 - angle).rem_euclid(360.0))
+    }
+
+    /// Set the clockwise rotation in degrees that needs to be applied to the
+    /// video frames in order to display them correctly. The method replaces
+    /// the display matrix side data, so the rotation must be set before
+    /// the muxer header is written. It is supported e.g. by the MP4/MOV
+    /// muxers.
+    pub fn set_rotation(&mut self, degrees: f64) -> Result<(), Error> {
+        let mut matrix = [0u8; DISPLAY_MATRIX_SIZE];
+
+        unsafe { ffw_display_matrix_init(matrix.as_mut_ptr(), -degrees) }
+
+        self.add_side_data(SideDataType::DisplayMatrix, &matrix)
+    }
 }
 
 unsafe impl Send for Stream {}
@@ -259,3 +542,86 @@ impl<'a> Iterator for SideDataIter<'a> {
 }
 
 impl ExactSizeIterator for SideDataIter<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use crate::{
+        format::{
+            demuxer::Demuxer,
+            extract::reset_codec_tag,
+            io::IO,
+            muxer::{Muxer, OutputFormat},
+        },
+        test_util::encode_video,
+    };
+
+    /// Check that a given rotation is approximately equal to the expected
+    /// one.
+    fn assert_rotation(rotation: Option<f64>, expected: f64) {
+        let rotation = rotation.unwrap();
+
+        assert!((rotation - expected).abs() < 0.01, "{}", rotation);
+    }
+
+    /// Remux the `encode_video()` output into MP4 with a given rotation and
+    /// get the rotation of the demuxed stream.
+    fn remux_with_rotation(degrees: f64) -> Option<f64> {
+        let data = encode_video(64, 48, 5, 5);
+
+        let mut demuxer = Demuxer::builder()
+            .build(IO::from_read_stream(Cursor::new(data)))
+            .unwrap()
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)
+            .unwrap();
+
+        // there is no display matrix in the input
+        assert!(demuxer.streams()[0].rotation().is_none());
+
+        let mut builder = Muxer::builder();
+
+        builder
+            .add_stream(&reset_codec_tag(demuxer.streams()[0].codec_parameters()))
+            .unwrap();
+
+        let stream = &mut builder.streams_mut()[0];
+
+        stream.set_rotation(degrees).unwrap();
+
+        assert_rotation(stream.rotation(), degrees);
+
+        let io = IO::from_seekable_write_stream(Cursor::new(Vec::new()));
+
+        let mut muxer = builder
+            .build(io, OutputFormat::find_by_name("mp4").unwrap())
+            .unwrap();
+
+        while let Some(packet) = demuxer.take().unwrap() {
+            muxer.push(packet).unwrap();
+        }
+
+        let data = muxer.finish().unwrap().into_inner();
+
+        let demuxer = Demuxer::builder()
+            .build(IO::from_seekable_read_stream(Cursor::new(data)))
+            .unwrap()
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)
+            .unwrap();
+
+        demuxer.streams()[0].rotation()
+    }
+
+    #[test]
+    fn test_rotation() {
+        // clockwise rotations
+        assert_rotation(remux_with_rotation(90.0), 90.0);
+        assert_rotation(remux_with_rotation(180.0), 180.0);
+        assert_rotation(remux_with_rotation(270.0), 270.0);
+
+        // negative angles are normalized
+        assert_rotation(remux_with_rotation(-90.0), 270.0);
+    }
+}
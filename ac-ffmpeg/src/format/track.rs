@@ -0,0 +1,283 @@
+//! Audio track roles and accessibility dispositions.
+
+use crate::{
+    codec::CodecParameters,
+    format::{
+        muxer::{MuxerBuilder, OutputFormat},
+        stream::{Disposition, Stream},
+    },
+    Error,
+};
+
+/// Role of an audio track.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum AudioTrackRole {
+    /// Main program audio.
+    Main,
+    /// Main program audio in the original language.
+    Original,
+    /// Dubbed audio (i.e. not in the original language).
+    Dub,
+    /// Audio with enhanced dialogue intelligibility intended for hearing
+    /// impaired audiences.
+    HearingImpaired,
+    /// Audio description (i.e. the program audio mixed with a narration of
+    /// the visual content) intended for visually impaired audiences.
+    AudioDescription,
+    /// Commentary.
+    Commentary,
+    /// Music and effects without dialogue.
+    CleanEffects,
+}
+
+impl AudioTrackRole {
+    /// Get the generic disposition of the role.
+    pub fn disposition(self) -> Disposition {
+        match self {
+            Self::Main => Disposition::empty(),
+            Self::Original => Disposition::ORIGINAL,
+            Self::Dub => Disposition::DUB,
+            Self::HearingImpaired => Disposition::HEARING_IMPAIRED,
+            Self::AudioDescription => Disposition::VISUAL_IMPAIRED | Disposition::DESCRIPTIONS,
+            Self::Commentary => Disposition::COMMENT,
+            Self::CleanEffects => Disposition::CLEAN_EFFECTS,
+        }
+    }
+
+    /// Check if the role describes a primary program audio, i.e. a track
+    /// that players can select by default.
+    pub fn is_primary(self) -> bool {
+        matches!(self, Self::Main | Self::Original | Self::Dub)
+    }
+
+    /// Get the default track title for the role (if any).
+    pub fn title(self) -> Option<&'static str> {
+        match self {
+            Self::HearingImpaired => Some("Hearing Impaired"),
+            Self::AudioDescription => Some("Audio Description"),
+            Self::Commentary => Some("Commentary"),
+            Self::CleanEffects => Some("Music and Effects"),
+            _ => None,
+        }
+    }
+
+    /// Get the position of the role in the track order.
+    fn priority(self) -> u8 {
+        match self {
+            Self::Main => 0,
+            Self::Original => 1,
+            Self::Dub => 2,
+            Self::HearingImpaired => 3,
+            Self::AudioDescription => 4,
+            Self::Commentary => 5,
+            Self::CleanEffects => 6,
+        }
+    }
+}
+
+/// Description of an audio track.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AudioTrack {
+    role: AudioTrackRole,
+    language: Option<String>,
+    title: Option<String>,
+    default: bool,
+}
+
+impl AudioTrack {
+    /// Create a new audio track with a given role.
+    pub fn new(role: AudioTrackRole) -> Self {
+        Self {
+            role,
+            language: None,
+            title: None,
+            default: false,
+        }
+    }
+
+    /// Set the track language. It should be an ISO 639-2 language code
+    /// (e.g. "eng").
+    pub fn with_language<T>(mut self, language: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.language = Some(language.into());
+        self
+    }
+
+    /// Set the track title. The default title of the role is used if not
+    /// set.
+    pub fn with_title<T>(mut self, title: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Mark the track as the default one. The flag is ignored for roles that
+    /// are not primary (see `AudioTrackRole::is_primary()`), so that players
+    /// never select e.g. an audio description track automatically.
+    pub fn with_default(mut self, default: bool) -> Self {
+        self.default = default;
+        self
+    }
+
+    /// Get the track role.
+    pub fn role(&self) -> AudioTrackRole {
+        self.role
+    }
+
+    /// Get the track language.
+    pub fn language(&self) -> Option<&str> {
+        self.language.as_deref()
+    }
+
+    /// Get the track title.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref().or_else(|| self.role.title())
+    }
+
+    /// Check if the track should be selected by default.
+    pub fn is_default(&self) -> bool {
+        self.default && self.role.is_primary()
+    }
+
+    /// Get the disposition of the track for a given output format.
+    pub fn disposition(&self, format: &OutputFormat) -> Disposition {
+        self.disposition_for(format.name())
+    }
+
+    /// Set the disposition and metadata of a given stream according to the
+    /// track description.
+    pub fn apply(&self, stream: &mut Stream, format: &OutputFormat) {
+        stream.set_disposition(self.disposition(format));
+
+        if let Some(language) = self.language() {
+            stream.set_metadata("language", language);
+        }
+
+        if let Some(title) = self.title() {
+            stream.set_metadata("title", title);
+        }
+    }
+
+    /// Get the disposition of the track for an output format with a given
+    /// name.
+    fn disposition_for(&self, format: &str) -> Disposition {
+        let mut res = self.role.disposition();
+
+        // Matroska maps the descriptions flag to FlagTextDescriptions which
+        // is meant for subtitles, the visual impaired flag is the one
+        // identifying audio description tracks
+        if format == "matroska" || format == "webm" {
+            res = res.without(Disposition::DESCRIPTIONS);
+        }
+
+        if self.is_default() {
+            res |= Disposition::DEFAULT;
+        }
+
+        res
+    }
+}
+
+/// Get the recommended order of given audio tracks. The method returns
+/// indices of the tracks. Default tracks go first, followed by the main
+/// program audio, dubs, accessibility tracks, commentaries and music and
+/// effects tracks. The relative order of tracks with the same role is
+/// preserved.
+pub fn order_audio_tracks(tracks: &[AudioTrack]) -> Vec<usize> {
+    let mut res = (0..tracks.len()).collect::<Vec<_>>();
+
+    res.sort_by_key(|&index| {
+        let track = &tracks[index];
+
+        (!track.is_default(), track.role.priority())
+    });
+
+    res
+}
+
+/// Add given audio tracks to a muxer in the recommended order (see
+/// `order_audio_tracks()`) and set their dispositions and metadata. If
+/// there is no default track, the first primary track is marked as the
+/// default one. The method returns stream index of each track.
+pub fn add_audio_tracks(
+    builder: &mut MuxerBuilder,
+    tracks: &[(&CodecParameters, &AudioTrack)],
+    format: &OutputFormat,
+) -> Result<Vec<usize>, Error> {
+    let descriptions = tracks
+        .iter()
+        .map(|(_, track)| (*track).clone())
+        .collect::<Vec<_>>();
+
+    let mut has_default = descriptions.iter().any(|track| track.is_default());
+
+    let mut res = vec![0; tracks.len()];
+
+    for index in order_audio_tracks(&descriptions) {
+        let (params, track) = tracks[index];
+
+        let mut track = track.clone();
+
+        if !has_default && track.role.is_primary() {
+            track.default = true;
+            has_default = true;
+        }
+
+        let stream_index = builder.add_stream(params)?;
+
+        track.apply(&mut builder.streams_mut()[stream_index], format);
+
+        res[index] = stream_index;
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{order_audio_tracks, AudioTrack, AudioTrackRole};
+
+    use crate::format::stream::Disposition;
+
+    #[test]
+    fn test_order() {
+        let tracks = [
+            AudioTrack::new(AudioTrackRole::AudioDescription).with_default(true),
+            AudioTrack::new(AudioTrackRole::Commentary),
+            AudioTrack::new(AudioTrackRole::Dub).with_language("ger"),
+            AudioTrack::new(AudioTrackRole::Original)
+                .with_language("eng")
+                .with_default(true),
+            AudioTrack::new(AudioTrackRole::Dub).with_language("fre"),
+        ];
+
+        assert_eq!(order_audio_tracks(&tracks), vec![3, 2, 4, 0, 1]);
+    }
+
+    #[test]
+    fn test_disposition() {
+        let track = AudioTrack::new(AudioTrackRole::AudioDescription).with_default(true);
+
+        assert!(!track.is_default());
+        assert_eq!(track.title(), Some("Audio Description"));
+        assert_eq!(
+            track.disposition_for("mp4"),
+            Disposition::VISUAL_IMPAIRED | Disposition::DESCRIPTIONS
+        );
+        assert_eq!(
+            track.disposition_for("matroska"),
+            Disposition::VISUAL_IMPAIRED
+        );
+
+        let track = AudioTrack::new(AudioTrackRole::Original).with_default(true);
+
+        assert_eq!(
+            track.disposition_for("mpegts"),
+            Disposition::ORIGINAL | Disposition::DEFAULT
+        );
+    }
+}
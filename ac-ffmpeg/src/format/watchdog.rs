@@ -0,0 +1,521 @@
+//! Health watchdog for live inputs.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::packet::Packet;
+
+/// Health event reported by the watchdog.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum HealthEvent {
+    /// No packets have been received for a given amount of time.
+    Stalled(Duration),
+    /// Packets are being received again after a stall of a given length.
+    Resumed(Duration),
+    /// A given number of decoder errors has been reported within the error
+    /// window.
+    ErrorStorm(usize),
+    /// The bit rate dropped significantly.
+    BitRateCollapse {
+        /// Bit rate measured in the last window (bits per second).
+        bit_rate: u64,
+        /// Long-term average bit rate (bits per second).
+        average: u64,
+    },
+    /// The bit rate is back to normal (bits per second).
+    BitRateRecovered(u64),
+}
+
+impl HealthEvent {
+    /// Check if the event indicates a failure of the input.
+    pub fn is_failure(&self) -> bool {
+        matches!(
+            self,
+            HealthEvent::Stalled(_)
+                | HealthEvent::ErrorStorm(_)
+                | HealthEvent::BitRateCollapse { .. }
+        )
+    }
+}
+
+/// Action requested by the event handler.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum WatchdogAction {
+    /// Keep the input running.
+    Continue,
+    /// Restart the input.
+    Restart,
+}
+
+/// Health event handler.
+type EventHandler = Box<dyn FnMut(&HealthEvent) -> WatchdogAction + Send>;
+
+/// Builder for the input watchdog.
+pub struct WatchdogBuilder {
+    stall_timeout: Duration,
+    error_threshold: usize,
+    error_window: Duration,
+    bit_rate_window: Duration,
+    bit_rate_collapse_ratio: f64,
+    min_bit_rate: Option<u64>,
+    handler: Option<EventHandler>,
+}
+
+impl WatchdogBuilder {
+    /// Create a new builder.
+    fn new() -> Self {
+        Self {
+            stall_timeout: Duration::from_secs(5),
+            error_threshold: 25,
+            error_window: Duration::from_secs(10),
+            bit_rate_window: Duration::from_secs(5),
+            bit_rate_collapse_ratio: 0.1,
+            min_bit_rate: None,
+            handler: None,
+        }
+    }
+
+    /// Report a stall if there are no packets for a given amount of time.
+    /// The default is 5 seconds.
+    pub fn stall_timeout(mut self, timeout: Duration) -> Self {
+        self.stall_timeout = timeout;
+        self
+    }
+
+    /// Report an error storm if there are at least a given number of decoder
+    /// errors within a given time window. The default is 25 errors within 10
+    /// seconds.
+    pub fn error_threshold(mut self, errors: usize, window: Duration) -> Self {
+        self.error_threshold = errors.max(1);
+        self.error_window = window;
+        self
+    }
+
+    /// Set the window used for bit rate measurements. The default is 5
+    /// seconds.
+    pub fn bit_rate_window(mut self, window: Duration) -> Self {
+        self.bit_rate_window = window;
+        self
+    }
+
+    /// Report a bit rate collapse if the bit rate measured in the last window
+    /// drops below a given fraction of the long-term average bit rate. The
+    /// default is 0.1.
+    pub fn bit_rate_collapse_ratio(mut self, ratio: f64) -> Self {
+        self.bit_rate_collapse_ratio = ratio;
+        self
+    }
+
+    /// Report a bit rate collapse also if the bit rate measured in the last
+    /// window drops below a given absolute value (bits per second). There is
+    /// no absolute limit by default.
+    pub fn min_bit_rate(mut self, bit_rate: Option<u64>) -> Self {
+        self.min_bit_rate = bit_rate;
+        self
+    }
+
+    /// Set a handler that will be called for every health event. The handler
+    /// decides whether the input should be restarted. By default, no restart
+    /// is ever requested.
+    pub fn event_handler<F>(mut self, handler: F) -> Self
+    where
+        F: FnMut(&HealthEvent) -> WatchdogAction + Send + 'static,
+    {
+        self.handler = Some(Box::new(handler));
+        self
+    }
+
+    /// Build the watchdog.
+    pub fn build(self) -> Watchdog {
+        Watchdog {
+            stall_timeout: self.stall_timeout,
+            error_threshold: self.error_threshold,
+            error_window: self.error_window,
+            bit_rate_window: self.bit_rate_window,
+            bit_rate_collapse_ratio: self.bit_rate_collapse_ratio,
+            min_bit_rate: self.min_bit_rate,
+            handler: self.handler,
+            state: State::new(),
+            events: VecDeque::new(),
+            restart: false,
+            restarts: 0,
+        }
+    }
+}
+
+/// Internal state of the watchdog.
+struct State {
+    last_packet: Option<Instant>,
+    stalled: bool,
+    errors: VecDeque<Instant>,
+    error_storm: bool,
+    window_start: Option<Instant>,
+    window_bytes: u64,
+    average_bit_rate: Option<f64>,
+    bit_rate_collapse: bool,
+}
+
+impl State {
+    /// Create a new state.
+    fn new() -> Self {
+        Self {
+            last_packet: None,
+            stalled: false,
+            errors: VecDeque::new(),
+            error_storm: false,
+            window_start: None,
+            window_bytes: 0,
+            average_bit_rate: None,
+            bit_rate_collapse: false,
+        }
+    }
+}
+
+/// Health watchdog for live inputs (e.g. RTSP cameras or SRT feeds). It
+/// detects stalls (no packets for a given amount of time), decoder error
+/// storms and bit rate collapses.
+///
+/// # Usage
+/// 1. Report every packet received from the demuxer and every decoder error.
+/// 2. Call `check()` periodically (e.g. once per second). As the demuxer may
+///    block while the input is stalled, it is a good idea to call it from a
+///    separate thread.
+/// 3. Take the reported health events and/or let the event handler decide
+///    whether to restart the input. If the `check()` method returns
+///    `WatchdogAction::Restart`, re-open the input and call `reset()`.
+pub struct Watchdog {
+    stall_timeout: Duration,
+    error_threshold: usize,
+    error_window: Duration,
+    bit_rate_window: Duration,
+    bit_rate_collapse_ratio: f64,
+    min_bit_rate: Option<u64>,
+    handler: Option<EventHandler>,
+    state: State,
+    events: VecDeque<HealthEvent>,
+    restart: bool,
+    restarts: u64,
+}
+
+impl Watchdog {
+    /// Get a builder for the watchdog.
+    pub fn builder() -> WatchdogBuilder {
+        WatchdogBuilder::new()
+    }
+
+    /// Create a new watchdog with the default settings.
+    pub fn new() -> Self {
+        Self::builder().build()
+    }
+
+    /// Report a given packet.
+    pub fn packet(&mut self, packet: &Packet) {
+        self.packet_at(packet, Instant::now())
+    }
+
+    /// Report a given packet received at a given time.
+    pub fn packet_at(&mut self, packet: &Packet, now: Instant) {
+        if self.state.stalled {
+            let stall = self
+                .state
+                .last_packet
+                .map(|last| now.saturating_duration_since(last))
+                .unwrap_or_default();
+
+            self.state.stalled = false;
+
+            // do not let the stall affect the bit rate measurement
+            self.state.window_start = None;
+            self.state.window_bytes = 0;
+
+            self.emit(HealthEvent::Resumed(stall));
+        }
+
+        self.state.last_packet = Some(now);
+        self.state.window_start.get_or_insert(now);
+        self.state.window_bytes += packet.data().len() as u64;
+    }
+
+    /// Report a decoder error.
+    pub fn decoder_error(&mut self) {
+        self.decoder_error_at(Instant::now())
+    }
+
+    /// Report a decoder error that occurred at a given time.
+    pub fn decoder_error_at(&mut self, now: Instant) {
+        self.state.errors.push_back(now);
+
+        self.expire_errors(now);
+
+        let errors = self.state.errors.len();
+
+        if errors >= self.error_threshold && !self.state.error_storm {
+            self.state.error_storm = true;
+
+            self.emit(HealthEvent::ErrorStorm(errors));
+        }
+    }
+
+    /// Check the input health.
+    pub fn check(&mut self) -> WatchdogAction {
+        self.check_at(Instant::now())
+    }
+
+    /// Check the input health at a given time.
+    pub fn check_at(&mut self, now: Instant) -> WatchdogAction {
+        self.expire_errors(now);
+
+        if self.state.errors.len() < self.error_threshold {
+            self.state.error_storm = false;
+        }
+
+        // the watchdog is started by the first check if there are no packets
+        let last_packet = *self.state.last_packet.get_or_insert(now);
+
+        let idle = now.saturating_duration_since(last_packet);
+
+        if idle >= self.stall_timeout && !self.state.stalled {
+            self.state.stalled = true;
+
+            self.emit(HealthEvent::Stalled(idle));
+        }
+
+        if !self.state.stalled {
+            self.check_bit_rate(now);
+        }
+
+        if self.restart {
+            WatchdogAction::Restart
+        } else {
+            WatchdogAction::Continue
+        }
+    }
+
+    /// Take the next health event.
+    pub fn take_event(&mut self) -> Option<HealthEvent> {
+        self.events.pop_front()
+    }
+
+    /// Check if the input is healthy, i.e. it is not stalled, there is no
+    /// ongoing error storm and there is no bit rate collapse.
+    pub fn is_healthy(&self) -> bool {
+        !self.state.stalled && !self.state.error_storm && !self.state.bit_rate_collapse
+    }
+
+    /// Check if a restart has been requested by the event handler.
+    pub fn restart_requested(&self) -> bool {
+        self.restart
+    }
+
+    /// Reset the watchdog after the input has been restarted. Pending events
+    /// are kept.
+    pub fn reset(&mut self) {
+        if self.restart {
+            self.restarts += 1;
+        }
+
+        self.state = State::new();
+        self.restart = false;
+    }
+
+    /// Get the number of restarts (i.e. resets after a restart request).
+    pub fn restarts(&self) -> u64 {
+        self.restarts
+    }
+
+    /// Evaluate the current bit rate window.
+    fn check_bit_rate(&mut self, now: Instant) {
+        let start = match self.state.window_start {
+            Some(start) => start,
+            None => return,
+        };
+
+        let elapsed = now.saturating_duration_since(start);
+
+        if elapsed < self.bit_rate_window || elapsed.as_secs_f64() <= 0.0 {
+            return;
+        }
+
+        let bit_rate = (self.state.window_bytes * 8) as f64 / elapsed.as_secs_f64();
+
+        self.state.window_start = Some(now);
+        self.state.window_bytes = 0;
+
+        let collapse = self
+            .state
+            .average_bit_rate
+            .map(|average| bit_rate < average * self.bit_rate_collapse_ratio)
+            .unwrap_or(false)
+            || self
+                .min_bit_rate
+                .map(|min| bit_rate < min as f64)
+                .unwrap_or(false);
+
+        if collapse {
+            if !self.state.bit_rate_collapse {
+                self.state.bit_rate_collapse = true;
+
+                let average = self.state.average_bit_rate.unwrap_or_default();
+
+                self.emit(HealthEvent::BitRateCollapse {
+                    bit_rate: bit_rate as u64,
+                    average: average as u64,
+                });
+            }
+
+            return;
+        }
+
+        if self.state.bit_rate_collapse {
+            self.state.bit_rate_collapse = false;
+
+            self.emit(HealthEvent::BitRateRecovered(bit_rate as u64));
+        }
+
+        let average = self
+            .state
+            .average_bit_rate
+            .map(|average| 0.8 * average + 0.2 * bit_rate)
+            .unwrap_or(bit_rate);
+
+        self.state.average_bit_rate = Some(average);
+    }
+
+    /// Drop errors that are out of the error window.
+    fn expire_errors(&mut self, now: Instant) {
+        while let Some(t) = self.state.errors.front() {
+            if now.saturating_duration_since(*t) > self.error_window {
+                self.state.errors.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    /// Report a given event.
+    fn emit(&mut self, event: HealthEvent) {
+        if let Some(handler) = self.handler.as_mut() {
+            if handler(&event) == WatchdogAction::Restart {
+                self.restart = true;
+            }
+        }
+
+        self.events.push_back(event);
+    }
+}
+
+impl Default for Watchdog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{HealthEvent, Watchdog, WatchdogAction};
+
+    use crate::{test_util::packet, time::TimeBase};
+
+    #[test]
+    fn test_stall() {
+        let mut watchdog = Watchdog::builder()
+            .stall_timeout(Duration::from_secs(2))
+            .event_handler(|event| {
+                if event.is_failure() {
+                    WatchdogAction::Restart
+                } else {
+                    WatchdogAction::Continue
+                }
+            })
+            .build();
+
+        let start = Instant::now();
+
+        watchdog.packet_at(&packet(100, TimeBase::MICROSECONDS, 0).freeze(), start);
+
+        assert_eq!(
+            watchdog.check_at(start + Duration::from_secs(1)),
+            WatchdogAction::Continue
+        );
+        assert_eq!(
+            watchdog.check_at(start + Duration::from_secs(3)),
+            WatchdogAction::Restart
+        );
+        assert_eq!(
+            watchdog.take_event(),
+            Some(HealthEvent::Stalled(Duration::from_secs(3)))
+        );
+        assert!(!watchdog.is_healthy());
+
+        watchdog.packet_at(
+            &packet(100, TimeBase::MICROSECONDS, 0).freeze(),
+            start + Duration::from_secs(4),
+        );
+
+        assert_eq!(
+            watchdog.take_event(),
+            Some(HealthEvent::Resumed(Duration::from_secs(4)))
+        );
+
+        watchdog.reset();
+
+        assert_eq!(watchdog.restarts(), 1);
+        assert!(!watchdog.restart_requested());
+    }
+
+    #[test]
+    fn test_error_storm() {
+        let mut watchdog = Watchdog::builder()
+            .error_threshold(3, Duration::from_secs(1))
+            .build();
+
+        let start = Instant::now();
+
+        watchdog.decoder_error_at(start);
+        watchdog.decoder_error_at(start + Duration::from_millis(1500));
+        watchdog.decoder_error_at(start + Duration::from_millis(1600));
+
+        assert_eq!(watchdog.take_event(), None);
+
+        watchdog.decoder_error_at(start + Duration::from_millis(1700));
+
+        assert_eq!(watchdog.take_event(), Some(HealthEvent::ErrorStorm(3)));
+    }
+
+    #[test]
+    fn test_bit_rate_collapse() {
+        let mut watchdog = Watchdog::builder()
+            .bit_rate_window(Duration::from_secs(1))
+            .stall_timeout(Duration::from_secs(60))
+            .build();
+
+        let start = Instant::now();
+
+        // 8 kbps for 3 seconds
+        for i in 0..30 {
+            let t = start + Duration::from_millis(i * 100);
+
+            watchdog.packet_at(&packet(100, TimeBase::MICROSECONDS, 0).freeze(), t);
+            watchdog.check_at(t);
+        }
+
+        assert_eq!(watchdog.take_event(), None);
+
+        // 80 bps for 2 seconds
+        for i in 30..50 {
+            let t = start + Duration::from_millis(i * 100);
+
+            watchdog.packet_at(&packet(1, TimeBase::MICROSECONDS, 0).freeze(), t);
+            watchdog.check_at(t);
+        }
+
+        assert!(matches!(
+            watchdog.take_event(),
+            Some(HealthEvent::BitRateCollapse { .. })
+        ));
+    }
+}
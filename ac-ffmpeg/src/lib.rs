@@ -1,10 +1,19 @@
 //! Safe Rust interface for FFmpeg libraries. See the `examples` folder for
 //! code examples.
 
+#[macro_use]
+mod trace;
+
 pub mod codec;
 pub mod format;
+pub mod metrics;
 pub mod packet;
+pub mod pipeline;
 pub mod time;
+pub mod timecode;
+
+#[cfg(test)]
+mod test_util;
 
 use std::{
     ffi::CStr,
@@ -16,6 +25,8 @@ use std::{
 
 use lazy_static::lazy_static;
 
+use self::format::muxer::PacketError;
+
 lazy_static! {
     /// Log callback.
     static ref LOG_CALLBACK: RwLock<LogCallback> = {
@@ -90,11 +101,131 @@ where
     }
 }
 
+/// Create an FFmpeg error code from a given tag (see `FFERRTAG` in
+/// `libavutil/error.h`).
+const fn fferrtag(a: u8, b: u8, c: u8, d: u8) -> c_int {
+    -((a as u32 | (b as u32) << 8 | (c as u32) << 16 | (d as u32) << 24) as c_int)
+}
+
+const AVERROR_BSF_NOT_FOUND: c_int = fferrtag(0xf8, b'B', b'S', b'F');
+const AVERROR_BUG: c_int = fferrtag(b'B', b'U', b'G', b'!');
+const AVERROR_BUG2: c_int = fferrtag(b'B', b'U', b'G', b' ');
+const AVERROR_DECODER_NOT_FOUND: c_int = fferrtag(0xf8, b'D', b'E', b'C');
+const AVERROR_DEMUXER_NOT_FOUND: c_int = fferrtag(0xf8, b'D', b'E', b'M');
+const AVERROR_ENCODER_NOT_FOUND: c_int = fferrtag(0xf8, b'E', b'N', b'C');
+const AVERROR_EOF: c_int = fferrtag(b'E', b'O', b'F', b' ');
+const AVERROR_EXIT: c_int = fferrtag(b'E', b'X', b'I', b'T');
+const AVERROR_FILTER_NOT_FOUND: c_int = fferrtag(0xf8, b'F', b'I', b'L');
+const AVERROR_INVALIDDATA: c_int = fferrtag(b'I', b'N', b'D', b'A');
+const AVERROR_MUXER_NOT_FOUND: c_int = fferrtag(0xf8, b'M', b'U', b'X');
+const AVERROR_OPTION_NOT_FOUND: c_int = fferrtag(0xf8, b'O', b'P', b'T');
+const AVERROR_PATCHWELCOME: c_int = fferrtag(b'P', b'A', b'W', b'E');
+const AVERROR_PROTOCOL_NOT_FOUND: c_int = fferrtag(0xf8, b'P', b'R', b'O');
+const AVERROR_STREAM_NOT_FOUND: c_int = fferrtag(0xf8, b'S', b'T', b'R');
+
+/// Error kind. It allows matching errors without relying on error messages
+/// that may differ across FFmpeg versions.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// Resource temporarily unavailable, another operation needs to be done
+    /// first (EAGAIN).
+    Again,
+    /// End of file or stream.
+    Eof,
+    /// Invalid data found when processing input.
+    InvalidData,
+    /// Invalid argument.
+    InvalidArgument,
+    /// Not enough memory.
+    OutOfMemory,
+    /// Operating system or IO error. Use `Error::to_io_error()` to get the
+    /// corresponding standard IO error.
+    Io,
+    /// Decoder not found.
+    DecoderNotFound,
+    /// Encoder not found.
+    EncoderNotFound,
+    /// Demuxer not found.
+    DemuxerNotFound,
+    /// Muxer not found.
+    MuxerNotFound,
+    /// Protocol not found.
+    ProtocolNotFound,
+    /// Stream not found.
+    StreamNotFound,
+    /// Filter not found.
+    FilterNotFound,
+    /// Bitstream filter not found.
+    BitstreamFilterNotFound,
+    /// Option not found.
+    OptionNotFound,
+    /// Invalid packet passed to a muxer. Use `Error::packet_error()` to get
+    /// the details.
+    InvalidPacket,
+    /// Feature not implemented in FFmpeg.
+    NotImplemented,
+    /// Internal FFmpeg bug.
+    Bug,
+    /// Immediate exit requested.
+    Exit,
+    /// Unknown FFmpeg error.
+    Unknown,
+    /// Error that does not originate in FFmpeg.
+    Other,
+}
+
+impl ErrorKind {
+    /// Get error kind corresponding to a given FFmpeg error code.
+    fn from_raw_error_code(code: c_int) -> Self {
+        match code {
+            AVERROR_BSF_NOT_FOUND => Self::BitstreamFilterNotFound,
+            AVERROR_BUG | AVERROR_BUG2 => Self::Bug,
+            AVERROR_DECODER_NOT_FOUND => Self::DecoderNotFound,
+            AVERROR_DEMUXER_NOT_FOUND => Self::DemuxerNotFound,
+            AVERROR_ENCODER_NOT_FOUND => Self::EncoderNotFound,
+            AVERROR_EOF => Self::Eof,
+            AVERROR_EXIT => Self::Exit,
+            AVERROR_FILTER_NOT_FOUND => Self::FilterNotFound,
+            AVERROR_INVALIDDATA => Self::InvalidData,
+            AVERROR_MUXER_NOT_FOUND => Self::MuxerNotFound,
+            AVERROR_OPTION_NOT_FOUND => Self::OptionNotFound,
+            AVERROR_PATCHWELCOME => Self::NotImplemented,
+            AVERROR_PROTOCOL_NOT_FOUND => Self::ProtocolNotFound,
+            AVERROR_STREAM_NOT_FOUND => Self::StreamNotFound,
+            _ => Self::from_posix_error_code(code),
+        }
+    }
+
+    /// Get error kind corresponding to a given FFmpeg error code created
+    /// from a POSIX error code.
+    fn from_posix_error_code(code: c_int) -> Self {
+        unsafe {
+            if code == ffw_error_again() || code == ffw_error_would_block() {
+                return Self::Again;
+            } else if code == ffw_error_unknown() {
+                return Self::Unknown;
+            }
+        }
+
+        let posix = unsafe { ffw_error_to_posix(code) };
+
+        match io::Error::from_raw_os_error(posix as _).kind() {
+            io::ErrorKind::InvalidInput => Self::InvalidArgument,
+            io::ErrorKind::OutOfMemory => Self::OutOfMemory,
+            io::ErrorKind::Unsupported => Self::NotImplemented,
+            io::ErrorKind::Other => Self::Unknown,
+            _ => Self::Io,
+        }
+    }
+}
+
 /// Error variants.
 #[derive(Debug, Clone)]
 enum ErrorVariant {
     FFmpeg(c_int),
-    Other(String),
+    Packet(PacketError),
+    Other(ErrorKind, String),
 }
 
 /// An error.
@@ -109,8 +240,34 @@ impl Error {
     where
         T: ToString,
     {
-        Self {
-            variant: ErrorVariant::Other(msg.to_string()),
+        Self::with_kind(ErrorKind::Other, msg)
+    }
+
+    /// Get the error kind.
+    pub fn kind(&self) -> ErrorKind {
+        match &self.variant {
+            ErrorVariant::FFmpeg(code) => ErrorKind::from_raw_error_code(*code),
+            ErrorVariant::Packet(_) => ErrorKind::InvalidPacket,
+            ErrorVariant::Other(kind, _) => *kind,
+        }
+    }
+
+    /// Get the raw FFmpeg error code (if the error originates in FFmpeg).
+    pub fn raw_error_code(&self) -> Option<i32> {
+        if let ErrorVariant::FFmpeg(code) = &self.variant {
+            Some(*code as _)
+        } else {
+            None
+        }
+    }
+
+    /// Get details of an invalid packet error (if this is an invalid packet
+    /// error).
+    pub fn packet_error(&self) -> Option<&PacketError> {
+        if let ErrorVariant::Packet(err) = &self.variant {
+            Some(err)
+        } else {
+            None
         }
     }
 
@@ -126,6 +283,16 @@ impl Error {
         }
     }
 
+    /// Create a new error of a given kind.
+    fn with_kind<T>(kind: ErrorKind, msg: T) -> Self
+    where
+        T: ToString,
+    {
+        Self {
+            variant: ErrorVariant::Other(kind, msg.to_string()),
+        }
+    }
+
     /// Create a new FFmpeg error from a given FFmpeg error code.
     fn from_raw_error_code(code: c_int) -> Self {
         Self {
@@ -153,9 +320,51 @@ impl Display for Error {
 
                 write!(f, "{}", msg)
             }
-            ErrorVariant::Other(msg) => write!(f, "{}", msg),
+            ErrorVariant::Packet(err) => write!(f, "{}", err),
+            ErrorVariant::Other(_, msg) => write!(f, "{}", msg),
         }
     }
 }
 
 impl std::error::Error for Error {}
+
+impl From<PacketError> for Error {
+    fn from(err: PacketError) -> Self {
+        Self {
+            variant: ErrorVariant::Packet(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, ErrorKind, PacketError, AVERROR_EOF, AVERROR_INVALIDDATA};
+
+    #[test]
+    fn test_error_tags() {
+        assert_eq!(AVERROR_EOF, -0x2046_4f45);
+        assert_eq!(AVERROR_INVALIDDATA, -0x4144_4e49);
+    }
+
+    #[test]
+    fn test_error_kind() {
+        let err = Error::new("foo");
+
+        assert_eq!(err.kind(), ErrorKind::Other);
+        assert_eq!(err.raw_error_code(), None);
+        assert_eq!(err.to_string(), "foo");
+
+        let err = Error::from_raw_error_code(AVERROR_EOF);
+
+        assert_eq!(err.kind(), ErrorKind::Eof);
+        assert_eq!(err.raw_error_code(), Some(AVERROR_EOF));
+
+        let err = Error::from(PacketError::InvalidStreamIndex {
+            stream_index: 2,
+            streams: 1,
+        });
+
+        assert_eq!(err.kind(), ErrorKind::InvalidPacket);
+        assert_eq!(err.packet_error().map(|err| err.stream_index()), Some(2));
+    }
+}
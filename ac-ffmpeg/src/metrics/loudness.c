@@ -0,0 +1,16 @@
+#include <libavutil/channel_layout.h>
+
+double ffw_loudness_channel_weight(uint64_t channel) {
+    switch (channel) {
+        case AV_CH_LOW_FREQUENCY:
+        case AV_CH_LOW_FREQUENCY_2:
+            return 0.0;
+        case AV_CH_BACK_LEFT:
+        case AV_CH_BACK_RIGHT:
+        case AV_CH_SIDE_LEFT:
+        case AV_CH_SIDE_RIGHT:
+            return 1.41;
+        default:
+            return 1.0;
+    }
+}
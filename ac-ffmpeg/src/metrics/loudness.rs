@@ -0,0 +1,580 @@
+//! Loudness measurement according to EBU R128 (ITU-R BS.1770-4).
+
+use std::{collections::VecDeque, f64::consts::PI, os::raw::c_double};
+
+use crate::{
+    codec::audio::{AudioFrame, ChannelLayoutRef},
+    Error,
+};
+
+/// Absolute gating threshold in LUFS.
+const ABSOLUTE_GATE: f64 = -70.0;
+
+/// Relative gating threshold of the integrated loudness in LU.
+const INTEGRATED_RELATIVE_GATE: f64 = -10.0;
+
+/// Relative gating threshold of the loudness range in LU.
+const RANGE_RELATIVE_GATE: f64 = -20.0;
+
+/// Number of 100 ms sub-blocks of the momentary loudness window.
+const MOMENTARY_SUB_BLOCKS: usize = 4;
+
+/// Number of 100 ms sub-blocks of the short-term loudness window.
+const SHORT_TERM_SUB_BLOCKS: usize = 30;
+
+/// Number of 100 ms sub-blocks between two short-term blocks used for the
+/// loudness range.
+const SHORT_TERM_HOP: usize = 10;
+
+/// Number of taps of the true peak interpolation filter per phase.
+const TRUE_PEAK_TAPS: usize = 12;
+
+extern "C" {
+    fn ffw_loudness_channel_weight(channel: u64) -> c_double;
+}
+
+/// Convert a given mean square value into loudness.
+fn energy_to_loudness(energy: f64) -> f64 {
+    -0.691 + 10.0 * energy.log10()
+}
+
+/// Convert a given loudness into mean square value.
+fn loudness_to_energy(loudness: f64) -> f64 {
+    10f64.powf((loudness + 0.691) / 10.0)
+}
+
+/// Get the mean of given values.
+fn mean<'a, I>(values: I) -> Option<f64>
+where
+    I: IntoIterator<Item = &'a f64>,
+{
+    let mut sum = 0.0;
+    let mut count = 0;
+
+    for value in values {
+        sum += value;
+        count += 1;
+    }
+
+    if count > 0 {
+        Some(sum / count as f64)
+    } else {
+        None
+    }
+}
+
+/// Biquad filter (direct form I).
+#[derive(Clone)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    /// Create a new filter with given coefficients (a0 normalized to 1).
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Self {
+            b,
+            a,
+            x: [0.0; 2],
+            y: [0.0; 2],
+        }
+    }
+
+    /// Process a given sample.
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+
+        y
+    }
+}
+
+/// K-weighting filter (the "pre-filter" high shelf followed by the RLB
+/// high-pass filter) for a given sample rate.
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let fs = sample_rate as f64;
+
+    let f0 = 1_681.974_450_955_533;
+    let g = 3.999_843_853_973_347;
+    let q = 0.707_175_236_955_419_6;
+
+    let k = (PI * f0 / fs).tan();
+    let vh = 10f64.powf(g / 20.0);
+    let vb = vh.powf(0.499_666_774_154_541_6);
+
+    let a0 = 1.0 + k / q + k * k;
+
+    let shelf = Biquad::new(
+        [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    let f0 = 38.135_470_876_024_44;
+    let q = 0.500_327_037_323_877_3;
+
+    let k = (PI * f0 / fs).tan();
+
+    let a0 = 1.0 + k / q + k * k;
+
+    let high_pass = Biquad::new(
+        [1.0, -2.0, 1.0],
+        [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    [shelf, high_pass]
+}
+
+/// Oversampling peak meter.
+struct TruePeakMeter {
+    factor: usize,
+    coefficients: Vec<f64>,
+    history: VecDeque<f64>,
+    peak: f64,
+}
+
+impl TruePeakMeter {
+    /// Create a new true peak meter for a given sample rate. The signal is
+    /// oversampled 4x below 96 kHz and 2x below 192 kHz.
+    fn new(sample_rate: u32) -> Self {
+        let factor = if sample_rate < 96_000 {
+            4
+        } else if sample_rate < 192_000 {
+            2
+        } else {
+            1
+        };
+
+        // windowed sinc interpolation filter
+        let len = factor * TRUE_PEAK_TAPS;
+
+        let center = (len - 1) as f64 / 2.0;
+
+        let coefficients = (0..len)
+            .map(|n| {
+                let t = (n as f64 - center) / factor as f64;
+
+                let sinc = if t == 0.0 {
+                    1.0
+                } else {
+                    (PI * t).sin() / (PI * t)
+                };
+
+                let window = 0.5 - 0.5 * (2.0 * PI * (n as f64 + 0.5) / len as f64).cos();
+
+                sinc * window
+            })
+            .collect();
+
+        Self {
+            factor,
+            coefficients,
+            history: VecDeque::from(vec![0.0; TRUE_PEAK_TAPS]),
+            peak: 0.0,
+        }
+    }
+
+    /// Process a given sample.
+    fn process(&mut self, x: f64) {
+        self.history.pop_back();
+        self.history.push_front(x);
+
+        if self.factor == 1 {
+            self.peak = self.peak.max(x.abs());
+
+            return;
+        }
+
+        for phase in 0..self.factor {
+            let mut y = 0.0;
+
+            for (k, sample) in self.history.iter().enumerate() {
+                y += self.coefficients[k * self.factor + phase] * sample;
+            }
+
+            self.peak = self.peak.max(y.abs());
+        }
+    }
+}
+
+/// Per-channel state of the loudness meter.
+struct ChannelState {
+    weight: f64,
+    filter: [Biquad; 2],
+    true_peak: TruePeakMeter,
+    sample_peak: f64,
+}
+
+/// EBU R128 loudness meter. It consumes audio frames and reports the
+/// integrated loudness, the loudness range, the momentary and short-term
+/// loudness and the true peak of the audio.
+///
+/// # Example
+/// ```text
+/// let mut loudness = Loudness::new(decoder.codec_parameters().channel_layout(), 48_000);
+///
+/// while let Some(frame) = decoder.take()? {
+///     loudness.push(&frame)?;
+/// }
+///
+/// println!("integrated loudness: {:?} LUFS", loudness.integrated());
+///
+/// // gain (in dB) normalizing the audio to -16 LUFS with true peaks
+/// // limited to -1 dBTP
+/// let gain = loudness.normalization_gain(-16.0, Some(-1.0));
+/// ```
+pub struct Loudness {
+    sample_rate: u32,
+    channels: Vec<ChannelState>,
+    sub_block_size: usize,
+    sub_block_samples: usize,
+    sub_block_energy: f64,
+    sub_blocks: VecDeque<f64>,
+    sub_block_count: u64,
+    momentary_blocks: Vec<f64>,
+    short_term_blocks: Vec<f64>,
+}
+
+impl Loudness {
+    /// Create a new loudness meter for audio with a given channel layout and
+    /// sample rate. LFE channels are excluded from the measurement and
+    /// surround channels are weighted according to BS.1770.
+    pub fn new(channel_layout: &ChannelLayoutRef, sample_rate: u32) -> Self {
+        let channels = channel_layout.channels() as usize;
+
+        let mask = channel_layout
+            .mask()
+            .filter(|mask| mask.count_ones() as usize == channels);
+
+        let weights = if let Some(mask) = mask {
+            (0..64)
+                .map(|bit| 1u64 << bit)
+                .filter(|channel| mask & channel != 0)
+                .map(|channel| unsafe { ffw_loudness_channel_weight(channel) })
+                .collect()
+        } else {
+            vec![1.0; channels]
+        };
+
+        Self::with_weights(weights, sample_rate)
+    }
+
+    /// Create a new loudness meter with given channel weights.
+    fn with_weights(weights: Vec<f64>, sample_rate: u32) -> Self {
+        let channels = weights
+            .into_iter()
+            .map(|weight| ChannelState {
+                weight,
+                filter: k_weighting(sample_rate),
+                true_peak: TruePeakMeter::new(sample_rate),
+                sample_peak: 0.0,
+            })
+            .collect();
+
+        Self {
+            sample_rate,
+            channels,
+            sub_block_size: ((sample_rate as usize + 5) / 10).max(1),
+            sub_block_samples: 0,
+            sub_block_energy: 0.0,
+            sub_blocks: VecDeque::new(),
+            sub_block_count: 0,
+            momentary_blocks: Vec::new(),
+            short_term_blocks: Vec::new(),
+        }
+    }
+
+    /// Push a given audio frame. The frame must have the same channel count
+    /// and sample rate as given to the constructor. Any sample format is
+    /// accepted.
+    pub fn push(&mut self, frame: &AudioFrame) -> Result<(), Error> {
+        if frame.channel_layout().channels() as usize != self.channels.len() {
+            return Err(Error::new("invalid frame, channel count does not match"));
+        }
+
+        if frame.sample_rate() != self.sample_rate {
+            return Err(Error::new("invalid frame, sample rate does not match"));
+        }
+
+        self.push_interleaved(&frame.to_interleaved_f32());
+
+        Ok(())
+    }
+
+    /// Push given interleaved samples. Incomplete samples at the end are
+    /// ignored.
+    pub fn push_interleaved(&mut self, samples: &[f32]) {
+        let channels = self.channels.len();
+
+        if channels == 0 {
+            return;
+        }
+
+        for sample in samples.chunks_exact(channels) {
+            let mut energy = 0.0;
+
+            for (channel, &x) in self.channels.iter_mut().zip(sample) {
+                let x = x as f64;
+
+                channel.sample_peak = channel.sample_peak.max(x.abs());
+                channel.true_peak.process(x);
+
+                let y = channel.filter[0].process(x);
+                let y = channel.filter[1].process(y);
+
+                energy += channel.weight * y * y;
+            }
+
+            self.sub_block_energy += energy;
+            self.sub_block_samples += 1;
+
+            if self.sub_block_samples >= self.sub_block_size {
+                self.finish_sub_block();
+            }
+        }
+    }
+
+    /// Finish the current 100 ms sub-block.
+    fn finish_sub_block(&mut self) {
+        let energy = self.sub_block_energy / self.sub_block_samples as f64;
+
+        self.sub_block_energy = 0.0;
+        self.sub_block_samples = 0;
+
+        self.sub_blocks.push_back(energy);
+
+        if self.sub_blocks.len() > SHORT_TERM_SUB_BLOCKS {
+            self.sub_blocks.pop_front();
+        }
+
+        self.sub_block_count += 1;
+
+        if let Some(energy) = self.window_energy(MOMENTARY_SUB_BLOCKS) {
+            self.momentary_blocks.push(energy);
+        }
+
+        let offset = self
+            .sub_block_count
+            .saturating_sub(SHORT_TERM_SUB_BLOCKS as u64);
+        let offset = offset % SHORT_TERM_HOP as u64;
+
+        if offset == 0 {
+            if let Some(energy) = self.window_energy(SHORT_TERM_SUB_BLOCKS) {
+                self.short_term_blocks.push(energy);
+            }
+        }
+    }
+
+    /// Get the mean energy of the last given number of sub-blocks.
+    fn window_energy(&self, sub_blocks: usize) -> Option<f64> {
+        if self.sub_blocks.len() < sub_blocks {
+            return None;
+        }
+
+        mean(
+            self.sub_blocks
+                .iter()
+                .skip(self.sub_blocks.len() - sub_blocks),
+        )
+    }
+
+    /// Get the integrated (gated) loudness in LUFS. None is returned if there
+    /// is not enough audio above the absolute gate.
+    pub fn integrated(&self) -> Option<f64> {
+        gated_loudness(&self.momentary_blocks, INTEGRATED_RELATIVE_GATE)
+            .map(|blocks| energy_to_loudness(mean(&blocks).unwrap()))
+    }
+
+    /// Get the loudness range in LU.
+    pub fn loudness_range(&self) -> Option<f64> {
+        let blocks = gated_loudness(&self.short_term_blocks, RANGE_RELATIVE_GATE)?;
+
+        let mut loudness = blocks
+            .into_iter()
+            .map(energy_to_loudness)
+            .collect::<Vec<_>>();
+
+        loudness.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let percentile = |p: f64| loudness[((loudness.len() - 1) as f64 * p).round() as usize];
+
+        Some(percentile(0.95) - percentile(0.1))
+    }
+
+    /// Get the momentary loudness (400 ms window) in LUFS.
+    pub fn momentary(&self) -> Option<f64> {
+        self.window_energy(MOMENTARY_SUB_BLOCKS)
+            .map(energy_to_loudness)
+    }
+
+    /// Get the short-term loudness (3 s window) in LUFS.
+    pub fn short_term(&self) -> Option<f64> {
+        self.window_energy(SHORT_TERM_SUB_BLOCKS)
+            .map(energy_to_loudness)
+    }
+
+    /// Get the maximum true peak across all channels in dBTP.
+    pub fn true_peak(&self) -> Option<f64> {
+        self.channels
+            .iter()
+            .map(|channel| channel.true_peak.peak)
+            .fold(None, |max: Option<f64>, peak| {
+                Some(max.unwrap_or(peak).max(peak))
+            })
+            .map(|peak| 20.0 * peak.log10())
+    }
+
+    /// Get the maximum sample peak across all channels in dBFS.
+    pub fn sample_peak(&self) -> Option<f64> {
+        self.channels
+            .iter()
+            .map(|channel| channel.sample_peak)
+            .fold(None, |max: Option<f64>, peak| {
+                Some(max.unwrap_or(peak).max(peak))
+            })
+            .map(|peak| 20.0 * peak.log10())
+    }
+
+    /// Get the gain (in dB) normalizing the integrated loudness to a given
+    /// target (in LUFS). If the maximum true peak (in dBTP) is given, the
+    /// gain is reduced so that the true peak does not exceed it. Apply the
+    /// gain as `10^(gain / 20)` to the samples (e.g. using the `volume`
+    /// filter).
+    pub fn normalization_gain(&self, target: f64, max_true_peak: Option<f64>) -> Option<f64> {
+        let gain = target - self.integrated()?;
+
+        match (max_true_peak, self.true_peak()) {
+            (Some(max), Some(peak)) if peak.is_finite() => Some(gain.min(max - peak)),
+            _ => Some(gain),
+        }
+    }
+
+    /// Reset the meter.
+    pub fn reset(&mut self) {
+        let weights = self.channels.iter().map(|channel| channel.weight).collect();
+
+        *self = Self::with_weights(weights, self.sample_rate);
+    }
+}
+
+/// Apply the absolute gate and a given relative gate to given block
+/// energies. None is returned if there are no blocks above the absolute
+/// gate.
+fn gated_loudness(blocks: &[f64], relative_gate: f64) -> Option<Vec<f64>> {
+    let absolute = loudness_to_energy(ABSOLUTE_GATE);
+
+    let blocks = blocks
+        .iter()
+        .copied()
+        .filter(|energy| *energy > absolute)
+        .collect::<Vec<_>>();
+
+    let relative = loudness_to_energy(energy_to_loudness(mean(&blocks)?) + relative_gate);
+
+    let res = blocks
+        .into_iter()
+        .filter(|energy| *energy > relative)
+        .collect::<Vec<_>>();
+
+    Some(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::Loudness;
+
+    use crate::codec::audio::{ChannelLayout, StandardChannelLayout};
+
+    /// Generate a stereo sine wave with a given frequency and amplitude in
+    /// dBFS.
+    fn sine(frequency: f64, amplitude: f64, seconds: f64) -> Vec<f32> {
+        let sample_rate = 48_000.0;
+
+        let amplitude = 10f64.powf(amplitude / 20.0);
+
+        let mut res = Vec::new();
+
+        for n in 0..(seconds * sample_rate) as usize {
+            let x = amplitude * (2.0 * PI * frequency * n as f64 / sample_rate).sin();
+
+            res.push(x as f32);
+            res.push(x as f32);
+        }
+
+        res
+    }
+
+    #[test]
+    fn test_integrated_loudness() {
+        let mut loudness = Loudness::with_weights(vec![1.0, 1.0], 48_000);
+
+        loudness.push_interleaved(&sine(1_000.0, -23.0, 20.0));
+
+        let integrated = loudness.integrated().unwrap();
+
+        assert!((integrated + 23.0).abs() < 0.1);
+        assert!(loudness.loudness_range().unwrap() < 0.1);
+        assert!((loudness.normalization_gain(-16.0, None).unwrap() - 7.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_loudness_range() {
+        let mut loudness = Loudness::with_weights(vec![1.0, 1.0], 48_000);
+
+        // EBU Tech 3342 case 1
+        loudness.push_interleaved(&sine(1_000.0, -20.0, 20.0));
+        loudness.push_interleaved(&sine(1_000.0, -30.0, 20.0));
+
+        let range = loudness.loudness_range().unwrap();
+
+        assert!((range - 10.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_true_peak() {
+        let mut loudness = Loudness::with_weights(vec![1.0, 1.0], 48_000);
+
+        // the sample peaks of a quarter-sample-rate sine shifted by 45
+        // degrees are 3 dB below the true peak
+        let samples = (0..48_000)
+            .map(|n| (0.5 * (PI / 2.0 * n as f64 + PI / 4.0).sin()) as f32)
+            .flat_map(|x| vec![x, x])
+            .collect::<Vec<_>>();
+
+        loudness.push_interleaved(&samples);
+
+        let true_peak = loudness.true_peak().unwrap();
+        let sample_peak = loudness.sample_peak().unwrap();
+
+        assert!((true_peak + 6.02).abs() < 0.5);
+        assert!((sample_peak + 9.03).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_channel_weights() {
+        let layout = ChannelLayout::from(StandardChannelLayout::Surround5_1);
+
+        let loudness = Loudness::new(&layout, 48_000);
+
+        let weights = loudness
+            .channels
+            .iter()
+            .map(|channel| channel.weight)
+            .collect::<Vec<_>>();
+
+        // FL, FR, FC, LFE, SL, SR
+        assert_eq!(weights, [1.0, 1.0, 1.0, 0.0, 1.41, 1.41]);
+    }
+}
@@ -0,0 +1,6 @@
+//! Media analysis and measurements.
+
+pub mod loudness;
+pub mod silence;
+
+pub use self::{loudness::Loudness, silence::SilenceDetector};
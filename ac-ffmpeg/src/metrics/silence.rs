@@ -0,0 +1,325 @@
+//! Silence detection.
+
+use std::{collections::VecDeque, time::Duration};
+
+use crate::{
+    codec::audio::AudioFrame,
+    time::{TimeBase, Timestamp},
+    Error,
+};
+
+/// Builder for the silence detector.
+pub struct SilenceDetectorBuilder {
+    noise: f64,
+    min_duration: Duration,
+}
+
+impl SilenceDetectorBuilder {
+    /// Create a new builder.
+    fn new() -> Self {
+        Self {
+            noise: -60.0,
+            min_duration: Duration::from_secs(2),
+        }
+    }
+
+    /// Set the noise floor in dBFS. Samples below the noise floor in all
+    /// channels are considered to be silent. The default is -60 dBFS.
+    pub fn noise(mut self, noise: f64) -> Self {
+        self.noise = noise;
+        self
+    }
+
+    /// Set the minimum duration of reported silence intervals. The default
+    /// is 2 seconds.
+    pub fn min_duration(mut self, duration: Duration) -> Self {
+        self.min_duration = duration;
+        self
+    }
+
+    /// Build the silence detector.
+    pub fn build(self) -> SilenceDetector {
+        SilenceDetector {
+            threshold: 10f64.powf(self.noise / 20.0) as f32,
+            min_duration: self.min_duration,
+            sample_rate: 0,
+            position: None,
+            start: None,
+            reported: false,
+            intervals: VecDeque::new(),
+        }
+    }
+}
+
+/// Interval of silence.
+#[derive(Debug, Copy, Clone)]
+pub struct Silence {
+    start: Timestamp,
+    end: Option<Timestamp>,
+}
+
+impl Silence {
+    /// Get the start of the silence.
+    pub fn start(&self) -> Timestamp {
+        self.start
+    }
+
+    /// Get the end of the silence. None is returned if the silence lasts
+    /// until the end of the audio.
+    pub fn end(&self) -> Option<Timestamp> {
+        self.end
+    }
+
+    /// Get duration of the silence (if known). None is also returned if the
+    /// end precedes the start (e.g. after a timestamp discontinuity).
+    pub fn duration(&self) -> Option<Duration> {
+        let start = self.start.as_nanos()?;
+        let end = self.end?.as_nanos()?;
+
+        let nanos = end.checked_sub(start)?;
+
+        if nanos < 0 {
+            None
+        } else {
+            Some(Duration::from_nanos(nanos as u64))
+        }
+    }
+}
+
+/// Silence detector. It is an equivalent of the `silencedetect` filter. It
+/// consumes decoded audio frames and reports intervals of silence longer
+/// than a given minimum duration. It can be used e.g. for chaptering or
+/// ad-break detection.
+///
+/// # Example
+/// ```text
+/// let mut detector = SilenceDetector::builder()
+///     .noise(-50.0)
+///     .min_duration(Duration::from_millis(500))
+///     .build();
+///
+/// while let Some(frame) = decoder.take()? {
+///     detector.push(&frame)?;
+///
+///     while let Some(silence) = detector.take() {
+///         println!("silence from {:?} to {:?}", silence.start(), silence.end());
+///     }
+/// }
+///
+/// detector.flush();
+/// ```
+///
+/// Silence intervals are reported once they end. The `silent_since()`
+/// method can be used for finding out whether the audio is silent right
+/// now (e.g. for live monitoring).
+pub struct SilenceDetector {
+    threshold: f32,
+    min_duration: Duration,
+    sample_rate: u32,
+    position: Option<i64>,
+    start: Option<i64>,
+    reported: bool,
+    intervals: VecDeque<Silence>,
+}
+
+impl SilenceDetector {
+    /// Get a builder for the silence detector.
+    pub fn builder() -> SilenceDetectorBuilder {
+        SilenceDetectorBuilder::new()
+    }
+
+    /// Create a new silence detector with the default settings.
+    pub fn new() -> Self {
+        Self::builder().build()
+    }
+
+    /// Push a given audio frame. Frames without a timestamp are expected to
+    /// follow the previous frame. Any sample format is accepted.
+    pub fn push(&mut self, frame: &AudioFrame) -> Result<(), Error> {
+        let sample_rate = frame.sample_rate();
+
+        if sample_rate == 0 {
+            return Err(Error::new("invalid sample rate"));
+        } else if self.sample_rate != 0 && self.sample_rate != sample_rate {
+            return Err(Error::new("invalid frame, sample rate does not match"));
+        }
+
+        self.sample_rate = sample_rate;
+
+        let time_base = TimeBase::new(1, sample_rate);
+
+        let pts = frame.pts();
+
+        if !pts.is_null() {
+            self.position = Some(pts.with_time_base(time_base).timestamp());
+        }
+
+        let channels = frame.channel_layout().channels() as usize;
+
+        self.push_interleaved(&frame.to_interleaved_f32(), channels);
+
+        Ok(())
+    }
+
+    /// Process given interleaved samples following the current position.
+    fn push_interleaved(&mut self, samples: &[f32], channels: usize) {
+        let mut position = self.position.unwrap_or(0);
+
+        for sample in samples.chunks_exact(channels.max(1)) {
+            let silent = sample.iter().all(|x| x.abs() < self.threshold);
+
+            if silent {
+                let start = *self.start.get_or_insert(position);
+
+                if !self.reported && self.to_duration(position + 1 - start) >= self.min_duration {
+                    self.reported = true;
+                }
+            } else if let Some(start) = self.start.take() {
+                if self.reported {
+                    let silence = Silence {
+                        start: self.to_timestamp(start),
+                        end: Some(self.to_timestamp(position)),
+                    };
+
+                    self.intervals.push_back(silence);
+                }
+
+                self.reported = false;
+            }
+
+            position += 1;
+        }
+
+        self.position = Some(position);
+    }
+
+    /// Take the next detected silence interval.
+    pub fn take(&mut self) -> Option<Silence> {
+        self.intervals.pop_front()
+    }
+
+    /// Get the start of the current silence if the audio is silent for at
+    /// least the minimum duration right now.
+    pub fn silent_since(&self) -> Option<Timestamp> {
+        if self.reported {
+            self.start.map(|start| self.to_timestamp(start))
+        } else {
+            None
+        }
+    }
+
+    /// Report the silence lasting until the end of the audio (if any). The
+    /// interval will have no end. The detector can be used for another
+    /// audio afterwards.
+    pub fn flush(&mut self) {
+        if let Some(start) = self.start.take() {
+            if self.reported {
+                let silence = Silence {
+                    start: self.to_timestamp(start),
+                    end: None,
+                };
+
+                self.intervals.push_back(silence);
+            }
+        }
+
+        self.reported = false;
+        self.position = None;
+        self.sample_rate = 0;
+    }
+
+    /// Convert a given sample position into a timestamp.
+    fn to_timestamp(&self, position: i64) -> Timestamp {
+        Timestamp::new(position, TimeBase::new(1, self.sample_rate.max(1)))
+    }
+
+    /// Convert a given number of samples into duration.
+    fn to_duration(&self, samples: i64) -> Duration {
+        let sample_rate = self.sample_rate.max(1) as u64;
+
+        let samples = samples.max(0) as u64;
+
+        Duration::from_secs(samples / sample_rate)
+            + Duration::from_nanos((samples % sample_rate) * 1_000_000_000 / sample_rate)
+    }
+}
+
+impl Default for SilenceDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Silence, SilenceDetector};
+
+    use crate::time::Timestamp;
+
+    #[test]
+    fn test_silence_detection() {
+        let mut detector = SilenceDetector::builder()
+            .noise(-40.0)
+            .min_duration(Duration::from_millis(500))
+            .build();
+
+        detector.sample_rate = 1_000;
+
+        let mut samples = vec![0.5; 1_000];
+
+        // short silence
+        samples.extend(vec![0.001; 200]);
+        samples.extend(vec![0.5; 100]);
+
+        // long silence
+        samples.extend(vec![0.0; 700]);
+        samples.extend(vec![-0.5; 100]);
+
+        // silence till the end
+        samples.extend(vec![0.0; 600]);
+
+        detector.push_interleaved(&samples, 1);
+
+        let silence = detector.take().unwrap();
+
+        assert_eq!(silence.start().as_millis(), Some(1_300));
+        assert_eq!(silence.end().unwrap().as_millis(), Some(2_000));
+        assert_eq!(silence.duration(), Some(Duration::from_millis(700)));
+
+        assert!(detector.take().is_none());
+        assert_eq!(detector.silent_since().unwrap().as_millis(), Some(2_100));
+
+        detector.flush();
+
+        let silence = detector.take().unwrap();
+
+        assert_eq!(silence.start().as_millis(), Some(2_100));
+        assert!(silence.end().is_none());
+    }
+
+    #[test]
+    fn test_silence_duration() {
+        let silence = Silence {
+            start: Timestamp::from_millis(1_000),
+            end: Some(Timestamp::from_millis(1_500)),
+        };
+
+        assert_eq!(silence.duration(), Some(Duration::from_millis(500)));
+
+        let silence = Silence {
+            start: Timestamp::from_millis(1_000),
+            end: Some(Timestamp::from_millis(400)),
+        };
+
+        assert_eq!(silence.duration(), None);
+
+        let silence = Silence {
+            start: Timestamp::from_millis(1_000),
+            end: None,
+        };
+
+        assert_eq!(silence.duration(), None);
+    }
+}
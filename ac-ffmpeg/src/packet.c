@@ -93,6 +93,25 @@ int ffw_packet_make_writable(AVPacket* packet) {
     return av_packet_make_writable(packet);
 }
 
+size_t ffw_packet_get_nb_side_data(const AVPacket* packet) {
+    return packet->side_data_elems;
+}
+
+const AVPacketSideData* ffw_packet_get_side_data(const AVPacket* packet, size_t index) {
+    return &packet->side_data[index];
+}
+
+int ffw_packet_add_side_data(AVPacket* packet, int data_type, const uint8_t* data, size_t size) {
+    uint8_t* dst = av_packet_new_side_data(packet, data_type, size);
+    if (!dst) {
+        return AVERROR(ENOMEM);
+    }
+
+    memcpy(dst, data, size);
+
+    return 0;
+}
+
 size_t ffw_packet_side_data_get_size(const AVPacketSideData* side_data) {
     return side_data->size;
 }
@@ -108,3 +127,73 @@ int ffw_packet_side_data_get_type(const AVPacketSideData* side_data) {
 const char* ffw_packet_get_side_data_name(int side_data_type) {
     return av_packet_side_data_name(side_data_type);
 }
+
+int ffw_packet_side_data_type_to_raw(int type) {
+    switch (type) {
+        case 0: return AV_PKT_DATA_PALETTE;
+        case 1: return AV_PKT_DATA_NEW_EXTRADATA;
+        case 2: return AV_PKT_DATA_PARAM_CHANGE;
+        case 3: return AV_PKT_DATA_H263_MB_INFO;
+        case 4: return AV_PKT_DATA_REPLAYGAIN;
+        case 5: return AV_PKT_DATA_DISPLAYMATRIX;
+        case 6: return AV_PKT_DATA_STEREO3D;
+        case 7: return AV_PKT_DATA_AUDIO_SERVICE_TYPE;
+        case 8: return AV_PKT_DATA_QUALITY_STATS;
+        case 9: return AV_PKT_DATA_FALLBACK_TRACK;
+        case 10: return AV_PKT_DATA_CPB_PROPERTIES;
+        case 11: return AV_PKT_DATA_SKIP_SAMPLES;
+        case 12: return AV_PKT_DATA_JP_DUALMONO;
+        case 13: return AV_PKT_DATA_STRINGS_METADATA;
+        case 14: return AV_PKT_DATA_SUBTITLE_POSITION;
+        case 15: return AV_PKT_DATA_MATROSKA_BLOCKADDITIONAL;
+        case 16: return AV_PKT_DATA_WEBVTT_IDENTIFIER;
+        case 17: return AV_PKT_DATA_WEBVTT_SETTINGS;
+        case 18: return AV_PKT_DATA_METADATA_UPDATE;
+        case 19: return AV_PKT_DATA_MPEGTS_STREAM_ID;
+        case 20: return AV_PKT_DATA_MASTERING_DISPLAY_METADATA;
+        case 21: return AV_PKT_DATA_SPHERICAL;
+        case 22: return AV_PKT_DATA_CONTENT_LIGHT_LEVEL;
+        case 23: return AV_PKT_DATA_A53_CC;
+        case 24: return AV_PKT_DATA_ENCRYPTION_INIT_INFO;
+        case 25: return AV_PKT_DATA_ENCRYPTION_INFO;
+#if LIBAVCODEC_VERSION_INT >= AV_VERSION_INT(58, 91, 100)
+        case 26: return AV_PKT_DATA_AFD;
+        case 27: return AV_PKT_DATA_PRFT;
+#endif
+#if LIBAVCODEC_VERSION_INT >= AV_VERSION_INT(58, 134, 100)
+        case 28: return AV_PKT_DATA_ICC_PROFILE;
+        case 29: return AV_PKT_DATA_DOVI_CONF;
+        case 30: return AV_PKT_DATA_S12M_TIMECODE;
+#endif
+#if LIBAVCODEC_VERSION_INT >= AV_VERSION_INT(60, 31, 102)
+        case 31: return AV_PKT_DATA_DYNAMIC_HDR10_PLUS;
+#endif
+#if LIBAVCODEC_VERSION_INT >= AV_VERSION_INT(61, 3, 100)
+        case 32: return AV_PKT_DATA_IAMF_MIX_GAIN_PARAM;
+        case 33: return AV_PKT_DATA_IAMF_DEMIXING_INFO_PARAM;
+        case 34: return AV_PKT_DATA_IAMF_RECON_GAIN_INFO_PARAM;
+        case 35: return AV_PKT_DATA_AMBIENT_VIEWING_ENVIRONMENT;
+#endif
+#if LIBAVCODEC_VERSION_INT >= AV_VERSION_INT(61, 19, 100)
+        case 36: return AV_PKT_DATA_FRAME_CROPPING;
+        case 37: return AV_PKT_DATA_LCEVC;
+#endif
+        default: return -1;
+    }
+}
+
+int ffw_packet_side_data_type_from_raw(int raw) {
+    int type;
+
+    if (raw < 0) {
+        return -1;
+    }
+
+    for (type = 0; type < 38; type++) {
+        if (ffw_packet_side_data_type_to_raw(type) == raw) {
+            return type;
+        }
+    }
+
+    return -1;
+}
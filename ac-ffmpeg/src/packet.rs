@@ -5,12 +5,18 @@
 
 use std::{
     ffi::CStr,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    mem,
     os::raw::{c_char, c_int, c_void},
     ptr, slice,
     time::Duration,
 };
 
-use crate::time::{TimeBase, Timestamp};
+use crate::{
+    time::{TimeBase, Timestamp},
+    Error,
+};
 
 extern "C" {
     fn ffw_packet_alloc() -> *mut c_void;
@@ -31,10 +37,20 @@ extern "C" {
     fn ffw_packet_set_stream_index(packet: *mut c_void, index: c_int);
     fn ffw_packet_is_writable(packet: *const c_void) -> c_int;
     fn ffw_packet_make_writable(packet: *mut c_void) -> c_int;
+    fn ffw_packet_get_nb_side_data(packet: *const c_void) -> usize;
+    fn ffw_packet_get_side_data(packet: *const c_void, index: usize) -> *const c_void;
+    fn ffw_packet_add_side_data(
+        packet: *mut c_void,
+        data_type: c_int,
+        data: *const u8,
+        size: usize,
+    ) -> c_int;
     fn ffw_packet_side_data_get_size(side_data: *const c_void) -> usize;
     fn ffw_packet_side_data_get_data(side_data: *const c_void) -> *const u8;
     fn ffw_packet_side_data_get_type(side_data: *const c_void) -> c_int;
     fn ffw_packet_get_side_data_name(side_data_type: c_int) -> *const c_char;
+    fn ffw_packet_side_data_type_to_raw(data_type: c_int) -> c_int;
+    fn ffw_packet_side_data_type_from_raw(raw: c_int) -> c_int;
 }
 
 /// Packet with mutable data.
@@ -221,6 +237,21 @@ impl PacketMut {
         }
     }
 
+    /// Get packet side data.
+    pub fn side_data(&self) -> SideDataIter<'_> {
+        unsafe { SideDataIter::new(self.ptr) }
+    }
+
+    /// Get packet side data of a given type (if present).
+    pub fn get_side_data(&self, data_type: SideDataType) -> Option<&SideDataRef> {
+        unsafe { get_side_data(self.ptr, data_type) }
+    }
+
+    /// Add packet side data.
+    pub fn add_side_data(&mut self, data_type: SideDataType, data: &[u8]) -> Result<(), Error> {
+        unsafe { add_side_data(self.ptr, data_type, data) }
+    }
+
     /// Make the packet immutable.
     pub fn freeze(mut self) -> Packet {
         let ptr = self.ptr;
@@ -417,6 +448,21 @@ impl Packet {
         }
     }
 
+    /// Get packet side data.
+    pub fn side_data(&self) -> SideDataIter<'_> {
+        unsafe { SideDataIter::new(self.ptr) }
+    }
+
+    /// Get packet side data of a given type (if present).
+    pub fn get_side_data(&self, data_type: SideDataType) -> Option<&SideDataRef> {
+        unsafe { get_side_data(self.ptr, data_type) }
+    }
+
+    /// Add packet side data.
+    pub fn add_side_data(&mut self, data_type: SideDataType, data: &[u8]) -> Result<(), Error> {
+        unsafe { add_side_data(self.ptr, data_type, data) }
+    }
+
     /// Try to make this packet mutable.
     ///
     /// The method returns `PacketMut` if the packet can be made mutable
@@ -509,32 +555,374 @@ impl SideDataRef {
     }
 }
 
+/// Get side data of a given type from a given packet (if present).
+unsafe fn get_side_data<'a>(
+    packet: *const c_void,
+    data_type: SideDataType,
+) -> Option<&'a SideDataRef> {
+    SideDataIter::new(packet).find(|sd| sd.data_type() == data_type)
+}
+
+/// Add side data of a given type to a given packet.
+unsafe fn add_side_data(
+    packet: *mut c_void,
+    data_type: SideDataType,
+    data: &[u8],
+) -> Result<(), Error> {
+    let data_type = data_type.try_into_raw()?;
+
+    let ret = ffw_packet_add_side_data(packet, data_type, data.as_ptr(), data.len());
+
+    if ret < 0 {
+        return Err(Error::from_raw_error_code(ret));
+    }
+
+    Ok(())
+}
+
+/// Iterator over packet side data.
+pub struct SideDataIter<'a> {
+    packet: *const c_void,
+    index: usize,
+    len: usize,
+    phantom: PhantomData<&'a ()>,
+}
+
+impl SideDataIter<'_> {
+    /// Create a new side data iterator for a given packet.
+    unsafe fn new(packet: *const c_void) -> Self {
+        Self {
+            packet,
+            index: 0,
+            len: ffw_packet_get_nb_side_data(packet),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a> Iterator for SideDataIter<'a> {
+    type Item = &'a SideDataRef;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index == self.len {
+            return None;
+        }
+
+        let side_data =
+            unsafe { SideDataRef::from_raw_ptr(ffw_packet_get_side_data(self.packet, self.index)) };
+        self.index += 1;
+
+        Some(side_data)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let hint = self.len - self.index;
+        (hint, Some(hint))
+    }
+}
+
+impl ExactSizeIterator for SideDataIter<'_> {}
+
 /// Packet side data type.
-pub struct SideDataType(c_int);
+///
+/// `Other` values are compared by their raw value, i.e. `Other` with a raw
+/// value of a known side data type is equal to the corresponding variant.
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone)]
+pub enum SideDataType {
+    /// Palette (AVPalette).
+    Palette,
+    /// New codec extradata.
+    NewExtradata,
+    /// Codec parameter change.
+    ParamChange,
+    /// H.263 macroblock info (used for RFC 2190 packetization).
+    H263MbInfo,
+    /// Replay gain (AVReplayGain).
+    ReplayGain,
+    /// Display matrix (3x3 transformation matrix).
+    DisplayMatrix,
+    /// Stereoscopic 3D information (AVStereo3D).
+    Stereo3d,
+    /// Audio service type (AVAudioServiceType).
+    AudioServiceType,
+    /// Encoder quality statistics.
+    QualityStats,
+    /// Fallback track id.
+    FallbackTrack,
+    /// Coded picture buffer properties (AVCPBProperties).
+    CpbProperties,
+    /// Number of samples to skip at the beginning/end of a packet.
+    SkipSamples,
+    /// Japanese dual mono (ARIB STD-B32).
+    JpDualMono,
+    /// String metadata.
+    StringsMetadata,
+    /// Subtitle position.
+    SubtitlePosition,
+    /// Matroska BlockAdditional data.
+    MatroskaBlockAdditional,
+    /// WebVTT cue identifier.
+    WebVttIdentifier,
+    /// WebVTT cue settings.
+    WebVttSettings,
+    /// Updated string metadata.
+    MetadataUpdate,
+    /// MPEG-TS stream id.
+    MpegTsStreamId,
+    /// HDR mastering display metadata (SMPTE ST 2086).
+    MasteringDisplayMetadata,
+    /// Spherical video mapping (AVSphericalMapping).
+    Spherical,
+    /// HDR content light level (MaxCLL and MaxFALL).
+    ContentLightLevel,
+    /// ATSC A53 Part 4 closed captions (CEA-608/708).
+    A53ClosedCaptions,
+    /// Encryption initialization data.
+    EncryptionInitInfo,
+    /// Encryption info (AVEncryptionInfo).
+    EncryptionInfo,
+    /// Active format description.
+    Afd,
+    /// Producer reference time (AVProducerReferenceTime).
+    ProducerReferenceTime,
+    /// ICC profile.
+    IccProfile,
+    /// Dolby Vision configuration record.
+    DoviConf,
+    /// SMPTE ST 12-1 timecodes.
+    S12mTimecode,
+    /// HDR10+ dynamic metadata (SMPTE ST 2094-40).
+    DynamicHdr10Plus,
+    /// IAMF mix gain parameter.
+    IamfMixGainParam,
+    /// IAMF demixing info parameter.
+    IamfDemixingInfoParam,
+    /// IAMF recon gain info parameter.
+    IamfReconGainInfoParam,
+    /// Ambient viewing environment.
+    AmbientViewingEnvironment,
+    /// Frame cropping.
+    FrameCropping,
+    /// LCEVC enhancement data.
+    Lcevc,
+    /// Other side data type given by its raw value. It is used for side
+    /// data types not covered by the variants above (e.g. types added in
+    /// newer FFmpeg versions), so that the side data can be passed through.
+    Other(i32),
+}
 
 impl SideDataType {
+    /// All side data types.
+    const ALL: [Self; 38] = [
+        Self::Palette,
+        Self::NewExtradata,
+        Self::ParamChange,
+        Self::H263MbInfo,
+        Self::ReplayGain,
+        Self::DisplayMatrix,
+        Self::Stereo3d,
+        Self::AudioServiceType,
+        Self::QualityStats,
+        Self::FallbackTrack,
+        Self::CpbProperties,
+        Self::SkipSamples,
+        Self::JpDualMono,
+        Self::StringsMetadata,
+        Self::SubtitlePosition,
+        Self::MatroskaBlockAdditional,
+        Self::WebVttIdentifier,
+        Self::WebVttSettings,
+        Self::MetadataUpdate,
+        Self::MpegTsStreamId,
+        Self::MasteringDisplayMetadata,
+        Self::Spherical,
+        Self::ContentLightLevel,
+        Self::A53ClosedCaptions,
+        Self::EncryptionInitInfo,
+        Self::EncryptionInfo,
+        Self::Afd,
+        Self::ProducerReferenceTime,
+        Self::IccProfile,
+        Self::DoviConf,
+        Self::S12mTimecode,
+        Self::DynamicHdr10Plus,
+        Self::IamfMixGainParam,
+        Self::IamfDemixingInfoParam,
+        Self::IamfReconGainInfoParam,
+        Self::AmbientViewingEnvironment,
+        Self::FrameCropping,
+        Self::Lcevc,
+    ];
+
     /// Create a packet side data type value from a given raw representation.
-    pub(crate) fn from_raw(v: c_int) -> Self {
-        Self(v)
+    pub(crate) fn from_raw(raw: c_int) -> Self {
+        let index = unsafe { ffw_packet_side_data_type_from_raw(raw) };
+
+        if index < 0 {
+            Self::Other(raw)
+        } else {
+            Self::ALL
+                .get(index as usize)
+                .copied()
+                .unwrap_or(Self::Other(raw))
+        }
     }
 
-    /// Get the raw value.
-    pub(crate) fn into_raw(self) -> c_int {
-        self.0
+    /// Get the raw value (or None if the type is not supported by the
+    /// FFmpeg version in use).
+    pub(crate) fn into_raw(self) -> Option<c_int> {
+        let index = match self.normalize() {
+            Self::Other(raw) => return Some(raw),
+            other => other.index(),
+        };
+
+        let raw = unsafe { ffw_packet_side_data_type_to_raw(index as c_int) };
+
+        if raw < 0 {
+            None
+        } else {
+            Some(raw)
+        }
+    }
+
+    /// Get the raw value or an error if the type is not supported.
+    pub(crate) fn try_into_raw(self) -> Result<c_int, Error> {
+        self.into_raw()
+            .ok_or_else(|| Error::new("packet side data type not supported by this FFmpeg version"))
+    }
+
+    /// Check if the side data type is supported by the FFmpeg version in
+    /// use.
+    pub fn is_supported(self) -> bool {
+        self.into_raw().is_some()
+    }
+
+    /// Replace `Other` values of known side data types with the
+    /// corresponding variants.
+    fn normalize(self) -> Self {
+        if let Self::Other(raw) = self {
+            Self::from_raw(raw)
+        } else {
+            self
+        }
+    }
+
+    /// Get index of a known side data type in `ALL`.
+    fn index(self) -> usize {
+        let discriminant = mem::discriminant(&self);
+
+        Self::ALL
+            .iter()
+            .position(|t| mem::discriminant(t) == discriminant)
+            .unwrap()
     }
 
     /// Get name of the packet side data type.
     pub fn name(self) -> &'static str {
+        let raw = match self.into_raw() {
+            Some(raw) => raw,
+            None => return "unknown",
+        };
+
         unsafe {
-            let ptr = ffw_packet_get_side_data_name(self.into_raw());
+            let ptr = ffw_packet_get_side_data_name(raw);
 
             if ptr.is_null() {
-                panic!("invalid packet side data type");
+                return "unknown";
             }
 
-            let name = CStr::from_ptr(ptr as _);
+            CStr::from_ptr(ptr as _).to_str().unwrap_or("unknown")
+        }
+    }
+}
+
+impl PartialEq for SideDataType {
+    fn eq(&self, other: &Self) -> bool {
+        match (self.normalize(), other.normalize()) {
+            (Self::Other(a), Self::Other(b)) => a == b,
+            (a, b) => mem::discriminant(&a) == mem::discriminant(&b),
+        }
+    }
+}
+
+impl Eq for SideDataType {}
+
+impl Hash for SideDataType {
+    fn hash<H>(&self, state: &mut H)
+    where
+        H: Hasher,
+    {
+        let normalized = self.normalize();
+
+        mem::discriminant(&normalized).hash(state);
 
-            name.to_str().unwrap()
+        if let Self::Other(raw) = normalized {
+            raw.hash(state);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::{PacketMut, SideDataType};
+
+    #[test]
+    fn test_side_data_type_raw_values() {
+        assert_eq!(SideDataType::from_raw(5), SideDataType::DisplayMatrix);
+        assert_eq!(SideDataType::from_raw(23), SideDataType::A53ClosedCaptions);
+        assert_eq!(SideDataType::IccProfile.into_raw(), Some(28));
+
+        for raw in -1..100 {
+            assert_eq!(SideDataType::from_raw(raw).into_raw(), Some(raw));
+        }
+
+        assert_eq!(SideDataType::from_raw(1000), SideDataType::Other(1000));
+    }
+
+    #[test]
+    fn test_side_data_type_equality() {
+        let mut set = HashSet::new();
+
+        set.insert(SideDataType::DisplayMatrix);
+
+        assert_eq!(SideDataType::Other(5), SideDataType::DisplayMatrix);
+        assert_eq!(SideDataType::DisplayMatrix, SideDataType::Other(5));
+        assert_ne!(SideDataType::Other(6), SideDataType::DisplayMatrix);
+        assert_ne!(SideDataType::Other(1000), SideDataType::Other(1001));
+
+        assert!(set.contains(&SideDataType::Other(5)));
+        assert!(!set.contains(&SideDataType::Other(6)));
+    }
+
+    #[test]
+    fn test_side_data() {
+        let mut packet = PacketMut::from(b"data");
+
+        packet
+            .add_side_data(SideDataType::DisplayMatrix, &[1, 2, 3])
+            .unwrap();
+
+        assert_eq!(
+            packet
+                .get_side_data(SideDataType::DisplayMatrix)
+                .map(|sd| sd.data()),
+            Some(&[1u8, 2, 3][..])
+        );
+
+        // unsupported types cannot be added
+        assert!(packet.add_side_data(SideDataType::Other(-1), &[1]).is_err());
+
+        let mut packet = packet.freeze();
+
+        packet.add_side_data(SideDataType::Other(23), &[4]).unwrap();
+
+        assert_eq!(packet.side_data().len(), 2);
+        assert!(packet
+            .get_side_data(SideDataType::A53ClosedCaptions)
+            .is_some());
+        assert!(packet.get_side_data(SideDataType::IccProfile).is_none());
+    }
+}
@@ -0,0 +1,1052 @@
+//! Multi-threaded transcoding pipeline.
+//!
+//! The pipeline wires demuxer -> decoder -> scaler/resampler -> encoder ->
+//! muxer. The demuxer runs in its own thread, every transcoded stream is
+//! decoded, converted and encoded in its own worker thread and the muxer
+//! runs in the calling thread. The stages are connected using bounded
+//! channels, so a slow stage blocks the stages feeding it and the amount
+//! of buffered media is limited.
+//!
+//! # Example
+//! ```text
+//! let pipeline = Pipeline::builder()
+//!     .video(Some(CodecPolicy::Transcode(String::from("libx264"))))
+//!     .video_size(1280, 720)
+//!     .set_video_encoder_option("preset", "veryfast")
+//!     .audio(Some(CodecPolicy::CopyOrTranscode(String::from("aac"))))
+//!     .progress(|progress| {
+//!         println!(
+//!             "{:?} processed, {} bytes, {:.1} fps, {:?} remaining",
+//!             progress.position(),
+//!             progress.output_bytes(),
+//!             progress.fps(),
+//!             progress.remaining(),
+//!         )
+//!     })
+//!     .build();
+//!
+//! let input = IO::from_seekable_read_stream(File::open("input.mkv")?);
+//! let output = IO::from_seekable_write_stream(File::create("output.mp4")?);
+//!
+//! let format = OutputFormat::find_by_name("mp4").unwrap();
+//!
+//! pipeline.run(input, output, format)?;
+//! ```
+
+use std::{
+    io::{Read, Write},
+    sync::mpsc::{self, Receiver, SyncSender},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    codec::{
+        audio::{AudioDecoder, AudioEncoder, AudioFrame, AudioResampler},
+        video::{
+            convert::FrameConverter, scaler::Algorithm, PixelFormat, VideoDecoder, VideoEncoder,
+            VideoFrame,
+        },
+        CodecParameters, Decoder, Encoder,
+    },
+    format::{
+        demuxer::{Demuxer, DemuxerWithStreamInfo},
+        extract::{encoder_name, reset_codec_tag, CodecPolicy},
+        io::IO,
+        muxer::{Muxer, OutputFormat},
+        stream::{Discard, Stream},
+    },
+    packet::Packet,
+    time::TimeBase,
+    Error,
+};
+
+/// Default capacity of the channels between the pipeline stages.
+const DEFAULT_CAPACITY: usize = 32;
+
+/// Default interval between two progress reports.
+const DEFAULT_PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Progress callback.
+type ProgressCallback = Box<dyn FnMut(&Progress) + Send>;
+
+/// Message passed to the muxer.
+type Message = Result<Packet, Error>;
+
+/// Pipeline progress. It provides roughly the same information as
+/// `ffmpeg -progress`.
+#[derive(Debug, Clone)]
+pub struct Progress {
+    position: Duration,
+    duration: Option<Duration>,
+    packets: u64,
+    frames: u64,
+    output_bytes: u64,
+    elapsed: Duration,
+    done: bool,
+    started: Instant,
+}
+
+impl Progress {
+    /// Create a new progress for an input of a given duration.
+    fn new(duration: Option<Duration>) -> Self {
+        Self {
+            position: Duration::from_secs(0),
+            duration,
+            packets: 0,
+            frames: 0,
+            output_bytes: 0,
+            elapsed: Duration::from_secs(0),
+            done: false,
+            started: Instant::now(),
+        }
+    }
+
+    /// Get the processed duration, i.e. the timestamp of the most recent
+    /// muxed packet.
+    pub fn position(&self) -> Duration {
+        self.position
+    }
+
+    /// Get duration of the input (if known).
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    /// Get the number of muxed packets.
+    pub fn packets(&self) -> u64 {
+        self.packets
+    }
+
+    /// Get the number of muxed video frames.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Get the number of bytes written into the output so far.
+    pub fn output_bytes(&self) -> u64 {
+        self.output_bytes
+    }
+
+    /// Get the wall-clock time elapsed since the pipeline started.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Get the average processing speed in video frames per second.
+    pub fn fps(&self) -> f64 {
+        let elapsed = self.elapsed.as_secs_f64();
+
+        if elapsed > 0.0 {
+            self.frames as f64 / elapsed
+        } else {
+            0.0
+        }
+    }
+
+    /// Get the processing speed relative to real time (e.g. 2.0 means that
+    /// one second of the input is processed in half a second).
+    pub fn speed(&self) -> f64 {
+        let elapsed = self.elapsed.as_secs_f64();
+
+        if elapsed > 0.0 {
+            self.position.as_secs_f64() / elapsed
+        } else {
+            0.0
+        }
+    }
+
+    /// Get the processed fraction of the input (0.0 - 1.0) if the input
+    /// duration is known.
+    pub fn fraction(&self) -> Option<f64> {
+        if self.done {
+            return Some(1.0);
+        }
+
+        let duration = self.duration?.as_secs_f64();
+
+        if duration > 0.0 {
+            Some((self.position.as_secs_f64() / duration).min(1.0))
+        } else {
+            None
+        }
+    }
+
+    /// Get the estimated remaining time. The estimate is available only if
+    /// the input duration is known and some of the input has been already
+    /// processed.
+    pub fn remaining(&self) -> Option<Duration> {
+        if self.done {
+            return Some(Duration::from_secs(0));
+        }
+
+        let speed = self.speed();
+
+        if speed <= 0.0 {
+            return None;
+        }
+
+        let remaining = self.duration?.saturating_sub(self.position);
+
+        Some(Duration::from_secs_f64(remaining.as_secs_f64() / speed))
+    }
+
+    /// Check if this is the final report sent after the output has been
+    /// finished.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+}
+
+/// Builder for the transcoding pipeline.
+pub struct PipelineBuilder {
+    video: Option<CodecPolicy>,
+    audio: Option<CodecPolicy>,
+    video_size: Option<(usize, usize)>,
+    video_options: Vec<(String, String)>,
+    audio_options: Vec<(String, String)>,
+    capacity: usize,
+    progress: Option<ProgressCallback>,
+    progress_interval: Duration,
+}
+
+impl PipelineBuilder {
+    /// Create a new builder.
+    fn new() -> Self {
+        Self {
+            video: Some(CodecPolicy::CopyOrTranscode(String::from("libx264"))),
+            audio: Some(CodecPolicy::CopyOrTranscode(String::from("aac"))),
+            video_size: None,
+            video_options: Vec::new(),
+            audio_options: Vec::new(),
+            capacity: DEFAULT_CAPACITY,
+            progress: None,
+            progress_interval: DEFAULT_PROGRESS_INTERVAL,
+        }
+    }
+
+    /// Set codec policy for video streams. Video streams are dropped if the
+    /// policy is None. The default is to copy the streams if possible and
+    /// transcode them into H.264 using `libx264` otherwise.
+    pub fn video(mut self, policy: Option<CodecPolicy>) -> Self {
+        self.video = policy;
+        self
+    }
+
+    /// Set codec policy for audio streams. Audio streams are dropped if the
+    /// policy is None. The default is to copy the streams if possible and
+    /// transcode them into AAC otherwise.
+    pub fn audio(mut self, policy: Option<CodecPolicy>) -> Self {
+        self.audio = policy;
+        self
+    }
+
+    /// Scale transcoded video streams to a given resolution. The resolution
+    /// is kept by default.
+    pub fn video_size(mut self, width: usize, height: usize) -> Self {
+        self.video_size = Some((width, height));
+        self
+    }
+
+    /// Set a video encoder option.
+    pub fn set_video_encoder_option<V>(mut self, name: &str, value: V) -> Self
+    where
+        V: ToString,
+    {
+        self.video_options
+            .push((String::from(name), value.to_string()));
+        self
+    }
+
+    /// Set an audio encoder option.
+    pub fn set_audio_encoder_option<V>(mut self, name: &str, value: V) -> Self
+    where
+        V: ToString,
+    {
+        self.audio_options
+            .push((String::from(name), value.to_string()));
+        self
+    }
+
+    /// Set capacity (in packets) of the channels connecting the pipeline
+    /// stages. The default is 32.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Set a progress callback. The callback is called periodically from
+    /// the thread running the pipeline and once more after the output has
+    /// been finished (see `Progress::is_done()`).
+    pub fn progress<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&Progress) + Send + 'static,
+    {
+        self.progress = Some(Box::new(callback));
+        self
+    }
+
+    /// Set the minimum interval between two progress reports. Zero means
+    /// that the progress is reported after every muxed packet. The default
+    /// is 500 ms.
+    pub fn progress_interval(mut self, interval: Duration) -> Self {
+        self.progress_interval = interval;
+        self
+    }
+
+    /// Build the pipeline.
+    pub fn build(self) -> Pipeline {
+        Pipeline {
+            video: self.video,
+            audio: self.audio,
+            video_size: self.video_size,
+            video_options: self.video_options,
+            audio_options: self.audio_options,
+            capacity: self.capacity,
+            progress: self.progress,
+            progress_interval: self.progress_interval,
+        }
+    }
+}
+
+/// Transcoding pipeline. Subtitle and data streams are dropped.
+pub struct Pipeline {
+    video: Option<CodecPolicy>,
+    audio: Option<CodecPolicy>,
+    video_size: Option<(usize, usize)>,
+    video_options: Vec<(String, String)>,
+    audio_options: Vec<(String, String)>,
+    capacity: usize,
+    progress: Option<ProgressCallback>,
+    progress_interval: Duration,
+}
+
+impl Pipeline {
+    /// Get a builder for the pipeline.
+    pub fn builder() -> PipelineBuilder {
+        PipelineBuilder::new()
+    }
+
+    /// Process a given input into a given output. The method blocks until
+    /// the whole input is processed or until an error occurs in any of the
+    /// stages.
+    pub fn run<R, W>(
+        mut self,
+        input: IO<R>,
+        output: IO<W>,
+        format: OutputFormat,
+    ) -> Result<IO<W>, Error>
+    where
+        R: Read + Send + 'static,
+        W: Write,
+    {
+        let mut demuxer = Demuxer::builder()
+            .build(input)?
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)?;
+
+        let mut muxer_builder = Muxer::builder();
+
+        let mut routes = Vec::new();
+        let mut stages = Vec::new();
+
+        // video flags of the output streams
+        let mut video = Vec::new();
+
+        for stream in demuxer.streams() {
+            let params = stream.codec_parameters();
+
+            let policy = if params.is_video_codec() {
+                self.video.as_ref()
+            } else if params.is_audio_codec() {
+                self.audio.as_ref()
+            } else {
+                None
+            };
+
+            let policy = match policy {
+                Some(policy) => policy,
+                None => {
+                    routes.push(Route::Drop);
+                    continue;
+                }
+            };
+
+            video.push(params.is_video_codec());
+
+            if let Some(encoder) = encoder_name(&format, &params, policy)? {
+                let stage = self.stage(stream, encoder, &format)?;
+
+                let index = muxer_builder.add_stream(&stage.codec_parameters())?;
+
+                routes.push(Route::Transcode(stages.len()));
+                stages.push((index, stage));
+            } else {
+                let index = muxer_builder.add_stream(&reset_codec_tag(params))?;
+
+                routes.push(Route::Copy(index));
+            }
+        }
+
+        if routes.iter().all(|route| matches!(route, Route::Drop)) {
+            return Err(Error::new("no streams to be processed"));
+        }
+
+        for (stream, route) in demuxer.streams_mut().iter_mut().zip(&routes) {
+            if let Route::Drop = route {
+                stream.set_discard(Discard::All);
+            }
+        }
+
+        let mut progress = Progress::new(demuxer.duration());
+
+        let mut muxer = muxer_builder.build(output, format)?;
+
+        let (mux_tx, mux_rx) = mpsc::sync_channel(self.capacity);
+
+        let mut threads = Vec::new();
+        let mut senders = Vec::new();
+
+        for (index, stage) in stages {
+            let (tx, rx) = mpsc::sync_channel(self.capacity);
+
+            let mux_tx = mux_tx.clone();
+
+            threads.push(thread::spawn(move || stage.run(rx, index, mux_tx)));
+
+            senders.push(tx);
+        }
+
+        threads.push(thread::spawn(move || {
+            demux(demuxer, routes, senders, mux_tx)
+        }));
+
+        let res = self.mux(&mut muxer, mux_rx, &video, &mut progress);
+
+        join(threads)?;
+
+        res?;
+
+        muxer.flush()?;
+
+        let output = muxer.close()?;
+
+        progress.output_bytes = output.bytes_written();
+        progress.done = true;
+
+        self.report(&mut progress);
+
+        Ok(output)
+    }
+
+    /// Create a transcoding stage for a given stream.
+    fn stage(&self, stream: &Stream, encoder: &str, format: &OutputFormat) -> Result<Stage, Error> {
+        if stream.codec_parameters().is_video_codec() {
+            VideoStage::new(
+                stream,
+                encoder,
+                format,
+                self.video_size,
+                &self.video_options,
+            )
+            .map(Stage::Video)
+        } else {
+            AudioStage::new(stream, encoder, format, &self.audio_options).map(Stage::Audio)
+        }
+    }
+
+    /// Push all packets received from a given channel into a given muxer.
+    /// The second argument tells which output streams are video streams.
+    fn mux<W>(
+        &mut self,
+        muxer: &mut Muxer<W>,
+        packets: Receiver<Message>,
+        video: &[bool],
+        progress: &mut Progress,
+    ) -> Result<(), Error>
+    where
+        W: Write,
+    {
+        let mut last_report = None;
+
+        for packet in packets {
+            let packet = packet?;
+
+            let ts = packet.pts().as_micros().unwrap_or(0).max(0) as u64;
+
+            if video.get(packet.stream_index()).copied().unwrap_or(false) {
+                progress.frames += 1;
+            }
+
+            muxer.push(packet)?;
+
+            progress.position = progress.position.max(Duration::from_micros(ts));
+            progress.packets += 1;
+
+            let now = Instant::now();
+
+            let report = match last_report {
+                Some(last) => now.duration_since(last) >= self.progress_interval,
+                None => true,
+            };
+
+            if report {
+                progress.output_bytes = muxer.io().bytes_written();
+
+                self.report(progress);
+
+                last_report = Some(now);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Report a given progress.
+    fn report(&mut self, progress: &mut Progress) {
+        progress.elapsed = progress.started.elapsed();
+
+        if let Some(callback) = self.progress.as_mut() {
+            callback(progress);
+        }
+    }
+}
+
+/// Routing of packets of an input stream.
+enum Route {
+    /// The stream is dropped.
+    Drop,
+    /// The stream is copied into an output stream with a given index.
+    Copy(usize),
+    /// The stream is passed to a transcoding stage with a given index.
+    Transcode(usize),
+}
+
+/// Read all packets from a given demuxer and route them to the
+/// corresponding stages.
+fn demux<R>(
+    mut demuxer: DemuxerWithStreamInfo<R>,
+    routes: Vec<Route>,
+    stages: Vec<SyncSender<Packet>>,
+    output: SyncSender<Message>,
+) -> Result<(), Error>
+where
+    R: Read,
+{
+    loop {
+        let packet = match demuxer.take() {
+            Ok(Some(packet)) => packet,
+            Ok(None) => return Ok(()),
+            Err(err) => {
+                // the error will be returned by the muxer
+                let _ = output.send(Err(err));
+
+                return Ok(());
+            }
+        };
+
+        let sent = match routes.get(packet.stream_index()) {
+            Some(Route::Copy(index)) => output.send(Ok(packet.with_stream_index(*index))).is_ok(),
+            Some(Route::Transcode(index)) => stages[*index].send(packet).is_ok(),
+            _ => true,
+        };
+
+        // the pipeline has been stopped
+        if !sent {
+            return Ok(());
+        }
+    }
+}
+
+/// Wait for given threads to finish.
+fn join(threads: Vec<JoinHandle<Result<(), Error>>>) -> Result<(), Error> {
+    for thread in threads {
+        thread
+            .join()
+            .map_err(|_| Error::new("pipeline stage panicked"))??;
+    }
+
+    Ok(())
+}
+
+/// Transcoding stage.
+enum Stage {
+    Video(VideoStage),
+    Audio(AudioStage),
+}
+
+impl Stage {
+    /// Get codec parameters of the output stream.
+    fn codec_parameters(&self) -> CodecParameters {
+        match self {
+            Self::Video(stage) => stage.encoder.codec_parameters().into(),
+            Self::Audio(stage) => stage.encoder.codec_parameters().into(),
+        }
+    }
+
+    /// Transcode all packets received from a given channel and send the
+    /// resulting packets with a given stream index to a given channel.
+    fn run(
+        self,
+        input: Receiver<Packet>,
+        index: usize,
+        output: SyncSender<Message>,
+    ) -> Result<(), Error> {
+        let mut output = Output { index, output };
+
+        let res = match self {
+            Self::Video(stage) => stage.run(input, &mut output),
+            Self::Audio(stage) => stage.run(input, &mut output),
+        };
+
+        // the error will be returned by the muxer
+        if let Err(err) = res {
+            output.error(err);
+        }
+
+        Ok(())
+    }
+}
+
+/// Output of a transcoding stage.
+struct Output {
+    index: usize,
+    output: SyncSender<Message>,
+}
+
+impl Output {
+    /// Send all packets available in a given encoder. False is returned if
+    /// the pipeline has been stopped.
+    fn forward<E>(&mut self, encoder: &mut E) -> Result<bool, Error>
+    where
+        E: Encoder,
+    {
+        while let Some(packet) = encoder.take()? {
+            let packet = packet.with_stream_index(self.index);
+
+            if self.output.send(Ok(packet)).is_err() {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Send a given error.
+    fn error(&mut self, err: Error) {
+        let _ = self.output.send(Err(err));
+    }
+}
+
+/// Video transcoding stage.
+struct VideoStage {
+    decoder: VideoDecoder,
+    converter: FrameConverter,
+    encoder: VideoEncoder,
+    pixel_format: PixelFormat,
+    width: usize,
+    height: usize,
+}
+
+impl VideoStage {
+    /// Create a new video transcoding stage.
+    fn new(
+        stream: &Stream,
+        encoder: &str,
+        format: &OutputFormat,
+        size: Option<(usize, usize)>,
+        options: &[(String, String)],
+    ) -> Result<Self, Error> {
+        let params = stream
+            .codec_parameters()
+            .into_video_codec_parameters()
+            .ok_or_else(|| Error::new("not a video stream"))?;
+
+        let decoder = VideoDecoder::from_stream(stream)?.build()?;
+
+        let mut builder = VideoEncoder::builder(encoder)?;
+
+        let pixel_format = builder
+            .default_pixel_format()
+            .unwrap_or_else(|| params.pixel_format());
+
+        let (width, height) = size.unwrap_or((params.width(), params.height()));
+
+        if format.requires_global_header() {
+            builder = builder.set_option("flags", "+global_header");
+        }
+
+        if size.is_none() {
+            if let Some(sar) = params.sample_aspect_ratio() {
+                builder = builder.sample_aspect_ratio(sar);
+            }
+        }
+
+        if let Some(frame_rate) = stream.avg_frame_rate() {
+            builder = builder.frame_rate(frame_rate);
+        }
+
+        for (name, value) in options {
+            builder = builder.set_option(name, value);
+        }
+
+        let encoder = builder
+            .pixel_format(pixel_format)
+            .width(width)
+            .height(height)
+            .time_base(stream.time_base())
+            .build()?;
+
+        let res = Self {
+            decoder,
+            converter: FrameConverter::new(pixel_format, Algorithm::Bicubic),
+            encoder,
+            pixel_format,
+            width,
+            height,
+        };
+
+        Ok(res)
+    }
+
+    /// Transcode all packets received from a given channel.
+    fn run(mut self, input: Receiver<Packet>, output: &mut Output) -> Result<(), Error> {
+        for packet in input {
+            self.decoder.push(packet)?;
+
+            if !self.process(output)? {
+                return Ok(());
+            }
+        }
+
+        self.decoder.flush()?;
+
+        if !self.process(output)? {
+            return Ok(());
+        }
+
+        self.encoder.flush()?;
+
+        output.forward(&mut self.encoder)?;
+
+        Ok(())
+    }
+
+    /// Encode all frames available in the decoder.
+    fn process(&mut self, output: &mut Output) -> Result<bool, Error> {
+        while let Some(frame) = self.decoder.take()? {
+            let frame = self.convert(frame)?;
+
+            self.encoder.push(frame)?;
+
+            if !output.forward(&mut self.encoder)? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Convert a given frame into the encoder format (if needed).
+    fn convert(&mut self, frame: VideoFrame) -> Result<VideoFrame, Error> {
+        let format = frame.pixel_format();
+        let width = frame.width();
+        let height = frame.height();
+
+        if format == self.pixel_format && width == self.width && height == self.height {
+            return Ok(frame);
+        }
+
+        self.converter.convert(&frame, self.width, self.height)
+    }
+}
+
+/// Audio transcoding stage.
+struct AudioStage {
+    decoder: AudioDecoder,
+    resampler: Option<AudioResampler>,
+    encoder: AudioEncoder,
+}
+
+impl AudioStage {
+    /// Create a new audio transcoding stage.
+    fn new(
+        stream: &Stream,
+        encoder: &str,
+        format: &OutputFormat,
+        options: &[(String, String)],
+    ) -> Result<Self, Error> {
+        let params = stream
+            .codec_parameters()
+            .into_audio_codec_parameters()
+            .ok_or_else(|| Error::new("not an audio stream"))?;
+
+        let decoder = AudioDecoder::from_stream(stream)?.build()?;
+
+        let mut builder = AudioEncoder::builder(encoder)?;
+
+        let sample_format = builder
+            .default_sample_format()
+            .unwrap_or_else(|| params.sample_format());
+
+        let sample_rate = builder.closest_sample_rate(params.sample_rate());
+
+        let channel_layout = builder.closest_channel_layout(params.channel_layout());
+
+        if format.requires_global_header() {
+            builder = builder.set_option("flags", "+global_header");
+        }
+
+        for (name, value) in options {
+            builder = builder.set_option(name, value);
+        }
+
+        let encoder = builder
+            .sample_format(sample_format)
+            .sample_rate(sample_rate)
+            .channel_layout(channel_layout)
+            .time_base(TimeBase::new(1, sample_rate as _))
+            .build()?;
+
+        let res = Self {
+            decoder,
+            resampler: None,
+            encoder,
+        };
+
+        Ok(res)
+    }
+
+    /// Transcode all packets received from a given channel.
+    fn run(mut self, input: Receiver<Packet>, output: &mut Output) -> Result<(), Error> {
+        for packet in input {
+            self.decoder.push(packet)?;
+
+            if !self.process(output)? {
+                return Ok(());
+            }
+        }
+
+        self.decoder.flush()?;
+
+        if !self.process(output)? {
+            return Ok(());
+        }
+
+        if let Some(resampler) = self.resampler.as_mut() {
+            resampler.flush()?;
+
+            if !self.resample(output)? {
+                return Ok(());
+            }
+        }
+
+        self.encoder.flush()?;
+
+        output.forward(&mut self.encoder)?;
+
+        Ok(())
+    }
+
+    /// Resample and encode all frames available in the decoder.
+    fn process(&mut self, output: &mut Output) -> Result<bool, Error> {
+        while let Some(frame) = self.decoder.take()? {
+            self.resampler(&frame)?.push(frame)?;
+
+            if !self.resample(output)? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Encode all frames available in the resampler.
+    fn resample(&mut self, output: &mut Output) -> Result<bool, Error> {
+        let resampler = match self.resampler.as_mut() {
+            Some(resampler) => resampler,
+            None => return Ok(true),
+        };
+
+        while let Some(frame) = resampler.take()? {
+            self.encoder.push(frame)?;
+
+            if !output.forward(&mut self.encoder)? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Get the resampler. The resampler is created using the parameters of
+    /// the first decoded frame.
+    fn resampler(&mut self, frame: &AudioFrame) -> Result<&mut AudioResampler, Error> {
+        if self.resampler.is_none() {
+            let params = self.encoder.codec_parameters();
+
+            let resampler = AudioResampler::builder()
+                .source_channel_layout(frame.channel_layout().to_owned())
+                .source_sample_format(frame.sample_format())
+                .source_sample_rate(frame.sample_rate())
+                .target_channel_layout(params.channel_layout().to_owned())
+                .target_sample_format(params.sample_format())
+                .target_sample_rate(params.sample_rate())
+                .target_frame_samples(self.encoder.samples_per_frame())
+                .build()?;
+
+            self.resampler = Some(resampler);
+        }
+
+        Ok(self.resampler.as_mut().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Cursor,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use super::{Pipeline, Progress};
+
+    use crate::{
+        codec::CodecParameters,
+        format::{demuxer::Demuxer, extract::CodecPolicy, io::IO, muxer::OutputFormat},
+        test_util::{encode_audio, encode_video},
+    };
+
+    /// Run a given pipeline with a given in-memory input and get codec
+    /// parameters of the output streams and the number of output packets.
+    fn run(pipeline: Pipeline, input: Vec<u8>) -> (Vec<CodecParameters>, usize) {
+        let input = IO::from_seekable_read_stream(Cursor::new(input));
+        let output = IO::from_seekable_write_stream(Cursor::new(Vec::new()));
+
+        let format = OutputFormat::find_by_name("matroska").unwrap();
+
+        let output = pipeline.run(input, output, format).unwrap();
+
+        let data = output.into_stream().into_inner();
+
+        let mut demuxer = Demuxer::builder()
+            .build(IO::from_read_stream(Cursor::new(data)))
+            .unwrap()
+            .find_stream_info(None)
+            .map_err(|(_, err)| err)
+            .unwrap();
+
+        let params = demuxer
+            .streams()
+            .iter()
+            .map(|stream| stream.codec_parameters())
+            .collect();
+
+        let mut packets = 0;
+
+        while demuxer.take().unwrap().is_some() {
+            packets += 1;
+        }
+
+        (params, packets)
+    }
+
+    #[test]
+    fn test_progress() {
+        let mut progress = Progress::new(Some(Duration::from_secs(60)));
+
+        assert_eq!(progress.fps(), 0.0);
+        assert!(progress.remaining().is_none());
+
+        progress.position = Duration::from_secs(20);
+        progress.frames = 500;
+        progress.elapsed = Duration::from_secs(10);
+
+        assert_eq!(progress.fps(), 50.0);
+        assert_eq!(progress.speed(), 2.0);
+        assert_eq!(progress.fraction(), Some(20.0 / 60.0));
+        assert_eq!(progress.remaining(), Some(Duration::from_secs(20)));
+
+        progress.done = true;
+
+        assert_eq!(progress.fraction(), Some(1.0));
+        assert_eq!(progress.remaining(), Some(Duration::from_secs(0)));
+    }
+
+    #[test]
+    fn test_video_pipeline() {
+        let done = Arc::new(AtomicBool::new(false));
+
+        let pipeline = Pipeline::builder()
+            .video(Some(CodecPolicy::Transcode(String::from("mpeg4"))))
+            .video_size(32, 24)
+            .audio(None)
+            .capacity(1)
+            .progress({
+                let done = done.clone();
+
+                move |progress| {
+                    if progress.is_done() {
+                        assert_eq!(progress.frames(), 25);
+
+                        done.store(true, Ordering::SeqCst);
+                    }
+                }
+            })
+            .build();
+
+        let (params, packets) = run(pipeline, encode_video(64, 48, 25, 5));
+
+        assert!(done.load(Ordering::SeqCst));
+
+        assert_eq!(params.len(), 1);
+        assert_eq!(packets, 25);
+
+        let params = params[0].as_video_codec_parameters().unwrap();
+
+        assert_eq!(params.encoder_name(), Some("mpeg4"));
+        assert_eq!((params.width(), params.height()), (32, 24));
+
+        // copy
+        let pipeline = Pipeline::builder().video(Some(CodecPolicy::Copy)).build();
+
+        let (params, packets) = run(pipeline, encode_video(64, 48, 25, 5));
+
+        assert_eq!(params.len(), 1);
+        assert_eq!(packets, 25);
+    }
+
+    #[test]
+    fn test_audio_pipeline() {
+        let pipeline = Pipeline::builder()
+            .video(None)
+            .audio(Some(CodecPolicy::Transcode(String::from("mp2"))))
+            .build();
+
+        let (params, packets) = run(pipeline, encode_audio(10));
+
+        assert_eq!(params.len(), 1);
+        assert!(packets > 0);
+
+        let params = params[0].as_audio_codec_parameters().unwrap();
+
+        // mp2 does not support 8 kHz, so the audio has to be resampled
+        assert_eq!(params.encoder_name(), Some("mp2"));
+        assert_ne!(params.sample_rate(), 8000);
+        assert_eq!(params.channel_layout().channels(), 1);
+
+        // no streams to be processed
+        let pipeline = Pipeline::builder().audio(None).build();
+
+        let input = IO::from_seekable_read_stream(Cursor::new(encode_audio(10)));
+        let output = IO::from_seekable_write_stream(Cursor::new(Vec::new()));
+
+        let format = OutputFormat::find_by_name("matroska").unwrap();
+
+        assert!(pipeline.run(input, output, format).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+//! Shared test fixtures.
+
+use std::{
+    io::Cursor,
+    sync::atomic::{AtomicI64, Ordering},
+};
+
+use crate::{
+    codec::{
+        audio::{frame::get_sample_format, AudioEncoder, AudioFrameMut, ChannelLayout},
+        video::{self, VideoEncoder, VideoFrameMut},
+        Encoder,
+    },
+    format::{
+        clock::Clock,
+        io::IO,
+        muxer::{Muxer, OutputFormat},
+    },
+    packet::{Packet, PacketMut},
+    time::{TimeBase, Timestamp},
+};
+
+/// Encode a given number of black frames (25 fps, MPEG-4 part 2) with a
+/// given GOP size into an in-memory Matroska file.
+pub fn encode_video(width: usize, height: usize, frames: i64, gop_size: u32) -> Vec<u8> {
+    let time_base = TimeBase::new(1, 25);
+
+    let pixel_format = video::frame::get_pixel_format("yuv420p");
+
+    let frame = VideoFrameMut::black(pixel_format, width, height)
+        .with_time_base(time_base)
+        .freeze();
+
+    let mut encoder = VideoEncoder::builder("mpeg4")
+        .unwrap()
+        .pixel_format(pixel_format)
+        .width(width)
+        .height(height)
+        .time_base(time_base)
+        .gop_size(gop_size)
+        .max_b_frames(0)
+        .build()
+        .unwrap();
+
+    let mut muxer_builder = Muxer::builder();
+
+    muxer_builder
+        .add_stream(&encoder.codec_parameters().into())
+        .unwrap();
+
+    let io = IO::from_seekable_write_stream(Cursor::new(Vec::new()));
+
+    let mut muxer = muxer_builder
+        .build(io, OutputFormat::find_by_name("matroska").unwrap())
+        .unwrap();
+
+    for index in 0..frames {
+        let frame = frame.clone().with_pts(Timestamp::new(index, time_base));
+
+        encoder.push(frame).unwrap();
+
+        while let Some(packet) = encoder.take().unwrap() {
+            muxer.push(packet.with_stream_index(0)).unwrap();
+        }
+    }
+
+    encoder.flush().unwrap();
+
+    while let Some(packet) = encoder.take().unwrap() {
+        muxer.push(packet.with_stream_index(0)).unwrap();
+    }
+
+    muxer.close().unwrap().into_stream().into_inner()
+}
+
+/// Encode a given number of black 64x48 frames (25 fps) with a GOP size
+/// of 10 frames into H.264 packets using libx264. The packets use the Annex
+/// B format with parameter sets in every keyframe. `None` is returned if
+/// libx264 is not available.
+pub fn encode_h264(frames: i64) -> Option<Vec<Packet>> {
+    let time_base = TimeBase::new(1, 25);
+
+    let pixel_format = video::frame::get_pixel_format("yuv420p");
+
+    let frame = VideoFrameMut::black(pixel_format, 64, 48)
+        .with_time_base(time_base)
+        .freeze();
+
+    // libx264 is an optional dependency of FFmpeg
+    let mut encoder = VideoEncoder::builder("libx264")
+        .ok()?
+        .pixel_format(pixel_format)
+        .width(64)
+        .height(48)
+        .time_base(time_base)
+        .gop_size(10)
+        .max_b_frames(0)
+        .preset("ultrafast")
+        .tune("zerolatency")
+        .build()
+        .ok()?;
+
+    let mut res = Vec::new();
+
+    for index in 0..frames {
+        let frame = frame.clone().with_pts(Timestamp::new(index, time_base));
+
+        encoder.push(frame).unwrap();
+
+        while let Some(packet) = encoder.take().unwrap() {
+            res.push(packet);
+        }
+    }
+
+    encoder.flush().unwrap();
+
+    while let Some(packet) = encoder.take().unwrap() {
+        res.push(packet);
+    }
+
+    Some(res)
+}
+
+/// Encode a given number of silent mono frames (8 kHz, 800 samples per
+/// frame, 16-bit PCM) into an in-memory Matroska file.
+pub fn encode_audio(frames: i64) -> Vec<u8> {
+    let time_base = TimeBase::new(1, 8000);
+
+    let channel_layout = ChannelLayout::from_channels(1).unwrap();
+    let sample_format = get_sample_format("s16");
+
+    let frame = AudioFrameMut::silence(&channel_layout, sample_format, 8000, 800)
+        .with_time_base(time_base)
+        .freeze();
+
+    let mut encoder = AudioEncoder::builder("pcm_s16le")
+        .unwrap()
+        .sample_format(sample_format)
+        .sample_rate(8000)
+        .channel_layout(channel_layout)
+        .time_base(time_base)
+        .build()
+        .unwrap();
+
+    let mut muxer_builder = Muxer::builder();
+
+    muxer_builder
+        .add_stream(&encoder.codec_parameters().into())
+        .unwrap();
+
+    let io = IO::from_seekable_write_stream(Cursor::new(Vec::new()));
+
+    let mut muxer = muxer_builder
+        .build(io, OutputFormat::find_by_name("matroska").unwrap())
+        .unwrap();
+
+    for index in 0..frames {
+        let frame = frame
+            .clone()
+            .with_pts(Timestamp::new(index * 800, time_base));
+
+        encoder.push(frame).unwrap();
+
+        while let Some(packet) = encoder.take().unwrap() {
+            muxer.push(packet.with_stream_index(0)).unwrap();
+        }
+    }
+
+    encoder.flush().unwrap();
+
+    while let Some(packet) = encoder.take().unwrap() {
+        muxer.push(packet.with_stream_index(0)).unwrap();
+    }
+
+    muxer.close().unwrap().into_stream().into_inner()
+}
+
+/// Create a new packet of a given size with a given time base and with
+/// both the presentation and the decoding timestamp set to a given value.
+pub fn packet(size: usize, time_base: TimeBase, timestamp: i64) -> PacketMut {
+    PacketMut::new(size)
+        .with_time_base(time_base)
+        .with_raw_pts(timestamp)
+        .with_raw_dts(timestamp)
+}
+
+/// Clock that moves only when it is set.
+pub struct ManualClock(AtomicI64);
+
+impl ManualClock {
+    /// Create a new clock with a given time in microseconds.
+    pub fn new(micros: i64) -> Self {
+        Self(AtomicI64::new(micros))
+    }
+
+    /// Set the time in microseconds.
+    pub fn set(&self, micros: i64) {
+        self.0.store(micros, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Timestamp {
+        Timestamp::from_micros(self.0.load(Ordering::SeqCst))
+    }
+}
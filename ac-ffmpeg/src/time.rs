@@ -4,14 +4,38 @@ use std::{
     cmp::{Eq, Ordering, PartialEq, PartialOrd},
     fmt::{self, Debug, Formatter},
     ops::{Add, AddAssign, Sub, SubAssign},
-    time::Duration,
+    os::raw::c_int,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 extern "C" {
     fn ffw_rescale_q(n: i64, aq_num: u32, aq_den: u32, bq_num: u32, bq_den: u32) -> i64;
+    fn ffw_rescale_q_rnd(
+        n: i64,
+        aq_num: u32,
+        aq_den: u32,
+        bq_num: u32,
+        bq_den: u32,
+        rnd: c_int,
+    ) -> i64;
     fn ffw_null_timestamp() -> i64;
 }
 
+/// Rounding method used when rescaling timestamps.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Rounding {
+    /// Round toward zero.
+    Zero = 0,
+    /// Round away from zero.
+    Infinity = 1,
+    /// Round toward negative infinity.
+    Down = 2,
+    /// Round toward positive infinity.
+    Up = 3,
+    /// Round to the nearest value, halfway cases away from zero.
+    NearInfinity = 5,
+}
+
 /// A rational time base (e.g. 1/1000 is a millisecond time base).
 #[derive(Copy, Clone)]
 pub struct TimeBase {
@@ -38,6 +62,32 @@ impl TimeBase {
     pub fn den(&self) -> u32 {
         self.den
     }
+
+    /// Rescale a given value from this time base to a given time base. The
+    /// result is rounded toward zero.
+    pub fn rescale(&self, value: i64, time_base: TimeBase) -> i64 {
+        unsafe { ffw_rescale_q(value, self.num, self.den, time_base.num, time_base.den) }
+    }
+
+    /// Rescale a given value from this time base to a given time base using
+    /// a given rounding method.
+    pub fn rescale_rnd(&self, value: i64, time_base: TimeBase, rounding: Rounding) -> i64 {
+        unsafe {
+            ffw_rescale_q_rnd(
+                value,
+                self.num,
+                self.den,
+                time_base.num,
+                time_base.den,
+                rounding as _,
+            )
+        }
+    }
+
+    /// Get the time base as a floating point number.
+    pub fn as_f64(&self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
 }
 
 impl Debug for TimeBase {
@@ -46,6 +96,121 @@ impl Debug for TimeBase {
     }
 }
 
+/// A rational number (e.g. frame rate or aspect ratio).
+#[derive(Copy, Clone)]
+pub struct Rational {
+    num: i32,
+    den: i32,
+}
+
+impl Rational {
+    /// Create a new rational number with a given numerator and denominator.
+    pub const fn new(num: i32, den: i32) -> Self {
+        Self { num, den }
+    }
+
+    /// Get the numerator.
+    pub fn num(&self) -> i32 {
+        self.num
+    }
+
+    /// Get the denominator.
+    pub fn den(&self) -> i32 {
+        self.den
+    }
+
+    /// Get the reciprocal value (e.g. frame duration from a frame rate).
+    pub fn invert(&self) -> Self {
+        Self::new(self.den, self.num)
+    }
+
+    /// Reduce the fraction to its lowest terms.
+    pub fn reduce(&self) -> Self {
+        let mut a = self.num.unsigned_abs();
+        let mut b = self.den.unsigned_abs();
+
+        while b != 0 {
+            let t = a % b;
+
+            a = b;
+            b = t;
+        }
+
+        if a == 0 {
+            *self
+        } else {
+            let a = a as i32;
+
+            Self::new(self.num / a, self.den / a)
+        }
+    }
+
+    /// Get the value as a floating point number.
+    pub fn as_f64(&self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+}
+
+impl Debug for Rational {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}/{}", self.num(), self.den())
+    }
+}
+
+impl PartialEq for Rational {
+    fn eq(&self, other: &Rational) -> bool {
+        self.num as i64 * other.den as i64 == other.num as i64 * self.den as i64
+    }
+}
+
+impl From<TimeBase> for Rational {
+    fn from(time_base: TimeBase) -> Self {
+        Self::new(time_base.num as _, time_base.den as _)
+    }
+}
+
+/// Create a rational number from given parts if the number is a valid
+/// positive number.
+pub(crate) fn positive_rational(num: i32, den: i32) -> Option<Rational> {
+    if num > 0 && den > 0 {
+        Some(Rational::new(num as _, den as _))
+    } else {
+        None
+    }
+}
+
+/// Calculate display aspect ratio of a frame with given dimensions and a
+/// given sample aspect ratio.
+pub(crate) fn display_aspect_ratio(width: usize, height: usize, sar: Rational) -> Option<Rational> {
+    let mut num = width as i64 * sar.num() as i64;
+    let mut den = height as i64 * sar.den() as i64;
+
+    if num <= 0 || den <= 0 {
+        return None;
+    }
+
+    let mut a = num;
+    let mut b = den;
+
+    while b != 0 {
+        let t = a % b;
+
+        a = b;
+        b = t;
+    }
+
+    num /= a;
+    den /= a;
+
+    // keep the ratio within the range of 32-bit integers
+    while num > i32::MAX as i64 || den > i32::MAX as i64 {
+        num >>= 1;
+        den >>= 1;
+    }
+
+    Some(Rational::new(num.max(1) as _, den.max(1) as _))
+}
+
 /// A timestamp supporting various time bases. All comparisons are done within
 /// microsecond time base.
 #[derive(Copy, Clone)]
@@ -118,18 +283,17 @@ impl Timestamp {
 
     /// Rescale the timestamp value to a given time base.
     pub fn with_time_base(&self, time_base: TimeBase) -> Self {
+        self.rescale_rnd(time_base, Rounding::Zero)
+    }
+
+    /// Rescale the timestamp value to a given time base using a given
+    /// rounding method.
+    pub fn rescale_rnd(&self, time_base: TimeBase, rounding: Rounding) -> Self {
         let timestamp = if self.is_null() {
             self.timestamp
         } else {
-            unsafe {
-                ffw_rescale_q(
-                    self.timestamp,
-                    self.time_base.num,
-                    self.time_base.den,
-                    time_base.num,
-                    time_base.den,
-                )
-            }
+            self.time_base
+                .rescale_rnd(self.timestamp, time_base, rounding)
         };
 
         Self {
@@ -138,6 +302,121 @@ impl Timestamp {
         }
     }
 
+    /// Create a new timestamp from a given duration (e.g. time since the
+    /// beginning of a stream).
+    pub fn from_duration(duration: Duration) -> Self {
+        Self::from_nanos(duration.as_nanos() as i64)
+    }
+
+    /// Get the timestamp value as a duration. The method returns None if the
+    /// timestamp is null or negative.
+    pub fn as_duration(&self) -> Option<Duration> {
+        let nanos = self.as_nanos()?;
+
+        if nanos < 0 {
+            None
+        } else {
+            Some(Duration::from_nanos(nanos as u64))
+        }
+    }
+
+    /// Create a new timestamp from a given wall-clock time. The timestamp
+    /// will represent time since the Unix epoch in microseconds.
+    pub fn from_system_time(time: SystemTime) -> Self {
+        let micros = match time.duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_micros() as i64,
+            Err(err) => -(err.duration().as_micros() as i64),
+        };
+
+        Self::from_micros(micros)
+    }
+
+    /// Interpret the timestamp as time since the Unix epoch and convert it
+    /// into wall-clock time.
+    pub fn as_system_time(&self) -> Option<SystemTime> {
+        let micros = self.as_micros()?;
+
+        let offset = Duration::from_micros(micros.unsigned_abs());
+
+        if micros < 0 {
+            UNIX_EPOCH.checked_sub(offset)
+        } else {
+            UNIX_EPOCH.checked_add(offset)
+        }
+    }
+
+    /// Parse a given normal play time (NPT) as used by RTSP (e.g. in the
+    /// `Range` header). Both the `npt-sec` (e.g. `123.45`) and the
+    /// `npt-hhmmss` (e.g. `0:02:03.45`) forms are accepted. The resulting
+    /// timestamp has microsecond time base.
+    pub fn from_npt(npt: &str) -> Option<Self> {
+        let npt = npt.trim();
+
+        let (int, frac) = match npt.find('.') {
+            Some(pos) => (&npt[..pos], &npt[pos + 1..]),
+            None => (npt, ""),
+        };
+
+        let parse = |s: &str| {
+            if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+                None
+            } else {
+                s.parse::<i64>().ok()
+            }
+        };
+
+        let parts = int.split(':').map(parse).collect::<Option<Vec<_>>>()?;
+
+        let secs = match parts[..] {
+            [s] => s,
+            [h, m, s] if m < 60 && s < 60 => h.checked_mul(3_600)?.checked_add(m * 60 + s)?,
+            _ => return None,
+        };
+
+        if !frac.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+
+        let micros = if frac.is_empty() {
+            0
+        } else {
+            let digits = &frac[..frac.len().min(6)];
+
+            parse(digits)? * 10i64.pow(6 - digits.len() as u32)
+        };
+
+        let micros = secs.checked_mul(1_000_000)?.checked_add(micros)?;
+
+        Some(Self::from_micros(micros))
+    }
+
+    /// Format the timestamp as normal play time (NPT) in the `npt-sec` form
+    /// with millisecond precision (e.g. `123.450`). The method returns None
+    /// if the timestamp is null or negative.
+    pub fn to_npt(&self) -> Option<String> {
+        let millis = self.as_millis()?;
+
+        if millis < 0 {
+            None
+        } else {
+            Some(format!("{}.{:03}", millis / 1_000, millis % 1_000))
+        }
+    }
+
+    /// Compare two timestamps exactly, i.e. without converting them into
+    /// the microsecond time base. The method returns None if any of the
+    /// timestamps is null.
+    pub fn cmp_exact(&self, other: &Timestamp) -> Option<Ordering> {
+        if self.is_null() || other.is_null() {
+            return None;
+        }
+
+        let a = self.timestamp as i128 * self.time_base.num as i128 * other.time_base.den as i128;
+        let b = other.timestamp as i128 * other.time_base.num as i128 * self.time_base.den as i128;
+
+        Some(a.cmp(&b))
+    }
+
     /// Get the timestamp value in seconds.
     pub fn as_secs(&self) -> Option<i64> {
         if self.is_null() {
@@ -316,9 +595,9 @@ impl PartialOrd for Timestamp {
 
 #[cfg(test)]
 mod tests {
-    use std::time::Duration;
+    use std::{cmp::Ordering, time::Duration};
 
-    use super::{TimeBase, Timestamp};
+    use super::{display_aspect_ratio, Rational, Rounding, TimeBase, Timestamp};
 
     #[test]
     fn test_duration_add() {
@@ -374,4 +653,84 @@ mod tests {
 
         assert_eq!(a, b);
     }
+
+    #[test]
+    fn test_exact_comparison() {
+        let a = Timestamp::from_secs(1);
+        let b = Timestamp::from_nanos(1_000_000_001);
+
+        assert_eq!(a.cmp_exact(&b), Some(Ordering::Less));
+        assert_eq!(b.cmp_exact(&a), Some(Ordering::Greater));
+        assert_eq!(
+            a.cmp_exact(&Timestamp::from_millis(1_000)),
+            Some(Ordering::Equal)
+        );
+        assert_eq!(a.cmp_exact(&Timestamp::null()), None);
+    }
+
+    #[test]
+    fn test_rescale() {
+        let ts = Timestamp::new(1_001, TimeBase::new(1, 30_000));
+
+        assert_eq!(ts.with_time_base(TimeBase::new(1, 1_000)).timestamp(), 33);
+        assert_eq!(
+            ts.rescale_rnd(TimeBase::new(1, 1_000), Rounding::Up)
+                .timestamp(),
+            34
+        );
+        assert_eq!(
+            TimeBase::new(1, 90_000).rescale(180_000, TimeBase::MICROSECONDS),
+            2_000_000
+        );
+    }
+
+    #[test]
+    fn test_duration_conversion() {
+        let ts = Timestamp::from_duration(Duration::from_millis(1_500));
+
+        assert_eq!(ts.as_duration(), Some(Duration::from_millis(1_500)));
+        assert_eq!(Timestamp::from_secs(-1).as_duration(), None);
+        assert_eq!(Timestamp::null().as_duration(), None);
+    }
+
+    #[test]
+    fn test_rational() {
+        let fps = Rational::new(60_000, 2_002);
+
+        assert_eq!(fps, Rational::new(30_000, 1_001));
+        assert_eq!(fps.reduce().num(), 30_000);
+        assert_eq!(fps.reduce().den(), 1_001);
+        assert_ne!(fps, Rational::new(30, 1));
+    }
+
+    #[test]
+    fn test_display_aspect_ratio() {
+        let dar = display_aspect_ratio(720, 576, Rational::new(16, 15)).unwrap();
+
+        assert_eq!((dar.num(), dar.den()), (4, 3));
+        assert!(display_aspect_ratio(0, 576, Rational::new(1, 1)).is_none());
+    }
+
+    #[test]
+    fn test_npt() {
+        assert_eq!(
+            Timestamp::from_npt("123.45"),
+            Some(Timestamp::from_millis(123_450))
+        );
+        assert_eq!(
+            Timestamp::from_npt("0:02:03.45"),
+            Some(Timestamp::from_millis(123_450))
+        );
+        assert_eq!(Timestamp::from_npt("10"), Some(Timestamp::from_secs(10)));
+        assert_eq!(Timestamp::from_npt("now"), None);
+        assert_eq!(Timestamp::from_npt("1:60:00"), None);
+        assert_eq!(Timestamp::from_npt("1.2x"), None);
+        assert_eq!(Timestamp::from_npt("9223372036854775807"), None);
+        assert_eq!(Timestamp::from_npt("9223372036854775807:00:00"), None);
+
+        assert_eq!(
+            Timestamp::from_millis(123_450).to_npt().as_deref(),
+            Some("123.450")
+        );
+    }
 }
@@ -0,0 +1,377 @@
+//! SMPTE timecodes.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use crate::{time::Rational, Error};
+
+/// SMPTE timecode (HH:MM:SS:FF). The frame rate is not part of the timecode,
+/// it needs to be supplied for all conversions.
+///
+/// Drop-frame timecodes (used with 29.97 and 59.94 fps) skip the first two
+/// (or four) frame numbers of every minute except every tenth minute, so
+/// that the timecode stays in sync with the wall clock.
+///
+/// # Example
+/// ```text
+/// let frame_rate = Rational::new(30_000, 1_001);
+///
+/// let tc = Timecode::from_frame_number(1_800, frame_rate, true)?;
+///
+/// assert_eq!(tc.to_string(), "00:01:00;02");
+/// assert_eq!(tc.to_frame_number(frame_rate)?, 1_800);
+/// ```
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Timecode {
+    hours: u32,
+    minutes: u32,
+    seconds: u32,
+    frames: u32,
+    drop_frame: bool,
+}
+
+impl Timecode {
+    /// Create a new timecode.
+    pub const fn new(
+        hours: u32,
+        minutes: u32,
+        seconds: u32,
+        frames: u32,
+        drop_frame: bool,
+    ) -> Self {
+        Self {
+            hours,
+            minutes,
+            seconds,
+            frames,
+            drop_frame,
+        }
+    }
+
+    /// Create a timecode for a given frame number (counted from
+    /// 00:00:00:00) and a given frame rate. Hours wrap around at 24.
+    pub fn from_frame_number(
+        frame_number: u64,
+        frame_rate: Rational,
+        drop_frame: bool,
+    ) -> Result<Self, Error> {
+        let fps = nominal_frame_rate(frame_rate, drop_frame)?;
+
+        let mut frame_number = frame_number;
+
+        if drop_frame {
+            let drop = fps / 15;
+            let frames_per_10_minutes = fps * 600 - drop * 9;
+            let frames_per_minute = fps * 60 - drop;
+
+            let d = frame_number / frames_per_10_minutes;
+            let m = frame_number % frames_per_10_minutes;
+
+            // the first minute of every ten minutes is not dropped
+            let skipped = if m < drop {
+                0
+            } else {
+                (m - drop) / frames_per_minute
+            };
+
+            frame_number += 9 * drop * d + drop * skipped;
+        }
+
+        let frames = frame_number % fps;
+        let seconds = frame_number / fps;
+
+        let res = Self {
+            hours: ((seconds / 3600) % 24) as u32,
+            minutes: ((seconds / 60) % 60) as u32,
+            seconds: (seconds % 60) as u32,
+            frames: frames as u32,
+            drop_frame,
+        };
+
+        Ok(res)
+    }
+
+    /// Get the frame number (counted from 00:00:00:00) of the timecode for
+    /// a given frame rate.
+    pub fn to_frame_number(&self, frame_rate: Rational) -> Result<u64, Error> {
+        let fps = nominal_frame_rate(frame_rate, self.drop_frame)?;
+
+        if self.minutes > 59 || self.seconds > 59 || self.frames as u64 >= fps {
+            return Err(Error::new("invalid timecode"));
+        }
+
+        let minutes = self.hours as u64 * 60 + self.minutes as u64;
+
+        let mut res = (minutes * 60 + self.seconds as u64) * fps + self.frames as u64;
+
+        if self.drop_frame {
+            let drop = fps / 15;
+
+            // frames skipped at the beginning of every minute except every
+            // tenth minute
+            let minute_of_ten = minutes % 10;
+
+            if self.seconds == 0 && minute_of_ten > 0 && (self.frames as u64) < drop {
+                return Err(Error::new("invalid drop-frame timecode"));
+            }
+
+            res -= drop * (minutes - minutes / 10);
+        }
+
+        Ok(res)
+    }
+
+    /// Decode a given SMPTE ST 12-1 binary timecode (as used e.g. by
+    /// `VideoFrame::s12m_timecodes()`). The frame rate is needed in order to
+    /// decode frame numbers of timecodes above 30 fps.
+    pub fn from_smpte(timecode: u32, frame_rate: Rational) -> Self {
+        let bcd = |value: u32| (value >> 4) * 10 + (value & 0xf);
+
+        let mut frames = bcd((timecode >> 24) & 0x3f);
+
+        // there is only a field bit for frame rates above 30 fps
+        if is_above_30_fps(frame_rate) {
+            frames <<= 1;
+
+            if is_50_fps(frame_rate) {
+                frames += (timecode >> 7) & 1;
+            } else {
+                frames += (timecode >> 23) & 1;
+            }
+        }
+
+        Self {
+            hours: bcd(timecode & 0x3f),
+            minutes: bcd((timecode >> 8) & 0x7f),
+            seconds: bcd((timecode >> 16) & 0x7f),
+            frames,
+            drop_frame: timecode & (1 << 30) != 0,
+        }
+    }
+
+    /// Encode the timecode into the SMPTE ST 12-1 binary representation.
+    pub fn to_smpte(&self, frame_rate: Rational) -> u32 {
+        let bcd = |value: u32| ((value / 10) << 4) | (value % 10);
+
+        let mut res = 0;
+
+        let mut frames = self.frames;
+
+        if is_above_30_fps(frame_rate) {
+            if frames % 2 == 1 {
+                if is_50_fps(frame_rate) {
+                    res |= 1 << 7;
+                } else {
+                    res |= 1 << 23;
+                }
+            }
+
+            frames /= 2;
+        }
+
+        res |= (self.drop_frame as u32) << 30;
+        res |= bcd(frames % 40) << 24;
+        res |= bcd(self.seconds.min(59)) << 16;
+        res |= bcd(self.minutes.min(59)) << 8;
+        res |= bcd(self.hours % 24);
+
+        res
+    }
+
+    /// Get the hours.
+    pub fn hours(&self) -> u32 {
+        self.hours
+    }
+
+    /// Get the minutes.
+    pub fn minutes(&self) -> u32 {
+        self.minutes
+    }
+
+    /// Get the seconds.
+    pub fn seconds(&self) -> u32 {
+        self.seconds
+    }
+
+    /// Get the frames.
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Check if this is a drop-frame timecode.
+    pub fn is_drop_frame(&self) -> bool {
+        self.drop_frame
+    }
+}
+
+impl Display for Timecode {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let separator = if self.drop_frame { ';' } else { ':' };
+
+        write!(
+            f,
+            "{:02}:{:02}:{:02}{}{:02}",
+            self.hours, self.minutes, self.seconds, separator, self.frames
+        )
+    }
+}
+
+impl FromStr for Timecode {
+    type Err = Error;
+
+    /// Parse a timecode in the `HH:MM:SS:FF` format. Any other separator
+    /// than `:` before the frames (typically `;`) denotes a drop-frame
+    /// timecode.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || Error::new("invalid timecode");
+
+        let separator = s
+            .char_indices()
+            .filter(|(_, c)| !c.is_ascii_digit())
+            .nth(2)
+            .ok_or_else(err)?;
+
+        let (hms, frames) = s.split_at(separator.0);
+
+        let frames = &frames[separator.1.len_utf8()..];
+
+        let mut parts = hms.split(':');
+
+        let mut next = || -> Result<u32, Error> {
+            parts
+                .next()
+                .and_then(|part| part.trim().parse().ok())
+                .ok_or_else(err)
+        };
+
+        let hours = next()?;
+        let minutes = next()?;
+        let seconds = next()?;
+
+        if parts.next().is_some() {
+            return Err(err());
+        }
+
+        let frames = frames.trim().parse().map_err(|_| err())?;
+
+        Ok(Self::new(
+            hours,
+            minutes,
+            seconds,
+            frames,
+            separator.1 != ':',
+        ))
+    }
+}
+
+/// Get the nominal (integer) frame rate used for counting frames, e.g. 30
+/// for 29.97 fps.
+fn nominal_frame_rate(frame_rate: Rational, drop_frame: bool) -> Result<u64, Error> {
+    let num = frame_rate.num() as i64;
+    let den = frame_rate.den() as i64;
+
+    if num <= 0 || den <= 0 {
+        return Err(Error::new("invalid frame rate"));
+    }
+
+    let fps = ((num + den / 2) / den) as u64;
+
+    if fps == 0 {
+        return Err(Error::new("invalid frame rate"));
+    }
+
+    let remainder = fps % 30;
+
+    if drop_frame && remainder > 0 {
+        return Err(Error::new(
+            "drop-frame timecodes require a multiple of 29.97 fps",
+        ));
+    }
+
+    Ok(fps)
+}
+
+/// Check if a given frame rate is above 30 fps.
+fn is_above_30_fps(frame_rate: Rational) -> bool {
+    frame_rate.num() as i64 > 30 * frame_rate.den() as i64
+}
+
+/// Check if a given frame rate is exactly 50 fps.
+fn is_50_fps(frame_rate: Rational) -> bool {
+    frame_rate.num() as i64 == 50 * frame_rate.den() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Timecode;
+
+    use crate::time::Rational;
+
+    #[test]
+    fn test_drop_frame() {
+        let fps = Rational::new(30_000, 1_001);
+
+        let tc = Timecode::from_frame_number(1_799, fps, true).unwrap();
+
+        assert_eq!(tc.to_string(), "00:00:59;29");
+
+        let tc = Timecode::from_frame_number(1_800, fps, true).unwrap();
+
+        assert_eq!(tc.to_string(), "00:01:00;02");
+        assert_eq!(tc.to_frame_number(fps).unwrap(), 1_800);
+
+        // the tenth minute is not dropped
+        let tc = Timecode::from_frame_number(17_982, fps, true).unwrap();
+
+        assert_eq!(tc.to_string(), "00:10:00;00");
+        assert_eq!(tc.to_frame_number(fps).unwrap(), 17_982);
+
+        assert!(Timecode::new(0, 1, 0, 0, true)
+            .to_frame_number(fps)
+            .is_err());
+        assert!(Timecode::from_frame_number(0, Rational::new(25, 1), true).is_err());
+    }
+
+    #[test]
+    fn test_non_drop_frame() {
+        let fps = Rational::new(25, 1);
+
+        let tc = Timecode::from_frame_number(90_000 + 26, fps, false).unwrap();
+
+        assert_eq!(tc.to_string(), "01:00:01:01");
+        assert_eq!(tc.to_frame_number(fps).unwrap(), 90_026);
+    }
+
+    #[test]
+    fn test_parse() {
+        let tc: Timecode = "10:20:30;15".parse().unwrap();
+
+        assert_eq!(tc, Timecode::new(10, 20, 30, 15, true));
+
+        let tc: Timecode = "01:00:00:00".parse().unwrap();
+
+        assert_eq!(tc, Timecode::new(1, 0, 0, 0, false));
+
+        assert!("01:00:00".parse::<Timecode>().is_err());
+        assert!("01:00:00:00:00".parse::<Timecode>().is_err());
+    }
+
+    #[test]
+    fn test_smpte() {
+        let fps = Rational::new(30_000, 1_001);
+
+        let tc = Timecode::new(12, 34, 56, 28, true);
+
+        assert_eq!(tc.to_smpte(fps), 0x6856_3412);
+        assert_eq!(Timecode::from_smpte(0x6856_3412, fps), tc);
+
+        // odd frame numbers above 30 fps use the field bit
+        let fps = Rational::new(60, 1);
+
+        let tc = Timecode::new(0, 0, 1, 59, false);
+
+        assert_eq!(Timecode::from_smpte(tc.to_smpte(fps), fps), tc);
+    }
+}
@@ -0,0 +1,37 @@
+//! Internal tracing helpers. Spans and events are emitted only if the
+//! `tracing` feature is enabled. Otherwise, the field values are not
+//! evaluated at all.
+
+/// Enter a new span at the `TRACE` level. The span is exited when the
+/// returned guard is dropped.
+macro_rules! trace_span {
+    ($name:expr $(, $field:ident = $value:expr)* $(,)?) => {{
+        #[cfg(feature = "tracing")]
+        let guard = tracing::trace_span!($name $(, $field = $value)*).entered();
+
+        #[cfg(not(feature = "tracing"))]
+        let guard = {
+            // keep the values "used" without evaluating them
+            let _ = || ($($value,)*);
+
+            $crate::trace::DisabledSpan
+        };
+
+        guard
+    }};
+}
+
+/// Emit a new event at the `TRACE` level.
+macro_rules! trace_event {
+    ($($field:ident = $value:expr,)* $message:literal) => {
+        #[cfg(feature = "tracing")]
+        tracing::trace!($($field = $value,)* $message);
+
+        #[cfg(not(feature = "tracing"))]
+        let _ = || ($($value,)*);
+    };
+}
+
+/// Placeholder for span guards if the `tracing` feature is disabled.
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct DisabledSpan;